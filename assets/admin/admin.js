@@ -0,0 +1,30 @@
+// Sends the action forms of the admin dashboard to the admin API, with the
+// CSRF token of the session, and reloads the page once they succeed.
+document.addEventListener("submit", async (event) => {
+  const form = event.target;
+  if (!form.hasAttribute("data-action")) {
+    return;
+  }
+  event.preventDefault();
+
+  if (form.dataset.confirm && !window.confirm(form.dataset.confirm)) {
+    return;
+  }
+
+  const flash = document.getElementById("flash");
+  const token = document.querySelector('meta[name="csrf-token"]').content;
+  const response = await fetch(form.action, {
+    method: "POST",
+    credentials: "same-origin",
+    headers: { "X-CSRF-Token": token },
+  });
+
+  if (response.ok) {
+    window.location.reload();
+    return;
+  }
+
+  const problem = await response.json().catch(() => ({}));
+  flash.textContent = problem.detail || `Request failed with status ${response.status}`;
+  flash.hidden = false;
+});
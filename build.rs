@@ -0,0 +1,51 @@
+// The migrations are embedded with `sqlx::migrate!`, so the crate must be
+// rebuilt when they change.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+/// Generates the server side of the gRPC service. The service is described
+/// here rather than compiled from `proto/betterauth/v1/auth.proto`, so that
+/// building needs no `protoc`; the two must be kept in sync.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    fn method(name: &str, route: &str, input: &str, output: &str) -> Method {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{input}"))
+            .output_type(format!("crate::grpc::{output}"))
+            .codec_path("tonic_prost::ProstCodec")
+            .build()
+    }
+
+    pub fn generate() {
+        let service = Service::builder()
+            .name("Auth")
+            .package("betterauth.v1")
+            .method(method(
+                "validate_token",
+                "ValidateToken",
+                "ValidateTokenRequest",
+                "ValidateTokenResponse",
+            ))
+            .method(method(
+                "introspect",
+                "Introspect",
+                "IntrospectRequest",
+                "IntrospectResponse",
+            ))
+            .method(method("get_user", "GetUser", "GetUserRequest", "User"))
+            .build();
+
+        Builder::new()
+            .build_client(false)
+            .build_transport(false)
+            .compile(&[service]);
+    }
+}
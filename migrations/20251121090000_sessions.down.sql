@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_sessions_expires_at;
+DROP INDEX IF EXISTS idx_sessions_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS sessions;
@@ -0,0 +1,13 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_email_verification_tokens_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS email_verification_tokens;
+
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN DEFAULT FALSE;
+
+UPDATE users SET email_verified = verified_at IS NOT NULL;
+
+ALTER TABLE users DROP COLUMN verified_at;
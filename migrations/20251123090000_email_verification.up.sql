@@ -0,0 +1,16 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN verified_at TIMESTAMPTZ;
+
+UPDATE users SET verified_at = updated_at WHERE email_verified;
+
+ALTER TABLE users DROP COLUMN email_verified;
+
+CREATE TABLE email_verification_tokens (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_webauthn_credentials_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS webauthn_challenges;
+DROP TABLE IF EXISTS webauthn_credentials;
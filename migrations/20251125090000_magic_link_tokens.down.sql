@@ -0,0 +1,7 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_magic_link_tokens_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS magic_link_tokens;
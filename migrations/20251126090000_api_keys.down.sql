@@ -0,0 +1,7 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_api_keys_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS api_keys;
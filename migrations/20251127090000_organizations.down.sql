@@ -0,0 +1,11 @@
+-- Add down migration script here
+ALTER TABLE sessions DROP COLUMN IF EXISTS active_organization_id;
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_organization_invitations_organization_id;
+DROP INDEX IF EXISTS idx_memberships_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS organization_invitations;
+DROP TABLE IF EXISTS memberships;
+DROP TABLE IF EXISTS organizations;
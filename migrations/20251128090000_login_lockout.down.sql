@@ -0,0 +1,11 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_failed_logins_ip_created_at;
+
+-- Drop Tables
+DROP TABLE IF EXISTS failed_logins;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS locked_until,
+    DROP COLUMN IF EXISTS failed_login_attempts;
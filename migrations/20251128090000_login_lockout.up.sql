@@ -0,0 +1,12 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until TIMESTAMPTZ;
+
+CREATE TABLE failed_logins (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    ip VARCHAR(45) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_failed_logins_ip_created_at ON failed_logins(ip, created_at);
@@ -0,0 +1,9 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_revoked_tokens_expires_at;
+
+-- Drop Tables
+DROP TABLE IF EXISTS revoked_tokens;
+
+ALTER TABLE users DROP COLUMN IF EXISTS tokens_revoked_at;
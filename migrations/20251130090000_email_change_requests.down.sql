@@ -0,0 +1,7 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_email_change_requests_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS email_change_requests;
@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_audit_events_created_at;
+DROP INDEX IF EXISTS idx_audit_events_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS audit_events;
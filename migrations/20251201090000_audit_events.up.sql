@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(64) NOT NULL,
+    ip VARCHAR(45),
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_audit_events_user_id ON audit_events(user_id);
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at);
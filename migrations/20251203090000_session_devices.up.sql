@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE sessions
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN ip VARCHAR(45),
+    ADD COLUMN last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now();
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DELETE FROM sessions WHERE user_id IS NULL;
+
+ALTER TABLE sessions ALTER COLUMN user_id SET NOT NULL;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE sessions ALTER COLUMN user_id DROP NOT NULL;
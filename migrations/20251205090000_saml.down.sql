@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_saml_accounts_user_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS saml_accounts;
+DROP TABLE IF EXISTS saml_requests;
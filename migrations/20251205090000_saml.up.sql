@@ -0,0 +1,17 @@
+-- Add up migration script here
+CREATE TABLE saml_requests (
+    id VARCHAR(64) PRIMARY KEY,
+    tenant VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE saml_accounts (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant VARCHAR(64) NOT NULL,
+    name_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tenant, name_id)
+);
+
+CREATE INDEX idx_saml_accounts_user_id ON saml_accounts(user_id);
@@ -0,0 +1,7 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_signup_invites_email;
+
+-- Drop Tables
+DROP TABLE IF EXISTS signup_invites;
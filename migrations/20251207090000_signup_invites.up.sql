@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE signup_invites (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    email VARCHAR(255) NOT NULL,
+    role TEXT NOT NULL DEFAULT 'user',
+    organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE,
+    organization_role TEXT,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_signup_invites_email ON signup_invites(email);
@@ -0,0 +1,10 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_users_deletion_scheduled_for;
+DROP INDEX IF EXISTS idx_data_exports_user_id;
+
+ALTER TABLE users DROP COLUMN IF EXISTS deletion_scheduled_for;
+
+-- Drop Tables
+DROP TABLE IF EXISTS data_exports;
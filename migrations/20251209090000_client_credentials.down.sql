@@ -0,0 +1,9 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_oauth_access_tokens_client_id;
+
+DELETE FROM oauth_access_tokens WHERE user_id IS NULL;
+
+ALTER TABLE oauth_access_tokens ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS scopes;
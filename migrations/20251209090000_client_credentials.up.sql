@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE oauth_clients ADD COLUMN scopes TEXT[] NOT NULL DEFAULT '{}';
+
+-- Tokens issued through the client-credentials grant act for the client itself
+ALTER TABLE oauth_access_tokens ALTER COLUMN user_id DROP NOT NULL;
+
+CREATE INDEX idx_oauth_access_tokens_client_id ON oauth_access_tokens(client_id);
@@ -0,0 +1,10 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_phone_otp_codes_user_id;
+DROP INDEX IF EXISTS idx_phone_otp_codes_phone;
+
+-- Drop Tables
+DROP TABLE IF EXISTS phone_otp_codes;
+
+ALTER TABLE users DROP COLUMN IF EXISTS phone;
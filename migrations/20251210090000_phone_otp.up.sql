@@ -0,0 +1,17 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN phone VARCHAR(16) UNIQUE;
+
+CREATE TABLE phone_otp_codes (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phone VARCHAR(16) NOT NULL,
+    purpose VARCHAR(16) NOT NULL,
+    code_hash VARCHAR(64) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_phone_otp_codes_phone ON phone_otp_codes(phone, created_at);
+CREATE INDEX idx_phone_otp_codes_user_id ON phone_otp_codes(user_id);
@@ -0,0 +1,8 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_webhook_deliveries_created_at;
+DROP INDEX IF EXISTS idx_webhook_deliveries_due;
+
+-- Drop Tables
+DROP TABLE IF EXISTS webhook_deliveries;
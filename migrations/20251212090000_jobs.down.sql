@@ -0,0 +1,10 @@
+-- Add down migration script here
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_jobs_failed_at;
+DROP INDEX IF EXISTS idx_jobs_locked_until;
+DROP INDEX IF EXISTS idx_jobs_due;
+
+-- Drop Tables
+DROP TABLE IF EXISTS jobs;
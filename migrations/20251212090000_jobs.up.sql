@@ -0,0 +1,35 @@
+-- Add up migration script here
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    kind VARCHAR(64) NOT NULL,
+    payload JSONB NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    run_at TIMESTAMPTZ NOT NULL,
+    locked_until TIMESTAMPTZ,
+    failed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_jobs_due ON jobs(run_at) WHERE status = 'pending';
+CREATE INDEX idx_jobs_locked_until ON jobs(locked_until) WHERE status = 'running';
+CREATE INDEX idx_jobs_failed_at ON jobs(failed_at) WHERE status = 'failed';
+
+-- Webhook retries and export builds now run from the job queue
+INSERT INTO jobs (kind, payload, max_attempts, created_at, run_at)
+SELECT 'deliver_webhook',
+       jsonb_build_object('kind', 'deliver_webhook', 'delivery_id', id),
+       GREATEST(8 - attempts, 1),
+       now(),
+       next_attempt_at
+FROM webhook_deliveries
+WHERE status = 'pending';
+
+INSERT INTO jobs (kind, payload, max_attempts, created_at, run_at)
+SELECT 'build_export', jsonb_build_object('kind', 'build_export', 'export_id', id), 3, now(), now()
+FROM data_exports
+WHERE status = 'pending';
+
+DROP INDEX IF EXISTS idx_webhook_deliveries_due;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+-- Drop Tables
+DROP TABLE IF EXISTS known_logins;
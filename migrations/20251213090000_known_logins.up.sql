@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE known_logins (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device VARCHAR(64) NOT NULL,
+    network VARCHAR(64) NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL,
+    last_seen_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, device, network)
+);
@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+    CHECK (status IN ('active', 'disabled', 'deleted'));
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+
+UPDATE users SET status = 'disabled' WHERE disabled_at IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX users_email_normalized_key;
+ALTER TABLE users DROP COLUMN email_normalized;
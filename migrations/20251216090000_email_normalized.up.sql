@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- Existing addresses differing only in case or surrounding whitespace must be
+-- merged by hand before this runs, or the unique index cannot be built.
+ALTER TABLE users ADD COLUMN email_normalized TEXT;
+UPDATE users SET email_normalized = lower(trim(email));
+ALTER TABLE users ALTER COLUMN email_normalized SET NOT NULL;
+
+CREATE UNIQUE INDEX users_email_normalized_key ON users (email_normalized);
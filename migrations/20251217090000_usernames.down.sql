@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX users_username_key;
+ALTER TABLE users DROP COLUMN username;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE users DROP COLUMN app_metadata;
+ALTER TABLE users DROP COLUMN user_metadata;
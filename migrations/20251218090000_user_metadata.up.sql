@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN user_metadata JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE users ADD COLUMN app_metadata JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE sessions ADD COLUMN authenticated_at TIMESTAMPTZ;
+UPDATE sessions SET authenticated_at = created_at WHERE user_id IS NOT NULL;
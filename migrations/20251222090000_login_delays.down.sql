@@ -0,0 +1,7 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_failed_login_identifiers_key_created_at;
+
+-- Drop Tables
+DROP TABLE IF EXISTS failed_login_identifiers;
@@ -0,0 +1,8 @@
+-- Add up migration script here
+CREATE TABLE failed_login_identifiers (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    key VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_failed_login_identifiers_key_created_at ON failed_login_identifiers(key, created_at);
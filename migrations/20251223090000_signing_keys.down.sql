@@ -0,0 +1,7 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_signing_keys_algorithm_created_at;
+
+-- Drop Tables
+DROP TABLE IF EXISTS signing_keys;
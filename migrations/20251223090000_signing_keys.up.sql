@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE signing_keys (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    kid VARCHAR(64) NOT NULL UNIQUE,
+    algorithm VARCHAR(16) NOT NULL,
+    -- PEM-encoded private key, sealed with a key derived from auth.secret
+    private_key TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    retired_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_signing_keys_algorithm_created_at ON signing_keys(algorithm, created_at);
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE sso_codes (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    code_hash VARCHAR(64) UNIQUE NOT NULL,
+    -- Id of the application in sso.apps
+    app VARCHAR(255) NOT NULL,
+    -- Not a foreign key: sessions may be kept outside the database
+    session_id UUID NOT NULL,
+    redirect_uri TEXT NOT NULL,
+    code_challenge VARCHAR(128) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE audit_export_cursors (
+    -- Name of the sink in audit.sinks
+    sink VARCHAR(255) PRIMARY KEY,
+    -- Last event exported, in (created_at, id) order
+    last_created_at TIMESTAMPTZ,
+    last_id UUID,
+    failures INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE sessions DROP COLUMN max_expires_at;
+ALTER TABLE organizations DROP COLUMN auth_policy;
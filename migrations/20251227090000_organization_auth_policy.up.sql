@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Stricter rules an organization holds its members to, see organizations::AuthPolicy
+ALTER TABLE organizations ADD COLUMN auth_policy JSONB NOT NULL DEFAULT '{}';
+
+-- Latest a session may last under the policies of its user's organizations
+ALTER TABLE sessions ADD COLUMN max_expires_at TIMESTAMPTZ;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE login_attempts (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    method VARCHAR(32) NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    reason VARCHAR(32),
+    device_kind VARCHAR(16) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_login_attempts_user_id ON login_attempts(user_id);
+CREATE INDEX idx_login_attempts_created_at ON login_attempts(created_at);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+UPDATE organizations
+SET auth_policy = (auth_policy - 'phishing_resistant_login_required')
+    || jsonb_build_object('mfa_required', auth_policy -> 'phishing_resistant_login_required')
+WHERE auth_policy ? 'phishing_resistant_login_required';
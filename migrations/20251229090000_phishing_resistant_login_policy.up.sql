@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Passkeys and SAML are not second factors; name the policy setting for what it requires
+UPDATE organizations
+SET auth_policy = (auth_policy - 'mfa_required')
+    || jsonb_build_object('phishing_resistant_login_required', auth_policy -> 'mfa_required')
+WHERE auth_policy ? 'mfa_required';
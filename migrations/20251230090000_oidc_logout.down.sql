@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE oauth_authorization_codes DROP COLUMN IF EXISTS session_id;
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS post_logout_redirect_uris;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE oauth_clients ADD COLUMN post_logout_redirect_uris TEXT[] NOT NULL DEFAULT '{}';
+
+-- ID tokens name the session they were issued in, so logouts can refer to it
+ALTER TABLE oauth_authorization_codes ADD COLUMN session_id UUID;
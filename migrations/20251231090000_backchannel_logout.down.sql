@@ -0,0 +1,12 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_backchannel_logout_deliveries_client_id;
+DROP INDEX IF EXISTS idx_oauth_client_sessions_expires_at;
+DROP INDEX IF EXISTS idx_oauth_client_sessions_session_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS backchannel_logout_deliveries;
+DROP TABLE IF EXISTS oauth_client_sessions;
+
+ALTER TABLE oauth_clients DROP COLUMN IF EXISTS backchannel_logout_uri;
@@ -0,0 +1,31 @@
+-- Add up migration script here
+ALTER TABLE oauth_clients ADD COLUMN backchannel_logout_uri TEXT;
+
+-- Sessions clients were issued ID tokens in, so they can be told when one ends
+CREATE TABLE oauth_client_sessions (
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    session_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (client_id, session_id)
+);
+
+CREATE INDEX idx_oauth_client_sessions_session_id ON oauth_client_sessions(session_id);
+CREATE INDEX idx_oauth_client_sessions_expires_at ON oauth_client_sessions(expires_at);
+
+CREATE TABLE backchannel_logout_deliveries (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL,
+    session_id UUID,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_status_code INTEGER,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_backchannel_logout_deliveries_client_id
+    ON backchannel_logout_deliveries(client_id, created_at);
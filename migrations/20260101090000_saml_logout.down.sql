@@ -0,0 +1,10 @@
+-- Add down migration script here
+
+-- Drop Indices
+DROP INDEX IF EXISTS idx_saml_sessions_expires_at;
+DROP INDEX IF EXISTS idx_saml_sessions_name_id;
+
+-- Drop Tables
+DROP TABLE IF EXISTS saml_sessions;
+
+ALTER TABLE saml_requests DROP COLUMN IF EXISTS purpose;
@@ -0,0 +1,17 @@
+-- Add up migration script here
+-- Logout requests sent to the IdP are remembered alongside login requests
+ALTER TABLE saml_requests ADD COLUMN purpose VARCHAR(16) NOT NULL DEFAULT 'login';
+
+-- Sessions started from a SAML assertion, so an IdP logout can end them
+CREATE TABLE saml_sessions (
+    session_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant VARCHAR(64) NOT NULL,
+    name_id TEXT NOT NULL,
+    session_index TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_saml_sessions_name_id ON saml_sessions(tenant, name_id);
+CREATE INDEX idx_saml_sessions_expires_at ON saml_sessions(expires_at);
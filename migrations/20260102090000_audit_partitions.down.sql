@@ -0,0 +1,24 @@
+-- Add down migration script here
+-- Archived partitions are not restored
+ALTER TABLE audit_events RENAME TO audit_events_partitioned;
+ALTER INDEX idx_audit_events_user_id RENAME TO idx_audit_events_partitioned_user_id;
+ALTER INDEX idx_audit_events_created_at RENAME TO idx_audit_events_partitioned_created_at;
+ALTER INDEX audit_events_pkey RENAME TO audit_events_partitioned_pkey;
+
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT (gen_random_uuid()),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(64) NOT NULL,
+    ip VARCHAR(45),
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_audit_events_user_id ON audit_events(user_id);
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at);
+
+INSERT INTO audit_events (id, user_id, action, ip, metadata, created_at)
+SELECT id, user_id, action, ip, metadata, created_at FROM audit_events_partitioned;
+
+-- Drops the partitions with it
+DROP TABLE audit_events_partitioned;
@@ -0,0 +1,50 @@
+-- Add up migration script here
+-- audit_events becomes partitioned by UTC month; maintenance creates the
+-- partitions of coming months and archives old ones
+ALTER TABLE audit_events RENAME TO audit_events_unpartitioned;
+ALTER INDEX audit_events_pkey RENAME TO audit_events_unpartitioned_pkey;
+ALTER INDEX idx_audit_events_user_id RENAME TO idx_audit_events_unpartitioned_user_id;
+ALTER INDEX idx_audit_events_created_at RENAME TO idx_audit_events_unpartitioned_created_at;
+
+CREATE TABLE audit_events (
+    id UUID NOT NULL DEFAULT (gen_random_uuid()),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(64) NOT NULL,
+    ip VARCHAR(45),
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (id, created_at)
+) PARTITION BY RANGE (created_at);
+
+CREATE INDEX idx_audit_events_user_id ON audit_events(user_id);
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at);
+
+-- Takes events of months without a partition until maintenance creates it
+CREATE TABLE audit_events_default PARTITION OF audit_events DEFAULT;
+
+-- A partition per month from the oldest event through next month
+DO $$
+DECLARE
+    month TIMESTAMP;
+BEGIN
+    FOR month IN
+        SELECT generate_series(
+            date_trunc('month', COALESCE(oldest, now()) AT TIME ZONE 'UTC'),
+            date_trunc('month', now() AT TIME ZONE 'UTC') + INTERVAL '1 month',
+            INTERVAL '1 month'
+        )
+        FROM (SELECT min(created_at) AS oldest FROM audit_events_unpartitioned) events
+    LOOP
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF audit_events FOR VALUES FROM (%L) TO (%L)',
+            'audit_events_' || to_char(month, 'YYYY_MM'),
+            month AT TIME ZONE 'UTC',
+            (month + INTERVAL '1 month') AT TIME ZONE 'UTC'
+        );
+    END LOOP;
+END $$;
+
+INSERT INTO audit_events (id, user_id, action, ip, metadata, created_at)
+SELECT id, user_id, action, ip, metadata, created_at FROM audit_events_unpartitioned;
+
+DROP TABLE audit_events_unpartitioned;
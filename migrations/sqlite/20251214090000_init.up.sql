@@ -0,0 +1,36 @@
+-- Tables behind the user, session and token stores, for the `sqlite` driver.
+-- UUIDs are stored as 16-byte blobs and timestamps as RFC 3339 text.
+CREATE TABLE users (
+    id BLOB PRIMARY KEY,
+    email TEXT UNIQUE NOT NULL,
+    password_hash TEXT,
+    name TEXT,
+    role TEXT NOT NULL DEFAULT 'user',
+    phone TEXT UNIQUE,
+    verified_at TEXT,
+    disabled_at TEXT,
+    tokens_revoked_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE sessions (
+    id BLOB PRIMARY KEY,
+    user_id BLOB REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT UNIQUE NOT NULL,
+    active_organization_id BLOB,
+    user_agent TEXT,
+    ip TEXT,
+    created_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
+
+CREATE TABLE revoked_tokens (
+    jti BLOB PRIMARY KEY,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    revoked_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Account state of the users behind the SQLite user store.
+ALTER TABLE users ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+    CHECK (status IN ('active', 'disabled', 'deleted'));
+ALTER TABLE users ADD COLUMN deleted_at TEXT;
+
+UPDATE users SET status = 'disabled' WHERE disabled_at IS NOT NULL;
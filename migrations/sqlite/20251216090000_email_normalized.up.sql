@@ -0,0 +1,5 @@
+-- Case-insensitive uniqueness of the addresses in the SQLite user store.
+ALTER TABLE users ADD COLUMN email_normalized TEXT;
+UPDATE users SET email_normalized = lower(trim(email));
+
+CREATE UNIQUE INDEX users_email_normalized_key ON users (email_normalized);
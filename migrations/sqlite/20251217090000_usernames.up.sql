@@ -0,0 +1,4 @@
+-- Optional usernames of the users behind the SQLite user store.
+ALTER TABLE users ADD COLUMN username TEXT;
+
+CREATE UNIQUE INDEX users_username_key ON users (lower(username));
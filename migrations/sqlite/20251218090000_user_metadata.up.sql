@@ -0,0 +1,3 @@
+-- Free-form JSON objects attached to the users behind the SQLite user store.
+ALTER TABLE users ADD COLUMN user_metadata TEXT NOT NULL DEFAULT '{}';
+ALTER TABLE users ADD COLUMN app_metadata TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+-- When the users of sessions behind the SQLite session store last proved who they are.
+ALTER TABLE sessions ADD COLUMN authenticated_at TEXT;
+UPDATE sessions SET authenticated_at = created_at WHERE user_id IS NOT NULL;
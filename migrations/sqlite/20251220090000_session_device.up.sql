@@ -0,0 +1,2 @@
+-- The devices sessions behind the SQLite session store were created from, as JSON.
+ALTER TABLE sessions ADD COLUMN device TEXT;
@@ -0,0 +1,2 @@
+-- Latest sessions behind the SQLite session store may last under the policies of their user's organizations.
+ALTER TABLE sessions ADD COLUMN max_expires_at TEXT;
@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    Result,
+    auth::{generate_token, hash_token},
+};
+
+/// Marker every API key starts with, which tells them apart from JWTs when
+/// sent as `Authorization: Bearer`.
+pub const KEY_PREFIX: &str = "bak_";
+
+/// Number of leading characters of a key kept in clear so users can tell
+/// their keys apart.
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// Header API keys may be sent in instead of `Authorization`.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// A long-lived credential for scripts and services acting as a user.
+///
+/// Like session tokens, only the SHA-256 hash of the key is stored; `prefix`
+/// is the start of the key, kept for display.
+///
+/// A key with `scopes` only passes the [`crate::auth::RequireScope`] guards
+/// naming one of them, and is refused on routes without such a guard; a key
+/// without may do anything its user may.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Postgres-backed persistence for [`ApiKey`]s.
+#[derive(Clone)]
+pub struct ApiKeyStore {
+    db: PgPool,
+}
+
+impl ApiKeyStore {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Whether `token` has the shape of an API key rather than a JWT.
+    #[must_use]
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(KEY_PREFIX)
+    }
+
+    /// Issues a new key for `user_id`, limited to `scopes` if given.
+    ///
+    /// Returns the stored key together with the plain key, which must be
+    /// shown to the user now as it cannot be recovered later.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        scopes: Option<&[String]>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(ApiKey, String)> {
+        let key = format!("{KEY_PREFIX}{}", generate_token());
+
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            r"
+            INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, user_id, name, prefix, scopes, created_at, expires_at, last_used_at
+            ",
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(&key[..DISPLAY_PREFIX_LEN])
+        .bind(hash_token(&key))
+        .bind(scopes)
+        .bind(Utc::now())
+        .bind(expires_at)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok((api_key, key))
+    }
+
+    /// Lists the keys of `user_id` that have not been revoked, newest first.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<ApiKey>> {
+        sqlx::query_as::<_, ApiKey>(
+            r"
+            SELECT id, user_id, name, prefix, scopes, created_at, expires_at, last_used_at
+            FROM api_keys
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at DESC
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Revokes a key of `user_id`. Returns `false` if the user has no such
+    /// active key.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let revoked = sqlx::query(
+            r"
+            UPDATE api_keys
+            SET revoked_at = now()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            ",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(revoked > 0)
+    }
+
+    /// Resolves a plain key to its active, unexpired [`ApiKey`] and records
+    /// the use. Keys of disabled users do not resolve.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn authenticate(&self, key: &str) -> Result<Option<ApiKey>> {
+        sqlx::query_as::<_, ApiKey>(
+            r"
+            UPDATE api_keys
+            SET last_used_at = now()
+            WHERE key_hash = $1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > now())
+              AND user_id IN (SELECT id FROM users WHERE status = 'active')
+            RETURNING id, user_id, name, prefix, scopes, created_at, expires_at, last_used_at
+            ",
+        )
+        .bind(hash_token(key))
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,72 @@
+use sqlx::PgPool;
+
+use crate::config::{Config, Environment};
+
+/// Logs what the server starts with: its version, environment, listeners,
+/// database and the features it offers. Only settings that are not secrets
+/// are read, so the database is named by its host and name, not its URI.
+pub(super) async fn log(config: &Config, env: &Environment, db: &PgPool) {
+    let listeners: Vec<String> = config
+        .server()
+        .listeners()
+        .iter()
+        .map(|listener| {
+            format!(
+                "{}:{} ({})",
+                listener.host(),
+                listener.port(),
+                listener.routes()
+            )
+        })
+        .collect();
+
+    let database = config.database();
+    let migrations = match database.pending_migrations(db).await {
+        Ok(pending) if pending.is_empty() => String::from("up to date"),
+        Ok(pending) => format!("{} pending", pending.len()),
+        Err(err) => format!("unknown ({err})"),
+    };
+
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        environment = %env,
+        listeners = %listeners.join(", "),
+        tls = config.server().tls().is_some(),
+        database = %format!("{}:{}/{}", database.host(), database.port(), database.name()),
+        driver = ?database.driver(),
+        migrations = %migrations,
+        redis = config.redis().is_some(),
+        features = %features(config).join(", "),
+        "Starting betterauth"
+    );
+}
+
+/// Names of the optional features `config` enables.
+fn features(config: &Config) -> Vec<&'static str> {
+    let switches = config.features();
+
+    [
+        ("registration", switches.registration()),
+        ("magic_links", switches.magic_links()),
+        ("email_otp", switches.email_otp()),
+        (
+            "social_login",
+            switches.social_login()
+                && (config.oauth().google().is_some() || config.oauth().github().is_some()),
+        ),
+        ("admin_ui", switches.admin_ui()),
+        ("passkeys", config.webauthn().is_some()),
+        ("saml", !config.saml().is_empty()),
+        ("oidc", config.oidc().is_some()),
+        ("sso", config.sso().is_some()),
+        ("sms", config.sms().is_some()),
+        ("captcha", config.captcha().is_some()),
+        ("grpc", config.grpc().is_some()),
+        ("webhooks", !config.webhooks().endpoints().is_empty()),
+        ("audit_export", !config.audit().sinks().is_empty()),
+        ("static_files", config.static_files().is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
@@ -0,0 +1,295 @@
+use std::{future::Future, sync::Arc};
+
+use axum::Router;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    AppContext, Result,
+    config::{Config, Overrides, Routes},
+    db::BoxFuture,
+    events::Subscriber,
+};
+
+type ContextHook = Box<dyn FnOnce(AppContext) -> AppContext + Send>;
+type LifecycleHook = Box<dyn FnOnce(Arc<AppContext>) -> BoxFuture<'static, Result<()>> + Send>;
+type Middleware = Box<dyn Fn(Router<Arc<AppContext>>) -> Router<Arc<AppContext>> + Send + Sync>;
+type Task = Box<dyn FnOnce(Arc<AppContext>, CancellationToken) -> JoinHandle<()> + Send>;
+
+/// A bundle of routes, middleware, subscribers, background tasks and
+/// lifecycle hooks added to the server in one go with
+/// [`AppBuilder::plugin`].
+///
+/// ```no_run
+/// use axum::{Router, routing::get};
+/// use betterauth::app::{AppBuilder, Plugin};
+///
+/// struct Billing;
+///
+/// impl Plugin for Billing {
+///     fn register(self, app: AppBuilder) -> AppBuilder {
+///         app.routes(Router::new().route("/billing/plans", get(|| async { "[]" })))
+///     }
+/// }
+/// ```
+pub trait Plugin {
+    /// Adds what the plugin brings to `app`.
+    fn register(self, app: AppBuilder) -> AppBuilder;
+}
+
+/// Assembles the server out of the built-in routes and whatever the
+/// application adds, for crates using betterauth as a library rather than
+/// running its binary. Start one with [`super::App::builder`].
+///
+/// Routes are merged next to the built-in ones, under their own paths, and
+/// served by the listeners serving the matching [`Routes`]. Middleware wraps
+/// every route but the health checks, inside the built-in middleware, so it
+/// sees the session of the request. Background tasks start once the context
+/// is built and are given a token that is cancelled on shutdown, which they
+/// get `server.shutdown_timeout` to finish after.
+///
+/// Start hooks run once the context is built, before the background tasks
+/// start and the listeners are bound, in the order they were added; one
+/// failing aborts the startup. Shutdown hooks run once the listeners and
+/// background tasks stopped, before the database pool is closed, in the
+/// reverse order; one failing is logged and the others still run.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::{
+///     Router,
+///     extract::Request,
+///     http::HeaderValue,
+///     middleware::{self, Next},
+///     response::Response,
+///     routing::get,
+/// };
+/// use betterauth::App;
+///
+/// async fn served_by(request: Request, next: Next) -> Response {
+///     let mut response = next.run(request).await;
+///     response
+///         .headers_mut()
+///         .insert("x-served-by", HeaderValue::from_static("betterauth"));
+///     response
+/// }
+///
+/// # async fn example() -> betterauth::Result<()> {
+/// App::builder()
+///     .routes(Router::new().route("/hello", get(|| async { "Hello" })))
+///     .middleware(|router| router.layer(middleware::from_fn(served_by)))
+///     .on_start(|ctx| async move {
+///         // Prime a cache, so the first requests are not slower.
+///         sqlx::query("SELECT 1").execute(ctx.db()).await?;
+///         Ok(())
+///     })
+///     .on_shutdown(|_ctx| async move {
+///         // Flush what the application buffered.
+///         Ok(())
+///     })
+///     .task(|_ctx, shutdown| async move {
+///         while !shutdown.is_cancelled() {
+///             // Sweep something with `_ctx.db()` every minute.
+///             tokio::select! {
+///                 () = tokio::time::sleep(Duration::from_secs(60)) => {}
+///                 () = shutdown.cancelled() => {}
+///             }
+///         }
+///     })
+///     .run()
+///     .await
+/// # }
+/// ```
+#[derive(Default)]
+pub struct AppBuilder {
+    public: Vec<Router<Arc<AppContext>>>,
+    admin: Vec<Router<Arc<AppContext>>>,
+    middleware: Vec<Middleware>,
+    context: Vec<ContextHook>,
+    tasks: Vec<Task>,
+    on_start: Vec<LifecycleHook>,
+    on_shutdown: Vec<LifecycleHook>,
+}
+
+impl AppBuilder {
+    /// Serves `router` on listeners serving the public API.
+    #[must_use]
+    pub fn routes(mut self, router: Router<Arc<AppContext>>) -> Self {
+        self.public.push(router);
+        self
+    }
+
+    /// Serves `router` on listeners serving the admin API.
+    #[must_use]
+    pub fn admin_routes(mut self, router: Router<Arc<AppContext>>) -> Self {
+        self.admin.push(router);
+        self
+    }
+
+    /// Wraps the routes in what `wrap` adds, typically with
+    /// [`Router::layer`]. Middleware added later runs first.
+    #[must_use]
+    pub fn middleware<F>(mut self, wrap: F) -> Self
+    where
+        F: Fn(Router<Arc<AppContext>>) -> Router<Arc<AppContext>> + Send + Sync + 'static,
+    {
+        self.middleware.push(Box::new(wrap));
+        self
+    }
+
+    /// Publishes events to `subscriber` as well, see
+    /// [`AppContext::with_subscriber`].
+    #[must_use]
+    pub fn subscriber(self, subscriber: impl Subscriber + 'static) -> Self {
+        self.context(move |ctx| ctx.with_subscriber(subscriber))
+    }
+
+    /// Changes the context before the server starts, e.g. to install a
+    /// store or a claims enricher.
+    #[must_use]
+    pub fn context<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(AppContext) -> AppContext + Send + 'static,
+    {
+        self.context.push(Box::new(configure));
+        self
+    }
+
+    /// Runs `task` in the background while the server is up.
+    #[must_use]
+    pub fn task<F, Fut>(mut self, task: F) -> Self
+    where
+        F: FnOnce(Arc<AppContext>, CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks
+            .push(Box::new(|ctx, shutdown| tokio::spawn(task(ctx, shutdown))));
+        self
+    }
+
+    /// Runs `hook` once the context is built, before the server starts
+    /// serving, e.g. to prime caches or load keys. Startup fails with the
+    /// error it returns.
+    #[must_use]
+    pub fn on_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(Arc<AppContext>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on_start.push(Box::new(|ctx| Box::pin(hook(ctx))));
+        self
+    }
+
+    /// Runs `hook` on shutdown, once the server stopped serving and the
+    /// background tasks finished, while the database can still be used.
+    #[must_use]
+    pub fn on_shutdown<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(Arc<AppContext>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.on_shutdown.push(Box::new(|ctx| Box::pin(hook(ctx))));
+        self
+    }
+
+    /// Adds everything `plugin` brings.
+    #[must_use]
+    pub fn plugin(self, plugin: impl Plugin) -> Self {
+        plugin.register(self)
+    }
+
+    /// Runs the server with the configuration of the current environment.
+    ///
+    /// ## Errors
+    /// See [`AppBuilder::run_with`].
+    pub async fn run(self) -> Result<()> {
+        self.run_with(Overrides::default()).await
+    }
+
+    /// Runs the server with `overrides`, as [`super::App::run_with`] does.
+    ///
+    /// ## Errors
+    /// * The configuration cannot be loaded or is invalid
+    /// * The database cannot be migrated or a listener cannot be bound
+    pub async fn run_with(self, overrides: Overrides) -> Result<()> {
+        super::run(self, overrides).await
+    }
+
+    /// The router [`super::App::router`] builds, with the routes and
+    /// middleware added here, for mounting in an axum application of its
+    /// own. Build `ctx` with [`AppBuilder::build_context`], so the context
+    /// changes apply, and start the background tasks added here with
+    /// [`AppBuilder::spawn_tasks`]; the lifecycle hooks only run with
+    /// [`AppBuilder::run`].
+    pub fn router(&self, ctx: &Arc<AppContext>) -> Router {
+        super::App::listener_router(self, ctx, &ctx.config(), Routes::All)
+    }
+
+    /// The context of `config` with the context changes applied, for
+    /// [`AppBuilder::router`].
+    ///
+    /// ## Errors
+    /// * As for [`AppContext::from_config`]
+    pub async fn build_context(&mut self, config: &Config) -> Result<AppContext> {
+        Ok(self.configure(AppContext::from_config(config).await?))
+    }
+
+    /// Applies the context changes to `ctx`.
+    pub(crate) fn configure(&mut self, mut ctx: AppContext) -> AppContext {
+        for configure in self.context.drain(..) {
+            ctx = configure(ctx);
+        }
+        ctx
+    }
+
+    /// Runs the start hooks, stopping at the first that fails.
+    pub(crate) async fn start(&mut self, ctx: &Arc<AppContext>) -> Result<()> {
+        for hook in self.on_start.drain(..) {
+            hook(ctx.clone()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the shutdown hooks, last added first.
+    pub(crate) async fn shut_down(&mut self, ctx: &Arc<AppContext>) {
+        for hook in self.on_shutdown.drain(..).rev() {
+            if let Err(err) = hook(ctx.clone()).await {
+                tracing::error!(error = %err, "Shutdown hook failed");
+            }
+        }
+    }
+
+    /// Starts the background tasks, each given `shutdown`. Only the first
+    /// call starts any.
+    pub fn spawn_tasks(
+        &mut self,
+        ctx: &Arc<AppContext>,
+        shutdown: &CancellationToken,
+    ) -> Vec<JoinHandle<()>> {
+        self.tasks
+            .drain(..)
+            .map(|task| task(ctx.clone(), shutdown.clone()))
+            .collect()
+    }
+
+    /// `router` with the added routes for `served` merged in and the
+    /// middleware applied.
+    pub(crate) fn extend(
+        &self,
+        mut router: Router<Arc<AppContext>>,
+        served: Routes,
+    ) -> Router<Arc<AppContext>> {
+        let public = matches!(served, Routes::All | Routes::Public).then_some(&self.public);
+        let admin = matches!(served, Routes::All | Routes::Admin).then_some(&self.admin);
+
+        for routes in public.into_iter().chain(admin).flatten() {
+            router = router.merge(routes.clone());
+        }
+
+        self.middleware
+            .iter()
+            .fold(router, |router, wrap| wrap(router))
+    }
+}
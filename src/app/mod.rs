@@ -0,0 +1,411 @@
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{Extension, Router, extract::DefaultBodyLimit, middleware, routing::get};
+use axum_server::{Handle, tls_rustls::RustlsConfig};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{
+    net::TcpListener,
+    task::{JoinHandle, JoinSet},
+};
+use tokio_util::sync::CancellationToken;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+
+use crate::{
+    AppContext,
+    audit::{self, AuditExporter},
+    config::{Config, Logger, Overrides, Routes, VaultSecrets},
+    errors, health, i18n, jobs, limits,
+    maintenance::{self, Maintenance},
+    metrics, privacy, reload, routes, security, sessions, shutdown, tokens,
+    trace::{self, REQUEST_ID_HEADER},
+};
+
+mod banner;
+mod builder;
+mod static_files;
+
+pub use self::builder::{AppBuilder, Plugin};
+use super::Result;
+
+pub struct App;
+
+impl App {
+    pub async fn run() -> Result<()> {
+        Self::run_with(Overrides::default()).await
+    }
+
+    /// Starts assembling a server with routes, middleware, subscribers and
+    /// background tasks of the application's own.
+    #[must_use]
+    pub fn builder() -> AppBuilder {
+        AppBuilder::default()
+    }
+
+    /// Loads the configuration with `overrides` as [`App::run_with`] does,
+    /// reading the secrets from Vault when `VAULT_ADDR` is set.
+    ///
+    /// ## Errors
+    /// * The configuration cannot be loaded or is invalid
+    /// * A secret cannot be read
+    pub async fn config(overrides: &Overrides) -> Result<Config> {
+        let config = match VaultSecrets::from_env() {
+            Some(vault) => Config::from_overrides_with(overrides, &vault).await?,
+            None => Config::from_overrides(overrides)?,
+        };
+
+        Ok(config)
+    }
+
+    /// Runs the server with `overrides`, usually from the command line,
+    /// layered on top of the file and `APP_` variables.
+    ///
+    /// Returns after SIGINT or SIGTERM, once open connections and the
+    /// background workers finished, or `server.shutdown_timeout` passed,
+    /// and the database pool is closed.
+    pub async fn run_with(overrides: Overrides) -> Result<()> {
+        Self::builder().run_with(overrides).await
+    }
+
+    /// The built-in routes, the health checks and `/metrics`, with the
+    /// middleware they need, for mounting in an axum application of its own
+    /// instead of running the server. With the routes mounted under a
+    /// prefix, `server.base_path` should name it, so the links and URLs the
+    /// server hands out point at them.
+    ///
+    /// The application builds `ctx` with [`AppContext::from_config`], after
+    /// [`Config::database`] was initialized, and runs the background work
+    /// the routes rely on with [`App::spawn_workers`]. Client IPs are only
+    /// known when it is served with connection info, and `/metrics` stays
+    /// empty until [`metrics::install`] is called. Routes and middleware of
+    /// the application's own can be added with [`AppBuilder::router`]
+    /// instead.
+    ///
+    /// ```no_run
+    /// use std::{net::SocketAddr, sync::Arc};
+    ///
+    /// use axum::{Router, routing::get};
+    /// use betterauth::{App, AppContext, config::Overrides};
+    /// use tokio_util::sync::CancellationToken;
+    ///
+    /// # async fn example() -> betterauth::Result<()> {
+    /// let config = App::config(&Overrides::default()).await?;
+    /// config.database().init().await?;
+    /// let ctx = Arc::new(AppContext::from_config(&config).await?);
+    /// let shutdown = CancellationToken::new();
+    /// let _workers = App::spawn_workers(&ctx, &shutdown);
+    ///
+    /// let app = Router::new()
+    ///     .route("/", get(|| async { "Shop" }))
+    ///     .nest("/identity", App::router(&ctx));
+    ///
+    /// let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+    /// axum::serve(
+    ///     listener,
+    ///     app.into_make_service_with_connect_info::<SocketAddr>(),
+    /// )
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn router(ctx: &Arc<AppContext>) -> Router {
+        Self::builder().router(ctx)
+    }
+
+    /// Wraps `router` in the middleware the built-in routes need: sessions,
+    /// CSRF protection, rate and concurrency limits, timeouts, body limits,
+    /// compression, CORS, locales, request ids, tracing and client IPs.
+    ///
+    /// For mounting only some of the route groups of [`crate::routes`], e.g.
+    /// [`crate::routes::public_router`], in an application of its own; see
+    /// [`App::router`].
+    pub fn with_middleware(ctx: &Arc<AppContext>, router: Router<Arc<AppContext>>) -> Router {
+        let config = ctx.config();
+
+        router
+            // For the route guards, see `auth::RequireRole`.
+            .layer(Extension(ctx.clone()))
+            .layer(middleware::from_fn_with_state(ctx.clone(), security::csrf))
+            .layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                security::rate_limit,
+            ))
+            .layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                sessions::middleware,
+            ))
+            .layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                limits::body_limit,
+            ))
+            .layer(DefaultBodyLimit::max(config.server().body_limit()))
+            .layer(config.server().decompression_layer())
+            .layer(config.server().compression_layer())
+            .layer(middleware::from_fn_with_state(ctx.clone(), limits::timeout))
+            .layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                limits::concurrency,
+            ))
+            .layer(config.cors().layer(ctx.cors_origins()))
+            .layer(middleware::from_fn_with_state(ctx.clone(), i18n::locale))
+            .layer(middleware::from_fn(errors::request_id))
+            .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(trace::make_span_with)
+                    .on_request(trace::on_request)
+                    .on_response(trace::on_response)
+                    .on_failure(trace::on_failure),
+            )
+            .layer(middleware::from_fn_with_state(
+                ctx.clone(),
+                security::client_ip,
+            ))
+            .layer(middleware::from_fn(trace::report_scope))
+            .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+            .with_state(ctx.clone())
+    }
+
+    /// Starts the background work the routes rely on: sending emails and
+    /// other jobs, erasing accounts and building data exports, exporting
+    /// the audit log, purging expired data, refreshing signing keys and
+    /// collecting metrics. Each stops once `shutdown` is cancelled; wait for
+    /// the returned handles before closing the database pool.
+    ///
+    /// [`App::run_with`] starts them itself; applications mounting
+    /// [`App::router`] call this instead.
+    #[must_use]
+    pub fn spawn_workers(
+        ctx: &Arc<AppContext>,
+        shutdown: &CancellationToken,
+    ) -> Vec<JoinHandle<()>> {
+        let config = ctx.config();
+
+        vec![
+            metrics::spawn_collector(ctx.clone(), shutdown.clone()),
+            privacy::spawn_worker(ctx.clone(), shutdown.clone()),
+            jobs::spawn_worker(ctx.clone(), shutdown.clone()),
+            audit::spawn_exporter(
+                AuditExporter::from_config(ctx.db().clone(), config.audit()),
+                shutdown.clone(),
+            ),
+            maintenance::spawn_worker(
+                Maintenance::new(ctx.db().clone(), config.maintenance().clone()),
+                shutdown.clone(),
+            ),
+            tokens::spawn_key_refresher(ctx.tokens().clone(), shutdown.clone()),
+        ]
+    }
+
+    /// The router for a listener serving `served`, with the middleware every
+    /// listener shares, the routes and middleware added to `app`, and the
+    /// health checks unless it only serves [`Routes::Public`] while another
+    /// listener serves the admin routes.
+    ///
+    /// The health checks bypass the middleware, so probes are not rate
+    /// limited, shed or timed out along with the traffic they check on.
+    pub(crate) fn listener_router(
+        app: &AppBuilder,
+        ctx: &Arc<AppContext>,
+        config: &Config,
+        served: Routes,
+    ) -> Router {
+        let mut router = match served {
+            Routes::All | Routes::Admin => Router::new()
+                .route("/metrics", get(metrics::handler))
+                .merge(routes::admin_ui_router()),
+            Routes::Public => Router::new(),
+        }
+        .merge(routes::router(served));
+
+        if served != Routes::Admin {
+            let files = config.static_files();
+            if files.is_none_or(|files| files.path() != "/") {
+                router = router.route("/", get(|| async { "Hello from axum" }));
+            }
+            if let Some(files) = files {
+                router = static_files::mount(router, files);
+            }
+        }
+
+        let router = Self::with_middleware(ctx, app.extend(router, served));
+
+        let admin_listener = config
+            .server()
+            .listeners()
+            .iter()
+            .any(|listener| listener.routes() != Routes::Public);
+        if served == Routes::Public && admin_listener {
+            return router;
+        }
+
+        router.merge(
+            Router::new()
+                .route("/health/live", get(health::live))
+                .route("/health/ready", get(health::ready))
+                .with_state(ctx.clone()),
+        )
+    }
+}
+
+/// Runs the server assembled by `app`, see [`App::run_with`].
+async fn run(mut app: AppBuilder, overrides: Overrides) -> Result<()> {
+    let config = App::config(&overrides).await?;
+
+    // Held until the server exits, so buffered logs are written out.
+    let _log_guard = config.logger().setup()?;
+    config.database().init().await?;
+    metrics::install()?;
+
+    let ctx = Arc::new(app.configure(AppContext::from_config(&config).await?));
+    banner::log(&config, &overrides.environment(), ctx.db()).await;
+    app.start(&ctx).await?;
+
+    let token = CancellationToken::new();
+    shutdown::spawn_listener(token.clone());
+
+    let mut workers = App::spawn_workers(&ctx, &token);
+    workers.push(reload::spawn_watcher(ctx.clone(), overrides, token.clone()));
+    workers.extend(app.spawn_tasks(&ctx, &token));
+
+    let drain = Duration::from_secs(config.server().shutdown_timeout());
+    let rustls = match config.server().tls() {
+        Some(tls) => Some(RustlsConfig::from_pem_file(tls.cert_path(), tls.key_path()).await?),
+        None => None,
+    };
+
+    let mut servers = JoinSet::new();
+
+    for listener in config.server().listeners() {
+        let bound = bind(listener.host(), listener.port()).await?;
+        let tls = rustls.clone().filter(|_| listener.tls());
+
+        tracing::info!(
+            routes = %listener.routes(),
+            "Listening on {}://{}",
+            if tls.is_some() { "https" } else { "http" },
+            bound.local_addr()?
+        );
+
+        servers.spawn(serve(
+            bound,
+            App::listener_router(&app, &ctx, &config, listener.routes()),
+            tls,
+            token.clone(),
+        ));
+    }
+
+    if let Some(grpc) = config.grpc() {
+        #[cfg(feature = "grpc")]
+        {
+            let bound = bind(grpc.host(), grpc.port()).await?;
+            tracing::info!("Serving gRPC on {}", bound.local_addr()?);
+
+            servers.spawn(crate::grpc::serve(bound, ctx.clone(), token.clone()));
+        }
+        #[cfg(not(feature = "grpc"))]
+        tracing::warn!(
+            port = grpc.port(),
+            "The grpc section is ignored, the server was built without the grpc feature"
+        );
+    }
+
+    // One listener failing takes the others down with it.
+    let served = async {
+        let mut result = Ok(());
+
+        while let Some(joined) = servers.join_next().await {
+            if let Err(err) = joined.map_err(io::Error::from).and_then(|served| served) {
+                token.cancel();
+                result = result.and(Err(err));
+            }
+        }
+
+        result
+    };
+
+    let result = tokio::select! {
+        result = served => result,
+        () = async {
+            token.cancelled().await;
+            tokio::time::sleep(drain).await;
+        } => {
+            tracing::warn!(timeout = drain.as_secs(), "Connections still open after the shutdown timeout, closing them");
+            Ok(())
+        }
+    };
+
+    token.cancel();
+    shutdown::join(workers, drain).await;
+    app.shut_down(&ctx).await;
+    ctx.db().close().await;
+
+    tracing::info!("Shut down");
+    // Exporting blocks on HTTP requests.
+    let _ = tokio::task::spawn_blocking(Logger::flush).await;
+
+    result.map_err(Into::into)
+}
+
+/// Binds `host` and `port`. IPv6 sockets only accept IPv6, so that
+/// `0.0.0.0` and `::` can be bound on the same port.
+async fn bind(host: &str, port: u16) -> io::Result<TcpListener> {
+    let addr = tokio::net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("{host} resolves to no address"),
+            )
+        })?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// Serves `router` on `listener`, over TLS with `tls`, until `token` is
+/// cancelled and the open connections are closed.
+pub(crate) async fn serve(
+    listener: TcpListener,
+    router: Router,
+    tls: Option<RustlsConfig>,
+    token: CancellationToken,
+) -> io::Result<()> {
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+
+    match tls {
+        Some(rustls) => {
+            let handle = Handle::new();
+
+            tokio::spawn({
+                let handle = handle.clone();
+                async move {
+                    token.cancelled().await;
+                    handle.graceful_shutdown(None);
+                }
+            });
+
+            axum_server::from_tcp_rustls(listener.into_std()?, rustls)
+                .handle(handle)
+                .serve(service)
+                .await
+        }
+        None => {
+            axum::serve(listener, service)
+                .with_graceful_shutdown(token.cancelled_owned())
+                .await
+        }
+    }
+}
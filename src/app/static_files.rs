@@ -0,0 +1,47 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{Router, extract::Request, response::IntoResponse, routing::any};
+use tower::Service;
+use tower_http::services::{ServeDir, ServeFile};
+
+use crate::{AppContext, Error, config::StaticFilesConfig};
+
+/// Serves the files of `config` from `router`, after its own routes, see
+/// [`StaticFilesConfig`].
+pub(super) fn mount(
+    router: Router<Arc<AppContext>>,
+    config: &StaticFilesConfig,
+) -> Router<Arc<AppContext>> {
+    let files = ServeDir::new(config.dir());
+
+    if !config.spa() {
+        return serve(router, config.path(), files);
+    }
+
+    // Answers with the status of the page, 200, unlike `not_found_service`,
+    // so the app decides what is missing.
+    let files = files.fallback(ServeFile::new(config.dir().join(config.index())));
+    let router = if config.path() == "/" {
+        router.route(
+            "/api/{*path}",
+            any(|| async { Error::NotFound("route").into_response() }),
+        )
+    } else {
+        router
+    };
+
+    serve(router, config.path(), files)
+}
+
+fn serve<S>(router: Router<Arc<AppContext>>, path: &str, service: S) -> Router<Arc<AppContext>>
+where
+    S: Service<Request, Error = Infallible> + Clone + Send + Sync + 'static,
+    S::Response: IntoResponse,
+    S::Future: Send + 'static,
+{
+    if path == "/" {
+        router.fallback_service(service)
+    } else {
+        router.nest_service(path, service)
+    }
+}
@@ -0,0 +1,241 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::{AuditEvent, AuditSink, sinks};
+use crate::{Error, Result, config::AuditConfig, jobs};
+
+/// How old an event must be before it is exported, so one recorded with an
+/// earlier `created_at` but committed later is not skipped.
+const SETTLE: Duration = Duration::seconds(5);
+
+struct Sink {
+    name: String,
+    /// Actions sent to the sink, every one when empty.
+    actions: Vec<String>,
+    sink: Box<dyn AuditSink>,
+}
+
+#[derive(sqlx::FromRow)]
+struct Cursor {
+    last_created_at: Option<DateTime<Utc>>,
+    last_id: Option<Uuid>,
+    failures: i32,
+}
+
+/// Streams the audit log to the sinks of the `audit` config section, see
+/// [`crate::config::AuditConfig`].
+pub struct AuditExporter {
+    db: PgPool,
+    config: AuditConfig,
+    sinks: Vec<Sink>,
+}
+
+impl AuditExporter {
+    /// # Panics
+    /// If the TLS backend of the HTTP client cannot be initialised.
+    #[must_use]
+    pub fn from_config(db: PgPool, config: &AuditConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("betterauth/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("failed to initialise HTTP client");
+
+        let mut sinks: Vec<Sink> = config
+            .sinks()
+            .iter()
+            .map(|(name, sink)| Sink {
+                name: name.clone(),
+                actions: sink.actions().to_vec(),
+                sink: sinks::from_config(sink, &http),
+            })
+            .collect();
+        sinks.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            db,
+            config: config.clone(),
+            sinks,
+        }
+    }
+
+    /// Adds `sink` under `name`, getting every action.
+    #[must_use]
+    pub fn with_sink(mut self, name: impl Into<String>, sink: impl AuditSink + 'static) -> Self {
+        self.sinks.push(Sink {
+            name: name.into(),
+            actions: Vec::new(),
+            sink: Box::new(sink),
+        });
+        self
+    }
+
+    /// Whether there is any sink to export to.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Sends every sink that is not waiting to retry the events recorded
+    /// since its last export, batch after batch. Returns how many were sent
+    /// in all. A failing sink is logged and does not stop the others.
+    pub async fn export(&self) -> u64 {
+        let mut exported = 0;
+
+        for sink in &self.sinks {
+            loop {
+                match self.export_batch(sink).await {
+                    Ok(sent) => {
+                        exported += sent;
+                        if sent < self.config.batch_size().cast_unsigned() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        tracing::warn!(sink = %sink.name, error = %err, "Audit export failed");
+                        break;
+                    }
+                }
+            }
+        }
+
+        exported
+    }
+
+    /// Sends `sink` the next batch of events and moves its cursor past
+    /// them, or schedules the next attempt if it fails. Returns how many
+    /// events were sent, 0 when there were none or another instance holds
+    /// the cursor.
+    ///
+    /// ## Errors
+    /// * Whatever the sink failed with
+    /// * Database errors
+    async fn export_batch(&self, sink: &Sink) -> Result<u64> {
+        sqlx::query("INSERT INTO audit_export_cursors (sink) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(&sink.name)
+            .execute(&self.db)
+            .await?;
+
+        let mut tx = self.db.begin().await?;
+
+        let cursor = sqlx::query_as::<_, Cursor>(
+            r"
+            SELECT last_created_at, last_id, failures
+            FROM audit_export_cursors
+            WHERE sink = $1 AND next_attempt_at <= now()
+            FOR UPDATE SKIP LOCKED
+            ",
+        )
+        .bind(&sink.name)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(cursor) = cursor else {
+            return Ok(0);
+        };
+
+        let events = sqlx::query_as::<_, AuditEvent>(
+            r"
+            SELECT id, user_id, action, ip, metadata, created_at
+            FROM audit_events
+            WHERE ($1::TIMESTAMPTZ IS NULL OR (created_at, id) > ($1, $2))
+              AND created_at <= $3
+              AND (cardinality($4::VARCHAR[]) = 0 OR action = ANY($4))
+            ORDER BY created_at, id
+            LIMIT $5
+            ",
+        )
+        .bind(cursor.last_created_at)
+        .bind(cursor.last_id)
+        .bind(Utc::now() - SETTLE)
+        .bind(&sink.actions)
+        .bind(self.config.batch_size())
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let Some(last) = events.last() else {
+            return Ok(0);
+        };
+
+        let timeout = StdDuration::from_secs(self.config.timeout());
+        let outcome = match tokio::time::timeout(timeout, sink.sink.send(&events)).await {
+            Ok(outcome) => outcome,
+            Err(_) => Err(Error::AuditSink(String::from("sink timed out"))),
+        };
+
+        if let Err(err) = outcome {
+            let failures = cursor.failures.saturating_add(1);
+
+            sqlx::query(
+                r"
+                UPDATE audit_export_cursors
+                SET failures = $2, last_error = $3, next_attempt_at = $4, updated_at = now()
+                WHERE sink = $1
+                ",
+            )
+            .bind(&sink.name)
+            .bind(failures)
+            .bind(err.to_string())
+            .bind(Utc::now() + jobs::backoff(failures))
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            metrics::counter!("audit_export_failures_total", "sink" => sink.name.clone())
+                .increment(1);
+
+            return Err(err);
+        }
+
+        sqlx::query(
+            r"
+            UPDATE audit_export_cursors
+            SET last_created_at = $2, last_id = $3, failures = 0, last_error = NULL,
+                updated_at = now()
+            WHERE sink = $1
+            ",
+        )
+        .bind(&sink.name)
+        .bind(last.created_at)
+        .bind(last.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let sent = events.len() as u64;
+        metrics::counter!("audit_events_exported_total", "sink" => sink.name.clone())
+            .increment(sent);
+
+        Ok(sent)
+    }
+}
+
+/// Spawns the background task that runs [`AuditExporter::export`] every
+/// `audit.flush_interval` until `shutdown` is cancelled. Does nothing
+/// without sinks.
+pub fn spawn_exporter(exporter: AuditExporter, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if exporter.is_empty() {
+            return;
+        }
+
+        let period = StdDuration::from_secs(exporter.config.flush_interval().max(1));
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.cancelled() => break,
+            }
+
+            let exported = exporter.export().await;
+            if exported > 0 {
+                tracing::debug!(events = exported, "Audit events exported");
+            }
+        }
+    })
+}
@@ -0,0 +1,232 @@
+mod export;
+mod sinks;
+
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub use self::{
+    export::{AuditExporter, spawn_exporter},
+    sinks::AuditSink,
+};
+use crate::{
+    AppContext, Result,
+    events::{Event, Subscriber},
+    pagination::{Paginated, sort_key},
+};
+
+/// Security-relevant things that happen to an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// The user logged in from a new device or location.
+    SuspiciousLogin,
+    PasswordChanged,
+    /// An admin disabled the account.
+    UserDisabled,
+    /// An admin re-enabled the account.
+    UserEnabled,
+    /// An admin deleted the account.
+    UserDeleted,
+    /// An admin merged a duplicate account into this one.
+    UsersMerged,
+    /// An admin cleared the password and sent a reset link.
+    PasswordResetForced,
+    /// The user requested a copy of their personal data.
+    DataExportRequested,
+    /// The user asked for their account to be deleted after the grace
+    /// period.
+    DeletionScheduled,
+    /// The user cancelled a scheduled deletion.
+    DeletionCancelled,
+    /// The user confirmed a phone number for their account.
+    PhoneAdded,
+    /// The user removed their phone number.
+    PhoneRemoved,
+}
+
+impl AuditAction {
+    /// Value stored in `audit_events.action`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SuspiciousLogin => "suspicious_login",
+            Self::PasswordChanged => "password_changed",
+            Self::UserDisabled => "user_disabled",
+            Self::UserEnabled => "user_enabled",
+            Self::UserDeleted => "user_deleted",
+            Self::UsersMerged => "users_merged",
+            Self::PasswordResetForced => "password_reset_forced",
+            Self::DataExportRequested => "data_export_requested",
+            Self::DeletionScheduled => "deletion_scheduled",
+            Self::DeletionCancelled => "deletion_cancelled",
+            Self::PhoneAdded => "phone_added",
+            Self::PhoneRemoved => "phone_removed",
+        }
+    }
+}
+
+/// A recorded [`AuditAction`], as listed by `GET /admin/audit-events`.
+/// `user_id` is cleared when the user is deleted.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub action: String,
+    pub ip: Option<String>,
+    pub metadata: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Paginated for AuditEvent {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, _field: &str) -> String {
+        sort_key(self.created_at)
+    }
+}
+
+/// Append-only, Postgres-backed record of [`AuditAction`]s, kept by
+/// subscribing to the matching [`Event`]s. [`AuditExporter`] streams it to
+/// external systems.
+#[derive(Clone)]
+pub struct AuditLog {
+    db: PgPool,
+}
+
+impl AuditLog {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Records that `action` happened to `user_id`, from `ip` if known.
+    /// `metadata` holds any action-specific details and must not contain
+    /// secrets.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn record(
+        &self,
+        user_id: Uuid,
+        action: AuditAction,
+        ip: Option<IpAddr>,
+        metadata: Value,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO audit_events (user_id, action, ip, metadata, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(user_id)
+        .bind(action.name())
+        .bind(ip.map(|ip| ip.to_string()))
+        .bind(metadata)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+
+        tracing::info!(%user_id, action = action.name(), "Audit event recorded");
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Subscriber for AuditLog {
+    async fn handle(&self, _ctx: &AppContext, event: &Event) -> Result<()> {
+        let (action, ip, metadata) = match event {
+            Event::SuspiciousLogin {
+                session_id,
+                device,
+                ip,
+                anomalies,
+                ..
+            } => (
+                AuditAction::SuspiciousLogin,
+                ip,
+                json!({
+                    "session_id": session_id,
+                    "device": device,
+                    "anomalies": anomalies.iter().map(|anomaly| anomaly.name()).collect::<Vec<_>>(),
+                }),
+            ),
+            Event::PasswordChanged {
+                ip, sessions_ended, ..
+            } => (
+                AuditAction::PasswordChanged,
+                ip,
+                json!({ "sessions_ended": sessions_ended }),
+            ),
+            Event::UserDisabled { admin_id, ip, .. } => (
+                AuditAction::UserDisabled,
+                ip,
+                json!({ "admin_id": admin_id }),
+            ),
+            Event::UserEnabled { admin_id, ip, .. } => (
+                AuditAction::UserEnabled,
+                ip,
+                json!({ "admin_id": admin_id }),
+            ),
+            Event::UserDeleted {
+                user_id,
+                email,
+                admin_id,
+                ip,
+            } => (
+                AuditAction::UserDeleted,
+                ip,
+                json!({ "admin_id": admin_id, "user_id": user_id, "email": email }),
+            ),
+            Event::UsersMerged {
+                merged_user_id,
+                merged_email,
+                admin_id,
+                ip,
+                ..
+            } => (
+                AuditAction::UsersMerged,
+                ip,
+                json!({
+                    "admin_id": admin_id,
+                    "merged_user_id": merged_user_id,
+                    "merged_email": merged_email,
+                }),
+            ),
+            Event::PasswordResetForced { admin_id, ip, .. } => (
+                AuditAction::PasswordResetForced,
+                ip,
+                json!({ "admin_id": admin_id }),
+            ),
+            Event::DataExportRequested { export_id, ip, .. } => (
+                AuditAction::DataExportRequested,
+                ip,
+                json!({ "export_id": export_id }),
+            ),
+            Event::DeletionScheduled {
+                scheduled_for, ip, ..
+            } => (
+                AuditAction::DeletionScheduled,
+                ip,
+                json!({ "scheduled_for": scheduled_for }),
+            ),
+            Event::DeletionCancelled { ip, .. } => (AuditAction::DeletionCancelled, ip, json!({})),
+            Event::PhoneAdded { phone, ip, .. } => {
+                (AuditAction::PhoneAdded, ip, json!({ "phone": phone }))
+            }
+            Event::PhoneRemoved { phone, ip, .. } => {
+                (AuditAction::PhoneRemoved, ip, json!({ "phone": phone }))
+            }
+            _ => return Ok(()),
+        };
+
+        self.record(event.user_id(), action, *ip, metadata).await
+    }
+}
@@ -0,0 +1,427 @@
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    net::{TcpStream, UdpSocket, lookup_host},
+};
+use url::Url;
+
+use super::AuditEvent;
+use crate::{
+    Error, Result,
+    config::{AuditSinkConfig, AuditSinkKind, SecretString, SyslogProtocol},
+};
+
+/// Receives the exported audit events, see [`crate::config::AuditConfig`].
+///
+/// Sinks other than the configurable ones are added with
+/// [`super::AuditExporter::with_sink`] to an exporter the application runs
+/// itself, e.g. from a task added with [`crate::app::AppBuilder::task`].
+///
+/// ```no_run
+/// use async_trait::async_trait;
+/// use betterauth::{Result, audit::{AuditEvent, AuditSink}};
+///
+/// struct Stdout;
+///
+/// #[async_trait]
+/// impl AuditSink for Stdout {
+///     async fn send(&self, events: &[AuditEvent]) -> Result<()> {
+///         for event in events {
+///             println!("{} {}", event.created_at, event.action);
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Sends `events`, oldest first.
+    ///
+    /// A batch that fails is sent again, whole, so events the sink took
+    /// before failing arrive twice; receivers can tell them apart by `id`.
+    ///
+    /// ## Errors
+    /// * [`Error::AuditSink`] or another error if the batch was not taken
+    async fn send(&self, events: &[AuditEvent]) -> Result<()>;
+}
+
+/// The sink configured by `config`, whose requests are made with `http`.
+pub(super) fn from_config(config: &AuditSinkConfig, http: &reqwest::Client) -> Box<dyn AuditSink> {
+    let required = |value: Option<&str>| value.unwrap_or_default().to_owned();
+
+    match config.kind() {
+        AuditSinkKind::Syslog => Box::new(SyslogSink {
+            address: required(config.address()),
+            protocol: config.protocol(),
+            app_name: config.app_name().to_owned(),
+        }),
+        AuditSinkKind::Kafka => Box::new(KafkaSink {
+            http: http.clone(),
+            url: format!(
+                "{}/topics/{}",
+                required(config.url()).trim_end_matches('/'),
+                required(config.topic())
+            ),
+            token: config.token().cloned(),
+        }),
+        AuditSinkKind::File => Box::new(FileSink {
+            dir: config.dir().map(PathBuf::from).unwrap_or_default(),
+        }),
+        AuditSinkKind::S3 => Box::new(S3Sink {
+            http: http.clone(),
+            bucket: required(config.bucket()),
+            region: required(config.region()),
+            endpoint: config
+                .endpoint()
+                .map(|endpoint| endpoint.trim_end_matches('/').to_owned()),
+            prefix: config.prefix().to_owned(),
+            access_key_id: required(config.access_key_id()),
+            secret_access_key: config.secret_access_key().cloned().unwrap_or_default(),
+        }),
+        AuditSinkKind::Http => Box::new(HttpSink {
+            http: http.clone(),
+            url: required(config.url()),
+            token: config.token().cloned(),
+        }),
+    }
+}
+
+fn sink_error(err: impl std::fmt::Display) -> Error {
+    Error::AuditSink(err.to_string())
+}
+
+/// `events` as newline-delimited JSON.
+fn ndjson(events: &[AuditEvent]) -> String {
+    events
+        .iter()
+        .map(|event| serde_json::to_string(event).unwrap_or_default() + "\n")
+        .collect()
+}
+
+/// [`Error::AuditSink`] unless `response` has a 2xx status.
+fn check_status(response: &reqwest::Response) -> Result<()> {
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(sink_error(format!("sink answered {}", response.status())))
+    }
+}
+
+/// RFC 5424 messages, facility `authpriv`, to a syslog server.
+struct SyslogSink {
+    address: String,
+    protocol: SyslogProtocol,
+    app_name: String,
+}
+
+/// `<86>`: facility `authpriv` (10), severity `info` (6).
+const SYSLOG_PRIORITY: u8 = 10 * 8 + 6;
+
+impl SyslogSink {
+    /// `event` as a message with the action as `MSGID` and the event as
+    /// JSON in `MSG`.
+    fn message(&self, event: &AuditEvent) -> String {
+        format!(
+            "<{SYSLOG_PRIORITY}>1 {} - {} - {} - {}",
+            event
+                .created_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            self.app_name,
+            event.action,
+            serde_json::to_string(event).unwrap_or_default()
+        )
+    }
+
+    async fn resolve(&self) -> Result<SocketAddr> {
+        lookup_host(&self.address)
+            .await
+            .map_err(sink_error)?
+            .next()
+            .ok_or_else(|| sink_error(format!("{} did not resolve", self.address)))
+    }
+}
+
+#[async_trait]
+impl AuditSink for SyslogSink {
+    async fn send(&self, events: &[AuditEvent]) -> Result<()> {
+        let address = self.resolve().await?;
+
+        match self.protocol {
+            SyslogProtocol::Udp => {
+                let local: SocketAddr = if address.is_ipv4() {
+                    ([0, 0, 0, 0], 0).into()
+                } else {
+                    ([0; 16], 0).into()
+                };
+                let socket = UdpSocket::bind(local).await.map_err(sink_error)?;
+                socket.connect(address).await.map_err(sink_error)?;
+
+                for event in events {
+                    socket
+                        .send(self.message(event).as_bytes())
+                        .await
+                        .map_err(sink_error)?;
+                }
+            }
+            SyslogProtocol::Tcp => {
+                let mut stream = TcpStream::connect(address).await.map_err(sink_error)?;
+                let framed: String = events
+                    .iter()
+                    .map(|event| {
+                        let message = self.message(event);
+                        format!("{} {message}", message.len())
+                    })
+                    .collect();
+
+                stream
+                    .write_all(framed.as_bytes())
+                    .await
+                    .map_err(sink_error)?;
+                stream.shutdown().await.map_err(sink_error)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Records produced through the v2 API of a Kafka REST Proxy, keyed by
+/// user so each user's events stay in order.
+struct KafkaSink {
+    http: reqwest::Client,
+    /// `{url}/topics/{topic}`.
+    url: String,
+    token: Option<SecretString>,
+}
+
+#[async_trait]
+impl AuditSink for KafkaSink {
+    async fn send(&self, events: &[AuditEvent]) -> Result<()> {
+        let records: Vec<Value> = events
+            .iter()
+            .map(|event| json!({ "key": event.user_id, "value": event }))
+            .collect();
+
+        let mut request = self
+            .http
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/vnd.kafka.json.v2+json")
+            .json(&json!({ "records": records }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token.expose());
+        }
+
+        let response = request.send().await.map_err(sink_error)?;
+        check_status(&response)?;
+
+        // The proxy answers 200 even when some records were refused, with
+        // an error per record in `offsets`.
+        let body: Value = response.json().await.map_err(sink_error)?;
+        let refused = body["offsets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find_map(|offset| offset["error"].as_str().filter(|error| !error.is_empty()));
+
+        match refused {
+            Some(error) => Err(sink_error(error)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// NDJSON appended to `audit-{date}.ndjson` in a directory, a file per UTC
+/// day of the events.
+struct FileSink {
+    dir: PathBuf,
+}
+
+#[async_trait]
+impl AuditSink for FileSink {
+    async fn send(&self, events: &[AuditEvent]) -> Result<()> {
+        let mut by_day: BTreeMap<String, Vec<AuditEvent>> = BTreeMap::new();
+        for event in events {
+            by_day
+                .entry(event.created_at.format("%Y-%m-%d").to_string())
+                .or_default()
+                .push(event.clone());
+        }
+
+        for (day, events) in by_day {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(format!("audit-{day}.ndjson")))
+                .await?;
+
+            file.write_all(ndjson(&events).as_bytes()).await?;
+            file.sync_data().await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An NDJSON object per batch, written with a `PutObject` signed with AWS
+/// Signature Version 4.
+///
+/// Objects are named after the first event of the batch, so a batch sent
+/// again replaces the object rather than adding another.
+struct S3Sink {
+    http: reqwest::Client,
+    bucket: String,
+    region: String,
+    endpoint: Option<String>,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: SecretString,
+}
+
+impl S3Sink {
+    /// URL of the object `key`: virtual-hosted on AWS, by path on the
+    /// configured endpoint.
+    fn object_url(&self, key: &str) -> Result<Url> {
+        let key = uri_encode(key);
+        let url = match &self.endpoint {
+            Some(endpoint) => format!("{endpoint}/{}/{key}", uri_encode(&self.bucket)),
+            None => format!(
+                "https://{}.s3.{}.amazonaws.com/{key}",
+                self.bucket, self.region
+            ),
+        };
+
+        Url::parse(&url).map_err(sink_error)
+    }
+
+    /// The `Authorization` header of a `PUT` of a body hashing to
+    /// `payload_hash` to `url` at `amz_date`.
+    fn authorization(&self, url: &Url, amz_date: &str, payload_hash: &str) -> String {
+        let host = host(url);
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let signed_headers = "content-type;host;x-amz-content-sha256;x-amz-date";
+
+        let canonical_request = format!(
+            "PUT\n{}\n\ncontent-type:application/x-ndjson\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}",
+            url.path()
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{:x}",
+            Sha256::digest(canonical_request.as_bytes())
+        );
+
+        let secret = format!("AWS4{}", self.secret_access_key.expose());
+        let key = [date, self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(secret.into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+        let signature: String = hmac(&key, string_to_sign.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key_id
+        )
+    }
+}
+
+#[async_trait]
+impl AuditSink for S3Sink {
+    async fn send(&self, events: &[AuditEvent]) -> Result<()> {
+        let Some(first) = events.first() else {
+            return Ok(());
+        };
+
+        let key = format!(
+            "{}{}-{}.ndjson",
+            self.prefix,
+            first.created_at.format("%Y/%m/%d/%H%M%S%.3f"),
+            first.id
+        );
+        let url = self.object_url(&key)?;
+        let body = ndjson(events);
+        let payload_hash = format!("{:x}", Sha256::digest(body.as_bytes()));
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let response = self
+            .http
+            .put(url.clone())
+            .header(CONTENT_TYPE, "application/x-ndjson")
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header(
+                AUTHORIZATION,
+                self.authorization(&url, &amz_date, &payload_hash),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(sink_error)?;
+
+        check_status(&response)
+    }
+}
+
+/// The `Host` header of a request to `url`.
+fn host(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+
+    match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    }
+}
+
+/// `path` with everything but unreserved characters and `/` percent-encoded,
+/// as Signature Version 4 canonicalizes it.
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// NDJSON batches `POST`ed to a collector.
+struct HttpSink {
+    http: reqwest::Client,
+    url: String,
+    token: Option<SecretString>,
+}
+
+#[async_trait]
+impl AuditSink for HttpSink {
+    async fn send(&self, events: &[AuditEvent]) -> Result<()> {
+        let mut request = self
+            .http
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/x-ndjson")
+            .body(ndjson(events));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token.expose());
+        }
+
+        let response = request.send().await.map_err(sink_error)?;
+
+        check_status(&response)
+    }
+}
@@ -0,0 +1,52 @@
+use crate::config::EmailNormalization;
+
+/// Minimal structural check: a non-empty local part and a dotted domain.
+#[must_use]
+pub fn is_valid_email(email: &str) -> bool {
+    email.len() <= 255
+        && email.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+        })
+}
+
+/// The form of `email` accounts are told apart by: trimmed and lowercased,
+/// with dots and `+tag` aliases of the local part folded as `options` ask.
+///
+/// ```
+/// use betterauth::{auth::normalize_email, config::EmailNormalization};
+///
+/// let options = EmailNormalization::default();
+/// assert_eq!(normalize_email(" Ada.L@Example.com ", &options), "ada.l@example.com");
+/// ```
+#[must_use]
+pub fn normalize_email(email: &str, options: &EmailNormalization) -> String {
+    let email = email.trim().to_lowercase();
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return email;
+    };
+
+    let folded = options.domains().is_empty()
+        || options
+            .domains()
+            .iter()
+            .any(|folded| folded.eq_ignore_ascii_case(domain));
+    if !folded {
+        return email;
+    }
+
+    let mut local = local;
+    if options.strip_aliases() {
+        local = local.split_once('+').map_or(local, |(local, _)| local);
+    }
+    let local = if options.fold_dots() {
+        local.replace('.', "")
+    } else {
+        local.to_owned()
+    };
+
+    format!("{local}@{domain}")
+}
@@ -0,0 +1,408 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error,
+    api_keys::{API_KEY_HEADER, ApiKeyStore},
+    auth::Role,
+    models::User,
+    sessions::{BindingChanged, Session},
+    tokens::TokenKind,
+    trace,
+};
+
+/// How an [`AuthUser`] proved who they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Credential {
+    /// A session cookie or an access token bound to this session.
+    Session(Uuid),
+    /// The API key with this id.
+    ApiKey(Uuid),
+    /// The access token with this id, issued by the OIDC provider to a
+    /// client the user authorized.
+    OAuthToken(Uuid),
+}
+
+/// The authenticated caller, with their account loaded through
+/// [`AppContext::users`].
+///
+/// Resolved, in order, from the session cookie (see
+/// [`crate::sessions::middleware`]), an `X-API-Key` header, or an
+/// `Authorization: Bearer` header carrying an access token, an API key, or
+/// an access token of the OIDC provider.
+///
+/// Add it as a handler argument to require authentication; requests without
+/// a valid session or access token, or whose account no longer exists, are
+/// rejected with `401 Unauthorized`. See [`OptionalAuthUser`] for routes
+/// open to everyone.
+///
+/// Session-backed callers also carry the organization they have switched to
+/// (see `POST /auth/organizations/active`), which handlers can use to scope
+/// queries. API keys are not tied to an organization.
+///
+/// Credentials limited to scopes, that is scoped API keys and OIDC access
+/// tokens, are rejected with `403 Forbidden` unless the route is guarded by
+/// a [`super::RequireScope`] they passed, so they only reach what they were
+/// granted.
+///
+/// ```no_run
+/// use betterauth::auth::AuthUser;
+///
+/// async fn whoami(user: AuthUser) -> String {
+///     user.user().email.clone()
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    user: User,
+    credential: Credential,
+    organization_id: Option<Uuid>,
+    scopes: Option<Vec<String>>,
+}
+
+impl AuthUser {
+    #[must_use]
+    pub fn id(&self) -> Uuid {
+        self.user.id
+    }
+
+    /// The caller's account, as loaded for this request.
+    #[must_use]
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    #[must_use]
+    pub fn into_user(self) -> User {
+        self.user
+    }
+
+    #[must_use]
+    pub fn credential(&self) -> Credential {
+        self.credential
+    }
+
+    /// The session the credentials belong to, or `None` for API keys and
+    /// OIDC access tokens.
+    #[must_use]
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self.credential {
+            Credential::Session(id) => Some(id),
+            Credential::ApiKey(_) | Credential::OAuthToken(_) => None,
+        }
+    }
+
+    /// The caller's active organization, if they have switched to one.
+    #[must_use]
+    pub fn organization_id(&self) -> Option<Uuid> {
+        self.organization_id
+    }
+
+    /// Whether the credentials grant `scope`. Sessions and API keys without
+    /// scopes grant every scope, see [`crate::api_keys::ApiKey`].
+    #[must_use]
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|granted| granted == scope))
+    }
+}
+
+impl FromRequestParts<Arc<AppContext>> for AuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<AppContext>,
+    ) -> Result<Self, Self::Rejection> {
+        let user = authenticate(parts, ctx)
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+
+        admit(parts, user)
+    }
+}
+
+/// The caller if the request carries credentials, for routes that behave
+/// differently for signed-in users.
+///
+/// Requests without credentials, including anonymous sessions, extract
+/// `None`; credentials that are present but invalid are still rejected with
+/// `401 Unauthorized`, so that a client notices its token expired.
+///
+/// ```no_run
+/// use betterauth::auth::OptionalAuthUser;
+///
+/// async fn greet(OptionalAuthUser(user): OptionalAuthUser) -> String {
+///     match user {
+///         Some(user) => format!("Hello, {}", user.user().email),
+///         None => String::from("Hello, stranger"),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OptionalAuthUser(pub Option<AuthUser>);
+
+impl FromRequestParts<Arc<AppContext>> for OptionalAuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<AppContext>,
+    ) -> Result<Self, Self::Rejection> {
+        let user = authenticate(parts, ctx).await?;
+
+        user.map(|user| admit(parts, user)).transpose().map(Self)
+    }
+}
+
+/// An authenticated caller whose session may be in use by another client
+/// than it is bound to, see [`crate::config::SessionBinding`]. Only for
+/// `POST /auth/reauthenticate`, which binds the session to the new client.
+#[derive(Debug, Clone)]
+pub(crate) struct RebindingUser(pub AuthUser);
+
+impl FromRequestParts<Arc<AppContext>> for RebindingUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<AppContext>,
+    ) -> Result<Self, Self::Rejection> {
+        parts.extensions.remove::<BindingChanged>();
+
+        AuthUser::from_request_parts(parts, ctx).await.map(Self)
+    }
+}
+
+/// Resolves the caller from the session cookie, an API key or an access
+/// token and loads their account, see [`AuthUser`]. `None` if the request
+/// carries none of them.
+///
+/// The caller is kept in the request extensions, so that a guard and the
+/// handler behind it resolve them once.
+pub(super) async fn authenticate(
+    parts: &mut Parts,
+    ctx: &Arc<AppContext>,
+) -> Result<Option<AuthUser>, Error> {
+    if let Some(user) = parts.extensions.get::<AuthUser>() {
+        return Ok(Some(user.clone()));
+    }
+
+    let Some(identity) = identify(parts, ctx).await? else {
+        return Ok(None);
+    };
+
+    let user = ctx
+        .users()
+        .find(identity.user_id)
+        .await?
+        .ok_or(Error::Unauthenticated)?;
+    trace::record_user(user.id);
+
+    let user = AuthUser {
+        user,
+        credential: identity.credential,
+        organization_id: identity.organization_id,
+        scopes: identity.scopes,
+    };
+    parts.extensions.insert(user.clone());
+
+    Ok(Some(user))
+}
+
+/// Left in the request extensions by a [`super::RequireScope`] guard the
+/// caller passed.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ScopeChecked;
+
+/// Lets `user` through to the handler unless their credentials are limited
+/// to scopes and no scope guard checked them.
+fn admit(parts: &Parts, user: AuthUser) -> Result<AuthUser, Error> {
+    if user.scopes.is_some() && parts.extensions.get::<ScopeChecked>().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    Ok(user)
+}
+
+/// Who the credentials of a request belong to, before their account is
+/// loaded.
+struct Identity {
+    user_id: Uuid,
+    credential: Credential,
+    organization_id: Option<Uuid>,
+    scopes: Option<Vec<String>>,
+}
+
+async fn identify(parts: &Parts, ctx: &Arc<AppContext>) -> Result<Option<Identity>, Error> {
+    // Anonymous sessions do not authenticate anyone, nor do sessions used by
+    // another client than they are bound to until they reauthenticate.
+    if let Some(session) = parts.extensions.get::<Session>()
+        && let Some(user_id) = session.user_id
+    {
+        if parts.extensions.get::<BindingChanged>().is_some() {
+            return Err(Error::ReauthenticationRequired);
+        }
+
+        return Ok(Some(Identity {
+            user_id,
+            credential: Credential::Session(session.id),
+            organization_id: session.active_organization_id,
+            scopes: None,
+        }));
+    }
+
+    let Some(token) = api_key_header(parts).or_else(|| bearer_token(parts)) else {
+        return Ok(None);
+    };
+
+    if ApiKeyStore::is_api_key(token) {
+        let key = ctx
+            .api_keys()
+            .authenticate(token)
+            .await?
+            .ok_or(Error::InvalidToken)?;
+
+        return Ok(Some(Identity {
+            user_id: key.user_id,
+            credential: Credential::ApiKey(key.id),
+            organization_id: None,
+            scopes: key.scopes,
+        }));
+    }
+
+    // Access tokens of the OIDC provider are opaque, where JWTs have dots.
+    if !token.contains('.') && ctx.oidc().is_ok() {
+        let grant = ctx
+            .oauth_clients()
+            .access_grant(token)
+            .await?
+            .ok_or(Error::InvalidToken)?;
+
+        return Ok(Some(Identity {
+            user_id: grant.user_id,
+            credential: Credential::OAuthToken(grant.id),
+            organization_id: None,
+            scopes: Some(grant.scope.split_whitespace().map(String::from).collect()),
+        }));
+    }
+
+    let claims = ctx.tokens().verify(token, TokenKind::Access)?;
+
+    if ctx.revocations().is_revoked(&claims).await? {
+        return Err(Error::InvalidToken);
+    }
+
+    Ok(Some(Identity {
+        user_id: claims.sub,
+        credential: Credential::Session(claims.sid),
+        organization_id: claims.org,
+        scopes: None,
+    }))
+}
+
+/// An authenticated caller holding the [`Role::Admin`] role.
+///
+/// Rejects other authenticated users with `403 Forbidden`.
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub AuthUser);
+
+impl FromRequestParts<Arc<AppContext>> for AdminUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<AppContext>,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, ctx).await?;
+
+        if user.user().role != Role::Admin {
+            return Err(Error::Forbidden);
+        }
+
+        Ok(Self(user))
+    }
+}
+
+/// An authenticated caller whose session logged in or reauthenticated
+/// within `auth.reauthentication_window`, for sensitive operations. See
+/// [`super::RequireRecentAuth`] for other windows.
+///
+/// Rejects callers who authenticated longer ago with `403 Forbidden` and
+/// the `reauthentication_required` code, upon which clients send the user
+/// through `POST /auth/reauthenticate` and retry. API keys cannot
+/// reauthenticate and are always rejected with `403 Forbidden`.
+#[derive(Debug, Clone)]
+pub struct RecentlyAuthenticated(pub AuthUser);
+
+impl FromRequestParts<Arc<AppContext>> for RecentlyAuthenticated {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<AppContext>,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, ctx).await?;
+        let window = Duration::from_secs(ctx.config().auth().reauthentication_window());
+
+        check_recent_auth(parts, ctx, &user, window).await?;
+
+        Ok(Self(user))
+    }
+}
+
+/// Checks that the session of `user` logged in or reauthenticated within
+/// `max_age`.
+pub(super) async fn check_recent_auth(
+    parts: &Parts,
+    ctx: &AppContext,
+    user: &AuthUser,
+    max_age: Duration,
+) -> Result<(), Error> {
+    let session_id = user.session_id().ok_or(Error::Forbidden)?;
+
+    let authenticated_at = match parts.extensions.get::<Session>() {
+        Some(session) if session.id == session_id => session.authenticated_at,
+        _ => {
+            ctx.sessions()
+                .find(session_id)
+                .await?
+                .ok_or(Error::Unauthenticated)?
+                .authenticated_at
+        }
+    };
+
+    let max_age = chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::MAX);
+    if authenticated_at.is_none_or(|at| ctx.clock().now() - at > max_age) {
+        return Err(Error::ReauthenticationRequired);
+    }
+
+    Ok(())
+}
+
+/// Extracts the token from an `Authorization: Bearer <token>` header.
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Extracts the key from an `X-API-Key` header.
+fn api_key_header(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(API_KEY_HEADER)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+}
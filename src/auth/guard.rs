@@ -0,0 +1,183 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    extract::Request,
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+
+use super::{
+    Role,
+    extract::{ScopeChecked, authenticate, check_recent_auth},
+};
+use crate::{AppContext, Error};
+
+/// Route layer letting only callers with `role` through, rejecting anyone
+/// else with `401 Unauthorized` or `403 Forbidden` before the handler runs.
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use axum::{Router, routing::get};
+/// use betterauth::{
+///     AppContext,
+///     auth::{RequireRole, RequireScope, Role},
+/// };
+///
+/// fn router() -> Router<Arc<AppContext>> {
+///     Router::new()
+///         .route("/reports", get(|| async { "reports" }))
+///         .route_layer(RequireScope("reports:read"))
+///         .route_layer(RequireRole(Role::Admin))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RequireRole(pub Role);
+
+/// Route layer letting only callers whose credentials grant the scope
+/// through, see [`super::AuthUser::has_scope`]. Others are rejected with
+/// `401 Unauthorized` or `403 Forbidden` before the handler runs.
+///
+/// Credentials limited to scopes only reach routes guarded by one, see
+/// [`super::AuthUser`]; the built-in ones are in
+/// [`super::BUILT_IN_SCOPES`].
+#[derive(Debug, Clone, Copy)]
+pub struct RequireScope(pub &'static str);
+
+/// Route layer letting only callers whose session logged in or
+/// reauthenticated within the duration through, see
+/// [`super::RecentlyAuthenticated`]. Others are rejected with
+/// `401 Unauthorized` or `403 Forbidden` before the handler runs.
+///
+/// ```no_run
+/// use std::{sync::Arc, time::Duration};
+///
+/// use axum::{Router, routing::post};
+/// use betterauth::{AppContext, auth::RequireRecentAuth};
+///
+/// fn router() -> Router<Arc<AppContext>> {
+///     Router::new()
+///         .route("/billing/payout-account", post(|| async { "updated" }))
+///         .route_layer(RequireRecentAuth(Duration::from_secs(10 * 60)))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RequireRecentAuth(pub Duration);
+
+#[derive(Debug, Clone, Copy)]
+enum Requirement {
+    Role(Role),
+    Scope(&'static str),
+    RecentAuth(Duration),
+}
+
+impl Requirement {
+    /// Resolves the caller of `request` and checks them against the
+    /// requirement, leaving them in the extensions for the handler.
+    async fn check(self, request: Request) -> Result<Request, Error> {
+        let (mut parts, body) = request.into_parts();
+
+        let ctx = parts
+            .extensions
+            .get::<Arc<AppContext>>()
+            .cloned()
+            .expect("Guards need the AppContext in the request extensions");
+        let user = authenticate(&mut parts, &ctx)
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+
+        let allowed = match self {
+            Self::Role(role) => user.user().role == role,
+            Self::Scope(scope) => user.has_scope(scope),
+            Self::RecentAuth(max_age) => {
+                check_recent_auth(&parts, &ctx, &user, max_age).await?;
+                true
+            }
+        };
+
+        if !allowed {
+            return Err(Error::Forbidden);
+        }
+        if let Self::Scope(_) = self {
+            parts.extensions.insert(ScopeChecked);
+        }
+
+        Ok(Request::from_parts(parts, body))
+    }
+}
+
+impl<S> Layer<S> for RequireRole {
+    type Service = Guard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Guard {
+            inner,
+            requirement: Requirement::Role(self.0),
+        }
+    }
+}
+
+impl<S> Layer<S> for RequireScope {
+    type Service = Guard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Guard {
+            inner,
+            requirement: Requirement::Scope(self.0),
+        }
+    }
+}
+
+impl<S> Layer<S> for RequireRecentAuth {
+    type Service = Guard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Guard {
+            inner,
+            requirement: Requirement::RecentAuth(self.0),
+        }
+    }
+}
+
+/// Service of the [`RequireRole`], [`RequireScope`] and
+/// [`RequireRecentAuth`] layers.
+#[derive(Debug, Clone)]
+pub struct Guard<S> {
+    inner: S,
+    requirement: Requirement,
+}
+
+impl<S> Service<Request> for Guard<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The clone may not be ready, so call the service that was polled.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let requirement = self.requirement;
+
+        Box::pin(async move {
+            match requirement.check(request).await {
+                Ok(request) => inner.call(request).await,
+                Err(err) => Ok(err.into_response()),
+            }
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Semaphore;
+
+use super::Passwords;
+use crate::{Error, Result, config::PasswordHashing};
+
+/// Runs the [`Passwords`] work on tokio's blocking threads, so that Argon2
+/// does not stall the requests sharing a runtime thread with it, at most
+/// `password_hashing.max_concurrent` at once.
+///
+/// Work waiting longer than `password_hashing.queue_timeout` for its turn
+/// fails with [`Error::Overloaded`].
+#[derive(Clone)]
+pub struct Hashing {
+    passwords: Arc<Passwords>,
+    permits: Arc<Semaphore>,
+    queue_timeout: Duration,
+}
+
+impl Hashing {
+    #[must_use]
+    pub fn new(passwords: Passwords, config: &PasswordHashing) -> Self {
+        Self {
+            passwords: Arc::new(passwords),
+            permits: Arc::new(Semaphore::new(config.max_concurrent())),
+            queue_timeout: Duration::from_secs(config.queue_timeout()),
+        }
+    }
+
+    #[must_use]
+    pub fn passwords(&self) -> &Passwords {
+        &self.passwords
+    }
+
+    /// [`Passwords::hash`] on a blocking thread.
+    ///
+    /// ## Errors
+    /// * [`Error::Overloaded`] if it waited too long for its turn
+    /// * Whatever [`Passwords::hash`] fails with
+    pub async fn hash(&self, password: &str) -> Result<String> {
+        let password = password.to_owned();
+
+        self.run(move |passwords| passwords.hash(&password)).await?
+    }
+
+    /// [`Passwords::verify`] on a blocking thread.
+    ///
+    /// ## Errors
+    /// * [`Error::Overloaded`] if it waited too long for its turn
+    /// * Whatever [`Passwords::verify`] fails with
+    pub async fn verify(&self, password: &str, hash: &str) -> Result<bool> {
+        let (password, hash) = (password.to_owned(), hash.to_owned());
+
+        self.run(move |passwords| passwords.verify(&password, &hash))
+            .await?
+    }
+
+    /// [`Passwords::verify_dummy`] on a blocking thread, waiting for its
+    /// turn like real work so timing tells nothing apart.
+    pub async fn verify_dummy(&self, password: &str) {
+        let password = password.to_owned();
+
+        let _ = self
+            .run(move |passwords| passwords.verify_dummy(&password))
+            .await;
+    }
+
+    /// Runs `work` once a permit is free, keeping it until `work` is done
+    /// even if the caller gives up waiting.
+    async fn run<T, F>(&self, work: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Passwords) -> T + Send + 'static,
+    {
+        let permit = tokio::time::timeout(self.queue_timeout, self.permits.clone().acquire_owned())
+            .await
+            .map_err(|_| Error::Overloaded { retry_after: 1 })?
+            .expect("the semaphore is never closed");
+        let passwords = self.passwords.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            work(&passwords)
+        })
+        .await
+        .map_err(|err| Error::IO(err.into()))
+    }
+}
@@ -0,0 +1,28 @@
+mod email;
+mod extract;
+mod guard;
+mod hashing;
+mod opaque;
+mod password;
+mod pwned;
+mod role;
+mod scope;
+mod username;
+
+pub use self::{
+    email::{is_valid_email, normalize_email},
+    extract::{AdminUser, AuthUser, Credential, OptionalAuthUser, RecentlyAuthenticated},
+    guard::{Guard, RequireRecentAuth, RequireRole, RequireScope},
+    hashing::Hashing,
+    opaque::{generate_token, hash_token},
+    password::{PasswordViolation, Passwords, validate_password},
+    pwned::{PASSWORD_WARNING_HEADER, PasswordWarning, PwnedPasswords},
+    role::Role,
+    scope::{
+        AUDIT_READ_SCOPE, BUILT_IN_SCOPES, REPORTS_READ_SCOPE, SESSIONS_READ_SCOPE,
+        SESSIONS_WRITE_SCOPE, USERS_READ_SCOPE, USERS_WRITE_SCOPE, is_grantable_scope,
+    },
+    username::validate_username,
+};
+
+pub(crate) use self::extract::RebindingUser;
@@ -0,0 +1,28 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Number of random bytes in a generated opaque token (256 bits).
+const TOKEN_BYTES: usize = 32;
+
+/// Generates a random, URL-safe opaque token.
+///
+/// Used for secrets handed to clients (session cookies, emailed links) that
+/// are looked up server-side rather than verified cryptographically.
+#[must_use]
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hashes an opaque token for storage, as lowercase hex SHA-256.
+///
+/// Only the hash is persisted so a database leak does not hand out usable
+/// tokens. A fast hash is sufficient because the tokens carry 256 bits of
+/// entropy and cannot be brute-forced.
+#[must_use]
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
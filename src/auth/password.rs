@@ -0,0 +1,303 @@
+use std::{collections::HashMap, time::Instant};
+
+use argon2::{
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+    password_hash::{self, SaltString, rand_core::OsRng},
+};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::{
+    Error, Result,
+    config::{PasswordHashing, PasswordPolicy, SecretString},
+};
+
+/// Passwords rejected when [`PasswordPolicy::deny_common`] is on, one per line.
+static COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// A [`PasswordPolicy`] rule a password failed.
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordViolation {
+    /// Machine-readable rule name, e.g. `min_length`.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl PasswordViolation {
+    pub(super) fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks a new password against `policy`, reporting every rule it fails.
+///
+/// ## Errors
+/// * [`Error::WeakPassword`] listing the failed rules
+pub fn validate_password(policy: &PasswordPolicy, password: &str) -> Result<()> {
+    let length = password.chars().count();
+    let mut violations = Vec::new();
+
+    if length < policy.min_length() {
+        violations.push(PasswordViolation::new(
+            "min_length",
+            format!("must be at least {} characters long", policy.min_length()),
+        ));
+    }
+
+    if length > policy.max_length() {
+        violations.push(PasswordViolation::new(
+            "max_length",
+            format!("must be at most {} characters long", policy.max_length()),
+        ));
+    }
+
+    if policy.require_uppercase() && !password.chars().any(char::is_uppercase) {
+        violations.push(PasswordViolation::new(
+            "uppercase",
+            "must contain an uppercase letter",
+        ));
+    }
+
+    if policy.require_lowercase() && !password.chars().any(char::is_lowercase) {
+        violations.push(PasswordViolation::new(
+            "lowercase",
+            "must contain a lowercase letter",
+        ));
+    }
+
+    if policy.require_digit() && !password.chars().any(|c| c.is_ascii_digit()) {
+        violations.push(PasswordViolation::new("digit", "must contain a digit"));
+    }
+
+    if policy.require_symbol() && password.chars().all(char::is_alphanumeric) {
+        violations.push(PasswordViolation::new(
+            "symbol",
+            "must contain a character that is not a letter or digit",
+        ));
+    }
+
+    if policy.deny_common() && is_common(password) {
+        violations.push(PasswordViolation::new(
+            "common",
+            "is too common and easily guessed",
+        ));
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::WeakPassword(violations))
+    }
+}
+
+fn is_common(password: &str) -> bool {
+    let password = password.to_lowercase();
+
+    COMMON_PASSWORDS.lines().any(|common| common == password)
+}
+
+/// Hashes and verifies account passwords.
+///
+/// New hashes are Argon2id with the parameters of the `password_hashing`
+/// config section. Stored hashes made with other Argon2 variants or
+/// parameters, or bcrypt hashes imported from another system, still verify;
+/// [`Passwords::needs_rehash`] tells when one should be replaced after a
+/// successful login.
+///
+/// With a pepper configured, Argon2 is given the HMAC-SHA256 of the password
+/// under it instead of the password, and the pepper version is stored in the
+/// `keyid` field of the PHC string so retired peppers can still be told
+/// apart and used.
+#[derive(Clone)]
+pub struct Passwords {
+    argon2: Argon2<'static>,
+    /// Version of the pepper new hashes are made with.
+    pepper: Option<u32>,
+    peppers: HashMap<u32, SecretString>,
+    dummy_hash: String,
+}
+
+impl Passwords {
+    /// # Panics
+    /// If the configured parameters are invalid, which config validation
+    /// rules out.
+    #[must_use]
+    pub fn from_config(config: &PasswordHashing) -> Self {
+        let mut params = ParamsBuilder::new();
+        params
+            .m_cost(config.memory_kib())
+            .t_cost(config.iterations())
+            .p_cost(config.parallelism());
+        let mut peppers = config.retired_peppers().clone();
+        let mut pepper = None;
+
+        if let Some(current) = config.pepper() {
+            let version = config.pepper_version();
+            let keyid = KeyId::new(&version.to_be_bytes()).expect("a u32 fits in a key id");
+
+            params.keyid(keyid);
+            peppers.insert(version, current.clone());
+            pepper = Some(version);
+        }
+
+        let params = params
+            .build()
+            .expect("password_hashing parameters are validated on load");
+
+        let mut passwords = Self {
+            argon2: Argon2::new(Algorithm::Argon2id, Version::V0x13, params),
+            pepper,
+            peppers,
+            dummy_hash: String::new(),
+        };
+        passwords.dummy_hash = passwords
+            .hash("betterauth-timing-equalizer")
+            .expect("hashing a constant password cannot fail");
+
+        passwords
+    }
+
+    /// Hashes `password` with Argon2id and a random salt, returning the PHC
+    /// string.
+    ///
+    /// ## Errors
+    /// * The underlying Argon2 implementation fails to produce a hash
+    pub fn hash(&self, password: &str) -> Result<String> {
+        let started = Instant::now();
+        let salt = SaltString::generate(&mut OsRng);
+        let input = self.peppered(password, self.pepper)?;
+
+        let hash = self
+            .argon2
+            .hash_password(&input, &salt)
+            .map(|hash| hash.to_string())
+            .map_err(Error::PasswordHash);
+        record_duration("hash", started);
+
+        hash
+    }
+
+    /// Checks `password` against a stored Argon2 PHC string or bcrypt hash.
+    ///
+    /// The comparison of the derived key is performed in constant time by
+    /// the `password-hash` and `bcrypt` crates. Returns `Ok(false)` on a
+    /// mismatch.
+    ///
+    /// ## Errors
+    /// * `hash` is neither a valid PHC string nor a valid bcrypt hash
+    /// * [`Error::MissingPepper`] if `hash` was made with a pepper that is no
+    ///   longer configured
+    pub fn verify(&self, password: &str, hash: &str) -> Result<bool> {
+        let started = Instant::now();
+        let verified = self.check(password, hash);
+        record_duration("verify", started);
+
+        verified
+    }
+
+    /// [`Passwords::verify`] without recording how long it took.
+    fn check(&self, password: &str, hash: &str) -> Result<bool> {
+        if is_bcrypt(hash) {
+            return bcrypt::verify(password, hash).map_err(Error::Bcrypt);
+        }
+
+        let parsed = PasswordHash::new(hash).map_err(Error::PasswordHash)?;
+        let input = self.peppered(password, pepper_version(&parsed)?)?;
+
+        // The stored hash carries its own algorithm and parameters, which
+        // override ours.
+        match self.argon2.verify_password(&input, &parsed) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(err) => Err(Error::PasswordHash(err)),
+        }
+    }
+
+    /// Whether `hash` was made with anything other than the configured
+    /// Argon2id parameters and pepper, and should be replaced by a fresh
+    /// [`Passwords::hash`] of the password once it has been verified.
+    #[must_use]
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        if is_bcrypt(hash) {
+            return true;
+        }
+
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&parsed) else {
+            return true;
+        };
+        let current = self.argon2.params();
+
+        Algorithm::try_from(parsed.algorithm) != Ok(Algorithm::Argon2id)
+            || parsed.version != Some(Version::V0x13.into())
+            || params.m_cost() != current.m_cost()
+            || params.t_cost() != current.t_cost()
+            || params.p_cost() != current.p_cost()
+            || params.output_len()
+                != Some(current.output_len().unwrap_or(Params::DEFAULT_OUTPUT_LEN))
+            || params.keyid() != current.keyid()
+    }
+
+    /// Burns the same amount of work as [`Passwords::verify`] without a real
+    /// hash.
+    ///
+    /// Call it when no account matches the submitted identifier so response
+    /// timing does not reveal which emails are registered.
+    pub fn verify_dummy(&self, password: &str) {
+        let _ = self.verify(password, &self.dummy_hash);
+    }
+
+    /// What Argon2 is given for `password` under the pepper of `version`:
+    /// the password itself without one, its HMAC-SHA256 with one.
+    fn peppered(&self, password: &str, version: Option<u32>) -> Result<Vec<u8>> {
+        let Some(version) = version else {
+            return Ok(password.as_bytes().to_vec());
+        };
+        let pepper = self
+            .peppers
+            .get(&version)
+            .ok_or(Error::MissingPepper(version))?;
+
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(pepper.expose().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(password.as_bytes());
+
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+}
+
+/// Records the time since `started` in `password_hash_duration_seconds`.
+fn record_duration(operation: &'static str, started: Instant) {
+    metrics::histogram!("password_hash_duration_seconds", "operation" => operation)
+        .record(started.elapsed().as_secs_f64());
+}
+
+/// The pepper version recorded in the `keyid` of `hash`, if any.
+fn pepper_version(hash: &PasswordHash<'_>) -> Result<Option<u32>> {
+    let params = Params::try_from(hash).map_err(Error::PasswordHash)?;
+
+    match params.keyid() {
+        [] => Ok(None),
+        keyid => <[u8; 4]>::try_from(keyid)
+            .map(|bytes| Some(u32::from_be_bytes(bytes)))
+            .map_err(|_| {
+                Error::PasswordHash(password_hash::Error::ParamValueInvalid(
+                    password_hash::errors::InvalidValue::Malformed,
+                ))
+            }),
+    }
+}
+
+/// Whether `hash` is in the modular crypt format of bcrypt, e.g. `$2b$12$…`.
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
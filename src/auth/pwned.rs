@@ -0,0 +1,146 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    http::{HeaderName, HeaderValue},
+    response::{IntoResponseParts, ResponseParts},
+};
+use sha1::{Digest, Sha1};
+
+use super::PasswordViolation;
+use crate::{
+    Error, Result,
+    config::{BreachedPasswords, PasswordPolicy},
+};
+
+/// Header set on responses accepting a breached password in
+/// [`BreachedPasswords::Warn`] mode.
+pub const PASSWORD_WARNING_HEADER: HeaderName = HeaderName::from_static("x-password-warning");
+
+/// Looks passwords up in a Pwned Passwords range API, Have I Been Pwned by
+/// default.
+///
+/// Only the first five hex characters of the SHA-1 hash leave the server;
+/// the service answers with every hash suffix sharing that prefix, padded
+/// with decoys, and the match is made locally.
+#[derive(Clone)]
+pub struct PwnedPasswords {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl PwnedPasswords {
+    /// # Panics
+    /// If the TLS backend of the HTTP client cannot be initialised.
+    #[must_use]
+    pub fn from_config(policy: &PasswordPolicy) -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("betterauth/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(5))
+            .build()
+            .expect("failed to initialise HTTP client");
+
+        Self {
+            http,
+            url: policy.pwned_passwords_url().to_owned(),
+        }
+    }
+
+    /// How many times `password` appears in known breaches.
+    ///
+    /// ## Errors
+    /// * [`Error::PwnedPasswords`] if the service could not be reached or
+    ///   answered with an error
+    pub async fn occurrences(&self, password: &str) -> Result<u64> {
+        let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(5);
+
+        let body = self
+            .http
+            .get(format!("{}{prefix}", self.url))
+            .header("Add-Padding", "true")
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(lookup_error)?
+            .text()
+            .await
+            .map_err(lookup_error)?;
+
+        Ok(body
+            .lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+            .and_then(|(_, count)| count.parse().ok())
+            .unwrap_or(0))
+    }
+
+    /// Applies [`PasswordPolicy::breached_passwords`] to a new password.
+    ///
+    /// The password is accepted when the lookup fails, so an outage of the
+    /// service does not block registrations.
+    ///
+    /// ## Errors
+    /// * [`Error::WeakPassword`] if the password was found and breached
+    ///   passwords are rejected
+    pub async fn check(&self, policy: &PasswordPolicy, password: &str) -> Result<PasswordWarning> {
+        let mode = policy.breached_passwords();
+        if mode == BreachedPasswords::Off {
+            return Ok(PasswordWarning::default());
+        }
+
+        let occurrences = match self.occurrences(password).await {
+            Ok(occurrences) => occurrences,
+            Err(err) => {
+                tracing::warn!(error = %err, "Breached password lookup failed, accepting the password");
+                return Ok(PasswordWarning::default());
+            }
+        };
+
+        match (occurrences, mode) {
+            (0, _) => Ok(PasswordWarning::default()),
+            (_, BreachedPasswords::Reject) => {
+                Err(Error::WeakPassword(vec![PasswordViolation::new(
+                    "breached",
+                    "has appeared in a data breach",
+                )]))
+            }
+            _ => Ok(PasswordWarning { breached: true }),
+        }
+    }
+}
+
+fn lookup_error(err: reqwest::Error) -> Error {
+    Error::PwnedPasswords(err.to_string())
+}
+
+/// Outcome of [`PwnedPasswords::check`] for a password that was accepted.
+///
+/// Add it to the response to set `X-Password-Warning: breached` when the
+/// password was found in a breach.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PasswordWarning {
+    breached: bool,
+}
+
+impl PasswordWarning {
+    /// Whether the password appeared in a known breach.
+    #[must_use]
+    pub fn is_breached(self) -> bool {
+        self.breached
+    }
+}
+
+impl IntoResponseParts for PasswordWarning {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        if self.breached {
+            res.headers_mut().insert(
+                PASSWORD_WARNING_HEADER,
+                HeaderValue::from_static("breached"),
+            );
+        }
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,47 @@
+use super::Role;
+
+/// Scope of `GET /admin/users` and `GET /admin/users/{id}`.
+pub const USERS_READ_SCOPE: &str = "users:read";
+/// Scope of the admin routes changing users, and of `POST /admin/invites`.
+pub const USERS_WRITE_SCOPE: &str = "users:write";
+/// Scope of `GET /admin/audit-events`.
+pub const AUDIT_READ_SCOPE: &str = "audit:read";
+/// Scope of the `GET /admin/reports/*` routes.
+pub const REPORTS_READ_SCOPE: &str = "reports:read";
+/// Scope of `GET /me/sessions`.
+pub const SESSIONS_READ_SCOPE: &str = "sessions:read";
+/// Scope of `DELETE /me/sessions/{id}`.
+pub const SESSIONS_WRITE_SCOPE: &str = "sessions:write";
+
+/// The scopes guarding the built-in routes, with the role a user needs to
+/// be granted each, if any.
+pub const BUILT_IN_SCOPES: [(&str, Option<Role>); 6] = [
+    (USERS_READ_SCOPE, Some(Role::Admin)),
+    (USERS_WRITE_SCOPE, Some(Role::Admin)),
+    (AUDIT_READ_SCOPE, Some(Role::Admin)),
+    (REPORTS_READ_SCOPE, Some(Role::Admin)),
+    (SESSIONS_READ_SCOPE, None),
+    (SESSIONS_WRITE_SCOPE, None),
+];
+
+/// Whether credentials of a user with `role` may be granted `scope`.
+///
+/// Built-in scopes reserved to a role are only granted to users holding it;
+/// any other scope, such as those guarding the routes of the application,
+/// may be granted to anyone.
+///
+/// ## Examples
+/// ```
+/// # use betterauth::auth::{Role, SESSIONS_READ_SCOPE, USERS_READ_SCOPE, is_grantable_scope};
+/// assert!(is_grantable_scope(SESSIONS_READ_SCOPE, Role::User));
+/// assert!(!is_grantable_scope(USERS_READ_SCOPE, Role::User));
+/// assert!(is_grantable_scope(USERS_READ_SCOPE, Role::Admin));
+/// assert!(is_grantable_scope("invoices:read", Role::User));
+/// ```
+#[must_use]
+pub fn is_grantable_scope(scope: &str, role: Role) -> bool {
+    BUILT_IN_SCOPES
+        .iter()
+        .find(|(name, _)| *name == scope)
+        .is_none_or(|(_, required)| required.is_none_or(|required| required == role))
+}
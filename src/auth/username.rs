@@ -0,0 +1,47 @@
+use crate::{Error, Result, config::UsernameConfig};
+
+/// Checks a username a user asked for against `config`.
+///
+/// ```
+/// use betterauth::{auth::validate_username, config::UsernameConfig};
+///
+/// let config = UsernameConfig::default();
+/// assert!(validate_username(&config, "ada").is_err()); // usernames are disabled by default
+/// ```
+///
+/// ## Errors
+/// * [`Error::Validation`] if usernames are disabled, or `username` is too
+///   short or long, holds other characters than letters, digits, `_`, `.`
+///   and `-`, or is reserved
+pub fn validate_username(config: &UsernameConfig, username: &str) -> Result<()> {
+    if !config.enabled() {
+        return Err(Error::Validation(String::from("usernames are not enabled")));
+    }
+
+    let length = username.chars().count();
+    if length < config.min_length() || length > config.max_length() {
+        return Err(Error::Validation(format!(
+            "username must be between {} and {} characters long",
+            config.min_length(),
+            config.max_length()
+        )));
+    }
+
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-');
+    if !username.chars().all(allowed) || !username.starts_with(|c: char| c.is_ascii_alphanumeric())
+    {
+        return Err(Error::Validation(String::from(
+            "username may only contain letters, digits, `_`, `.` and `-`, and must start with a letter or digit",
+        )));
+    }
+
+    if config
+        .reserved()
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(username))
+    {
+        return Err(Error::Validation(String::from("username is reserved")));
+    }
+
+    Ok(())
+}
@@ -1,9 +1,288 @@
-use betterauth::{App, Result};
+use std::path::PathBuf;
+
+use betterauth::{
+    App, AppContext, Error, Result,
+    auth::{self, Passwords, Role},
+    config::{Environment, Level, Overrides},
+    models::NewUser,
+    seed::Fixtures,
+};
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+
+/// Runs the betterauth server, or manages its database with `db`.
+///
+/// Flags take precedence over `APP_` environment variables, which take
+/// precedence over the config file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Directory holding the `{environment}.yaml`, `.toml` or `.json` files
+    /// [default: $APP_CONFIG_DIR or ./config]
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+
+    /// Config file to read instead of the environment's one in the config
+    /// directory [default: $APP_CONFIG_FILE]
+    #[arg(short, long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Environment to load, e.g. `production` or `staging`
+    /// [default: $APP_ENVIRONMENT, $APP_ENV or development]
+    #[arg(short, long, global = true, value_name = "NAME")]
+    environment: Option<Environment>,
+
+    /// Port to listen on, overriding `server.port`
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Minimum level of logs, overriding `logger.level`
+    #[arg(long, global = true, value_enum)]
+    log_level: Option<Level>,
+
+    /// Prints the resolved configuration, secrets redacted, and exits
+    #[arg(long)]
+    print_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manages the database schema, whatever `database.auto_migrate` says
+    #[command(subcommand)]
+    Db(DbCommand),
+    /// Manages the keys tokens are signed with
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Creates a verified user with the admin role, e.g. the first one of a
+    /// fresh deployment
+    CreateAdmin {
+        /// Email address to log in with
+        #[arg(long)]
+        email: String,
+
+        /// Display name
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Password, checked against `password_policy`; a random one is
+        /// generated and printed once if omitted. Visible to other local
+        /// users in the process list while the command runs
+        #[arg(long)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DbCommand {
+    /// Applies the pending migrations
+    Migrate,
+    /// Reverts the last applied migration
+    Rollback,
+    /// Lists the migrations and whether each was applied
+    Status,
+    /// Reverts every migration, dropping all data, then applies them again
+    Reset {
+        /// Confirms that all data may be dropped
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Loads users and organizations from a fixtures file, updating those
+    /// that already exist. Only in the development and testing environments
+    Seed {
+        /// YAML, TOML or JSON file of fixtures
+        file: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum KeysCommand {
+    /// Generates a new asymmetric signing key, published at once and signing
+    /// from two minutes on. Tokens signed with earlier keys stay valid
+    Rotate,
+}
+
+impl From<Cli> for Overrides {
+    fn from(cli: Cli) -> Self {
+        let mut overrides = Overrides::default();
+
+        if let Some(dir) = cli.config_dir {
+            overrides = overrides.with_config_dir(dir);
+        }
+        if let Some(file) = cli.config {
+            overrides = overrides.with_config_file(file);
+        }
+        if let Some(env) = cli.environment {
+            overrides = overrides.with_environment(env);
+        }
+        if let Some(port) = cli.port {
+            overrides = overrides.with_port(port);
+        }
+        if let Some(level) = cli.log_level {
+            overrides = overrides.with_log_level(level);
+        }
+
+        overrides
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    if let Err(e) = App::run().await {
+    let mut cli = Cli::parse();
+    let command = cli.command.take();
+    let print = cli.print_config;
+    let overrides = cli.into();
+
+    let result = match command {
+        _ if print => print_config(&overrides).await,
+        Some(Command::Db(command)) => db(command, &overrides).await,
+        Some(Command::Keys(command)) => keys(command, &overrides).await,
+        Some(Command::CreateAdmin {
+            email,
+            name,
+            password,
+        }) => create_admin(&overrides, &email, name.as_deref(), password).await,
+        None => App::run_with(overrides).await,
+    };
+
+    if let Err(e) = result {
         eprintln!("Error {e}");
     }
     Ok(())
 }
+
+async fn print_config(overrides: &Overrides) -> Result<()> {
+    let config = App::config(overrides).await?;
+    println!("{config:#?}");
+
+    Ok(())
+}
+
+async fn db(command: DbCommand, overrides: &Overrides) -> Result<()> {
+    if matches!(command, DbCommand::Seed { .. })
+        && !matches!(
+            overrides.environment(),
+            Environment::Development | Environment::Testing
+        )
+    {
+        return Err(Error::Disabled("seeding outside development and testing"));
+    }
+
+    let config = App::config(overrides).await?;
+    let database = config.database();
+
+    match command {
+        DbCommand::Migrate => {
+            database.run_migrations().await?;
+            println!("Migrations applied");
+        }
+        DbCommand::Rollback => match database.rollback().await? {
+            Some(version) => println!("Rolled back {version}"),
+            None => println!("No migration to roll back"),
+        },
+        DbCommand::Status => {
+            for migration in database.migration_status().await? {
+                let state = if migration.applied {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!("{} {state:<7} {}", migration.version, migration.description);
+            }
+        }
+        DbCommand::Reset { yes: false } => {
+            eprintln!("Resetting drops all data, pass --yes to confirm");
+        }
+        DbCommand::Reset { yes: true } => {
+            database.reset().await?;
+            println!("Database reset");
+        }
+        DbCommand::Seed { file } => {
+            let fixtures = Fixtures::load(&file)?;
+            database.init().await?;
+
+            let pool = database.connect_using_options().await;
+            let passwords = Passwords::from_config(config.password_hashing());
+            let report = fixtures
+                .apply(&pool, &passwords, config.auth().email_normalization())
+                .await?;
+
+            println!(
+                "Seeded {} users, {} organizations and {} memberships",
+                report.users, report.organizations, report.memberships
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn keys(command: KeysCommand, overrides: &Overrides) -> Result<()> {
+    let config = App::config(overrides).await?;
+    config.database().init().await?;
+    let ctx = AppContext::from_config(&config).await?;
+
+    match command {
+        KeysCommand::Rotate => {
+            let jwk = ctx.tokens().rotate_key().await?;
+            println!(
+                "Rotated to signing key {}",
+                jwk.common.key_id.unwrap_or_default()
+            );
+        }
+    }
+
+    ctx.db().close().await;
+
+    Ok(())
+}
+
+async fn create_admin(
+    overrides: &Overrides,
+    email: &str,
+    name: Option<&str>,
+    password: Option<String>,
+) -> Result<()> {
+    let email = email.trim();
+
+    if !auth::is_valid_email(email) {
+        return Err(Error::Validation(String::from("email address is invalid")));
+    }
+
+    let config = App::config(overrides).await?;
+
+    if let Some(password) = &password {
+        auth::validate_password(config.password_policy(), password)?;
+    }
+
+    config.database().init().await?;
+    let ctx = AppContext::from_config(&config).await?;
+
+    let generated = password.is_none();
+    let password = password.unwrap_or_else(auth::generate_token);
+    let password_hash = ctx.passwords().hash(&password)?;
+
+    let user = ctx
+        .users()
+        .create(&NewUser {
+            name: name.map(str::trim),
+            password_hash: Some(&password_hash),
+            role: Role::Admin,
+            verified_at: Some(Utc::now()),
+            ..NewUser::new(email, config.auth().email_normalization())
+        })
+        .await?
+        .ok_or(Error::EmailTaken)?;
+
+    println!("Created admin {} ({})", user.email, user.id);
+    if generated {
+        println!("Password: {password}");
+        println!("It is not shown again, change it after logging in");
+    }
+
+    ctx.db().close().await;
+
+    Ok(())
+}
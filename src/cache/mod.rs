@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::Duration as StdDuration,
+};
+
+use chrono::Utc;
+use redis::{
+    AsyncCommands, Client, ErrorKind, RedisError, RedisResult, Script,
+    aio::{ConnectionManager, ConnectionManagerConfig},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Result, config::RedisConfig};
+
+/// How long to wait for Redis to accept a connection.
+const CONNECT_TIMEOUT: StdDuration = StdDuration::from_secs(2);
+
+/// How long to wait for an answer before falling back to Postgres.
+const RESPONSE_TIMEOUT: StdDuration = StdDuration::from_millis(500);
+
+/// Reconnection attempts before a command fails.
+const RETRIES: usize = 2;
+
+/// Longest pause between reconnection attempts, in milliseconds.
+const MAX_RETRY_DELAY: u64 = 200;
+
+/// How long Redis is skipped after a command failed, in milliseconds, so an
+/// outage does not slow every request down by the reconnection attempts.
+const COOL_DOWN: i64 = 5_000;
+
+/// Connection to the Redis server of the `redis` config section.
+///
+/// Every key is prefixed with `redis.key_prefix`. Callers treat failures as
+/// a cache miss and carry on with Postgres, so Redis going away only costs
+/// performance. After a failure, commands fail straight away for a few
+/// seconds before Redis is tried again.
+#[derive(Clone)]
+pub struct Cache {
+    conn: ConnectionManager,
+    prefix: String,
+    /// Unix time in milliseconds until which Redis is skipped.
+    down_until: Arc<AtomicI64>,
+}
+
+impl Cache {
+    /// Connects to Redis, reconnecting on its own later if the connection
+    /// drops. Returns `None`, after logging why, if Redis is unreachable at
+    /// startup.
+    pub async fn connect(config: &RedisConfig) -> Option<Self> {
+        let manager = ConnectionManagerConfig::new()
+            .set_connection_timeout(CONNECT_TIMEOUT)
+            .set_response_timeout(RESPONSE_TIMEOUT)
+            .set_number_of_retries(RETRIES)
+            .set_factor(2)
+            .set_max_delay(MAX_RETRY_DELAY);
+
+        let conn = match Client::open(config.url().expose()) {
+            Ok(client) => ConnectionManager::new_with_config(client, manager).await,
+            Err(err) => Err(err),
+        };
+
+        match conn {
+            Ok(conn) => {
+                tracing::info!("Connected to Redis");
+                Some(Self {
+                    conn,
+                    prefix: config.key_prefix().to_owned(),
+                    down_until: Arc::default(),
+                })
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "Redis is unavailable, falling back to Postgres");
+                None
+            }
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    /// Hands out the connection unless Redis recently failed.
+    fn conn(&self) -> RedisResult<ConnectionManager> {
+        if Utc::now().timestamp_millis() < self.down_until.load(Ordering::Relaxed) {
+            return Err(RedisError::from((
+                ErrorKind::IoError,
+                "redis recently failed, skipped",
+            )));
+        }
+
+        Ok(self.conn.clone())
+    }
+
+    /// Passes `result` through, skipping Redis for a while if it failed
+    /// because of the connection.
+    fn watch<T>(&self, result: RedisResult<T>) -> Result<T> {
+        if let Err(err) = &result
+            && (err.is_io_error() || err.is_timeout() || err.is_connection_dropped())
+        {
+            self.down_until
+                .store(Utc::now().timestamp_millis() + COOL_DOWN, Ordering::Relaxed);
+        }
+
+        Ok(result?)
+    }
+
+    /// Checks that Redis answers.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn ping(&self) -> Result<()> {
+        let () = self.watch(redis::cmd("PING").query_async(&mut self.conn()?).await)?;
+
+        Ok(())
+    }
+
+    /// Reads the JSON value at `key`; values that no longer parse count as
+    /// missing.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let value: Option<String> = self.watch(self.conn()?.get(self.key(key)).await)?;
+
+        Ok(value.and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Stores `value` as JSON at `key` for `ttl` seconds.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn set_json<T: Serialize>(&self, key: &str, value: &T, ttl: u64) -> Result<()> {
+        let value = serde_json::to_string(value).unwrap_or_default();
+        let () = self.watch(self.conn()?.set_ex(self.key(key), value, ttl.max(1)).await)?;
+
+        Ok(())
+    }
+
+    /// Marks `key` as present for `ttl` seconds.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn flag(&self, key: &str, ttl: u64) -> Result<()> {
+        let () = self.watch(self.conn()?.set_ex(self.key(key), 1, ttl.max(1)).await)?;
+
+        Ok(())
+    }
+
+    /// Whether `key` is present.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        self.watch(self.conn()?.exists(self.key(key)).await)
+    }
+
+    /// Deletes `keys`, ignoring those that are missing.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn delete(&self, keys: &[String]) -> Result<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let keys: Vec<String> = keys.iter().map(|key| self.key(key)).collect();
+        let () = self.watch(self.conn()?.del(keys).await)?;
+
+        Ok(())
+    }
+
+    /// Reads every field of the hash at `key`, empty if it is missing.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn hash(&self, key: &str) -> Result<HashMap<String, String>> {
+        self.watch(self.conn()?.hgetall(self.key(key)).await)
+    }
+
+    /// Lists the members of the set at `key`, empty if it is missing.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn members(&self, key: &str) -> Result<Vec<String>> {
+        self.watch(self.conn()?.smembers(self.key(key)).await)
+    }
+
+    /// Removes `members` from the set at `key`.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the command failed
+    pub async fn remove_members(&self, key: &str, members: &[String]) -> Result<()> {
+        if members.is_empty() {
+            return Ok(());
+        }
+
+        let () = self.watch(self.conn()?.srem(self.key(key), members).await)?;
+
+        Ok(())
+    }
+
+    /// Runs `script` with `keys` and string `args`.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the script failed
+    pub async fn run<T: redis::FromRedisValue>(
+        &self,
+        script: &Script,
+        keys: &[String],
+        args: &[String],
+    ) -> Result<T> {
+        let mut invocation = script.prepare_invoke();
+        for key in keys {
+            invocation.key(self.key(key));
+        }
+        for arg in args {
+            invocation.arg(arg);
+        }
+
+        let mut conn = self.conn()?;
+        self.watch(invocation.invoke_async(&mut conn).await)
+    }
+
+    /// Runs `script` with `key` as its only key.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Redis`] if the script failed
+    pub async fn eval<T: redis::FromRedisValue>(
+        &self,
+        script: &Script,
+        key: &str,
+        args: &[f64],
+    ) -> Result<T> {
+        let mut invocation = script.key(self.key(key));
+        for arg in args {
+            invocation.arg(*arg);
+        }
+
+        let mut conn = self.conn()?;
+        self.watch(invocation.invoke_async(&mut conn).await)
+    }
+}
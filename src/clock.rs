@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Source of the current time that expirations are computed against.
+///
+/// Sessions, access and refresh tokens, revocations and text message codes
+/// read the time from the clock of the [`crate::AppContext`] rather than the
+/// system, so tests can swap in a [`MockClock`] and move time forward
+/// instead of sleeping.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock, used unless another one is given.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that stands still until it is moved, for tests.
+///
+/// Clones share the same time, so a test can keep one and advance it while
+/// the context holds another.
+///
+/// ```
+/// use betterauth::clock::{Clock, MockClock};
+/// use chrono::{Duration, Utc};
+///
+/// let start = Utc::now();
+/// let clock = MockClock::new(start);
+/// clock.advance(Duration::hours(1));
+///
+/// assert_eq!(clock.now(), start + Duration::hours(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// A clock showing `now`.
+    #[must_use]
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Moves the clock forward by `by`, or back if negative.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += by;
+    }
+
+    /// Sets the clock to `now`.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+}
+
+impl Default for MockClock {
+    /// A clock starting at the current system time.
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// Where an audit sink sends the events.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSinkKind {
+    /// RFC 5424 messages to the syslog server at `address`.
+    Syslog,
+    /// Records produced to `topic` through the Kafka REST Proxy at `url`.
+    Kafka,
+    /// NDJSON appended to a file per day in `dir`.
+    File,
+    /// An NDJSON object per batch in `bucket`, S3 or compatible.
+    S3,
+    /// NDJSON batches `POST`ed to the collector at `url`.
+    Http,
+}
+
+impl AuditSinkKind {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Syslog => "syslog",
+            Self::Kafka => "kafka",
+            Self::File => "file",
+            Self::S3 => "s3",
+            Self::Http => "http",
+        }
+    }
+}
+
+/// Transport of a syslog sink.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+    /// A datagram per event.
+    #[default]
+    Udp,
+    /// A connection per batch, events framed by octet counting (RFC 6587).
+    Tcp,
+}
+
+/// Export of the audit log to external systems, one entry per named sink.
+///
+/// Every `flush_interval` the events recorded since a sink's last export are
+/// sent to it in batches of up to `batch_size`, oldest first, as JSON
+/// objects shaped like the items of `GET /admin/audit-events`. Where a sink
+/// got to is kept in the `audit_export_cursors` table, so nothing is lost or
+/// sent twice across restarts, and a new sink starts with the oldest event
+/// still kept. A batch that fails is retried with exponential backoff until
+/// the sink takes it, later events waiting behind it. Only one instance
+/// exports to a sink at a time.
+///
+/// A sink gets the actions it lists, or every action when `actions` is
+/// empty; actions are named as in [`crate::audit::AuditAction::name`], e.g.
+/// `password_changed`.
+///
+/// `kafka` sinks need a Kafka REST Proxy in front of the brokers. `s3` sinks
+/// write to AWS S3 unless `endpoint` names a compatible service, whose
+/// objects are then addressed by path. `http` and `kafka` sinks send
+/// `token`, if set, as a bearer token.
+///
+/// ```yaml
+/// audit:
+///   batch_size: 100
+///   flush_interval: 5 # seconds
+///   timeout: 10 # seconds
+///   sinks:
+///     siem:
+///       type: "syslog" # syslog, kafka, file, s3 or http
+///       address: "logs.example.com:514"
+///       protocol: "udp" # udp or tcp
+///     stream:
+///       type: "kafka"
+///       url: "https://kafka-rest.example.com"
+///       topic: "auth-audit"
+///     archive:
+///       type: "s3"
+///       bucket: "example-audit"
+///       region: "eu-west-1"
+///       prefix: "betterauth/"
+///       access_key_id: "AKIA..."
+///       secret_access_key: "..."
+///     local:
+///       type: "file"
+///       dir: "/var/log/betterauth/audit"
+///     collector:
+///       type: "http"
+///       url: "https://collector.example.com/v1/audit"
+///       token: "..."
+///       actions: ["suspicious_login", "user_deleted"]
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditConfig {
+    #[serde(default)]
+    sinks: HashMap<String, AuditSinkConfig>,
+    #[serde(default = "default_batch_size")]
+    batch_size: i64,
+    #[serde(default = "default_flush_interval")]
+    flush_interval: u64,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sinks: HashMap::new(),
+            batch_size: default_batch_size(),
+            flush_interval: default_flush_interval(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+fn default_batch_size() -> i64 {
+    100
+}
+
+fn default_flush_interval() -> u64 {
+    5
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl AuditConfig {
+    /// Sinks by name.
+    #[must_use]
+    pub fn sinks(&self) -> &HashMap<String, AuditSinkConfig> {
+        &self.sinks
+    }
+
+    /// Most events sent to a sink at once. Defaults to 100.
+    #[must_use]
+    pub fn batch_size(&self) -> i64 {
+        self.batch_size
+    }
+
+    /// Time between two exports, in seconds. Defaults to 5.
+    #[must_use]
+    pub fn flush_interval(&self) -> u64 {
+        self.flush_interval
+    }
+
+    /// How long to wait on a sink, in seconds. Defaults to 10.
+    #[must_use]
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+}
+
+/// A single audit sink; which fields it needs depends on its `type`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditSinkConfig {
+    #[serde(rename = "type")]
+    kind: AuditSinkKind,
+    #[serde(default)]
+    actions: Vec<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    token: Option<SecretString>,
+    #[serde(default)]
+    address: Option<String>,
+    #[serde(default)]
+    protocol: SyslogProtocol,
+    #[serde(default = "default_app_name")]
+    app_name: String,
+    #[serde(default)]
+    topic: Option<String>,
+    #[serde(default)]
+    dir: Option<PathBuf>,
+    #[serde(default)]
+    bucket: Option<String>,
+    #[serde(default)]
+    region: Option<String>,
+    #[serde(default)]
+    endpoint: Option<String>,
+    #[serde(default)]
+    prefix: String,
+    #[serde(default)]
+    access_key_id: Option<String>,
+    #[serde(default)]
+    secret_access_key: Option<SecretString>,
+}
+
+fn default_app_name() -> String {
+    String::from("betterauth")
+}
+
+impl AuditSinkConfig {
+    #[must_use]
+    pub fn kind(&self) -> AuditSinkKind {
+        self.kind
+    }
+
+    /// Whether the sink gets `action`, e.g. `password_changed`.
+    #[must_use]
+    pub fn wants(&self, action: &str) -> bool {
+        self.actions.is_empty() || self.actions.iter().any(|wanted| wanted == action)
+    }
+
+    /// Actions the sink gets, every one when empty.
+    #[must_use]
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+
+    /// Collector of `http` sinks, REST Proxy of `kafka` sinks.
+    #[must_use]
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Bearer token of `http` and `kafka` sinks.
+    #[must_use]
+    pub fn token(&self) -> Option<&SecretString> {
+        self.token.as_ref()
+    }
+
+    /// `host:port` of the server of `syslog` sinks.
+    #[must_use]
+    pub fn address(&self) -> Option<&str> {
+        self.address.as_deref()
+    }
+
+    /// Transport of `syslog` sinks. Defaults to UDP.
+    #[must_use]
+    pub fn protocol(&self) -> SyslogProtocol {
+        self.protocol
+    }
+
+    /// `APP-NAME` of the messages of `syslog` sinks. Defaults to
+    /// `betterauth`.
+    #[must_use]
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    /// Topic of `kafka` sinks.
+    #[must_use]
+    pub fn topic(&self) -> Option<&str> {
+        self.topic.as_deref()
+    }
+
+    /// Directory of `file` sinks.
+    #[must_use]
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Bucket of `s3` sinks.
+    #[must_use]
+    pub fn bucket(&self) -> Option<&str> {
+        self.bucket.as_deref()
+    }
+
+    /// Region of `s3` sinks, e.g. `eu-west-1`.
+    #[must_use]
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// Base URL of an S3-compatible service, e.g. `http://127.0.0.1:9000`.
+    #[must_use]
+    pub fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref()
+    }
+
+    /// Prepended to the keys of the objects of `s3` sinks. Defaults to none.
+    #[must_use]
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Access key of `s3` sinks.
+    #[must_use]
+    pub fn access_key_id(&self) -> Option<&str> {
+        self.access_key_id.as_deref()
+    }
+
+    /// Secret key of `s3` sinks.
+    #[must_use]
+    pub fn secret_access_key(&self) -> Option<&SecretString> {
+        self.secret_access_key.as_ref()
+    }
+}
@@ -0,0 +1,528 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::config::SecretString;
+
+/// Algorithm access and refresh tokens are signed with.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenAlgorithm {
+    /// HMAC-SHA256 with `auth.secret`.
+    #[default]
+    HS256,
+    /// RSA PKCS#1 v1.5 with SHA-256.
+    RS256,
+    /// ECDSA on P-256 with SHA-256.
+    ES256,
+    /// Ed25519.
+    EdDSA,
+}
+
+/// Where server-side sessions are kept.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionBackend {
+    /// The `database.driver` database, next to the users.
+    #[default]
+    Database,
+    /// The memory of the process. Sessions end on restart and are not
+    /// shared between instances, so this is for development and tests only.
+    Memory,
+    /// The `redis` server, taking session reads and writes off the database.
+    /// Sessions end if Redis loses its data.
+    Redis,
+}
+
+/// How email addresses are folded into the form accounts are told apart by.
+///
+/// Addresses are always trimmed and lowercased. `fold_dots` also drops the
+/// dots of the local part and `strip_aliases` a `+tag` suffix, as providers
+/// such as Gmail deliver those variants to the same mailbox. Both apply to
+/// addresses at `domains` only, or at every domain if none are listed.
+///
+/// Changing these settings does not renormalize accounts that already exist.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct EmailNormalization {
+    #[serde(default)]
+    fold_dots: bool,
+    #[serde(default)]
+    strip_aliases: bool,
+    #[serde(default)]
+    domains: Vec<String>,
+}
+
+impl EmailNormalization {
+    /// Whether dots in the local part are ignored. Defaults to `false`.
+    #[must_use]
+    pub fn fold_dots(&self) -> bool {
+        self.fold_dots
+    }
+
+    /// Whether a `+tag` suffix of the local part is ignored. Defaults to
+    /// `false`.
+    #[must_use]
+    pub fn strip_aliases(&self) -> bool {
+        self.strip_aliases
+    }
+
+    /// Domains dots and aliases are folded at, all of them when empty.
+    #[must_use]
+    pub fn domains(&self) -> &[String] {
+        &self.domains
+    }
+}
+
+/// Optional usernames, unique handles users can log in with in place of
+/// their email address.
+///
+/// Disabled by default, leaving accounts email-only. Usernames are compared
+/// case-insensitively, hold letters, digits, `_`, `.` and `-`, start with a
+/// letter or digit and cannot be one of `reserved`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct UsernameConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_username_min_length")]
+    min_length: usize,
+    #[serde(default = "default_username_max_length")]
+    max_length: usize,
+    #[serde(default = "default_reserved_usernames")]
+    reserved: Vec<String>,
+}
+
+impl Default for UsernameConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_length: default_username_min_length(),
+            max_length: default_username_max_length(),
+            reserved: default_reserved_usernames(),
+        }
+    }
+}
+
+fn default_username_min_length() -> usize {
+    3
+}
+
+fn default_username_max_length() -> usize {
+    32
+}
+
+fn default_reserved_usernames() -> Vec<String> {
+    [
+        "admin",
+        "administrator",
+        "api",
+        "auth",
+        "help",
+        "me",
+        "root",
+        "security",
+        "support",
+        "system",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+impl UsernameConfig {
+    /// Whether users can have a username. Defaults to `false`.
+    #[must_use]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Fewest characters in a username. Defaults to 3.
+    #[must_use]
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    /// Most characters in a username. Defaults to 32.
+    #[must_use]
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Names nobody can take, such as `admin` and `support`.
+    #[must_use]
+    pub fn reserved(&self) -> &[String] {
+        &self.reserved
+    }
+}
+
+/// How long tokens and sessions stay valid.
+///
+/// Each lifetime is a duration such as `"90s"`, `"15m"`, `"12h"` or
+/// `"30d"`, or a number of seconds. Sessions end `session` after they were
+/// created, or once unused for `session_idle` if that is set.
+///
+/// ```yaml
+/// auth:
+///   lifetimes:
+///     access_token: "15m"
+///     refresh_token: "30d"
+///     session: "7d"
+///     session_idle: "12h" # 0 to only end sessions after `session`
+///     password_reset: "1h"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct Lifetimes {
+    #[serde(default = "default_access_token", deserialize_with = "duration")]
+    access_token: Duration,
+    #[serde(default = "default_refresh_token", deserialize_with = "duration")]
+    refresh_token: Duration,
+    #[serde(default = "default_session", deserialize_with = "duration")]
+    session: Duration,
+    #[serde(default, deserialize_with = "duration")]
+    session_idle: Duration,
+    #[serde(default = "default_password_reset", deserialize_with = "duration")]
+    password_reset: Duration,
+}
+
+impl Default for Lifetimes {
+    fn default() -> Self {
+        Self {
+            access_token: default_access_token(),
+            refresh_token: default_refresh_token(),
+            session: default_session(),
+            session_idle: Duration::ZERO,
+            password_reset: default_password_reset(),
+        }
+    }
+}
+
+fn default_access_token() -> Duration {
+    Duration::from_secs(15 * 60)
+}
+
+fn default_refresh_token() -> Duration {
+    Duration::from_secs(30 * 24 * 60 * 60)
+}
+
+fn default_session() -> Duration {
+    Duration::from_secs(7 * 24 * 60 * 60)
+}
+
+fn default_password_reset() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+/// Reads a duration written like `"15m"` or `"1h 30m"`, or as seconds.
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Seconds(u64),
+        Text(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Seconds(seconds) => Ok(Duration::from_secs(seconds)),
+        Raw::Text(text) => {
+            let text = text.trim();
+            match text.parse::<u64>() {
+                Ok(seconds) => Ok(Duration::from_secs(seconds)),
+                Err(_) => humantime::parse_duration(text).map_err(|err| {
+                    serde::de::Error::custom(format!(
+                        "invalid duration {text:?}, expected e.g. \"15m\" or \"30d\": {err}"
+                    ))
+                }),
+            }
+        }
+    }
+}
+
+impl Lifetimes {
+    /// Defaults to 15 minutes.
+    #[must_use]
+    pub fn access_token(&self) -> Duration {
+        self.access_token
+    }
+
+    /// Defaults to 30 days.
+    #[must_use]
+    pub fn refresh_token(&self) -> Duration {
+        self.refresh_token
+    }
+
+    /// Longest a server-side session lasts, however busy. Defaults to 7
+    /// days.
+    #[must_use]
+    pub fn session(&self) -> Duration {
+        self.session
+    }
+
+    /// How long a session may go unused before it ends, or zero if it does
+    /// not. Defaults to zero.
+    #[must_use]
+    pub fn session_idle(&self) -> Duration {
+        self.session_idle
+    }
+
+    /// Defaults to 1 hour.
+    #[must_use]
+    pub fn password_reset(&self) -> Duration {
+        self.password_reset
+    }
+}
+
+/// Authentication configuration.
+///
+/// Holds the key material used to sign access and refresh tokens and, in
+/// `lifetimes`, how long tokens and server-side sessions stay valid, see
+/// [`Lifetimes`].
+///
+/// Tokens are signed with `secret` (HS256) unless `algorithm` picks an
+/// asymmetric one, which needs a PEM-encoded private key, either inline in
+/// `signing_key` or read from `signing_key_file`. The public key is then
+/// published at `GET /.well-known/jwks.json` so other services can verify
+/// tokens without knowing any secret.
+///
+/// Asymmetric keys can be rotated with `betterauth keys rotate`,
+/// `POST /admin/signing-keys/rotate` or, every `signing_key_rotation`
+/// seconds, automatically. Rotated keys are generated and stored in the
+/// database, sealed with `secret`; the newest one signs, while older ones,
+/// `signing_key` included, keep verifying and stay published for the
+/// refresh token lifetime after they were replaced. With `signing_key_rotation`
+/// set, `signing_key` may be left out and a key is generated on first start.
+/// Changing `secret` makes the stored keys unreadable.
+///
+/// Sessions are kept in the database unless `session_backend` moves them to
+/// Redis, or for development and tests, to memory, see [`SessionBackend`].
+///
+/// ```yaml
+/// auth:
+///   secret: "change-me-to-a-long-random-string"
+///   algorithm: "ES256" # HS256, RS256, ES256 or EdDSA
+///   signing_key_file: "/run/secrets/jwt.pem"
+///   signing_key_rotation: 7776000 # seconds, 0 to rotate by hand only
+///   lifetimes:
+///     access_token: "15m"
+///     refresh_token: "30d"
+///     session: "7d"
+///   session_backend: "database" # database, memory or redis
+///   # Page where users pick a new password, receives `?token=...`
+///   password_reset_url: "https://app.example.com/reset-password"
+///   email_verification_ttl: 86400 # seconds
+///   magic_link_ttl: 900 # seconds
+///   # Reject logins until the email address has been verified
+///   require_email_verification: false
+///   # Tell the old address when an email change is confirmed
+///   notify_email_change: true
+///   # Allow guest sessions that registration later upgrades
+///   anonymous_sessions: false
+///   invite_ttl: 604800 # seconds
+///   # Page where invited users pick a password, receives `?token=...`
+///   invite_url: "https://app.example.com/accept-invite"
+///   # Only let invited users create accounts
+///   invite_only: false
+///   # How addresses are compared when checking for duplicate accounts
+///   email_normalization:
+///     fold_dots: true
+///     strip_aliases: true
+///     domains: ["gmail.com", "googlemail.com"]
+///   # Let users pick a unique handle to log in with instead of the email
+///   usernames:
+///     enabled: true
+///     min_length: 3
+///     max_length: 32
+///     reserved: ["admin", "root", "support"]
+///   # Largest user_metadata or app_metadata object, in bytes of JSON
+///   max_metadata_size: 16384
+///   # How recently users must have logged in for sensitive operations
+///   reauthentication_window: 300 # seconds
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthConfig {
+    secret: SecretString,
+    #[serde(default)]
+    algorithm: TokenAlgorithm,
+    #[serde(default)]
+    signing_key: Option<SecretString>,
+    #[serde(default)]
+    signing_key_rotation: u64,
+    #[serde(default)]
+    lifetimes: Lifetimes,
+    #[serde(default)]
+    session_backend: SessionBackend,
+    #[serde(default)]
+    password_reset_url: Option<String>,
+    #[serde(default = "default_email_verification_ttl")]
+    email_verification_ttl: u64,
+    #[serde(default)]
+    require_email_verification: bool,
+    #[serde(default = "default_magic_link_ttl")]
+    magic_link_ttl: u64,
+    #[serde(default = "default_notify_email_change")]
+    notify_email_change: bool,
+    #[serde(default)]
+    anonymous_sessions: bool,
+    #[serde(default = "default_invite_ttl")]
+    invite_ttl: u64,
+    #[serde(default)]
+    invite_url: Option<String>,
+    #[serde(default)]
+    invite_only: bool,
+    #[serde(default)]
+    email_normalization: EmailNormalization,
+    #[serde(default)]
+    usernames: UsernameConfig,
+    #[serde(default = "default_max_metadata_size")]
+    max_metadata_size: usize,
+    #[serde(default = "default_reauthentication_window")]
+    reauthentication_window: u64,
+}
+
+fn default_email_verification_ttl() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_magic_link_ttl() -> u64 {
+    15 * 60
+}
+
+fn default_notify_email_change() -> bool {
+    true
+}
+
+fn default_invite_ttl() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_max_metadata_size() -> usize {
+    16 * 1024
+}
+
+fn default_reauthentication_window() -> u64 {
+    5 * 60
+}
+
+impl AuthConfig {
+    /// Key used to sign and verify tokens.
+    #[must_use]
+    pub fn secret(&self) -> &SecretString {
+        &self.secret
+    }
+
+    /// Algorithm tokens are signed with. Defaults to HS256.
+    #[must_use]
+    pub fn algorithm(&self) -> TokenAlgorithm {
+        self.algorithm
+    }
+
+    /// PEM-encoded private key for asymmetric algorithms, given inline or
+    /// read from `signing_key_file`.
+    #[must_use]
+    pub fn signing_key(&self) -> Option<&SecretString> {
+        self.signing_key.as_ref()
+    }
+
+    /// How old the newest signing key may get before a new one is
+    /// generated, in seconds. Defaults to 0, rotating only when asked to.
+    #[must_use]
+    pub fn signing_key_rotation(&self) -> u64 {
+        self.signing_key_rotation
+    }
+
+    /// How long tokens and sessions stay valid.
+    #[must_use]
+    pub fn lifetimes(&self) -> &Lifetimes {
+        &self.lifetimes
+    }
+
+    /// Where sessions are kept. Defaults to the database.
+    #[must_use]
+    pub fn session_backend(&self) -> SessionBackend {
+        self.session_backend
+    }
+
+    /// Page linked from password reset emails, if it is served elsewhere
+    /// than this server.
+    #[must_use]
+    pub fn password_reset_url(&self) -> Option<&str> {
+        self.password_reset_url.as_deref()
+    }
+
+    /// Lifetime of an email verification or email change token, in seconds.
+    /// Defaults to 24 hours.
+    #[must_use]
+    pub fn email_verification_ttl(&self) -> u64 {
+        self.email_verification_ttl
+    }
+
+    /// Whether users must verify their email address before they can log in.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn require_email_verification(&self) -> bool {
+        self.require_email_verification
+    }
+
+    /// Lifetime of a magic login link, in seconds. Defaults to 15 minutes.
+    #[must_use]
+    pub fn magic_link_ttl(&self) -> u64 {
+        self.magic_link_ttl
+    }
+
+    /// Whether the previous address is notified once an email change is
+    /// confirmed. Defaults to `true`.
+    #[must_use]
+    pub fn notify_email_change(&self) -> bool {
+        self.notify_email_change
+    }
+
+    /// Whether clients may start anonymous guest sessions. Defaults to
+    /// `false`.
+    #[must_use]
+    pub fn anonymous_sessions(&self) -> bool {
+        self.anonymous_sessions
+    }
+
+    /// Lifetime of a signup invitation, in seconds. Defaults to 7 days.
+    #[must_use]
+    pub fn invite_ttl(&self) -> u64 {
+        self.invite_ttl
+    }
+
+    /// Page linked from invitation emails. Without one, the email carries
+    /// the invitation code to paste into the client.
+    #[must_use]
+    pub fn invite_url(&self) -> Option<&str> {
+        self.invite_url.as_deref()
+    }
+
+    /// Whether `POST /auth/register` is closed, so accounts can only be
+    /// created from an invitation. Defaults to `false`.
+    #[must_use]
+    pub fn invite_only(&self) -> bool {
+        self.invite_only
+    }
+
+    /// How email addresses are normalized before they are compared.
+    #[must_use]
+    pub fn email_normalization(&self) -> &EmailNormalization {
+        &self.email_normalization
+    }
+
+    /// Whether and which usernames users can pick.
+    #[must_use]
+    pub fn usernames(&self) -> &UsernameConfig {
+        &self.usernames
+    }
+
+    /// Largest `user_metadata` or `app_metadata` object a user can have, in
+    /// bytes of JSON. Defaults to 16 KiB.
+    #[must_use]
+    pub fn max_metadata_size(&self) -> usize {
+        self.max_metadata_size
+    }
+
+    /// How long after logging in or `POST /auth/reauthenticate` users may
+    /// change their password, create API keys or delete their account, in
+    /// seconds. Defaults to 5 minutes.
+    #[must_use]
+    pub fn reauthentication_window(&self) -> u64 {
+        self.reauthentication_window
+    }
+}
@@ -0,0 +1,64 @@
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// CAPTCHA service whose `siteverify` API checks the tokens.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    Hcaptcha,
+    Turnstile,
+    Recaptcha,
+}
+
+impl CaptchaProvider {
+    /// Endpoint tokens are verified against.
+    #[must_use]
+    pub fn verify_url(self) -> &'static str {
+        match self {
+            Self::Hcaptcha => "https://api.hcaptcha.com/siteverify",
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            Self::Recaptcha => "https://www.google.com/recaptcha/api/siteverify",
+        }
+    }
+}
+
+/// CAPTCHA verification for registration, login and password reset.
+///
+/// With this section present those endpoints require an `X-Captcha-Token`
+/// header holding the token the widget produced, and reject the request
+/// unless the provider accepts it. `min_score` only applies to reCAPTCHA v3,
+/// which scores tokens instead of passing or failing them.
+///
+/// ```yaml
+/// captcha:
+///   provider: "turnstile" # hcaptcha, turnstile or recaptcha
+///   secret: "0x4AAAAAAA..."
+///   min_score: 0.5
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct CaptchaConfig {
+    provider: CaptchaProvider,
+    secret: SecretString,
+    #[serde(default)]
+    min_score: Option<f64>,
+}
+
+impl CaptchaConfig {
+    #[must_use]
+    pub fn provider(&self) -> CaptchaProvider {
+        self.provider
+    }
+
+    /// Secret key shared with the provider.
+    #[must_use]
+    pub fn secret(&self) -> &SecretString {
+        &self.secret
+    }
+
+    /// Lowest acceptable reCAPTCHA v3 score, if scores are checked.
+    #[must_use]
+    pub fn min_score(&self) -> Option<f64> {
+        self.min_score
+    }
+}
@@ -0,0 +1,168 @@
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// `SameSite` attribute of the session cookie.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSitePolicy {
+    Strict,
+    #[default]
+    Lax,
+    /// Sent on cross-site requests too; requires a `Secure` cookie.
+    None,
+}
+
+/// Attributes of the cookies set by the auth routes, and protection of the
+/// session cookie.
+///
+/// Cookie names start with `prefix`: the session cookie is `{prefix}session`
+/// and the cookie remembering a pending OAuth login `{prefix}oauth`. A
+/// `__Secure-` prefix needs `secure`, and `__Host-` also needs `path: "/"`
+/// and no `domain`, as browsers drop such cookies otherwise. The OAuth
+/// cookie keeps `SameSite=Lax` so it survives the redirect back from the
+/// provider, and is scoped to the OAuth routes unless the prefix is
+/// `__Host-`.
+///
+/// With `keys` set, the session token in the cookie is sealed: encrypted and
+/// authenticated with XChaCha20-Poly1305, or only signed with HMAC-SHA256
+/// when `encrypt` is off. The first key seals new cookies and every key is
+/// accepted when reading them, so keys are rotated by prepending a new one
+/// and dropping the old one once its cookies have been resealed, which
+/// happens on their next request. Without keys the cookie holds the plain
+/// token.
+///
+/// `secure` defaults to whether the server URL is https. In production,
+/// cookies must be `Secure` and `HttpOnly`.
+///
+/// Read from `cookie` too, the former name of the section.
+///
+/// ```yaml
+/// cookies:
+///   prefix: "__Secure-betterauth_"
+///   domain: "example.com"
+///   path: "/"
+///   same_site: "lax" # strict, lax or none
+///   secure: true
+///   http_only: true
+///   encrypt: true
+///   keys:
+///     - "new-long-random-string"
+///     - "previous-long-random-string"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct CookieConfig {
+    #[serde(default = "default_prefix")]
+    prefix: String,
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    same_site: SameSitePolicy,
+    #[serde(default)]
+    secure: Option<bool>,
+    #[serde(default = "default_http_only")]
+    http_only: bool,
+    #[serde(default = "default_encrypt")]
+    encrypt: bool,
+    #[serde(default)]
+    keys: Vec<SecretString>,
+}
+
+impl Default for CookieConfig {
+    fn default() -> Self {
+        Self {
+            prefix: default_prefix(),
+            domain: None,
+            path: default_path(),
+            same_site: SameSitePolicy::default(),
+            secure: None,
+            http_only: default_http_only(),
+            encrypt: default_encrypt(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+fn default_prefix() -> String {
+    String::from("betterauth_")
+}
+
+fn default_path() -> String {
+    String::from("/")
+}
+
+fn default_http_only() -> bool {
+    true
+}
+
+fn default_encrypt() -> bool {
+    true
+}
+
+impl CookieConfig {
+    /// Start of every cookie name. Defaults to `betterauth_`.
+    #[must_use]
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Full name of the cookie called `name` after the prefix.
+    #[must_use]
+    pub fn name(&self, name: &str) -> String {
+        format!("{}{name}", self.prefix)
+    }
+
+    /// Domain the cookies are scoped to; the request host only if unset. A
+    /// parent domain shares the login with the applications on its
+    /// subdomains, see [`super::SsoConfig`].
+    #[must_use]
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Path the session cookie is scoped to. Defaults to `/`.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn same_site(&self) -> SameSitePolicy {
+        self.same_site
+    }
+
+    /// Whether cookies are marked `Secure`, if set explicitly.
+    #[must_use]
+    pub fn secure(&self) -> Option<bool> {
+        self.secure
+    }
+
+    /// Whether cookies are marked `Secure`, given whether the server URL is
+    /// https.
+    #[must_use]
+    pub fn is_secure(&self, https: bool) -> bool {
+        self.secure.unwrap_or(https)
+    }
+
+    /// Whether cookies are hidden from scripts with `HttpOnly`. Defaults to
+    /// `true`.
+    #[must_use]
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Whether sealed cookies are encrypted rather than only signed.
+    /// Defaults to `true`.
+    #[must_use]
+    pub fn encrypt(&self) -> bool {
+        self.encrypt
+    }
+
+    /// Sealing keys, current first.
+    #[must_use]
+    pub fn keys(&self) -> &[SecretString] {
+        &self.keys
+    }
+}
@@ -0,0 +1,171 @@
+use std::{sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::Deserialize;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::{auth::PASSWORD_WARNING_HEADER, trace::REQUEST_ID_HEADER};
+
+/// Cross-origin resource sharing policy.
+///
+/// By default no origin is allowed, so browsers only let pages served from
+/// this server's own origin call it. List the origins of browser apps that
+/// call the API from elsewhere; `"*"` allows any origin but cannot be combined
+/// with `allow_credentials`, which browser apps relying on the session cookie
+/// need. The `x-request-id` and `x-password-warning` response headers are
+/// always exposed.
+///
+/// ```yaml
+/// cors:
+///   allowed_origins: ["https://app.example.com"]
+///   allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"]
+///   allowed_headers: ["content-type", "authorization", "x-csrf-token"]
+///   allow_credentials: true
+///   max_age: 600 # seconds
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct CorsConfig {
+    #[serde(default)]
+    allowed_origins: Vec<String>,
+    #[serde(default = "default_allowed_methods")]
+    allowed_methods: Vec<String>,
+    #[serde(default = "default_allowed_headers")]
+    allowed_headers: Vec<String>,
+    #[serde(default)]
+    allow_credentials: bool,
+    #[serde(default = "default_max_age")]
+    max_age: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: default_allowed_methods(),
+            allowed_headers: default_allowed_headers(),
+            allow_credentials: false,
+            max_age: default_max_age(),
+        }
+    }
+}
+
+fn default_allowed_methods() -> Vec<String> {
+    ["GET", "POST", "PUT", "PATCH", "DELETE"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_allowed_headers() -> Vec<String> {
+    [
+        "content-type",
+        "authorization",
+        "x-api-key",
+        "x-csrf-token",
+        "x-captcha-token",
+        "x-request-id",
+        "x-device-id",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_max_age() -> u64 {
+    10 * 60
+}
+
+impl CorsConfig {
+    /// Origins allowed to call the API. Defaults to none.
+    #[must_use]
+    pub fn allowed_origins(&self) -> &[String] {
+        &self.allowed_origins
+    }
+
+    /// Methods allowed in cross-origin requests.
+    #[must_use]
+    pub fn allowed_methods(&self) -> &[String] {
+        &self.allowed_methods
+    }
+
+    /// Request headers allowed in cross-origin requests.
+    #[must_use]
+    pub fn allowed_headers(&self) -> &[String] {
+        &self.allowed_headers
+    }
+
+    /// Whether cross-origin requests may carry cookies. Defaults to `false`.
+    #[must_use]
+    pub fn allow_credentials(&self) -> bool {
+        self.allow_credentials
+    }
+
+    /// How long browsers may cache a preflight response, in seconds.
+    /// Defaults to 10 minutes.
+    #[must_use]
+    pub fn max_age(&self) -> u64 {
+        self.max_age
+    }
+
+    /// Whether requests from `origin` are allowed, by being listed or by a
+    /// `"*"` entry.
+    #[must_use]
+    pub fn allows(&self, origin: &HeaderValue) -> bool {
+        self.allowed_origins.iter().any(|allowed| {
+            allowed == "*" || allowed.trim_end_matches('/').as_bytes() == origin.as_bytes()
+        })
+    }
+
+    /// Builds the layer enforcing this policy, allowing the origins
+    /// currently in `origins` so they can change without rebuilding the
+    /// router.
+    ///
+    /// Methods and headers that do not parse, which
+    /// [`crate::config::Config::validate`] reports, are left out.
+    pub fn layer(&self, origins: &CorsOrigins) -> CorsLayer {
+        let origins = origins.clone();
+        let origins = AllowOrigin::predicate(move |origin, _| origins.0.load().allows(origin));
+
+        let methods = self
+            .allowed_methods
+            .iter()
+            .filter_map(|method| parse_method(method));
+        let headers = self
+            .allowed_headers
+            .iter()
+            .filter_map(|header| parse_header(header));
+
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods.collect::<Vec<_>>())
+            .allow_headers(headers.collect::<Vec<_>>())
+            .allow_credentials(self.allow_credentials)
+            .expose_headers([REQUEST_ID_HEADER, PASSWORD_WARNING_HEADER])
+            .max_age(Duration::from_secs(self.max_age))
+    }
+}
+
+/// `method` as a [`Method`], in any case, e.g. `get`.
+pub(super) fn parse_method(method: &str) -> Option<Method> {
+    Method::from_bytes(method.to_uppercase().as_bytes()).ok()
+}
+
+pub(super) fn parse_header(header: &str) -> Option<HeaderName> {
+    HeaderName::from_bytes(header.as_bytes()).ok()
+}
+
+/// Origins allowed by the layers of [`CorsConfig::layer`], swapped when the
+/// configuration is reloaded.
+#[derive(Clone)]
+pub struct CorsOrigins(Arc<ArcSwap<CorsConfig>>);
+
+impl CorsOrigins {
+    #[must_use]
+    pub fn new(config: &CorsConfig) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(config.clone())))
+    }
+
+    /// Allows the origins of `config` from now on.
+    pub fn update(&self, config: &CorsConfig) {
+        self.0.store(Arc::new(config.clone()));
+    }
+}
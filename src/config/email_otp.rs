@@ -0,0 +1,81 @@
+use serde::Deserialize;
+
+/// One-time code login by email, for clients where following a magic link
+/// is awkward, e.g. mobile apps. Switched off with `features.email_otp`.
+///
+/// Codes expire after `code_ttl` and are burned after `max_attempts` wrong
+/// guesses. An address gets at most one code per `resend_interval` and
+/// `max_sends_per_hour` codes per hour.
+///
+/// ```yaml
+/// email_otp:
+///   code_ttl: 600 # seconds
+///   max_attempts: 5
+///   resend_interval: 60 # seconds
+///   max_sends_per_hour: 5
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmailOtpConfig {
+    #[serde(default = "default_code_ttl")]
+    code_ttl: u64,
+    #[serde(default = "default_max_attempts")]
+    max_attempts: i32,
+    #[serde(default = "default_resend_interval")]
+    resend_interval: u64,
+    #[serde(default = "default_max_sends_per_hour")]
+    max_sends_per_hour: i64,
+}
+
+impl Default for EmailOtpConfig {
+    fn default() -> Self {
+        Self {
+            code_ttl: default_code_ttl(),
+            max_attempts: default_max_attempts(),
+            resend_interval: default_resend_interval(),
+            max_sends_per_hour: default_max_sends_per_hour(),
+        }
+    }
+}
+
+fn default_code_ttl() -> u64 {
+    10 * 60
+}
+
+fn default_max_attempts() -> i32 {
+    5
+}
+
+fn default_resend_interval() -> u64 {
+    60
+}
+
+fn default_max_sends_per_hour() -> i64 {
+    5
+}
+
+impl EmailOtpConfig {
+    /// How long a code may be used, in seconds. Defaults to 10 minutes.
+    #[must_use]
+    pub fn code_ttl(&self) -> u64 {
+        self.code_ttl
+    }
+
+    /// Wrong guesses after which a code stops working. Defaults to 5.
+    #[must_use]
+    pub fn max_attempts(&self) -> i32 {
+        self.max_attempts
+    }
+
+    /// Least time between two codes sent to an address, in seconds.
+    /// Defaults to 1 minute.
+    #[must_use]
+    pub fn resend_interval(&self) -> u64 {
+        self.resend_interval
+    }
+
+    /// Most codes sent to an address per hour. Defaults to 5.
+    #[must_use]
+    pub fn max_sends_per_hour(&self) -> i64 {
+        self.max_sends_per_hour
+    }
+}
@@ -95,6 +95,16 @@ pub enum ConfigError {
     #[error(transparent)]
     MigrateError(#[from] sqlx::migrate::MigrateError),
 
+    /// Error installing the Prometheus metrics recorder.
+    ///
+    /// Wraps [`metrics_exporter_prometheus::BuildError`], which occurs when:
+    /// - A global `metrics` recorder has already been installed
+    /// - Histogram buckets are empty or otherwise invalid
+    ///
+    /// Returned by `metrics::install()` during application startup.
+    #[error(transparent)]
+    Metrics(#[from] metrics_exporter_prometheus::BuildError),
+
     /// Error parsing tracing filter directives.
     ///
     /// Wraps `tracing_subscriber::filter::ParseError`, which occurs when:
@@ -107,6 +117,51 @@ pub enum ConfigError {
     #[error(transparent)]
     Parse(#[from] ParseError),
 
+    /// A secret could not be loaded.
+    ///
+    /// Occurs when:
+    /// - The file named by a `*_file` setting cannot be read
+    /// - A [`crate::config::SecretProvider`] cannot reach its secret manager
+    ///
+    /// `key` names the setting, or the secret manager location.
+    #[error("failed to load secret {key}: {reason}")]
+    Secret { key: String, reason: String },
+
+    /// More than one configuration file exists for the environment, e.g.
+    /// both `production.yaml` and `production.toml`.
+    ///
+    /// Holds the conflicting paths; remove all but one of them.
+    #[error("several configuration files found, keep only one of: {}", .0.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "))]
+    AmbiguousFile(Vec<std::path::PathBuf>),
+
+    /// The configuration violates invariants checked by
+    /// [`crate::config::Config::validate`].
+    ///
+    /// Lists every violation found, e.g. a port of 0, a `database.uri` that
+    /// disagrees with `database.host`, or an empty secret in production.
+    #[error("invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+
+    /// The directory of the `daily` log writer could not be set up.
+    #[error("failed to set up the log directory: {0}")]
+    LogWriter(#[from] tracing_appender::rolling::InitError),
+
+    /// The OTLP span exporter configured in `logger.otlp` could not be
+    /// built, e.g. because its endpoint is not a valid URI.
+    #[error("failed to build the OTLP exporter: {0}")]
+    Otlp(#[from] opentelemetry_otlp::ExporterBuildError),
+
+    /// The DSN in `logger.sentry` is not a valid Sentry DSN.
+    #[error("invalid Sentry DSN: {0}")]
+    Sentry(#[from] sentry::types::ParseDsnError),
+
+    /// The log filter of the running subscriber could not be replaced.
+    ///
+    /// Returned by `Logger::reload()` when the subscriber installed by
+    /// `Logger::setup()` is gone.
+    #[error("failed to reload the log filter: {0}")]
+    Reload(String),
+
     /// Database-related errors from sqlx.
     ///
     /// Wraps all errors from the `sqlx` crate, including:
@@ -122,6 +177,13 @@ pub enum ConfigError {
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
 
+    /// The database could not be reached on startup.
+    ///
+    /// Returned by `DatabaseConfig::ping()` once `database.connect_retries`
+    /// retries failed too; `source` is the failure of the last attempt.
+    #[error("database unreachable after {attempts} attempts: {source}")]
+    Unreachable { attempts: u32, source: sqlx::Error },
+
     /// Error initializing the tracing subscriber.
     ///
     /// Wraps [`tracing_subscriber::util::TryInitError`], which occurs when:
@@ -0,0 +1,81 @@
+use serde::Deserialize;
+
+/// Switches for capabilities an environment may not want to offer, all on
+/// by default. A disabled capability answers `404 Not Found`, as if the
+/// server did not have it. The switches take effect when the configuration
+/// is reloaded.
+///
+/// ```yaml
+/// features:
+///   registration: true
+///   magic_links: true
+///   email_otp: true
+///   social_login: false
+///   admin_ui: true
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeaturesConfig {
+    #[serde(default = "default_enabled")]
+    registration: bool,
+    #[serde(default = "default_enabled")]
+    magic_links: bool,
+    #[serde(default = "default_enabled")]
+    email_otp: bool,
+    #[serde(default = "default_enabled")]
+    social_login: bool,
+    #[serde(default = "default_enabled")]
+    admin_ui: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            registration: default_enabled(),
+            magic_links: default_enabled(),
+            email_otp: default_enabled(),
+            social_login: default_enabled(),
+            admin_ui: default_enabled(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl FeaturesConfig {
+    /// Whether people can sign up on their own, through
+    /// `POST /auth/register` or by logging in with an OAuth provider for the
+    /// first time. Accounts created by an admin or from an invitation are
+    /// unaffected.
+    #[must_use]
+    pub fn registration(&self) -> bool {
+        self.registration
+    }
+
+    /// Whether users can log in with a link emailed to them.
+    #[must_use]
+    pub fn magic_links(&self) -> bool {
+        self.magic_links
+    }
+
+    /// Whether users can log in with a one-time code emailed to them, see
+    /// [`super::EmailOtpConfig`].
+    #[must_use]
+    pub fn email_otp(&self) -> bool {
+        self.email_otp
+    }
+
+    /// Whether users can log in with the configured OAuth providers.
+    #[must_use]
+    pub fn social_login(&self) -> bool {
+        self.social_login
+    }
+
+    /// Whether admins can use the web dashboard at `/admin/ui`, served
+    /// alongside the admin API.
+    #[must_use]
+    pub fn admin_ui(&self) -> bool {
+        self.admin_ui
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+/// The gRPC token validation service, served on a port of its own when the
+/// crate is built with the `grpc` feature and this section is present.
+///
+/// The service trusts its callers, so bind it to an address only internal
+/// services can reach.
+///
+/// ```yaml
+/// grpc:
+///   host: 127.0.0.1 # the default
+///   port: 7151
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct GrpcConfig {
+    #[serde(default = "default_host")]
+    host: String,
+    port: u16,
+}
+
+fn default_host() -> String {
+    String::from("127.0.0.1")
+}
+
+impl GrpcConfig {
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Languages error messages and emails are offered in, picked per request
+/// from its `Accept-Language` header.
+///
+/// English is built in. For any other language in `languages`, `dir` holds
+/// a message catalog named after it, e.g. `fr.yaml`, and
+/// `mailer.template_dir` a directory of the same name with its email
+/// templates, e.g. `fr/password_reset.html`. Messages and templates missing
+/// from either fall back to the built-in English ones. The first language
+/// is used when the client accepts none of them.
+///
+/// ```yaml
+/// i18n:
+///   languages: ["en", "fr", "pt-br"]
+///   dir: "/etc/betterauth/locales"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct I18nConfig {
+    #[serde(default = "default_languages")]
+    languages: Vec<String>,
+    #[serde(default)]
+    dir: Option<PathBuf>,
+}
+
+impl Default for I18nConfig {
+    fn default() -> Self {
+        Self {
+            languages: default_languages(),
+            dir: None,
+        }
+    }
+}
+
+fn default_languages() -> Vec<String> {
+    vec![String::from("en")]
+}
+
+impl I18nConfig {
+    /// Language tags offered, the default first. Defaults to `en` alone.
+    #[must_use]
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+
+    /// Directory of the message catalogs.
+    #[must_use]
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// How outgoing email is delivered.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// Through the SMTP server in the `smtp` subsection.
+    Smtp,
+    /// Written to the log instead of delivered, for development.
+    #[default]
+    Log,
+    /// Silently discarded, for tests.
+    Noop,
+}
+
+/// Connection security of the SMTP server.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// TLS from the start of the connection, usually on port 465.
+    Tls,
+    /// Plain connection upgraded with `STARTTLS`, usually on port 587.
+    #[default]
+    StartTls,
+    /// No encryption at all; only for local relays and mail catchers.
+    None,
+}
+
+/// Outgoing email delivery.
+///
+/// Auth emails (password resets, verification, magic links, invites, ...)
+/// are sent from `from` through the selected transport. Without this
+/// section they are only logged. Other services can be used by installing a
+/// custom [`crate::mail::Mailer`] with [`crate::AppContext::with_mailer`].
+///
+/// Emails are rendered from Tera templates with an HTML and a plain-text
+/// part. Built-in templates are used unless `template_dir` holds a file of
+/// the same name, e.g. `password_reset.html`, or for emails in another
+/// language a subdirectory named after it does, see [`super::I18nConfig`];
+/// see `templates/email` for the names and variables. `product_name` and `base_url` are available to every
+/// template for branding.
+///
+/// ```yaml
+/// mailer:
+///   transport: "smtp" # smtp, log or noop
+///   from: "Example <no-reply@example.com>"
+///   product_name: "Example"
+///   base_url: "https://app.example.com" # defaults to the server URL
+///   template_dir: "/etc/betterauth/templates"
+///   smtp:
+///     host: "smtp.example.com"
+///     port: 587 # defaults to the standard port of the security mode
+///     security: "starttls" # tls, starttls or none
+///     username: "no-reply@example.com"
+///     password: "..."
+///     timeout: 10 # seconds
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct MailerConfig {
+    #[serde(default)]
+    transport: MailTransport,
+    #[serde(default = "default_from")]
+    from: String,
+    #[serde(default)]
+    smtp: Option<SmtpConfig>,
+    #[serde(default = "default_product_name")]
+    product_name: String,
+    #[serde(default)]
+    base_url: Option<String>,
+    #[serde(default)]
+    template_dir: Option<PathBuf>,
+}
+
+impl Default for MailerConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::default(),
+            from: default_from(),
+            smtp: None,
+            product_name: default_product_name(),
+            base_url: None,
+            template_dir: None,
+        }
+    }
+}
+
+fn default_from() -> String {
+    String::from("no-reply@localhost")
+}
+
+fn default_product_name() -> String {
+    String::from("Better Auth")
+}
+
+impl MailerConfig {
+    /// Defaults to [`MailTransport::Log`].
+    #[must_use]
+    pub fn transport(&self) -> MailTransport {
+        self.transport
+    }
+
+    /// Sender of every email, as `address` or `Name <address>`.
+    #[must_use]
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    /// SMTP server, required by [`MailTransport::Smtp`].
+    #[must_use]
+    pub fn smtp(&self) -> Option<&SmtpConfig> {
+        self.smtp.as_ref()
+    }
+
+    /// Name emails refer to the service by.
+    #[must_use]
+    pub fn product_name(&self) -> &str {
+        &self.product_name
+    }
+
+    /// Site emails link to for branding, if it differs from the server URL.
+    #[must_use]
+    pub fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// Directory of templates overriding the built-in ones.
+    #[must_use]
+    pub fn template_dir(&self) -> Option<&Path> {
+        self.template_dir.as_deref()
+    }
+}
+
+/// SMTP server connection settings.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SmtpConfig {
+    host: String,
+    #[serde(default)]
+    port: Option<u16>,
+    #[serde(default)]
+    security: SmtpSecurity,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<SecretString>,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl SmtpConfig {
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Port, if it differs from the standard one of [`Self::security`].
+    #[must_use]
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Defaults to [`SmtpSecurity::StartTls`].
+    #[must_use]
+    pub fn security(&self) -> SmtpSecurity {
+        self.security
+    }
+
+    /// Login, if the server requires authentication.
+    #[must_use]
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    #[must_use]
+    pub fn password(&self) -> Option<&SecretString> {
+        self.password.as_ref()
+    }
+
+    /// How long to wait on the server, in seconds. Defaults to 10.
+    #[must_use]
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+}
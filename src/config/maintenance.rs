@@ -0,0 +1,99 @@
+use serde::Deserialize;
+
+/// Periodic purge of data that is no longer needed.
+///
+/// Every `interval` the maintenance task deletes expired sessions, expired
+/// or used one-time tokens and codes, denylist entries for tokens that have
+/// expired anyway, expired invitations, audit events older than
+/// `audit_retention` and the login attempts behind `GET /admin/reports/*`
+/// older than `login_history_retention`; `0` keeps either forever. How many
+/// rows were deleted is recorded in the `maintenance_rows_deleted_total`
+/// metric.
+///
+/// `audit_events` is partitioned by UTC month, and the task also creates
+/// the partitions of the coming months. With `audit_archive_after` set,
+/// each month that ended longer ago is detached, written gzipped as NDJSON
+/// to `audit-{month}.ndjson.gz` in `audit_archive_dir` and dropped,
+/// keeping the live table small. Archived events are out of reach of
+/// `GET /admin/audit-events`, the audit sinks and account erasure.
+///
+/// ```yaml
+/// maintenance:
+///   interval: 3600 # seconds
+///   audit_retention: 31536000 # seconds
+///   login_history_retention: 7776000 # seconds
+///   audit_archive_after: 7776000 # seconds
+///   audit_archive_dir: "/var/lib/betterauth/audit"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct MaintenanceConfig {
+    #[serde(default = "default_interval")]
+    interval: u64,
+    #[serde(default = "default_audit_retention")]
+    audit_retention: u64,
+    #[serde(default = "default_login_history_retention")]
+    login_history_retention: u64,
+    #[serde(default)]
+    audit_archive_after: u64,
+    #[serde(default)]
+    audit_archive_dir: Option<String>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            audit_retention: default_audit_retention(),
+            login_history_retention: default_login_history_retention(),
+            audit_archive_after: 0,
+            audit_archive_dir: None,
+        }
+    }
+}
+
+fn default_interval() -> u64 {
+    60 * 60
+}
+
+fn default_audit_retention() -> u64 {
+    365 * 24 * 60 * 60
+}
+
+fn default_login_history_retention() -> u64 {
+    90 * 24 * 60 * 60
+}
+
+impl MaintenanceConfig {
+    /// Time between two purges, in seconds. Defaults to an hour.
+    #[must_use]
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// How long audit events are kept, in seconds. Defaults to 365 days;
+    /// `0` keeps them forever.
+    #[must_use]
+    pub fn audit_retention(&self) -> u64 {
+        self.audit_retention
+    }
+
+    /// How long login attempts are kept for reports, in seconds. Defaults
+    /// to 90 days; `0` keeps them forever.
+    #[must_use]
+    pub fn login_history_retention(&self) -> u64 {
+        self.login_history_retention
+    }
+
+    /// How long after its month ends a partition of audit events is
+    /// archived, in seconds. Defaults to `0`, never.
+    #[must_use]
+    pub fn audit_archive_after(&self) -> u64 {
+        self.audit_archive_after
+    }
+
+    /// Directory archived partitions of audit events are written to.
+    #[must_use]
+    pub fn audit_archive_dir(&self) -> Option<&str> {
+        self.audit_archive_dir.as_deref()
+    }
+}
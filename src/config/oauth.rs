@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// OAuth2 social login configuration.
+///
+/// Each provider is enabled by giving it a section; providers without one
+/// answer `404 Not Found`. The redirect URL must match the one registered
+/// with the provider and point at `/auth/oauth/{provider}/callback`.
+///
+/// ```yaml
+/// oauth:
+///   google:
+///     client_id: "1234.apps.googleusercontent.com"
+///     client_secret: "secret"
+///     redirect_url: "https://auth.example.com/auth/oauth/google/callback"
+///   github:
+///     client_id: "Iv1.abcdef"
+///     client_secret: "secret"
+///     redirect_url: "https://auth.example.com/auth/oauth/github/callback"
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OAuthConfig {
+    #[serde(default)]
+    google: Option<OAuthProviderConfig>,
+    #[serde(default)]
+    github: Option<OAuthProviderConfig>,
+}
+
+impl OAuthConfig {
+    /// Google credentials, if Google login is enabled.
+    #[must_use]
+    pub fn google(&self) -> Option<&OAuthProviderConfig> {
+        self.google.as_ref()
+    }
+
+    /// GitHub credentials, if GitHub login is enabled.
+    #[must_use]
+    pub fn github(&self) -> Option<&OAuthProviderConfig> {
+        self.github.as_ref()
+    }
+}
+
+/// Client credentials registered with a single OAuth2 provider.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OAuthProviderConfig {
+    client_id: String,
+    client_secret: SecretString,
+    redirect_url: String,
+}
+
+impl OAuthProviderConfig {
+    #[must_use]
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    #[must_use]
+    pub fn client_secret(&self) -> &SecretString {
+        &self.client_secret
+    }
+
+    /// Callback URL the provider redirects back to after consent.
+    #[must_use]
+    pub fn redirect_url(&self) -> &str {
+        &self.redirect_url
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use config::{ConfigBuilder, builder::DefaultState};
+use url::Url;
+
+use super::{ConfigResult, Environment, Level};
+
+/// Variable naming the directory the config files are read from.
+pub const CONFIG_DIR_VAR: &str = "APP_CONFIG_DIR";
+
+/// Variable naming the config file to read, whatever the environment.
+pub const CONFIG_FILE_VAR: &str = "APP_CONFIG_FILE";
+
+/// Settings given on the command line, layered on top of the config file
+/// and `APP_` variables by [`crate::config::Config::from_overrides`].
+///
+/// Every field is optional; an unset one leaves the value from the lower
+/// layers in place.
+///
+/// # Examples
+///
+/// ```no_run
+/// use betterauth::config::{Config, Environment, Overrides};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let overrides = Overrides::default()
+///     .with_environment(Environment::Production)
+///     .with_port(8080);
+/// let config = Config::from_overrides(&overrides)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    config_dir: Option<PathBuf>,
+    config_file: Option<PathBuf>,
+    environment: Option<Environment>,
+    port: Option<u16>,
+    log_level: Option<Level>,
+    database_name: Option<String>,
+}
+
+impl Overrides {
+    /// Reads the config files from `dir` instead of `{cwd}/config`.
+    #[must_use]
+    pub fn with_config_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config_dir = Some(dir.into());
+        self
+    }
+
+    /// Reads the configuration from `path`, in the format its extension
+    /// names, instead of the file of the environment in the config
+    /// directory. The environment still decides what is validated.
+    #[must_use]
+    pub fn with_config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// Loads `env` instead of the one named by `APP_ENVIRONMENT`.
+    #[must_use]
+    pub fn with_environment(mut self, env: Environment) -> Self {
+        self.environment = Some(env);
+        self
+    }
+
+    /// Overrides `server.port`.
+    #[must_use]
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Overrides `logger.level`.
+    #[must_use]
+    pub fn with_log_level(mut self, level: Level) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// Overrides `database.name`, and the database named in `database.uri`
+    /// with it, e.g. to point each test at its own database.
+    #[must_use]
+    pub fn with_database_name(mut self, name: impl Into<String>) -> Self {
+        self.database_name = Some(name.into());
+        self
+    }
+
+    /// The directory the config files are read from: the overridden one,
+    /// else the one named by `APP_CONFIG_DIR`, else `{cwd}/config`.
+    ///
+    /// # Errors
+    ///
+    /// * Cannot determine current working directory
+    pub fn config_dir(&self) -> ConfigResult<PathBuf> {
+        if let Some(dir) = &self.config_dir {
+            return Ok(dir.clone());
+        }
+
+        match std::env::var_os(CONFIG_DIR_VAR).filter(|dir| !dir.is_empty()) {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => Ok(std::env::current_dir()?.join("config")),
+        }
+    }
+
+    /// The config file to read regardless of the environment, if any: the
+    /// overridden one, else the one named by `APP_CONFIG_FILE` unless the
+    /// config directory is overridden.
+    #[must_use]
+    pub fn config_file(&self) -> Option<PathBuf> {
+        if let Some(file) = &self.config_file {
+            return Some(file.clone());
+        }
+        if self.config_dir.is_some() {
+            return None;
+        }
+
+        std::env::var_os(CONFIG_FILE_VAR)
+            .filter(|file| !file.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// The environment to load, [`Environment::current`] unless overridden.
+    #[must_use]
+    pub fn environment(&self) -> Environment {
+        self.environment
+            .clone()
+            .unwrap_or_else(Environment::current)
+    }
+
+    /// Sets the overridden values on `builder`, above all of its sources.
+    pub(super) fn apply(
+        &self,
+        mut builder: ConfigBuilder<DefaultState>,
+    ) -> ConfigResult<ConfigBuilder<DefaultState>> {
+        if let Some(port) = self.port {
+            builder = builder.set_override("server.port", port)?;
+        }
+        if let Some(level) = &self.log_level {
+            builder = builder.set_override("logger.level", level.to_string())?;
+        }
+
+        Ok(builder)
+    }
+
+    /// Points `config` at the overridden database, once every other source
+    /// and secret is in, so that `database.uri` can be rewritten.
+    pub(super) fn apply_database(&self, config: config::Config) -> ConfigResult<config::Config> {
+        let Some(name) = &self.database_name else {
+            return Ok(config);
+        };
+
+        let uri = config
+            .get_string("database.uri")
+            .ok()
+            .and_then(|uri| Url::parse(&uri).ok());
+        let mut builder = config::Config::builder()
+            .add_source(config)
+            .set_override("database.name", name.as_str())?;
+
+        if let Some(mut uri) = uri {
+            uri.set_path(name);
+            builder = builder.set_override("database.uri", uri.as_str())?;
+        }
+
+        builder.build().map_err(Into::into)
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// Argon2id cost of new password hashes.
+///
+/// Defaults follow the OWASP recommendation of 19 MiB of memory, 2
+/// iterations and 1 lane. Raising them makes every login slower and every
+/// guess at a stolen hash more expensive. Hashes made with other settings,
+/// or imported from bcrypt, keep working and are rehashed with these on the
+/// user's next successful login.
+///
+/// With a `pepper`, passwords are first keyed with HMAC-SHA256 under it, so
+/// a leaked database is useless without the key, kept apart from it in a
+/// secret file or manager. Each hash records the `pepper_version` it was
+/// made with. To rotate the pepper, move the current one to
+/// `retired_peppers` under its version and set a new one with a higher
+/// version; hashes made with a retired pepper still verify and are rehashed
+/// with the current one on the next successful login.
+///
+/// Requests hash on a blocking thread, never on the async runtime, at most
+/// `max_concurrent` at once, the number of CPUs by default. The others wait
+/// their turn for up to `queue_timeout` seconds, 5 by default, and are then
+/// answered with `503 Service Unavailable`.
+///
+/// ```yaml
+/// password_hashing:
+///   memory_kib: 19456
+///   iterations: 2
+///   parallelism: 1
+///   max_concurrent: 4
+///   queue_timeout: 5
+///   pepper_file: "/run/secrets/pepper"
+///   pepper_version: 2
+///   retired_peppers:
+///     1: "previous-long-random-string"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordHashing {
+    #[serde(default = "default_memory_kib")]
+    memory_kib: u32,
+    #[serde(default = "default_iterations")]
+    iterations: u32,
+    #[serde(default = "default_parallelism")]
+    parallelism: u32,
+    #[serde(default)]
+    pepper: Option<SecretString>,
+    #[serde(default = "default_pepper_version")]
+    pepper_version: u32,
+    #[serde(default)]
+    retired_peppers: HashMap<u32, SecretString>,
+    #[serde(default)]
+    max_concurrent: Option<usize>,
+    #[serde(default = "default_queue_timeout")]
+    queue_timeout: u64,
+}
+
+impl Default for PasswordHashing {
+    fn default() -> Self {
+        Self {
+            memory_kib: default_memory_kib(),
+            iterations: default_iterations(),
+            parallelism: default_parallelism(),
+            pepper: None,
+            pepper_version: default_pepper_version(),
+            retired_peppers: HashMap::new(),
+            max_concurrent: None,
+            queue_timeout: default_queue_timeout(),
+        }
+    }
+}
+
+fn default_memory_kib() -> u32 {
+    argon2::Params::DEFAULT_M_COST
+}
+
+fn default_iterations() -> u32 {
+    argon2::Params::DEFAULT_T_COST
+}
+
+fn default_parallelism() -> u32 {
+    argon2::Params::DEFAULT_P_COST
+}
+
+fn default_pepper_version() -> u32 {
+    1
+}
+
+fn default_queue_timeout() -> u64 {
+    5
+}
+
+impl PasswordHashing {
+    /// Memory used per hash, in KiB. Defaults to 19456.
+    #[must_use]
+    pub fn memory_kib(&self) -> u32 {
+        self.memory_kib
+    }
+
+    /// Passes over the memory. Defaults to 2.
+    #[must_use]
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Lanes computed in parallel. Defaults to 1.
+    #[must_use]
+    pub fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
+
+    /// Key passwords are keyed with before hashing, if any.
+    #[must_use]
+    pub fn pepper(&self) -> Option<&SecretString> {
+        self.pepper.as_ref()
+    }
+
+    /// Version recorded in hashes made with [`PasswordHashing::pepper`].
+    /// Defaults to 1.
+    #[must_use]
+    pub fn pepper_version(&self) -> u32 {
+        self.pepper_version
+    }
+
+    /// Earlier peppers by version, still accepted when verifying.
+    #[must_use]
+    pub fn retired_peppers(&self) -> &HashMap<u32, SecretString> {
+        &self.retired_peppers
+    }
+
+    /// Most passwords hashed or verified at once. Defaults to the number of
+    /// CPUs.
+    #[must_use]
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+        })
+    }
+
+    /// Seconds a password waits for its turn to be hashed. Defaults to 5.
+    #[must_use]
+    pub fn queue_timeout(&self) -> u64 {
+        self.queue_timeout
+    }
+
+    /// The Argon2 parameters these settings describe.
+    ///
+    /// ## Errors
+    /// * A setting is outside the range Argon2 allows, e.g. less than 8 KiB
+    ///   of memory per lane
+    pub fn params(&self) -> Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
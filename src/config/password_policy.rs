@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+/// Rules a new password must satisfy, checked at registration and whenever a
+/// password is changed.
+///
+/// With `breached_passwords` set to `warn` or `reject`, new passwords are
+/// also looked up in the Have I Been Pwned password list. Only the first
+/// five characters of the password's SHA-1 hash are sent, and the lookup
+/// is skipped when the service cannot be reached.
+///
+/// ```yaml
+/// password_policy:
+///   min_length: 8
+///   max_length: 128
+///   require_uppercase: false
+///   require_lowercase: false
+///   require_digit: false
+///   require_symbol: false
+///   # Reject passwords from a built-in list of the most common ones
+///   deny_common: true
+///   # off, warn (accept with an X-Password-Warning header) or reject
+///   breached_passwords: reject
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordPolicy {
+    #[serde(default = "default_min_length")]
+    min_length: usize,
+    #[serde(default = "default_max_length")]
+    max_length: usize,
+    #[serde(default)]
+    require_uppercase: bool,
+    #[serde(default)]
+    require_lowercase: bool,
+    #[serde(default)]
+    require_digit: bool,
+    #[serde(default)]
+    require_symbol: bool,
+    #[serde(default = "default_deny_common")]
+    deny_common: bool,
+    #[serde(default)]
+    breached_passwords: BreachedPasswords,
+    #[serde(default = "default_pwned_passwords_url")]
+    pwned_passwords_url: String,
+}
+
+/// Rules tightening a [`PasswordPolicy`], as organizations hold their
+/// members to, see [`PasswordPolicy::tightened`]. Rules left unset leave the
+/// policy as it is.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PasswordRules {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(default)]
+    pub require_uppercase: bool,
+    #[serde(default)]
+    pub require_lowercase: bool,
+    #[serde(default)]
+    pub require_digit: bool,
+    #[serde(default)]
+    pub require_symbol: bool,
+}
+
+impl PasswordRules {
+    /// Whether the rules add nothing to any policy.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Rules requiring everything either `self` or `other` does.
+    #[must_use]
+    pub fn stricter(&self, other: &Self) -> Self {
+        Self {
+            min_length: self.min_length.max(other.min_length),
+            require_uppercase: self.require_uppercase || other.require_uppercase,
+            require_lowercase: self.require_lowercase || other.require_lowercase,
+            require_digit: self.require_digit || other.require_digit,
+            require_symbol: self.require_symbol || other.require_symbol,
+        }
+    }
+}
+
+/// What happens to a new password found in a data breach.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BreachedPasswords {
+    /// Passwords are not looked up.
+    #[default]
+    Off,
+    /// The password is accepted, and the response carries an
+    /// `X-Password-Warning: breached` header.
+    Warn,
+    /// The password is rejected like one failing the policy.
+    Reject,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: default_min_length(),
+            max_length: default_max_length(),
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+            deny_common: default_deny_common(),
+            breached_passwords: BreachedPasswords::default(),
+            pwned_passwords_url: default_pwned_passwords_url(),
+        }
+    }
+}
+
+fn default_min_length() -> usize {
+    8
+}
+
+fn default_max_length() -> usize {
+    128
+}
+
+fn default_deny_common() -> bool {
+    true
+}
+
+fn default_pwned_passwords_url() -> String {
+    String::from("https://api.pwnedpasswords.com/range/")
+}
+
+impl PasswordPolicy {
+    /// Fewest characters a password may have. Defaults to 8.
+    #[must_use]
+    pub fn min_length(&self) -> usize {
+        self.min_length
+    }
+
+    /// Most characters a password may have. Defaults to 128.
+    #[must_use]
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Whether an uppercase letter is required. Defaults to `false`.
+    #[must_use]
+    pub fn require_uppercase(&self) -> bool {
+        self.require_uppercase
+    }
+
+    /// Whether a lowercase letter is required. Defaults to `false`.
+    #[must_use]
+    pub fn require_lowercase(&self) -> bool {
+        self.require_lowercase
+    }
+
+    /// Whether a digit is required. Defaults to `false`.
+    #[must_use]
+    pub fn require_digit(&self) -> bool {
+        self.require_digit
+    }
+
+    /// Whether a character that is neither a letter nor a digit is required.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn require_symbol(&self) -> bool {
+        self.require_symbol
+    }
+
+    /// Whether well-known common passwords are rejected. Defaults to `true`.
+    #[must_use]
+    pub fn deny_common(&self) -> bool {
+        self.deny_common
+    }
+
+    /// Whether new passwords are looked up in known breaches. Defaults to
+    /// [`BreachedPasswords::Off`].
+    #[must_use]
+    pub fn breached_passwords(&self) -> BreachedPasswords {
+        self.breached_passwords
+    }
+
+    /// This policy with `rules` applied on top: the longer of the two
+    /// minimum lengths, and every character class either one requires.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use betterauth::config::{PasswordPolicy, PasswordRules};
+    /// let rules = PasswordRules {
+    ///     min_length: Some(14),
+    ///     require_symbol: true,
+    ///     ..PasswordRules::default()
+    /// };
+    /// let policy = PasswordPolicy::default().tightened(&rules);
+    /// assert_eq!(policy.min_length(), 14);
+    /// assert!(policy.require_symbol());
+    /// assert!(!policy.require_digit());
+    /// ```
+    #[must_use]
+    pub fn tightened(&self, rules: &PasswordRules) -> Self {
+        Self {
+            min_length: self.min_length.max(rules.min_length.unwrap_or_default()),
+            require_uppercase: self.require_uppercase || rules.require_uppercase,
+            require_lowercase: self.require_lowercase || rules.require_lowercase,
+            require_digit: self.require_digit || rules.require_digit,
+            require_symbol: self.require_symbol || rules.require_symbol,
+            ..self.clone()
+        }
+    }
+
+    /// Range API breached passwords are looked up in, followed by the hash
+    /// prefix. Defaults to Have I Been Pwned.
+    #[must_use]
+    pub fn pwned_passwords_url(&self) -> &str {
+        &self.pwned_passwords_url
+    }
+}
@@ -0,0 +1,54 @@
+use serde::Deserialize;
+
+/// Personal data export and account deletion.
+///
+/// Exports requested through `POST /me/export` are built in the background
+/// and can be downloaded for `export_ttl`. `DELETE /me` schedules the account
+/// for deletion after `deletion_grace_period`, during which the user can
+/// still log in and cancel; `0` deletes immediately.
+///
+/// ```yaml
+/// privacy:
+///   export_ttl: 604800 # seconds
+///   deletion_grace_period: 2592000 # seconds
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct PrivacyConfig {
+    #[serde(default = "default_export_ttl")]
+    export_ttl: u64,
+    #[serde(default = "default_deletion_grace_period")]
+    deletion_grace_period: u64,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            export_ttl: default_export_ttl(),
+            deletion_grace_period: default_deletion_grace_period(),
+        }
+    }
+}
+
+fn default_export_ttl() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_deletion_grace_period() -> u64 {
+    30 * 24 * 60 * 60
+}
+
+impl PrivacyConfig {
+    /// How long a finished export can be downloaded, in seconds. Defaults to
+    /// 7 days.
+    #[must_use]
+    pub fn export_ttl(&self) -> u64 {
+        self.export_ttl
+    }
+
+    /// Delay between a deletion request and the account being erased, in
+    /// seconds. Defaults to 30 days; `0` erases immediately.
+    #[must_use]
+    pub fn deletion_grace_period(&self) -> u64 {
+        self.deletion_grace_period
+    }
+}
@@ -0,0 +1,193 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::Deserialize;
+
+/// Request rate limits, one policy per route group.
+///
+/// A request belongs to the group named after the first segment of its path,
+/// after any `/api/{version}` prefix (`/auth/login` and `/api/v1/auth/login`
+/// are in `auth`, `/admin/users` in `admin`), or to `default` if that group
+/// has no policy. Each policy may limit requests per client IP,
+/// per authenticated user, or both; callers over a limit get
+/// `429 Too Many Requests` with a `Retry-After` header.
+///
+/// `endpoints` sets stricter limits for single routes, by their path without
+/// the `/api/{version}` prefix. They are enforced on top of the limits of the
+/// route group, in buckets of their own. A limit may be written as
+/// `<requests>/<second|minute|hour|day>`, and a policy as a single limit,
+/// which then applies per client IP.
+///
+/// Limits are token buckets holding `requests` tokens that refill evenly over
+/// `period`, so short bursts are allowed as long as the average stays under
+/// the limit. Without this section nothing is limited.
+///
+/// ```yaml
+/// rate_limit:
+///   default:
+///     ip: { requests: 300, period: 60 } # period in seconds
+///   auth:
+///     ip: { requests: 20, period: 60 }
+///   admin:
+///     user: 120/minute
+///   endpoints:
+///     /auth/login: 5/minute
+///     /auth/forgot-password: 3/hour
+///     /auth/refresh: { ip: 30/minute, user: 10/minute }
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    endpoints: HashMap<String, RateLimitPolicy>,
+    #[serde(flatten)]
+    groups: HashMap<String, RateLimitPolicy>,
+}
+
+impl RateLimitConfig {
+    /// The endpoint `path` is and its policy, if it has one of its own.
+    #[must_use]
+    pub fn endpoint_policy(&self, path: &str) -> Option<(&str, &RateLimitPolicy)> {
+        let path = super::unversioned_path(path);
+        let path = path
+            .strip_suffix('/')
+            .filter(|path| !path.is_empty())
+            .unwrap_or(path);
+
+        self.endpoints
+            .get_key_value(path)
+            .map(|(endpoint, policy)| (endpoint.as_str(), policy))
+    }
+
+    /// Paths of the endpoints with a policy of their own.
+    pub fn endpoints(&self) -> impl Iterator<Item = &str> {
+        self.endpoints.keys().map(String::as_str)
+    }
+
+    /// Every route group and endpoint with its policy, groups by name and
+    /// endpoints by path.
+    pub fn policies(&self) -> impl Iterator<Item = (&str, &RateLimitPolicy)> {
+        self.groups
+            .iter()
+            .chain(&self.endpoints)
+            .map(|(name, policy)| (name.as_str(), policy))
+    }
+
+    /// The group `path` falls in and its policy, if any applies.
+    #[must_use]
+    pub fn policy(&self, path: &str) -> Option<(&str, &RateLimitPolicy)> {
+        let segment = super::route_group(path);
+
+        self.groups
+            .get_key_value(segment)
+            .or_else(|| self.groups.get_key_value("default"))
+            .map(|(group, policy)| (group.as_str(), policy))
+    }
+}
+
+/// Limits applied to one route group or endpoint.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(from = "RawPolicy")]
+pub struct RateLimitPolicy {
+    ip: Option<RateLimit>,
+    user: Option<RateLimit>,
+}
+
+/// A policy as written: in full, or a single limit per client IP.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPolicy {
+    Ip(RateLimit),
+    Full {
+        #[serde(default)]
+        ip: Option<RateLimit>,
+        #[serde(default)]
+        user: Option<RateLimit>,
+    },
+}
+
+impl From<RawPolicy> for RateLimitPolicy {
+    fn from(raw: RawPolicy) -> Self {
+        match raw {
+            RawPolicy::Ip(limit) => Self {
+                ip: Some(limit),
+                user: None,
+            },
+            RawPolicy::Full { ip, user } => Self { ip, user },
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    /// Limit per client IP address.
+    #[must_use]
+    pub fn ip(&self) -> Option<&RateLimit> {
+        self.ip.as_ref()
+    }
+
+    /// Limit per authenticated user, across all their sessions.
+    #[must_use]
+    pub fn user(&self) -> Option<&RateLimit> {
+        self.user.as_ref()
+    }
+}
+
+/// Allows `requests` requests per `period`.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(try_from = "RawLimit")]
+pub struct RateLimit {
+    requests: u32,
+    period: u64,
+}
+
+/// A limit as written: `{ requests, period }` or e.g. `5/minute`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLimit {
+    Short(String),
+    Full {
+        requests: u32,
+        #[serde(default = "default_period")]
+        period: u64,
+    },
+}
+
+fn default_period() -> u64 {
+    60
+}
+
+impl TryFrom<RawLimit> for RateLimit {
+    type Error = String;
+
+    fn try_from(raw: RawLimit) -> Result<Self, Self::Error> {
+        let short = match raw {
+            RawLimit::Full { requests, period } => return Ok(Self { requests, period }),
+            RawLimit::Short(short) => short,
+        };
+
+        let invalid = || format!("invalid rate limit `{short}`, expected e.g. `5/minute`");
+        let (requests, unit) = short.split_once('/').ok_or_else(invalid)?;
+        let requests = requests.trim().parse().map_err(|_| invalid())?;
+        let period = match unit.trim() {
+            "s" | "sec" | "second" => 1,
+            "m" | "min" | "minute" => 60,
+            "h" | "hour" => 60 * 60,
+            "d" | "day" => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self { requests, period })
+    }
+}
+
+impl RateLimit {
+    /// Size of the bucket, i.e. the longest allowed burst.
+    #[must_use]
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    /// Time over which a full bucket refills. Defaults to 1 minute.
+    #[must_use]
+    pub fn period(&self) -> Duration {
+        Duration::from_secs(self.period)
+    }
+}
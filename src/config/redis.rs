@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// Redis, shared by all instances, for state that is read on every request.
+///
+/// With this section present, session lookups are cached in Redis, rate
+/// limit counters and the failed logins behind `security.login_delay` are
+/// kept there instead of in each process or Postgres, and revoked
+/// token ids go to Redis instead of the `revoked_tokens` table. Each use can
+/// be switched off on its own. Postgres stays the source of truth for
+/// sessions, unless `auth.session_backend` keeps them in Redis only; if
+/// Redis cannot be reached at startup, or a command fails later, the server
+/// falls back to Postgres and in-memory rate limits.
+///
+/// Use a `rediss://` URL for TLS.
+///
+/// ```yaml
+/// redis:
+///   url: "redis://127.0.0.1:6379/0"
+///   key_prefix: "betterauth:"
+///   sessions: true
+///   rate_limit: true
+///   revocations: true
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct RedisConfig {
+    url: SecretString,
+    #[serde(default = "default_key_prefix")]
+    key_prefix: String,
+    #[serde(default = "default_enabled")]
+    sessions: bool,
+    #[serde(default = "default_enabled")]
+    rate_limit: bool,
+    #[serde(default = "default_enabled")]
+    revocations: bool,
+}
+
+fn default_key_prefix() -> String {
+    String::from("betterauth:")
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl RedisConfig {
+    /// Connection URL, e.g. `redis://:password@host:6379/0`.
+    #[must_use]
+    pub fn url(&self) -> &SecretString {
+        &self.url
+    }
+
+    /// Prepended to every key, so several deployments can share a server.
+    /// Defaults to `betterauth:`.
+    #[must_use]
+    pub fn key_prefix(&self) -> &str {
+        &self.key_prefix
+    }
+
+    /// Whether session lookups are cached. Defaults to `true`.
+    #[must_use]
+    pub fn sessions(&self) -> bool {
+        self.sessions
+    }
+
+    /// Whether rate limit counters and login delays are shared through
+    /// Redis. Defaults to `true`.
+    #[must_use]
+    pub fn rate_limit(&self) -> bool {
+        self.rate_limit
+    }
+
+    /// Whether revoked tokens are listed in Redis. Defaults to `true`.
+    #[must_use]
+    pub fn revocations(&self) -> bool {
+        self.revocations
+    }
+}
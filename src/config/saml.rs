@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// SAML 2.0 single sign-on configuration, one entry per tenant.
+///
+/// Each tenant names an identity provider and is served under
+/// `/auth/saml/{tenant}`; tenants without an entry answer `404 Not Found`.
+/// The service-provider entity ID and assertion consumer service URL are
+/// derived from the server URL, see `GET /auth/saml/{tenant}/metadata`.
+///
+/// `idp_certificate` is the IdP's signing certificate, either PEM or the bare
+/// base64 found in its metadata. Responses must be signed with RSA-SHA256.
+///
+/// `domains` lists the email domains the IdP speaks for. Users are only
+/// provisioned, and existing accounts only linked to a NameID, for addresses
+/// within them, so one tenant's IdP cannot log in as users of another.
+///
+/// `idp_slo_url`, if the IdP supports single logout, receives logout
+/// requests and responses from this server (HTTP-Redirect binding). The IdP
+/// posts its own to `/auth/saml/{tenant}/slo`, signed.
+///
+/// ```yaml
+/// saml:
+///   acme:
+///     idp_entity_id: "https://idp.acme.com/saml"
+///     idp_sso_url: "https://idp.acme.com/saml/sso"
+///     idp_slo_url: "https://idp.acme.com/saml/slo"
+///     idp_certificate: |
+///       -----BEGIN CERTIFICATE-----
+///       MIIC...
+///       -----END CERTIFICATE-----
+///     domains: ["acme.com", "acme.co.uk"]
+///     email_attribute: "email" # falls back to an email-shaped NameID
+///     name_attribute: "name"
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SamlConfig {
+    #[serde(flatten)]
+    tenants: HashMap<String, SamlProviderConfig>,
+}
+
+impl SamlConfig {
+    /// Identity provider settings of `tenant`, if configured.
+    #[must_use]
+    pub fn tenant(&self, tenant: &str) -> Option<&SamlProviderConfig> {
+        self.tenants.get(tenant)
+    }
+
+    /// Every tenant with its identity provider settings.
+    pub fn tenants(&self) -> impl Iterator<Item = (&str, &SamlProviderConfig)> {
+        self.tenants
+            .iter()
+            .map(|(tenant, idp)| (tenant.as_str(), idp))
+    }
+
+    /// Whether no tenant is configured.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+}
+
+/// A single tenant's SAML identity provider.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SamlProviderConfig {
+    idp_entity_id: String,
+    idp_sso_url: String,
+    #[serde(default)]
+    idp_slo_url: Option<String>,
+    idp_certificate: String,
+    #[serde(default)]
+    domains: Vec<String>,
+    #[serde(default = "default_email_attribute")]
+    email_attribute: String,
+    #[serde(default = "default_name_attribute")]
+    name_attribute: String,
+}
+
+fn default_email_attribute() -> String {
+    String::from("email")
+}
+
+fn default_name_attribute() -> String {
+    String::from("name")
+}
+
+impl SamlProviderConfig {
+    /// Issuer the IdP puts on its responses and assertions.
+    #[must_use]
+    pub fn idp_entity_id(&self) -> &str {
+        &self.idp_entity_id
+    }
+
+    /// IdP endpoint receiving authentication requests (HTTP-Redirect binding).
+    #[must_use]
+    pub fn idp_sso_url(&self) -> &str {
+        &self.idp_sso_url
+    }
+
+    /// IdP endpoint receiving logout requests and responses (HTTP-Redirect
+    /// binding), if it supports single logout.
+    #[must_use]
+    pub fn idp_slo_url(&self) -> Option<&str> {
+        self.idp_slo_url.as_deref()
+    }
+
+    /// The IdP's signing certificate.
+    #[must_use]
+    pub fn idp_certificate(&self) -> &str {
+        &self.idp_certificate
+    }
+
+    /// Email domains the IdP speaks for.
+    #[must_use]
+    pub fn domains(&self) -> &[String] {
+        &self.domains
+    }
+
+    /// Whether `email` is within [`SamlProviderConfig::domains`], compared
+    /// case-insensitively.
+    #[must_use]
+    pub fn vouches_for(&self, email: &str) -> bool {
+        email.rsplit_once('@').is_some_and(|(_, domain)| {
+            self.domains
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+        })
+    }
+
+    /// Assertion attribute holding the user's email address.
+    #[must_use]
+    pub fn email_attribute(&self) -> &str {
+        &self.email_attribute
+    }
+
+    /// Assertion attribute holding the user's display name.
+    #[must_use]
+    pub fn name_attribute(&self) -> &str {
+        &self.name_attribute
+    }
+}
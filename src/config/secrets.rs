@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::config::{ConfigError, ConfigResult};
+
+/// Settings that may be kept out of the YAML files, by config key.
+///
+/// For each of them `<key>_file`, e.g. `database.password_file` or
+/// `APP_DATABASE__PASSWORD_FILE`, names a file to read the value from, as
+/// mounted by Docker or Kubernetes secrets. A [`SecretProvider`] passed to
+/// [`crate::config::Config::load_with`] is asked for them as well and wins
+/// over both the file and the inline value.
+pub const SECRETS: [&str; 9] = [
+    "database.uri",
+    "database.password",
+    "auth.secret",
+    "auth.signing_key",
+    "mailer.smtp.password",
+    "oidc.signing_key",
+    "captcha.secret",
+    "sms.auth_token",
+    "password_hashing.pepper",
+];
+
+/// A sensitive setting, such as a password or signing key.
+///
+/// `Debug` and `Display` print `[REDACTED]`, so configs holding one can be
+/// logged safely; the value itself is only read with
+/// [`SecretString::expose`].
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    #[must_use]
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret value.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl std::fmt::Display for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+/// Source of [`SECRETS`] outside the configuration files.
+///
+/// [`VaultSecrets`] reads them from HashiCorp Vault; other secret managers
+/// can be plugged in by implementing this trait and loading the
+/// configuration with [`crate::config::Config::load_with`].
+///
+/// ```no_run
+/// use async_trait::async_trait;
+/// use betterauth::config::{Config, ConfigResult, SecretProvider};
+///
+/// struct MySecrets;
+///
+/// #[async_trait]
+/// impl SecretProvider for MySecrets {
+///     async fn secret(&self, key: &str) -> ConfigResult<Option<String>> {
+///         // look `key` up in the secret manager here
+///         Ok(None)
+///     }
+/// }
+///
+/// # async fn example() -> ConfigResult<()> {
+/// let config = Config::load_with(&MySecrets).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    /// Value of the setting at `key`, one of [`SECRETS`], or `None` to keep
+    /// the configured one.
+    ///
+    /// ## Errors
+    /// * [`ConfigError::Secret`] if the secret manager could not be asked
+    async fn secret(&self, key: &str) -> ConfigResult<Option<String>>;
+}
+
+/// Reads secrets from a HashiCorp Vault KV version 2 secret, whose fields
+/// are named after the config keys, e.g. `database.password`.
+///
+/// The secret is fetched once, on the first lookup.
+pub struct VaultSecrets {
+    http: reqwest::Client,
+    url: String,
+    token: String,
+    fields: OnceCell<HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct VaultResponse {
+    data: VaultData,
+}
+
+#[derive(Deserialize)]
+struct VaultData {
+    data: HashMap<String, serde_json::Value>,
+}
+
+impl VaultSecrets {
+    /// Reads the secret at `path` of the KV engine mounted at `mount`, on
+    /// the Vault server at `address`.
+    #[must_use]
+    pub fn new(address: &str, token: String, mount: &str, path: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: format!(
+                "{}/v1/{}/data/{}",
+                address.trim_end_matches('/'),
+                mount.trim_matches('/'),
+                path.trim_matches('/')
+            ),
+            token,
+            fields: OnceCell::new(),
+        }
+    }
+
+    /// Configured by Vault's own `VAULT_ADDR` and `VAULT_TOKEN` variables,
+    /// plus `VAULT_KV_MOUNT` (defaults to `secret`) and `VAULT_SECRET_PATH`
+    /// (defaults to `betterauth`). Returns `None` unless `VAULT_ADDR` is set.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let address = std::env::var("VAULT_ADDR").ok()?;
+        let token = std::env::var("VAULT_TOKEN").unwrap_or_default();
+        let mount = std::env::var("VAULT_KV_MOUNT").unwrap_or_else(|_| String::from("secret"));
+        let path =
+            std::env::var("VAULT_SECRET_PATH").unwrap_or_else(|_| String::from("betterauth"));
+
+        Some(Self::new(&address, token, &mount, &path))
+    }
+
+    async fn fetch(&self) -> ConfigResult<HashMap<String, String>> {
+        let failed = |reason: String| ConfigError::Secret {
+            key: self.url.clone(),
+            reason,
+        };
+
+        let response = self
+            .http
+            .get(&self.url)
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| failed(err.to_string()))?;
+
+        let body: VaultResponse = response
+            .json()
+            .await
+            .map_err(|err| failed(err.to_string()))?;
+
+        Ok(body
+            .data
+            .data
+            .into_iter()
+            .map(|(field, value)| match value {
+                serde_json::Value::String(value) => (field, value),
+                other => (field, other.to_string()),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl SecretProvider for VaultSecrets {
+    async fn secret(&self, key: &str) -> ConfigResult<Option<String>> {
+        let fields = self.fields.get_or_try_init(|| self.fetch()).await?;
+
+        Ok(fields.get(key).cloned())
+    }
+}
+
+/// The section `key` belongs to, e.g. `mailer.smtp` for
+/// `mailer.smtp.password`.
+pub(super) fn section(key: &str) -> &str {
+    key.rsplit_once('.').map_or(key, |(section, _)| section)
+}
+
+/// Reads the secret file named by `<key>_file`, without the trailing
+/// newline most tools write.
+pub(super) fn read_file(key: &str, path: &str) -> ConfigResult<String> {
+    std::fs::read_to_string(path)
+        .map(|value| value.trim_end_matches(['\r', '\n']).to_owned())
+        .map_err(|err| ConfigError::Secret {
+            key: format!("{key}_file"),
+            reason: format!("failed to read {path}: {err}"),
+        })
+}
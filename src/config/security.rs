@@ -0,0 +1,274 @@
+use serde::Deserialize;
+
+/// What part of the client address a session is bound to.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IpBinding {
+    /// The address may change.
+    #[default]
+    Off,
+    /// The exact address the session was created from.
+    Exact,
+    /// Its network, the `/24` of an IPv4 address or the `/48` of an IPv6
+    /// one, so that clients moving within it stay logged in.
+    Network,
+}
+
+/// What happens to a session used by a client it is not bound to.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BindingAction {
+    /// The session is ended, logging out whoever holds it.
+    #[default]
+    Reject,
+    /// Requests get `403 Forbidden` with the `reauthentication_required`
+    /// code until `POST /auth/reauthenticate` binds the session to the new
+    /// client.
+    Reauthenticate,
+}
+
+/// Binds session cookies to the client they were created for, so that a
+/// stolen cookie cannot be replayed from elsewhere.
+///
+/// Sessions are bound to the address they were created from with `ip`, and
+/// to the `User-Agent` that created them with `user_agent`; `on_change`
+/// decides what happens when either changes. Requests whose address is
+/// unknown are not checked against it. Access tokens are short-lived and
+/// not bound.
+///
+/// ```yaml
+/// security:
+///   session_binding:
+///     ip: "network" # off, exact or network
+///     user_agent: true
+///     on_change: "reauthenticate" # reject or reauthenticate
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SessionBinding {
+    #[serde(default)]
+    ip: IpBinding,
+    #[serde(default)]
+    user_agent: bool,
+    #[serde(default)]
+    on_change: BindingAction,
+}
+
+impl SessionBinding {
+    /// Defaults to [`IpBinding::Off`].
+    #[must_use]
+    pub fn ip(&self) -> IpBinding {
+        self.ip
+    }
+
+    /// Whether the `User-Agent` may not change. Defaults to `false`.
+    #[must_use]
+    pub fn user_agent(&self) -> bool {
+        self.user_agent
+    }
+
+    /// Defaults to [`BindingAction::Reject`].
+    #[must_use]
+    pub fn on_change(&self) -> BindingAction {
+        self.on_change
+    }
+}
+
+/// Brute-force protection for password logins.
+///
+/// An account is locked for `lockout_duration` after `max_failed_logins`
+/// wrong passwords in a row. Independently, a client IP that produces
+/// `max_failed_logins_per_ip` failures within `ip_window` is turned away
+/// until older failures fall out of the window.
+///
+/// Below both limits, failures for the same email or username from the
+/// same IP slow further attempts down: after `login_delay_after` of them
+/// within `login_delay_window`, the next attempt has to wait `login_delay`
+/// seconds after the last failure, doubling with every further failure up to
+/// `max_login_delay`. Attempts made too early get `429 Too Many Requests`
+/// with a `Retry-After` header. A `login_delay` of 0 turns this off.
+///
+/// With `csrf_protection` on, state-changing requests authenticated by the
+/// session cookie must send the session's CSRF token in `X-CSRF-Token`.
+///
+/// Logins from a device or location new to the account are audited and,
+/// with `notify_suspicious_login`, emailed to the user.
+///
+/// Session cookies can be bound to their client with `session_binding`, see
+/// [`SessionBinding`].
+///
+/// ```yaml
+/// security:
+///   max_failed_logins: 5
+///   lockout_duration: 900 # seconds
+///   max_failed_logins_per_ip: 20
+///   ip_window: 900 # seconds
+///   login_delay_after: 3
+///   login_delay_window: 900 # seconds
+///   login_delay: 1 # seconds
+///   max_login_delay: 300 # seconds
+///   csrf_protection: true
+///   notify_suspicious_login: true
+///   session_binding:
+///     ip: "off"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct SecurityConfig {
+    #[serde(default = "default_max_failed_logins")]
+    max_failed_logins: u32,
+    #[serde(default = "default_lockout_duration")]
+    lockout_duration: u64,
+    #[serde(default = "default_max_failed_logins_per_ip")]
+    max_failed_logins_per_ip: u32,
+    #[serde(default = "default_ip_window")]
+    ip_window: u64,
+    #[serde(default = "default_login_delay_after")]
+    login_delay_after: u32,
+    #[serde(default = "default_login_delay_window")]
+    login_delay_window: u64,
+    #[serde(default = "default_login_delay")]
+    login_delay: u64,
+    #[serde(default = "default_max_login_delay")]
+    max_login_delay: u64,
+    #[serde(default = "default_csrf_protection")]
+    csrf_protection: bool,
+    #[serde(default = "default_notify_suspicious_login")]
+    notify_suspicious_login: bool,
+    #[serde(default)]
+    session_binding: SessionBinding,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            max_failed_logins: default_max_failed_logins(),
+            lockout_duration: default_lockout_duration(),
+            max_failed_logins_per_ip: default_max_failed_logins_per_ip(),
+            ip_window: default_ip_window(),
+            login_delay_after: default_login_delay_after(),
+            login_delay_window: default_login_delay_window(),
+            login_delay: default_login_delay(),
+            max_login_delay: default_max_login_delay(),
+            csrf_protection: default_csrf_protection(),
+            notify_suspicious_login: default_notify_suspicious_login(),
+            session_binding: SessionBinding::default(),
+        }
+    }
+}
+
+fn default_max_failed_logins() -> u32 {
+    5
+}
+
+fn default_lockout_duration() -> u64 {
+    15 * 60
+}
+
+fn default_max_failed_logins_per_ip() -> u32 {
+    20
+}
+
+fn default_ip_window() -> u64 {
+    15 * 60
+}
+
+fn default_login_delay_after() -> u32 {
+    3
+}
+
+fn default_login_delay_window() -> u64 {
+    15 * 60
+}
+
+fn default_login_delay() -> u64 {
+    1
+}
+
+fn default_max_login_delay() -> u64 {
+    5 * 60
+}
+
+fn default_csrf_protection() -> bool {
+    true
+}
+
+fn default_notify_suspicious_login() -> bool {
+    true
+}
+
+impl SecurityConfig {
+    /// Consecutive wrong passwords that lock an account. Defaults to 5.
+    #[must_use]
+    pub fn max_failed_logins(&self) -> u32 {
+        self.max_failed_logins
+    }
+
+    /// How long a locked account stays locked, in seconds. Defaults to 15
+    /// minutes.
+    #[must_use]
+    pub fn lockout_duration(&self) -> u64 {
+        self.lockout_duration
+    }
+
+    /// Failed logins from one IP within [`Self::ip_window`] before further
+    /// attempts are refused. Defaults to 20.
+    #[must_use]
+    pub fn max_failed_logins_per_ip(&self) -> u32 {
+        self.max_failed_logins_per_ip
+    }
+
+    /// Sliding window failures per IP are counted over, in seconds. Defaults
+    /// to 15 minutes.
+    #[must_use]
+    pub fn ip_window(&self) -> u64 {
+        self.ip_window
+    }
+
+    /// Failures for the same identifier and IP within
+    /// [`Self::login_delay_window`] before attempts are delayed. Defaults to
+    /// 3.
+    #[must_use]
+    pub fn login_delay_after(&self) -> u32 {
+        self.login_delay_after
+    }
+
+    /// Sliding window failures per identifier and IP are counted over, in
+    /// seconds. Defaults to 15 minutes.
+    #[must_use]
+    pub fn login_delay_window(&self) -> u64 {
+        self.login_delay_window
+    }
+
+    /// First delay between attempts, in seconds; 0 turns delays off.
+    /// Defaults to 1 second.
+    #[must_use]
+    pub fn login_delay(&self) -> u64 {
+        self.login_delay
+    }
+
+    /// Longest delay between attempts, in seconds. Defaults to 5 minutes.
+    #[must_use]
+    pub fn max_login_delay(&self) -> u64 {
+        self.max_login_delay
+    }
+
+    /// Whether cookie-authenticated requests need a CSRF token. Defaults to
+    /// `true`.
+    #[must_use]
+    pub fn csrf_protection(&self) -> bool {
+        self.csrf_protection
+    }
+
+    /// Whether users are emailed about logins from a new device or
+    /// location. Defaults to `true`.
+    #[must_use]
+    pub fn notify_suspicious_login(&self) -> bool {
+        self.notify_suspicious_login
+    }
+
+    /// How session cookies are bound to their client. Binds them to nothing
+    /// by default.
+    #[must_use]
+    pub fn session_binding(&self) -> &SessionBinding {
+        &self.session_binding
+    }
+}
@@ -1,14 +1,400 @@
-use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer};
+use tower_http::{compression::CompressionLayer, decompression::RequestDecompressionLayer};
 
 /// Server configuration for network binding and URL generation.
 ///
 /// Contains the protocol, host, and port settings for the application server.
-/// Used to generate bind addresses and public URLs.
+/// Used to generate bind addresses and public URLs. With a `tls` section the
+/// server terminates HTTPS itself; without one, `protocol: https` assumes a
+/// proxy in front does.
+///
+/// `host` and `port` are bound unless `listeners` are given, in which case
+/// those are bound instead and `host` and `port` only make up the public
+/// [`ServerConfig::url`].
+///
+/// `base_path` is the prefix the routes are served under when another axum
+/// application mounts them, see [`crate::App::router`], e.g. `/identity`.
+/// It ends the public URL, so links in emails and the URLs given to identity
+/// providers point at the mounted routes.
+///
+/// With an `admin` section the admin API, `/metrics` and `/health/*` are
+/// served on a port of their own, see [`AdminListenerConfig`], and `host`
+/// and `port` only serve the public routes.
+///
+/// On SIGINT or SIGTERM the server stops accepting connections and gives
+/// open ones and background work `shutdown_timeout` seconds to finish,
+/// 30 by default.
+///
+/// Requests taking longer than `request_timeout` seconds, 30 by default, are
+/// answered with `408 Request Timeout`. `route_timeouts` overrides it per
+/// route group, the first segment of the path as for rate limits. Bodies
+/// over `body_limit` bytes, 1 MiB by default, are refused with
+/// `413 Payload Too Large`.
+///
+/// Responses are compressed with gzip, brotli or zstd when the client
+/// accepts one of them, unless `compression` is `false`, and request bodies
+/// sent with one of these encodings are decompressed before `body_limit`
+/// is applied, unless `decompression` is `false`.
+///
+/// `concurrency` caps how many requests are handled at once, see
+/// [`ConcurrencyConfig`].
+///
+/// Behind a load balancer or reverse proxy, its addresses or networks go in
+/// `trusted_proxies`: the client IP logged, rate limited and recorded in the
+/// audit log is then taken from the `Forwarded` or `X-Forwarded-For` header
+/// of requests coming through them, see [`crate::security::ClientIp`].
+/// Without it these headers are ignored, as anyone can send them.
+///
+/// ```yaml
+/// server:
+///   base_path: "/identity"
+///   trusted_proxies: ["10.0.0.0/8", "192.0.2.7"]
+///   request_timeout: 10
+///   route_timeouts:
+///     admin: 120
+///   body_limit: 65536
+///   compression: true
+///   decompression: false
+/// ```
 #[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ServerConfig {
     protocol: String,
     host: String,
     port: u16,
+    #[serde(default)]
+    base_path: String,
+    #[serde(default)]
+    tls: Option<TlsConfig>,
+    #[serde(default)]
+    listeners: Vec<ListenerConfig>,
+    #[serde(default)]
+    admin: Option<AdminListenerConfig>,
+    #[serde(default = "default_shutdown_timeout")]
+    shutdown_timeout: u64,
+    #[serde(default = "default_request_timeout")]
+    request_timeout: u64,
+    #[serde(default)]
+    route_timeouts: HashMap<String, u64>,
+    #[serde(default = "default_body_limit")]
+    body_limit: usize,
+    #[serde(default = "default_compression")]
+    compression: bool,
+    #[serde(default = "default_compression")]
+    decompression: bool,
+    #[serde(default)]
+    concurrency: ConcurrencyConfig,
+    #[serde(default, deserialize_with = "networks")]
+    trusted_proxies: Vec<IpNet>,
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_request_timeout() -> u64 {
+    30
+}
+
+fn default_body_limit() -> usize {
+    1024 * 1024
+}
+
+fn default_compression() -> bool {
+    true
+}
+
+/// Networks in CIDR notation, or single addresses.
+fn networks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<IpNet>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|network| {
+            let network = network.trim();
+            network
+                .parse::<IpNet>()
+                .or_else(|_| network.parse::<std::net::IpAddr>().map(IpNet::from))
+                .map_err(|_| {
+                    serde::de::Error::custom(format!(
+                        "invalid network {network:?}, expected e.g. \"10.0.0.0/8\" or \"192.0.2.7\""
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Caps on the requests handled at once, so a flood of expensive ones, such
+/// as logins hashing passwords, cannot exhaust the server.
+///
+/// `max_requests` caps every request together, `groups` those of a route
+/// group, as for rate limits, and `endpoints` those of a single route, by
+/// its path without the `/api/{version}` prefix. A request over any cap is
+/// not queued but shed at once with `503 Service Unavailable` and a
+/// `Retry-After` of `retry_after` seconds, 1 by default. Nothing is capped
+/// without this section, and the health checks and `/metrics` never are.
+/// Changes take effect on restart.
+///
+/// ```yaml
+/// server:
+///   concurrency:
+///     max_requests: 1024
+///     groups:
+///       admin: 16
+///     endpoints:
+///       /auth/login: 64
+///       /auth/register: 32
+///     retry_after: 2
+/// ```
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ConcurrencyConfig {
+    #[serde(default)]
+    max_requests: Option<usize>,
+    #[serde(default)]
+    groups: HashMap<String, usize>,
+    #[serde(default)]
+    endpoints: HashMap<String, usize>,
+    #[serde(default = "default_retry_after")]
+    retry_after: u64,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_requests: None,
+            groups: HashMap::new(),
+            endpoints: HashMap::new(),
+            retry_after: default_retry_after(),
+        }
+    }
+}
+
+fn default_retry_after() -> u64 {
+    1
+}
+
+impl ConcurrencyConfig {
+    /// Most requests handled at once in all, if capped.
+    #[must_use]
+    pub fn max_requests(&self) -> Option<usize> {
+        self.max_requests
+    }
+
+    /// Most requests handled at once by route group.
+    #[must_use]
+    pub fn groups(&self) -> &HashMap<String, usize> {
+        &self.groups
+    }
+
+    /// Most requests handled at once by endpoint path.
+    #[must_use]
+    pub fn endpoints(&self) -> &HashMap<String, usize> {
+        &self.endpoints
+    }
+
+    /// Seconds shed requests are told to wait before retrying. Defaults
+    /// to 1.
+    #[must_use]
+    pub fn retry_after(&self) -> u64 {
+        self.retry_after
+    }
+
+    /// The route group `path` falls in, if it is capped.
+    #[must_use]
+    pub fn group(&self, path: &str) -> Option<&str> {
+        self.groups
+            .get_key_value(super::route_group(path))
+            .map(|(group, _)| group.as_str())
+    }
+
+    /// The endpoint `path` is, if it is capped on its own.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use betterauth::config::ConcurrencyConfig;
+    /// let config: ConcurrencyConfig = serde_json::from_value(serde_json::json!({
+    ///     "endpoints": { "/auth/login": 64 },
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(config.endpoint("/api/v1/auth/login"), Some("/auth/login"));
+    /// assert_eq!(config.endpoint("/auth/login/"), Some("/auth/login"));
+    /// assert_eq!(config.endpoint("/auth/register"), None);
+    /// ```
+    #[must_use]
+    pub fn endpoint(&self, path: &str) -> Option<&str> {
+        let path = super::unversioned_path(path);
+        let path = path
+            .strip_suffix('/')
+            .filter(|path| !path.is_empty())
+            .unwrap_or(path);
+
+        self.endpoints
+            .get_key_value(path)
+            .map(|(endpoint, _)| endpoint.as_str())
+    }
+}
+
+/// Certificate the server presents when it terminates TLS.
+///
+/// ```yaml
+/// server:
+///   protocol: https
+///   tls:
+///     cert_path: /etc/betterauth/tls/fullchain.pem
+///     key_path: /etc/betterauth/tls/privkey.pem
+/// ```
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl TlsConfig {
+    /// PEM file with the certificate chain, leaf first.
+    #[must_use]
+    pub fn cert_path(&self) -> &Path {
+        &self.cert_path
+    }
+
+    /// PEM file with the private key of the leaf certificate.
+    #[must_use]
+    pub fn key_path(&self) -> &Path {
+        &self.key_path
+    }
+}
+
+/// Routes served on a listener.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Routes {
+    /// Every route, including the admin API, `/metrics` and `/health/*`.
+    #[default]
+    All,
+    /// Every route except the admin API, `/metrics` and `/health/*`. The
+    /// health checks stay when no listener serves the admin routes.
+    Public,
+    /// Only the admin API, `/metrics` and `/health/*`, e.g. on a port kept
+    /// off the public network.
+    Admin,
+}
+
+impl Display for Routes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::All => "all",
+            Self::Public => "public",
+            Self::Admin => "admin",
+        })
+    }
+}
+
+/// An address the server listens on, see [`ServerConfig`].
+///
+/// An IPv6 listener only accepts IPv6 connections, so dual-stack serving
+/// binds both `0.0.0.0` and `::` on the same port:
+///
+/// ```yaml
+/// server:
+///   listeners:
+///     - host: 0.0.0.0
+///       port: 8080
+///       routes: public
+///     - host: "::"
+///       port: 8080
+///       routes: public
+///     - host: 127.0.0.1
+///       port: 9090
+///       routes: admin
+///       tls: false
+/// ```
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ListenerConfig {
+    host: String,
+    port: u16,
+    #[serde(default)]
+    routes: Routes,
+    #[serde(default = "default_listener_tls")]
+    tls: bool,
+}
+
+fn default_listener_tls() -> bool {
+    true
+}
+
+/// The internal port the operational endpoints are served on, see
+/// [`ServerConfig`]: the admin API and dashboard, `/metrics`, and
+/// `/health/live` and `/health/ready`, which probes must then target.
+///
+/// It listens on `host`, `127.0.0.1` by default, and speaks plain HTTP even
+/// when `server.tls` is set, unless `tls` is `true`.
+///
+/// ```yaml
+/// server:
+///   host: 0.0.0.0
+///   port: 8080
+///   admin:
+///     host: 0.0.0.0 # reachable by the cluster, not published
+///     port: 9090
+/// ```
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct AdminListenerConfig {
+    #[serde(default = "default_admin_host")]
+    host: String,
+    port: u16,
+    #[serde(default)]
+    tls: bool,
+}
+
+fn default_admin_host() -> String {
+    String::from("127.0.0.1")
+}
+
+impl AdminListenerConfig {
+    /// Defaults to `127.0.0.1`.
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Whether `server.tls`, when set, applies here. Defaults to `false`.
+    #[must_use]
+    pub fn tls(&self) -> bool {
+        self.tls
+    }
+}
+
+impl ListenerConfig {
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Routes served here. Defaults to [`Routes::All`].
+    #[must_use]
+    pub fn routes(&self) -> Routes {
+        self.routes
+    }
+
+    /// Whether `server.tls`, when set, applies here. Defaults to `true`.
+    #[must_use]
+    pub fn tls(&self) -> bool {
+        self.tls
+    }
 }
 
 impl ServerConfig {
@@ -19,17 +405,30 @@ impl ServerConfig {
     ///
     /// ## Examples
     /// ```
-    /// # use server::ServerConfig;
-    /// let config = ServerConfig {
-    ///     protocol: "http".to_string(),
-    ///     host: "0.0.0.0".to_string(),
-    ///     port: 8080,
-    /// };
+    /// # use betterauth::config::ServerConfig;
+    /// let config: ServerConfig = serde_json::from_value(serde_json::json!({
+    ///     "protocol": "http",
+    ///     "host": "0.0.0.0",
+    ///     "port": 8080,
+    /// }))
+    /// .unwrap();
     /// assert_eq!(config.url(), "http://0.0.0.0:8080");
+    ///
+    /// let config: ServerConfig = serde_json::from_value(serde_json::json!({
+    ///     "protocol": "https",
+    ///     "host": "example.com",
+    ///     "port": 443,
+    ///     "base_path": "/identity",
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(config.url(), "https://example.com:443/identity");
     /// ```
     #[must_use]
     pub fn url(&self) -> String {
-        format!("{}://{}:{}", &self.protocol, &self.host, self.port)
+        format!(
+            "{}://{}:{}{}",
+            &self.protocol, &self.host, self.port, &self.base_path
+        )
     }
 
     /// Generates the server bind address without protocol.
@@ -40,16 +439,187 @@ impl ServerConfig {
     ///
     /// ## Examples
     /// ```
-    /// # use server::ServerConfig;
-    /// let config = ServerConfig {
-    ///     protocol: "http".to_string(),
-    ///     host: "127.0.0.1".to_string(),
-    ///     port: 3000,
-    /// };
+    /// # use betterauth::config::ServerConfig;
+    /// let config: ServerConfig = serde_json::from_value(serde_json::json!({
+    ///     "protocol": "http",
+    ///     "host": "127.0.0.1",
+    ///     "port": 3000,
+    /// }))
+    /// .unwrap();
     /// assert_eq!(config.address(), "127.0.0.1:3000");
     /// ```
     #[must_use]
     pub fn address(&self) -> String {
         format!("{}:{}", &self.host, self.port)
     }
+
+    /// Prefix the routes are mounted under, e.g. `/identity`. Defaults to
+    /// none.
+    #[must_use]
+    pub fn base_path(&self) -> &str {
+        &self.base_path
+    }
+
+    /// `http` or `https`.
+    #[must_use]
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// How long to drain connections and background work on shutdown, in
+    /// seconds.
+    #[must_use]
+    pub fn shutdown_timeout(&self) -> u64 {
+        self.shutdown_timeout
+    }
+
+    /// Seconds a request may take unless its route group sets its own.
+    #[must_use]
+    pub fn request_timeout(&self) -> u64 {
+        self.request_timeout
+    }
+
+    /// Timeouts in seconds by route group.
+    #[must_use]
+    pub fn route_timeouts(&self) -> &HashMap<String, u64> {
+        &self.route_timeouts
+    }
+
+    /// How long a request to `path` may take: the timeout of its route
+    /// group, or `request_timeout`.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// # use betterauth::config::ServerConfig;
+    /// let config: ServerConfig = serde_json::from_value(serde_json::json!({
+    ///     "protocol": "http",
+    ///     "host": "127.0.0.1",
+    ///     "port": 3000,
+    ///     "route_timeouts": { "admin": 120 },
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(config.timeout_for("/admin/users"), Duration::from_secs(120));
+    /// assert_eq!(config.timeout_for("/api/v1/admin/users"), Duration::from_secs(120));
+    /// assert_eq!(config.timeout_for("/auth/login"), Duration::from_secs(30));
+    /// ```
+    #[must_use]
+    pub fn timeout_for(&self, path: &str) -> Duration {
+        let segment = super::route_group(path);
+
+        Duration::from_secs(
+            self.route_timeouts
+                .get(segment)
+                .copied()
+                .unwrap_or(self.request_timeout),
+        )
+    }
+
+    /// Largest request body accepted, in bytes.
+    #[must_use]
+    pub fn body_limit(&self) -> usize {
+        self.body_limit
+    }
+
+    /// Caps on the requests handled at once.
+    #[must_use]
+    pub fn concurrency(&self) -> &ConcurrencyConfig {
+        &self.concurrency
+    }
+
+    /// Proxies whose forwarding headers are believed. Defaults to none.
+    #[must_use]
+    pub fn trusted_proxies(&self) -> &[IpNet] {
+        &self.trusted_proxies
+    }
+
+    /// Whether responses are compressed for clients accepting it.
+    #[must_use]
+    pub fn compression(&self) -> bool {
+        self.compression
+    }
+
+    /// Whether compressed request bodies are accepted.
+    #[must_use]
+    pub fn decompression(&self) -> bool {
+        self.decompression
+    }
+
+    /// Builds the layer compressing responses with gzip, brotli or zstd,
+    /// which passes them through untouched when `compression` is off.
+    #[must_use]
+    pub fn compression_layer(&self) -> CompressionLayer {
+        CompressionLayer::new()
+            .gzip(self.compression)
+            .br(self.compression)
+            .zstd(self.compression)
+    }
+
+    /// Builds the layer decompressing gzip, brotli or zstd request bodies.
+    /// With `decompression` off, such bodies are refused with
+    /// `415 Unsupported Media Type`.
+    #[must_use]
+    pub fn decompression_layer(&self) -> RequestDecompressionLayer {
+        RequestDecompressionLayer::new()
+            .gzip(self.decompression)
+            .br(self.decompression)
+            .zstd(self.decompression)
+    }
+
+    /// The addresses to bind: `listeners`, or `host` and `port` when there
+    /// are none, serving [`Routes::Public`] if there is an `admin` listener,
+    /// which comes last, and [`Routes::All`] otherwise.
+    #[must_use]
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        let mut listeners = if self.listeners.is_empty() {
+            vec![ListenerConfig {
+                host: self.host.clone(),
+                port: self.port,
+                routes: if self.admin.is_some() {
+                    Routes::Public
+                } else {
+                    Routes::All
+                },
+                tls: true,
+            }]
+        } else {
+            self.listeners.clone()
+        };
+
+        if let Some(admin) = &self.admin {
+            listeners.push(ListenerConfig {
+                host: admin.host.clone(),
+                port: admin.port,
+                routes: Routes::Admin,
+                tls: admin.tls,
+            });
+        }
+
+        listeners
+    }
+
+    /// The internal listener of the operational endpoints, if they have
+    /// their own port.
+    #[must_use]
+    pub fn admin(&self) -> Option<&AdminListenerConfig> {
+        self.admin.as_ref()
+    }
+
+    /// TLS settings, if the server terminates HTTPS itself.
+    #[must_use]
+    pub fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    /// Whether the server is reached over HTTPS, in which case cookies are
+    /// marked `Secure`.
+    #[must_use]
+    pub fn is_https(&self) -> bool {
+        self.protocol.eq_ignore_ascii_case("https")
+    }
 }
@@ -0,0 +1,134 @@
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// Service delivering text messages.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsProvider {
+    /// Twilio's Messages API; needs `account_sid`, `auth_token` and `from`.
+    Twilio,
+    /// Writes messages to the log instead of sending them, for development.
+    Log,
+}
+
+/// Phone number verification and one-time code login.
+///
+/// With this section present users can attach a phone number to their
+/// account and log in with codes sent to it by text message; without it the
+/// `/auth/phone` endpoints answer `404 Not Found`.
+///
+/// Codes expire after `code_ttl` and are burned after `max_attempts` wrong
+/// guesses. A number gets at most one code per `resend_interval` and
+/// `max_sends_per_hour` codes per hour.
+///
+/// ```yaml
+/// sms:
+///   provider: "twilio" # twilio or log
+///   account_sid: "AC..."
+///   auth_token: "..."
+///   from: "+15551234567"
+///   code_ttl: 300 # seconds
+///   max_attempts: 5
+///   resend_interval: 60 # seconds
+///   max_sends_per_hour: 5
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct SmsConfig {
+    provider: SmsProvider,
+    #[serde(default)]
+    account_sid: Option<String>,
+    #[serde(default)]
+    auth_token: Option<SecretString>,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default = "default_code_ttl")]
+    code_ttl: u64,
+    #[serde(default = "default_max_attempts")]
+    max_attempts: i32,
+    #[serde(default = "default_resend_interval")]
+    resend_interval: u64,
+    #[serde(default = "default_max_sends_per_hour")]
+    max_sends_per_hour: i64,
+}
+
+impl Default for SmsConfig {
+    fn default() -> Self {
+        Self {
+            provider: SmsProvider::Log,
+            account_sid: None,
+            auth_token: None,
+            from: None,
+            code_ttl: default_code_ttl(),
+            max_attempts: default_max_attempts(),
+            resend_interval: default_resend_interval(),
+            max_sends_per_hour: default_max_sends_per_hour(),
+        }
+    }
+}
+
+fn default_code_ttl() -> u64 {
+    5 * 60
+}
+
+fn default_max_attempts() -> i32 {
+    5
+}
+
+fn default_resend_interval() -> u64 {
+    60
+}
+
+fn default_max_sends_per_hour() -> i64 {
+    5
+}
+
+impl SmsConfig {
+    #[must_use]
+    pub fn provider(&self) -> SmsProvider {
+        self.provider
+    }
+
+    /// Twilio account SID.
+    #[must_use]
+    pub fn account_sid(&self) -> Option<&str> {
+        self.account_sid.as_deref()
+    }
+
+    /// Twilio auth token.
+    #[must_use]
+    pub fn auth_token(&self) -> Option<&SecretString> {
+        self.auth_token.as_ref()
+    }
+
+    /// Number or sender ID messages are sent from.
+    #[must_use]
+    pub fn from(&self) -> Option<&str> {
+        self.from.as_deref()
+    }
+
+    /// How long a code may be used, in seconds. Defaults to 5 minutes.
+    #[must_use]
+    pub fn code_ttl(&self) -> u64 {
+        self.code_ttl
+    }
+
+    /// Wrong guesses after which a code stops working. Defaults to 5.
+    #[must_use]
+    pub fn max_attempts(&self) -> i32 {
+        self.max_attempts
+    }
+
+    /// Least time between two codes sent to a number, in seconds. Defaults
+    /// to 1 minute.
+    #[must_use]
+    pub fn resend_interval(&self) -> u64 {
+        self.resend_interval
+    }
+
+    /// Most codes sent to a number per hour. Defaults to 5.
+    #[must_use]
+    pub fn max_sends_per_hour(&self) -> i64 {
+        self.max_sends_per_hour
+    }
+}
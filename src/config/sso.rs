@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// Single sign-on across the first-party applications of this server.
+///
+/// With this section present, every application in `apps` shares the login
+/// of this server; without it the single sign-on endpoints answer
+/// `404 Not Found`. Access tokens issued to an application carry its id in
+/// the `aud` claim, which the application must check so tokens issued to
+/// another one are refused. This server refuses them all as credentials for
+/// its own routes.
+///
+/// Applications on subdomains of a common parent share the session cookie
+/// once `cookies.domain` is set to that parent, and get tokens from
+/// `POST /auth/sso/token`, which answers only pages served from one of the
+/// application's `origins`. Applications on other domains send users to
+/// `GET /auth/authorize` instead, which hands a one-time code back to one
+/// of their `redirect_urls`. When `login_url` is set, users reaching it
+/// without a session are sent there with a `return_to` parameter instead of
+/// getting `401 Unauthorized`.
+///
+/// ```yaml
+/// sso:
+///   login_url: "https://accounts.example.com/login"
+///   code_ttl: 60 # seconds
+///   apps:
+///     billing:
+///       origins: ["https://billing.example.com"]
+///     partner:
+///       redirect_urls: ["https://partner.example.org/auth/callback"]
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct SsoConfig {
+    #[serde(default)]
+    login_url: Option<String>,
+    #[serde(default = "default_code_ttl")]
+    code_ttl: u64,
+    #[serde(default)]
+    apps: HashMap<String, SsoApp>,
+}
+
+fn default_code_ttl() -> u64 {
+    60
+}
+
+impl SsoConfig {
+    /// Page to send users without a session to.
+    #[must_use]
+    pub fn login_url(&self) -> Option<&str> {
+        self.login_url.as_deref()
+    }
+
+    /// How long an authorization code may be redeemed, in seconds. Defaults
+    /// to 1 minute.
+    #[must_use]
+    pub fn code_ttl(&self) -> u64 {
+        self.code_ttl
+    }
+
+    /// Applications sharing the login, keyed by the id tokens name them by.
+    #[must_use]
+    pub fn apps(&self) -> &HashMap<String, SsoApp> {
+        &self.apps
+    }
+
+    /// The application `id`, if registered.
+    #[must_use]
+    pub fn app(&self, id: &str) -> Option<&SsoApp> {
+        self.apps.get(id)
+    }
+}
+
+/// An application sharing the login of this server.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SsoApp {
+    #[serde(default)]
+    origins: Vec<String>,
+    #[serde(default)]
+    redirect_urls: Vec<String>,
+}
+
+impl SsoApp {
+    /// Origins of the pages that may get tokens with the shared session
+    /// cookie, e.g. `https://billing.example.com`.
+    #[must_use]
+    pub fn origins(&self) -> &[String] {
+        &self.origins
+    }
+
+    /// URLs codes from `GET /auth/authorize` may be sent back to.
+    #[must_use]
+    pub fn redirect_urls(&self) -> &[String] {
+        &self.redirect_urls
+    }
+
+    /// Whether `origin` is one of [`Self::origins`].
+    #[must_use]
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.origins.iter().any(|allowed| allowed == origin)
+    }
+
+    /// Whether `url` is exactly one of [`Self::redirect_urls`].
+    #[must_use]
+    pub fn allows_redirect(&self, url: &str) -> bool {
+        self.redirect_urls.iter().any(|allowed| allowed == url)
+    }
+}
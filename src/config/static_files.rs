@@ -0,0 +1,66 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Files served from a directory, such as a login and consent frontend
+/// shipped with the server.
+///
+/// The files under `dir` are served at `path` by the listeners serving the
+/// public routes. The API routes come first, so no file can shadow one.
+///
+/// With `spa` on, `GET` requests for a path without a file get `index`
+/// instead, so a single-page app can route them in the browser. Paths under
+/// `/api/` still get `404 Not Found`.
+///
+/// ```yaml
+/// static_files:
+///   dir: "./public"
+///   path: "/"
+///   spa: true
+///   index: "index.html"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct StaticFilesConfig {
+    dir: PathBuf,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    spa: bool,
+    #[serde(default = "default_index")]
+    index: String,
+}
+
+fn default_path() -> String {
+    String::from("/")
+}
+
+fn default_index() -> String {
+    String::from("index.html")
+}
+
+impl StaticFilesConfig {
+    /// Directory the files are read from.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path the files are served at. Defaults to `/`.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Whether paths without a file get [`Self::index`]. Defaults to off.
+    #[must_use]
+    pub fn spa(&self) -> bool {
+        self.spa
+    }
+
+    /// File in [`Self::dir`] holding the page of the single-page app.
+    /// Defaults to `index.html`.
+    #[must_use]
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+}
@@ -2,25 +2,57 @@ use std::{
     env::VarError,
     error::Error as _,
     fmt::{self, Display},
+    fs::OpenOptions,
     io::IsTerminal,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::OnceLock,
 };
 
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+};
+use sentry::{ClientInitGuard, ClientOptions, types::Dsn};
 use serde::{Deserialize, Serialize};
-use tracing::Subscriber;
+use tracing::{Subscriber, log::LevelFilter};
+use tracing_appender::{
+    non_blocking::{NonBlocking, WorkerGuard},
+    rolling::{RollingFileAppender, Rotation},
+};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{
-    EnvFilter, Layer, filter::Directive, fmt::Layer as FmtLayer, layer::SubscriberExt,
-    registry::LookupSpan, util::SubscriberInitExt,
+    EnvFilter, Layer,
+    filter::Directive,
+    fmt::{
+        Layer as FmtLayer,
+        format::{DefaultFields, Format as FmtFormat},
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
 };
 
 use super::{ConfigError, ConfigResult};
+use crate::trace::QueryAccounting;
+
+type ReloadFilter = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Swaps the filter of the subscriber installed by [`Logger::setup`].
+static RELOAD_FILTER: OnceLock<ReloadFilter> = OnceLock::new();
+
+/// Exports spans when `logger.otlp` is set, see [`Logger::flush`].
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
 
 /// Logging level configuration.
 ///
 /// Determines the minimum severity level for log messages to be recorded.
-/// Can be configured via YAML or environment variables.
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+/// Can be configured via YAML, environment variables or `--log-level`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, clap::ValueEnum)]
 pub enum Level {
     #[serde(rename = "off")]
     Off,
@@ -54,6 +86,19 @@ impl Display for Level {
     }
 }
 
+impl From<&Level> for LevelFilter {
+    fn from(level: &Level) -> Self {
+        match level {
+            Level::Off => Self::Off,
+            Level::Trace => Self::Trace,
+            Level::Debug => Self::Debug,
+            Level::Info => Self::Info,
+            Level::Warn => Self::Warn,
+            Level::Error => Self::Error,
+        }
+    }
+}
+
 /// Log output format configuration.
 ///
 /// Determines how log messages are formatted when written to output.
@@ -85,16 +130,165 @@ impl Display for Format {
     }
 }
 
+/// Where log output is written.
+///
+/// `file` appends to `logger.file`; `daily` starts a new file every day,
+/// named after `logger.file` with the date appended, e.g.
+/// `logs/betterauth.log.2025-01-31`. Writes happen on a background thread,
+/// see [`LogGuard`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Writer {
+    #[default]
+    Stdout,
+    Stderr,
+    File,
+    Daily,
+}
+
+impl Display for Writer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Stdout => "stdout",
+                Self::Stderr => "stderr",
+                Self::File => "file",
+                Self::Daily => "daily",
+            }
+        )
+    }
+}
+
+/// Keeps the background thread writing logs alive. Lines still buffered are
+/// written out when it is dropped, so hold it until the application exits.
+#[must_use = "logs are no longer written once the guard is dropped"]
+pub struct LogGuard {
+    _guard: WorkerGuard,
+    _sentry: Option<ClientInitGuard>,
+}
+
 /// Logger configuration for the application.
 ///
 /// Configures the tracing subscriber with the specified level, format,
 /// and per-crate log directives. Supports environment variable overrides
-/// via `RUST_LOG`.
+/// via `RUST_LOG`. With `otlp` set, the same spans are also exported to an
+/// OpenTelemetry collector, and with `sentry` set, errors and panics are
+/// reported to Sentry.
 #[derive(Debug, Deserialize, Clone)]
 pub struct Logger {
     level: Level,
     format: Format,
     crates: Vec<String>,
+    #[serde(default)]
+    writer: Writer,
+    #[serde(default)]
+    file: Option<PathBuf>,
+    #[serde(default)]
+    otlp: Option<OtlpConfig>,
+    #[serde(default)]
+    sentry: Option<SentryConfig>,
+}
+
+/// Export of spans over OTLP/HTTP, e.g. to Jaeger, Tempo or an
+/// OpenTelemetry collector.
+///
+/// `endpoint` is the full URL spans are posted to. `sampling_ratio` is the
+/// share of traces exported, `1.0` by default; requests continuing a trace
+/// follow the sampling decision of their parent.
+///
+/// Traces are propagated with the W3C `traceparent` and `tracestate`
+/// headers: a request carrying them continues the caller's trace, and
+/// webhook deliveries and calls to OAuth providers carry the trace on.
+///
+/// ```yaml
+/// logger:
+///   otlp:
+///     endpoint: http://localhost:4318/v1/traces
+///     service_name: betterauth
+///     sampling_ratio: 0.1
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct OtlpConfig {
+    endpoint: String,
+    #[serde(default = "default_service_name")]
+    service_name: String,
+    #[serde(default = "default_sampling_ratio")]
+    sampling_ratio: f64,
+}
+
+fn default_service_name() -> String {
+    String::from(env!("CARGO_PKG_NAME"))
+}
+
+fn default_sampling_ratio() -> f64 {
+    1.0
+}
+
+impl OtlpConfig {
+    #[must_use]
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// `service.name` the spans are reported under.
+    #[must_use]
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    #[must_use]
+    pub fn sampling_ratio(&self) -> f64 {
+        self.sampling_ratio
+    }
+}
+
+/// Error reporting to Sentry, or a compatible service such as GlitchTip.
+///
+/// Every `error` event becomes a Sentry event, with the `info` and `warn`
+/// events before it as breadcrumbs, regardless of `logger.level`. Panics
+/// are reported too. Events raised while serving a request carry its route,
+/// request ID and, once authenticated, the user ID.
+///
+/// ```yaml
+/// logger:
+///   sentry:
+///     dsn: https://public@o0.ingest.sentry.io/0
+///     environment: production
+///     sample_rate: 1.0
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct SentryConfig {
+    dsn: String,
+    #[serde(default)]
+    environment: Option<String>,
+    #[serde(default = "default_sample_rate")]
+    sample_rate: f32,
+}
+
+fn default_sample_rate() -> f32 {
+    1.0
+}
+
+impl SentryConfig {
+    #[must_use]
+    pub fn dsn(&self) -> &str {
+        &self.dsn
+    }
+
+    /// Environment events are tagged with, by default `development` in
+    /// debug builds and `production` otherwise.
+    #[must_use]
+    pub fn environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    /// Share of error events sent, from `0.0` to `1.0`.
+    #[must_use]
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
 }
 
 impl Logger {
@@ -107,25 +301,93 @@ impl Logger {
     ///
     /// This should be called once at application startup.
     ///
+    /// The [`EnvFilter`] only applies to the output layers. sqlx query events
+    /// always reach the [`QueryAccounting`] layer so per-request query counts
+    /// are recorded even when `sqlx` logging is turned off.
+    ///
     /// ## Errors
     ///
     /// * Environment filter parsing errors
     /// * Invalid log directive format
     /// * Subscriber already initialized
-    pub fn setup(&self) -> ConfigResult<()> {
-        let env_filter_layer = self.env_filter()?;
+    /// * [`ConfigError::Otlp`] if the exporter cannot be built
+    /// * [`ConfigError::Sentry`] if the Sentry DSN is invalid
+    /// * The log file or its directory cannot be created
+    pub fn setup(&self) -> ConfigResult<LogGuard> {
+        let (writer, guard) = self.non_blocking()?;
+        let sentry = self.sentry_client()?;
+        let env_filter = self.env_filter()?;
         let registry = tracing_subscriber::registry()
-            .with(env_filter_layer)
-            .with(ErrorLayer::default());
+            .with(QueryAccounting.with_filter(QueryAccounting::filter()))
+            .with(self.otlp_layer()?)
+            .with(
+                sentry
+                    .as_ref()
+                    .map(|_| sentry::integrations::tracing::layer()),
+            );
 
         match self.format {
-            Format::Compact => registry.with(self.compact_fmt_layer()).try_init()?,
-            Format::Full => registry.with(self.base_fmt_layer()).try_init()?,
-            Format::Json => registry.with(self.json_fmt_layer()).try_init()?,
-            Format::Pretty => registry.with(self.pretty_fmt_layer()).try_init()?,
+            Format::Compact => registry
+                .with(
+                    self.compact_fmt_layer(writer)
+                        .and_then(ErrorLayer::default())
+                        .with_filter(reloadable(env_filter)),
+                )
+                .try_init()?,
+            Format::Full => registry
+                .with(
+                    self.base_fmt_layer(writer)
+                        .and_then(ErrorLayer::default())
+                        .with_filter(reloadable(env_filter)),
+                )
+                .try_init()?,
+            Format::Json => registry
+                .with(
+                    self.json_fmt_layer(writer)
+                        .and_then(ErrorLayer::default())
+                        .with_filter(reloadable(env_filter)),
+                )
+                .try_init()?,
+            Format::Pretty => registry
+                .with(
+                    self.pretty_fmt_layer(writer)
+                        .and_then(ErrorLayer::default())
+                        .with_filter(reloadable(env_filter)),
+                )
+                .try_init()?,
         }
 
-        Ok(())
+        Ok(LogGuard {
+            _guard: guard,
+            _sentry: sentry,
+        })
+    }
+
+    /// Exports the spans still buffered for `otlp` and stops the exporter.
+    /// Call once before exiting; does nothing without `otlp`.
+    pub fn flush() {
+        if let Some(provider) = TRACER_PROVIDER.get()
+            && let Err(err) = provider.shutdown()
+        {
+            tracing::warn!(error = %err, "Failed to flush OTLP spans");
+        }
+    }
+
+    /// Applies this logger's `level` and `crates` to the subscriber
+    /// installed by [`Logger::setup`], e.g. after the configuration was
+    /// reloaded. `RUST_LOG` still takes precedence, and `format`,
+    /// `writer` and `otlp` only change on restart. Does nothing if `setup`
+    /// was never called.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Logger::setup`] for invalid directives.
+    pub fn reload(&self) -> ConfigResult<()> {
+        let Some(reload) = RELOAD_FILTER.get() else {
+            return Ok(());
+        };
+
+        reload(self.env_filter()?).map_err(|err| ConfigError::Reload(err.to_string()))
     }
 
     /// Creates an [`EnvFilter`] from configuration and environment variables.
@@ -165,36 +427,141 @@ impl Logger {
         Ok(env_filter)
     }
 
-    #[allow(clippy::unused_self)]
-    fn base_fmt_layer<S>(&self) -> FmtLayer<S>
+    /// The layer exporting spans over OTLP, filtered like the output layers
+    /// as they are at startup.
+    fn otlp_layer<S>(&self) -> ConfigResult<Option<impl Layer<S>>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let Some(otlp) = &self.otlp else {
+            return Ok(None);
+        };
+
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&otlp.endpoint)
+            .build()?;
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                otlp.sampling_ratio,
+            ))))
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(otlp.service_name.clone())
+                    .build(),
+            )
+            .build();
+
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        let _ = TRACER_PROVIDER.set(provider);
+        // W3C `traceparent` and `tracestate`, read from requests and sent on
+        // with outgoing calls, see `crate::trace::trace_headers`.
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        Ok(Some(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(self.env_filter()?),
+        ))
+    }
+
+    /// Starts the Sentry client when `sentry` is set, which also installs
+    /// the panic handler reporting panics.
+    fn sentry_client(&self) -> ConfigResult<Option<ClientInitGuard>> {
+        let Some(config) = &self.sentry else {
+            return Ok(None);
+        };
+
+        let guard = sentry::init(ClientOptions {
+            dsn: Some(config.dsn.parse::<Dsn>()?),
+            environment: config.environment.clone().map(Into::into),
+            release: sentry::release_name!(),
+            sample_rate: config.sample_rate,
+            ..ClientOptions::default()
+        });
+
+        Ok(Some(guard))
+    }
+
+    /// A non-blocking writer to the configured [`Writer`], and the guard
+    /// flushing it.
+    fn non_blocking(&self) -> ConfigResult<(NonBlocking, WorkerGuard)> {
+        match self.writer {
+            Writer::Stdout => Ok(tracing_appender::non_blocking(std::io::stdout())),
+            Writer::Stderr => Ok(tracing_appender::non_blocking(std::io::stderr())),
+            Writer::File => {
+                let path = self.log_file()?;
+                if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+                Ok(tracing_appender::non_blocking(file))
+            }
+            Writer::Daily => {
+                let path = self.log_file()?;
+                let prefix = path.file_name().ok_or_else(|| {
+                    ConfigError::Invalid(vec![String::from("logger.file must name a file")])
+                })?;
+                let appender = RollingFileAppender::builder()
+                    .rotation(Rotation::DAILY)
+                    .filename_prefix(prefix.to_string_lossy())
+                    .build(path.parent().unwrap_or(Path::new(".")))?;
+
+                Ok(tracing_appender::non_blocking(appender))
+            }
+        }
+    }
+
+    fn log_file(&self) -> ConfigResult<&Path> {
+        self.file.as_deref().ok_or_else(|| {
+            ConfigError::Invalid(vec![format!(
+                "logger.file is required by the {} writer",
+                self.writer
+            )])
+        })
+    }
+
+    /// Whether to colour the output, only when writing to a terminal.
+    fn ansi(&self) -> bool {
+        match self.writer {
+            Writer::Stdout => std::io::stdout().is_terminal(),
+            Writer::Stderr => std::io::stderr().is_terminal(),
+            Writer::File | Writer::Daily => false,
+        }
+    }
+
+    fn base_fmt_layer<S>(
+        &self,
+        writer: NonBlocking,
+    ) -> FmtLayer<S, DefaultFields, FmtFormat, NonBlocking>
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
-        FmtLayer::new()
-            .with_ansi(std::io::stderr().is_terminal())
-            // TODO: Implement other writers
-            .with_writer(std::io::stdout)
+        FmtLayer::new().with_ansi(self.ansi()).with_writer(writer)
     }
 
-    fn pretty_fmt_layer<S>(&self) -> impl Layer<S>
+    fn pretty_fmt_layer<S>(&self, writer: NonBlocking) -> impl Layer<S>
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
-        self.base_fmt_layer().pretty()
+        self.base_fmt_layer(writer).pretty()
     }
 
-    fn json_fmt_layer<S>(&self) -> impl Layer<S>
+    fn json_fmt_layer<S>(&self, writer: NonBlocking) -> impl Layer<S>
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
-        self.base_fmt_layer().json()
+        self.base_fmt_layer(writer).json()
     }
 
-    fn compact_fmt_layer<S>(&self) -> impl Layer<S>
+    fn compact_fmt_layer<S>(&self, writer: NonBlocking) -> impl Layer<S>
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
-        self.base_fmt_layer()
+        self.base_fmt_layer(writer)
             .compact()
             .with_target(false)
             .with_thread_ids(false)
@@ -213,6 +580,30 @@ impl Logger {
         &self.format
     }
 
+    /// Where logs are written. Defaults to [`Writer::Stdout`].
+    #[must_use]
+    pub fn writer(&self) -> Writer {
+        self.writer
+    }
+
+    /// The log file of the `file` and `daily` writers.
+    #[must_use]
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Span export settings, if spans are exported over OTLP.
+    #[must_use]
+    pub fn otlp(&self) -> Option<&OtlpConfig> {
+        self.otlp.as_ref()
+    }
+
+    /// Error reporting settings, if errors are reported to Sentry.
+    #[must_use]
+    pub fn sentry(&self) -> Option<&SentryConfig> {
+        self.sentry.as_ref()
+    }
+
     /// Converts the configured crates list into tracing [`Directive`]
     ///
     /// Creates a directive for each crate in the format `crate=level`,
@@ -230,3 +621,14 @@ impl Logger {
             .collect()
     }
 }
+
+/// Wraps `filter` so [`Logger::reload`] can replace it later.
+fn reloadable<S>(filter: EnvFilter) -> reload::Layer<EnvFilter, S>
+where
+    S: Subscriber + 'static,
+{
+    let (layer, handle) = reload::Layer::new(filter);
+    let _ = RELOAD_FILTER.set(Box::new(move |filter| handle.reload(filter)));
+
+    layer
+}
@@ -0,0 +1,854 @@
+use axum::http::HeaderValue;
+use lettre::message::Mailbox;
+use sentry::types::Dsn;
+use sqlx::postgres::PgConnectOptions;
+use url::Url;
+
+use crate::{
+    config::{
+        AuditSinkKind, Config, ConfigError, ConfigResult, DatabaseConfig, DatabaseDriver,
+        Environment, MailTransport, Routes, SameSitePolicy, SecretString, SessionBackend,
+        SmsProvider, TokenAlgorithm, Writer, cors,
+    },
+    oidc, tokens,
+};
+
+/// Port PostgreSQL listens on when the URI names none.
+const DEFAULT_PG_PORT: u16 = 5432;
+
+impl Config {
+    /// Checks the invariants deserialization cannot express: values within
+    /// range, `database.uri` agreeing with the discrete `database` fields,
+    /// settings a feature needs being present, and, in production, no secret
+    /// left empty.
+    ///
+    /// Run by [`Config::load`] and the other loaders.
+    ///
+    /// # Errors
+    ///
+    /// [`ConfigError::Invalid`] listing every violation found, not just the
+    /// first.
+    pub fn validate(&self, env: &Environment) -> ConfigResult<()> {
+        let mut violations = Vec::new();
+
+        self.check_ranges(&mut violations);
+        check_database(self.database(), &mut violations);
+        self.check_required(&mut violations);
+        self.check_tls(&mut violations);
+
+        if *env == Environment::Production {
+            self.check_secrets(&mut violations);
+
+            if self.auth().session_backend() == SessionBackend::Memory {
+                violations.push(String::from(
+                    "auth.session_backend memory is not for production",
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(violations))
+        }
+    }
+
+    fn check_ranges(&self, violations: &mut Vec<String>) {
+        let protocol = self.server().protocol();
+        if !protocol.eq_ignore_ascii_case("http") && !protocol.eq_ignore_ascii_case("https") {
+            violations.push(format!(
+                "server.protocol must be http or https, not `{protocol}`"
+            ));
+        }
+
+        if self.server().port() == 0 {
+            violations.push(String::from("server.port must be between 1 and 65535"));
+        }
+
+        let base_path = self.server().base_path();
+        if !base_path.is_empty() && (!base_path.starts_with('/') || base_path.ends_with('/')) {
+            violations.push(format!(
+                "server.base_path must start with / and not end with one, not `{base_path}`"
+            ));
+        }
+
+        let mut listeners = self.server().listeners();
+        if let Some(admin) = self.server().admin() {
+            // The last listener is the admin one.
+            listeners.pop();
+
+            if admin.port() == 0 {
+                violations.push(String::from(
+                    "server.admin.port must be between 1 and 65535",
+                ));
+            }
+        }
+
+        for (i, listener) in listeners.iter().enumerate() {
+            if listener.port() == 0 {
+                violations.push(format!(
+                    "server.listeners[{i}].port must be between 1 and 65535"
+                ));
+            }
+            if self.server().admin().is_some() && listener.routes() == Routes::All {
+                violations.push(format!(
+                    "server.listeners[{i}] must not serve all routes with server.admin set"
+                ));
+            }
+        }
+
+        if self.grpc().is_some_and(|grpc| grpc.port() == 0) {
+            violations.push(String::from("grpc.port must be between 1 and 65535"));
+        }
+
+        if self.server().request_timeout() == 0 {
+            violations.push(String::from(
+                "server.request_timeout must be greater than 0",
+            ));
+        }
+        for (group, &timeout) in self.server().route_timeouts() {
+            if timeout == 0 {
+                violations.push(format!(
+                    "server.route_timeouts.{group} must be greater than 0"
+                ));
+            }
+        }
+        if self.server().body_limit() == 0 {
+            violations.push(String::from("server.body_limit must be greater than 0"));
+        }
+
+        let concurrency = self.server().concurrency();
+        if concurrency.max_requests() == Some(0) {
+            violations.push(String::from(
+                "server.concurrency.max_requests must be greater than 0",
+            ));
+        }
+        for (group, &limit) in concurrency.groups() {
+            if limit == 0 {
+                violations.push(format!(
+                    "server.concurrency.groups.{group} must be greater than 0"
+                ));
+            }
+        }
+        for (endpoint, &limit) in concurrency.endpoints() {
+            if !endpoint.starts_with('/') {
+                violations.push(format!(
+                    "server.concurrency.endpoints.{endpoint} must be a path starting with /"
+                ));
+            }
+            if limit == 0 {
+                violations.push(format!(
+                    "server.concurrency.endpoints.{endpoint} must be greater than 0"
+                ));
+            }
+        }
+
+        for (name, policy) in self.rate_limit().policies() {
+            for limit in [policy.ip(), policy.user()].into_iter().flatten() {
+                if limit.requests() == 0 || limit.period().is_zero() {
+                    violations.push(format!(
+                        "rate_limit.{name} must allow at least 1 request over a period of at least 1 second"
+                    ));
+                }
+            }
+        }
+        for endpoint in self.rate_limit().endpoints() {
+            if !endpoint.starts_with('/') {
+                violations.push(format!(
+                    "rate_limit.endpoints.{endpoint} must be a path starting with `/`"
+                ));
+            }
+        }
+
+        if let Err(err) = self.password_hashing().params() {
+            violations.push(format!("password_hashing is invalid: {err}"));
+        }
+        if self
+            .password_hashing()
+            .pepper()
+            .is_some_and(SecretString::is_empty)
+        {
+            violations.push(String::from("password_hashing.pepper must not be empty"));
+        }
+        if self.password_hashing().max_concurrent() == 0 {
+            violations.push(String::from(
+                "password_hashing.max_concurrent must be greater than 0",
+            ));
+        }
+        if self.password_hashing().pepper_version() == 0 {
+            violations.push(String::from(
+                "password_hashing.pepper_version must be greater than 0",
+            ));
+        }
+        for (version, pepper) in self.password_hashing().retired_peppers() {
+            if *version == 0 {
+                violations.push(String::from(
+                    "password_hashing.retired_peppers versions must be greater than 0",
+                ));
+            }
+            if self.password_hashing().pepper().is_some()
+                && *version == self.password_hashing().pepper_version()
+            {
+                violations.push(format!(
+                    "password_hashing.retired_peppers.{version} has the version of the current pepper"
+                ));
+            }
+            if pepper.is_empty() {
+                violations.push(format!(
+                    "password_hashing.retired_peppers.{version} must not be empty"
+                ));
+            }
+        }
+
+        if let Some(otlp) = self.logger().otlp() {
+            if !(0.0..=1.0).contains(&otlp.sampling_ratio()) {
+                violations.push(String::from(
+                    "logger.otlp.sampling_ratio must be between 0 and 1",
+                ));
+            }
+            if !Url::parse(otlp.endpoint())
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+            {
+                violations.push(format!(
+                    "logger.otlp.endpoint must be an http or https URL, not `{}`",
+                    otlp.endpoint()
+                ));
+            }
+        }
+
+        if let Some(sentry) = self.logger().sentry() {
+            if !(0.0..=1.0).contains(&sentry.sample_rate()) {
+                violations.push(String::from(
+                    "logger.sentry.sample_rate must be between 0 and 1",
+                ));
+            }
+            if sentry.dsn().parse::<Dsn>().is_err() {
+                violations.push(format!(
+                    "logger.sentry.dsn must be a Sentry DSN, not `{}`",
+                    sentry.dsn()
+                ));
+            }
+        }
+
+        if self.database().port() == 0 {
+            violations.push(String::from("database.port must be between 1 and 65535"));
+        }
+
+        let database = self.database();
+        if database.max_connections() == 0 {
+            violations.push(String::from(
+                "database.max_connections must be greater than 0",
+            ));
+        }
+        if database.min_connections() > database.max_connections() {
+            violations.push(String::from(
+                "database.min_connections must not exceed database.max_connections",
+            ));
+        }
+        if database.acquire_timeout() == 0 {
+            violations.push(String::from(
+                "database.acquire_timeout must be greater than 0",
+            ));
+        }
+
+        if self.mailer().smtp().and_then(|smtp| smtp.port()) == Some(0) {
+            violations.push(String::from("mailer.smtp.port must be between 1 and 65535"));
+        }
+
+        let auth = self.auth();
+        let lifetimes = auth.lifetimes();
+        for (key, lifetime) in [
+            ("access_token", lifetimes.access_token()),
+            ("refresh_token", lifetimes.refresh_token()),
+            ("session", lifetimes.session()),
+            ("password_reset", lifetimes.password_reset()),
+        ] {
+            if lifetime.as_secs() == 0 {
+                violations.push(format!("auth.lifetimes.{key} must be at least one second"));
+            }
+        }
+
+        if lifetimes.refresh_token() < lifetimes.access_token() {
+            violations.push(String::from(
+                "auth.lifetimes.refresh_token must not be shorter than auth.lifetimes.access_token",
+            ));
+        }
+        if lifetimes.session_idle() > lifetimes.session() {
+            violations.push(String::from(
+                "auth.lifetimes.session_idle must not be longer than auth.lifetimes.session",
+            ));
+        }
+        // Sessions record their last use to the minute.
+        if !lifetimes.session_idle().is_zero() && lifetimes.session_idle().as_secs() < 60 {
+            violations.push(String::from(
+                "auth.lifetimes.session_idle must be 0 or at least a minute",
+            ));
+        }
+
+        if auth.max_metadata_size() == 0 {
+            violations.push(String::from(
+                "auth.max_metadata_size must be greater than 0",
+            ));
+        }
+
+        if auth.reauthentication_window() == 0 {
+            violations.push(String::from(
+                "auth.reauthentication_window must be greater than 0",
+            ));
+        }
+
+        let security = self.security();
+        if security.login_delay() > 0 && security.login_delay_window() == 0 {
+            violations.push(String::from(
+                "security.login_delay_window must be greater than 0",
+            ));
+        }
+        if security.max_login_delay() < security.login_delay() {
+            violations.push(String::from(
+                "security.max_login_delay must not be shorter than security.login_delay",
+            ));
+        }
+
+        let email_otp = self.email_otp();
+        if email_otp.code_ttl() == 0 {
+            violations.push(String::from("email_otp.code_ttl must be greater than 0"));
+        }
+        if email_otp.max_attempts() < 1 {
+            violations.push(String::from(
+                "email_otp.max_attempts must be greater than 0",
+            ));
+        }
+
+        let usernames = auth.usernames();
+        if usernames.min_length() == 0 || usernames.min_length() > usernames.max_length() {
+            violations.push(String::from(
+                "auth.usernames.min_length must be between 1 and auth.usernames.max_length",
+            ));
+        }
+
+        let languages = self.i18n().languages();
+        if languages.is_empty() {
+            violations.push(String::from("i18n.languages must not be empty"));
+        }
+        for language in languages {
+            let valid = !language.is_empty()
+                && language.split('-').all(|part| {
+                    !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric())
+                });
+            if !valid {
+                violations.push(format!(
+                    "i18n.languages must hold language tags like `en` or `pt-BR`, not `{language}`"
+                ));
+            }
+        }
+    }
+
+    fn check_required(&self, violations: &mut Vec<String>) {
+        if self.auth().algorithm() != TokenAlgorithm::HS256
+            && self.auth().signing_key().is_none()
+            && self.auth().signing_key_rotation() == 0
+        {
+            violations.push(format!(
+                "auth.algorithm {:?} requires auth.signing_key, auth.signing_key_file or auth.signing_key_rotation",
+                self.auth().algorithm()
+            ));
+        }
+
+        if let Some(key) = self.auth().signing_key()
+            && self.auth().algorithm() != TokenAlgorithm::HS256
+            && !tokens::is_signing_key(self.auth().algorithm(), key.expose())
+        {
+            violations.push(format!(
+                "auth.signing_key is not a PEM-encoded private key for auth.algorithm {:?}",
+                self.auth().algorithm()
+            ));
+        }
+
+        if let Some(oidc) = self.oidc()
+            && oidc::signing_key(oidc.signing_key().expose()).is_none()
+        {
+            violations.push(String::from(
+                "oidc.signing_key must be a PEM-encoded RSA private key",
+            ));
+        }
+
+        let mailer = self.mailer();
+        if mailer.transport() == MailTransport::Smtp {
+            if mailer.smtp().is_none() {
+                violations.push(String::from(
+                    "mailer.transport smtp requires the mailer.smtp section",
+                ));
+            }
+            if mailer.from().parse::<Mailbox>().is_err() {
+                violations.push(format!(
+                    "mailer.from {:?} is not a valid email address",
+                    mailer.from()
+                ));
+            }
+        }
+
+        if self.auth().session_backend() == SessionBackend::Redis && self.redis().is_none() {
+            violations.push(String::from(
+                "auth.session_backend redis requires the redis section",
+            ));
+        }
+
+        self.check_cookies(violations);
+        self.check_saml(violations);
+        self.check_sso(violations);
+        self.check_static_files(violations);
+        self.check_well_known(violations);
+        self.check_audit(violations);
+
+        if self.maintenance().audit_archive_after() > 0
+            && self.maintenance().audit_archive_dir().is_none()
+        {
+            violations.push(String::from(
+                "maintenance.audit_archive_after requires maintenance.audit_archive_dir",
+            ));
+        }
+
+        let logger = self.logger();
+        if matches!(logger.writer(), Writer::File | Writer::Daily) && logger.file().is_none() {
+            violations.push(format!(
+                "logger.writer {} requires logger.file",
+                logger.writer()
+            ));
+        }
+
+        let cors = self.cors();
+        if cors.allow_credentials() && cors.allowed_origins().iter().any(|origin| origin == "*") {
+            violations.push(String::from(
+                "cors.allow_credentials cannot be combined with the \"*\" origin",
+            ));
+        }
+        for origin in cors.allowed_origins() {
+            if HeaderValue::from_str(origin).is_err() {
+                violations.push(format!(
+                    "cors.allowed_origins has an invalid origin {origin:?}"
+                ));
+            }
+        }
+        for method in cors.allowed_methods() {
+            if cors::parse_method(method).is_none() {
+                violations.push(format!(
+                    "cors.allowed_methods has an invalid method {method:?}"
+                ));
+            }
+        }
+        for header in cors.allowed_headers() {
+            if cors::parse_header(header).is_none() {
+                violations.push(format!(
+                    "cors.allowed_headers has an invalid header {header:?}"
+                ));
+            }
+        }
+
+        if let Some(sms) = self.sms()
+            && sms.provider() == SmsProvider::Twilio
+        {
+            for (key, missing) in [
+                ("account_sid", sms.account_sid().is_none()),
+                ("auth_token", sms.auth_token().is_none()),
+                ("from", sms.from().is_none()),
+            ] {
+                if missing {
+                    violations.push(format!("sms.{key} is required by the twilio provider"));
+                }
+            }
+        }
+    }
+
+    fn check_cookies(&self, violations: &mut Vec<String>) {
+        let cookies = self.cookies();
+        let secure = cookies.is_secure(self.server().is_https());
+
+        let prefix = cookies.prefix();
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+        {
+            violations.push(format!(
+                "cookies.prefix {prefix:?} has characters cookie names cannot hold"
+            ));
+        }
+        if !cookies.path().starts_with('/') {
+            violations.push(String::from("cookies.path must start with /"));
+        }
+
+        if cookies.same_site() == SameSitePolicy::None && !secure {
+            violations.push(String::from(
+                "cookies.same_site none requires secure cookies",
+            ));
+        }
+        if (prefix.starts_with("__Secure-") || prefix.starts_with("__Host-")) && !secure {
+            violations.push(format!("cookies.prefix {prefix} requires secure cookies"));
+        }
+        if prefix.starts_with("__Host-") && (cookies.path() != "/" || cookies.domain().is_some()) {
+            violations.push(String::from(
+                "cookies.prefix __Host- requires cookies.path / and no cookies.domain",
+            ));
+        }
+    }
+
+    fn check_saml(&self, violations: &mut Vec<String>) {
+        for (tenant, idp) in self.saml().tenants() {
+            if idp.domains().is_empty() {
+                violations.push(format!(
+                    "saml.{tenant}.domains must name the email domains the identity provider speaks for"
+                ));
+            }
+
+            if idp
+                .idp_slo_url()
+                .is_some_and(|url| Url::parse(url).is_err())
+            {
+                violations.push(format!("saml.{tenant}.idp_slo_url must be an absolute URL"));
+            }
+        }
+    }
+
+    fn check_sso(&self, violations: &mut Vec<String>) {
+        let Some(sso) = self.sso() else {
+            return;
+        };
+
+        if sso.code_ttl() == 0 {
+            violations.push(String::from("sso.code_ttl must be greater than 0"));
+        }
+        if let Some(login_url) = sso.login_url()
+            && Url::parse(login_url).is_err()
+        {
+            violations.push(format!("sso.login_url {login_url:?} is not a valid URL"));
+        }
+
+        for (id, app) in sso.apps() {
+            if app.origins().is_empty() && app.redirect_urls().is_empty() {
+                violations.push(format!(
+                    "sso.apps.{id} needs at least one of origins and redirect_urls"
+                ));
+            }
+            for origin in app.origins() {
+                // An origin is a bare scheme, host and port, as browsers send
+                // it in the `Origin` header.
+                if !Url::parse(origin)
+                    .is_ok_and(|url| url.origin().ascii_serialization() == *origin)
+                {
+                    violations.push(format!(
+                        "sso.apps.{id}.origins has an invalid origin {origin:?}"
+                    ));
+                }
+            }
+            for url in app.redirect_urls() {
+                if Url::parse(url).is_err() {
+                    violations.push(format!(
+                        "sso.apps.{id}.redirect_urls has an invalid URL {url:?}"
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_static_files(&self, violations: &mut Vec<String>) {
+        let Some(files) = self.static_files() else {
+            return;
+        };
+
+        if !files.dir().is_dir() {
+            violations.push(format!(
+                "static_files.dir {} is not a directory",
+                files.dir().display()
+            ));
+        } else if files.spa() && !files.dir().join(files.index()).is_file() {
+            violations.push(format!(
+                "static_files.spa requires static_files.index {} in static_files.dir",
+                files.index()
+            ));
+        }
+
+        let path = files.path();
+        if !path.starts_with('/') || (path.len() > 1 && path.ends_with('/')) {
+            violations.push(format!(
+                "static_files.path must start and not end with /, not `{path}`"
+            ));
+        }
+        if path == "/api" || path.starts_with("/api/") {
+            violations.push(String::from("static_files.path cannot be under /api"));
+        }
+    }
+
+    fn check_well_known(&self, violations: &mut Vec<String>) {
+        let well_known = self.well_known();
+
+        if let Some(url) = well_known.change_password_url()
+            && Url::parse(url).is_err()
+        {
+            violations.push(format!(
+                "well_known.change_password_url {url:?} is not a valid URL"
+            ));
+        }
+
+        let Some(security_txt) = well_known.security_txt() else {
+            return;
+        };
+
+        if security_txt.contact().is_empty() {
+            violations.push(String::from(
+                "well_known.security_txt.contact must not be empty",
+            ));
+        }
+        for contact in security_txt.contact() {
+            if !Url::parse(contact)
+                .is_ok_and(|url| matches!(url.scheme(), "mailto" | "tel" | "https"))
+            {
+                violations.push(format!(
+                    "well_known.security_txt.contact {contact:?} must be a mailto:, tel: or https:// URI"
+                ));
+            }
+        }
+    }
+
+    fn check_audit(&self, violations: &mut Vec<String>) {
+        let audit = self.audit();
+
+        if audit.batch_size() <= 0 {
+            violations.push(String::from("audit.batch_size must be greater than 0"));
+        }
+        if audit.flush_interval() == 0 {
+            violations.push(String::from("audit.flush_interval must be greater than 0"));
+        }
+
+        for (name, sink) in audit.sinks() {
+            let kind = sink.kind();
+            let required: &[(&str, bool)] = match kind {
+                AuditSinkKind::Syslog => &[("address", sink.address().is_some())],
+                AuditSinkKind::Kafka => &[
+                    ("url", sink.url().is_some()),
+                    ("topic", sink.topic().is_some()),
+                ],
+                AuditSinkKind::File => &[("dir", sink.dir().is_some())],
+                AuditSinkKind::S3 => &[
+                    ("bucket", sink.bucket().is_some()),
+                    ("region", sink.region().is_some()),
+                    ("access_key_id", sink.access_key_id().is_some()),
+                    ("secret_access_key", sink.secret_access_key().is_some()),
+                ],
+                AuditSinkKind::Http => &[("url", sink.url().is_some())],
+            };
+            for (key, present) in required {
+                if !present {
+                    violations.push(format!(
+                        "audit.sinks.{name}.{key} is required by {} sinks",
+                        kind.name()
+                    ));
+                }
+            }
+
+            for (key, url) in [("url", sink.url()), ("endpoint", sink.endpoint())] {
+                if let Some(url) = url
+                    && Url::parse(url).is_err()
+                {
+                    violations.push(format!(
+                        "audit.sinks.{name}.{key} {url:?} is not a valid URL"
+                    ));
+                }
+            }
+            if let Some(address) = sink.address()
+                && address
+                    .rsplit_once(':')
+                    .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+            {
+                violations.push(format!(
+                    "audit.sinks.{name}.address must be host:port, not {address:?}"
+                ));
+            }
+            if let Some(dir) = sink.dir()
+                && !dir.is_dir()
+            {
+                violations.push(format!(
+                    "audit.sinks.{name}.dir {} is not a directory",
+                    dir.display()
+                ));
+            }
+        }
+    }
+
+    fn check_tls(&self, violations: &mut Vec<String>) {
+        let Some(tls) = self.server().tls() else {
+            return;
+        };
+
+        if !self.server().is_https() {
+            violations.push(String::from("server.tls requires server.protocol https"));
+        }
+        for (key, path) in [("cert_path", tls.cert_path()), ("key_path", tls.key_path())] {
+            if !path.is_file() {
+                violations.push(format!("server.tls.{key} {} is not a file", path.display()));
+            }
+        }
+    }
+
+    fn check_secrets(&self, violations: &mut Vec<String>) {
+        let mut secrets: Vec<(String, &SecretString)> = vec![
+            (String::from("auth.secret"), self.auth().secret()),
+            (
+                String::from("database.password"),
+                self.database().password(),
+            ),
+        ];
+
+        if let Some(key) = self.auth().signing_key() {
+            secrets.push((String::from("auth.signing_key"), key));
+        }
+        if let Some(password) = self.mailer().smtp().and_then(|smtp| smtp.password()) {
+            secrets.push((String::from("mailer.smtp.password"), password));
+        }
+        if let Some(oidc) = self.oidc() {
+            secrets.push((String::from("oidc.signing_key"), oidc.signing_key()));
+        }
+        if let Some(captcha) = self.captcha() {
+            secrets.push((String::from("captcha.secret"), captcha.secret()));
+        }
+        if let Some(token) = self.sms().and_then(|sms| sms.auth_token()) {
+            secrets.push((String::from("sms.auth_token"), token));
+        }
+        for (name, provider) in [
+            ("google", self.oauth().google()),
+            ("github", self.oauth().github()),
+        ] {
+            if let Some(provider) = provider {
+                secrets.push((
+                    format!("oauth.{name}.client_secret"),
+                    provider.client_secret(),
+                ));
+            }
+        }
+        for (name, endpoint) in self.webhooks().endpoints() {
+            secrets.push((
+                format!("webhooks.endpoints.{name}.secret"),
+                endpoint.secret(),
+            ));
+        }
+        for (name, sink) in self.audit().sinks() {
+            if let Some(secret) = sink.secret_access_key() {
+                secrets.push((format!("audit.sinks.{name}.secret_access_key"), secret));
+            }
+        }
+        for (index, key) in self.cookies().keys().iter().enumerate() {
+            secrets.push((format!("cookies.keys[{index}]"), key));
+        }
+
+        for (key, secret) in secrets {
+            if secret.is_empty() {
+                violations.push(format!("{key} must not be empty in production"));
+            }
+        }
+
+        let cookies = self.cookies();
+        if cookies.encrypt() && cookies.keys().is_empty() {
+            violations.push(String::from(
+                "cookies.keys must be set in production while cookies.encrypt is on",
+            ));
+        }
+        if !cookies.is_secure(self.server().is_https()) {
+            violations.push(String::from(
+                "cookies.secure must be true in production; it defaults to whether server.protocol is https",
+            ));
+        }
+        if !cookies.http_only() {
+            violations.push(String::from("cookies.http_only must be true in production"));
+        }
+    }
+}
+
+/// Checks that `database.uri`, used by the application, points at the same
+/// database as the discrete fields, used for migrations. With the SQLite
+/// driver it names the SQLite file instead. Replicas only need to be
+/// Postgres URIs.
+fn check_database(database: &DatabaseConfig, violations: &mut Vec<String>) {
+    for (index, replica) in database.replicas().iter().enumerate() {
+        if replica.expose().parse::<PgConnectOptions>().is_err() {
+            violations.push(format!(
+                "database.replicas[{index}] is not a valid Postgres URI"
+            ));
+        }
+    }
+
+    if let Some(dir) = database.migrations_dir()
+        && !dir.is_dir()
+    {
+        violations.push(format!(
+            "database.migrations_dir {} is not a directory",
+            dir.display()
+        ));
+    }
+
+    let Ok(uri) = Url::parse(database.uri().expose()) else {
+        violations.push(String::from("database.uri is not a valid URI"));
+        return;
+    };
+
+    if database.driver() == DatabaseDriver::Sqlite {
+        if !cfg!(feature = "sqlite") {
+            violations.push(String::from(
+                "database.driver sqlite needs betterauth built with the `sqlite` feature",
+            ));
+        }
+        if uri.scheme() != "sqlite" {
+            violations.push(format!(
+                "database.uri must use sqlite:// with the sqlite driver, not {}://",
+                uri.scheme()
+            ));
+        }
+        return;
+    }
+
+    let scheme = uri.scheme();
+    if !matches!(scheme, "postgres" | "postgresql") {
+        violations.push(format!(
+            "database.uri must use postgres:// or postgresql://, not {scheme}://"
+        ));
+    }
+
+    let host = uri.host_str().unwrap_or_default();
+    if host != database.host() {
+        violations.push(format!(
+            "database.uri host `{host}` does not match database.host `{}`",
+            database.host()
+        ));
+    }
+
+    let port = uri.port().unwrap_or(DEFAULT_PG_PORT);
+    if port != database.port() {
+        violations.push(format!(
+            "database.uri port {port} does not match database.port {}",
+            database.port()
+        ));
+    }
+
+    // Compare percent-encoded forms, as the URI stores them.
+    let mut expected = uri.clone();
+    if expected.set_username(database.user()).is_ok() && expected.username() != uri.username() {
+        violations.push(format!(
+            "database.uri user does not match database.user `{}`",
+            database.user()
+        ));
+    }
+    if expected
+        .set_password(Some(database.password().expose()))
+        .is_ok()
+        && expected.password() != uri.password()
+    {
+        violations.push(String::from(
+            "database.uri password does not match database.password",
+        ));
+    }
+
+    let name = uri.path().trim_start_matches('/');
+    if !name.is_empty() && name != database.name() {
+        violations.push(format!(
+            "database.uri database `{name}` does not match database.name `{}`",
+            database.name()
+        ));
+    }
+}
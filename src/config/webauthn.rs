@@ -0,0 +1,60 @@
+use serde::Deserialize;
+
+/// WebAuthn (passkey) relying-party configuration.
+///
+/// Passkey endpoints answer `404 Not Found` until this section is present.
+/// `rp_id` is the registrable domain credentials are scoped to and `origin`
+/// the exact origin of the pages calling the WebAuthn browser API; both are
+/// checked on every ceremony.
+///
+/// ```yaml
+/// webauthn:
+///   rp_id: "example.com"
+///   rp_name: "Example"
+///   origin: "https://app.example.com"
+///   challenge_ttl: 300 # seconds
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebAuthnConfig {
+    rp_id: String,
+    #[serde(default = "default_rp_name")]
+    rp_name: String,
+    origin: String,
+    #[serde(default = "default_challenge_ttl")]
+    challenge_ttl: u64,
+}
+
+fn default_rp_name() -> String {
+    String::from("betterauth")
+}
+
+fn default_challenge_ttl() -> u64 {
+    5 * 60
+}
+
+impl WebAuthnConfig {
+    /// Relying-party ID, a domain such as `example.com`.
+    #[must_use]
+    pub fn rp_id(&self) -> &str {
+        &self.rp_id
+    }
+
+    /// Human-readable name shown by authenticators. Defaults to `betterauth`.
+    #[must_use]
+    pub fn rp_name(&self) -> &str {
+        &self.rp_name
+    }
+
+    /// Origin expected in client data, e.g. `https://app.example.com`.
+    #[must_use]
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// How long a registration or login challenge may be answered, in
+    /// seconds. Defaults to 5 minutes.
+    #[must_use]
+    pub fn challenge_ttl(&self) -> u64 {
+        self.challenge_ttl
+    }
+}
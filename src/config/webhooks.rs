@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::config::SecretString;
+
+/// Outgoing webhooks for auth events, one entry per named endpoint.
+///
+/// Each endpoint receives a signed JSON `POST` for the events it lists, or
+/// for every event when `events` is empty. Events are named as in
+/// [`crate::events::Event::name`], e.g. `user.created` or `session.revoked`.
+///
+/// Requests carry `webhook-id`, `webhook-timestamp` and `webhook-signature`
+/// headers; the signature is `v1,` followed by the base64 HMAC-SHA256 of
+/// `{id}.{timestamp}.{body}` keyed with the endpoint's `secret`.
+///
+/// Deliveries that fail or answer with a non-2xx status are retried with
+/// exponential backoff until `max_attempts` is reached. Every delivery is
+/// tracked in the `webhook_deliveries` table and listed at
+/// `GET /admin/webhooks/deliveries`.
+///
+/// ```yaml
+/// webhooks:
+///   max_attempts: 8
+///   timeout: 10 # seconds
+///   endpoints:
+///     billing:
+///       url: "https://billing.example.com/hooks/auth"
+///       secret: "..."
+///       events: ["user.created", "user.login", "session.revoked"]
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhooksConfig {
+    #[serde(default)]
+    endpoints: HashMap<String, WebhookEndpoint>,
+    #[serde(default = "default_max_attempts")]
+    max_attempts: i32,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: HashMap::new(),
+            max_attempts: default_max_attempts(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+fn default_max_attempts() -> i32 {
+    8
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl WebhooksConfig {
+    /// Endpoints by name.
+    #[must_use]
+    pub fn endpoints(&self) -> &HashMap<String, WebhookEndpoint> {
+        &self.endpoints
+    }
+
+    /// Settings of the endpoint called `name`, if configured.
+    #[must_use]
+    pub fn endpoint(&self, name: &str) -> Option<&WebhookEndpoint> {
+        self.endpoints.get(name)
+    }
+
+    /// How often a delivery is tried before it is marked failed. Defaults
+    /// to 8, the last attempt coming about an hour after the first.
+    #[must_use]
+    pub fn max_attempts(&self) -> i32 {
+        self.max_attempts
+    }
+
+    /// How long to wait on an endpoint, in seconds. Defaults to 10.
+    #[must_use]
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+}
+
+/// A single webhook receiver.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookEndpoint {
+    url: String,
+    secret: SecretString,
+    #[serde(default)]
+    events: Vec<String>,
+}
+
+impl WebhookEndpoint {
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Key the payloads sent to this endpoint are signed with.
+    #[must_use]
+    pub fn secret(&self) -> &SecretString {
+        &self.secret
+    }
+
+    /// Whether the endpoint subscribed to `event`, e.g. `user.login`.
+    #[must_use]
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|wanted| wanted == event)
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+
+/// Documents served under `/.well-known/` for password managers and
+/// security researchers.
+///
+/// `GET /.well-known/change-password` redirects to `change_password_url`,
+/// the page where users change their password, so password managers can
+/// send them straight there; without it, it answers `404 Not Found` as the
+/// convention asks of sites without such a page.
+///
+/// `GET /.well-known/security.txt` serves the `security_txt` section as
+/// RFC 9116 describes, or answers `404 Not Found` without it, see
+/// [`SecurityTxtConfig`].
+///
+/// ```yaml
+/// well_known:
+///   change_password_url: "https://app.example.com/settings/password"
+///   security_txt:
+///     contact: ["mailto:security@example.com"]
+///     expires: "2027-06-30T00:00:00Z"
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct WellKnownConfig {
+    #[serde(default)]
+    change_password_url: Option<String>,
+    #[serde(default)]
+    security_txt: Option<SecurityTxtConfig>,
+}
+
+impl WellKnownConfig {
+    /// Page `/.well-known/change-password` redirects to.
+    #[must_use]
+    pub fn change_password_url(&self) -> Option<&str> {
+        self.change_password_url.as_deref()
+    }
+
+    /// Fields of `/.well-known/security.txt`, if it is served.
+    #[must_use]
+    pub fn security_txt(&self) -> Option<&SecurityTxtConfig> {
+        self.security_txt.as_ref()
+    }
+}
+
+/// Fields of `/.well-known/security.txt`, telling those who found a
+/// vulnerability how to report it (RFC 9116).
+///
+/// `contact` and `expires` are required; the file is considered stale once
+/// `expires` passes, so it should be moved forward, less than a year ahead,
+/// before then. The other fields are left out unless set.
+///
+/// ```yaml
+/// well_known:
+///   security_txt:
+///     contact: ["mailto:security@example.com", "https://example.com/report"]
+///     expires: "2027-06-30T00:00:00Z"
+///     encryption: ["https://example.com/pgp-key.txt"]
+///     acknowledgments: "https://example.com/hall-of-fame"
+///     policy: "https://example.com/disclosure-policy"
+///     preferred_languages: ["en", "fr"]
+///     canonical: ["https://auth.example.com/.well-known/security.txt"]
+///     hiring: "https://example.com/jobs"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct SecurityTxtConfig {
+    contact: Vec<String>,
+    expires: DateTime<Utc>,
+    #[serde(default)]
+    encryption: Vec<String>,
+    #[serde(default)]
+    acknowledgments: Option<String>,
+    #[serde(default)]
+    policy: Option<String>,
+    #[serde(default)]
+    preferred_languages: Vec<String>,
+    #[serde(default)]
+    canonical: Vec<String>,
+    #[serde(default)]
+    hiring: Option<String>,
+}
+
+impl SecurityTxtConfig {
+    /// Where to report vulnerabilities, as `mailto:`, `tel:` or `https://`
+    /// URIs, preferred first.
+    #[must_use]
+    pub fn contact(&self) -> &[String] {
+        &self.contact
+    }
+
+    /// When the file goes stale.
+    #[must_use]
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.expires
+    }
+
+    /// The file, one `Field: value` line per value.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use betterauth::config::SecurityTxtConfig;
+    /// let config: SecurityTxtConfig = serde_json::from_value(serde_json::json!({
+    ///     "contact": ["mailto:security@example.com"],
+    ///     "expires": "2027-06-30T00:00:00Z",
+    ///     "preferred_languages": ["en", "fr"],
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(
+    ///     config.render(),
+    ///     "Contact: mailto:security@example.com\n\
+    ///      Expires: 2027-06-30T00:00:00Z\n\
+    ///      Preferred-Languages: en, fr\n",
+    /// );
+    /// ```
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut fields: Vec<(&str, String)> = Vec::new();
+
+        fields.extend(self.contact.iter().map(|uri| ("Contact", uri.clone())));
+        fields.push((
+            "Expires",
+            self.expires.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ));
+        fields.extend(
+            self.encryption
+                .iter()
+                .map(|uri| ("Encryption", uri.clone())),
+        );
+        fields.extend(
+            self.acknowledgments
+                .iter()
+                .map(|uri| ("Acknowledgments", uri.clone())),
+        );
+        fields.extend(self.policy.iter().map(|uri| ("Policy", uri.clone())));
+        if !self.preferred_languages.is_empty() {
+            fields.push(("Preferred-Languages", self.preferred_languages.join(", ")));
+        }
+        fields.extend(self.canonical.iter().map(|uri| ("Canonical", uri.clone())));
+        fields.extend(self.hiring.iter().map(|uri| ("Hiring", uri.clone())));
+
+        fields
+            .into_iter()
+            .fold(String::new(), |mut text, (field, value)| {
+                let _ = writeln!(text, "{field}: {value}");
+                text
+            })
+    }
+}
@@ -1,6 +1,48 @@
-use sqlx::PgPool;
+use std::{sync::Arc, time::Duration as StdDuration};
 
-use crate::config::Config;
+use arc_swap::ArcSwap;
+use chrono::Duration;
+use serde_json::{Map, Value};
+use sqlx::{PgPool, PgTransaction};
+use uuid::Uuid;
+
+#[cfg(feature = "sqlite")]
+use crate::sqlite::{SqliteSessionStore, SqliteTokenStore, SqliteUserStore};
+use crate::{
+    Error, Result,
+    api_keys::ApiKeyStore,
+    audit::AuditLog,
+    auth::{Hashing, Passwords, PwnedPasswords, normalize_email},
+    cache::Cache,
+    clock::{Clock, SystemClock},
+    config::{
+        Config, CorsOrigins, MailTransport, PasswordPolicy, RedisConfig, SessionBackend,
+        SmsProvider,
+    },
+    db::{BoxFuture, ReadPool},
+    events::{Event, EventBus, LoginMethod, Subscriber},
+    i18n::Translations,
+    jobs::JobQueue,
+    limits::ConcurrencyLimiter,
+    mail::{EmailOtp, LogMailer, Mailer, NoopMailer, Notices, SmtpMailer, Templates},
+    metrics::LoginCounter,
+    oauth::OAuthClient,
+    oidc::{BackchannelLogout, ClientStore, OidcProvider},
+    organizations::AuthPolicy,
+    privacy::Privacy,
+    reports::Reports,
+    repositories::{PgUserStore, UserStore},
+    saml::SamlClient,
+    security::{CaptchaVerifier, LoginMonitor, LoginThrottle, RateLimiter, SiteVerify},
+    sessions::{
+        DeviceInfo, MemorySessionStore, PgSessionStore, RedisSessionStore, Session, SessionCookies,
+        SessionStore,
+    },
+    sms::{LogSmsSender, PhoneOtp, SmsSender, TwilioSender},
+    tokens::{AccessToken, ClaimsEnricher, PgTokenStore, TokenPair, TokenService, TokenStore},
+    webauthn::WebAuthn,
+    webhooks::Webhooks,
+};
 
 /// Shared application state container.
 ///
@@ -23,8 +65,41 @@ use crate::config::Config;
 ///
 /// # Fields
 ///
-/// - `config`: Application configuration loaded from files and environment variables
-/// - `db`: PostgreSQL connection pool for database operations
+/// - `config`: Application configuration loaded from files and environment variables, swapped by [`AppContext::reload()`]
+/// - `cors_origins`: Origins the CORS layer allows, updated on reload
+/// - `db`: PostgreSQL connection pool for database operations, on the primary
+/// - `db_read`: Read replicas read-only queries go to, the primary when none are configured
+/// - `cache`: Redis connection, present when the `redis` config section is set and Redis was reachable at startup
+/// - `tokens`: JWT access/refresh token issuer and verifier
+/// - `claims_enrichers`: Extra access token claims, none unless added via [`AppContext::with_claims_enricher()`]
+/// - `sessions`: Server-side session persistence, in the database or wherever `auth.session_backend` puts them, unless replaced via [`AppContext::with_session_store()`]
+/// - `session_cookies`: Session cookie attributes and sealing
+/// - `revocations`: Denylist of revoked access and refresh tokens, in Postgres unless replaced via [`AppContext::with_token_store()`]
+/// - `api_keys`: API key persistence and lookup
+/// - `users`: User persistence, in Postgres unless replaced via [`AppContext::with_user_store()`]
+/// - `audit`: Append-only log of security-relevant account events
+/// - `privacy`: Personal data exports and scheduled account erasure
+/// - `jobs`: Postgres-backed queue of deferred work, run by the job worker
+/// - `webhooks`: Signed deliveries of auth events to the configured endpoints
+/// - `events`: Subscribers to auth events, the audit log, webhooks, back-channel logout, email notices and login metrics unless more are added via [`AppContext::with_subscriber()`]
+/// - `login_throttle`: Failed login tracking and account lockout
+/// - `hashing`: Password hashing and verification on blocking threads, at most `password_hashing.max_concurrent` at once
+/// - `rate_limiter`: Per-IP and per-user request rate limits
+/// - `concurrency_limiter`: Caps on the requests handled at once, fixed at startup
+/// - `oauth`: OAuth2 client for the configured social login providers
+/// - `saml`: SAML 2.0 service provider for the configured tenants
+/// - `oidc`: OpenID Connect provider, present when the `oidc` config section is
+/// - `oauth_clients`: Applications registered to log in through the OIDC provider
+/// - `backchannel_logout`: Logout tokens sent to those applications when sessions they logged users in from end
+/// - `webauthn`: Passkey ceremonies, present when the `webauthn` config section is
+/// - `captcha`: CAPTCHA verifier, present when the `captcha` config section is or one is installed via [`AppContext::with_captcha()`]
+/// - `sms`: Text message delivery, present when the `sms` config section is or one is installed via [`AppContext::with_sms_sender()`]
+/// - `phone_otp`: One-time codes for phone verification and login
+/// - `email_otp`: One-time login codes sent by email
+/// - `mailer`: Outgoing email delivery, chosen by the `mailer` config section unless replaced via [`AppContext::with_mailer()`]
+/// - `mail_templates`: Templates auth emails are rendered from
+/// - `translations`: Message catalogs of the languages in the `i18n` config section
+/// - `clock`: Time source of session, token and one-time code expiry, the system clock unless another is given to [`AppContext::from_config_with_clock()`]
 ///
 /// # Examples
 ///
@@ -34,7 +109,7 @@ use crate::config::Config;
 /// use std::sync::Arc;
 ///
 /// use betterauth::{AppContext, Config};
-/// use axum::{Router, routing::get};
+/// use axum::{Router, extract::State, routing::get};
 ///
 /// #[tokio::main]
 /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -42,10 +117,10 @@ use crate::config::Config;
 ///     let config = Config::load()?;
 ///     
 ///     // Initialize logging
-///     config.logger().setup()?;
+///     let _guard = config.logger().setup()?;
 ///     
 ///     // Create application context
-///     let app_context = AppContext::from_config(&config).await;
+///     let app_context = AppContext::from_config(&config).await?;
 ///     
 ///     // Build router with shared state
 ///     let app = Router::new()
@@ -73,6 +148,8 @@ use crate::config::Config;
 /// ## Using in Handlers
 ///
 /// ```no_run
+/// use std::sync::Arc;
+///
 /// use axum::{extract::State, Json};
 /// use serde_json::{json, Value};
 /// use betterauth::AppContext;
@@ -80,35 +157,651 @@ use crate::config::Config;
 /// async fn get_users(
 ///     State(ctx): State<Arc<AppContext>>,
 /// ) -> Json<Value> {
-///     let users = sqlx::query!("SELECT id, name FROM users")
+///     let emails: Vec<String> = sqlx::query_scalar("SELECT email FROM users")
 ///         .fetch_all(ctx.db())
 ///         .await
 ///         .unwrap();
 ///     
-///     Json(json!({ "users": users }))
+///     Json(json!({ "users": emails }))
 /// }
 /// ```
 #[derive(Clone)]
 pub struct AppContext {
-    config: Config,
+    config: Arc<ArcSwap<Config>>,
+    cors_origins: CorsOrigins,
     db: PgPool,
+    db_read: ReadPool,
+    cache: Option<Cache>,
+    tokens: TokenService,
+    claims_enrichers: Vec<Arc<dyn ClaimsEnricher>>,
+    sessions: Arc<dyn SessionStore>,
+    session_cookies: SessionCookies,
+    revocations: Arc<dyn TokenStore>,
+    api_keys: ApiKeyStore,
+    users: Arc<dyn UserStore>,
+    audit: AuditLog,
+    reports: Reports,
+    privacy: Privacy,
+    jobs: JobQueue,
+    webhooks: Webhooks,
+    events: EventBus,
+    login_throttle: LoginThrottle,
+    hashing: Hashing,
+    pwned_passwords: PwnedPasswords,
+    rate_limiter: RateLimiter,
+    concurrency_limiter: Arc<ConcurrencyLimiter>,
+    oauth: OAuthClient,
+    saml: SamlClient,
+    oidc: Option<OidcProvider>,
+    oauth_clients: ClientStore,
+    backchannel_logout: BackchannelLogout,
+    webauthn: Option<WebAuthn>,
+    captcha: Option<Arc<dyn CaptchaVerifier>>,
+    sms: Option<Arc<dyn SmsSender>>,
+    phone_otp: PhoneOtp,
+    email_otp: EmailOtp,
+    mailer: Arc<dyn Mailer>,
+    mail_templates: Templates,
+    translations: Translations,
+    clock: Arc<dyn Clock>,
 }
 
 impl AppContext {
-    pub fn config(&self) -> &Config {
-        &self.config
+    /// The current configuration. Hold on to the returned value rather than
+    /// calling this repeatedly when several settings must agree, as a reload
+    /// may happen in between.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load_full()
+    }
+
+    /// `email` in the form accounts are told apart by, normalized as
+    /// `auth.email_normalization` asks.
+    pub fn normalize_email(&self, email: &str) -> String {
+        normalize_email(email, self.config().auth().email_normalization())
+    }
+
+    pub fn cors_origins(&self) -> &CorsOrigins {
+        &self.cors_origins
+    }
+
+    /// Applies the settings of `config` that can change while running, see
+    /// [`Config::reloaded`]: the log level, rate limits, CORS origins and
+    /// feature switches.
+    ///
+    /// ## Errors
+    /// * [`crate::config::ConfigError`] if the log filter could not be
+    ///   replaced; nothing is applied then
+    pub fn reload(&self, config: &Config) -> Result<()> {
+        config.logger().reload()?;
+        self.rate_limiter.reconfigure(config.rate_limit().clone());
+        self.cors_origins.update(config.cors());
+        self.config
+            .store(Arc::new(self.config.load().reloaded(config)));
+
+        Ok(())
     }
 
+    /// The primary pool, same as [`AppContext::db_write`].
     pub fn db(&self) -> &PgPool {
         &self.db
     }
 
-    pub async fn from_config(config: &Config) -> Self {
+    /// The primary pool, for writes and reads that must see them.
+    pub fn db_write(&self) -> &PgPool {
+        &self.db
+    }
+
+    /// The read replicas, for read-only queries that may lag behind the
+    /// primary.
+    pub fn db_read(&self) -> &ReadPool {
+        &self.db_read
+    }
+
+    /// Runs `work` in a transaction on the primary, committed if it returns
+    /// `Ok` and rolled back if it returns `Err`, so flows changing several
+    /// tables apply all their changes or none. Repository methods taking an
+    /// `executor`, such as [`PgUserStore::insert`], join it with `&mut **tx`.
+    ///
+    /// ```no_run
+    /// # async fn example(ctx: &betterauth::AppContext, id: uuid::Uuid) -> betterauth::Result<()> {
+    /// ctx.transaction(|tx| {
+    ///     Box::pin(async move {
+    ///         sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+    ///             .bind(id)
+    ///             .execute(&mut **tx)
+    ///             .await?;
+    ///         sqlx::query("UPDATE users SET disabled_at = now() WHERE id = $1")
+    ///             .bind(id)
+    ///             .execute(&mut **tx)
+    ///             .await?;
+    ///         Ok(())
+    ///     })
+    /// })
+    /// .await
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    /// * The error `work` returns
+    /// * Database errors beginning or committing the transaction
+    pub async fn transaction<'a, T, F>(&self, work: F) -> Result<T>
+    where
+        F: for<'tx> FnOnce(&'tx mut PgTransaction<'a>) -> BoxFuture<'tx, Result<T>>,
+    {
+        let mut tx: PgTransaction<'a> = self.db.begin().await?;
+
+        match work(&mut tx).await {
+            Ok(value) => {
+                tx.commit().await?;
+                Ok(value)
+            }
+            Err(err) => {
+                if let Err(rollback) = tx.rollback().await {
+                    tracing::warn!(error = %rollback, "Failed to roll back the transaction");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Redis connection, if configured and reachable at startup.
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    pub fn tokens(&self) -> &TokenService {
+        &self.tokens
+    }
+
+    /// Issues a fresh access/refresh pair for `session`, with the claims of
+    /// every [`ClaimsEnricher`] added to the access token.
+    ///
+    /// ## Errors
+    /// * The first error returned by an enricher
+    /// * See [`TokenService::issue_pair`]
+    pub async fn issue_tokens(&self, session: &Session) -> Result<TokenPair> {
+        let claims = self.enriched_claims(session).await?;
+
+        self.tokens.issue_pair_with(session, claims)
+    }
+
+    /// Issues an access token for `session` to the single sign-on
+    /// application `audience`, with the claims of every [`ClaimsEnricher`]
+    /// added.
+    ///
+    /// ## Errors
+    /// * The first error returned by an enricher
+    /// * See [`TokenService::issue_access`]
+    pub async fn issue_access_token(
+        &self,
+        session: &Session,
+        audience: &str,
+    ) -> Result<AccessToken> {
+        let claims = self.enriched_claims(session).await?;
+
+        self.tokens.issue_access(session, audience, claims)
+    }
+
+    async fn enriched_claims(&self, session: &Session) -> Result<Map<String, Value>> {
+        let mut claims = Map::new();
+        for enricher in &self.claims_enrichers {
+            enricher.enrich(self, session, &mut claims).await?;
+        }
+
+        Ok(claims)
+    }
+
+    /// Adds `enricher` to the ones run when access tokens are minted, after
+    /// those added before.
+    #[must_use]
+    pub fn with_claims_enricher(mut self, enricher: impl ClaimsEnricher + 'static) -> Self {
+        self.claims_enrichers.push(Arc::new(enricher));
+        self
+    }
+
+    pub fn sessions(&self) -> &dyn SessionStore {
+        self.sessions.as_ref()
+    }
+
+    /// The `password_policy` with the password rules of the organizations
+    /// of `user_id` applied, which new passwords of the user must satisfy.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn password_policy_for(&self, user_id: Uuid) -> Result<PasswordPolicy> {
+        let policy = AuthPolicy::for_user(self.db(), user_id).await?;
+
+        Ok(self.config().password_policy().tightened(&policy.password))
+    }
+
+    /// Starts a session for `user_id`, who just proved who they are with
+    /// `method` from `device`, holding it to the [`AuthPolicy`] of their
+    /// organizations. Every login method goes through here.
+    ///
+    /// ## Errors
+    /// * [`Error::LoginMethodNotAllowed`] if the policy rules `method` out
+    /// * See [`SessionStore::create`]
+    pub async fn start_session(
+        &self,
+        user_id: Uuid,
+        device: &DeviceInfo,
+        method: &LoginMethod,
+    ) -> Result<(Session, String)> {
+        let policy = AuthPolicy::for_user(self.db(), user_id).await?;
+        if !policy.allows(method) {
+            tracing::info!(%user_id, method = method.name(), "Login refused by organization policy");
+            return Err(Error::LoginMethodNotAllowed);
+        }
+
+        let (session, token) = self.sessions.create(user_id, device).await?;
+
+        let lifetime = self.config().auth().lifetimes().session();
+        let Some(limit) = policy
+            .session_lifetime
+            .map(StdDuration::from_secs)
+            .filter(|limit| *limit < lifetime)
+        else {
+            return Ok((session, token));
+        };
+
+        let max_expires_at =
+            session.created_at + Duration::from_std(limit).unwrap_or(Duration::MAX);
+        let session = self
+            .sessions
+            .limit(session.id, max_expires_at)
+            .await?
+            .unwrap_or(session);
+
+        Ok((session, token))
+    }
+
+    pub fn session_cookies(&self) -> &SessionCookies {
+        &self.session_cookies
+    }
+
+    pub fn revocations(&self) -> &dyn TokenStore {
+        self.revocations.as_ref()
+    }
+
+    pub fn api_keys(&self) -> &ApiKeyStore {
+        &self.api_keys
+    }
+
+    pub fn users(&self) -> &dyn UserStore {
+        self.users.as_ref()
+    }
+
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    pub fn reports(&self) -> &Reports {
+        &self.reports
+    }
+
+    pub fn privacy(&self) -> &Privacy {
+        &self.privacy
+    }
+
+    pub fn jobs(&self) -> &JobQueue {
+        &self.jobs
+    }
+
+    pub fn webhooks(&self) -> &Webhooks {
+        &self.webhooks
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Hands `event` to every subscriber, see [`EventBus::publish`].
+    ///
+    /// ## Errors
+    /// * The first error returned by a subscriber
+    pub async fn publish(&self, event: Event) -> Result<()> {
+        self.events.publish(self, &event).await
+    }
+
+    /// Adds `subscriber` to the events, after the built-in ones.
+    #[must_use]
+    pub fn with_subscriber(mut self, subscriber: impl Subscriber + 'static) -> Self {
+        self.events.subscribe(subscriber);
+        self
+    }
+
+    pub fn login_throttle(&self) -> &LoginThrottle {
+        &self.login_throttle
+    }
+
+    /// For request handlers, [`AppContext::hashing`] hashes and verifies
+    /// without blocking the runtime.
+    pub fn passwords(&self) -> &Passwords {
+        self.hashing.passwords()
+    }
+
+    pub fn hashing(&self) -> &Hashing {
+        &self.hashing
+    }
+
+    pub fn pwned_passwords(&self) -> &PwnedPasswords {
+        &self.pwned_passwords
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn concurrency_limiter(&self) -> &ConcurrencyLimiter {
+        &self.concurrency_limiter
+    }
+
+    pub fn oauth(&self) -> &OAuthClient {
+        &self.oauth
+    }
+
+    pub fn saml(&self) -> &SamlClient {
+        &self.saml
+    }
+
+    /// The OpenID Connect provider.
+    ///
+    /// ## Errors
+    /// * [`Error::Disabled`] if the provider is not configured
+    pub fn oidc(&self) -> Result<&OidcProvider> {
+        self.oidc
+            .as_ref()
+            .ok_or(Error::Disabled("openid connect provider"))
+    }
+
+    pub fn oauth_clients(&self) -> &ClientStore {
+        &self.oauth_clients
+    }
+
+    pub fn backchannel_logout(&self) -> &BackchannelLogout {
+        &self.backchannel_logout
+    }
+
+    /// The passkey relying party.
+    ///
+    /// ## Errors
+    /// * [`Error::Disabled`] if passkeys are not configured
+    pub fn webauthn(&self) -> Result<&WebAuthn> {
+        self.webauthn
+            .as_ref()
+            .ok_or(Error::Disabled("passkey login"))
+    }
+
+    /// The CAPTCHA verifier, if CAPTCHAs are required.
+    pub fn captcha(&self) -> Option<&dyn CaptchaVerifier> {
+        self.captcha.as_deref()
+    }
+
+    /// Requires CAPTCHAs on sensitive endpoints, checked by `verifier`.
+    #[must_use]
+    pub fn with_captcha(mut self, verifier: impl CaptchaVerifier + 'static) -> Self {
+        self.captcha = Some(Arc::new(verifier));
+        self
+    }
+
+    /// The text message sender.
+    ///
+    /// ## Errors
+    /// * [`Error::Disabled`] if no sender is configured
+    pub fn sms(&self) -> Result<&dyn SmsSender> {
+        self.sms
+            .as_deref()
+            .ok_or(Error::Disabled("sms authentication"))
+    }
+
+    /// Enables phone login, delivering codes through `sender`.
+    #[must_use]
+    pub fn with_sms_sender(mut self, sender: impl SmsSender + 'static) -> Self {
+        self.sms = Some(Arc::new(sender));
+        self
+    }
+
+    pub fn phone_otp(&self) -> &PhoneOtp {
+        &self.phone_otp
+    }
+
+    pub fn email_otp(&self) -> &EmailOtp {
+        &self.email_otp
+    }
+
+    pub fn mailer(&self) -> &dyn Mailer {
+        self.mailer.as_ref()
+    }
+
+    pub fn mail_templates(&self) -> &Templates {
+        &self.mail_templates
+    }
+
+    pub fn translations(&self) -> &Translations {
+        &self.translations
+    }
+
+    /// Replaces the Postgres user store with another backend.
+    #[must_use]
+    pub fn with_user_store(mut self, users: impl UserStore + 'static) -> Self {
+        self.users = Arc::new(users);
+        self
+    }
+
+    /// Replaces the Postgres session store with another backend.
+    #[must_use]
+    pub fn with_session_store(mut self, sessions: impl SessionStore + 'static) -> Self {
+        self.sessions = Arc::new(sessions);
+        self
+    }
+
+    /// Replaces the Postgres token denylist with another backend.
+    #[must_use]
+    pub fn with_token_store(mut self, revocations: impl TokenStore + 'static) -> Self {
+        self.revocations = Arc::new(revocations);
+        self
+    }
+
+    /// Replaces the mailer used to deliver auth emails.
+    #[must_use]
+    pub fn with_mailer(mut self, mailer: impl Mailer + 'static) -> Self {
+        self.mailer = Arc::new(mailer);
+        self
+    }
+
+    /// The time source session, token and one-time code expiry is computed
+    /// against.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// ## Errors
+    /// * [`crate::config::ConfigError::Invalid`] if a signing key or the
+    ///   SMTP settings cannot be used
+    /// * [`Error::Mail`] if the SMTP TLS backend cannot be initialised
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        Self::from_config_with_clock(config, Arc::new(SystemClock)).await
+    }
+
+    /// Like [`AppContext::from_config`], but telling the time with `clock`,
+    /// e.g. a [`crate::clock::MockClock`] in tests. Stores installed later
+    /// through the `with_*` methods bring their own clock.
+    ///
+    /// ## Errors
+    /// * As for [`AppContext::from_config`]
+    pub async fn from_config_with_clock(config: &Config, clock: Arc<dyn Clock>) -> Result<Self> {
         let db = config.database().connect_using_options().await;
+        let db_read = ReadPool::with_replicas(
+            db.clone(),
+            config
+                .database()
+                .connect_replicas()
+                .expect("database.replicas are validated on load"),
+        );
+        let redis = config.redis();
+        let cache = match redis {
+            Some(redis) => Cache::connect(redis).await,
+            None => None,
+        };
+        let shared = |enabled: fn(&RedisConfig) -> bool| {
+            cache.clone().filter(|_| redis.is_some_and(enabled))
+        };
 
-        Self {
-            config: config.clone(),
-            db,
+        let lifetimes = config.auth().lifetimes();
+        let mut sessions = PgSessionStore::new(db.clone(), lifetimes.session().as_secs())
+            .with_idle_timeout(lifetimes.session_idle().as_secs())
+            .with_clock(clock.clone());
+        if let Some(cache) = shared(RedisConfig::sessions) {
+            sessions = sessions.with_cache(cache);
+        }
+        let mut revocations = PgTokenStore::new(db.clone()).with_clock(clock.clone());
+        if let Some(cache) = shared(RedisConfig::revocations) {
+            revocations = revocations.with_cache(cache);
+        }
+        let mut rate_limiter = RateLimiter::new(config.rate_limit().clone());
+        if let Some(cache) = shared(RedisConfig::rate_limit) {
+            rate_limiter = rate_limiter.with_cache(cache);
+        }
+        let mut login_throttle = LoginThrottle::new(db.clone(), config.security().clone());
+        if let Some(cache) = shared(RedisConfig::rate_limit) {
+            login_throttle = login_throttle.with_cache(cache);
+        }
+        let tokens = TokenService::from_config(config.auth())?
+            .with_clock(clock.clone())
+            .with_key_store(db.clone(), config.auth().secret().expose());
+        if let Err(err) = tokens.refresh_keys().await {
+            tracing::warn!(error = %err, "Failed to load signing keys, using the configured one");
         }
+        let audit = AuditLog::new(db.clone());
+        let webhooks = Webhooks::new(db.clone(), config.webhooks().clone());
+        let backchannel_logout = BackchannelLogout::new(db.clone());
+        let mut reports = Reports::new(db.clone()).with_reads(db_read.clone());
+        if let Some(cache) = cache.clone() {
+            reports = reports.with_cache(cache);
+        }
+
+        let mut events = EventBus::default();
+        events.subscribe(audit.clone());
+        events.subscribe(webhooks.clone());
+        events.subscribe(backchannel_logout.clone());
+        events.subscribe(Notices);
+        events.subscribe(LoginMonitor::new(db.clone()));
+        events.subscribe(LoginCounter);
+        events.subscribe(reports.clone());
+
+        let ctx = Self {
+            config: Arc::new(ArcSwap::from_pointee(config.clone())),
+            cors_origins: CorsOrigins::new(config.cors()),
+            tokens,
+            claims_enrichers: Vec::new(),
+            sessions: Arc::new(sessions),
+            session_cookies: SessionCookies::from_config(
+                config.cookies(),
+                config.server().is_https(),
+                lifetimes.session().as_secs(),
+            ),
+            revocations: Arc::new(revocations),
+            api_keys: ApiKeyStore::new(db.clone()),
+            users: Arc::new(PgUserStore::new(db.clone()).with_reads(db_read.clone())),
+            audit,
+            reports,
+            privacy: Privacy::new(db.clone(), config.privacy().clone()),
+            jobs: JobQueue::new(db.clone()),
+            webhooks,
+            events,
+            login_throttle,
+            hashing: Hashing::new(
+                Passwords::from_config(config.password_hashing()),
+                config.password_hashing(),
+            ),
+            pwned_passwords: PwnedPasswords::from_config(config.password_policy()),
+            rate_limiter,
+            concurrency_limiter: Arc::new(ConcurrencyLimiter::from_config(
+                config.server().concurrency(),
+            )),
+            oauth: OAuthClient::from_config(config.oauth()),
+            saml: SamlClient::new(config.saml(), config.server().url()),
+            oidc: config
+                .oidc()
+                .map(|oidc| OidcProvider::from_config(oidc, config.server().url()))
+                .transpose()?,
+            oauth_clients: ClientStore::new(db.clone()),
+            backchannel_logout,
+            webauthn: config.webauthn().map(WebAuthn::from_config),
+            captcha: config.captcha().map(|captcha| {
+                Arc::new(SiteVerify::from_config(captcha)) as Arc<dyn CaptchaVerifier>
+            }),
+            sms: config.sms().map(|sms| match sms.provider() {
+                SmsProvider::Twilio => {
+                    Arc::new(TwilioSender::from_config(sms)) as Arc<dyn SmsSender>
+                }
+                SmsProvider::Log => Arc::new(LogSmsSender),
+            }),
+            phone_otp: PhoneOtp::new(
+                db.clone(),
+                config.auth().secret().expose(),
+                config.sms().cloned().unwrap_or_default(),
+            )
+            .with_clock(clock.clone()),
+            email_otp: EmailOtp::new(
+                db.clone(),
+                config.auth().secret().expose(),
+                config.email_otp().clone(),
+            )
+            .with_clock(clock.clone()),
+            mailer: match config.mailer().transport() {
+                MailTransport::Smtp => Arc::new(SmtpMailer::from_config(config.mailer())?),
+                MailTransport::Log => Arc::new(LogMailer),
+                MailTransport::Noop => Arc::new(NoopMailer),
+            },
+            mail_templates: Templates::from_config(config.mailer(), &config.server().url()),
+            translations: Translations::from_config(config.i18n()),
+            db,
+            db_read,
+            cache,
+            clock: clock.clone(),
+        };
+
+        #[cfg(feature = "sqlite")]
+        let ctx = if config.database().driver() == crate::config::DatabaseDriver::Sqlite {
+            let sqlite = config
+                .database()
+                .connect_sqlite()
+                .expect("database.uri is validated on load");
+
+            ctx.with_user_store(SqliteUserStore::new(sqlite.clone()))
+                .with_session_store(
+                    SqliteSessionStore::new(sqlite.clone(), lifetimes.session().as_secs())
+                        .with_idle_timeout(lifetimes.session_idle().as_secs())
+                        .with_clock(clock.clone()),
+                )
+                .with_token_store(SqliteTokenStore::new(sqlite).with_clock(clock.clone()))
+        } else {
+            ctx
+        };
+
+        let ctx = match (config.auth().session_backend(), ctx.cache.clone()) {
+            (SessionBackend::Database, _) => ctx,
+            (SessionBackend::Memory, _) => {
+                let sessions =
+                    MemorySessionStore::new(ctx.users.clone(), lifetimes.session().as_secs())
+                        .with_idle_timeout(lifetimes.session_idle().as_secs())
+                        .with_clock(clock);
+                ctx.with_session_store(sessions)
+            }
+            (SessionBackend::Redis, Some(cache)) => {
+                let sessions =
+                    RedisSessionStore::new(cache, ctx.users.clone(), lifetimes.session().as_secs())
+                        .with_idle_timeout(lifetimes.session_idle().as_secs())
+                        .with_clock(clock);
+                ctx.with_session_store(sessions)
+            }
+            (SessionBackend::Redis, None) => {
+                tracing::warn!("Redis is unavailable, keeping sessions in the database");
+                ctx
+            }
+        };
+
+        Ok(ctx)
     }
 }
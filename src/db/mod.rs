@@ -0,0 +1,152 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+
+use crate::{Error, Result};
+
+/// The future a closure given to [`crate::AppContext::transaction`] returns,
+/// borrowing the transaction for `'tx`.
+pub type BoxFuture<'tx, T> = Pin<Box<dyn Future<Output = T> + Send + 'tx>>;
+
+/// How long a replica that could not be reached is skipped before it is
+/// tried again.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// A Postgres read replica and when it last failed.
+struct Replica {
+    pool: PgPool,
+    failed_at: Mutex<Option<Instant>>,
+}
+
+impl Replica {
+    fn is_available(&self) -> bool {
+        self.failed_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_none_or(|at| at.elapsed() >= RETRY_AFTER)
+    }
+
+    fn mark(&self, failed: bool) {
+        *self
+            .failed_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = failed.then(Instant::now);
+    }
+}
+
+/// Pools read-only queries run on: the read replicas configured in
+/// `database.replicas`, taken in turn, or the primary when there are none.
+///
+/// A replica that cannot be reached is skipped for a while and its query
+/// retried on the next one, falling back to the primary when none is left.
+/// Replicas lag behind the primary, so rows written moments ago may not be
+/// visible yet; queries that must see them run on
+/// [`crate::AppContext::db_write`].
+///
+/// ```no_run
+/// # async fn example(ctx: &betterauth::AppContext) -> betterauth::Result<()> {
+/// let users: i64 = ctx
+///     .db_read()
+///     .run(|db| async move {
+///         sqlx::query_scalar("SELECT COUNT(*) FROM users")
+///             .fetch_one(&db)
+///             .await
+///     })
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ReadPool {
+    primary: PgPool,
+    replicas: Arc<[Replica]>,
+    next: Arc<AtomicUsize>,
+}
+
+impl ReadPool {
+    /// Reads from `primary` alone.
+    #[must_use]
+    pub fn new(primary: PgPool) -> Self {
+        Self::with_replicas(primary, Vec::new())
+    }
+
+    /// Reads from `replicas`, falling back to `primary`.
+    #[must_use]
+    pub fn with_replicas(primary: PgPool, replicas: Vec<PgPool>) -> Self {
+        let replicas = replicas
+            .into_iter()
+            .map(|pool| Replica {
+                pool,
+                failed_at: Mutex::new(None),
+            })
+            .collect();
+
+        Self {
+            primary,
+            replicas,
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// The replica pools, in the order they were configured.
+    pub fn replicas(&self) -> impl Iterator<Item = &PgPool> {
+        self.replicas.iter().map(|replica| &replica.pool)
+    }
+
+    /// Runs `query` on the next available replica, and again on the
+    /// following ones and finally the primary for as long as the database
+    /// cannot be reached.
+    ///
+    /// ## Errors
+    /// * The error of `query` on the last pool tried
+    pub async fn run<T, E, F, Fut>(&self, query: F) -> Result<T>
+    where
+        F: Fn(PgPool) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<Error>,
+    {
+        let count = self.replicas.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+
+        for offset in 0..count {
+            let replica = &self.replicas[(start + offset) % count];
+            if !replica.is_available() {
+                continue;
+            }
+
+            match query(replica.pool.clone()).await.map_err(Into::into) {
+                Err(Error::Sqlx(err)) if is_unreachable(&err) => {
+                    tracing::warn!(error = %err, "Read replica is unreachable, trying the next pool");
+                    replica.mark(true);
+                }
+                result => {
+                    replica.mark(false);
+                    return result;
+                }
+            }
+        }
+
+        query(self.primary.clone()).await.map_err(Into::into)
+    }
+}
+
+/// Whether `err` means the database could not be reached, rather than that
+/// the query failed.
+fn is_unreachable(err: &sqlx::Error) -> bool {
+    matches!(
+        err,
+        sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+    )
+}
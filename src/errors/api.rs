@@ -0,0 +1,148 @@
+use axum::{
+    Json,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use super::Error;
+use crate::{auth::PasswordViolation, i18n, trace::REQUEST_ID_HEADER};
+
+tokio::task_local! {
+    /// ID of the request being handled, set by [`request_id`].
+    static REQUEST_ID: Option<String>;
+}
+
+const PROBLEM_JSON: HeaderValue = HeaderValue::from_static("application/problem+json");
+
+/// An error as the client sees it, rendered as an RFC 7807
+/// `application/problem+json` document:
+///
+/// ```json
+/// {
+///   "type": "about:blank",
+///   "title": "Conflict",
+///   "status": 409,
+///   "code": "email_taken",
+///   "detail": "an account with this email already exists",
+///   "request_id": "1b4e28ba-2fa1-11d2-883f-0016d3cca427"
+/// }
+/// ```
+///
+/// Password policy failures add a `violations` array naming each failed rule,
+/// and rate limit and load shedding failures a `Retry-After` header. `request_id` is only
+/// present behind the [`request_id`] middleware, and `detail` is only
+/// translated behind [`crate::i18n::locale`], see
+/// [`crate::i18n::Translations`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiError {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    #[serde(serialize_with = "serialize_status")]
+    status: StatusCode,
+    code: &'static str,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    violations: Option<Vec<PasswordViolation>>,
+    #[serde(skip)]
+    retry_after: Option<u64>,
+}
+
+impl ApiError {
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Machine-readable error code, e.g. `email_taken`.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Message meant for people, never containing server-side details.
+    #[must_use]
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+fn serialize_status<S: serde::Serializer>(
+    status: &StatusCode,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u16(status.as_u16())
+}
+
+/// Server-side failures are logged and replaced with a generic message so
+/// that database or configuration details never reach the client.
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let status = error.status();
+
+        let code = error.code();
+
+        // Shedding load is expected under a flood, not a failure to log.
+        let detail = if status.is_server_error() && !matches!(error, Error::Overloaded { .. }) {
+            tracing::error!(error = %error, "Request failed");
+            i18n::translate("errors.internal_error", &[])
+                .unwrap_or_else(|| String::from("internal server error"))
+        } else {
+            error
+                .message_args()
+                .and_then(|args| i18n::translate(&format!("errors.{code}"), &args))
+                .unwrap_or_else(|| error.to_string())
+        };
+
+        let (violations, retry_after) = match error {
+            Error::WeakPassword(violations) => (Some(violations), None),
+            Error::RateLimited { retry_after } | Error::Overloaded { retry_after } => {
+                (None, Some(retry_after))
+            }
+            _ => (None, None),
+        };
+
+        Self {
+            kind: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status,
+            code,
+            detail,
+            request_id: REQUEST_ID.try_with(Clone::clone).ok().flatten(),
+            violations,
+            retry_after,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(&self)).into_response();
+
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, PROBLEM_JSON);
+        if let Some(retry_after) = self.retry_after {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+
+        response
+    }
+}
+
+/// Makes the ID of the request available to the errors it fails with, so
+/// that their problem documents carry it. Must run inside the layer that
+/// sets the request ID.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .map(ToOwned::to_owned);
+
+    REQUEST_ID.scope(request_id, next.run(request)).await
+}
@@ -1,4 +1,12 @@
-use crate::config::ConfigError;
+mod api;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+pub use self::api::{ApiError, request_id};
+use crate::{auth::PasswordViolation, config::ConfigError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -6,6 +14,236 @@ pub enum Error {
     Config(#[from] ConfigError),
     #[error(transparent)]
     IO(#[from] tokio::io::Error),
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error("failed to hash password: {0}")]
+    PasswordHash(argon2::password_hash::Error),
+    #[error("failed to verify bcrypt hash: {0}")]
+    Bcrypt(bcrypt::BcryptError),
+    /// A password hash was made with a pepper version that is not configured.
+    #[error("password hash uses unknown pepper version {0}")]
+    MissingPepper(u32),
+    #[error("failed to sign token: {0}")]
+    Jwt(jsonwebtoken::errors::Error),
+    #[error("failed to render email: {0}")]
+    Template(tera::Error),
+    /// An OAuth provider failed or returned something unusable.
+    #[error("oauth provider error: {0}")]
+    OAuth(String),
+    /// The CAPTCHA verification service failed or returned something unusable.
+    #[error("captcha provider error: {0}")]
+    Captcha(String),
+    /// The mail server failed or rejected a message.
+    #[error("mail delivery error: {0}")]
+    Mail(String),
+    /// The text message gateway failed or rejected a message.
+    #[error("sms provider error: {0}")]
+    Sms(String),
+    /// The breached password service failed or returned an error.
+    #[error("breached password lookup failed: {0}")]
+    PwnedPasswords(String),
+    /// A webhook endpoint could not be reached or rejected a delivery.
+    #[error("webhook delivery error: {0}")]
+    Webhook(String),
+    /// A client's back-channel logout URI could not be reached or rejected a
+    /// logout token.
+    #[error("back-channel logout delivery error: {0}")]
+    LogoutDelivery(String),
+    /// An audit sink could not be reached or refused a batch of events.
+    #[error("audit export error: {0}")]
+    AuditSink(String),
+
+    /// The request payload failed validation; the message is shown to the client.
+    #[error("{0}")]
+    Validation(String),
+    /// A new password failed the configured password policy.
+    #[error("password does not meet the password policy")]
+    WeakPassword(Vec<PasswordViolation>),
+    #[error("an account with this email already exists")]
+    EmailTaken,
+    #[error("this username is already taken")]
+    UsernameTaken,
+    #[error("an organization with this slug already exists")]
+    SlugTaken,
+    #[error("this phone number is already in use")]
+    PhoneTaken,
+    #[error("invalid email or password")]
+    InvalidCredentials,
+    #[error("invalid or expired token")]
+    InvalidToken,
+    #[error("authentication required")]
+    Unauthenticated,
+    #[error("not allowed to perform this action")]
+    Forbidden,
+    /// The CAPTCHA token was missing or refused.
+    #[error("captcha verification failed")]
+    CaptchaFailed,
+    /// A cookie-authenticated request lacked the session's CSRF token.
+    #[error("missing or invalid csrf token")]
+    CsrfFailed,
+    #[error("too many failed login attempts, try again later")]
+    TooManyAttempts,
+    /// The request took longer than its configured timeout.
+    #[error("request timed out")]
+    Timeout,
+    /// The request body exceeded the configured limit, in bytes.
+    #[error("request body is larger than {0} bytes")]
+    PayloadTooLarge(usize),
+    /// A rate limit was exceeded; `retry_after` is in seconds.
+    #[error("too many requests, try again later")]
+    RateLimited { retry_after: u64 },
+    /// The request was shed because too many are being handled, see
+    /// [`crate::config::ConcurrencyConfig`]; `retry_after` is in seconds.
+    #[error("the server is busy, try again later")]
+    Overloaded { retry_after: u64 },
+    #[error("account has been disabled")]
+    AccountDisabled,
+    #[error("email address has not been verified")]
+    EmailNotVerified,
+    /// The policy of an organization the user belongs to rules out the
+    /// login method, see [`crate::organizations::AuthPolicy`].
+    #[error("your organization does not allow logging in this way")]
+    LoginMethodNotAllowed,
+    /// The password is correct but fails the password rules of an
+    /// organization the user belongs to; it must be reset first.
+    #[error("your organization requires a stronger password, reset it to continue")]
+    PasswordChangeRequired,
+    /// The operation needs the caller to have authenticated recently, see
+    /// [`crate::auth::RequireRecentAuth`].
+    #[error("this action requires you to reauthenticate")]
+    ReauthenticationRequired,
+    #[error("{0} not found")]
+    NotFound(&'static str),
+    #[error("unknown or disabled oauth provider")]
+    UnknownProvider,
+    /// An optional feature was called without being configured.
+    #[error("{0} is not enabled")]
+    Disabled(&'static str),
+    /// A WebAuthn response failed verification.
+    #[error("passkey verification failed: {0}")]
+    WebAuthn(String),
+    /// A SAML response from an identity provider failed validation.
+    #[error("saml response rejected: {0}")]
+    Saml(String),
+}
+
+impl Error {
+    /// Machine-readable code of the error, e.g. `email_taken`. Server-side
+    /// failures share `internal_error`, like they share their message.
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Config(_)
+            | Self::IO(_)
+            | Self::Redis(_)
+            | Self::Sqlx(_)
+            | Self::PasswordHash(_)
+            | Self::Bcrypt(_)
+            | Self::MissingPepper(_)
+            | Self::Jwt(_)
+            | Self::Template(_) => "internal_error",
+            Self::OAuth(_) => "oauth_provider_error",
+            Self::Captcha(_) => "captcha_provider_error",
+            Self::Mail(_) => "mail_delivery_failed",
+            Self::Sms(_) => "sms_delivery_failed",
+            Self::PwnedPasswords(_) => "breached_password_lookup_failed",
+            Self::Webhook(_) => "webhook_delivery_failed",
+            Self::LogoutDelivery(_) => "logout_delivery_failed",
+            Self::AuditSink(_) => "audit_export_failed",
+            Self::Validation(_) => "validation_failed",
+            Self::WeakPassword(_) => "weak_password",
+            Self::EmailTaken => "email_taken",
+            Self::UsernameTaken => "username_taken",
+            Self::SlugTaken => "slug_taken",
+            Self::PhoneTaken => "phone_taken",
+            Self::InvalidCredentials => "invalid_credentials",
+            Self::InvalidToken => "invalid_token",
+            Self::Unauthenticated => "unauthenticated",
+            Self::Forbidden => "forbidden",
+            Self::CaptchaFailed => "captcha_failed",
+            Self::CsrfFailed => "csrf_failed",
+            Self::TooManyAttempts => "too_many_attempts",
+            Self::Timeout => "timeout",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Overloaded { .. } => "overloaded",
+            Self::AccountDisabled => "account_disabled",
+            Self::EmailNotVerified => "email_not_verified",
+            Self::LoginMethodNotAllowed => "login_method_not_allowed",
+            Self::PasswordChangeRequired => "password_change_required",
+            Self::ReauthenticationRequired => "reauthentication_required",
+            Self::NotFound(_) => "not_found",
+            Self::UnknownProvider => "unknown_provider",
+            Self::Disabled(_) => "disabled",
+            Self::WebAuthn(_) => "passkey_verification_failed",
+            Self::Saml(_) => "saml_response_rejected",
+        }
+    }
+
+    /// The values the message of the error is made of, by name, for a
+    /// translation to fill in. `None` when the message is free text, which
+    /// is shown as it is.
+    fn message_args(&self) -> Option<Vec<(&'static str, String)>> {
+        match self {
+            Self::Validation(_) | Self::WebAuthn(_) | Self::Saml(_) => None,
+            Self::NotFound(resource) => Some(vec![("resource", (*resource).to_owned())]),
+            Self::Disabled(feature) => Some(vec![("feature", (*feature).to_owned())]),
+            Self::PayloadTooLarge(limit) => Some(vec![("limit", limit.to_string())]),
+            _ => Some(Vec::new()),
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Validation(_) | Self::WeakPassword(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::EmailTaken | Self::UsernameTaken | Self::SlugTaken | Self::PhoneTaken => {
+                StatusCode::CONFLICT
+            }
+            Self::InvalidCredentials
+            | Self::InvalidToken
+            | Self::Unauthenticated
+            | Self::WebAuthn(_)
+            | Self::Saml(_) => StatusCode::UNAUTHORIZED,
+            Self::EmailNotVerified
+            | Self::LoginMethodNotAllowed
+            | Self::PasswordChangeRequired
+            | Self::ReauthenticationRequired
+            | Self::AccountDisabled
+            | Self::Forbidden
+            | Self::CaptchaFailed
+            | Self::CsrfFailed => StatusCode::FORBIDDEN,
+            Self::NotFound(_) | Self::UnknownProvider | Self::Disabled(_) => StatusCode::NOT_FOUND,
+            Self::Timeout => StatusCode::REQUEST_TIMEOUT,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::TooManyAttempts | Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::OAuth(_)
+            | Self::Captcha(_)
+            | Self::Mail(_)
+            | Self::Sms(_)
+            | Self::PwnedPasswords(_)
+            | Self::Webhook(_)
+            | Self::LogoutDelivery(_)
+            | Self::AuditSink(_) => StatusCode::BAD_GATEWAY,
+            Self::Config(_)
+            | Self::IO(_)
+            | Self::Sqlx(_)
+            | Self::Redis(_)
+            | Self::PasswordHash(_)
+            | Self::Bcrypt(_)
+            | Self::MissingPepper(_)
+            | Self::Jwt(_)
+            | Self::Template(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Renders the error as the problem document of its [`ApiError`].
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,375 @@
+use std::{net::IpAddr, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{AppContext, Result, oauth::Provider, security::LoginAnomaly};
+
+/// How a session was started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginMethod {
+    Password,
+    /// Accepting a signup invitation, which logs the new account in.
+    Invite,
+    Passkey,
+    MagicLink,
+    /// A one-time code sent by email.
+    EmailOtp,
+    Phone,
+    OAuth(Provider),
+    /// Single sign-on through the named SAML tenant.
+    Saml(String),
+}
+
+impl LoginMethod {
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Password => "password",
+            Self::Invite => "invite",
+            Self::Passkey => "passkey",
+            Self::MagicLink => "magic_link",
+            Self::EmailOtp => "email_otp",
+            Self::Phone => "phone",
+            Self::OAuth(_) => "oauth",
+            Self::Saml(_) => "saml",
+        }
+    }
+}
+
+/// Something that happened to an account, published by the handlers through
+/// [`AppContext::publish`] once it is committed.
+///
+/// `ip` is the client address of the request that caused the event, if
+/// known; `admin_id` is the admin who acted on someone else's account.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// An account was created, by registration, invitation or a first
+    /// social or SAML login.
+    UserCreated {
+        user_id: Uuid,
+        email: String,
+    },
+    /// A session was started.
+    UserLoggedIn {
+        user_id: Uuid,
+        session_id: Uuid,
+        method: LoginMethod,
+    },
+    /// A session was started from a device or location the account had not
+    /// used before, see [`crate::security::LoginMonitor`].
+    SuspiciousLogin {
+        user_id: Uuid,
+        session_id: Uuid,
+        email: String,
+        /// Browser and operating system, e.g. `Firefox on Linux`.
+        device: String,
+        ip: Option<IpAddr>,
+        anomalies: Vec<LoginAnomaly>,
+    },
+    /// Sessions were ended before they expired, by logging out, a password
+    /// change or reset, or an admin.
+    SessionsRevoked {
+        user_id: Uuid,
+        session_ids: Vec<Uuid>,
+    },
+    /// The user changed their password; their other sessions were ended.
+    PasswordChanged {
+        user_id: Uuid,
+        ip: Option<IpAddr>,
+        sessions_ended: usize,
+    },
+    /// The user confirmed a new email address.
+    EmailChanged {
+        user_id: Uuid,
+        old_email: String,
+        new_email: String,
+    },
+    UserDisabled {
+        user_id: Uuid,
+        admin_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    UserEnabled {
+        user_id: Uuid,
+        admin_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    /// An admin is deleting the account; published just before the user
+    /// row is removed.
+    UserDeleted {
+        user_id: Uuid,
+        email: String,
+        admin_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    /// An admin merged the duplicate account `merged_user_id` into this one;
+    /// published once the duplicate is gone.
+    UsersMerged {
+        user_id: Uuid,
+        merged_user_id: Uuid,
+        merged_email: String,
+        admin_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    /// An admin cleared the password and sent a reset link.
+    PasswordResetForced {
+        user_id: Uuid,
+        admin_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    DataExportRequested {
+        user_id: Uuid,
+        export_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    /// The user asked for their account to be deleted at `scheduled_for`.
+    DeletionScheduled {
+        user_id: Uuid,
+        email: String,
+        scheduled_for: DateTime<Utc>,
+        ip: Option<IpAddr>,
+    },
+    DeletionCancelled {
+        user_id: Uuid,
+        ip: Option<IpAddr>,
+    },
+    PhoneAdded {
+        user_id: Uuid,
+        phone: String,
+        ip: Option<IpAddr>,
+    },
+    PhoneRemoved {
+        user_id: Uuid,
+        phone: String,
+        ip: Option<IpAddr>,
+    },
+}
+
+impl Event {
+    /// Dotted name of the event, e.g. `user.created`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UserCreated { .. } => "user.created",
+            Self::UserLoggedIn { .. } => "user.login",
+            Self::SuspiciousLogin { .. } => "user.login_suspicious",
+            Self::SessionsRevoked { .. } => "session.revoked",
+            Self::PasswordChanged { .. } => "password.changed",
+            Self::EmailChanged { .. } => "email.changed",
+            Self::UserDisabled { .. } => "user.disabled",
+            Self::UserEnabled { .. } => "user.enabled",
+            Self::UserDeleted { .. } => "user.deleted",
+            Self::UsersMerged { .. } => "user.merged",
+            Self::PasswordResetForced { .. } => "password.reset_forced",
+            Self::DataExportRequested { .. } => "data_export.requested",
+            Self::DeletionScheduled { .. } => "deletion.scheduled",
+            Self::DeletionCancelled { .. } => "deletion.cancelled",
+            Self::PhoneAdded { .. } => "phone.added",
+            Self::PhoneRemoved { .. } => "phone.removed",
+        }
+    }
+
+    /// The account the event is about.
+    #[must_use]
+    pub fn user_id(&self) -> Uuid {
+        match self {
+            Self::UserCreated { user_id, .. }
+            | Self::UserLoggedIn { user_id, .. }
+            | Self::SuspiciousLogin { user_id, .. }
+            | Self::SessionsRevoked { user_id, .. }
+            | Self::PasswordChanged { user_id, .. }
+            | Self::EmailChanged { user_id, .. }
+            | Self::UserDisabled { user_id, .. }
+            | Self::UserEnabled { user_id, .. }
+            | Self::UserDeleted { user_id, .. }
+            | Self::UsersMerged { user_id, .. }
+            | Self::PasswordResetForced { user_id, .. }
+            | Self::DataExportRequested { user_id, .. }
+            | Self::DeletionScheduled { user_id, .. }
+            | Self::DeletionCancelled { user_id, .. }
+            | Self::PhoneAdded { user_id, .. }
+            | Self::PhoneRemoved { user_id, .. } => *user_id,
+        }
+    }
+
+    /// The event's fields as JSON, with `user_id` always present.
+    #[must_use]
+    pub fn data(&self) -> Value {
+        let ip = |ip: &Option<IpAddr>| ip.map(|ip| ip.to_string());
+
+        match self {
+            Self::UserCreated { user_id, email } => json!({ "user_id": user_id, "email": email }),
+            Self::UserLoggedIn {
+                user_id,
+                session_id,
+                method,
+            } => {
+                let mut data = json!({ "user_id": user_id, "session_id": session_id, "method": method.name() });
+                match method {
+                    LoginMethod::OAuth(provider) => data["provider"] = json!(provider.name()),
+                    LoginMethod::Saml(tenant) => data["tenant"] = json!(tenant),
+                    _ => {}
+                }
+                data
+            }
+            Self::SuspiciousLogin {
+                user_id,
+                session_id,
+                device,
+                ip: addr,
+                anomalies,
+                ..
+            } => json!({
+                "user_id": user_id,
+                "session_id": session_id,
+                "device": device,
+                "ip": ip(addr),
+                "anomalies": anomalies.iter().map(|anomaly| anomaly.name()).collect::<Vec<_>>(),
+            }),
+            Self::SessionsRevoked {
+                user_id,
+                session_ids,
+            } => json!({ "user_id": user_id, "session_ids": session_ids }),
+            Self::PasswordChanged {
+                user_id,
+                ip: addr,
+                sessions_ended,
+            } => json!({ "user_id": user_id, "ip": ip(addr), "sessions_ended": sessions_ended }),
+            Self::EmailChanged {
+                user_id,
+                old_email,
+                new_email,
+            } => json!({ "user_id": user_id, "old_email": old_email, "new_email": new_email }),
+            Self::UserDisabled {
+                user_id,
+                admin_id,
+                ip: addr,
+            }
+            | Self::UserEnabled {
+                user_id,
+                admin_id,
+                ip: addr,
+            }
+            | Self::PasswordResetForced {
+                user_id,
+                admin_id,
+                ip: addr,
+            } => json!({ "user_id": user_id, "admin_id": admin_id, "ip": ip(addr) }),
+            Self::UserDeleted {
+                user_id,
+                email,
+                admin_id,
+                ip: addr,
+            } => json!({
+                "user_id": user_id,
+                "email": email,
+                "admin_id": admin_id,
+                "ip": ip(addr),
+            }),
+            Self::UsersMerged {
+                user_id,
+                merged_user_id,
+                merged_email,
+                admin_id,
+                ip: addr,
+            } => json!({
+                "user_id": user_id,
+                "merged_user_id": merged_user_id,
+                "merged_email": merged_email,
+                "admin_id": admin_id,
+                "ip": ip(addr),
+            }),
+            Self::DataExportRequested {
+                user_id,
+                export_id,
+                ip: addr,
+            } => json!({ "user_id": user_id, "export_id": export_id, "ip": ip(addr) }),
+            Self::DeletionScheduled {
+                user_id,
+                scheduled_for,
+                ip: addr,
+                ..
+            } => json!({ "user_id": user_id, "scheduled_for": scheduled_for, "ip": ip(addr) }),
+            Self::DeletionCancelled { user_id, ip: addr } => {
+                json!({ "user_id": user_id, "ip": ip(addr) })
+            }
+            Self::PhoneAdded {
+                user_id,
+                phone,
+                ip: addr,
+            }
+            | Self::PhoneRemoved {
+                user_id,
+                phone,
+                ip: addr,
+            } => json!({ "user_id": user_id, "phone": phone, "ip": ip(addr) }),
+        }
+    }
+}
+
+/// Reacts to published [`Event`]s.
+///
+/// The audit log, webhooks and email notices are subscribers; deployments
+/// add their own with [`AppContext::with_subscriber`].
+///
+/// ```no_run
+/// use async_trait::async_trait;
+/// use betterauth::{AppContext, Result, events::{Event, Subscriber}};
+///
+/// struct SignupCounter;
+///
+/// #[async_trait]
+/// impl Subscriber for SignupCounter {
+///     async fn handle(&self, _ctx: &AppContext, event: &Event) -> Result<()> {
+///         if let Event::UserCreated { .. } = event {
+///             // count the signup here
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Subscriber: Send + Sync {
+    /// Handles `event`, ignoring the events it is not interested in.
+    ///
+    /// ## Errors
+    /// * Whatever failed; it is logged and fails the publishing request
+    async fn handle(&self, ctx: &AppContext, event: &Event) -> Result<()>;
+}
+
+/// The subscribers events are published to, in the order they subscribed.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Vec<Arc<dyn Subscriber>>,
+}
+
+impl EventBus {
+    /// Adds `subscriber` after the existing ones.
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.subscribers.push(Arc::new(subscriber));
+    }
+
+    /// Hands `event` to every subscriber in turn. A failing subscriber does
+    /// not stop the others.
+    ///
+    /// ## Errors
+    /// * The first error returned by a subscriber
+    pub async fn publish(&self, ctx: &AppContext, event: &Event) -> Result<()> {
+        let mut outcome = Ok(());
+
+        for subscriber in &self.subscribers {
+            if let Err(err) = subscriber.handle(ctx, event).await {
+                tracing::error!(event = event.name(), user_id = %event.user_id(), error = %err, "Event subscriber failed");
+
+                if outcome.is_ok() {
+                    outcome = Err(err);
+                }
+            }
+        }
+
+        outcome
+    }
+}
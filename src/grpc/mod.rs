@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status, transport::server::TcpIncoming};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error,
+    errors::ApiError,
+    models,
+    tokens::{Claims, TokenKind},
+};
+
+#[allow(clippy::pedantic, clippy::nursery)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/betterauth.v1.Auth.rs"));
+}
+
+pub use self::generated::auth_server::{Auth, AuthServer};
+
+// The messages of `proto/betterauth/v1/auth.proto`.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidateTokenRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidateTokenResponse {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub session_id: String,
+    #[prost(string, optional, tag = "3")]
+    pub organization_id: Option<String>,
+    #[prost(int64, tag = "4")]
+    pub expires_at: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IntrospectRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IntrospectResponse {
+    #[prost(bool, tag = "1")]
+    pub active: bool,
+    #[prost(string, optional, tag = "2")]
+    pub sub: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub sid: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub org: Option<String>,
+    #[prost(int64, optional, tag = "5")]
+    pub iat: Option<i64>,
+    #[prost(int64, optional, tag = "6")]
+    pub exp: Option<i64>,
+    #[prost(string, optional, tag = "7")]
+    pub aud: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetUserRequest {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct User {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub email: String,
+    #[prost(string, optional, tag = "3")]
+    pub name: Option<String>,
+    #[prost(string, tag = "4")]
+    pub role: String,
+    #[prost(string, optional, tag = "5")]
+    pub phone: Option<String>,
+    #[prost(int64, optional, tag = "6")]
+    pub verified_at: Option<i64>,
+    #[prost(int64, tag = "7")]
+    pub created_at: i64,
+    #[prost(int64, tag = "8")]
+    pub updated_at: i64,
+}
+
+impl From<models::User> for User {
+    fn from(user: models::User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            email: user.email,
+            name: user.name,
+            role: user.role.to_string(),
+            phone: user.phone,
+            verified_at: user.verified_at.map(|at| at.timestamp()),
+            created_at: user.created_at.timestamp(),
+            updated_at: user.updated_at.timestamp(),
+        }
+    }
+}
+
+/// The `betterauth.v1.Auth` gRPC service, letting internal services check
+/// access tokens and look up users without going through the HTTP API.
+///
+/// Access tokens are verified, checked against revocations and refused once
+/// their user is deleted, exactly like the HTTP API does. Callers are not authenticated, see
+/// [`crate::config::GrpcConfig`].
+#[derive(Clone)]
+pub struct AuthService {
+    ctx: Arc<AppContext>,
+}
+
+impl AuthService {
+    #[must_use]
+    pub fn new(ctx: Arc<AppContext>) -> Self {
+        Self { ctx }
+    }
+
+    /// The claims of `token` if it is an active access token of a user who
+    /// still exists, for this server or, with `any_audience`, for a single
+    /// sign-on application.
+    async fn verify(&self, token: &str, any_audience: bool) -> crate::Result<Claims> {
+        let tokens = self.ctx.tokens();
+        let claims = if any_audience {
+            tokens.verify_any_audience(token.trim(), TokenKind::Access)?
+        } else {
+            tokens.verify(token.trim(), TokenKind::Access)?
+        };
+
+        if self.ctx.revocations().is_revoked(&claims).await?
+            || self.ctx.users().find(claims.sub).await?.is_none()
+        {
+            return Err(Error::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+}
+
+#[tonic::async_trait]
+impl Auth for AuthService {
+    async fn validate_token(
+        &self,
+        request: Request<ValidateTokenRequest>,
+    ) -> Result<Response<ValidateTokenResponse>, Status> {
+        let claims = self.verify(&request.into_inner().token, false).await?;
+
+        Ok(Response::new(ValidateTokenResponse {
+            user_id: claims.sub.to_string(),
+            session_id: claims.sid.to_string(),
+            organization_id: claims.org.map(|org| org.to_string()),
+            expires_at: claims.exp,
+        }))
+    }
+
+    async fn introspect(
+        &self,
+        request: Request<IntrospectRequest>,
+    ) -> Result<Response<IntrospectResponse>, Status> {
+        let introspection = match self.verify(&request.into_inner().token, true).await {
+            Ok(claims) => IntrospectResponse {
+                active: true,
+                sub: Some(claims.sub.to_string()),
+                sid: Some(claims.sid.to_string()),
+                org: claims.org.map(|org| org.to_string()),
+                iat: Some(claims.iat),
+                exp: Some(claims.exp),
+                aud: claims.aud,
+            },
+            Err(Error::InvalidToken) => IntrospectResponse::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Response::new(introspection))
+    }
+
+    async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<User>, Status> {
+        let id: Uuid = request
+            .into_inner()
+            .user_id
+            .parse()
+            .map_err(|_| Status::invalid_argument("user_id must be a UUID"))?;
+
+        let user = self
+            .ctx
+            .users()
+            .find(id)
+            .await?
+            .ok_or(Error::NotFound("user"))?;
+
+        Ok(Response::new(user.into()))
+    }
+}
+
+/// Reports the error with the gRPC code closest to its HTTP status, and the
+/// message clients of the HTTP API would see.
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        let error = ApiError::from(err);
+
+        let code = match error.status() {
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                tonic::Code::InvalidArgument
+            }
+            StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+            StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+            StatusCode::NOT_FOUND => tonic::Code::NotFound,
+            StatusCode::CONFLICT => tonic::Code::AlreadyExists,
+            StatusCode::REQUEST_TIMEOUT => tonic::Code::DeadlineExceeded,
+            StatusCode::TOO_MANY_REQUESTS => tonic::Code::ResourceExhausted,
+            StatusCode::BAD_GATEWAY => tonic::Code::Unavailable,
+            _ => tonic::Code::Internal,
+        };
+
+        Self::new(code, error.detail())
+    }
+}
+
+/// Serves the [`AuthService`] on `listener` until `token` is cancelled.
+///
+/// ## Errors
+/// * The server fails
+pub async fn serve(
+    listener: TcpListener,
+    ctx: Arc<AppContext>,
+    token: CancellationToken,
+) -> std::io::Result<()> {
+    tonic::transport::Server::builder()
+        .add_service(AuthServer::new(AuthService::new(ctx)))
+        .serve_with_incoming_shutdown(TcpIncoming::from(listener), token.cancelled_owned())
+        .await
+        .map_err(std::io::Error::other)
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::AppContext;
+
+/// How long a single readiness check may take before it counts as failing.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Outcome of one readiness check.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    status: CheckStatus,
+    /// Whether a failure makes the server not ready. Optional services,
+    /// like Redis which is fallen back from, only degrade it.
+    required: bool,
+    latency_ms: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failing,
+}
+
+/// `GET /health/live`
+///
+/// Answers as long as the process serves requests, without touching any
+/// dependency, so orchestrators only restart the server when it hangs.
+pub async fn live() -> Json<Value> {
+    Json(json!({ "status": "alive" }))
+}
+
+/// `GET /health/ready`
+///
+/// Checks the database answers `SELECT 1`, every migration has been applied
+/// and, when configured, Redis answers `PING` and every read replica
+/// `SELECT 1`. Responds `200` with `"status": "ready"` when every required
+/// check passes and `503` with `"status": "unavailable"` otherwise, listing
+/// each check under `checks`. Why a check failed is only logged, as the
+/// endpoint is public.
+pub async fn ready(State(ctx): State<Arc<AppContext>>) -> (StatusCode, Json<Value>) {
+    let mut checks = BTreeMap::new();
+
+    checks.insert(
+        "database",
+        check("database", true, async {
+            sqlx::query("SELECT 1")
+                .execute(ctx.db())
+                .await
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .await,
+    );
+
+    checks.insert(
+        "migrations",
+        check("migrations", true, async {
+            match ctx.config().database().pending_migrations(ctx.db()).await {
+                Ok(pending) if pending.is_empty() => Ok(()),
+                Ok(pending) => Err(format!("pending migrations: {pending:?}")),
+                Err(err) => Err(err.to_string()),
+            }
+        })
+        .await,
+    );
+
+    if ctx.config().redis().is_some() {
+        checks.insert(
+            "redis",
+            check("redis", false, async {
+                match ctx.cache() {
+                    Some(cache) => cache.ping().await.map_err(|err| err.to_string()),
+                    None => Err(String::from("unreachable at startup")),
+                }
+            })
+            .await,
+        );
+    }
+
+    if !ctx.config().database().replicas().is_empty() {
+        checks.insert(
+            "replicas",
+            check("replicas", false, async {
+                for (index, replica) in ctx.db_read().replicas().enumerate() {
+                    sqlx::query("SELECT 1")
+                        .execute(replica)
+                        .await
+                        .map_err(|err| format!("replica {index}: {err}"))?;
+                }
+                Ok(())
+            })
+            .await,
+        );
+    }
+
+    let ready = checks
+        .values()
+        .all(|check| !check.required || check.status == CheckStatus::Ok);
+
+    let (status, label) = if ready {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    };
+
+    (status, Json(json!({ "status": label, "checks": checks })))
+}
+
+/// Runs the `name` check `probe`, failing it after [`CHECK_TIMEOUT`].
+async fn check(
+    name: &'static str,
+    required: bool,
+    probe: impl Future<Output = Result<(), String>>,
+) -> Check {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(CHECK_TIMEOUT, probe)
+        .await
+        .unwrap_or_else(|_| Err(String::from("timed out")));
+
+    if let Err(err) = &outcome {
+        tracing::warn!(check = name, error = %err, "Readiness check failed");
+    }
+
+    Check {
+        status: if outcome.is_ok() {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Failing
+        },
+        required,
+        latency_ms: started.elapsed().as_millis(),
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{HeaderMap, header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{AppContext, config::I18nConfig};
+
+tokio::task_local! {
+    /// Language of the request being handled, set by [`locale`].
+    static LOCALE: Locale;
+}
+
+/// The languages offered, with the message catalogs of `i18n.dir`.
+///
+/// A catalog holds the messages of one language by section, e.g. for
+/// `fr.yaml`:
+///
+/// ```yaml
+/// errors:
+///   email_taken: "un compte existe déjà avec cette adresse"
+///   not_found: "{resource} introuvable"
+/// ```
+///
+/// Error messages are keyed by the `code` of their problem document and
+/// can use the values the English message is made of in braces: `resource`
+/// for `not_found`, `feature` for `disabled` and `limit` for
+/// `payload_too_large`. Messages that are free text, such as those of
+/// `validation_failed`, are not translated.
+#[derive(Debug, Clone)]
+pub struct Translations {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    languages: Vec<String>,
+    catalogs: HashMap<String, HashMap<String, String>>,
+}
+
+impl Translations {
+    /// # Panics
+    /// If a catalog in `i18n.dir` cannot be read or parsed.
+    #[must_use]
+    pub fn from_config(config: &I18nConfig) -> Self {
+        let mut catalogs = HashMap::new();
+
+        if let Some(dir) = config.dir() {
+            for language in config.languages() {
+                let path = dir.join(language);
+                let sections: HashMap<String, HashMap<String, String>> = config::Config::builder()
+                    .add_source(config::File::with_name(&path.to_string_lossy()).required(false))
+                    .build()
+                    .and_then(config::Config::try_deserialize)
+                    .unwrap_or_else(|err| {
+                        panic!("invalid message catalog {}: {err}", path.display())
+                    });
+
+                let messages = sections
+                    .into_iter()
+                    .flat_map(|(section, messages)| {
+                        messages
+                            .into_iter()
+                            .map(move |(key, message)| (format!("{section}.{key}"), message))
+                    })
+                    .collect();
+                catalogs.insert(language.clone(), messages);
+            }
+        }
+
+        Self {
+            inner: Arc::new(Inner {
+                languages: config.languages().to_vec(),
+                catalogs,
+            }),
+        }
+    }
+
+    /// The offered language the client prefers according to
+    /// `accept_language`, the value of an `Accept-Language` header, or the
+    /// default one if it accepts none of them.
+    ///
+    /// A range matches a language of the same tag, or of the same primary
+    /// tag, so `fr-CH` matches `fr` and `pt` matches `pt-BR`.
+    ///
+    /// ```
+    /// use betterauth::{config::I18nConfig, i18n::Translations};
+    ///
+    /// let config: I18nConfig =
+    ///     serde_json::from_value(serde_json::json!({ "languages": ["en", "fr", "pt-BR"] }))
+    ///         .unwrap();
+    /// let translations = Translations::from_config(&config);
+    ///
+    /// let negotiate = |header| translations.negotiate(Some(header)).language().to_owned();
+    /// assert_eq!(negotiate("fr-CH, fr;q=0.9, en;q=0.8"), "fr");
+    /// assert_eq!(negotiate("de, pt;q=0.5"), "pt-BR");
+    /// assert_eq!(negotiate("de, fr;q=0"), "en");
+    /// ```
+    #[must_use]
+    pub fn negotiate(&self, accept_language: Option<&str>) -> Locale {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let tag = params.next()?.trim();
+                let quality = params
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let primary = |tag: &str| tag.split('-').next().unwrap_or_default().to_owned();
+        let languages = &self.inner.languages;
+
+        let language = ranges
+            .iter()
+            .find_map(|&(tag, _)| {
+                if tag == "*" {
+                    return languages.first();
+                }
+                languages
+                    .iter()
+                    .find(|language| language.eq_ignore_ascii_case(tag))
+                    .or_else(|| {
+                        languages
+                            .iter()
+                            .find(|language| primary(language).eq_ignore_ascii_case(&primary(tag)))
+                    })
+            })
+            .or_else(|| languages.first())
+            .cloned()
+            .unwrap_or_else(|| String::from("en"));
+
+        Locale {
+            language,
+            translations: self.clone(),
+        }
+    }
+}
+
+/// A language negotiated for a request, from its `Accept-Language` header.
+#[derive(Debug, Clone)]
+pub struct Locale {
+    language: String,
+    translations: Translations,
+}
+
+impl Locale {
+    /// The language tag, as configured in `i18n.languages`.
+    #[must_use]
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The message of `key` in this language, e.g. `errors.email_taken`,
+    /// with each `{name}` replaced by its value in `args`. `None` if the
+    /// catalog of the language has no such message.
+    #[must_use]
+    pub fn message(&self, key: &str, args: &[(&str, String)]) -> Option<String> {
+        let message = self
+            .translations
+            .inner
+            .catalogs
+            .get(&self.language)?
+            .get(key)?;
+
+        Some(args.iter().fold(message.clone(), |message, (name, value)| {
+            message.replace(&format!("{{{name}}}"), value)
+        }))
+    }
+}
+
+impl FromRequestParts<Arc<AppContext>> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &Arc<AppContext>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(ctx
+            .translations()
+            .negotiate(accept_language(&parts.headers)))
+    }
+}
+
+fn accept_language(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Makes the language of the request available to the errors it fails
+/// with, so that their problem documents are translated.
+pub async fn locale(State(ctx): State<Arc<AppContext>>, request: Request, next: Next) -> Response {
+    let locale = ctx
+        .translations()
+        .negotiate(accept_language(request.headers()));
+
+    LOCALE.scope(locale, next.run(request)).await
+}
+
+/// The message of `key` in the language of the current request, see
+/// [`Locale::message`].
+pub(crate) fn translate(key: &str, args: &[(&str, String)]) -> Option<String> {
+    LOCALE
+        .try_with(|locale| locale.message(key, args))
+        .ok()
+        .flatten()
+}
@@ -0,0 +1,337 @@
+use std::{
+    sync::Arc,
+    time::{Duration as StdDuration, Instant},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgExecutor, PgPool};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{AppContext, Result, mail::Email};
+
+/// How long the worker waits before looking again when no job was due.
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(1);
+
+/// Jobs claimed, and run concurrently, per worker run.
+const BATCH: i64 = 10;
+
+/// How long a claimed job is held. A job still running after this is assumed
+/// lost, e.g. to a restart, and is claimed again.
+const LEASE: Duration = Duration::minutes(5);
+
+/// Delay before the first retry, doubled for every further one.
+const BASE_BACKOFF: Duration = Duration::seconds(30);
+
+/// Longest delay between two attempts.
+const MAX_BACKOFF: Duration = Duration::hours(6);
+
+/// How long jobs that gave up are kept for inspection.
+const FAILED_RETENTION: Duration = Duration::days(7);
+
+/// How often the worker drops failed jobs past [`FAILED_RETENTION`].
+const PRUNE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+/// Deferred work, stored as JSON in `jobs.payload`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    /// Hands `email` to the configured mailer.
+    SendEmail { email: Email },
+    /// Makes one attempt at a webhook delivery, see
+    /// [`crate::webhooks::Webhooks::deliver`].
+    DeliverWebhook { delivery_id: Uuid },
+    /// Makes one attempt at sending a back-channel logout token, see
+    /// [`crate::oidc::BackchannelLogout::deliver`].
+    DeliverLogout { delivery_id: Uuid },
+    /// Builds the archive of a pending data export.
+    BuildExport { export_id: Uuid },
+}
+
+impl Job {
+    /// Value stored in `jobs.kind`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SendEmail { .. } => "send_email",
+            Self::DeliverWebhook { .. } => "deliver_webhook",
+            Self::DeliverLogout { .. } => "deliver_logout",
+            Self::BuildExport { .. } => "build_export",
+        }
+    }
+
+    /// How often the job is tried when queued with [`JobQueue::enqueue`].
+    #[must_use]
+    pub fn max_attempts(&self) -> i32 {
+        match self {
+            Self::SendEmail { .. } => 5,
+            Self::DeliverWebhook { .. } | Self::DeliverLogout { .. } => 8,
+            Self::BuildExport { .. } => 3,
+        }
+    }
+
+    async fn run(&self, ctx: &AppContext) -> Result<()> {
+        match self {
+            Self::SendEmail { email } => ctx.mailer().send(email.clone()).await,
+            Self::DeliverWebhook { delivery_id } => ctx.webhooks().deliver(*delivery_id).await,
+            Self::DeliverLogout { delivery_id } => {
+                ctx.backchannel_logout()
+                    .deliver(ctx.oidc()?, *delivery_id)
+                    .await
+            }
+            Self::BuildExport { export_id } => {
+                ctx.privacy().build_export(ctx.sessions(), *export_id).await
+            }
+        }
+    }
+
+    /// Called once the job failed for the last time.
+    async fn abandon(&self, ctx: &AppContext, error: &str) -> Result<()> {
+        match self {
+            Self::SendEmail { email } => {
+                tracing::error!(to = %email.to, subject = %email.subject, error, "Gave up sending email");
+                Ok(())
+            }
+            Self::DeliverWebhook { delivery_id } => {
+                ctx.webhooks().abandon(*delivery_id, error).await
+            }
+            Self::DeliverLogout { delivery_id } => {
+                ctx.backchannel_logout().abandon(*delivery_id, error).await
+            }
+            Self::BuildExport { export_id } => ctx.privacy().abandon_export(*export_id).await,
+        }
+    }
+}
+
+/// Delay before attempt `attempts + 1` of a job that failed `attempts`
+/// times: 30 seconds, doubled for every further attempt, at most 6 hours.
+#[must_use]
+pub fn backoff(attempts: i32) -> Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(20);
+    (BASE_BACKOFF * 2_i32.pow(exponent)).min(MAX_BACKOFF)
+}
+
+#[derive(sqlx::FromRow)]
+struct ClaimedJob {
+    id: Uuid,
+    kind: String,
+    payload: Value,
+    attempts: i32,
+    max_attempts: i32,
+}
+
+/// Postgres-backed queue of deferred [`Job`]s.
+///
+/// Jobs are claimed with `FOR UPDATE SKIP LOCKED`, so several instances can
+/// run workers against the same table without running a job twice. A job
+/// that fails is tried again after [`backoff`] until it reaches its
+/// `max_attempts`; it is then marked failed and kept for a week. Jobs that
+/// succeed are deleted.
+#[derive(Clone)]
+pub struct JobQueue {
+    db: PgPool,
+}
+
+impl JobQueue {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Queues `job` to run as soon as a worker is free.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn enqueue(&self, job: Job) -> Result<Uuid> {
+        let max_attempts = job.max_attempts();
+        Self::push(&self.db, &job, Utc::now(), max_attempts).await
+    }
+
+    /// Queues `job` to run at `run_at` and be tried at most `max_attempts`
+    /// times. Pass a transaction as `executor` to queue the job only if the
+    /// transaction commits.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn push(
+        executor: impl PgExecutor<'_>,
+        job: &Job,
+        run_at: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> Result<Uuid> {
+        sqlx::query_scalar(
+            r"
+            INSERT INTO jobs (kind, payload, max_attempts, created_at, run_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            ",
+        )
+        .bind(job.name())
+        .bind(sqlx::types::Json(job))
+        .bind(max_attempts)
+        .bind(Utc::now())
+        .bind(run_at)
+        .fetch_one(executor)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Claims the jobs that are due, runs them concurrently and records the
+    /// outcomes. Returns how many jobs were run.
+    ///
+    /// ## Errors
+    /// * Database errors while claiming
+    pub async fn run_due(&self, ctx: &Arc<AppContext>) -> Result<usize> {
+        let claimed = sqlx::query_as::<_, ClaimedJob>(
+            r"
+            UPDATE jobs
+            SET status = 'running', attempts = attempts + 1, locked_until = now() + $2
+            WHERE id IN (
+                SELECT id FROM jobs
+                WHERE (status = 'pending' AND run_at <= now())
+                   OR (status = 'running' AND locked_until < now())
+                ORDER BY run_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind, payload, attempts, max_attempts
+            ",
+        )
+        .bind(BATCH)
+        .bind(LEASE)
+        .fetch_all(&self.db)
+        .await?;
+
+        let count = claimed.len();
+        let mut running = JoinSet::new();
+
+        for job in claimed {
+            let queue = self.clone();
+            let ctx = ctx.clone();
+            running.spawn(async move {
+                if let Err(err) = queue.execute(&ctx, job).await {
+                    tracing::warn!(error = %err, "Failed to record job outcome");
+                }
+            });
+        }
+
+        running.join_all().await;
+
+        Ok(count)
+    }
+
+    async fn execute(&self, ctx: &AppContext, claimed: ClaimedJob) -> Result<()> {
+        let id = claimed.id;
+
+        let job = match serde_json::from_value::<Job>(claimed.payload) {
+            Ok(job) => job,
+            Err(err) => {
+                return self
+                    .fail(id, &format!("unreadable {} job: {err}", claimed.kind))
+                    .await;
+            }
+        };
+
+        let Err(err) = job.run(ctx).await else {
+            sqlx::query("DELETE FROM jobs WHERE id = $1")
+                .bind(id)
+                .execute(&self.db)
+                .await?;
+
+            return Ok(());
+        };
+
+        let error = err.to_string();
+
+        if claimed.attempts >= claimed.max_attempts {
+            tracing::error!(job_id = %id, kind = job.name(), attempts = claimed.attempts, error, "Job failed for good");
+            self.fail(id, &error).await?;
+
+            return job.abandon(ctx, &error).await;
+        }
+
+        tracing::warn!(job_id = %id, kind = job.name(), attempts = claimed.attempts, error, "Job failed, retrying");
+
+        sqlx::query(
+            r"
+            UPDATE jobs
+            SET status = 'pending', last_error = $2, run_at = now() + $3, locked_until = NULL
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(&error)
+        .bind(backoff(claimed.attempts))
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn fail(&self, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            r"
+            UPDATE jobs
+            SET status = 'failed', last_error = $2, locked_until = NULL, failed_at = now()
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes failed jobs older than a week.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn prune(&self) -> Result<()> {
+        sqlx::query("DELETE FROM jobs WHERE status = 'failed' AND failed_at <= $1")
+            .bind(Utc::now() - FAILED_RETENTION)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Spawns the background task that runs due jobs with
+/// [`JobQueue::run_due`] and hourly [`JobQueue::prune`]s failed ones.
+///
+/// Once `shutdown` is cancelled no more jobs are claimed; the batch already
+/// running is finished first.
+pub fn spawn_worker(ctx: Arc<AppContext>, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let jobs = ctx.jobs().clone();
+        let mut pruned_at: Option<Instant> = None;
+
+        while !shutdown.is_cancelled() {
+            if pruned_at.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                if let Err(err) = jobs.prune().await {
+                    tracing::warn!(error = %err, "Job pruning failed");
+                }
+                pruned_at = Some(Instant::now());
+            }
+
+            match jobs.run_due(&ctx).await {
+                // Keep going while there is work, more jobs may be due.
+                Ok(count) if count > 0 => continue,
+                Ok(_) => {}
+                Err(err) => tracing::warn!(error = %err, "Job run failed"),
+            }
+
+            tokio::select! {
+                () = tokio::time::sleep(POLL_INTERVAL) => {}
+                () = shutdown.cancelled() => {}
+            }
+        }
+    })
+}
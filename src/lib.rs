@@ -1,8 +1,47 @@
+pub mod api_keys;
 pub mod app;
+pub mod audit;
+pub mod auth;
+pub mod cache;
+pub mod clock;
 pub mod config;
 pub mod context;
+pub mod db;
 pub mod errors;
+pub mod events;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod i18n;
+pub mod jobs;
+pub mod limits;
+pub mod mail;
+pub mod maintenance;
+pub mod metrics;
+pub mod models;
+pub mod oauth;
+pub mod oidc;
+pub mod organizations;
+pub mod pagination;
+pub mod privacy;
+pub mod reload;
+pub mod reports;
+pub mod repositories;
+pub mod routes;
+pub mod saml;
+pub mod security;
+pub mod seed;
+pub mod sessions;
+pub mod shutdown;
+pub mod sms;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tokens;
 pub(crate) mod trace;
+pub mod webauthn;
+pub mod webhooks;
 
 pub use self::{
     app::App,
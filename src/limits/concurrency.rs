@@ -0,0 +1,98 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::ConcurrencyConfig;
+
+/// Paths that are never capped, so probes and scrapes still reach a server
+/// under load.
+const UNCAPPED: [&str; 2] = ["/health/", "/metrics"];
+
+/// Counts the requests in flight in `http_requests_in_flight` while alive.
+pub(super) struct InFlight;
+
+impl InFlight {
+    pub(super) fn start() -> Self {
+        metrics::gauge!("http_requests_in_flight").increment(1);
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        metrics::gauge!("http_requests_in_flight").decrement(1);
+    }
+}
+
+/// The caps of `server.concurrency`, see [`ConcurrencyConfig`], as
+/// semaphores shared by the listeners.
+pub struct ConcurrencyLimiter {
+    config: ConcurrencyConfig,
+    global: Option<Arc<Semaphore>>,
+    groups: HashMap<String, Arc<Semaphore>>,
+    endpoints: HashMap<String, Arc<Semaphore>>,
+}
+
+impl ConcurrencyLimiter {
+    #[must_use]
+    pub fn from_config(config: &ConcurrencyConfig) -> Self {
+        let semaphores = |limits: &HashMap<String, usize>| {
+            limits
+                .iter()
+                .map(|(name, &limit)| (name.clone(), Arc::new(Semaphore::new(limit))))
+                .collect()
+        };
+
+        Self {
+            config: config.clone(),
+            global: config
+                .max_requests()
+                .map(|limit| Arc::new(Semaphore::new(limit))),
+            groups: semaphores(config.groups()),
+            endpoints: semaphores(config.endpoints()),
+        }
+    }
+
+    /// Seconds shed requests are told to wait.
+    #[must_use]
+    pub fn retry_after(&self) -> u64 {
+        self.config.retry_after()
+    }
+
+    /// Takes a slot under every cap a request to `path` falls under, held
+    /// until the permits are dropped, or names the first cap that is full:
+    /// `global`, the route group or the endpoint path.
+    ///
+    /// ## Errors
+    /// * The name of the full cap
+    pub fn acquire(&self, path: &str) -> Result<Vec<OwnedSemaphorePermit>, String> {
+        if UNCAPPED.iter().any(|uncapped| path.starts_with(uncapped)) {
+            return Ok(Vec::new());
+        }
+
+        let group = self
+            .config
+            .group(path)
+            .and_then(|group| self.groups.get_key_value(group));
+        let endpoint = self
+            .config
+            .endpoint(path)
+            .and_then(|endpoint| self.endpoints.get_key_value(endpoint));
+
+        let global = self.global.as_ref().map(|global| ("global", global));
+        let caps = global.into_iter().chain(
+            group
+                .into_iter()
+                .chain(endpoint)
+                .map(|(name, semaphore)| (name.as_str(), semaphore)),
+        );
+
+        caps.map(|(name, semaphore)| {
+            semaphore
+                .clone()
+                .try_acquire_owned()
+                .map_err(|_| name.to_owned())
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{self, Body},
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use http_body_util::LengthLimitError;
+
+use crate::{AppContext, Error, Result};
+
+mod concurrency;
+
+pub use self::concurrency::ConcurrencyLimiter;
+use self::concurrency::InFlight;
+
+/// Sheds requests over a cap of `server.concurrency` with
+/// `503 Service Unavailable` and a `Retry-After` header rather than queueing
+/// them, and counts the requests in flight.
+pub async fn concurrency(
+    State(ctx): State<Arc<AppContext>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let _in_flight = InFlight::start();
+    let limiter = ctx.concurrency_limiter();
+
+    let _permits = limiter.acquire(request.uri().path()).map_err(|limit| {
+        metrics::counter!("http_requests_shed_total", "limit" => limit).increment(1);
+        Error::Overloaded {
+            retry_after: limiter.retry_after(),
+        }
+    })?;
+
+    Ok(next.run(request).await)
+}
+
+/// Answers with `408 Request Timeout` once the request has taken longer
+/// than the timeout of its route group.
+pub async fn timeout(
+    State(ctx): State<Arc<AppContext>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let limit = ctx.config().server().timeout_for(request.uri().path());
+
+    tokio::time::timeout(limit, next.run(request))
+        .await
+        .map_err(|_| Error::Timeout)
+}
+
+/// Refuses bodies over `server.body_limit` bytes with
+/// `413 Payload Too Large`.
+///
+/// A `Content-Length` over the limit is refused without reading the body;
+/// bodies of unknown length are read up to the limit before the handler
+/// runs.
+pub async fn body_limit(
+    State(ctx): State<Arc<AppContext>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let limit = ctx.config().server().body_limit();
+
+    let length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    let request = match length {
+        Some(length) if length > limit => return Err(Error::PayloadTooLarge(limit)),
+        Some(_) => request,
+        None => {
+            let (parts, body) = request.into_parts();
+            let bytes = body::to_bytes(body, limit).await.map_err(|err| {
+                if err.into_inner().is::<LengthLimitError>() {
+                    Error::PayloadTooLarge(limit)
+                } else {
+                    Error::Validation(String::from("failed to read the request body"))
+                }
+            })?;
+
+            Request::from_parts(parts, Body::from(bytes))
+        }
+    };
+
+    Ok(next.run(request).await)
+}
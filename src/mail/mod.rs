@@ -0,0 +1,87 @@
+mod notices;
+mod otp;
+mod smtp;
+mod templates;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub use self::{
+    notices::Notices,
+    otp::EmailOtp,
+    smtp::SmtpMailer,
+    templates::{EmailTemplate, Templates},
+};
+use crate::Result;
+
+/// An email message with a plain-text body and, optionally, an HTML
+/// alternative.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub html: Option<String>,
+}
+
+/// Delivers emails sent by the auth flows (password reset, verification, ...).
+///
+/// [`SmtpMailer`], [`LogMailer`] and [`NoopMailer`] are selected by the
+/// `mailer` config section; deployments plug in other delivery by
+/// implementing this trait and installing it with
+/// [`crate::AppContext::with_mailer`].
+///
+/// ```no_run
+/// use async_trait::async_trait;
+/// use betterauth::{Result, mail::{Email, Mailer}};
+///
+/// struct MyProvider;
+///
+/// #[async_trait]
+/// impl Mailer for MyProvider {
+///     async fn send(&self, email: Email) -> Result<()> {
+///         // call the provider's API here
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Sends `email`.
+    ///
+    /// ## Errors
+    /// * The message could not be handed over for delivery
+    async fn send(&self, email: Email) -> Result<()>;
+}
+
+/// Mailer that writes every message to the log instead of delivering it.
+///
+/// The default until a real mailer is configured, which keeps development
+/// setups working without any mail infrastructure.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<()> {
+        tracing::info!(
+            to = %email.to,
+            subject = %email.subject,
+            body = %email.body,
+            "Email not delivered, no mailer configured"
+        );
+
+        Ok(())
+    }
+}
+
+/// Mailer that silently discards every message, for tests.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMailer;
+
+#[async_trait]
+impl Mailer for NoopMailer {
+    async fn send(&self, _email: Email) -> Result<()> {
+        Ok(())
+    }
+}
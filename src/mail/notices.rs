@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+
+use super::Email;
+use crate::{
+    AppContext, Result,
+    events::{Event, Subscriber},
+    jobs::Job,
+};
+
+/// Emails the user about changes to their account they did not necessarily
+/// make themselves: a scheduled deletion, a changed address when
+/// `auth.notify_email_change` is enabled, and a login from a new device or
+/// location when `security.notify_suspicious_login` is. The emails are queued as
+/// [`Job::SendEmail`]s.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Notices;
+
+#[async_trait]
+impl Subscriber for Notices {
+    async fn handle(&self, ctx: &AppContext, event: &Event) -> Result<()> {
+        let email = match event {
+            Event::DeletionScheduled {
+                email,
+                scheduled_for,
+                ..
+            } => Email {
+                to: email.clone(),
+                subject: String::from("Your account is scheduled for deletion"),
+                body: format!(
+                    "Your account will be permanently deleted on {}.\n\n\
+                     If you change your mind, log in before then and cancel the deletion.",
+                    scheduled_for.format("%Y-%m-%d %H:%M UTC")
+                ),
+                html: None,
+            },
+            Event::EmailChanged {
+                old_email,
+                new_email,
+                ..
+            } if ctx.config().auth().notify_email_change() => Email {
+                to: old_email.clone(),
+                subject: String::from("Your email address was changed"),
+                body: format!(
+                    "The email address of your account was changed to {new_email}.\n\n\
+                     If you did not make this change, please contact support right away."
+                ),
+                html: None,
+            },
+            Event::SuspiciousLogin {
+                email, device, ip, ..
+            } if ctx.config().security().notify_suspicious_login() => Email {
+                to: email.clone(),
+                subject: String::from("New sign-in to your account"),
+                body: format!(
+                    "Your account was just signed in to from {device}{}.\n\n\
+                     If this was you, there is nothing to do. If not, change your password \
+                     right away and sign out your other sessions.",
+                    ip.map(|ip| format!(" at {ip}")).unwrap_or_default()
+                ),
+                html: None,
+            },
+            _ => return Ok(()),
+        };
+
+        ctx.jobs().enqueue(Job::SendEmail { email }).await?;
+
+        Ok(())
+    }
+}
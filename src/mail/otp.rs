@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    clock::{Clock, SystemClock},
+    config::EmailOtpConfig,
+};
+
+/// Codes sent to an address are kept this long for rate limiting, then
+/// dropped.
+const RETENTION: Duration = Duration::days(1);
+
+#[derive(sqlx::FromRow)]
+struct PendingCode {
+    id: Uuid,
+    code_hash: String,
+}
+
+/// Issues and redeems the six-digit login codes sent by email.
+///
+/// Works like [`crate::sms::PhoneOtp`]: codes are stored as an HMAC keyed
+/// with `auth.secret`, only the latest code sent to an account works, and
+/// each allows `email_otp.max_attempts` guesses. A code is bound to the
+/// address it was sent to, so it stops working if the email changes.
+#[derive(Clone)]
+pub struct EmailOtp {
+    db: PgPool,
+    secret: String,
+    config: EmailOtpConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl EmailOtp {
+    #[must_use]
+    pub fn new(db: PgPool, secret: &str, config: EmailOtpConfig) -> Self {
+        Self {
+            db,
+            secret: secret.to_owned(),
+            config,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &EmailOtpConfig {
+        &self.config
+    }
+
+    fn hash(&self, email: &str, code: &str) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"email:");
+        mac.update(email.as_bytes());
+        mac.update(b":");
+        mac.update(code.as_bytes());
+        mac
+    }
+
+    /// Creates a code for `user_id` to redeem while its address is `email`,
+    /// replacing any earlier one, and returns it for sending.
+    ///
+    /// ## Errors
+    /// * [`Error::RateLimited`] if the account was sent a code too recently
+    ///   or too often in the last hour
+    /// * Database errors
+    pub async fn issue(&self, user_id: Uuid, email: &str) -> Result<String> {
+        let now = self.clock.now();
+
+        let (sent, first, last) =
+            sqlx::query_as::<_, (i64, Option<DateTime<Utc>>, Option<DateTime<Utc>>)>(
+                r"
+                SELECT count(*), min(created_at), max(created_at)
+                FROM email_otp_codes
+                WHERE user_id = $1 AND created_at > $2
+                ",
+            )
+            .bind(user_id)
+            .bind(now - Duration::hours(1))
+            .fetch_one(&self.db)
+            .await?;
+
+        let resend_interval =
+            Duration::seconds(i64::try_from(self.config.resend_interval()).unwrap_or(i64::MAX));
+        let next_send = match (first, last) {
+            (Some(first), _) if sent >= self.config.max_sends_per_hour() => {
+                Some(first + Duration::hours(1))
+            }
+            (_, Some(last)) if last + resend_interval > now => Some(last + resend_interval),
+            _ => None,
+        };
+
+        if let Some(next_send) = next_send {
+            let retry_after = (next_send - now).num_seconds().max(1);
+            return Err(Error::RateLimited {
+                retry_after: u64::try_from(retry_after).unwrap_or(1),
+            });
+        }
+
+        let code = format!("{:06}", rand::rng().random_range(0..1_000_000));
+        let ttl = Duration::seconds(i64::try_from(self.config.code_ttl()).unwrap_or(i64::MAX));
+        let code_hash = URL_SAFE_NO_PAD.encode(self.hash(email, &code).finalize().into_bytes());
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("DELETE FROM email_otp_codes WHERE user_id = $1 AND created_at < $2")
+            .bind(user_id)
+            .bind(now - RETENTION)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "UPDATE email_otp_codes SET used_at = $2 WHERE user_id = $1 AND used_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO email_otp_codes (user_id, email, code_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(code_hash)
+        .bind(now)
+        .bind(now + ttl)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(code)
+    }
+
+    /// Redeems `code` sent to `user_id` at `email`. Returns `false` if there
+    /// is no usable code or `code` is wrong, in which case the guess counts
+    /// against the code's attempts.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn verify(&self, user_id: Uuid, email: &str, code: &str) -> Result<bool> {
+        let now = self.clock.now();
+        let mut tx = self.db.begin().await?;
+
+        let pending = sqlx::query_as::<_, PendingCode>(
+            r"
+            SELECT id, code_hash
+            FROM email_otp_codes
+            WHERE user_id = $1 AND email = $2 AND used_at IS NULL AND expires_at > $3
+            ORDER BY created_at DESC
+            LIMIT 1
+            FOR UPDATE
+            ",
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(pending) = pending else {
+            return Ok(false);
+        };
+
+        let matches = URL_SAFE_NO_PAD
+            .decode(&pending.code_hash)
+            .is_ok_and(|tag| self.hash(email, code.trim()).verify_slice(&tag).is_ok());
+
+        if matches {
+            sqlx::query("UPDATE email_otp_codes SET used_at = $2 WHERE id = $1")
+                .bind(pending.id)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            // The last allowed guess burns the code.
+            sqlx::query(
+                r"
+                UPDATE email_otp_codes
+                SET attempts = attempts + 1,
+                    used_at = CASE WHEN attempts + 1 >= $2 THEN $3 END
+                WHERE id = $1
+                ",
+            )
+            .bind(pending.id)
+            .bind(self.config.max_attempts())
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(matches)
+    }
+}
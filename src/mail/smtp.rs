@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+};
+
+use super::{Email, Mailer};
+use crate::{
+    Error, Result,
+    config::{ConfigError, MailerConfig, SecretString, SmtpSecurity},
+};
+
+/// Mailer delivering through an SMTP server, from the `mailer` config
+/// section. Connections are pooled and reused between messages.
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// ## Errors
+    /// * [`ConfigError::Invalid`] if the `mailer.smtp` section is missing or
+    ///   `mailer.from` is not a valid address
+    /// * [`Error::Mail`] if the TLS backend cannot be initialised
+    pub fn from_config(config: &MailerConfig) -> Result<Self> {
+        let smtp = config.smtp().ok_or_else(|| {
+            ConfigError::Invalid(vec![String::from(
+                "mailer.transport smtp requires the mailer.smtp section",
+            )])
+        })?;
+        let from = config.from().parse().map_err(|_| {
+            ConfigError::Invalid(vec![String::from(
+                "mailer.from must be a valid email address",
+            )])
+        })?;
+
+        let mut builder = match smtp.security() {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(smtp.host()),
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp.host())
+            }
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                smtp.host(),
+            )),
+        }
+        .map_err(|err| Error::Mail(format!("failed to initialise SMTP TLS: {err}")))?
+        .timeout(Some(Duration::from_secs(smtp.timeout())));
+
+        if let Some(port) = smtp.port() {
+            builder = builder.port(port);
+        }
+
+        if let Some(username) = smtp.username() {
+            builder = builder.credentials(Credentials::new(
+                username.to_owned(),
+                smtp.password()
+                    .map(SecretString::expose)
+                    .unwrap_or_default()
+                    .to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<()> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|err| Error::Mail(format!("invalid recipient {:?}: {err}", email.to)))?;
+
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject);
+
+        let message = match email.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(email.body, html)),
+            None => builder.singlepart(SinglePart::plain(email.body)),
+        }
+        .map_err(|err| Error::Mail(err.to_string()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|err| Error::Mail(err.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, path::Path};
+
+use serde_json::Value;
+use tera::{Context, Tera};
+
+use super::Email;
+use crate::{Error, Result, config::MailerConfig};
+
+/// Templates compiled into the binary, overridable from `mailer.template_dir`.
+const BUILT_IN: [(&str, &str); 19] = [
+    (
+        "layout.html",
+        include_str!("../../templates/email/layout.html"),
+    ),
+    (
+        "verification.subject.txt",
+        include_str!("../../templates/email/verification.subject.txt"),
+    ),
+    (
+        "verification.txt",
+        include_str!("../../templates/email/verification.txt"),
+    ),
+    (
+        "verification.html",
+        include_str!("../../templates/email/verification.html"),
+    ),
+    (
+        "password_reset.subject.txt",
+        include_str!("../../templates/email/password_reset.subject.txt"),
+    ),
+    (
+        "password_reset.txt",
+        include_str!("../../templates/email/password_reset.txt"),
+    ),
+    (
+        "password_reset.html",
+        include_str!("../../templates/email/password_reset.html"),
+    ),
+    (
+        "magic_link.subject.txt",
+        include_str!("../../templates/email/magic_link.subject.txt"),
+    ),
+    (
+        "magic_link.txt",
+        include_str!("../../templates/email/magic_link.txt"),
+    ),
+    (
+        "magic_link.html",
+        include_str!("../../templates/email/magic_link.html"),
+    ),
+    (
+        "email_otp.subject.txt",
+        include_str!("../../templates/email/email_otp.subject.txt"),
+    ),
+    (
+        "email_otp.txt",
+        include_str!("../../templates/email/email_otp.txt"),
+    ),
+    (
+        "email_otp.html",
+        include_str!("../../templates/email/email_otp.html"),
+    ),
+    (
+        "invite.subject.txt",
+        include_str!("../../templates/email/invite.subject.txt"),
+    ),
+    (
+        "invite.txt",
+        include_str!("../../templates/email/invite.txt"),
+    ),
+    (
+        "invite.html",
+        include_str!("../../templates/email/invite.html"),
+    ),
+    (
+        "organization_invitation.subject.txt",
+        include_str!("../../templates/email/organization_invitation.subject.txt"),
+    ),
+    (
+        "organization_invitation.txt",
+        include_str!("../../templates/email/organization_invitation.txt"),
+    ),
+    (
+        "organization_invitation.html",
+        include_str!("../../templates/email/organization_invitation.html"),
+    ),
+];
+
+/// An email rendered from templates, named `<name>.subject.txt`,
+/// `<name>.txt` and `<name>.html` after [`EmailTemplate::name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    /// Confirms the address of a new account; has `url` and `ttl_hours`.
+    Verification,
+    /// Has `url` and `ttl_minutes`.
+    PasswordReset,
+    /// Has `url` and `ttl_minutes`.
+    MagicLink,
+    /// Has `code` and `ttl_minutes`.
+    EmailOtp,
+    /// Invites someone to sign up; has `url` or `code`, `organization` if
+    /// they will join one, and `ttl_days`.
+    Invite,
+    /// Invites someone to an organization; has `organization`, `role`,
+    /// `code` and `ttl_days`.
+    OrganizationInvitation,
+}
+
+impl EmailTemplate {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Verification => "verification",
+            Self::PasswordReset => "password_reset",
+            Self::MagicLink => "magic_link",
+            Self::EmailOtp => "email_otp",
+            Self::Invite => "invite",
+            Self::OrganizationInvitation => "organization_invitation",
+        }
+    }
+}
+
+/// Renders auth emails from the built-in templates and any overrides in
+/// `mailer.template_dir`.
+///
+/// Templates in a subdirectory named after a language of `i18n.languages`,
+/// e.g. `fr/magic_link.html`, are used for emails in that language, the
+/// others for every language.
+///
+/// Besides their own variables, all templates get `product_name`,
+/// `base_url`, the recipient's `email` and the `language` of the email.
+/// HTML templates are autoescaped.
+#[derive(Clone)]
+pub struct Templates {
+    tera: Tera,
+    product_name: String,
+    base_url: String,
+}
+
+impl Templates {
+    /// `server_url` is the `base_url` unless `mailer.base_url` overrides it.
+    ///
+    /// # Panics
+    /// If `mailer.template_dir` cannot be read or a template does not parse.
+    #[must_use]
+    pub fn from_config(config: &MailerConfig, server_url: &str) -> Self {
+        let mut sources: HashMap<String, String> = BUILT_IN
+            .iter()
+            .map(|(name, source)| ((*name).to_owned(), (*source).to_owned()))
+            .collect();
+
+        if let Some(dir) = config.template_dir() {
+            let entries = std::fs::read_dir(dir).unwrap_or_else(|err| {
+                panic!(
+                    "failed to read mailer.template_dir {}: {err}",
+                    dir.display()
+                )
+            });
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+
+                if path.is_dir() {
+                    let entries = std::fs::read_dir(&path).unwrap_or_else(|err| {
+                        panic!("failed to read email templates {}: {err}", path.display())
+                    });
+                    for entry in entries.flatten() {
+                        read_template(&entry.path(), Some(name), &mut sources);
+                    }
+                } else {
+                    read_template(&path, None, &mut sources);
+                }
+            }
+        }
+
+        let mut tera = Tera::default();
+        tera.add_raw_templates(sources)
+            .unwrap_or_else(|err| panic!("invalid email template: {err:?}"));
+
+        Self {
+            tera,
+            product_name: config.product_name().to_owned(),
+            base_url: config
+                .base_url()
+                .unwrap_or(server_url)
+                .trim_end_matches('/')
+                .to_owned(),
+        }
+    }
+
+    /// Renders `template` in `language` for `to`, with the template's
+    /// variables in `vars`. Parts without a template of the language are
+    /// rendered from the one for every language.
+    ///
+    /// ## Errors
+    /// * [`Error::Template`] if a template uses a variable that is missing
+    pub fn render(
+        &self,
+        template: EmailTemplate,
+        language: &str,
+        to: &str,
+        vars: &Value,
+    ) -> Result<Email> {
+        let mut context = Context::from_value(vars.clone()).map_err(Error::Template)?;
+        context.insert("product_name", &self.product_name);
+        context.insert("base_url", &self.base_url);
+        context.insert("email", to);
+        context.insert("language", language);
+
+        let name = template.name();
+        let render = |part: &str| {
+            let localized = format!("{language}/{name}.{part}");
+            let file = if self.tera.get_template(&localized).is_ok() {
+                localized
+            } else {
+                format!("{name}.{part}")
+            };
+
+            self.tera.render(&file, &context).map_err(Error::Template)
+        };
+
+        Ok(Email {
+            to: to.to_owned(),
+            subject: render("subject.txt")?.trim().to_owned(),
+            body: render("txt")?,
+            html: Some(render("html")?),
+        })
+    }
+}
+
+/// Adds the template at `path`, if it is one, to `sources`, under its file
+/// name prefixed with `language`.
+fn read_template(path: &Path, language: Option<&str>, sources: &mut HashMap<String, String>) {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return;
+    };
+    if !path.is_file() || !(name.ends_with(".txt") || name.ends_with(".html")) {
+        return;
+    }
+
+    let source = std::fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("failed to read email template {}: {err}", path.display()));
+    let name = language.map_or_else(|| name.to_owned(), |language| format!("{language}/{name}"));
+    sources.insert(name, source);
+}
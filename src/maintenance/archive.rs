@@ -0,0 +1,286 @@
+use std::{io::Write, path::Path};
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc};
+use flate2::{Compression, write::GzEncoder};
+use sqlx::PgPool;
+use tokio::{fs, io::AsyncWriteExt};
+use uuid::Uuid;
+
+use super::Maintenance;
+use crate::{Result, audit::AuditEvent};
+
+/// Months past the current one that get a partition ahead of time.
+const MONTHS_AHEAD: u32 = 2;
+
+/// Events read from a partition at a time while archiving it.
+const BATCH_SIZE: i64 = 1000;
+
+/// Key of the advisory lock taken while partitions are created or
+/// archived, so instances sharing the database take turns.
+const LOCK_KEY: i64 = 0x6175_6469_745f_7061;
+
+/// A month of audit events moved out of the database by
+/// [`Maintenance::archive_audit_partitions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archived {
+    /// Name of the dropped partition, e.g. `audit_events_2025_01`.
+    pub partition: String,
+    pub rows: u64,
+}
+
+/// A monthly partition of `audit_events`, attached or left detached by an
+/// archive run that did not finish.
+#[derive(sqlx::FromRow)]
+struct Partition {
+    name: String,
+    attached: bool,
+}
+
+impl Partition {
+    /// First day of the month the partition holds.
+    fn month(&self) -> Option<NaiveDate> {
+        let suffix = self.name.strip_prefix("audit_events_")?;
+        NaiveDate::parse_from_str(&format!("{suffix}_01"), "%Y_%m_%d").ok()
+    }
+}
+
+impl Maintenance {
+    /// Creates the partitions of `audit_events` for this month and the next
+    /// two, and for any month with events in the default partition, that do
+    /// not exist yet. Events already written to the default partition for
+    /// such a month are moved into it.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn create_audit_partitions(&self) -> Result<()> {
+        let this_month = month_start(Utc::now().date_naive());
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut *tx)
+            .await?;
+
+        // Months that already have events in the default partition, when
+        // maintenance did not run in time, get theirs too.
+        let mut months: Vec<NaiveDate> = sqlx::query_scalar(
+            r"
+            SELECT DISTINCT date_trunc('month', created_at AT TIME ZONE 'UTC')::DATE
+            FROM audit_events_default
+            ",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        months.extend((0..=MONTHS_AHEAD).map(|ahead| this_month + Months::new(ahead)));
+        months.sort_unstable();
+        months.dedup();
+
+        for month in months {
+            let name = partition_name(month);
+
+            let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+                .bind(&name)
+                .fetch_one(&mut *tx)
+                .await?;
+            if exists {
+                continue;
+            }
+
+            let (from, to) = bounds(month);
+
+            // A partition cannot be attached while the default one holds
+            // rows of its range, so those are moved first.
+            sqlx::query(&format!(
+                "CREATE TABLE {name} (LIKE audit_events INCLUDING DEFAULTS INCLUDING CONSTRAINTS)"
+            ))
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(&format!(
+                r"
+                WITH moved AS (
+                    DELETE FROM audit_events_default
+                    WHERE created_at >= $1 AND created_at < $2
+                    RETURNING id, user_id, action, ip, metadata, created_at
+                )
+                INSERT INTO {name} (id, user_id, action, ip, metadata, created_at)
+                SELECT id, user_id, action, ip, metadata, created_at FROM moved
+                "
+            ))
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(&format!(
+                "ALTER TABLE audit_events ATTACH PARTITION {name} FOR VALUES FROM ('{}') TO ('{}')",
+                from.to_rfc3339(),
+                to.to_rfc3339(),
+            ))
+            .execute(&mut *tx)
+            .await?;
+
+            tracing::info!(partition = %name, "Audit partition created");
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Archives every month of audit events that ended more than
+    /// `maintenance.audit_archive_after` ago: its partition is detached,
+    /// written gzipped as NDJSON to `audit-{month}.ndjson.gz` in
+    /// `maintenance.audit_archive_dir` and dropped. Does nothing when
+    /// archiving is off.
+    ///
+    /// A partition is only dropped once its file is written, so a run that
+    /// fails halfway is completed by the next one.
+    ///
+    /// ## Errors
+    /// * Database errors
+    /// * I/O errors writing the archive; months archived before the failure
+    ///   stay archived
+    pub async fn archive_audit_partitions(&self) -> Result<Vec<Archived>> {
+        let (after, Some(dir)) = (
+            self.config.audit_archive_after(),
+            self.config.audit_archive_dir(),
+        ) else {
+            return Ok(Vec::new());
+        };
+        if after == 0 {
+            return Ok(Vec::new());
+        }
+
+        let cutoff = Utc::now() - Duration::seconds(i64::try_from(after).unwrap_or(i64::MAX));
+        let mut archived = Vec::new();
+
+        let mut lock = self.db.acquire().await?;
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut *lock)
+            .await?;
+
+        let outcome = async {
+            let partitions = sqlx::query_as::<_, Partition>(
+                r"
+                SELECT c.relname AS name, i.inhparent IS NOT NULL AS attached
+                FROM pg_class c
+                LEFT JOIN pg_inherits i ON i.inhrelid = c.oid
+                WHERE c.relkind = 'r'
+                  AND c.relnamespace = current_schema()::regnamespace
+                  AND c.relname ~ '^audit_events_[0-9]{4}_[0-9]{2}$'
+                ORDER BY c.relname
+                ",
+            )
+            .fetch_all(&self.db)
+            .await?;
+
+            for partition in partitions {
+                let Some(month) = partition.month() else {
+                    continue;
+                };
+                if bounds(month).1 > cutoff {
+                    continue;
+                }
+
+                if partition.attached {
+                    sqlx::query(&format!(
+                        "ALTER TABLE audit_events DETACH PARTITION {}",
+                        partition.name
+                    ))
+                    .execute(&self.db)
+                    .await?;
+                }
+
+                let path = Path::new(dir).join(format!("audit-{}.ndjson.gz", month.format("%Y-%m")));
+                let rows = write_archive(&self.db, &partition.name, &path).await?;
+
+                sqlx::query(&format!("DROP TABLE {}", partition.name))
+                    .execute(&self.db)
+                    .await?;
+
+                tracing::info!(partition = %partition.name, rows, path = %path.display(), "Audit partition archived");
+                metrics::counter!("audit_partitions_archived_total").increment(1);
+
+                archived.push(Archived {
+                    partition: partition.name,
+                    rows,
+                });
+            }
+
+            Ok(())
+        }
+        .await;
+
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut *lock)
+            .await?;
+
+        outcome.map(|()| archived)
+    }
+}
+
+/// Writes every event of the detached partition `table` to `path`, oldest
+/// first, through a temporary file so a partial archive never takes its
+/// place. Returns the number of events written.
+async fn write_archive(db: &PgPool, table: &str, path: &Path) -> Result<u64> {
+    let partial = path.with_extension("gz.partial");
+    let mut file = fs::File::create(&partial).await?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut last: Option<(DateTime<Utc>, Uuid)> = None;
+    let mut rows = 0;
+
+    loop {
+        let events = sqlx::query_as::<_, AuditEvent>(&format!(
+            r"
+            SELECT id, user_id, action, ip, metadata, created_at
+            FROM {table}
+            WHERE $1::TIMESTAMPTZ IS NULL OR (created_at, id) > ($1, $2)
+            ORDER BY created_at, id
+            LIMIT $3
+            "
+        ))
+        .bind(last.map(|(created_at, _)| created_at))
+        .bind(last.map(|(_, id)| id))
+        .bind(BATCH_SIZE)
+        .fetch_all(db)
+        .await?;
+
+        let Some(event) = events.last() else {
+            break;
+        };
+        last = Some((event.created_at, event.id));
+
+        for event in &events {
+            serde_json::to_writer(&mut encoder, event).map_err(std::io::Error::from)?;
+            encoder.write_all(b"\n")?;
+        }
+        rows += events.len() as u64;
+
+        // The encoder writes to memory; the compressed output so far goes
+        // to the file after each batch.
+        file.write_all(encoder.get_ref()).await?;
+        encoder.get_mut().clear();
+    }
+
+    file.write_all(&encoder.finish()?).await?;
+    file.sync_all().await?;
+    fs::rename(&partial, path).await?;
+
+    Ok(rows)
+}
+
+fn month_start(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).unwrap_or(day)
+}
+
+fn partition_name(month: NaiveDate) -> String {
+    format!("audit_events_{}", month.format("%Y_%m"))
+}
+
+/// The range of `created_at` held by the partition of `month`.
+fn bounds(month: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = |day: NaiveDate| Utc.from_utc_datetime(&day.and_time(chrono::NaiveTime::MIN));
+
+    (start(month), start(month + Months::new(1)))
+}
@@ -0,0 +1,194 @@
+mod archive;
+
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+pub use self::archive::Archived;
+use crate::{Result, config::MaintenanceConfig};
+
+/// What each purge deletes, by table. Rows that are still needed, such as
+/// unexpired tokens or accepted invitations, are never matched.
+const PURGES: [(&str, &str); 16] = [
+    ("sessions", "DELETE FROM sessions WHERE expires_at <= now()"),
+    (
+        "password_reset_tokens",
+        "DELETE FROM password_reset_tokens WHERE expires_at <= now() OR used_at IS NOT NULL",
+    ),
+    (
+        "email_verification_tokens",
+        "DELETE FROM email_verification_tokens WHERE expires_at <= now()",
+    ),
+    (
+        "magic_link_tokens",
+        "DELETE FROM magic_link_tokens WHERE expires_at <= now() OR used_at IS NOT NULL",
+    ),
+    (
+        "email_change_requests",
+        "DELETE FROM email_change_requests WHERE expires_at <= now() OR used_at IS NOT NULL",
+    ),
+    (
+        "phone_otp_codes",
+        "DELETE FROM phone_otp_codes WHERE expires_at <= now() OR used_at IS NOT NULL",
+    ),
+    (
+        "email_otp_codes",
+        "DELETE FROM email_otp_codes WHERE expires_at <= now() OR used_at IS NOT NULL",
+    ),
+    (
+        "webauthn_challenges",
+        "DELETE FROM webauthn_challenges WHERE expires_at <= now()",
+    ),
+    (
+        "saml_requests",
+        "DELETE FROM saml_requests WHERE expires_at <= now()",
+    ),
+    (
+        "saml_sessions",
+        "DELETE FROM saml_sessions WHERE expires_at <= now()",
+    ),
+    (
+        "oauth_authorization_codes",
+        "DELETE FROM oauth_authorization_codes WHERE expires_at <= now() OR used_at IS NOT NULL",
+    ),
+    (
+        "oauth_access_tokens",
+        "DELETE FROM oauth_access_tokens WHERE expires_at <= now()",
+    ),
+    (
+        "oauth_client_sessions",
+        "DELETE FROM oauth_client_sessions WHERE expires_at <= now()",
+    ),
+    (
+        "revoked_tokens",
+        "DELETE FROM revoked_tokens WHERE expires_at <= now()",
+    ),
+    (
+        "signup_invites",
+        "DELETE FROM signup_invites WHERE expires_at <= now() AND accepted_at IS NULL",
+    ),
+    (
+        "organization_invitations",
+        "DELETE FROM organization_invitations WHERE expires_at <= now() AND accepted_at IS NULL",
+    ),
+];
+
+/// Rows deleted from one table by [`Maintenance::purge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Purged {
+    pub table: &'static str,
+    pub rows: u64,
+}
+
+/// Deletes expired sessions, spent tokens, old audit events and old login
+/// attempts, and keeps the monthly partitions of `audit_events`, see
+/// [`MaintenanceConfig`].
+#[derive(Clone)]
+pub struct Maintenance {
+    db: PgPool,
+    config: MaintenanceConfig,
+}
+
+impl Maintenance {
+    #[must_use]
+    pub fn new(db: PgPool, config: MaintenanceConfig) -> Self {
+        Self { db, config }
+    }
+
+    /// Runs every purge once, records the deleted rows in the
+    /// `maintenance_rows_deleted_total` metric and returns them by table.
+    ///
+    /// ## Errors
+    /// * Database errors; purges that already ran are still recorded
+    pub async fn purge(&self) -> Result<Vec<Purged>> {
+        let started = Instant::now();
+        let mut purged = Vec::with_capacity(PURGES.len() + 2);
+
+        for (table, statement) in PURGES {
+            let rows = sqlx::query(statement)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+
+            record(table, rows);
+            purged.push(Purged { table, rows });
+        }
+
+        for (table, retention) in [
+            ("audit_events", self.config.audit_retention()),
+            ("login_attempts", self.config.login_history_retention()),
+        ] {
+            if retention == 0 {
+                continue;
+            }
+            let retention = Duration::seconds(i64::try_from(retention).unwrap_or(i64::MAX));
+
+            let rows = sqlx::query(&format!("DELETE FROM {table} WHERE created_at <= $1"))
+                .bind(Utc::now() - retention)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+
+            record(table, rows);
+            purged.push(Purged { table, rows });
+        }
+
+        metrics::histogram!("maintenance_duration_seconds").record(started.elapsed().as_secs_f64());
+
+        Ok(purged)
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn record(table: &'static str, rows: u64) {
+    metrics::counter!("maintenance_rows_deleted_total", "table" => table).increment(rows);
+    metrics::gauge!("maintenance_last_rows_deleted", "table" => table).set(rows as f64);
+}
+
+/// Spawns the background task that runs [`Maintenance::purge`],
+/// [`Maintenance::create_audit_partitions`] and
+/// [`Maintenance::archive_audit_partitions`] every `maintenance.interval`
+/// until `shutdown` is cancelled.
+pub fn spawn_worker(maintenance: Maintenance, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let period = StdDuration::from_secs(maintenance.config.interval().max(1));
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.cancelled() => break,
+            }
+
+            match maintenance.purge().await {
+                Ok(purged) => {
+                    let total: u64 = purged.iter().map(|purged| purged.rows).sum();
+                    if total > 0 {
+                        tracing::info!(
+                            rows = total,
+                            tables = ?purged.iter().filter(|purged| purged.rows > 0).map(|purged| (purged.table, purged.rows)).collect::<Vec<_>>(),
+                            "Maintenance purge finished"
+                        );
+                    }
+                }
+                Err(err) => {
+                    metrics::counter!("maintenance_failures_total").increment(1);
+                    tracing::warn!(error = %err, "Maintenance purge failed");
+                }
+            }
+
+            if let Err(err) = maintenance.create_audit_partitions().await {
+                metrics::counter!("maintenance_failures_total").increment(1);
+                tracing::warn!(error = %err, "Creating audit partitions failed");
+            }
+
+            if let Err(err) = maintenance.archive_audit_partitions().await {
+                metrics::counter!("maintenance_failures_total").increment(1);
+                tracing::warn!(error = %err, "Archiving audit partitions failed");
+            }
+        }
+    })
+}
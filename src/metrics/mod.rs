@@ -0,0 +1,229 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use axum::{http::header, response::IntoResponse};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    AppContext, Result,
+    config::ConfigResult,
+    events::{Event, Subscriber},
+    sessions::SessionStore,
+};
+
+/// How often the pool statistics are sampled and the recorder upkeep runs.
+const COLLECT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Histogram buckets (in seconds) for connection acquisition latency.
+const ACQUIRE_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Histogram buckets (in seconds) for password hashing and verification.
+const PASSWORD_HASH_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the global Prometheus recorder.
+///
+/// Every `metrics::counter!`/`gauge!`/`histogram!` call made anywhere in the
+/// process is recorded by it and rendered by [`handler`]. Installing twice is a
+/// no-op, so tests and embedding applications can call this freely.
+///
+/// ## Errors
+///
+/// * Another global `metrics` recorder has already been installed
+/// * Invalid histogram bucket configuration
+pub fn install() -> ConfigResult<()> {
+    if HANDLE.get().is_some() {
+        return Ok(());
+    }
+
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("db_pool_acquire_duration_seconds".into()),
+            ACQUIRE_BUCKETS,
+        )?
+        .set_buckets_for_metric(
+            Matcher::Full("password_hash_duration_seconds".into()),
+            PASSWORD_HASH_BUCKETS,
+        )?
+        .install_recorder()?;
+
+    let _ = HANDLE.set(handle);
+
+    describe();
+
+    Ok(())
+}
+
+fn describe() {
+    metrics::describe_gauge!(
+        "db_pool_connections",
+        "Number of connections currently held by the pool, idle or in use"
+    );
+    metrics::describe_gauge!(
+        "db_pool_idle_connections",
+        "Number of idle connections waiting in the pool"
+    );
+    metrics::describe_gauge!(
+        "db_pool_max_connections",
+        "Maximum number of connections the pool may open"
+    );
+    metrics::describe_histogram!(
+        "db_pool_acquire_duration_seconds",
+        metrics::Unit::Seconds,
+        "Time spent waiting to acquire a pooled connection"
+    );
+    metrics::describe_counter!(
+        "db_pool_acquire_timeouts_total",
+        "Number of connection acquisitions that timed out"
+    );
+    metrics::describe_gauge!(
+        "http_requests_in_flight",
+        "Number of requests being handled"
+    );
+    metrics::describe_counter!(
+        "http_requests_shed_total",
+        "Number of requests shed with 503 for being over a concurrency cap, by cap"
+    );
+    metrics::describe_counter!(
+        "maintenance_rows_deleted_total",
+        "Number of expired or spent rows deleted by the maintenance purge, by table"
+    );
+    metrics::describe_gauge!(
+        "maintenance_last_rows_deleted",
+        "Number of rows deleted from each table by the last maintenance purge"
+    );
+    metrics::describe_histogram!(
+        "maintenance_duration_seconds",
+        metrics::Unit::Seconds,
+        "Time taken by a maintenance purge"
+    );
+    metrics::describe_counter!(
+        "maintenance_failures_total",
+        "Number of maintenance runs that failed"
+    );
+    metrics::describe_counter!(
+        "audit_partitions_archived_total",
+        "Number of monthly audit event partitions archived and dropped"
+    );
+    metrics::describe_counter!(
+        "audit_events_exported_total",
+        "Number of audit events sent to each audit sink"
+    );
+    metrics::describe_counter!(
+        "audit_export_failures_total",
+        "Number of audit event batches an audit sink failed to take"
+    );
+    metrics::describe_counter!(
+        "login_success_total",
+        "Number of sessions started, by login method"
+    );
+    metrics::describe_counter!(
+        "login_failure_total",
+        "Number of password logins refused, by reason"
+    );
+    metrics::describe_counter!(
+        "token_issued_total",
+        "Number of access and refresh tokens issued, by kind"
+    );
+    metrics::describe_gauge!(
+        "active_sessions",
+        "Number of unexpired sessions of logged-in users"
+    );
+    metrics::describe_histogram!(
+        "password_hash_duration_seconds",
+        metrics::Unit::Seconds,
+        "Time taken to hash or verify a password, by operation"
+    );
+}
+
+/// Axum handler rendering all recorded metrics in the Prometheus text format.
+pub async fn handler() -> impl IntoResponse {
+    let body = HANDLE
+        .get()
+        .map(PrometheusHandle::render)
+        .unwrap_or_default();
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// [`Subscriber`] counting the sessions started in `login_success_total`,
+/// by login method.
+pub struct LoginCounter;
+
+#[async_trait]
+impl Subscriber for LoginCounter {
+    async fn handle(&self, _ctx: &AppContext, event: &Event) -> Result<()> {
+        if let Event::UserLoggedIn { method, .. } = event {
+            metrics::counter!("login_success_total", "method" => method.name()).increment(1);
+        }
+        Ok(())
+    }
+}
+
+/// Spawns the background task that samples pool and session statistics.
+///
+/// On every tick the task records the pool size, idle and maximum
+/// connections as gauges, then times a real `acquire()` so the
+/// `db_pool_acquire_duration_seconds` histogram reflects how long requests
+/// currently wait for a connection. Acquisitions that hit the pool's
+/// `acquire_timeout` increment `db_pool_acquire_timeouts_total`. The
+/// `active_sessions` gauge is set from the session store of `ctx`, when it
+/// can count them. Stops when `shutdown` is cancelled.
+pub fn spawn_collector(ctx: Arc<AppContext>, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(COLLECT_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.cancelled() => break,
+            }
+            collect_pool_stats(ctx.db()).await;
+            collect_session_stats(ctx.sessions()).await;
+
+            if let Some(handle) = HANDLE.get() {
+                handle.run_upkeep();
+            }
+        }
+    })
+}
+
+#[allow(clippy::cast_precision_loss)]
+async fn collect_pool_stats(pool: &PgPool) {
+    metrics::gauge!("db_pool_connections").set(f64::from(pool.size()));
+    metrics::gauge!("db_pool_idle_connections").set(pool.num_idle() as f64);
+    metrics::gauge!("db_pool_max_connections").set(f64::from(pool.options().get_max_connections()));
+
+    let started = Instant::now();
+
+    match pool.acquire().await {
+        Ok(_conn) => {
+            metrics::histogram!("db_pool_acquire_duration_seconds")
+                .record(started.elapsed().as_secs_f64());
+        }
+        Err(sqlx::Error::PoolTimedOut) => {
+            metrics::counter!("db_pool_acquire_timeouts_total").increment(1);
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "Failed to sample database pool");
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+async fn collect_session_stats(sessions: &dyn SessionStore) {
+    match sessions.count_active().await {
+        Ok(Some(count)) => metrics::gauge!("active_sessions").set(count as f64),
+        Ok(None) => {}
+        Err(err) => tracing::warn!(error = %err, "Failed to count active sessions"),
+    }
+}
@@ -0,0 +1,83 @@
+use serde_json::{Map, Value};
+
+use crate::{Error, Result};
+
+/// The free-form JSON objects stored with every user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metadata {
+    /// `user_metadata`, which users edit themselves through `PATCH /me`.
+    User,
+    /// `app_metadata`, which only admins and services can change, for data
+    /// users must not tamper with such as plan or tenant ids.
+    App,
+}
+
+impl Metadata {
+    /// The column and JSON field holding this metadata.
+    #[must_use]
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::User => "user_metadata",
+            Self::App => "app_metadata",
+        }
+    }
+}
+
+/// Applies `patch` to the metadata object `target` as a JSON merge patch
+/// (RFC 7396): keys set to `null` are removed, nested objects are merged
+/// and anything else replaces the current value. The result must serialize
+/// to at most `max_size` bytes.
+///
+/// ```
+/// use betterauth::models::merge_metadata;
+/// use serde_json::json;
+///
+/// let mut metadata = json!({ "theme": "dark", "beta": true });
+/// merge_metadata(&mut metadata, &json!({ "beta": null, "locale": "de" }), 1024).unwrap();
+///
+/// assert_eq!(metadata, json!({ "theme": "dark", "locale": "de" }));
+/// ```
+///
+/// ## Errors
+/// * [`Error::Validation`] if `patch` is not an object or the result is too
+///   large
+pub fn merge_metadata(target: &mut Value, patch: &Value, max_size: usize) -> Result<()> {
+    if !patch.is_object() {
+        return Err(Error::Validation(String::from(
+            "metadata must be a JSON object",
+        )));
+    }
+
+    merge(target, patch);
+
+    let size = serde_json::to_vec(target).map_or(usize::MAX, |json| json.len());
+    if size > max_size {
+        return Err(Error::Validation(format!(
+            "metadata must be at most {max_size} bytes"
+        )));
+    }
+
+    Ok(())
+}
+
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        patch.clone_into(target);
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
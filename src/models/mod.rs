@@ -0,0 +1,7 @@
+mod metadata;
+mod user;
+
+pub use self::{
+    metadata::{Metadata, merge_metadata},
+    user::{NewUser, User, UserStatus},
+};
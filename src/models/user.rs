@@ -0,0 +1,128 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    auth::{Role, normalize_email},
+    config::EmailNormalization,
+};
+
+/// Whether an account may be used, stored in `users.status`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    #[default]
+    Active,
+    /// Blocked by an admin until re-enabled; sessions and tokens stop
+    /// working.
+    Disabled,
+    /// Deleted by an admin. The row stays, so audit events keep pointing at
+    /// it, but the account is left out of lookups and cannot sign in.
+    Deleted,
+}
+
+impl UserStatus {
+    /// Value stored in `users.status`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Disabled => "disabled",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+impl FromStr for UserStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "active" => Ok(Self::Active),
+            "disabled" => Ok(Self::Disabled),
+            "deleted" => Ok(Self::Deleted),
+            other => Err(Error::Validation(format!("unknown user status: {other}"))),
+        }
+    }
+}
+
+impl TryFrom<String> for UserStatus {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for UserStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A registered account.
+///
+/// Serializes without the password hash, so it can be returned to clients
+/// as is. Loaded and stored through [`crate::repositories::UserStore`].
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct User {
+    pub id: Uuid,
+    pub email: String,
+    /// Unique handle to log in with, when `auth.usernames` is enabled.
+    pub username: Option<String>,
+    pub name: Option<String>,
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub role: Role,
+    /// Verified phone number in E.164 format, used for phone login.
+    pub phone: Option<String>,
+    pub verified_at: Option<DateTime<Utc>>,
+    #[sqlx(try_from = "String")]
+    pub status: UserStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Free-form profile data the user edits, see [`super::Metadata`].
+    #[sqlx(json)]
+    pub user_metadata: Value,
+    /// Free-form data only admins and services edit.
+    #[sqlx(json)]
+    pub app_metadata: Value,
+}
+
+/// The fields of a [`User`] about to be created; the rest are filled in by
+/// the database.
+#[derive(Debug, Clone)]
+pub struct NewUser<'a> {
+    pub email: &'a str,
+    /// `email` as [`normalize_email`] folds it, unique across accounts.
+    pub email_normalized: String,
+    pub username: Option<&'a str>,
+    pub name: Option<&'a str>,
+    /// `None` for accounts that only sign in through a provider.
+    pub password_hash: Option<&'a str>,
+    pub role: Role,
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+impl<'a> NewUser<'a> {
+    /// An unverified account with the `user` role and no password, its
+    /// address normalized with `normalization`.
+    #[must_use]
+    pub fn new(email: &'a str, normalization: &EmailNormalization) -> Self {
+        Self {
+            email,
+            email_normalized: normalize_email(email, normalization),
+            username: None,
+            name: None,
+            password_hash: None,
+            role: Role::User,
+            verified_at: None,
+        }
+    }
+}
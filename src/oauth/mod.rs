@@ -0,0 +1,414 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{
+    Error, Result,
+    auth::generate_token,
+    config::{CookieConfig, OAuthConfig, OAuthProviderConfig},
+    trace,
+};
+
+/// Name of the cookie carrying the pending authorization between the
+/// redirect to the provider and the callback, after `cookies.prefix`.
+pub const OAUTH_COOKIE: &str = "oauth";
+
+/// How long a user has to complete consent at the provider, in seconds.
+const PENDING_TTL: i64 = 10 * 60;
+
+/// Social login providers supported out of the box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    Google,
+    Github,
+}
+
+impl Provider {
+    /// Identifier used in routes and stored in `oauth_accounts.provider`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Google => "google",
+            Self::Github => "github",
+        }
+    }
+
+    fn authorize_endpoint(self) -> &'static str {
+        match self {
+            Self::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+            Self::Github => "https://github.com/login/oauth/authorize",
+        }
+    }
+
+    fn token_endpoint(self) -> &'static str {
+        match self {
+            Self::Google => "https://oauth2.googleapis.com/token",
+            Self::Github => "https://github.com/login/oauth/access_token",
+        }
+    }
+
+    fn scopes(self) -> &'static str {
+        match self {
+            Self::Google => "openid email profile",
+            Self::Github => "read:user user:email",
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "google" => Ok(Self::Google),
+            "github" => Ok(Self::Github),
+            _ => Err(Error::UnknownProvider),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An authorization started by [`OAuthClient::authorize`].
+///
+/// `state` and `verifier` must be kept by the client until the callback,
+/// see [`pending_cookie`].
+#[derive(Debug)]
+pub struct Authorization {
+    /// Provider consent page to redirect the user to.
+    pub url: String,
+    /// CSRF token echoed back by the provider.
+    pub state: String,
+    /// PKCE code verifier; only its SHA-256 challenge is sent up front.
+    pub verifier: String,
+}
+
+/// Tokens returned by the provider's token endpoint.
+#[derive(Debug, Deserialize)]
+pub struct ProviderTokens {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Lifetime of the access token, in seconds.
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+}
+
+/// The provider's view of the user who just signed in.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Stable account identifier at the provider.
+    pub id: String,
+    pub email: String,
+    /// Whether the provider has confirmed that the user owns `email`.
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// Runs the OAuth2 authorization-code flow with PKCE against the configured
+/// providers.
+#[derive(Clone)]
+pub struct OAuthClient {
+    http: reqwest::Client,
+    config: OAuthConfig,
+}
+
+impl OAuthClient {
+    /// # Panics
+    /// If the TLS backend of the HTTP client cannot be initialised.
+    #[must_use]
+    pub fn from_config(config: &OAuthConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("betterauth/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("failed to initialise HTTP client");
+
+        Self {
+            http,
+            config: config.clone(),
+        }
+    }
+
+    fn credentials(&self, provider: Provider) -> Result<&OAuthProviderConfig> {
+        match provider {
+            Provider::Google => self.config.google(),
+            Provider::Github => self.config.github(),
+        }
+        .ok_or(Error::UnknownProvider)
+    }
+
+    /// Path the pending authorization cookie of `provider` is scoped to: the
+    /// OAuth routes its `redirect_url` points at, e.g. `/api/v1/auth/oauth`,
+    /// so the cookie reaches the callback whichever API version started it.
+    ///
+    /// ## Errors
+    /// * [`Error::UnknownProvider`] if the provider is not configured
+    pub fn cookie_path(&self, provider: Provider) -> Result<String> {
+        let redirect = Url::parse(self.credentials(provider)?.redirect_url()).ok();
+        let scope = redirect.as_ref().and_then(|url| {
+            let path = url.path();
+            path.rfind("/auth/oauth/")
+                .map(|start| path[..start + "/auth/oauth".len()].to_owned())
+        });
+
+        Ok(scope.unwrap_or_else(|| String::from("/auth/oauth")))
+    }
+
+    /// Starts an authorization with `provider`, generating a fresh state and
+    /// PKCE verifier.
+    ///
+    /// ## Errors
+    /// * [`Error::UnknownProvider`] if the provider is not configured
+    pub fn authorize(&self, provider: Provider) -> Result<Authorization> {
+        let credentials = self.credentials(provider)?;
+        let state = generate_token();
+        let verifier = generate_token();
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+        let url = Url::parse_with_params(
+            provider.authorize_endpoint(),
+            [
+                ("response_type", "code"),
+                ("client_id", credentials.client_id()),
+                ("redirect_uri", credentials.redirect_url()),
+                ("scope", provider.scopes()),
+                ("state", state.as_str()),
+                ("code_challenge", challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|err| Error::OAuth(err.to_string()))?;
+
+        Ok(Authorization {
+            url: url.into(),
+            state,
+            verifier,
+        })
+    }
+
+    /// Exchanges the authorization `code` from the callback for tokens.
+    ///
+    /// ## Errors
+    /// * [`Error::UnknownProvider`] if the provider is not configured
+    /// * [`Error::OAuth`] if the provider rejects the code or cannot be reached
+    pub async fn exchange_code(
+        &self,
+        provider: Provider,
+        code: &str,
+        verifier: &str,
+    ) -> Result<ProviderTokens> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            #[serde(flatten)]
+            tokens: Option<ProviderTokens>,
+            error: Option<String>,
+        }
+
+        let credentials = self.credentials(provider)?;
+
+        let response: TokenResponse = self
+            .http
+            .post(provider.token_endpoint())
+            .headers(trace::trace_headers())
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", credentials.redirect_url()),
+                ("client_id", credentials.client_id()),
+                ("client_secret", credentials.client_secret().expose()),
+                ("code_verifier", verifier),
+            ])
+            .send()
+            .await
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        match response {
+            TokenResponse {
+                tokens: Some(tokens),
+                ..
+            } => Ok(tokens),
+            TokenResponse { error, .. } => Err(Error::OAuth(format!(
+                "{provider} token exchange failed: {}",
+                error.as_deref().unwrap_or("no access token returned")
+            ))),
+        }
+    }
+
+    /// Looks up the signed-in user at the provider.
+    ///
+    /// ## Errors
+    /// * [`Error::OAuth`] if the provider cannot be reached or the account
+    ///   has no usable email address
+    pub async fn fetch_profile(&self, provider: Provider, access_token: &str) -> Result<Profile> {
+        match provider {
+            Provider::Google => self.google_profile(access_token).await,
+            Provider::Github => self.github_profile(access_token).await,
+        }
+    }
+
+    async fn google_profile(&self, access_token: &str) -> Result<Profile> {
+        #[derive(Deserialize)]
+        struct UserInfo {
+            sub: String,
+            email: Option<String>,
+            #[serde(default)]
+            email_verified: bool,
+            name: Option<String>,
+        }
+
+        let info: UserInfo = self
+            .get_json(
+                "https://openidconnect.googleapis.com/v1/userinfo",
+                access_token,
+            )
+            .await?;
+
+        Ok(Profile {
+            id: info.sub,
+            email: info
+                .email
+                .ok_or_else(|| Error::OAuth(String::from("google account has no email")))?,
+            email_verified: info.email_verified,
+            name: info.name,
+        })
+    }
+
+    async fn github_profile(&self, access_token: &str) -> Result<Profile> {
+        #[derive(Deserialize)]
+        struct User {
+            id: u64,
+            login: String,
+            name: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct UserEmail {
+            email: String,
+            primary: bool,
+            verified: bool,
+        }
+
+        let user: User = self
+            .get_json("https://api.github.com/user", access_token)
+            .await?;
+
+        // The public profile email is optional and unverified, the primary
+        // address from the emails endpoint is neither.
+        let emails: Vec<UserEmail> = self
+            .get_json("https://api.github.com/user/emails", access_token)
+            .await?;
+
+        let primary = emails
+            .into_iter()
+            .find(|email| email.primary)
+            .ok_or_else(|| Error::OAuth(String::from("github account has no primary email")))?;
+
+        Ok(Profile {
+            id: user.id.to_string(),
+            email: primary.email,
+            email_verified: primary.verified,
+            name: user.name.or(Some(user.login)),
+        })
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        access_token: &str,
+    ) -> Result<T> {
+        self.http
+            .get(url)
+            .headers(trace::trace_headers())
+            .bearer_auth(access_token)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)
+    }
+}
+
+fn provider_error(err: reqwest::Error) -> Error {
+    Error::OAuth(err.to_string())
+}
+
+/// Builds the short-lived cookie that remembers a pending authorization,
+/// with the name, domain and flags of the `cookies` section; `https` is
+/// whether the server URL is https.
+///
+/// It is scoped to `path`, the OAuth routes, see [`OAuthClient::cookie_path`],
+/// and must survive the top-level redirect back from the provider, hence
+/// `SameSite=Lax`.
+#[must_use]
+pub fn pending_cookie(
+    provider: Provider,
+    authorization: &Authorization,
+    config: &CookieConfig,
+    https: bool,
+    path: String,
+) -> Cookie<'static> {
+    let mut cookie = pending_removal_cookie(config, path);
+    cookie.set_value(format!(
+        "{provider}:{}:{}",
+        authorization.state, authorization.verifier
+    ));
+    cookie.set_http_only(config.http_only());
+    cookie.set_secure(config.is_secure(https));
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(time::Duration::seconds(PENDING_TTL));
+    cookie
+}
+
+/// Cookie that clears the pending authorization scoped to `path` once the
+/// callback ran.
+#[must_use]
+pub fn pending_removal_cookie(config: &CookieConfig, path: String) -> Cookie<'static> {
+    // `__Host-` cookies must be scoped to the whole site.
+    let path = if config.prefix().starts_with("__Host-") {
+        String::from("/")
+    } else {
+        path
+    };
+
+    let mut cookie = Cookie::new(config.name(OAUTH_COOKIE), "");
+    cookie.set_path(path);
+    if let Some(domain) = config.domain() {
+        cookie.set_domain(domain.to_owned());
+    }
+    cookie
+}
+
+/// Checks the callback against the pending authorization cookie and returns
+/// the PKCE verifier to redeem the code with.
+///
+/// ## Errors
+/// * [`Error::InvalidToken`] if the cookie is missing, belongs to another
+///   provider or its state does not match
+pub fn verify_pending(cookie: Option<&str>, provider: Provider, state: &str) -> Result<String> {
+    let mut parts = cookie.ok_or(Error::InvalidToken)?.splitn(3, ':');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(name), Some(expected), Some(verifier))
+            if name == provider.name() && expected == state =>
+        {
+            Ok(verifier.to_owned())
+        }
+        _ => Err(Error::InvalidToken),
+    }
+}
@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    Result,
+    auth::{generate_token, hash_token},
+};
+
+/// An application registered to delegate login to this server.
+///
+/// Confidential clients authenticate at the token endpoint with a secret, of
+/// which only the SHA-256 hash is stored. Public clients (SPAs, native apps)
+/// have none and must use PKCE instead. Confidential clients with `scopes`
+/// may also obtain tokens for themselves through the client-credentials
+/// grant, for machine-to-machine calls where no user is involved.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RegisteredClient {
+    pub id: Uuid,
+    pub client_id: String,
+    pub name: String,
+    /// Exact redirect URIs the client may ask codes to be sent to.
+    pub redirect_uris: Vec<String>,
+    /// Scopes the client may request through the client-credentials grant.
+    pub scopes: Vec<String>,
+    /// Exact URIs the client may ask users to be sent back to after logging
+    /// out at the end-session endpoint.
+    pub post_logout_redirect_uris: Vec<String>,
+    /// Where logout tokens are posted when a session the client was issued
+    /// an ID token in ends.
+    pub backchannel_logout_uri: Option<String>,
+    pub confidential: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RegisteredClient {
+    /// Whether `redirect_uri` is one of the registered ones, compared
+    /// exactly.
+    #[must_use]
+    pub fn allows_redirect(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+
+    /// Whether `redirect_uri` is one of the registered post-logout redirect
+    /// URIs, compared exactly.
+    #[must_use]
+    pub fn allows_post_logout_redirect(&self, redirect_uri: &str) -> bool {
+        self.post_logout_redirect_uris
+            .iter()
+            .any(|uri| uri == redirect_uri)
+    }
+
+    /// The scope granted for a space-separated `requested` scope through the
+    /// client-credentials grant: every allowed scope if none is requested,
+    /// and `None` if a requested scope is not allowed.
+    #[must_use]
+    pub fn grant_scope(&self, requested: Option<&str>) -> Option<String> {
+        let Some(requested) = requested.filter(|scope| !scope.trim().is_empty()) else {
+            return Some(self.scopes.join(" "));
+        };
+
+        let mut granted = Vec::new();
+        for scope in requested.split_whitespace() {
+            if !self.scopes.iter().any(|allowed| allowed == scope) {
+                return None;
+            }
+            if !granted.contains(&scope) {
+                granted.push(scope);
+            }
+        }
+
+        Some(granted.join(" "))
+    }
+}
+
+/// An access token issued to a client through the authorization-code grant,
+/// acting for the user who authorized it.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AccessGrant {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Space-separated scopes the user granted.
+    pub scope: String,
+}
+
+/// Postgres-backed persistence for [`RegisteredClient`]s.
+#[derive(Clone)]
+pub struct ClientStore {
+    db: PgPool,
+}
+
+impl ClientStore {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Registers a client.
+    ///
+    /// For confidential clients the plain secret is returned alongside; it
+    /// must be handed over now as it cannot be recovered later.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn create(
+        &self,
+        name: &str,
+        redirect_uris: &[String],
+        scopes: &[String],
+        post_logout_redirect_uris: &[String],
+        backchannel_logout_uri: Option<&str>,
+        confidential: bool,
+    ) -> Result<(RegisteredClient, Option<String>)> {
+        let secret = confidential.then(generate_token);
+
+        let client = sqlx::query_as::<_, RegisteredClient>(
+            r"
+            INSERT INTO oauth_clients
+                (client_id, client_secret_hash, name, redirect_uris, scopes,
+                 post_logout_redirect_uris, backchannel_logout_uri, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, client_id, name, redirect_uris, scopes, post_logout_redirect_uris,
+                      backchannel_logout_uri, client_secret_hash IS NOT NULL AS confidential,
+                      created_at
+            ",
+        )
+        .bind(Uuid::new_v4().simple().to_string())
+        .bind(secret.as_deref().map(hash_token))
+        .bind(name)
+        .bind(redirect_uris)
+        .bind(scopes)
+        .bind(post_logout_redirect_uris)
+        .bind(backchannel_logout_uri)
+        .bind(Utc::now())
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok((client, secret))
+    }
+
+    /// Lists every registered client, newest first.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn list(&self) -> Result<Vec<RegisteredClient>> {
+        sqlx::query_as::<_, RegisteredClient>(
+            r"
+            SELECT id, client_id, name, redirect_uris, scopes, post_logout_redirect_uris,
+                   backchannel_logout_uri, client_secret_hash IS NOT NULL AS confidential,
+                   created_at
+            FROM oauth_clients
+            ORDER BY created_at DESC
+            ",
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Looks up a client by its public `client_id`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn find(&self, client_id: &str) -> Result<Option<RegisteredClient>> {
+        sqlx::query_as::<_, RegisteredClient>(
+            r"
+            SELECT id, client_id, name, redirect_uris, scopes, post_logout_redirect_uris,
+                   backchannel_logout_uri, client_secret_hash IS NOT NULL AS confidential,
+                   created_at
+            FROM oauth_clients
+            WHERE client_id = $1
+            ",
+        )
+        .bind(client_id)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Resolves the client presenting `client_id` and, for confidential
+    /// clients, `secret`. Returns `None` if the client is unknown, a
+    /// confidential client's secret is missing or wrong, or a public client
+    /// sends one.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn authenticate(
+        &self,
+        client_id: &str,
+        secret: Option<&str>,
+    ) -> Result<Option<RegisteredClient>> {
+        let found = sqlx::query_as::<_, (Option<String>,)>(
+            "SELECT client_secret_hash FROM oauth_clients WHERE client_id = $1",
+        )
+        .bind(client_id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let authenticated = match (found, secret) {
+            (Some((Some(expected),)), Some(secret)) => hash_token(secret) == expected,
+            (Some((None,)), None) => true,
+            _ => false,
+        };
+
+        if !authenticated {
+            return Ok(None);
+        }
+
+        self.find(client_id).await
+    }
+
+    /// Resolves an access token the token endpoint issued acting for a user,
+    /// as long as it has not expired, the user is active and their tokens
+    /// were not revoked since. Tokens issued to clients for themselves
+    /// resolve to `None`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn access_grant(&self, token: &str) -> Result<Option<AccessGrant>> {
+        sqlx::query_as::<_, AccessGrant>(
+            r"
+            SELECT t.id, t.user_id, t.scope
+            FROM oauth_access_tokens t
+            JOIN users u ON u.id = t.user_id
+            WHERE t.token_hash = $1
+              AND t.expires_at > now()
+              AND u.status = 'active'
+              AND (u.tokens_revoked_at IS NULL OR t.created_at >= u.tokens_revoked_at)
+            ",
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Deletes a client together with its codes and tokens. Returns `false`
+    /// if there was no such client.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn delete(&self, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM oauth_clients WHERE id = $1")
+            .bind(id)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+        Ok(deleted > 0)
+    }
+}
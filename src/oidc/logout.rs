@@ -0,0 +1,317 @@
+use std::time::Duration as StdDuration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use sqlx::PgPool;
+use url::form_urlencoded;
+use uuid::Uuid;
+
+use super::OidcProvider;
+use crate::{
+    AppContext, Error, Result,
+    events::{Event, Subscriber},
+    jobs::{Job, JobQueue},
+    trace,
+    webhooks::DeliveryStatus,
+};
+
+/// How long a client's back-channel logout URI is given to answer.
+const TIMEOUT: StdDuration = StdDuration::from_secs(10);
+
+/// One logout token sent, or to be sent, to one client.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LogoutDelivery {
+    pub id: Uuid,
+    /// `oauth_clients.id` of the client told.
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    /// The session that ended.
+    pub session_id: Option<Uuid>,
+    #[sqlx(try_from = "String")]
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// HTTP status of the last attempt, if the client answered.
+    pub last_status_code: Option<i32>,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingLogout {
+    client_id: String,
+    backchannel_logout_uri: Option<String>,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+}
+
+/// OpenID Connect back-channel logout: tells clients that a session they
+/// were issued an ID token in has ended.
+///
+/// The token endpoint records every session a client logs a user in from
+/// with [`Self::record_session`]. When [`Event::SessionsRevoked`] is
+/// published, by logging out, a password change, an admin ending sessions
+/// or disabling the account, each client with a `backchannel_logout_uri`
+/// is queued a [`Job::DeliverLogout`], which posts a signed logout token
+/// naming the user and the session. Failed attempts are retried by the job
+/// worker and the outcome is kept per client.
+#[derive(Clone)]
+pub struct BackchannelLogout {
+    db: PgPool,
+    http: reqwest::Client,
+}
+
+impl BackchannelLogout {
+    /// # Panics
+    /// If the TLS backend of the HTTP client cannot be initialised.
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("betterauth/", env!("CARGO_PKG_VERSION")))
+            .timeout(TIMEOUT)
+            .build()
+            .expect("failed to initialise HTTP client");
+
+        Self { db, http }
+    }
+
+    /// Records that client `client_id` was issued an ID token in session
+    /// `session_id` of `user_id`, which ends by `expires_at` at the latest.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn record_session(
+        &self,
+        client_id: Uuid,
+        session_id: Uuid,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO oauth_client_sessions (client_id, session_id, user_id, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (client_id, session_id) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            ",
+        )
+        .bind(client_id)
+        .bind(session_id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .bind(expires_at)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Queues a logout token for every client with a back-channel logout
+    /// URI that was issued an ID token in one of `session_ids` of
+    /// `user_id`, and forgets those sessions.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn dispatch(&self, user_id: Uuid, session_ids: &[Uuid]) -> Result<()> {
+        if session_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        let ids: Vec<Uuid> = sqlx::query_scalar(
+            r"
+            WITH ended AS (
+                DELETE FROM oauth_client_sessions
+                WHERE user_id = $1 AND session_id = ANY($2)
+                RETURNING client_id, session_id
+            )
+            INSERT INTO backchannel_logout_deliveries (client_id, user_id, session_id, created_at)
+            SELECT ended.client_id, $1, ended.session_id, $3
+            FROM ended
+            JOIN oauth_clients c ON c.id = ended.client_id
+            WHERE c.backchannel_logout_uri IS NOT NULL
+            RETURNING id
+            ",
+        )
+        .bind(user_id)
+        .bind(session_ids)
+        .bind(Utc::now())
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for delivery_id in ids {
+            let job = Job::DeliverLogout { delivery_id };
+            let max_attempts = job.max_attempts();
+            JobQueue::push(&mut *tx, &job, Utc::now(), max_attempts).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Makes one attempt at delivery `id`: posts a fresh logout token signed
+    /// by `oidc` to the client's back-channel logout URI and records the
+    /// outcome. Deliveries that are no longer pending are skipped.
+    ///
+    /// ## Errors
+    /// * [`Error::LogoutDelivery`] if the client could not be reached or did
+    ///   not answer with a 2xx status, so the job is retried
+    /// * Database errors
+    pub async fn deliver(&self, oidc: &OidcProvider, id: Uuid) -> Result<()> {
+        let pending = sqlx::query_as::<_, PendingLogout>(
+            r"
+            SELECT c.client_id, c.backchannel_logout_uri, d.user_id, d.session_id
+            FROM backchannel_logout_deliveries d
+            JOIN oauth_clients c ON c.id = d.client_id
+            WHERE d.id = $1 AND d.status = 'pending'
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let Some(pending) = pending else {
+            return Ok(());
+        };
+
+        let Some(uri) = pending.backchannel_logout_uri else {
+            return self
+                .abandon(id, "client has no back-channel logout URI")
+                .await;
+        };
+
+        let token = oidc.logout_token(&pending.client_id, pending.user_id, pending.session_id)?;
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("logout_token", &token)
+            .finish();
+
+        let outcome = self
+            .http
+            .post(&uri)
+            .headers(trace::trace_headers())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await;
+
+        let (status_code, error) = match outcome {
+            Ok(response) if response.status().is_success() => {
+                sqlx::query(
+                    r"
+                    UPDATE backchannel_logout_deliveries
+                    SET status = 'delivered', attempts = attempts + 1,
+                        last_status_code = $2, last_error = NULL, delivered_at = now()
+                    WHERE id = $1
+                    ",
+                )
+                .bind(id)
+                .bind(i32::from(response.status().as_u16()))
+                .execute(&self.db)
+                .await?;
+
+                tracing::info!(delivery_id = %id, client_id = %pending.client_id, "Logout token delivered");
+
+                return Ok(());
+            }
+            Ok(response) => (
+                Some(i32::from(response.status().as_u16())),
+                format!("client answered {}", response.status()),
+            ),
+            Err(err) => (None, err.to_string()),
+        };
+
+        sqlx::query(
+            r"
+            UPDATE backchannel_logout_deliveries
+            SET attempts = attempts + 1, last_status_code = $2, last_error = $3
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(status_code)
+        .bind(&error)
+        .execute(&self.db)
+        .await?;
+
+        tracing::warn!(
+            delivery_id = %id,
+            client_id = %pending.client_id,
+            error,
+            "Logout token delivery failed"
+        );
+
+        Err(Error::LogoutDelivery(error))
+    }
+
+    /// Marks pending delivery `id` failed for good, e.g. once its job ran out
+    /// of attempts.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn abandon(&self, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            r"
+            UPDATE backchannel_logout_deliveries
+            SET status = 'failed', last_error = COALESCE(last_error, $2)
+            WHERE id = $1 AND status = 'pending'
+            ",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most recent deliveries to client `client_id` first.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn list(
+        &self,
+        client_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LogoutDelivery>> {
+        sqlx::query_as::<_, LogoutDelivery>(
+            r"
+            SELECT id, client_id, user_id, session_id, status, attempts, last_status_code,
+                   last_error, created_at, delivered_at
+            FROM backchannel_logout_deliveries
+            WHERE client_id = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            ",
+        )
+        .bind(client_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+#[async_trait]
+impl Subscriber for BackchannelLogout {
+    async fn handle(&self, ctx: &AppContext, event: &Event) -> Result<()> {
+        let Event::SessionsRevoked {
+            user_id,
+            session_ids,
+        } = event
+        else {
+            return Ok(());
+        };
+
+        if ctx.oidc().is_err() {
+            return Ok(());
+        }
+
+        self.dispatch(*user_id, session_ids).await
+    }
+}
@@ -0,0 +1,412 @@
+mod clients;
+mod logout;
+
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+    jwk::{Jwk, JwkSet, PublicKeyUse, ThumbprintHash},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+pub use self::{
+    clients::{AccessGrant, ClientStore, RegisteredClient},
+    logout::{BackchannelLogout, LogoutDelivery},
+};
+use crate::{
+    Error,
+    auth::{BUILT_IN_SCOPES, Role, is_grantable_scope},
+    config::{ConfigError, OidcConfig},
+    models::User,
+};
+
+/// How long a logout token is valid, in seconds.
+const LOGOUT_TOKEN_TTL: i64 = 120;
+
+/// The `events` member marking a JWT as a back-channel logout token.
+const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+
+/// Scopes this provider understands; others are dropped from requests.
+pub const SUPPORTED_SCOPES: [&str; 3] = ["openid", "email", "profile"];
+
+/// The scope granted to a client for a space-separated `requested` scope
+/// authorized by a user with `role`: the supported scopes, then the
+/// built-in API scopes the user may be granted, see
+/// [`crate::auth::BUILT_IN_SCOPES`]. Others are dropped.
+///
+/// ## Examples
+/// ```
+/// # use betterauth::{auth::Role, oidc};
+/// let requested = "sessions:read openid users:read";
+/// assert_eq!(oidc::grant_scope(requested, Role::User), "openid sessions:read");
+/// assert_eq!(oidc::grant_scope(requested, Role::Admin), "openid users:read sessions:read");
+/// ```
+#[must_use]
+pub fn grant_scope(requested: &str, role: Role) -> String {
+    let api_scopes = BUILT_IN_SCOPES
+        .iter()
+        .map(|(scope, _)| *scope)
+        .filter(|scope| has_scope(requested, scope) && is_grantable_scope(scope, role));
+
+    SUPPORTED_SCOPES
+        .iter()
+        .copied()
+        .filter(|scope| has_scope(requested, scope))
+        .chain(api_scopes)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `scope` is a scope token as defined by RFC 6749, section 3.3,
+/// of at most 128 characters.
+#[must_use]
+pub fn is_valid_scope(scope: &str) -> bool {
+    !scope.is_empty()
+        && scope.len() <= 128
+        && scope
+            .bytes()
+            .all(|byte| matches!(byte, 0x21 | 0x23..=0x5B | 0x5D..=0x7E))
+}
+
+fn has_scope(scope: &str, wanted: &str) -> bool {
+    scope.split_whitespace().any(|s| s == wanted)
+}
+
+/// Claims about `user` released under `scope`, as returned by the userinfo
+/// endpoint and embedded in ID tokens.
+#[must_use]
+pub fn user_claims(user: &User, scope: &str) -> Value {
+    let mut claims = json!({ "sub": user.id });
+
+    if has_scope(scope, "email") {
+        claims["email"] = json!(user.email);
+        claims["email_verified"] = json!(user.verified_at.is_some());
+    }
+
+    if has_scope(scope, "profile") {
+        claims["name"] = json!(user.name);
+        claims["updated_at"] = json!(user.updated_at.timestamp());
+    }
+
+    claims
+}
+
+/// The RSA private key `pem` ID tokens are signed with, and its public JWK.
+/// `None` if it is not a PEM-encoded RSA private key, as
+/// `oidc.signing_key` must be.
+pub(crate) fn signing_key(pem: &str) -> Option<(EncodingKey, Jwk)> {
+    let encoding = EncodingKey::from_rsa_pem(pem.as_bytes()).ok()?;
+    let jwk = Jwk::from_encoding_key(&encoding, Algorithm::RS256).ok()?;
+
+    Some((encoding, jwk))
+}
+
+/// Signs ID tokens and describes this server to relying parties.
+#[derive(Clone)]
+pub struct OidcProvider {
+    config: OidcConfig,
+    issuer: String,
+    key_id: String,
+    encoding: EncodingKey,
+    jwk: Jwk,
+}
+
+impl OidcProvider {
+    /// `server_url` is the issuer unless `oidc.issuer` overrides it.
+    ///
+    /// ## Errors
+    /// * [`ConfigError::Invalid`] if `oidc.signing_key` is not a PEM-encoded
+    ///   RSA private key
+    pub fn from_config(config: &OidcConfig, server_url: String) -> crate::Result<Self> {
+        let (encoding, mut jwk) = signing_key(config.signing_key().expose()).ok_or_else(|| {
+            ConfigError::Invalid(vec![String::from(
+                "oidc.signing_key must be a PEM-encoded RSA private key",
+            )])
+        })?;
+        let key_id = jwk.thumbprint(ThumbprintHash::SHA256);
+        jwk.common.key_id = Some(key_id.clone());
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+
+        Ok(Self {
+            issuer: config
+                .issuer()
+                .map_or(server_url, str::to_owned)
+                .trim_end_matches('/')
+                .to_owned(),
+            config: config.clone(),
+            key_id,
+            encoding,
+            jwk,
+        })
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    #[must_use]
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// The public half of the signing key.
+    #[must_use]
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: vec![self.jwk.clone()],
+        }
+    }
+
+    /// The `/.well-known/openid-configuration` document.
+    #[must_use]
+    pub fn discovery(&self) -> Value {
+        let issuer = &self.issuer;
+
+        json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{issuer}/oauth/authorize"),
+            "token_endpoint": format!("{issuer}/oauth/token"),
+            "userinfo_endpoint": format!("{issuer}/oauth/userinfo"),
+            "introspection_endpoint": format!("{issuer}/oauth/introspect"),
+            "end_session_endpoint": format!("{issuer}/oauth/logout"),
+            "jwks_uri": format!("{issuer}/oauth/jwks"),
+            "scopes_supported": SUPPORTED_SCOPES
+                .iter()
+                .chain(BUILT_IN_SCOPES.iter().map(|(scope, _)| scope))
+                .collect::<Vec<_>>(),
+            "response_types_supported": ["code"],
+            "grant_types_supported": ["authorization_code", "client_credentials"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["RS256"],
+            "token_endpoint_auth_methods_supported":
+                ["client_secret_basic", "client_secret_post", "none"],
+            "code_challenge_methods_supported": ["S256"],
+            "backchannel_logout_supported": true,
+            "backchannel_logout_session_supported": true,
+            "claims_supported": [
+                "sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "sid",
+                "email", "email_verified", "name", "updated_at",
+            ],
+        })
+    }
+
+    /// Signs an ID token for `user` addressed to `client_id`.
+    ///
+    /// ## Errors
+    /// * [`Error::Jwt`] if the token cannot be signed
+    pub fn id_token(&self, grant: &IdTokenGrant<'_>) -> crate::Result<String> {
+        let now = Utc::now().timestamp();
+        let ttl = i64::try_from(self.config.token_ttl()).unwrap_or(i64::MAX);
+
+        let mut claims = user_claims(grant.user, grant.scope);
+        claims["iss"] = json!(self.issuer);
+        claims["aud"] = json!(grant.client_id);
+        claims["iat"] = json!(now);
+        claims["exp"] = json!(now.saturating_add(ttl));
+        claims["auth_time"] = json!(grant.auth_time);
+        if let Some(nonce) = grant.nonce {
+            claims["nonce"] = json!(nonce);
+        }
+        if let Some(session_id) = grant.session_id {
+            claims["sid"] = json!(session_id);
+        }
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.key_id.clone());
+
+        jsonwebtoken::encode(&header, &claims, &self.encoding).map_err(Error::Jwt)
+    }
+
+    /// Signs a back-channel logout token telling `client_id` that session
+    /// `session_id` of `user_id` ended.
+    ///
+    /// ## Errors
+    /// * [`Error::Jwt`] if the token cannot be signed
+    pub fn logout_token(
+        &self,
+        client_id: &str,
+        user_id: Uuid,
+        session_id: Option<Uuid>,
+    ) -> crate::Result<String> {
+        let now = Utc::now().timestamp();
+
+        let mut claims = json!({
+            "iss": self.issuer,
+            "aud": client_id,
+            "sub": user_id,
+            "iat": now,
+            "exp": now.saturating_add(LOGOUT_TOKEN_TTL),
+            "jti": Uuid::new_v4(),
+            "events": { BACKCHANNEL_LOGOUT_EVENT: {} },
+        });
+        if let Some(session_id) = session_id {
+            claims["sid"] = json!(session_id);
+        }
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.typ = Some(String::from("logout+jwt"));
+        header.kid = Some(self.key_id.clone());
+
+        jsonwebtoken::encode(&header, &claims, &self.encoding).map_err(Error::Jwt)
+    }
+
+    /// The claims of an ID token this provider signed, as sent back in
+    /// `id_token_hint`. Expired tokens are accepted, as the spec asks of
+    /// hints. `None` if the token was not issued here.
+    #[must_use]
+    pub fn verify_id_token_hint(&self, token: &str) -> Option<IdTokenHint> {
+        let decoding = DecodingKey::from_jwk(&self.jwk).ok()?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["iss", "sub", "aud"]);
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+
+        jsonwebtoken::decode::<IdTokenHint>(token, &decoding, &validation)
+            .ok()
+            .map(|data| data.claims)
+    }
+}
+
+/// Claims of an ID token sent back to the end-session endpoint.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenHint {
+    pub sub: Uuid,
+    /// `client_id` of the client the token was issued to.
+    pub aud: String,
+    /// The session the token was issued in, for tokens issued since ID
+    /// tokens carry one.
+    pub sid: Option<Uuid>,
+}
+
+/// What an ID token is issued for.
+#[derive(Debug)]
+pub struct IdTokenGrant<'a> {
+    pub user: &'a User,
+    pub client_id: &'a str,
+    pub scope: &'a str,
+    pub nonce: Option<&'a str>,
+    /// When the user authenticated, as a unix timestamp.
+    pub auth_time: i64,
+    /// The session the user authorized the client from.
+    pub session_id: Option<Uuid>,
+}
+
+/// Successful response of the token endpoint.
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Lifetime of the access token, in seconds.
+    pub expires_in: u64,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+/// Response of the introspection endpoint (RFC 7662). Inactive tokens are
+/// described by `active` alone.
+#[derive(Debug, Default, Serialize)]
+pub struct Introspection {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// The user the token acts for; absent on client-credentials tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+}
+
+/// Errors of the token endpoint, rendered as RFC 6749 error responses rather
+/// than this crate's usual error body so OAuth client libraries understand
+/// them.
+#[derive(Debug)]
+pub enum TokenError {
+    InvalidRequest(&'static str),
+    /// Client authentication failed.
+    InvalidClient,
+    /// The code is unknown, expired, used, or was issued to someone else.
+    InvalidGrant(&'static str),
+    /// The client may not use the requested grant type.
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    /// A requested scope is not allowed for the client.
+    InvalidScope,
+    /// Anything else, rendered as a regular [`Error`].
+    Internal(Error),
+}
+
+impl From<Error> for TokenError {
+    fn from(err: Error) -> Self {
+        Self::Internal(err)
+    }
+}
+
+impl From<sqlx::Error> for TokenError {
+    fn from(err: sqlx::Error) -> Self {
+        Self::Internal(err.into())
+    }
+}
+
+impl IntoResponse for TokenError {
+    fn into_response(self) -> Response {
+        let (status, error, description) = match self {
+            Self::InvalidRequest(description) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+                Some(description),
+            ),
+            Self::InvalidClient => (StatusCode::UNAUTHORIZED, "invalid_client", None),
+            Self::InvalidGrant(description) => {
+                (StatusCode::BAD_REQUEST, "invalid_grant", Some(description))
+            }
+            Self::UnauthorizedClient => (StatusCode::BAD_REQUEST, "unauthorized_client", None),
+            Self::UnsupportedGrantType => (StatusCode::BAD_REQUEST, "unsupported_grant_type", None),
+            Self::InvalidScope => (StatusCode::BAD_REQUEST, "invalid_scope", None),
+            Self::Internal(err) => return err.into_response(),
+        };
+
+        let mut body = json!({ "error": error });
+        if let Some(description) = description {
+            body["error_description"] = json!(description);
+        }
+        let body = Json(body);
+
+        if status == StatusCode::UNAUTHORIZED {
+            (status, [(header::WWW_AUTHENTICATE, "Basic")], body).into_response()
+        } else {
+            (status, body).into_response()
+        }
+    }
+}
+
+/// A user authorization awaiting redemption at the token endpoint.
+#[derive(Debug, sqlx::FromRow)]
+pub struct AuthorizationCode {
+    /// `oauth_clients.id` of the client the code was issued to.
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    /// Base64url SHA-256 of the PKCE verifier, if the client sent one.
+    pub code_challenge: Option<String>,
+    pub auth_time: DateTime<Utc>,
+    /// The session the user authorized the client from; `None` for codes
+    /// issued before codes recorded it.
+    pub session_id: Option<Uuid>,
+}
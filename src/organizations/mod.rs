@@ -0,0 +1,103 @@
+mod policy;
+
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub use self::policy::AuthPolicy;
+use crate::{Error, Result};
+
+/// How long an invitation to join an organization stays valid.
+pub const INVITATION_TTL: Duration = Duration::days(7);
+
+/// What a member may do within an organization.
+///
+/// Roles are ordered, each one including the permissions of those below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl OrgRole {
+    /// Value stored in `memberships.role`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "member" => Ok(Self::Member),
+            "admin" => Ok(Self::Admin),
+            "owner" => Ok(Self::Owner),
+            other => Err(Error::Validation(format!("unknown role: {other}"))),
+        }
+    }
+}
+
+impl fmt::Display for OrgRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A tenant that users belong to through memberships.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Looks up the role of `user_id` in `organization_id`, or `None` if the
+/// user is not a member.
+///
+/// ## Errors
+/// * Database errors
+pub async fn member_role(
+    db: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<OrgRole>> {
+    let role: Option<String> = sqlx::query_scalar(
+        "SELECT role FROM memberships WHERE organization_id = $1 AND user_id = $2",
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+
+    role.as_deref().map(str::parse).transpose()
+}
+
+/// Checks that `user_id` holds at least `required` in `organization_id`.
+///
+/// ## Errors
+/// * [`Error::Forbidden`] if the user is not a member or their role is too low
+/// * Database errors
+pub async fn require_role(
+    db: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+    required: OrgRole,
+) -> Result<OrgRole> {
+    match member_role(db, organization_id, user_id).await? {
+        Some(role) if role >= required => Ok(role),
+        _ => Err(Error::Forbidden),
+    }
+}
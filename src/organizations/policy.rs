@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    config::{PasswordPolicy, PasswordRules},
+    events::LoginMethod,
+};
+
+/// Login methods an [`AuthPolicy`] may allow by name. `oauth` and `saml`
+/// also take a single provider or tenant, e.g. `oauth:google`.
+const LOGIN_METHODS: [&str; 7] = [
+    "password",
+    "passkey",
+    "magic_link",
+    "email_otp",
+    "phone",
+    "oauth",
+    "saml",
+];
+
+/// Shortest session lifetime a policy may set, in seconds.
+const MIN_SESSION_LIFETIME: u64 = 60;
+
+/// Rules an organization holds its members to on top of the instance
+/// configuration, stored in `organizations.auth_policy` and set by its
+/// owners through `PUT /auth/organizations/{id}/policy`.
+///
+/// Policies are resolved when a member logs in, so changes apply from their
+/// next login. A user in several organizations is held to all of their
+/// policies at once, see [`AuthPolicy::stricter`]. A policy only tightens
+/// the configuration: what it rules out stays ruled out.
+///
+/// ```json
+/// {
+///   "phishing_resistant_login_required": false,
+///   "allowed_login_methods": ["passkey", "saml:acme"],
+///   "session_lifetime": 28800,
+///   "password": { "min_length": 14, "require_symbol": true }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthPolicy {
+    /// Whether members must log in with a method that cannot be phished: a
+    /// passkey, which is bound to this server, or SAML single sign-on, the
+    /// identity provider being trusted with how it authenticates them.
+    /// Neither is a second factor. Members need a passkey or a SAML tenant
+    /// before this is turned on.
+    #[serde(default)]
+    pub phishing_resistant_login_required: bool,
+    /// The login methods members may use, from `password` (which covers
+    /// accepting a signup invitation), `passkey`, `magic_link`, `email_otp`,
+    /// `phone`, `oauth` and `saml`, or `oauth:<provider>` and
+    /// `saml:<tenant>` for a single one. `None` allows them all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_login_methods: Option<Vec<String>>,
+    /// Longest a member's session lasts from login, in seconds, when shorter
+    /// than `auth.lifetimes.session`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_lifetime: Option<u64>,
+    /// Rules the passwords of members must satisfy on top of
+    /// `password_policy`, checked when they set one and when they log in
+    /// with it.
+    #[serde(default, skip_serializing_if = "PasswordRules::is_empty")]
+    pub password: PasswordRules,
+}
+
+impl AuthPolicy {
+    /// The policy `user_id` is held to: the [`AuthPolicy::stricter`] of those
+    /// of every organization they belong to.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn for_user(db: &PgPool, user_id: Uuid) -> Result<Self> {
+        let policies: Vec<Json<Self>> = sqlx::query_scalar(
+            r"
+            SELECT o.auth_policy
+            FROM memberships m
+            JOIN organizations o ON o.id = m.organization_id
+            WHERE m.user_id = $1
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(policies
+            .iter()
+            .fold(Self::default(), |policy, Json(other)| {
+                policy.stricter(other)
+            }))
+    }
+
+    /// The policy of `organization_id`, or `None` if it does not exist.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn for_organization(db: &PgPool, organization_id: Uuid) -> Result<Option<Self>> {
+        let policy: Option<Json<Self>> =
+            sqlx::query_scalar("SELECT auth_policy FROM organizations WHERE id = $1")
+                .bind(organization_id)
+                .fetch_optional(db)
+                .await?;
+
+        Ok(policy.map(|Json(policy)| policy))
+    }
+
+    /// Stores the policy as that of `organization_id`, returning `false` if it
+    /// does not exist. Members are held to it from their next login.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn save(&self, db: &PgPool, organization_id: Uuid) -> Result<bool> {
+        let updated = sqlx::query(
+            "UPDATE organizations SET auth_policy = $2, updated_at = now() WHERE id = $1",
+        )
+        .bind(organization_id)
+        .bind(Json(self))
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    /// Checks the policy before it is stored, against the instance
+    /// `password_policy`.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] naming the first invalid setting
+    pub fn validate(&self, passwords: &PasswordPolicy) -> Result<()> {
+        for method in self.allowed_login_methods.iter().flatten() {
+            let known = match method.split_once(':') {
+                Some((kind, name)) => matches!(kind, "oauth" | "saml") && !name.is_empty(),
+                None => LOGIN_METHODS.contains(&method.as_str()),
+            };
+            if !known {
+                return Err(Error::Validation(format!("unknown login method: {method}")));
+            }
+        }
+
+        if self
+            .session_lifetime
+            .is_some_and(|lifetime| lifetime < MIN_SESSION_LIFETIME)
+        {
+            return Err(Error::Validation(format!(
+                "session_lifetime must be at least {MIN_SESSION_LIFETIME} seconds"
+            )));
+        }
+
+        if self
+            .password
+            .min_length
+            .is_some_and(|min_length| min_length > passwords.max_length())
+        {
+            return Err(Error::Validation(format!(
+                "password.min_length must be at most {}",
+                passwords.max_length()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// A policy holding members to both `self` and `other`: a
+    /// phishing-resistant login if either requires one, only the login methods both allow, the shorter
+    /// session lifetime and the password rules of both.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use betterauth::organizations::AuthPolicy;
+    /// let acme = AuthPolicy {
+    ///     allowed_login_methods: Some(vec!["password".into(), "oauth".into()]),
+    ///     session_lifetime: Some(3600),
+    ///     ..AuthPolicy::default()
+    /// };
+    /// let globex = AuthPolicy {
+    ///     allowed_login_methods: Some(vec!["oauth:google".into(), "passkey".into()]),
+    ///     session_lifetime: Some(86400),
+    ///     ..AuthPolicy::default()
+    /// };
+    ///
+    /// let both = acme.stricter(&globex);
+    /// assert_eq!(both.allowed_login_methods, Some(vec!["oauth:google".to_owned()]));
+    /// assert_eq!(both.session_lifetime, Some(3600));
+    /// ```
+    #[must_use]
+    pub fn stricter(&self, other: &Self) -> Self {
+        let allowed_login_methods =
+            match (&self.allowed_login_methods, &other.allowed_login_methods) {
+                (Some(ours), Some(theirs)) => {
+                    let mut both = Vec::new();
+                    for method in ours.iter().flat_map(|ours| {
+                        theirs
+                            .iter()
+                            .filter_map(move |theirs| narrower(ours, theirs))
+                    }) {
+                        if !both.contains(method) {
+                            both.push(method.clone());
+                        }
+                    }
+                    Some(both)
+                }
+                (Some(methods), None) | (None, Some(methods)) => Some(methods.clone()),
+                (None, None) => None,
+            };
+
+        Self {
+            phishing_resistant_login_required: self.phishing_resistant_login_required
+                || other.phishing_resistant_login_required,
+            allowed_login_methods,
+            session_lifetime: match (self.session_lifetime, other.session_lifetime) {
+                (Some(ours), Some(theirs)) => Some(ours.min(theirs)),
+                (ours, theirs) => ours.or(theirs),
+            },
+            password: self.password.stricter(&other.password),
+        }
+    }
+
+    /// Whether members may log in with `method`.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use betterauth::{events::LoginMethod, oauth::Provider, organizations::AuthPolicy};
+    /// let policy = AuthPolicy {
+    ///     allowed_login_methods: Some(vec!["password".into(), "oauth:google".into()]),
+    ///     ..AuthPolicy::default()
+    /// };
+    /// assert!(policy.allows(&LoginMethod::Password));
+    /// assert!(policy.allows(&LoginMethod::OAuth(Provider::Google)));
+    /// assert!(!policy.allows(&LoginMethod::OAuth(Provider::Github)));
+    /// assert!(!policy.allows(&LoginMethod::MagicLink));
+    ///
+    /// let policy = AuthPolicy {
+    ///     phishing_resistant_login_required: true,
+    ///     ..AuthPolicy::default()
+    /// };
+    /// assert!(policy.allows(&LoginMethod::Passkey));
+    /// assert!(!policy.allows(&LoginMethod::Password));
+    /// ```
+    #[must_use]
+    pub fn allows(&self, method: &LoginMethod) -> bool {
+        let (kind, name) = match method {
+            LoginMethod::Password | LoginMethod::Invite => ("password", None),
+            LoginMethod::OAuth(provider) => ("oauth", Some(provider.name())),
+            LoginMethod::Saml(tenant) => ("saml", Some(tenant.as_str())),
+            other => (other.name(), None),
+        };
+
+        if self.phishing_resistant_login_required
+            && !matches!(method, LoginMethod::Passkey | LoginMethod::Saml(_))
+        {
+            return false;
+        }
+
+        self.allowed_login_methods.as_ref().is_none_or(|allowed| {
+            allowed.iter().any(|entry| match entry.split_once(':') {
+                Some((entry_kind, entry_name)) => entry_kind == kind && Some(entry_name) == name,
+                None => entry == kind,
+            })
+        })
+    }
+}
+
+/// The narrower of two allowed login methods if they overlap, e.g.
+/// `oauth:google` for `oauth` and `oauth:google`.
+fn narrower<'a>(ours: &'a String, theirs: &'a String) -> Option<&'a String> {
+    let kind = |method: &'a String| {
+        method
+            .split_once(':')
+            .map_or(method.as_str(), |(kind, _)| kind)
+    };
+
+    if ours == theirs || (!theirs.contains(':') && kind(ours) == theirs) {
+        Some(ours)
+    } else if !ours.contains(':') && kind(theirs) == ours {
+        Some(theirs)
+    } else {
+        None
+    }
+}
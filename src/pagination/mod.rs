@@ -0,0 +1,377 @@
+use std::cmp::Ordering;
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Encode, Postgres, QueryBuilder, Type};
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+pub const DEFAULT_PER_PAGE: u32 = 20;
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// A field a listing can be sorted by: its name in the `sort` parameter, and
+/// the column and SQL type it is compared as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortField {
+    pub name: &'static str,
+    pub column: &'static str,
+    pub sql_type: &'static str,
+}
+
+impl SortField {
+    #[must_use]
+    pub const fn new(name: &'static str, column: &'static str, sql_type: &'static str) -> Self {
+        Self {
+            name,
+            column,
+            sql_type,
+        }
+    }
+
+    #[must_use]
+    pub const fn ascending(self) -> Sort {
+        Sort {
+            field: self,
+            descending: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn descending(self) -> Sort {
+        Sort {
+            field: self,
+            descending: true,
+        }
+    }
+}
+
+/// The order of a listing, ties broken by `id` in the same direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// The `sort` parameter selecting this order, e.g. `-created_at`.
+    #[must_use]
+    pub fn spec(&self) -> String {
+        format!(
+            "{}{}",
+            if self.descending { "-" } else { "" },
+            self.field.name
+        )
+    }
+
+    fn direction(self) -> &'static str {
+        if self.descending { "DESC" } else { "ASC" }
+    }
+}
+
+/// A row of a paginated listing, which cursors point at.
+pub trait Paginated {
+    fn id(&self) -> Uuid;
+
+    /// The value of the [`SortField`] named `field`, as the text it is
+    /// compared as; see [`sort_key`] for timestamps.
+    fn sort_value(&self, field: &str) -> String;
+}
+
+/// `at` as a sort value, in a fixed-width format that orders like the
+/// timestamp itself.
+#[must_use]
+pub fn sort_key(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Position after the last row of a page, handed out as `next_cursor`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    value: String,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let json = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+/// The paging and sorting parameters of a list endpoint, taken from the
+/// query string.
+///
+/// Pages are selected by number with `page` (1-based) or by position with
+/// `cursor`, the `next_cursor` of the previous page, which stays stable
+/// while rows are added. Both hold `per_page` rows, at most
+/// [`MAX_PER_PAGE`]. `sort` names a field, prefixed with `-` for descending
+/// order.
+///
+/// ```no_run
+/// use betterauth::pagination::{Page, Paginated, Pagination, SortField, push_filter};
+/// use sqlx::{PgPool, QueryBuilder};
+/// # use uuid::Uuid;
+/// # #[derive(sqlx::FromRow, serde::Serialize)]
+/// # struct Row { id: Uuid }
+/// # impl Paginated for Row {
+/// #     fn id(&self) -> Uuid { self.id }
+/// #     fn sort_value(&self, _: &str) -> String { String::new() }
+/// # }
+///
+/// const CREATED_AT: SortField = SortField::new("created_at", "created_at", "timestamptz");
+///
+/// async fn list(db: &PgPool, pagination: Pagination, email: Option<String>) -> betterauth::Result<Page<Row>> {
+///     let sort = pagination.sort(&[CREATED_AT], CREATED_AT.descending())?;
+///
+///     let mut query = QueryBuilder::new("SELECT id FROM users WHERE TRUE");
+///     push_filter(&mut query, "email = ?", email);
+///     pagination.push(&mut query, sort)?;
+///
+///     let rows = query.build_query_as().fetch_all(db).await?;
+///     Ok(pagination.page(rows, sort, None))
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Pagination {
+    page: Option<u32>,
+    per_page: Option<u32>,
+    cursor: Option<String>,
+    sort: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Pagination {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pagination) = Query::<Self>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| Error::Validation(rejection.body_text()))?;
+
+        Ok(pagination)
+    }
+}
+
+impl Pagination {
+    #[must_use]
+    pub fn per_page(&self) -> u32 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    /// The 1-based page number, unless paging by cursor.
+    #[must_use]
+    pub fn page_number(&self) -> Option<u32> {
+        self.cursor.is_none().then(|| self.page.unwrap_or(1).max(1))
+    }
+
+    /// The requested order, one of `fields`, or `default` if none was asked
+    /// for.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if the listing cannot be sorted as requested
+    pub fn sort(&self, fields: &[SortField], default: Sort) -> Result<Sort> {
+        let Some(spec) = self.sort.as_deref().map(str::trim) else {
+            return Ok(default);
+        };
+
+        let (name, descending) = spec
+            .strip_prefix('-')
+            .map_or((spec, false), |name| (name, true));
+
+        fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| Sort {
+                field: *field,
+                descending,
+            })
+            .ok_or_else(|| {
+                let names: Vec<_> = fields.iter().map(|field| field.name).collect();
+                Error::Validation(format!("sort must be one of {}", names.join(", ")))
+            })
+    }
+
+    /// The cursor to continue after, checked to belong to `sort`.
+    fn cursor(&self, sort: Sort) -> Result<Option<Cursor>> {
+        let Some(cursor) = &self.cursor else {
+            return Ok(None);
+        };
+
+        Cursor::decode(cursor)
+            .filter(|cursor| cursor.sort == sort.spec())
+            .map(Some)
+            .ok_or_else(|| Error::Validation(String::from("cursor is invalid")))
+    }
+
+    /// Appends the cursor condition, `ORDER BY` and `LIMIT` of the page to
+    /// `query`, which must select from a table with an `id` column and end
+    /// in a `WHERE` clause. One row more than fits on the page is fetched,
+    /// telling [`Pagination::page`] whether there is a next one.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if the cursor is malformed or belongs to
+    ///   another order
+    pub fn push(&self, query: &mut QueryBuilder<'_, Postgres>, sort: Sort) -> Result<()> {
+        let column = sort.field.column;
+
+        if let Some(cursor) = self.cursor(sort)? {
+            query
+                .push(format_args!(
+                    " AND ({column}, id) {} (CAST(",
+                    if sort.descending { "<" } else { ">" }
+                ))
+                .push_bind(cursor.value)
+                .push(format_args!(" AS {}), ", sort.field.sql_type))
+                .push_bind(cursor.id)
+                .push(")");
+        }
+
+        let direction = sort.direction();
+        query
+            .push(format_args!(
+                " ORDER BY {column} {direction}, id {direction} LIMIT "
+            ))
+            .push_bind(i64::from(self.per_page()) + 1);
+
+        if let Some(page) = self.page_number() {
+            query
+                .push(" OFFSET ")
+                .push_bind(i64::from(page - 1) * i64::from(self.per_page()));
+        }
+
+        Ok(())
+    }
+
+    /// Wraps `rows`, fetched with [`Pagination::push`], into a [`Page`],
+    /// with `total` as the number of rows across all pages if it was
+    /// counted.
+    #[must_use]
+    pub fn page<T: Paginated>(&self, mut rows: Vec<T>, sort: Sort, total: Option<i64>) -> Page<T> {
+        let per_page = self.per_page();
+        let per_page_len = usize::try_from(per_page).unwrap_or(usize::MAX);
+
+        let next_cursor = (rows.len() > per_page_len)
+            .then(|| {
+                rows.truncate(per_page_len);
+                rows.last()
+            })
+            .flatten()
+            .map(|last| {
+                Cursor {
+                    sort: sort.spec(),
+                    value: last.sort_value(sort.field.name),
+                    id: last.id(),
+                }
+                .encode()
+            });
+
+        Page {
+            items: rows,
+            per_page,
+            page: self.page_number(),
+            total,
+            next_cursor,
+        }
+    }
+
+    /// Pages through `items` in memory, for listings short enough to load
+    /// whole, such as the sessions of a user. Values of `sort` are compared
+    /// as text.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if the cursor is malformed or belongs to
+    ///   another order
+    pub fn slice<T: Paginated>(&self, mut items: Vec<T>, sort: Sort) -> Result<Page<T>> {
+        let key = |item: &T| (item.sort_value(sort.field.name), item.id());
+        let order = |a: &T, b: &T| {
+            let ordering = key(a).cmp(&key(b));
+            if sort.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        items.sort_by(order);
+
+        let total = i64::try_from(items.len()).unwrap_or(i64::MAX);
+        let per_page = usize::try_from(self.per_page()).unwrap_or(usize::MAX);
+
+        let skip = match (self.cursor(sort)?, self.page_number()) {
+            (Some(cursor), _) => {
+                let after = (cursor.value, cursor.id);
+                items
+                    .iter()
+                    .take_while(|item| {
+                        let ordering = key(item).cmp(&after);
+                        ordering == Ordering::Equal
+                            || (ordering == Ordering::Less) != sort.descending
+                    })
+                    .count()
+            }
+            (None, page) => usize::try_from(page.unwrap_or(1) - 1)
+                .unwrap_or(usize::MAX)
+                .saturating_mul(per_page),
+        };
+
+        let rows: Vec<T> = items.into_iter().skip(skip).take(per_page + 1).collect();
+
+        Ok(self.page(rows, sort, Some(total)))
+    }
+}
+
+/// One page of a listing, as list endpoints respond with.
+///
+/// `page` is set when paging by number, and `total` when the listing was
+/// counted. `next_cursor` is absent on the last page.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub per_page: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Appends ` AND <condition>` to `query` if there is a `value`, binding it
+/// in place of the `?` in `condition`.
+///
+/// ```no_run
+/// # use betterauth::pagination::push_filter;
+/// # use sqlx::{Postgres, QueryBuilder};
+/// let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM users WHERE TRUE");
+/// push_filter(&mut query, "email ILIKE '%' || ? || '%'", Some("example.com"));
+/// push_filter(&mut query, "role = ?", None::<&str>);
+/// ```
+pub fn push_filter<'args, T>(
+    query: &mut QueryBuilder<'args, Postgres>,
+    condition: &str,
+    value: Option<T>,
+) where
+    T: 'args + Encode<'args, Postgres> + Type<Postgres> + Send,
+{
+    let Some(value) = value else {
+        return;
+    };
+
+    let (before, after) = condition.split_once('?').unwrap_or((condition, ""));
+    query
+        .push(" AND ")
+        .push(before)
+        .push_bind(value)
+        .push(after);
+}
@@ -0,0 +1,462 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    config::PrivacyConfig,
+    jobs::{Job, JobQueue},
+    repositories::{PgUserStore, UserStore},
+    sessions::SessionStore,
+};
+
+/// How often the background worker erases accounts whose grace period is
+/// over and drops expired exports.
+const SWEEP_INTERVAL: StdDuration = StdDuration::from_secs(5 * 60);
+
+/// Progress of a [`DataExport`], stored in `data_exports.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl ExportStatus {
+    /// Value stored in `data_exports.status`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Ready => "ready",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for ExportStatus {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        match s.as_str() {
+            "pending" => Ok(Self::Pending),
+            "ready" => Ok(Self::Ready),
+            "failed" => Ok(Self::Failed),
+            other => Err(Error::Validation(format!("unknown export status: {other}"))),
+        }
+    }
+}
+
+/// A request for a copy of a user's personal data. The archive itself is
+/// only loaded by [`Privacy::archive`].
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DataExport {
+    pub id: Uuid,
+    #[sqlx(try_from = "String")]
+    pub status: ExportStatus,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the archive stops being downloadable.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct AuditRecord {
+    action: String,
+    ip: Option<String>,
+    metadata: Value,
+    created_at: DateTime<Utc>,
+}
+
+/// Personal data exports and delayed account erasure.
+#[derive(Clone)]
+pub struct Privacy {
+    db: PgPool,
+    users: PgUserStore,
+    config: PrivacyConfig,
+}
+
+impl Privacy {
+    #[must_use]
+    pub fn new(db: PgPool, config: PrivacyConfig) -> Self {
+        Self {
+            users: PgUserStore::new(db.clone()),
+            db,
+            config,
+        }
+    }
+
+    /// Records a pending export for `user_id` and queues a
+    /// [`Job::BuildExport`] to produce the archive.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn request_export(&self, user_id: Uuid) -> Result<DataExport> {
+        let mut tx = self.db.begin().await?;
+
+        let export = sqlx::query_as::<_, DataExport>(
+            r"
+            INSERT INTO data_exports (user_id, created_at)
+            VALUES ($1, $2)
+            RETURNING id, status, created_at, completed_at, expires_at
+            ",
+        )
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let job = Job::BuildExport {
+            export_id: export.id,
+        };
+        let max_attempts = job.max_attempts();
+        JobQueue::push(&mut *tx, &job, Utc::now(), max_attempts).await?;
+
+        tx.commit().await?;
+
+        Ok(export)
+    }
+
+    /// Collects the user's data into the archive of pending export `id` and
+    /// marks it ready, or failed if the data could not be collected. The
+    /// user's current sessions are read from `sessions`, wherever they are
+    /// kept.
+    ///
+    /// ## Errors
+    /// * Database errors while storing the outcome
+    pub async fn build_export(&self, sessions: &dyn SessionStore, id: Uuid) -> Result<()> {
+        let user_id: Option<Uuid> = sqlx::query_scalar(
+            "SELECT user_id FROM data_exports WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let Some(user_id) = user_id else {
+            return Ok(());
+        };
+
+        let (status, archive) = match self.collect(sessions, user_id).await {
+            Ok(archive) => (ExportStatus::Ready, Some(archive)),
+            Err(err) => {
+                tracing::error!(export_id = %id, error = %err, "Failed to build data export");
+                (ExportStatus::Failed, None)
+            }
+        };
+
+        let now = Utc::now();
+        let ttl = Duration::seconds(i64::try_from(self.config.export_ttl()).unwrap_or(i64::MAX));
+
+        sqlx::query(
+            r"
+            UPDATE data_exports
+            SET status = $2, archive = $3, completed_at = $4, expires_at = $5
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(status.name())
+        .bind(archive)
+        .bind(now)
+        .bind(now + ttl)
+        .execute(&self.db)
+        .await?;
+
+        tracing::info!(%user_id, export_id = %id, status = status.name(), "Data export built");
+
+        Ok(())
+    }
+
+    /// Marks pending export `id` failed once its build job gave up.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn abandon_export(&self, id: Uuid) -> Result<()> {
+        sqlx::query(
+            r"
+            UPDATE data_exports
+            SET status = 'failed', completed_at = now()
+            WHERE id = $1 AND status = 'pending'
+            ",
+        )
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn collect(&self, sessions: &dyn SessionStore, user_id: Uuid) -> Result<Value> {
+        let profile = self
+            .users
+            .find(user_id)
+            .await?
+            .ok_or(Error::NotFound("user"))?;
+
+        let sessions = sessions.list(user_id).await?;
+
+        let audit_events = sqlx::query_as::<_, AuditRecord>(
+            r"
+            SELECT action, ip, metadata, created_at
+            FROM audit_events
+            WHERE user_id = $1
+            ORDER BY created_at
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        let known_logins: Value = sqlx::query_scalar(
+            r"
+            SELECT COALESCE(
+                json_agg(json_build_object(
+                    'device', device,
+                    'network', network,
+                    'first_seen_at', first_seen_at,
+                    'last_seen_at', last_seen_at
+                ) ORDER BY first_seen_at),
+                '[]'
+            )
+            FROM known_logins
+            WHERE user_id = $1
+            ",
+        )
+        .bind(user_id)
+        .fetch_one(&self.db)
+        .await?;
+
+        let login_attempts: Value = sqlx::query_scalar(
+            r"
+            SELECT COALESCE(
+                json_agg(json_build_object(
+                    'method', method,
+                    'device_kind', device_kind,
+                    'created_at', created_at
+                ) ORDER BY created_at),
+                '[]'
+            )
+            FROM login_attempts
+            WHERE user_id = $1
+            ",
+        )
+        .bind(user_id)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(json!({
+            "exported_at": Utc::now(),
+            "profile": profile,
+            "sessions": sessions,
+            "audit_events": audit_events,
+            "known_logins": known_logins,
+            "login_attempts": login_attempts,
+        }))
+    }
+
+    /// Looks up export `id` if it belongs to `user_id`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn find_export(&self, user_id: Uuid, id: Uuid) -> Result<Option<DataExport>> {
+        sqlx::query_as::<_, DataExport>(
+            r"
+            SELECT id, status, created_at, completed_at, expires_at
+            FROM data_exports
+            WHERE id = $1 AND user_id = $2
+            ",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// The archive of export `id` if it belongs to `user_id`, is ready and
+    /// has not expired.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn archive(&self, user_id: Uuid, id: Uuid) -> Result<Option<Value>> {
+        sqlx::query_scalar(
+            r"
+            SELECT archive
+            FROM data_exports
+            WHERE id = $1 AND user_id = $2 AND status = 'ready' AND expires_at > now()
+            ",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Schedules `user_id` for erasure once the grace period is over and
+    /// returns when that will be. Scheduling again keeps the earlier date.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn schedule_deletion(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        let grace = Duration::seconds(
+            i64::try_from(self.config.deletion_grace_period()).unwrap_or(i64::MAX),
+        );
+
+        sqlx::query_scalar(
+            r"
+            UPDATE users
+            SET deletion_scheduled_for = COALESCE(deletion_scheduled_for, $2)
+            WHERE id = $1
+            RETURNING deletion_scheduled_for
+            ",
+        )
+        .bind(user_id)
+        .bind(Utc::now() + grace)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Cancels a scheduled deletion of `user_id`. Returns `false` if none
+    /// was scheduled.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn cancel_deletion(&self, user_id: Uuid) -> Result<bool> {
+        let cancelled = sqlx::query(
+            r"
+            UPDATE users
+            SET deletion_scheduled_for = NULL
+            WHERE id = $1 AND deletion_scheduled_for IS NOT NULL
+            ",
+        )
+        .bind(user_id)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(cancelled > 0)
+    }
+
+    /// Deletes `user_id` with everything that belongs to it. Audit events
+    /// are kept for the record but stripped of the IP address, details and
+    /// link to the user. Webhook deliveries about the user keep their
+    /// identifiers but lose the email addresses, phone number, IP address
+    /// and device, and emails still queued for the user's address are
+    /// dropped. Returns `false` if the user did not exist.
+    ///
+    /// The user's sessions are ended through `sessions` first, so those kept
+    /// outside Postgres and cached copies go too.
+    ///
+    /// ## Errors
+    /// * Database errors
+    /// * Session store errors
+    pub async fn erase(&self, sessions: &dyn SessionStore, user_id: Uuid) -> Result<bool> {
+        sessions.delete_all(user_id).await?;
+
+        let mut tx = self.db.begin().await?;
+
+        let email: Option<String> = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r"
+            UPDATE webhook_deliveries
+            SET payload = jsonb_set(
+                payload,
+                '{data}',
+                (payload->'data')
+                    - ARRAY['email', 'old_email', 'new_email', 'merged_email', 'phone', 'ip', 'device']
+            )
+            WHERE (payload->'data'->>'user_id')::uuid = $1
+            ",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "DELETE FROM jobs WHERE kind = 'send_email' AND lower(payload->'email'->>'to') = lower($1)",
+        )
+        .bind(&email)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r"
+            UPDATE audit_events
+            SET user_id = NULL, ip = NULL, metadata = '{}'
+            WHERE user_id = $1
+            ",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let deleted = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+
+        if deleted > 0 {
+            tracing::info!(%user_id, "User erased");
+        }
+
+        Ok(deleted > 0)
+    }
+
+    /// Erases accounts whose grace period is over, ending their sessions
+    /// through `sessions`, and drops expired archives.
+    ///
+    /// ## Errors
+    /// * Database errors
+    /// * Session store errors
+    pub async fn sweep(&self, sessions: &dyn SessionStore) -> Result<()> {
+        let due: Vec<Uuid> =
+            sqlx::query_scalar("SELECT id FROM users WHERE deletion_scheduled_for <= now()")
+                .fetch_all(&self.db)
+                .await?;
+
+        for user_id in due {
+            self.erase(sessions, user_id).await?;
+        }
+
+        sqlx::query("DELETE FROM data_exports WHERE expires_at <= now()")
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Spawns the background task that periodically runs [`Privacy::sweep`]
+/// until `shutdown` is cancelled.
+pub fn spawn_worker(ctx: Arc<AppContext>, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.cancelled() => break,
+            }
+
+            if let Err(err) = ctx.privacy().sweep(ctx.sessions()).await {
+                tracing::warn!(error = %err, "Privacy sweep failed");
+            }
+        }
+    })
+}
@@ -0,0 +1,66 @@
+use std::{sync::Arc, time::Duration as StdDuration, time::SystemTime};
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    AppContext,
+    config::{Config, Overrides},
+};
+
+/// How often the configuration file is checked for changes.
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(2);
+
+/// When the base and configuration files loaded with `overrides` were last
+/// modified, where they can be found.
+fn modified(overrides: &Overrides) -> [Option<SystemTime>; 2] {
+    let base = Config::base_path(overrides).ok().flatten();
+
+    [base, Config::path(overrides).ok()].map(|path| {
+        path.and_then(|path| path.metadata().ok())
+            .and_then(|metadata| metadata.modified().ok())
+    })
+}
+
+/// Spawns the background task that reloads the configuration whenever its
+/// file or the base file changes, with the same `overrides` it was first loaded with, and
+/// applies it with [`AppContext::reload`].
+///
+/// Only the log level, rate limits and CORS origins take effect; other
+/// changes need a restart. A file that no longer loads or validates is
+/// reported and the running configuration kept. Stops when `shutdown` is
+/// cancelled.
+pub fn spawn_watcher(
+    ctx: Arc<AppContext>,
+    overrides: Overrides,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last = modified(&overrides);
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.cancelled() => break,
+            }
+
+            let current = modified(&overrides);
+            if current == last {
+                continue;
+            }
+            last = current;
+
+            let applied = Config::from_overrides(&overrides)
+                .map_err(Into::into)
+                .and_then(|config| ctx.reload(&config));
+
+            match applied {
+                Ok(()) => tracing::info!("Configuration reloaded"),
+                Err(err) => {
+                    tracing::warn!(error = %err, "Configuration reload failed, keeping the current one");
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,561 @@
+use std::future::Future;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    cache::Cache,
+    db::ReadPool,
+    events::{Event, LoginMethod, Subscriber},
+    sessions::{Device, DeviceKind},
+};
+
+/// How long a computed report is served from the cache, in seconds.
+const CACHE_TTL: u64 = 300;
+
+/// Longest window a report covers, in days.
+pub const MAX_REPORT_DAYS: u32 = 366;
+
+/// Users who logged in on a day.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ActiveUsersDay {
+    pub date: NaiveDate,
+    pub active_users: i64,
+    /// Average of `active_users` over the day and the six before it, within
+    /// the report.
+    pub rolling_average: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveUsersReport {
+    pub since: DateTime<Utc>,
+    /// Distinct users who logged in over the whole window.
+    pub active_users: i64,
+    pub days: Vec<ActiveUsersDay>,
+}
+
+/// Login attempts on a day.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoginsDay {
+    pub date: NaiveDate,
+    pub succeeded: i64,
+    pub failed: i64,
+    /// Share of the attempts that failed, `0` without any.
+    pub failure_rate: f64,
+}
+
+/// Login attempts with one method.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoginsByMethod {
+    pub method: String,
+    pub succeeded: i64,
+    pub failed: i64,
+}
+
+/// Failed login attempts for one reason, as in the `reason` label of the
+/// `login_failure_total` metric.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoginsByReason {
+    pub reason: String,
+    pub failed: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginsReport {
+    pub since: DateTime<Utc>,
+    pub succeeded: i64,
+    pub failed: i64,
+    pub failure_rate: f64,
+    pub days: Vec<LoginsDay>,
+    pub methods: Vec<LoginsByMethod>,
+    pub reasons: Vec<LoginsByReason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MfaReport {
+    pub since: DateTime<Utc>,
+    /// Accounts that are not deleted.
+    pub users: i64,
+    /// Users with at least one passkey.
+    pub with_passkey: i64,
+    /// Users linked to a SAML tenant.
+    pub with_saml: i64,
+    /// Share of the users with a passkey or a SAML tenant.
+    pub adoption: f64,
+    /// Organizations whose policy requires a phishing-resistant login.
+    pub organizations_requiring_phishing_resistant_login: i64,
+    /// Sessions started within the window.
+    pub logins: i64,
+    /// Of those, the phishing-resistant ones, started with a passkey or
+    /// SAML.
+    pub phishing_resistant_logins: i64,
+    /// Share of the logins that were phishing-resistant.
+    pub phishing_resistant_login_rate: f64,
+}
+
+/// Sessions started from one kind of device.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionsByDevice {
+    pub device_kind: String,
+    pub sessions: i64,
+    /// Share of all the sessions started within the window.
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicesReport {
+    pub since: DateTime<Utc>,
+    pub sessions: i64,
+    pub devices: Vec<SessionsByDevice>,
+}
+
+/// The reason a login refused with `err` is recorded and counted under.
+#[must_use]
+pub fn failure_reason(err: &Error) -> &'static str {
+    match err {
+        Error::CaptchaFailed => "captcha",
+        Error::TooManyAttempts | Error::RateLimited { .. } => "throttled",
+        Error::InvalidCredentials | Error::InvalidToken | Error::WebAuthn(_) | Error::Saml(_) => {
+            "invalid_credentials"
+        }
+        Error::OAuth(_) => "provider",
+        Error::EmailNotVerified => "email_not_verified",
+        Error::AccountDisabled => "account_disabled",
+        Error::LoginMethodNotAllowed | Error::PasswordChangeRequired => "policy",
+        _ => "error",
+    }
+}
+
+/// Aggregate reports on logins and sessions, for dashboards that should not
+/// need the raw audit log.
+///
+/// Every session started is recorded in the `login_attempts` table by
+/// subscribing to [`Event::UserLoggedIn`], and every attempt refused by a
+/// login endpoint, whether by password, email code, magic link, phone
+/// code, passkey, OAuth or SAML, through [`Reports::record_outcome`],
+/// without the user, who may not exist. Rows are kept for
+/// `maintenance.login_history_retention`.
+///
+/// Reports cover whole days in UTC, today included, and are computed on the
+/// read replicas. With Redis configured, each is cached for five minutes,
+/// so dashboards polling them cost one query per report and window.
+#[derive(Clone)]
+pub struct Reports {
+    db: PgPool,
+    reads: ReadPool,
+    cache: Option<Cache>,
+}
+
+impl Reports {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            reads: ReadPool::new(db.clone()),
+            db,
+            cache: None,
+        }
+    }
+
+    /// Computes reports on `reads` instead of the primary.
+    #[must_use]
+    pub fn with_reads(mut self, reads: ReadPool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    /// Caches computed reports in `cache`.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Records a login attempt refused for `reason`, from `device`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn record_failure(
+        &self,
+        method: &LoginMethod,
+        device: &Device,
+        reason: &str,
+    ) -> Result<()> {
+        self.record(None, method, device.kind, Some(reason)).await
+    }
+
+    /// Records `outcome` of a login with `method` from `device` if it was
+    /// refused, for [`failure_reason`]. Errors are logged rather than
+    /// returned, so a report never changes the answer to the login.
+    ///
+    /// Requests refused before any credentials were looked at, because the
+    /// login method is disabled or unknown, are not attempts and are skipped.
+    pub async fn record_outcome<T>(
+        &self,
+        method: &LoginMethod,
+        device: &Device,
+        outcome: &Result<T>,
+    ) {
+        let Err(err) = outcome else {
+            return;
+        };
+
+        if matches!(err, Error::Disabled(_) | Error::UnknownProvider) {
+            return;
+        }
+
+        if let Err(err) = self
+            .record_failure(method, device, failure_reason(err))
+            .await
+        {
+            tracing::warn!(error = %err, "Failed to record a failed login");
+        }
+    }
+
+    async fn record(
+        &self,
+        user_id: Option<Uuid>,
+        method: &LoginMethod,
+        device_kind: DeviceKind,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT INTO login_attempts (user_id, method, succeeded, reason, device_kind, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+        )
+        .bind(user_id)
+        .bind(method.name())
+        .bind(reason.is_none())
+        .bind(reason)
+        .bind(device_kind.name())
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Users who logged in on each of the last `days` days.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if `days` is not between 1 and
+    ///   [`MAX_REPORT_DAYS`]
+    /// * Database errors
+    pub async fn active_users(&self, days: u32) -> Result<ActiveUsersReport> {
+        let (first_day, since) = window(days)?;
+
+        self.cached(&format!("reports:active_users:{days}"), || {
+            self.reads.run(move |db| async move {
+                let days: Vec<ActiveUsersDay> = sqlx::query_as(
+                    r"
+                    WITH daily AS (
+                        SELECT (created_at AT TIME ZONE 'UTC')::date AS date,
+                               COUNT(DISTINCT user_id) AS active_users
+                        FROM login_attempts
+                        WHERE succeeded AND created_at >= $2
+                        GROUP BY 1
+                    )
+                    SELECT calendar.day::date AS date,
+                           COALESCE(daily.active_users, 0) AS active_users,
+                           (AVG(COALESCE(daily.active_users, 0)) OVER (
+                               ORDER BY calendar.day ROWS BETWEEN 6 PRECEDING AND CURRENT ROW
+                           ))::float8 AS rolling_average
+                    FROM generate_series($1::date, (now() AT TIME ZONE 'UTC')::date, interval '1 day')
+                        AS calendar(day)
+                    LEFT JOIN daily ON daily.date = calendar.day::date
+                    ORDER BY 1
+                    ",
+                )
+                .bind(first_day)
+                .bind(since)
+                .fetch_all(&db)
+                .await?;
+
+                let active_users: i64 = sqlx::query_scalar(
+                    r"
+                    SELECT COUNT(DISTINCT user_id)
+                    FROM login_attempts
+                    WHERE succeeded AND created_at >= $1
+                    ",
+                )
+                .bind(since)
+                .fetch_one(&db)
+                .await?;
+
+                Ok::<_, Error>(ActiveUsersReport {
+                    since,
+                    active_users,
+                    days,
+                })
+            })
+        })
+        .await
+    }
+
+    /// Login attempts that succeeded and failed on each of the last `days`
+    /// days, with totals by method and by reason of failure.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if `days` is not between 1 and
+    ///   [`MAX_REPORT_DAYS`]
+    /// * Database errors
+    pub async fn logins(&self, days: u32) -> Result<LoginsReport> {
+        let (first_day, since) = window(days)?;
+
+        self.cached(&format!("reports:logins:{days}"), || {
+            self.reads.run(move |db| async move {
+                let days: Vec<LoginsDay> = sqlx::query_as(
+                    r"
+                    WITH daily AS (
+                        SELECT (created_at AT TIME ZONE 'UTC')::date AS date,
+                               COUNT(*) FILTER (WHERE succeeded) AS succeeded,
+                               COUNT(*) FILTER (WHERE NOT succeeded) AS failed
+                        FROM login_attempts
+                        WHERE created_at >= $2
+                        GROUP BY 1
+                    )
+                    SELECT calendar.day::date AS date,
+                           COALESCE(daily.succeeded, 0) AS succeeded,
+                           COALESCE(daily.failed, 0) AS failed,
+                           COALESCE(daily.failed::float8 / NULLIF(daily.succeeded + daily.failed, 0), 0)
+                               AS failure_rate
+                    FROM generate_series($1::date, (now() AT TIME ZONE 'UTC')::date, interval '1 day')
+                        AS calendar(day)
+                    LEFT JOIN daily ON daily.date = calendar.day::date
+                    ORDER BY 1
+                    ",
+                )
+                .bind(first_day)
+                .bind(since)
+                .fetch_all(&db)
+                .await?;
+
+                let methods: Vec<LoginsByMethod> = sqlx::query_as(
+                    r"
+                    SELECT method,
+                           COUNT(*) FILTER (WHERE succeeded) AS succeeded,
+                           COUNT(*) FILTER (WHERE NOT succeeded) AS failed
+                    FROM login_attempts
+                    WHERE created_at >= $1
+                    GROUP BY method
+                    ORDER BY COUNT(*) DESC, method
+                    ",
+                )
+                .bind(since)
+                .fetch_all(&db)
+                .await?;
+
+                let reasons: Vec<LoginsByReason> = sqlx::query_as(
+                    r"
+                    SELECT reason, COUNT(*) AS failed
+                    FROM login_attempts
+                    WHERE created_at >= $1 AND NOT succeeded
+                    GROUP BY reason
+                    ORDER BY COUNT(*) DESC, reason
+                    ",
+                )
+                .bind(since)
+                .fetch_all(&db)
+                .await?;
+
+                let succeeded = days.iter().map(|day| day.succeeded).sum();
+                let failed = days.iter().map(|day| day.failed).sum();
+
+                Ok::<_, Error>(LoginsReport {
+                    since,
+                    succeeded,
+                    failed,
+                    failure_rate: rate(failed, succeeded + failed),
+                    days,
+                    methods,
+                    reasons,
+                })
+            })
+        })
+        .await
+    }
+
+    /// How many users can log in with a passkey or SAML, as
+    /// [`crate::organizations::AuthPolicy::phishing_resistant_login_required`]
+    /// requires, and
+    /// how many of the sessions of the last `days` days were started so.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if `days` is not between 1 and
+    ///   [`MAX_REPORT_DAYS`]
+    /// * Database errors
+    pub async fn mfa(&self, days: u32) -> Result<MfaReport> {
+        let (_, since) = window(days)?;
+
+        self.cached(&format!("reports:mfa:{days}"), || {
+            self.reads.run(move |db| async move {
+                sqlx::query_as::<_, MfaReport>(
+                    r"
+                    WITH accounts AS (
+                        SELECT EXISTS (SELECT 1 FROM webauthn_credentials w WHERE w.user_id = u.id)
+                                   AS with_passkey,
+                               EXISTS (SELECT 1 FROM saml_accounts s WHERE s.user_id = u.id)
+                                   AS with_saml
+                        FROM users u
+                        WHERE u.deleted_at IS NULL
+                    ),
+                    users AS (
+                        SELECT COUNT(*) AS users,
+                               COUNT(*) FILTER (WHERE with_passkey) AS with_passkey,
+                               COUNT(*) FILTER (WHERE with_saml) AS with_saml,
+                               COUNT(*) FILTER (WHERE with_passkey OR with_saml) AS adopted
+                        FROM accounts
+                    ),
+                    logins AS (
+                        SELECT COUNT(*) AS logins,
+                               COUNT(*) FILTER (WHERE method IN ('passkey', 'saml')) AS phishing_resistant_logins
+                        FROM login_attempts
+                        WHERE succeeded AND created_at >= $1
+                    )
+                    SELECT $1 AS since,
+                           users.users,
+                           users.with_passkey,
+                           users.with_saml,
+                           COALESCE(users.adopted::float8 / NULLIF(users.users, 0), 0) AS adoption,
+                           (SELECT COUNT(*) FROM organizations
+                            WHERE (auth_policy->>'phishing_resistant_login_required')::boolean)
+                               AS organizations_requiring_phishing_resistant_login,
+                           logins.logins,
+                           logins.phishing_resistant_logins,
+                           COALESCE(logins.phishing_resistant_logins::float8 / NULLIF(logins.logins, 0), 0)
+                               AS phishing_resistant_login_rate
+                    FROM users, logins
+                    ",
+                )
+                .bind(since)
+                .fetch_one(&db)
+                .await
+            })
+        })
+        .await
+    }
+
+    /// Sessions started over the last `days` days, by kind of device.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if `days` is not between 1 and
+    ///   [`MAX_REPORT_DAYS`]
+    /// * Database errors
+    pub async fn devices(&self, days: u32) -> Result<DevicesReport> {
+        let (_, since) = window(days)?;
+
+        self.cached(&format!("reports:devices:{days}"), || {
+            self.reads.run(move |db| async move {
+                let devices: Vec<SessionsByDevice> = sqlx::query_as(
+                    r"
+                    SELECT device_kind,
+                           COUNT(*) AS sessions,
+                           COUNT(*)::float8 / SUM(COUNT(*)) OVER () AS share
+                    FROM login_attempts
+                    WHERE succeeded AND created_at >= $1
+                    GROUP BY device_kind
+                    ORDER BY COUNT(*) DESC, device_kind
+                    ",
+                )
+                .bind(since)
+                .fetch_all(&db)
+                .await?;
+
+                Ok::<_, Error>(DevicesReport {
+                    since,
+                    sessions: devices.iter().map(|device| device.sessions).sum(),
+                    devices,
+                })
+            })
+        })
+        .await
+    }
+
+    /// The report cached at `key`, or the one `compute` returns, cached from
+    /// then on. Cache failures only cost the caching.
+    async fn cached<T, F, Fut>(&self, key: &str, compute: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if let Some(cache) = &self.cache {
+            match cache.get_json(key).await {
+                Ok(Some(report)) => return Ok(report),
+                Ok(None) => {}
+                Err(err) => tracing::warn!(error = %err, key, "Failed to read a cached report"),
+            }
+        }
+
+        let report = compute().await?;
+
+        if let Some(cache) = &self.cache
+            && let Err(err) = cache.set_json(key, &report, CACHE_TTL).await
+        {
+            tracing::warn!(error = %err, key, "Failed to cache a report");
+        }
+
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl Subscriber for Reports {
+    async fn handle(&self, ctx: &AppContext, event: &Event) -> Result<()> {
+        let Event::UserLoggedIn {
+            user_id,
+            session_id,
+            method,
+        } = event
+        else {
+            return Ok(());
+        };
+
+        // Like failed logins, recorded on a best-effort basis: a login does
+        // not fail over its report.
+        let recorded: Result<()> = async {
+            let device_kind = ctx
+                .sessions()
+                .find(*session_id)
+                .await?
+                .and_then(|session| session.device)
+                .map_or(DeviceKind::Unknown, |device| device.kind);
+
+            self.record(Some(*user_id), method, device_kind, None).await
+        }
+        .await;
+        if let Err(err) = recorded {
+            tracing::warn!(error = %err, %user_id, "Failed to record a login");
+        }
+
+        Ok(())
+    }
+}
+
+/// The first day of a report over the last `days` days, and the time it
+/// starts at.
+fn window(days: u32) -> Result<(NaiveDate, DateTime<Utc>)> {
+    if !(1..=MAX_REPORT_DAYS).contains(&days) {
+        return Err(Error::Validation(format!(
+            "days must be between 1 and {MAX_REPORT_DAYS}"
+        )));
+    }
+
+    let first_day = Utc::now().date_naive() - Duration::days(i64::from(days) - 1);
+
+    Ok((first_day, first_day.and_time(NaiveTime::MIN).and_utc()))
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn rate(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
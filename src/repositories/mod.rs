@@ -0,0 +1,102 @@
+mod postgres;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
+
+pub use self::postgres::PgUserStore;
+use crate::{
+    Result,
+    models::{Metadata, NewUser, User},
+};
+
+/// Persistence for [`User`]s.
+///
+/// [`PgUserStore`] is the default; another backend is installed with
+/// [`crate::AppContext::with_user_store`]. Handlers that change users as
+/// part of a larger Postgres transaction go through [`PgUserStore`]
+/// directly.
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    /// Looks up a user by id. Deleted users are not found.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn find(&self, id: Uuid) -> Result<Option<User>>;
+
+    /// Looks up a user by the normalized form of their email address, as
+    /// [`crate::AppContext::normalize_email`] returns it. Deleted users are
+    /// not found.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>>;
+
+    /// Looks up a user by username, compared case-insensitively. Deleted
+    /// users are not found.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>>;
+
+    /// Creates `user`, returning it as stored, or `None` if its email
+    /// address is already registered or its username taken.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn create(&self, user: &NewUser<'_>) -> Result<Option<User>>;
+
+    /// Sets the display name of user `id`, returning the updated user or
+    /// `None` if it does not exist.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn update_name(&self, id: Uuid, name: Option<&str>) -> Result<Option<User>>;
+
+    /// Sets or, with `None`, clears the username of user `id`, returning the
+    /// updated user or `None` if it does not exist.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::UsernameTaken`] if another user has the username
+    /// * Backend errors
+    async fn set_username(&self, id: Uuid, username: Option<&str>) -> Result<Option<User>>;
+
+    /// Applies `patch` to the `metadata` object of user `id` as
+    /// [`crate::models::merge_metadata`] does, returning the updated user or
+    /// `None` if it does not exist. Concurrent patches must not overwrite
+    /// each other.
+    ///
+    /// ## Errors
+    /// * [`crate::Error::Validation`] if the patch is not an object or the
+    ///   result exceeds `max_size` bytes
+    /// * Backend errors
+    async fn patch_metadata(
+        &self,
+        id: Uuid,
+        metadata: Metadata,
+        patch: &Value,
+        max_size: usize,
+    ) -> Result<Option<User>>;
+
+    /// Replaces the password hash of user `id`, or removes the password with
+    /// `None`. Returns `false` if the user does not exist.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn set_password_hash(&self, id: Uuid, password_hash: Option<&str>) -> Result<bool>;
+
+    /// Marks user `id` [`crate::models::UserStatus::Deleted`], keeping its row for the
+    /// records that refer to it. Returns `false` if the user does not exist
+    /// or was deleted already.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn soft_delete(&self, id: Uuid) -> Result<bool>;
+
+    /// Deletes user `id` for good, together with everything that belongs to
+    /// it. Returns `false` if the user did not exist.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn delete(&self, id: Uuid) -> Result<bool>;
+}
@@ -0,0 +1,482 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::{PgConnection, PgExecutor, PgPool, types::Json};
+use uuid::Uuid;
+
+use super::UserStore;
+use crate::{
+    Error, Result,
+    db::ReadPool,
+    models::{Metadata, NewUser, User, merge_metadata},
+    organizations::OrgRole,
+};
+
+/// What [`PgUserStore::merge`] may take over from the duplicate account.
+#[derive(sqlx::FromRow)]
+struct Profile {
+    name: Option<String>,
+    username: Option<String>,
+    phone: Option<String>,
+    password_hash: Option<String>,
+    user_metadata: Json<Value>,
+    app_metadata: Json<Value>,
+}
+
+/// Adds the keys of the metadata `source` that `target` lacks to it.
+fn fill_metadata(mut target: Value, Json(source): Json<Value>, max_size: usize) -> Result<Value> {
+    let Value::Object(mut patch) = source else {
+        return Ok(target);
+    };
+    if let Value::Object(existing) = &target {
+        patch.retain(|key, _| !existing.contains_key(key));
+    }
+
+    merge_metadata(&mut target, &Value::Object(patch), max_size)?;
+
+    Ok(target)
+}
+
+/// Postgres-backed persistence for [`User`]s, the default [`UserStore`].
+///
+/// [`PgUserStore::insert`] and [`PgUserStore::update_password_hash`] take an
+/// `executor`, which is either the pool or a transaction, for handlers that
+/// change users together with other tables. Lookups by id and email
+/// address go to the read replicas given to [`PgUserStore::with_reads`].
+/// [`PgUserStore::merge`] folds duplicate accounts into one.
+#[derive(Clone)]
+pub struct PgUserStore {
+    db: PgPool,
+    reads: ReadPool,
+}
+
+impl PgUserStore {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            reads: ReadPool::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Looks users up on `reads` instead of the primary.
+    #[must_use]
+    pub fn with_reads(mut self, reads: ReadPool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    /// [`UserStore::create`] through `executor`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn insert(executor: impl PgExecutor<'_>, user: &NewUser<'_>) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            INSERT INTO users
+                (email, email_normalized, username, name, password_hash, role, verified_at, created_at,
+                 updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
+            ON CONFLICT DO NOTHING
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            ",
+        )
+        .bind(user.email)
+        .bind(&user.email_normalized)
+        .bind(user.username)
+        .bind(user.name)
+        .bind(user.password_hash)
+        .bind(user.role.name())
+        .bind(user.verified_at)
+        .bind(Utc::now())
+        .fetch_optional(executor)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// [`UserStore::set_password_hash`] through `executor`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn update_password_hash(
+        executor: impl PgExecutor<'_>,
+        id: Uuid,
+        password_hash: Option<&str>,
+    ) -> Result<bool> {
+        let updated =
+            sqlx::query("UPDATE users SET password_hash = $2, updated_at = now() WHERE id = $1")
+                .bind(id)
+                .bind(password_hash)
+                .execute(executor)
+                .await?
+                .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    /// Folds the duplicate account `source` into `target` within the
+    /// transaction `conn` is part of, then deletes `source`. Returns the ids
+    /// of the sessions of `source` it ended, or `None`, changing nothing, if
+    /// either user is missing or deleted.
+    ///
+    /// OIDC grants, revocations, data exports, known devices and
+    /// organization memberships move to `target`, as do the audit events,
+    /// login attempts and invitations referring to `source`. Where both
+    /// belong to the same organization, `target` keeps the higher of the two
+    /// roles, so no organization loses its owner. The profile of `target`
+    /// wins: only a name or username it lacks is taken from `source`, and
+    /// metadata keys are added unless `target` already has them. Its
+    /// verification is never taken over, as it holds for another address.
+    ///
+    /// The ways of logging in to `source`, i.e. its password, phone number,
+    /// passkeys, linked identities and API keys, only move with
+    /// `take_credentials`, the password and phone number only where `target`
+    /// has none; otherwise they are deleted with it, as are its pending
+    /// one-time tokens. Cached copies of the ended sessions should be dropped
+    /// through the session store once the transaction commits.
+    ///
+    /// ## Errors
+    /// * [`Error::Validation`] if the merged metadata would exceed
+    ///   `max_metadata_size` bytes
+    /// * Database errors
+    pub async fn merge(
+        conn: &mut PgConnection,
+        target: Uuid,
+        source: Uuid,
+        take_credentials: bool,
+        max_metadata_size: usize,
+    ) -> Result<Option<Vec<Uuid>>> {
+        let locked: Vec<Uuid> = sqlx::query_scalar(
+            r"
+            SELECT id FROM users
+            WHERE id IN ($1, $2) AND deleted_at IS NULL
+            ORDER BY id
+            FOR UPDATE
+            ",
+        )
+        .bind(target)
+        .bind(source)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if target == source || locked.len() != 2 {
+            return Ok(None);
+        }
+
+        let ended: Vec<Uuid> =
+            sqlx::query_scalar("DELETE FROM sessions WHERE user_id = $1 RETURNING id")
+                .bind(source)
+                .fetch_all(&mut *conn)
+                .await?;
+
+        let credentials: &[&str] = if take_credentials {
+            &[
+                "oauth_accounts",
+                "saml_accounts",
+                "webauthn_credentials",
+                "api_keys",
+            ]
+        } else {
+            &[]
+        };
+        for table in [
+            "oauth_authorization_codes",
+            "oauth_access_tokens",
+            "revoked_tokens",
+            "data_exports",
+            "audit_events",
+            "login_attempts",
+        ]
+        .iter()
+        .chain(credentials)
+        {
+            sqlx::query(&format!(
+                "UPDATE {table} SET user_id = $1 WHERE user_id = $2"
+            ))
+            .bind(target)
+            .bind(source)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        for table in ["organization_invitations", "signup_invites"] {
+            sqlx::query(&format!(
+                "UPDATE {table} SET invited_by = $1 WHERE invited_by = $2"
+            ))
+            .bind(target)
+            .bind(source)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        // Where both are members, `target` is promoted to the role of
+        // `source` if that is higher, before the membership of `source` goes
+        // with it.
+        sqlx::query(
+            r"
+            UPDATE memberships AS kept
+            SET role = merged.role
+            FROM memberships AS merged
+            WHERE kept.user_id = $1
+              AND merged.user_id = $2
+              AND merged.organization_id = kept.organization_id
+              AND array_position($3, merged.role) > array_position($3, kept.role)
+            ",
+        )
+        .bind(target)
+        .bind(source)
+        .bind([
+            OrgRole::Member.name(),
+            OrgRole::Admin.name(),
+            OrgRole::Owner.name(),
+        ])
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r"
+            UPDATE memberships
+            SET user_id = $1
+            WHERE user_id = $2
+              AND organization_id NOT IN (SELECT organization_id FROM memberships WHERE user_id = $1)
+            ",
+        )
+        .bind(target)
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO known_logins (user_id, device, network, first_seen_at, last_seen_at)
+            SELECT $1, device, network, first_seen_at, last_seen_at
+            FROM known_logins
+            WHERE user_id = $2
+            ON CONFLICT (user_id, device, network) DO NOTHING
+            ",
+        )
+        .bind(target)
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+
+        let (Json(user_metadata), Json(app_metadata)): (Json<Value>, Json<Value>) =
+            sqlx::query_as("SELECT user_metadata, app_metadata FROM users WHERE id = $1")
+                .bind(target)
+                .fetch_one(&mut *conn)
+                .await?;
+
+        // Deleted before the gaps of `target` are filled, so its username
+        // and phone number are free to take over.
+        let profile: Profile = sqlx::query_as(
+            r"
+            DELETE FROM users
+            WHERE id = $1
+            RETURNING name, username, phone, password_hash, user_metadata, app_metadata
+            ",
+        )
+        .bind(source)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let user_metadata = fill_metadata(user_metadata, profile.user_metadata, max_metadata_size)?;
+        let app_metadata = fill_metadata(app_metadata, profile.app_metadata, max_metadata_size)?;
+
+        sqlx::query(
+            r"
+            UPDATE users
+            SET name = COALESCE(name, $2),
+                username = COALESCE(username, $3),
+                phone = COALESCE(phone, $4),
+                password_hash = COALESCE(password_hash, $5),
+                user_metadata = $6,
+                app_metadata = $7,
+                updated_at = now()
+            WHERE id = $1
+            ",
+        )
+        .bind(target)
+        .bind(profile.name)
+        .bind(profile.username)
+        .bind(profile.phone.filter(|_| take_credentials))
+        .bind(profile.password_hash.filter(|_| take_credentials))
+        .bind(Json(user_metadata))
+        .bind(Json(app_metadata))
+        .execute(&mut *conn)
+        .await?;
+
+        tracing::info!(user_id = %target, merged_user_id = %source, "Users merged");
+
+        Ok(Some(ended))
+    }
+}
+
+#[async_trait]
+impl UserStore for PgUserStore {
+    async fn find(&self, id: Uuid) -> Result<Option<User>> {
+        self.reads
+            .run(|db| async move {
+                sqlx::query_as::<_, User>(
+                    r"
+                    SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                           updated_at, deleted_at, user_metadata, app_metadata
+                    FROM users
+                    WHERE id = $1 AND deleted_at IS NULL
+                    ",
+                )
+                .bind(id)
+                .fetch_optional(&db)
+                .await
+            })
+            .await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
+        self.reads
+            .run(|db| async move {
+                sqlx::query_as::<_, User>(
+                    r"
+                    SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                           updated_at, deleted_at, user_metadata, app_metadata
+                    FROM users
+                    WHERE email_normalized = $1 AND deleted_at IS NULL
+                    ",
+                )
+                .bind(email)
+                .fetch_optional(&db)
+                .await
+            })
+            .await
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>> {
+        self.reads
+            .run(|db| async move {
+                sqlx::query_as::<_, User>(
+                    r"
+                    SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                           updated_at, deleted_at, user_metadata, app_metadata
+                    FROM users
+                    WHERE lower(username) = lower($1) AND deleted_at IS NULL
+                    ",
+                )
+                .bind(username)
+                .fetch_optional(&db)
+                .await
+            })
+            .await
+    }
+
+    async fn create(&self, user: &NewUser<'_>) -> Result<Option<User>> {
+        Self::insert(&self.db, user).await
+    }
+
+    async fn update_name(&self, id: Uuid, name: Option<&str>) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            UPDATE users
+            SET name = $2, updated_at = now()
+            WHERE id = $1
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            ",
+        )
+        .bind(id)
+        .bind(name)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn set_username(&self, id: Uuid, username: Option<&str>) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            UPDATE users
+            SET username = $2, updated_at = now()
+            WHERE id = $1
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            ",
+        )
+        .bind(id)
+        .bind(username)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(db) if db.is_unique_violation() => Error::UsernameTaken,
+            other => other.into(),
+        })
+    }
+
+    async fn patch_metadata(
+        &self,
+        id: Uuid,
+        metadata: Metadata,
+        patch: &Value,
+        max_size: usize,
+    ) -> Result<Option<User>> {
+        let column = metadata.column();
+        let mut tx = self.db.begin().await?;
+
+        let current: Option<Json<Value>> = sqlx::query_scalar(&format!(
+            "SELECT {column} FROM users WHERE id = $1 FOR UPDATE"
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(Json(mut value)) = current else {
+            return Ok(None);
+        };
+
+        merge_metadata(&mut value, patch, max_size)?;
+
+        let user = sqlx::query_as::<_, User>(&format!(
+            r"
+            UPDATE users
+            SET {column} = $2, updated_at = now()
+            WHERE id = $1
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            "
+        ))
+        .bind(id)
+        .bind(Json(&value))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(user))
+    }
+
+    async fn set_password_hash(&self, id: Uuid, password_hash: Option<&str>) -> Result<bool> {
+        Self::update_password_hash(&self.db, id, password_hash).await
+    }
+
+    async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query(
+            r"
+            UPDATE users
+            SET status = 'deleted', deleted_at = now(), updated_at = now()
+            WHERE id = $1 AND deleted_at IS NULL
+            ",
+        )
+        .bind(id)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(deleted > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+        Ok(deleted > 0)
+    }
+}
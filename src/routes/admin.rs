@@ -0,0 +1,821 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get, patch, post},
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::Jwk;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use super::password_reset;
+use crate::{
+    AppContext, Error, Result,
+    audit::AuditEvent,
+    auth::{
+        AUDIT_READ_SCOPE, AdminUser, REPORTS_READ_SCOPE, RequireScope, Role, USERS_READ_SCOPE,
+        USERS_WRITE_SCOPE, generate_token, hash_token, is_valid_email,
+    },
+    events::Event,
+    i18n::Locale,
+    jobs::Job,
+    mail::EmailTemplate,
+    models::{Metadata, User, UserStatus},
+    organizations::OrgRole,
+    pagination::{Page, Paginated, Pagination, SortField, push_filter, sort_key},
+    reports::{ActiveUsersReport, DevicesReport, LoginsReport, MfaReport},
+    repositories::PgUserStore,
+    security::ClientIp,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    let read_users = Router::new()
+        .route("/admin/users", get(list_users))
+        .route("/admin/users/{id}", get(get_user))
+        .route_layer(RequireScope(USERS_READ_SCOPE));
+
+    let write_users = Router::new()
+        .route("/admin/users/{id}", delete(delete_user))
+        .route("/admin/users/{id}/disable", post(disable_user))
+        .route("/admin/users/{id}/enable", post(enable_user))
+        .route(
+            "/admin/users/{id}/force-password-reset",
+            post(force_password_reset),
+        )
+        .route("/admin/users/{id}/revoke", post(revoke_user_tokens))
+        .route("/admin/users/{id}/metadata", patch(update_user_metadata))
+        .route("/admin/users/{id}/merge", post(merge_users))
+        .route("/admin/invites", post(create_invite))
+        .route_layer(RequireScope(USERS_WRITE_SCOPE));
+
+    let read_reports = Router::new()
+        .route("/admin/reports/active-users", get(active_users_report))
+        .route("/admin/reports/logins", get(logins_report))
+        .route("/admin/reports/mfa", get(mfa_report))
+        .route("/admin/reports/devices", get(devices_report))
+        .route_layer(RequireScope(REPORTS_READ_SCOPE));
+
+    Router::new()
+        .merge(read_users)
+        .merge(write_users)
+        .merge(read_reports)
+        .route(
+            "/admin/audit-events",
+            get(list_audit_events).route_layer(RequireScope(AUDIT_READ_SCOPE)),
+        )
+        .route("/admin/signing-keys/rotate", post(rotate_signing_key))
+}
+
+/// A user as seen by admins, including account state hidden from the user
+/// themselves.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AdminUserView {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    user: User,
+    disabled_at: Option<DateTime<Utc>>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl Paginated for AdminUserView {
+    fn id(&self) -> Uuid {
+        self.user.id
+    }
+
+    fn sort_value(&self, field: &str) -> String {
+        match field {
+            "email" => self.user.email.clone(),
+            _ => sort_key(self.user.created_at),
+        }
+    }
+}
+
+const USER_CREATED_AT: SortField = SortField::new("created_at", "created_at", "timestamptz");
+const USER_EMAIL: SortField = SortField::new("email", "email", "text");
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListUsersQuery {
+    /// Case-insensitive substring of the email address.
+    email: Option<String>,
+    role: Option<Role>,
+    /// Deleted users are only listed when asked for by status.
+    status: Option<UserStatus>,
+    disabled: Option<bool>,
+    verified: Option<bool>,
+}
+
+/// `GET /admin/users`
+///
+/// Lists users, newest first, one [`Page`] at a time. Filter with `email`,
+/// `role`, `status`, `disabled` and `verified`; sort by `created_at` or
+/// `email`. Deleted users are left out unless `status=deleted`.
+async fn list_users(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    pagination: Pagination,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<Page<AdminUserView>>> {
+    users_page(&ctx, &pagination, &query).await.map(Json)
+}
+
+/// The page of users [`list_users`] responds with.
+pub(super) async fn users_page(
+    ctx: &AppContext,
+    pagination: &Pagination,
+    query: &ListUsersQuery,
+) -> Result<Page<AdminUserView>> {
+    let sort = pagination.sort(&[USER_CREATED_AT, USER_EMAIL], USER_CREATED_AT.descending())?;
+    let email = query.email.as_deref().map(str::trim);
+    let role = query.role.map(Role::name);
+
+    let filter = |builder: &mut QueryBuilder<'_, Postgres>| {
+        push_filter(
+            builder,
+            "email ILIKE '%' || ? || '%'",
+            email.map(str::to_owned),
+        );
+        push_filter(builder, "role = ?", role);
+        match query.status {
+            Some(status) => push_filter(builder, "status = ?", Some(status.name())),
+            None => {
+                builder.push(" AND status <> 'deleted'");
+            }
+        }
+        push_filter(builder, "(disabled_at IS NOT NULL) = ?", query.disabled);
+        push_filter(builder, "(verified_at IS NOT NULL) = ?", query.verified);
+    };
+
+    let (users, total) = ctx
+        .db_read()
+        .run(|db| async move {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE TRUE");
+            filter(&mut count);
+            let total: i64 = count.build_query_scalar().fetch_one(&db).await?;
+
+            let mut select = QueryBuilder::new(
+                r"
+                SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                       updated_at, deleted_at, user_metadata, app_metadata, disabled_at, locked_until
+                FROM users
+                WHERE TRUE
+                ",
+            );
+            filter(&mut select);
+            pagination.push(&mut select, sort)?;
+            let users = select.build_query_as().fetch_all(&db).await?;
+
+            Ok::<_, Error>((users, total))
+        })
+        .await?;
+
+    Ok(pagination.page(users, sort, Some(total)))
+}
+
+const AUDIT_CREATED_AT: SortField = SortField::new("created_at", "created_at", "timestamptz");
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListAuditEventsQuery {
+    user_id: Option<Uuid>,
+    action: Option<String>,
+    /// Only events at or after this time.
+    since: Option<DateTime<Utc>>,
+    /// Only events before this time.
+    until: Option<DateTime<Utc>>,
+}
+
+impl ListAuditEventsQuery {
+    /// The events of `user_id`.
+    pub(super) fn for_user(user_id: Uuid) -> Self {
+        Self {
+            user_id: Some(user_id),
+            ..Self::default()
+        }
+    }
+}
+
+/// `GET /admin/audit-events`
+///
+/// Lists audit events, newest first, one [`Page`] at a time. Filter with
+/// `user_id`, `action`, `since` and `until`.
+async fn list_audit_events(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    pagination: Pagination,
+    Query(query): Query<ListAuditEventsQuery>,
+) -> Result<Json<Page<AuditEvent>>> {
+    audit_events_page(&ctx, &pagination, &query).await.map(Json)
+}
+
+/// The page of audit events [`list_audit_events`] responds with.
+pub(super) async fn audit_events_page(
+    ctx: &AppContext,
+    pagination: &Pagination,
+    query: &ListAuditEventsQuery,
+) -> Result<Page<AuditEvent>> {
+    let sort = pagination.sort(&[AUDIT_CREATED_AT], AUDIT_CREATED_AT.descending())?;
+
+    let filter = |builder: &mut QueryBuilder<'_, Postgres>| {
+        push_filter(builder, "user_id = ?", query.user_id);
+        push_filter(builder, "action = ?", query.action.clone());
+        push_filter(builder, "created_at >= ?", query.since);
+        push_filter(builder, "created_at < ?", query.until);
+    };
+
+    let (events, total) = ctx
+        .db_read()
+        .run(|db| async move {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM audit_events WHERE TRUE");
+            filter(&mut count);
+            let total: i64 = count.build_query_scalar().fetch_one(&db).await?;
+
+            let mut select = QueryBuilder::new(
+                "SELECT id, user_id, action, ip, metadata, created_at FROM audit_events WHERE TRUE",
+            );
+            filter(&mut select);
+            pagination.push(&mut select, sort)?;
+            let events = select.build_query_as().fetch_all(&db).await?;
+
+            Ok::<_, Error>((events, total))
+        })
+        .await?;
+
+    Ok(pagination.page(events, sort, Some(total)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// Days covered, today included. Defaults to 30.
+    #[serde(default = "default_report_days")]
+    days: u32,
+}
+
+fn default_report_days() -> u32 {
+    30
+}
+
+/// `GET /admin/reports/active-users`
+///
+/// Distinct users who logged in on each of the last `days` days, with a
+/// seven-day rolling average, and over the whole window. See
+/// [`crate::reports::Reports`].
+///
+/// Responds with `422 Unprocessable Entity` if `days` is not between 1 and
+/// 366.
+async fn active_users_report(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<ActiveUsersReport>> {
+    ctx.reports().active_users(query.days).await.map(Json)
+}
+
+/// `GET /admin/reports/logins`
+///
+/// Logins that succeeded and failed on each of the last `days` days, with
+/// their failure rate, and totals by login method and by reason of failure.
+/// Failures are those of every login endpoint, each under its method.
+///
+/// Responds with `422 Unprocessable Entity` if `days` is not between 1 and
+/// 366.
+async fn logins_report(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<LoginsReport>> {
+    ctx.reports().logins(query.days).await.map(Json)
+}
+
+/// `GET /admin/reports/mfa`
+///
+/// How many users have a passkey or a SAML tenant to log in with, how many
+/// organizations require one, and the share of the logins of the last
+/// `days` days that used them, i.e. were phishing-resistant.
+///
+/// Responds with `422 Unprocessable Entity` if `days` is not between 1 and
+/// 366.
+async fn mfa_report(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<MfaReport>> {
+    ctx.reports().mfa(query.days).await.map(Json)
+}
+
+/// `GET /admin/reports/devices`
+///
+/// Sessions started over the last `days` days by kind of device: desktop,
+/// mobile, tablet or unknown, with their share of the total.
+///
+/// Responds with `422 Unprocessable Entity` if `days` is not between 1 and
+/// 366.
+async fn devices_report(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<DevicesReport>> {
+    ctx.reports().devices(query.days).await.map(Json)
+}
+
+/// `GET /admin/users/{id}`
+///
+/// Returns a single user, or `404 Not Found`.
+async fn get_user(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AdminUserView>> {
+    find_user(&ctx, id).await.map(Json)
+}
+
+/// `POST /admin/users/{id}/disable`
+///
+/// Disables an account: its sessions end, its tokens and API keys stop
+/// working and it can no longer log in until re-enabled. Admins cannot
+/// disable themselves.
+///
+/// Responds with the updated user, `403 Forbidden` for the caller's own
+/// account or `404 Not Found`, also for deleted users.
+async fn disable_user(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AdminUserView>> {
+    if id == admin.id() {
+        return Err(Error::Forbidden);
+    }
+
+    let updated = sqlx::query(
+        r"
+        UPDATE users
+        SET status = 'disabled', disabled_at = COALESCE(disabled_at, now()), updated_at = now()
+        WHERE id = $1 AND deleted_at IS NULL
+        ",
+    )
+    .bind(id)
+    .execute(ctx.db())
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err(Error::NotFound("user"));
+    }
+
+    let user = find_user(&ctx, id).await?;
+    let ended = ctx.sessions().delete_all(id).await?;
+    ctx.publish(Event::SessionsRevoked {
+        user_id: id,
+        session_ids: ended,
+    })
+    .await?;
+
+    ctx.publish(Event::UserDisabled {
+        user_id: id,
+        admin_id: admin.id(),
+        ip,
+    })
+    .await?;
+
+    Ok(Json(user))
+}
+
+/// `POST /admin/users/{id}/enable`
+///
+/// Lifts a previous [`disable_user`]. Responds with the updated user or
+/// `404 Not Found`, also for deleted users.
+async fn enable_user(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AdminUserView>> {
+    let updated = sqlx::query(
+        r"
+        UPDATE users
+        SET status = 'active', disabled_at = NULL, updated_at = now()
+        WHERE id = $1 AND deleted_at IS NULL
+        ",
+    )
+    .bind(id)
+    .execute(ctx.db())
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        return Err(Error::NotFound("user"));
+    }
+
+    let user = find_user(&ctx, id).await?;
+
+    ctx.publish(Event::UserEnabled {
+        user_id: id,
+        admin_id: admin.id(),
+        ip,
+    })
+    .await?;
+
+    Ok(Json(user))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMetadataRequest {
+    user_metadata: Option<Value>,
+    app_metadata: Option<Value>,
+}
+
+/// `PATCH /admin/users/{id}/metadata`
+///
+/// Changes the `user_metadata` and `app_metadata` of an account, the latter
+/// of which users cannot edit themselves. Each is applied as a JSON merge
+/// patch: keys set to `null` are removed, objects are merged and other
+/// values replaced.
+///
+/// Responds with the updated user, `404 Not Found`, also for deleted users,
+/// or `422 Unprocessable Entity` if a patch is not an object or the result
+/// exceeds `auth.max_metadata_size`.
+async fn update_user_metadata(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateMetadataRequest>,
+) -> Result<Json<AdminUserView>> {
+    if ctx.users().find(id).await?.is_none() {
+        return Err(Error::NotFound("user"));
+    }
+
+    let max_size = ctx.config().auth().max_metadata_size();
+    for (metadata, patch) in [
+        (Metadata::User, &payload.user_metadata),
+        (Metadata::App, &payload.app_metadata),
+    ] {
+        if let Some(patch) = patch {
+            ctx.users()
+                .patch_metadata(id, metadata, patch, max_size)
+                .await?
+                .ok_or(Error::NotFound("user"))?;
+        }
+    }
+
+    find_user(&ctx, id).await.map(Json)
+}
+
+/// `DELETE /admin/users/{id}`
+///
+/// Soft-deletes an account: it is marked deleted, its sessions end and it
+/// can no longer log in or be looked up, but its row stays so audit events
+/// keep pointing at it, and its email address stays taken. It is still
+/// listed with `status=deleted`. Admins cannot delete themselves this way;
+/// they use `DELETE /me`.
+///
+/// Responds with `204 No Content`, `403 Forbidden` for the caller's own
+/// account or `404 Not Found`, also for users deleted before.
+async fn delete_user(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if id == admin.id() {
+        return Err(Error::Forbidden);
+    }
+
+    let user = find_user(&ctx, id).await?;
+
+    if !ctx.users().soft_delete(id).await? {
+        return Err(Error::NotFound("user"));
+    }
+
+    // Ended through the store so cached copies of the sessions go too.
+    ctx.sessions().delete_all(id).await?;
+
+    ctx.publish(Event::UserDeleted {
+        user_id: id,
+        email: user.user.email,
+        admin_id: admin.id(),
+        ip,
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeUsersRequest {
+    source_id: Uuid,
+    /// Moves the password, phone number, passkeys, linked identities and API
+    /// keys of the duplicate over too, so its owner keeps logging in the way they did.
+    #[serde(default)]
+    take_credentials: bool,
+}
+
+/// `POST /admin/users/{id}/merge`
+///
+/// Merges the duplicate account `source_id` into user `id` and deletes it,
+/// for cleaning up accounts registered twice under differently written
+/// email addresses; see [`PgUserStore::merge`] for what moves over. The
+/// sessions of the duplicate end, and its ways of logging in, including the
+/// phone number it logs in with through `/auth/phone/login`, are deleted
+/// unless `take_credentials` is set. Admins cannot merge their own account
+/// away.
+///
+/// Responds with `200 OK` and the merged user, `403 Forbidden` when
+/// `source_id` is the caller, `404 Not Found` when either user does not
+/// exist or is deleted, or `422 Unprocessable Entity` when both are the
+/// same or their metadata together is too large.
+async fn merge_users(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<MergeUsersRequest>,
+) -> Result<Json<AdminUserView>> {
+    let MergeUsersRequest {
+        source_id,
+        take_credentials,
+    } = payload;
+    if source_id == id {
+        return Err(Error::Validation(String::from(
+            "cannot merge a user into itself",
+        )));
+    }
+    if source_id == admin.id() {
+        return Err(Error::Forbidden);
+    }
+
+    let (Some(_), Some(source)) = (
+        ctx.users().find(id).await?,
+        ctx.users().find(source_id).await?,
+    ) else {
+        return Err(Error::NotFound("user"));
+    };
+
+    let max_metadata_size = ctx.config().auth().max_metadata_size();
+    let ended = ctx
+        .transaction(|tx| {
+            Box::pin(async move {
+                PgUserStore::merge(tx, id, source_id, take_credentials, max_metadata_size).await
+            })
+        })
+        .await?
+        .ok_or(Error::NotFound("user"))?;
+
+    // Sessions in Postgres ended with the merge; those of other stores end
+    // here, along with cached copies.
+    ctx.sessions().forget(&ended).await;
+    ctx.sessions().delete_all(source_id).await?;
+
+    ctx.publish(Event::UsersMerged {
+        user_id: id,
+        merged_user_id: source_id,
+        merged_email: source.email,
+        admin_id: admin.id(),
+        ip,
+    })
+    .await?;
+
+    find_user(&ctx, id).await.map(Json)
+}
+
+/// `POST /admin/users/{id}/force-password-reset`
+///
+/// Clears the user's password, ends their sessions, revokes their tokens and
+/// emails them a password reset link. They cannot log in with a password
+/// until they pick a new one.
+///
+/// Responds with `202 Accepted` or `404 Not Found`.
+async fn force_password_reset(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    AdminUser(admin): AdminUser,
+    locale: Locale,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let email: String = sqlx::query_scalar(
+        r"
+        UPDATE users
+        SET password_hash = NULL, updated_at = now()
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING email
+        ",
+    )
+    .bind(id)
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::NotFound("user"))?;
+
+    let ended = ctx.sessions().delete_all(id).await?;
+    ctx.revocations().revoke_all(id).await?;
+    ctx.publish(Event::SessionsRevoked {
+        user_id: id,
+        session_ids: ended,
+    })
+    .await?;
+    password_reset::send_reset_email(&ctx, id, &email, &locale).await?;
+
+    ctx.publish(Event::PasswordResetForced {
+        user_id: id,
+        admin_id: admin.id(),
+        ip,
+    })
+    .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// `POST /admin/users/{id}/revoke`
+///
+/// Revokes every access and refresh token issued to another user so far.
+///
+/// Responds with `204 No Content` or `404 Not Found`.
+async fn revoke_user_tokens(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    find_user(&ctx, user_id).await?;
+
+    ctx.revocations().revoke_all(user_id).await?;
+
+    tracing::info!(admin_id = %admin.id(), %user_id, "All tokens of user revoked by admin");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteRequest {
+    email: String,
+    /// Role of the new account; defaults to `user`.
+    role: Option<Role>,
+    /// Organization the new account joins on signup.
+    organization_id: Option<Uuid>,
+    /// Role in `organization_id`; defaults to `member`.
+    organization_role: Option<OrgRole>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignupInvite {
+    id: Uuid,
+    email: String,
+    role: Role,
+    organization_id: Option<Uuid>,
+    organization_role: Option<OrgRole>,
+    expires_at: DateTime<Utc>,
+}
+
+/// `POST /admin/invites`
+///
+/// Invites someone to create an account. The invitation is emailed and
+/// redeemed at `POST /auth/accept-invite`, which pre-approves the address
+/// and applies the role and organization membership chosen here. A new
+/// invitation replaces any pending one for the same address.
+///
+/// Responds with `201 Created`, `404 Not Found` for an unknown organization,
+/// `409 Conflict` if the address already has an account or
+/// `422 Unprocessable Entity` on invalid input.
+async fn create_invite(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    locale: Locale,
+    Json(payload): Json<CreateInviteRequest>,
+) -> Result<(StatusCode, Json<SignupInvite>)> {
+    let email = payload.email.trim();
+
+    if !is_valid_email(email) {
+        return Err(Error::Validation(String::from("email address is invalid")));
+    }
+
+    if payload.organization_role.is_some() && payload.organization_id.is_none() {
+        return Err(Error::Validation(String::from(
+            "organization_role requires organization_id",
+        )));
+    }
+
+    let taken: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE email_normalized = $1)")
+            .bind(ctx.normalize_email(email))
+            .fetch_one(ctx.db())
+            .await?;
+
+    if taken {
+        return Err(Error::EmailTaken);
+    }
+
+    let organization = match payload.organization_id {
+        Some(id) => Some(
+            sqlx::query_scalar::<_, String>("SELECT name FROM organizations WHERE id = $1")
+                .bind(id)
+                .fetch_optional(ctx.db())
+                .await?
+                .ok_or(Error::NotFound("organization"))?,
+        ),
+        None => None,
+    };
+
+    let role = payload.role.unwrap_or(Role::User);
+    let organization_role = payload
+        .organization_id
+        .map(|_| payload.organization_role.unwrap_or(OrgRole::Member));
+    let token = generate_token();
+    let now = Utc::now();
+    let ttl =
+        Duration::seconds(i64::try_from(ctx.config().auth().invite_ttl()).unwrap_or(i64::MAX));
+
+    sqlx::query("DELETE FROM signup_invites WHERE email = $1 AND accepted_at IS NULL")
+        .bind(email)
+        .execute(ctx.db())
+        .await?;
+
+    let id: Uuid = sqlx::query_scalar(
+        r"
+        INSERT INTO signup_invites
+            (email, role, organization_id, organization_role, token_hash, invited_by, created_at,
+             expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        ",
+    )
+    .bind(email)
+    .bind(role.name())
+    .bind(payload.organization_id)
+    .bind(organization_role.map(OrgRole::name))
+    .bind(hash_token(&token))
+    .bind(admin.id())
+    .bind(now)
+    .bind(now + ttl)
+    .fetch_one(ctx.db())
+    .await?;
+
+    let message = ctx.mail_templates().render(
+        EmailTemplate::Invite,
+        locale.language(),
+        email,
+        &json!({
+            "url": ctx.config().auth().invite_url().map(|url| format!("{url}?token={token}")),
+            "code": token,
+            "organization": organization,
+            "ttl_days": ttl.num_days(),
+        }),
+    )?;
+
+    ctx.jobs()
+        .enqueue(Job::SendEmail { email: message })
+        .await?;
+
+    tracing::info!(admin_id = %admin.id(), invite_id = %id, "Signup invitation created");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SignupInvite {
+            id,
+            email: email.to_owned(),
+            role,
+            organization_id: payload.organization_id,
+            organization_role,
+            expires_at: now + ttl,
+        }),
+    ))
+}
+
+/// `POST /admin/signing-keys/rotate`
+///
+/// Generates a new key to sign access and refresh tokens with, see
+/// [`crate::tokens::TokenService::rotate_key`]. The key is published at
+/// once and signs from two minutes on; tokens signed with earlier keys stay
+/// valid.
+///
+/// Responds with `201 Created` and the public half of the new key, or
+/// `404 Not Found` when `auth.algorithm` is HS256.
+async fn rotate_signing_key(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+) -> Result<(StatusCode, Json<Jwk>)> {
+    let jwk = ctx.tokens().rotate_key().await?;
+
+    tracing::info!(
+        admin_id = %admin.id(),
+        kid = jwk.common.key_id.as_deref().unwrap_or_default(),
+        "Token signing key rotated by admin"
+    );
+
+    Ok((StatusCode::CREATED, Json(jwk)))
+}
+
+pub(super) async fn find_user(ctx: &AppContext, id: Uuid) -> Result<AdminUserView> {
+    sqlx::query_as::<_, AdminUserView>(
+        r"
+        SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+               updated_at, deleted_at, user_metadata, app_metadata, disabled_at, locked_until
+        FROM users
+        WHERE id = $1
+        ",
+    )
+    .bind(id)
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::NotFound("user"))
+}
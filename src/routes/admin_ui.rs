@@ -0,0 +1,308 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
+use axum::{
+    Extension, Router,
+    extract::{Path, Query, State},
+    http::{Uri, header},
+    response::{Html, IntoResponse, Redirect},
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use tera::{Context, Tera};
+use url::form_urlencoded;
+use uuid::Uuid;
+
+use super::admin::{
+    ListAuditEventsQuery, ListUsersQuery, audit_events_page, find_user, users_page,
+};
+use crate::{
+    AppContext, Error, Result, auth::AdminUser, pagination::Pagination, security, sessions::Session,
+};
+
+/// Templates of the dashboard pages.
+const TEMPLATES: [(&str, &str); 5] = [
+    (
+        "layout.html",
+        include_str!("../../templates/admin/layout.html"),
+    ),
+    (
+        "users.html",
+        include_str!("../../templates/admin/users.html"),
+    ),
+    ("user.html", include_str!("../../templates/admin/user.html")),
+    (
+        "events.html",
+        include_str!("../../templates/admin/events.html"),
+    ),
+    (
+        "audit_events.html",
+        include_str!("../../templates/admin/audit_events.html"),
+    ),
+];
+
+/// Stylesheet and script of the dashboard, with their content types.
+const ASSETS: [(&str, &str, &str); 2] = [
+    (
+        "admin.css",
+        "text/css; charset=utf-8",
+        include_str!("../../assets/admin/admin.css"),
+    ),
+    (
+        "admin.js",
+        "text/javascript; charset=utf-8",
+        include_str!("../../assets/admin/admin.js"),
+    ),
+];
+
+/// Where the actions of the dashboard send their requests, under
+/// `server.base_path`.
+const API: &str = "/api/v1";
+
+static TERA: LazyLock<Tera> = LazyLock::new(|| {
+    let mut tera = Tera::default();
+    tera.add_raw_templates(TEMPLATES)
+        .unwrap_or_else(|err| panic!("invalid admin dashboard template: {err:?}"));
+    tera.register_filter("datetime", datetime);
+    tera
+});
+
+/// The pages of the admin dashboard, see [`super::admin_ui_router`].
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route(
+            "/admin/ui",
+            get(|State(ctx): State<Arc<AppContext>>| async move {
+                Redirect::to(&format!(
+                    "{}/admin/ui/users",
+                    ctx.config().server().base_path()
+                ))
+            }),
+        )
+        .route("/admin/ui/users", get(users))
+        .route("/admin/ui/users/{id}", get(user))
+        .route("/admin/ui/audit-events", get(audit_events))
+}
+
+/// The stylesheet and script of the dashboard, which hold nothing secret.
+pub fn assets() -> Router<Arc<AppContext>> {
+    Router::new().route("/admin/ui/assets/{file}", get(asset))
+}
+
+async fn asset(
+    State(ctx): State<Arc<AppContext>>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse> {
+    check_enabled(&ctx)?;
+
+    let (_, content_type, body) = ASSETS
+        .iter()
+        .find(|(name, _, _)| *name == file)
+        .ok_or(Error::NotFound("asset"))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, *content_type),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        *body,
+    ))
+}
+
+/// `GET /admin/ui/users`
+///
+/// Users, newest first, filtered as `GET /admin/users` filters them.
+async fn users(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    session: Option<Extension<Session>>,
+    pagination: Pagination,
+    uri: Uri,
+) -> Result<Html<String>> {
+    check_enabled(&ctx)?;
+
+    let query: ListUsersQuery = filters(&uri)?;
+    let page = users_page(&ctx, &pagination, &query).await?;
+
+    let mut context = Context::new();
+    context.insert(
+        "filters",
+        &filter_values(&uri, &["email", "status", "role"]),
+    );
+    context.insert(
+        "next_url",
+        &next_url(&ctx, &uri, page.next_cursor.as_deref()),
+    );
+    context.insert("page", &page);
+
+    render(&ctx, "users.html", &admin.user().email, session, context)
+}
+
+/// `GET /admin/ui/users/{id}`
+///
+/// A user with their active sessions and latest audit events, and buttons
+/// disabling or enabling the account, forcing a password reset and revoking
+/// their tokens through the admin API.
+async fn user(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    session: Option<Extension<Session>>,
+    Path(id): Path<Uuid>,
+) -> Result<Html<String>> {
+    check_enabled(&ctx)?;
+
+    let user = find_user(&ctx, id).await?;
+    let sessions = ctx.sessions().list(id).await?;
+    let events = audit_events_page(
+        &ctx,
+        &Pagination::default(),
+        &ListAuditEventsQuery::for_user(id),
+    )
+    .await?;
+
+    let mut context = Context::new();
+    context.insert("user", &user);
+    context.insert("sessions", &sessions);
+    context.insert("events", &events);
+
+    render(&ctx, "user.html", &admin.user().email, session, context)
+}
+
+/// `GET /admin/ui/audit-events`
+///
+/// Audit events, newest first, filtered as `GET /admin/audit-events`
+/// filters them.
+async fn audit_events(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    session: Option<Extension<Session>>,
+    pagination: Pagination,
+    uri: Uri,
+) -> Result<Html<String>> {
+    check_enabled(&ctx)?;
+
+    let query: ListAuditEventsQuery = filters(&uri)?;
+    let events = audit_events_page(&ctx, &pagination, &query).await?;
+
+    let mut context = Context::new();
+    context.insert("filters", &filter_values(&uri, &["user_id", "action"]));
+    context.insert(
+        "next_url",
+        &next_url(&ctx, &uri, events.next_cursor.as_deref()),
+    );
+    context.insert("events", &events);
+
+    render(
+        &ctx,
+        "audit_events.html",
+        &admin.user().email,
+        session,
+        context,
+    )
+}
+
+/// [`Error::Disabled`] unless `features.admin_ui` is on.
+fn check_enabled(ctx: &AppContext) -> Result<()> {
+    if ctx.config().features().admin_ui() {
+        Ok(())
+    } else {
+        Err(Error::Disabled("admin dashboard"))
+    }
+}
+
+/// Renders `template` with `context` and the variables of the layout.
+fn render(
+    ctx: &AppContext,
+    template: &str,
+    admin_email: &str,
+    session: Option<Extension<Session>>,
+    mut context: Context,
+) -> Result<Html<String>> {
+    let config = ctx.config();
+    let csrf_token = session
+        .map(|Extension(session)| security::csrf_token(config.auth().secret().expose(), session.id))
+        .unwrap_or_default();
+
+    context.insert("product_name", config.mailer().product_name());
+    context.insert("admin_email", admin_email);
+    context.insert("csrf_token", &csrf_token);
+    let base = config.server().base_path();
+    context.insert("base", base);
+    context.insert("api", &format!("{base}{API}"));
+
+    TERA.render(template, &context)
+        .map(Html)
+        .map_err(Error::Template)
+}
+
+/// The query string of `uri` without the fields the filter form left empty.
+fn non_empty_params(uri: &Uri) -> Vec<(String, String)> {
+    form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes())
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect()
+}
+
+/// The filters of a listing, from the query string of `uri`.
+fn filters<T: DeserializeOwned>(uri: &Uri) -> Result<T> {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(non_empty_params(uri))
+        .finish();
+    let uri: Uri = format!("/?{query}")
+        .parse()
+        .map_err(|_| Error::Validation(String::from("query string is invalid")))?;
+
+    Query::try_from_uri(&uri)
+        .map(|Query(filters)| filters)
+        .map_err(|rejection| Error::Validation(rejection.body_text()))
+}
+
+/// The values of the filter form fields `names`, empty when not given.
+fn filter_values(uri: &Uri, names: &[&str]) -> Map<String, Value> {
+    let params: HashMap<String, String> = non_empty_params(uri).into_iter().collect();
+
+    names
+        .iter()
+        .map(|name| {
+            let value = params.get(*name).cloned().unwrap_or_default();
+            ((*name).to_owned(), Value::String(value))
+        })
+        .collect()
+}
+
+/// The page after the one at `uri`, continuing at `cursor` with the same
+/// filters, if there is one.
+fn next_url(ctx: &AppContext, uri: &Uri, cursor: Option<&str>) -> Option<String> {
+    let cursor = cursor?;
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(
+            non_empty_params(uri)
+                .into_iter()
+                .filter(|(name, _)| name != "cursor" && name != "page"),
+        )
+        .append_pair("cursor", cursor)
+        .finish();
+
+    // Mounted under `server.base_path`, the routes see their path without it.
+    Some(format!(
+        "{}{}?{query}",
+        ctx.config().server().base_path(),
+        uri.path()
+    ))
+}
+
+/// Tera filter formatting an RFC 3339 timestamp to the minute, in UTC.
+fn datetime(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(match value.as_str().map(DateTime::parse_from_rfc3339) {
+        Some(Ok(at)) => Value::String(
+            at.with_timezone(&Utc)
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        ),
+        _ => value.clone(),
+    })
+}
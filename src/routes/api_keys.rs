@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    api_keys::ApiKey,
+    auth::{AuthUser, RecentlyAuthenticated, is_grantable_scope},
+    oidc,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/api-keys/{id}", delete(revoke_api_key))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    name: String,
+    /// Scopes the key is limited to; omit for a key that may do anything the
+    /// caller may.
+    scopes: Option<Vec<String>>,
+    /// Days until the key stops working; omit for a key that never expires.
+    expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    /// The full key. It is only ever returned here.
+    key: String,
+}
+
+/// `POST /auth/api-keys`
+///
+/// Issues a new API key for the caller. The key is returned once in the
+/// response and only its hash is stored.
+///
+/// Responds with `201 Created`, `403 Forbidden` when called with an API key
+/// (keys cannot mint further keys) or without having authenticated recently,
+/// see [`RecentlyAuthenticated`], or `422 Unprocessable Entity` on invalid
+/// input, including built-in scopes the caller's role may not be granted.
+async fn create_api_key(
+    State(ctx): State<Arc<AppContext>>,
+    RecentlyAuthenticated(user): RecentlyAuthenticated,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKey>)> {
+    let name = payload.name.trim();
+
+    if name.is_empty() || name.len() > 255 {
+        return Err(Error::Validation(String::from(
+            "name must be between 1 and 255 characters",
+        )));
+    }
+
+    if let Some(scopes) = &payload.scopes {
+        if scopes.is_empty() {
+            return Err(Error::Validation(String::from(
+                "scopes must not be empty, omit them for an unrestricted key",
+            )));
+        }
+
+        if let Some(scope) = scopes.iter().find(|scope| !oidc::is_valid_scope(scope)) {
+            return Err(Error::Validation(format!("scope {scope:?} is invalid")));
+        }
+
+        if let Some(scope) = scopes
+            .iter()
+            .find(|scope| !is_grantable_scope(scope, user.user().role))
+        {
+            return Err(Error::Validation(format!(
+                "scope {scope:?} is not available to your role"
+            )));
+        }
+    }
+
+    let expires_at: Option<DateTime<Utc>> = payload
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(i64::from(days)));
+
+    let (api_key, key) = ctx
+        .api_keys()
+        .create(user.id(), name, payload.scopes.as_deref(), expires_at)
+        .await?;
+
+    tracing::info!(user_id = %user.id(), api_key_id = %api_key.id, "API key created");
+
+    Ok((StatusCode::CREATED, Json(CreatedApiKey { api_key, key })))
+}
+
+/// `GET /auth/api-keys`
+///
+/// Lists the caller's active API keys, newest first. Only the display prefix
+/// of each key is included.
+async fn list_api_keys(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+) -> Result<Json<Vec<ApiKey>>> {
+    Ok(Json(ctx.api_keys().list(user.id()).await?))
+}
+
+/// `DELETE /auth/api-keys/{id}`
+///
+/// Revokes one of the caller's API keys; it stops working immediately.
+/// Responds with `204 No Content`, or `401 Unauthorized` if no such active
+/// key belongs to the caller.
+async fn revoke_api_key(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !ctx.api_keys().revoke(user.id(), id).await? {
+        return Err(Error::InvalidToken);
+    }
+
+    tracing::info!(user_id = %user.id(), api_key_id = %id, "API key revoked");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,710 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    extract::State,
+    http::StatusCode,
+    routing::{delete, get, post},
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::email_verification;
+use crate::{
+    AppContext, Error, Result,
+    auth::{self, AuthUser, PasswordWarning, RebindingUser, is_valid_email},
+    events::{Event, LoginMethod},
+    i18n::Locale,
+    models::{NewUser, User},
+    organizations::AuthPolicy,
+    reports::failure_reason,
+    repositories::PgUserStore,
+    security::{self, Captcha, LoginThrottle},
+    sessions::{DeviceInfo, Session},
+    tokens::{TokenKind, TokenPair},
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/csrf", get(csrf_token))
+        .route("/anonymous", post(start_anonymous))
+        .route("/register", post(register))
+        .route("/accept-invite", post(accept_invite))
+        .route("/login", post(login))
+        .route("/reauthenticate", post(reauthenticate))
+        .route("/refresh", post(refresh))
+        .route("/revoke", post(revoke))
+        .route("/logout", delete(logout))
+        .route("/logout-all", post(logout_all))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    email: String,
+    password: String,
+    name: Option<String>,
+    /// Only accepted when `auth.usernames` is enabled.
+    username: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsrfToken {
+    token: String,
+}
+
+/// `GET /auth/csrf`
+///
+/// Returns the CSRF token of the session cookie sent with the request. Browser
+/// clients send it back in the `X-CSRF-Token` header on every state-changing
+/// request; it stays the same for the life of the session.
+///
+/// Responds with `401 Unauthorized` without a session cookie.
+async fn csrf_token(
+    State(ctx): State<Arc<AppContext>>,
+    session: Option<Extension<Session>>,
+) -> Result<Json<CsrfToken>> {
+    let Extension(session) = session.ok_or(Error::Unauthenticated)?;
+
+    Ok(Json(CsrfToken {
+        token: security::csrf_token(ctx.config().auth().secret().expose(), session.id),
+    }))
+}
+
+/// `POST /auth/anonymous`
+///
+/// Starts a guest session for a visitor without an account and sets its
+/// cookie. The session authenticates no one, but registering while holding it
+/// upgrades it to the new account. Only available when
+/// `auth.anonymous_sessions` is enabled.
+///
+/// Responds with `201 Created` and the session, or `404 Not Found` when
+/// anonymous sessions are disabled.
+async fn start_anonymous(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    jar: CookieJar,
+) -> Result<(StatusCode, CookieJar, Json<Session>)> {
+    if !ctx.config().auth().anonymous_sessions() {
+        return Err(Error::Disabled("anonymous sessions"));
+    }
+
+    let (session, token) = ctx.sessions().create_anonymous(&device).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(session_id = %session.id, "Anonymous session started");
+
+    Ok((StatusCode::CREATED, jar.add(cookie), Json(session)))
+}
+
+/// `POST /auth/register`
+///
+/// Creates a new account from an email and password. The password is hashed
+/// with Argon2id before it is stored; the plain text never touches the
+/// database. A verification link is emailed to the new address.
+///
+/// When called with an anonymous session cookie, that session is upgraded to
+/// the new account in place: it keeps its id and token, so the client stays
+/// logged in and anything tied to the session carries over.
+///
+/// With `auth.invite_only` enabled, accounts can only be created through
+/// `POST /auth/accept-invite` and this endpoint answers `404 Not Found`, as
+/// it does with `features.registration` disabled.
+///
+/// Responds with `201 Created` and the new user, `403 Forbidden` without a
+/// valid CAPTCHA token when CAPTCHAs are configured, `409 Conflict` if the
+/// email is already registered, compared as `auth.email_normalization`
+/// folds it, or the username taken, or `422 Unprocessable Entity` on invalid
+/// input, including a username while `auth.usernames` is disabled.
+async fn register(
+    State(ctx): State<Arc<AppContext>>,
+    _: Captcha,
+    session: Option<Extension<Session>>,
+    locale: Locale,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<(StatusCode, PasswordWarning, Json<User>)> {
+    if !ctx.config().features().registration() {
+        return Err(Error::Disabled("registration"));
+    }
+
+    if ctx.config().auth().invite_only() {
+        return Err(Error::Disabled("open registration"));
+    }
+
+    let email = payload.email.trim();
+
+    if !is_valid_email(email) {
+        return Err(Error::Validation(String::from("email address is invalid")));
+    }
+
+    let username = payload
+        .username
+        .as_deref()
+        .map(str::trim)
+        .filter(|username| !username.is_empty());
+
+    if let Some(username) = username {
+        auth::validate_username(ctx.config().auth().usernames(), username)?;
+
+        if ctx.users().find_by_username(username).await?.is_some() {
+            return Err(Error::UsernameTaken);
+        }
+    }
+
+    auth::validate_password(ctx.config().password_policy(), &payload.password)?;
+    let warning = ctx
+        .pwned_passwords()
+        .check(ctx.config().password_policy(), &payload.password)
+        .await?;
+
+    let password_hash = ctx.hashing().hash(&payload.password).await?;
+
+    let user = ctx
+        .users()
+        .create(&NewUser {
+            name: payload.name.as_deref().map(str::trim),
+            username,
+            password_hash: Some(&password_hash),
+            ..NewUser::new(email, ctx.config().auth().email_normalization())
+        })
+        .await?
+        .ok_or(Error::EmailTaken)?;
+
+    tracing::info!(user_id = %user.id, "User registered");
+
+    ctx.publish(Event::UserCreated {
+        user_id: user.id,
+        email: user.email.clone(),
+    })
+    .await?;
+
+    if let Some(Extension(session)) = session
+        && session.user_id.is_none()
+        && ctx.sessions().upgrade(session.id, user.id).await?.is_some()
+    {
+        tracing::info!(user_id = %user.id, session_id = %session.id, "Anonymous session upgraded");
+    }
+
+    email_verification::send_verification_email(&ctx, user.id, &user.email, &locale).await?;
+
+    Ok((StatusCode::CREATED, warning, Json(user)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInviteRequest {
+    token: String,
+    password: String,
+    name: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AcceptedInvite {
+    email: String,
+    role: String,
+    organization_id: Option<Uuid>,
+    organization_role: Option<String>,
+}
+
+/// `POST /auth/accept-invite`
+///
+/// Creates the account an admin invited through `POST /admin/invites` and
+/// logs it in. The email address is taken from the invitation and counts as
+/// verified; the invitation's role and organization membership are applied.
+/// Each invitation can be accepted once.
+///
+/// Responds with `201 Created`, the session cookie and a token pair,
+/// `401 Unauthorized` if the token is unknown, expired or used,
+/// `409 Conflict` if the address was registered in the meantime or
+/// `422 Unprocessable Entity` on a weak password.
+async fn accept_invite(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Json(payload): Json<AcceptInviteRequest>,
+) -> Result<(StatusCode, PasswordWarning, CookieJar, Json<TokenPair>)> {
+    // The invitation may add the user to an organization asking for more.
+    let organization_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT organization_id FROM signup_invites WHERE token_hash = $1")
+            .bind(auth::hash_token(&payload.token))
+            .fetch_optional(ctx.db())
+            .await?
+            .flatten();
+    let mut passwords = ctx.config().password_policy().clone();
+    if let Some(organization_id) = organization_id
+        && let Some(policy) = AuthPolicy::for_organization(ctx.db(), organization_id).await?
+    {
+        passwords = passwords.tightened(&policy.password);
+    }
+    auth::validate_password(&passwords, &payload.password)?;
+    let warning = ctx
+        .pwned_passwords()
+        .check(&passwords, &payload.password)
+        .await?;
+
+    let password_hash = ctx.hashing().hash(&payload.password).await?;
+    let now = Utc::now();
+    let config = ctx.config();
+
+    let (invite, user_id) = ctx
+        .transaction(|tx| {
+            Box::pin(async move {
+                let invite = sqlx::query_as::<_, AcceptedInvite>(
+                    r"
+                    UPDATE signup_invites
+                    SET accepted_at = $2
+                    WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > $2
+                    RETURNING email, role, organization_id, organization_role
+                    ",
+                )
+                .bind(auth::hash_token(&payload.token))
+                .bind(now)
+                .fetch_optional(&mut **tx)
+                .await?
+                .ok_or(Error::InvalidToken)?;
+
+                let user_id = PgUserStore::insert(
+                    &mut **tx,
+                    &NewUser {
+                        name: payload.name.as_deref().map(str::trim),
+                        password_hash: Some(&password_hash),
+                        role: invite.role.parse()?,
+                        verified_at: Some(now),
+                        ..NewUser::new(&invite.email, config.auth().email_normalization())
+                    },
+                )
+                .await?
+                .ok_or(Error::EmailTaken)?
+                .id;
+
+                if let (Some(organization_id), Some(role)) =
+                    (invite.organization_id, &invite.organization_role)
+                {
+                    sqlx::query(
+                        r"
+                        INSERT INTO memberships (organization_id, user_id, role, created_at)
+                        VALUES ($1, $2, $3, $4)
+                        ",
+                    )
+                    .bind(organization_id)
+                    .bind(user_id)
+                    .bind(role)
+                    .bind(now)
+                    .execute(&mut **tx)
+                    .await?;
+                }
+
+                Ok((invite, user_id))
+            })
+        })
+        .await?;
+
+    tracing::info!(%user_id, "User registered from invitation");
+
+    let (session, token) = ctx
+        .start_session(user_id, &device, &LoginMethod::Invite)
+        .await?;
+    let tokens = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    ctx.publish(Event::UserCreated {
+        user_id,
+        email: invite.email.clone(),
+    })
+    .await?;
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::Invite,
+    })
+    .await?;
+
+    Ok((StatusCode::CREATED, warning, jar.add(cookie), Json(tokens)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    /// Email address, or username when `auth.usernames` is enabled.
+    #[serde(alias = "username")]
+    email: String,
+    password: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct LoginCredentials {
+    id: Uuid,
+    password_hash: Option<String>,
+    verified_at: Option<DateTime<Utc>>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// `POST /auth/login`
+///
+/// Verifies the email and password and starts a server-side session. With
+/// `auth.usernames` enabled, the `email` field may carry a username instead,
+/// also accepted as `username`; anything without an `@` is taken as one. The
+/// session token is set as an `HttpOnly` cookie for browser clients, and an
+/// access/refresh token pair bound to the same session is returned for API
+/// clients, which send the access token as `Authorization: Bearer <token>`.
+///
+/// Unknown emails, accounts without a password and wrong passwords all
+/// produce the same `401 Unauthorized` response, and unknown emails still
+/// run a full hash verification so timing does not reveal which accounts
+/// exist.
+///
+/// When `auth.require_email_verification` is enabled, users with the right
+/// password but an unverified email get `403 Forbidden`.
+///
+/// Repeated failures lock the account, and flood the client IP, for the
+/// periods set in the `security` config section; both answer
+/// `429 Too Many Requests` until they clear. Before that, failures for the
+/// same email or username from the same IP make the next attempt wait, ever
+/// longer, answering `429 Too Many Requests` with `Retry-After` when made
+/// too early.
+///
+/// When CAPTCHAs are configured, requests without a valid token get
+/// `403 Forbidden` before the credentials are looked at.
+///
+/// A correct password whose stored hash is bcrypt, or Argon2 with other
+/// parameters than `password_hashing`, is rehashed on the way in.
+///
+/// Refused attempts are counted in the `login_failure_total` metric, by
+/// reason, so spikes of them can be alerted on, and recorded for
+/// `GET /admin/reports/logins`.
+async fn login(
+    State(ctx): State<Arc<AppContext>>,
+    captcha: Result<Captcha>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Json(payload): Json<LoginRequest>,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let outcome = match captcha {
+        Ok(_) => password_login(&ctx, &device, jar, payload).await,
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = &outcome {
+        metrics::counter!("login_failure_total", "reason" => failure_reason(err)).increment(1);
+    }
+
+    ctx.reports()
+        .record_outcome(&LoginMethod::Password, &device.device, &outcome)
+        .await;
+
+    outcome
+}
+
+async fn password_login(
+    ctx: &AppContext,
+    device: &DeviceInfo,
+    jar: CookieJar,
+    payload: LoginRequest,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let ip = device.ip;
+    let by_username = ctx.config().auth().usernames().enabled() && !payload.email.contains('@');
+    let identifier = if by_username {
+        payload.email.trim().to_lowercase()
+    } else {
+        ctx.normalize_email(&payload.email)
+    };
+
+    let throttle = ctx.login_throttle();
+    throttle.check(&identifier, ip).await?;
+
+    let credentials = if by_username {
+        sqlx::query_as::<_, LoginCredentials>(
+            r"
+            SELECT id, password_hash, verified_at, locked_until
+            FROM users
+            WHERE lower(username) = lower($1) AND deleted_at IS NULL
+            ",
+        )
+        .bind(&identifier)
+        .fetch_optional(ctx.db())
+        .await?
+    } else {
+        sqlx::query_as::<_, LoginCredentials>(
+            r"
+            SELECT id, password_hash, verified_at, locked_until
+            FROM users
+            WHERE email_normalized = $1 AND deleted_at IS NULL
+            ",
+        )
+        .bind(&identifier)
+        .fetch_optional(ctx.db())
+        .await?
+    };
+
+    let Some(credentials) = credentials else {
+        ctx.hashing().verify_dummy(&payload.password).await;
+        throttle.record_failure(&identifier, ip, None).await?;
+        return Err(Error::InvalidCredentials);
+    };
+
+    LoginThrottle::check_account(credentials.locked_until)?;
+
+    let user_id = credentials.id;
+    let password_ok = match &credentials.password_hash {
+        Some(hash) => ctx.hashing().verify(&payload.password, hash).await?,
+        None => false,
+    };
+
+    if !password_ok {
+        throttle
+            .record_failure(&identifier, ip, Some(user_id))
+            .await?;
+        return Err(Error::InvalidCredentials);
+    }
+
+    throttle.record_success(&identifier, ip, user_id).await?;
+
+    if let Some(hash) = &credentials.password_hash
+        && ctx.passwords().needs_rehash(hash)
+    {
+        let password_hash = ctx.hashing().hash(&payload.password).await?;
+
+        ctx.users()
+            .set_password_hash(user_id, Some(&password_hash))
+            .await?;
+
+        tracing::info!(%user_id, "Rehashed password with current parameters");
+    }
+
+    let verified_at = credentials.verified_at;
+
+    if verified_at.is_none() && ctx.config().auth().require_email_verification() {
+        return Err(Error::EmailNotVerified);
+    }
+
+    // Passwords set before an organization tightened its rules have to be
+    // reset before they can be used again.
+    let policy = AuthPolicy::for_user(ctx.db(), user_id).await?;
+    if !policy.password.is_empty() {
+        let passwords = ctx.config().password_policy().tightened(&policy.password);
+        if auth::validate_password(&passwords, &payload.password).is_err() {
+            return Err(Error::PasswordChangeRequired);
+        }
+    }
+
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::Password)
+        .await?;
+    let tokens = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(%user_id, session_id = %session.id, "User logged in");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::Password,
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(tokens)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReauthenticateRequest {
+    password: String,
+}
+
+/// `POST /auth/reauthenticate`
+///
+/// Confirms the caller's password again, so that for the next
+/// `auth.reauthentication_window` their session may perform sensitive
+/// operations, see [`auth::RecentlyAuthenticated`]. Users without a password
+/// log in again instead, which starts a recently authenticated session.
+///
+/// The session is bound to the client it reauthenticates from, which is how
+/// a session turned away by `security.session_binding` under
+/// `on_change: reauthenticate` gets back to work.
+///
+/// Responds with the updated session, `401 Unauthorized` if the password
+/// is wrong or the account has none, `403 Forbidden` when called with an API
+/// key, or `429 Too Many Requests` while the account or client IP is locked
+/// out, failures counting towards the same limits as logins.
+async fn reauthenticate(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    RebindingUser(user): RebindingUser,
+    Json(payload): Json<ReauthenticateRequest>,
+) -> Result<Json<Session>> {
+    let session_id = user.session_id().ok_or(Error::Forbidden)?;
+    let identifier = user.id().to_string();
+    let throttle = ctx.login_throttle();
+    throttle.check(&identifier, device.ip).await?;
+
+    let credentials = sqlx::query_as::<_, LoginCredentials>(
+        "SELECT id, password_hash, verified_at, locked_until FROM users WHERE id = $1",
+    )
+    .bind(user.id())
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::Unauthenticated)?;
+
+    LoginThrottle::check_account(credentials.locked_until)?;
+
+    let password_ok = match &credentials.password_hash {
+        Some(hash) => ctx.hashing().verify(&payload.password, hash).await?,
+        None => false,
+    };
+
+    if !password_ok {
+        throttle
+            .record_failure(&identifier, device.ip, Some(user.id()))
+            .await?;
+        return Err(Error::InvalidCredentials);
+    }
+
+    throttle
+        .record_success(&identifier, device.ip, user.id())
+        .await?;
+
+    let session = ctx
+        .sessions()
+        .reauthenticate(session_id, &device)
+        .await?
+        .ok_or(Error::Unauthenticated)?;
+
+    tracing::info!(user_id = %user.id(), %session_id, "User reauthenticated");
+
+    Ok(Json(session))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+/// `POST /auth/refresh`
+///
+/// Exchanges a valid refresh token for a new access/refresh pair. The
+/// refresh token is revoked in the exchange, so each one is used once. Fails
+/// with `401 Unauthorized` if the token is invalid, expired, revoked, an
+/// access token, or its session has ended.
+async fn refresh(
+    State(ctx): State<Arc<AppContext>>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<TokenPair>> {
+    let claims = ctx
+        .tokens()
+        .verify(&payload.refresh_token, TokenKind::Refresh)?;
+
+    if ctx.revocations().is_revoked(&claims).await? {
+        return Err(Error::InvalidToken);
+    }
+
+    let session = ctx
+        .sessions()
+        .find(claims.sid)
+        .await?
+        .ok_or(Error::InvalidToken)?;
+
+    ctx.revocations().revoke(&claims).await?;
+    ctx.sessions().touch(&session).await?;
+
+    Ok(Json(ctx.issue_tokens(&session).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    /// A single access or refresh token to revoke.
+    token: Option<String>,
+    /// Revoke every token issued to the caller so far instead.
+    #[serde(default)]
+    all: bool,
+}
+
+/// `POST /auth/revoke`
+///
+/// Revokes one of the caller's access or refresh tokens, or with
+/// `{"all": true}` every token issued to them so far. Revoked tokens are
+/// rejected immediately, even before they expire. Sessions are not ended;
+/// use `DELETE /auth/logout` for that.
+///
+/// Responds with `204 No Content`, `401 Unauthorized` if `token` is invalid
+/// or expired, `403 Forbidden` if it belongs to another user, or
+/// `422 Unprocessable Entity` if neither `token` nor `all` is given.
+async fn revoke(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<RevokeRequest>,
+) -> Result<StatusCode> {
+    if payload.all {
+        ctx.revocations().revoke_all(user.id()).await?;
+
+        tracing::info!(user_id = %user.id(), "All tokens revoked");
+
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    let token = payload
+        .token
+        .ok_or_else(|| Error::Validation(String::from("either token or all is required")))?;
+    let claims = ctx.tokens().decode(&token)?;
+
+    if claims.sub != user.id() {
+        return Err(Error::Forbidden);
+    }
+
+    ctx.revocations().revoke(&claims).await?;
+
+    tracing::info!(user_id = %user.id(), jti = %claims.jti, "Token revoked");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /auth/logout`
+///
+/// Ends the caller's current session and clears the session cookie. Refresh
+/// tokens issued for the session stop working immediately. Requests
+/// authenticated with an API key have no session and get `403 Forbidden`.
+async fn logout(
+    State(ctx): State<Arc<AppContext>>,
+    jar: CookieJar,
+    user: AuthUser,
+) -> Result<(StatusCode, CookieJar)> {
+    let session_id = user.session_id().ok_or(Error::Forbidden)?;
+
+    ctx.sessions().delete(session_id).await?;
+
+    tracing::info!(user_id = %user.id(), %session_id, "User logged out");
+
+    ctx.publish(Event::SessionsRevoked {
+        user_id: user.id(),
+        session_ids: vec![session_id],
+    })
+    .await?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        jar.remove(ctx.session_cookies().removal()),
+    ))
+}
+
+/// `POST /auth/logout-all`
+///
+/// Ends every session of the caller on all devices and revokes every access
+/// and refresh token issued to them, then clears the session cookie.
+async fn logout_all(
+    State(ctx): State<Arc<AppContext>>,
+    jar: CookieJar,
+    user: AuthUser,
+) -> Result<(StatusCode, CookieJar)> {
+    let ended = ctx.sessions().delete_all(user.id()).await?;
+    ctx.revocations().revoke_all(user.id()).await?;
+
+    tracing::info!(user_id = %user.id(), sessions = ended.len(), "User logged out everywhere");
+
+    ctx.publish(Event::SessionsRevoked {
+        user_id: user.id(),
+        session_ids: ended,
+    })
+    .await?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        jar.remove(ctx.session_cookies().removal()),
+    ))
+}
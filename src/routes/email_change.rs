@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{AuthUser, generate_token, hash_token, is_valid_email},
+    events::Event,
+    jobs::Job,
+    mail::Email,
+    models::User,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/me/email", post(request_email_change))
+        .route("/me/email/confirm", get(confirm_email_change))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailChangeRequest {
+    email: String,
+    /// Required when the account has a password.
+    current_password: Option<String>,
+}
+
+/// `POST /me/email`
+///
+/// Starts changing the caller's email address. A confirmation link is sent
+/// to the new address; the change only takes effect once it is followed.
+///
+/// Responds with `202 Accepted`, `401 Unauthorized` if the current password
+/// is wrong or missing, `403 Forbidden` when called with an API key,
+/// `409 Conflict` if the new address belongs to another account or
+/// `422 Unprocessable Entity` on invalid input.
+async fn request_email_change(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<EmailChangeRequest>,
+) -> Result<StatusCode> {
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    let new_email = payload.email.trim();
+
+    if !is_valid_email(new_email) {
+        return Err(Error::Validation(String::from("email address is invalid")));
+    }
+
+    let account = ctx
+        .users()
+        .find(user.id())
+        .await?
+        .ok_or(Error::Unauthenticated)?;
+
+    if let Some(hash) = &account.password_hash {
+        let password = payload
+            .current_password
+            .as_deref()
+            .ok_or(Error::InvalidCredentials)?;
+
+        if !ctx.hashing().verify(password, hash).await? {
+            return Err(Error::InvalidCredentials);
+        }
+    }
+
+    if account.email == new_email {
+        return Err(Error::Validation(String::from(
+            "new email address is the same as the current one",
+        )));
+    }
+
+    let taken: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM users WHERE email_normalized = $1 AND id <> $2)",
+    )
+    .bind(ctx.normalize_email(new_email))
+    .bind(user.id())
+    .fetch_one(ctx.db())
+    .await?;
+
+    if taken {
+        return Err(Error::EmailTaken);
+    }
+
+    let token = generate_token();
+    let now = Utc::now();
+    let ttl = Duration::seconds(
+        i64::try_from(ctx.config().auth().email_verification_ttl()).unwrap_or(i64::MAX),
+    );
+
+    // A new request supersedes any change requested earlier.
+    sqlx::query("DELETE FROM email_change_requests WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user.id())
+        .execute(ctx.db())
+        .await?;
+
+    sqlx::query(
+        r"
+        INSERT INTO email_change_requests (user_id, new_email, token_hash, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ",
+    )
+    .bind(user.id())
+    .bind(new_email)
+    .bind(hash_token(&token))
+    .bind(now)
+    .bind(now + ttl)
+    .execute(ctx.db())
+    .await?;
+
+    let email = Email {
+        to: new_email.to_owned(),
+        subject: String::from("Confirm your new email address"),
+        body: format!(
+            "Please confirm that you want to use this address for your account by \
+             opening the link below.\n\n\
+             {}/me/email/confirm?token={token}\n\n\
+             The link expires in {} hours. If you did not ask for this, you can \
+             ignore this email.",
+            ctx.config().server().url(),
+            ttl.num_hours()
+        ),
+        html: None,
+    };
+
+    ctx.jobs().enqueue(Job::SendEmail { email }).await?;
+
+    tracing::info!(user_id = %user.id(), "Email change requested");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmEmailChangeQuery {
+    token: String,
+}
+
+/// `GET /me/email/confirm?token=...`
+///
+/// Completes an email change. Following the link proves ownership of the new
+/// address, so it is marked verified. When `auth.notify_email_change` is on,
+/// the previous address is told about the change.
+///
+/// Responds with the updated profile, `401 Unauthorized` for an unknown,
+/// expired or already used token, or `409 Conflict` if the address was
+/// registered by another account in the meantime.
+async fn confirm_email_change(
+    State(ctx): State<Arc<AppContext>>,
+    Query(query): Query<ConfirmEmailChangeQuery>,
+) -> Result<Json<User>> {
+    let mut tx = ctx.db().begin().await?;
+
+    let (user_id, new_email) = sqlx::query_as::<_, (Uuid, String)>(
+        r"
+        UPDATE email_change_requests
+        SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id, new_email
+        ",
+    )
+    .bind(hash_token(&query.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    let old_email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let user = sqlx::query_as::<_, User>(
+        r"
+        UPDATE users
+        SET email = $2, email_normalized = $3, verified_at = now(), updated_at = now()
+        WHERE id = $1
+        RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                  updated_at, deleted_at, user_metadata, app_metadata
+        ",
+    )
+    .bind(user_id)
+    .bind(&new_email)
+    .bind(ctx.normalize_email(&new_email))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(db) if db.is_unique_violation() => Error::EmailTaken,
+        other => other.into(),
+    })?;
+
+    tx.commit().await?;
+
+    tracing::info!(%user_id, "Email changed");
+
+    ctx.publish(Event::EmailChanged {
+        user_id,
+        old_email,
+        new_email,
+    })
+    .await?;
+
+    Ok(Json(user))
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    events::{Event, LoginMethod},
+    i18n::Locale,
+    jobs::Job,
+    mail::EmailTemplate,
+    security::{Captcha, LoginThrottle},
+    sessions::DeviceInfo,
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/email-otp/send", post(send_code))
+        .route("/email-otp/verify", post(verify_code))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SendCodeRequest {
+    email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyCodeRequest {
+    email: String,
+    code: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct Account {
+    id: Uuid,
+    email: String,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+async fn find_account(ctx: &AppContext, email: &str) -> Result<Option<Account>> {
+    Ok(sqlx::query_as::<_, Account>(
+        r"
+        SELECT id, email, locked_until
+        FROM users
+        WHERE email_normalized = $1 AND deleted_at IS NULL
+        ",
+    )
+    .bind(ctx.normalize_email(email))
+    .fetch_optional(ctx.db())
+    .await?)
+}
+
+/// `POST /auth/email-otp/send`
+///
+/// Emails a six-digit login code if an account with the given email exists,
+/// to be redeemed at `POST /auth/email-otp/verify`. Always answers
+/// `202 Accepted` so the endpoint cannot be used to discover registered
+/// emails; for the same reason, requests over the per-address send limits
+/// of `email_otp` are dropped silently.
+///
+/// When CAPTCHAs are configured, requests without a valid token get
+/// `403 Forbidden`. Answers `404 Not Found` with `features.email_otp`
+/// disabled.
+async fn send_code(
+    State(ctx): State<Arc<AppContext>>,
+    _: Captcha,
+    locale: Locale,
+    Json(payload): Json<SendCodeRequest>,
+) -> Result<StatusCode> {
+    if !ctx.config().features().email_otp() {
+        return Err(Error::Disabled("email code login"));
+    }
+
+    let Some(account) = find_account(&ctx, &payload.email).await? else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    let user_id = account.id;
+
+    let otp = ctx.email_otp();
+    let code = match otp.issue(user_id, &account.email).await {
+        Ok(code) => code,
+        Err(Error::RateLimited { .. }) => {
+            tracing::warn!(%user_id, "Login code not sent, address over its send limit");
+            return Ok(StatusCode::ACCEPTED);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let email = ctx.mail_templates().render(
+        EmailTemplate::EmailOtp,
+        locale.language(),
+        &account.email,
+        &json!({
+            "code": code,
+            "ttl_minutes": otp.config().code_ttl().div_ceil(60),
+        }),
+    )?;
+
+    ctx.jobs().enqueue(Job::SendEmail { email }).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// `POST /auth/email-otp/verify`
+///
+/// Redeems a code from `POST /auth/email-otp/send` and logs the user in
+/// exactly like `POST /auth/login`: a session cookie is set and a token
+/// pair returned. Receiving the code proves ownership of the address, so
+/// the email is marked verified too.
+///
+/// Each code works once and is burned after `email_otp.max_attempts` wrong
+/// guesses. Wrong codes also count as failed logins, so they delay further
+/// attempts, lock the account and flood the client IP like wrong passwords
+/// do, answering `429 Too Many Requests` until that clears.
+///
+/// Responds with `401 Unauthorized` for an unknown email or a wrong,
+/// expired or used code, or `404 Not Found` with `features.email_otp`
+/// disabled.
+async fn verify_code(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Json(payload): Json<VerifyCodeRequest>,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let outcome = email_otp_login(&ctx, &device, jar, payload).await;
+
+    ctx.reports()
+        .record_outcome(&LoginMethod::EmailOtp, &device.device, &outcome)
+        .await;
+
+    outcome
+}
+
+async fn email_otp_login(
+    ctx: &AppContext,
+    device: &DeviceInfo,
+    jar: CookieJar,
+    payload: VerifyCodeRequest,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    if !ctx.config().features().email_otp() {
+        return Err(Error::Disabled("email code login"));
+    }
+
+    let ip = device.ip;
+    let identifier = ctx.normalize_email(&payload.email);
+    let throttle = ctx.login_throttle();
+    throttle.check(&identifier, ip).await?;
+
+    let Some(account) = find_account(ctx, &payload.email).await? else {
+        throttle.record_failure(&identifier, ip, None).await?;
+        return Err(Error::InvalidToken);
+    };
+    let user_id = account.id;
+
+    LoginThrottle::check_account(account.locked_until)?;
+
+    let redeemed = ctx
+        .email_otp()
+        .verify(user_id, &account.email, &payload.code)
+        .await?;
+
+    if !redeemed {
+        throttle
+            .record_failure(&identifier, ip, Some(user_id))
+            .await?;
+        return Err(Error::InvalidToken);
+    }
+
+    throttle.record_success(&identifier, ip, user_id).await?;
+
+    sqlx::query(
+        "UPDATE users SET verified_at = now(), updated_at = now() WHERE id = $1 AND verified_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(ctx.db())
+    .await?;
+
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::EmailOtp)
+        .await?;
+    let tokens = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(%user_id, session_id = %session.id, "User logged in with email code");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::EmailOtp,
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(tokens)))
+}
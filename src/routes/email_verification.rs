@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{generate_token, hash_token},
+    i18n::Locale,
+    jobs::Job,
+    mail::EmailTemplate,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/verify-email", get(verify_email))
+        .route("/verify-email/resend", post(resend_verification))
+}
+
+/// Issues a verification token for `user_id` and emails the link to `email`,
+/// in the language of `locale`.
+///
+/// Any token sent earlier is invalidated. Delivery failures are logged rather
+/// than returned so that they never fail the calling request.
+///
+/// ## Errors
+/// * Database errors while storing the token
+pub(super) async fn send_verification_email(
+    ctx: &AppContext,
+    user_id: Uuid,
+    email: &str,
+    locale: &Locale,
+) -> Result<()> {
+    let token = generate_token();
+    let now = Utc::now();
+    let ttl = Duration::seconds(
+        i64::try_from(ctx.config().auth().email_verification_ttl()).unwrap_or(i64::MAX),
+    );
+
+    sqlx::query("DELETE FROM email_verification_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(ctx.db())
+        .await?;
+
+    sqlx::query(
+        r"
+        INSERT INTO email_verification_tokens (user_id, token_hash, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ",
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(now)
+    .bind(now + ttl)
+    .execute(ctx.db())
+    .await?;
+
+    let email = ctx.mail_templates().render(
+        EmailTemplate::Verification,
+        locale.language(),
+        email,
+        &json!({
+            "url": format!("{}/auth/verify-email?token={token}", ctx.config().server().url()),
+            "ttl_hours": ttl.num_hours(),
+        }),
+    )?;
+
+    ctx.jobs().enqueue(Job::SendEmail { email }).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyEmailResponse {
+    verified_at: DateTime<Utc>,
+}
+
+/// `GET /auth/verify-email?token=...`
+///
+/// Marks the email address of the token's owner as verified and consumes the
+/// token. Responds with `401 Unauthorized` for unknown or expired tokens.
+async fn verify_email(
+    State(ctx): State<Arc<AppContext>>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<Json<VerifyEmailResponse>> {
+    let mut tx = ctx.db().begin().await?;
+
+    let user_id: Uuid = sqlx::query_scalar(
+        r"
+        DELETE FROM email_verification_tokens
+        WHERE token_hash = $1 AND expires_at > now()
+        RETURNING user_id
+        ",
+    )
+    .bind(hash_token(&query.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    let verified_at: DateTime<Utc> = sqlx::query_scalar(
+        r"
+        UPDATE users
+        SET verified_at = COALESCE(verified_at, now()), updated_at = now()
+        WHERE id = $1
+        RETURNING verified_at
+        ",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(%user_id, "Email verified");
+
+    Ok(Json(VerifyEmailResponse { verified_at }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    email: String,
+}
+
+/// `POST /auth/verify-email/resend`
+///
+/// Sends a fresh verification link to an unverified account. Always answers
+/// `202 Accepted` so the endpoint does not reveal which emails are registered
+/// or verified.
+async fn resend_verification(
+    State(ctx): State<Arc<AppContext>>,
+    locale: Locale,
+    Json(payload): Json<ResendVerificationRequest>,
+) -> Result<StatusCode> {
+    let user = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, email FROM users WHERE email_normalized = $1 AND verified_at IS NULL AND deleted_at IS NULL",
+    )
+    .bind(ctx.normalize_email(&payload.email))
+    .fetch_optional(ctx.db())
+    .await?;
+
+    if let Some((user_id, email)) = user {
+        send_verification_email(&ctx, user_id, &email, &locale).await?;
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
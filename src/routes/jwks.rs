@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::get};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::AppContext;
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new().route("/.well-known/jwks.json", get(jwks))
+}
+
+/// `GET /.well-known/jwks.json`
+///
+/// Public keys for verifying tokens issued by this server: the access and
+/// refresh token keys when `auth.algorithm` is asymmetric, and the ID token
+/// key of the OpenID Connect provider when it is enabled. HS256 keys are
+/// never published, so the set is empty in the default configuration.
+///
+/// Tokens name their key in the `kid` header. Rotated keys appear here as
+/// soon as they sign and stay until `auth.lifetimes.refresh_token` after they
+/// were replaced, so verifiers caching the set should refetch it on an unknown
+/// `kid`.
+async fn jwks(State(ctx): State<Arc<AppContext>>) -> Json<JwkSet> {
+    let mut set = ctx.tokens().jwks();
+
+    if let Ok(oidc) = ctx.oidc() {
+        set.keys.extend(oidc.jwks().keys);
+    }
+
+    Json(set)
+}
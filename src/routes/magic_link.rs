@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{generate_token, hash_token},
+    events::{Event, LoginMethod},
+    i18n::Locale,
+    jobs::Job,
+    mail::EmailTemplate,
+    sessions::DeviceInfo,
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/magic-link", post(request_magic_link))
+        .route("/magic-link/verify", get(verify_magic_link))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkRequest {
+    email: String,
+}
+
+/// `POST /auth/magic-link`
+///
+/// Emails a single-use login link if an account with the given email exists.
+/// Always answers `202 Accepted` so the endpoint cannot be used to discover
+/// registered emails, or `404 Not Found` with `features.magic_links`
+/// disabled.
+async fn request_magic_link(
+    State(ctx): State<Arc<AppContext>>,
+    locale: Locale,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<StatusCode> {
+    if !ctx.config().features().magic_links() {
+        return Err(Error::Disabled("magic link login"));
+    }
+
+    let user = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, email FROM users WHERE email_normalized = $1 AND deleted_at IS NULL",
+    )
+    .bind(ctx.normalize_email(&payload.email))
+    .fetch_optional(ctx.db())
+    .await?;
+
+    let Some((user_id, email)) = user else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+
+    let token = generate_token();
+    let now = Utc::now();
+    let ttl =
+        Duration::seconds(i64::try_from(ctx.config().auth().magic_link_ttl()).unwrap_or(i64::MAX));
+
+    // A new request supersedes any link sent earlier.
+    sqlx::query("DELETE FROM magic_link_tokens WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(ctx.db())
+        .await?;
+
+    sqlx::query(
+        r"
+        INSERT INTO magic_link_tokens (user_id, token_hash, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ",
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(now)
+    .bind(now + ttl)
+    .execute(ctx.db())
+    .await?;
+
+    let email = ctx.mail_templates().render(
+        EmailTemplate::MagicLink,
+        locale.language(),
+        &email,
+        &json!({
+            "url": format!(
+                "{}/auth/magic-link/verify?token={token}",
+                ctx.config().server().url()
+            ),
+            "ttl_minutes": ttl.num_minutes(),
+        }),
+    )?;
+
+    ctx.jobs().enqueue(Job::SendEmail { email }).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMagicLinkQuery {
+    token: String,
+}
+
+/// `GET /auth/magic-link/verify?token=...`
+///
+/// Redeems a magic link and logs the user in exactly like `POST /auth/login`:
+/// a session cookie is set and a token pair returned. Following the link
+/// proves ownership of the address, so the email is marked verified too.
+///
+/// Responds with `401 Unauthorized` for an unknown, expired or already used
+/// token, or `404 Not Found` with `features.magic_links` disabled.
+async fn verify_magic_link(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Query(query): Query<VerifyMagicLinkQuery>,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let outcome = magic_link_login(&ctx, &device, jar, query).await;
+
+    ctx.reports()
+        .record_outcome(&LoginMethod::MagicLink, &device.device, &outcome)
+        .await;
+
+    outcome
+}
+
+async fn magic_link_login(
+    ctx: &AppContext,
+    device: &DeviceInfo,
+    jar: CookieJar,
+    query: VerifyMagicLinkQuery,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    if !ctx.config().features().magic_links() {
+        return Err(Error::Disabled("magic link login"));
+    }
+
+    let mut tx = ctx.db().begin().await?;
+
+    let user_id: Uuid = sqlx::query_scalar(
+        r"
+        UPDATE magic_link_tokens
+        SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id
+        ",
+    )
+    .bind(hash_token(&query.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    sqlx::query(
+        "UPDATE users SET verified_at = now(), updated_at = now() WHERE id = $1 AND verified_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::MagicLink)
+        .await?;
+    let tokens = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(%user_id, session_id = %session.id, "User logged in with magic link");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::MagicLink,
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(tokens)))
+}
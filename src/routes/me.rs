@@ -0,0 +1,465 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{
+        self, AuthUser, PasswordWarning, RecentlyAuthenticated, RequireScope, SESSIONS_READ_SCOPE,
+        SESSIONS_WRITE_SCOPE,
+    },
+    events::Event,
+    models::{Metadata, User},
+    pagination::{Page, Paginated, Pagination, SortField, sort_key},
+    privacy::DataExport,
+    security::ClientIp,
+    sessions::Device,
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/me", get(get_me).patch(update_me).delete(delete_me))
+        .route("/me/password", post(change_password))
+        .route("/me/deletion", delete(cancel_deletion))
+        .route("/me/export", post(request_export))
+        .route("/me/exports/{id}", get(get_export))
+        .route("/me/exports/{id}/download", get(download_export))
+        .route(
+            "/me/sessions",
+            get(list_sessions).route_layer(RequireScope(SESSIONS_READ_SCOPE)),
+        )
+        .route(
+            "/me/sessions/{id}",
+            delete(delete_session).route_layer(RequireScope(SESSIONS_WRITE_SCOPE)),
+        )
+}
+
+/// `GET /me`
+///
+/// Returns the caller's profile.
+async fn get_me(user: AuthUser) -> Json<User> {
+    Json(user.into_user())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMeRequest {
+    /// New display name; an empty string clears it.
+    name: Option<String>,
+    /// New username, when `auth.usernames` is enabled; an empty string
+    /// clears it.
+    username: Option<String>,
+    /// Merge patch applied to the caller's `user_metadata`.
+    user_metadata: Option<Value>,
+}
+
+/// `PATCH /me`
+///
+/// Updates the caller's profile. Fields left out of the request are kept.
+/// `user_metadata` is applied as a JSON merge patch: keys set to `null` are
+/// removed, objects are merged and other values replaced. `app_metadata`
+/// can only be changed through the admin API.
+///
+/// Responds with the updated profile, `409 Conflict` if the username is
+/// taken or `422 Unprocessable Entity` on invalid input, including metadata
+/// larger than `auth.max_metadata_size`.
+async fn update_me(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<UpdateMeRequest>,
+) -> Result<Json<User>> {
+    let name = payload.name.as_deref().map(str::trim);
+    let username = payload.username.as_deref().map(str::trim);
+
+    if name.is_some_and(|name| name.len() > 255) {
+        return Err(Error::Validation(String::from(
+            "name must be at most 255 characters",
+        )));
+    }
+
+    if let Some(username) = username.filter(|username| !username.is_empty()) {
+        auth::validate_username(ctx.config().auth().usernames(), username)?;
+    }
+
+    let id = user.id();
+    let mut updated = user.into_user();
+
+    if let Some(name) = name {
+        updated = ctx
+            .users()
+            .update_name(id, (!name.is_empty()).then_some(name))
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+    }
+
+    if let Some(username) = username {
+        updated = ctx
+            .users()
+            .set_username(id, (!username.is_empty()).then_some(username))
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+    }
+
+    if let Some(patch) = &payload.user_metadata {
+        updated = ctx
+            .users()
+            .patch_metadata(
+                id,
+                Metadata::User,
+                patch,
+                ctx.config().auth().max_metadata_size(),
+            )
+            .await?
+            .ok_or(Error::Unauthenticated)?;
+    }
+
+    Ok(Json(updated))
+}
+
+/// `DELETE /me`
+///
+/// Schedules the caller's account for deletion after the
+/// `privacy.deletion_grace_period` and emails a notice. Until then the user
+/// can still log in and cancel with `DELETE /me/deletion`; afterwards the
+/// account is erased along with its sessions, passkeys, API keys and
+/// memberships, and its audit events are anonymized. With a grace period of
+/// `0` this happens right away and the session cookie is cleared.
+///
+/// Responds with `202 Accepted` and the deletion date, or `204 No Content`
+/// when erased immediately. Requests authenticated with an API key, or
+/// without having authenticated recently, see [`RecentlyAuthenticated`], get
+/// `403 Forbidden`.
+async fn delete_me(
+    State(ctx): State<Arc<AppContext>>,
+    jar: CookieJar,
+    ClientIp(ip): ClientIp,
+    RecentlyAuthenticated(user): RecentlyAuthenticated,
+) -> Result<Response> {
+    if ctx.config().privacy().deletion_grace_period() == 0 {
+        if !ctx.privacy().erase(ctx.sessions(), user.id()).await? {
+            return Err(Error::Unauthenticated);
+        }
+
+        tracing::info!(user_id = %user.id(), "User deleted their account");
+
+        return Ok((
+            StatusCode::NO_CONTENT,
+            jar.remove(ctx.session_cookies().removal()),
+        )
+            .into_response());
+    }
+
+    let account = ctx
+        .users()
+        .find(user.id())
+        .await?
+        .ok_or(Error::Unauthenticated)?;
+
+    let scheduled_for = ctx
+        .privacy()
+        .schedule_deletion(user.id())
+        .await?
+        .ok_or(Error::Unauthenticated)?;
+
+    ctx.publish(Event::DeletionScheduled {
+        user_id: user.id(),
+        email: account.email,
+        scheduled_for,
+        ip,
+    })
+    .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "deletion_scheduled_for": scheduled_for })),
+    )
+        .into_response())
+}
+
+/// `DELETE /me/deletion`
+///
+/// Cancels a deletion scheduled with `DELETE /me`.
+///
+/// Responds with `204 No Content`, `403 Forbidden` when called with an API
+/// key or `404 Not Found` if no deletion is scheduled.
+async fn cancel_deletion(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    user: AuthUser,
+) -> Result<StatusCode> {
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    if !ctx.privacy().cancel_deletion(user.id()).await? {
+        return Err(Error::NotFound("scheduled deletion"));
+    }
+
+    ctx.publish(Event::DeletionCancelled {
+        user_id: user.id(),
+        ip,
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `POST /me/export`
+///
+/// Starts building a JSON archive of the caller's personal data: profile,
+/// sessions and audit events. The archive is built in the background; poll
+/// `GET /me/exports/{id}` until it is `ready`, then fetch it from
+/// `GET /me/exports/{id}/download` within `privacy.export_ttl`.
+///
+/// Responds with `202 Accepted` and the pending export, or `403 Forbidden`
+/// when called with an API key.
+async fn request_export(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    user: AuthUser,
+) -> Result<(StatusCode, Json<DataExport>)> {
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    let export = ctx.privacy().request_export(user.id()).await?;
+
+    ctx.publish(Event::DataExportRequested {
+        user_id: user.id(),
+        export_id: export.id,
+        ip,
+    })
+    .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(export)))
+}
+
+/// `GET /me/exports/{id}`
+///
+/// Returns the status of one of the caller's exports, or `404 Not Found`.
+async fn get_export(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DataExport>> {
+    ctx.privacy()
+        .find_export(user.id(), id)
+        .await?
+        .map(Json)
+        .ok_or(Error::NotFound("export"))
+}
+
+/// `GET /me/exports/{id}/download`
+///
+/// Downloads the archive of a ready export as a JSON attachment.
+///
+/// Responds with `403 Forbidden` when called with an API key, or
+/// `404 Not Found` if the export is unknown, still pending, failed or
+/// expired.
+async fn download_export(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    let archive = ctx
+        .privacy()
+        .archive(user.id(), id)
+        .await?
+        .ok_or(Error::NotFound("export"))?;
+
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"export-{id}.json\""),
+        )],
+        Json(archive),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+/// `POST /me/password`
+///
+/// Changes the caller's password after checking the current one. Every other
+/// session of the user is ended and all previously issued tokens are
+/// revoked; the current session stays logged in and a fresh token pair for
+/// it is returned.
+///
+/// Responds with `401 Unauthorized` if the current password is wrong or the
+/// account has none (use password reset instead), `403 Forbidden` when called
+/// with an API key or without having authenticated recently, see
+/// [`RecentlyAuthenticated`], or `422 Unprocessable Entity` if the new
+/// password fails the password policy.
+async fn change_password(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    RecentlyAuthenticated(user): RecentlyAuthenticated,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<(PasswordWarning, Json<TokenPair>)> {
+    let session_id = user.session_id().ok_or(Error::Forbidden)?;
+
+    let account = ctx
+        .users()
+        .find(user.id())
+        .await?
+        .ok_or(Error::Unauthenticated)?;
+
+    let current_ok = match &account.password_hash {
+        Some(hash) => {
+            ctx.hashing()
+                .verify(&payload.current_password, hash)
+                .await?
+        }
+        None => false,
+    };
+
+    if !current_ok {
+        return Err(Error::InvalidCredentials);
+    }
+
+    let passwords = ctx.password_policy_for(user.id()).await?;
+    auth::validate_password(&passwords, &payload.new_password)?;
+    let warning = ctx
+        .pwned_passwords()
+        .check(&passwords, &payload.new_password)
+        .await?;
+
+    let password_hash = ctx.hashing().hash(&payload.new_password).await?;
+
+    ctx.users()
+        .set_password_hash(user.id(), Some(&password_hash))
+        .await?;
+
+    let ended = ctx.sessions().delete_others(user.id(), session_id).await?;
+    ctx.revocations().revoke_all(user.id()).await?;
+    let sessions_ended = ended.len();
+    ctx.publish(Event::SessionsRevoked {
+        user_id: user.id(),
+        session_ids: ended,
+    })
+    .await?;
+
+    let session = ctx
+        .sessions()
+        .find(session_id)
+        .await?
+        .ok_or(Error::InvalidToken)?;
+
+    ctx.publish(Event::PasswordChanged {
+        user_id: user.id(),
+        ip,
+        sessions_ended,
+    })
+    .await?;
+
+    Ok((warning, Json(ctx.issue_tokens(&session).await?)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    id: Uuid,
+    user_agent: Option<String>,
+    ip: Option<String>,
+    device: Option<Device>,
+    created_at: DateTime<Utc>,
+    last_seen_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    /// Whether this is the session making the request.
+    current: bool,
+}
+
+impl Paginated for SessionInfo {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, field: &str) -> String {
+        match field {
+            "created_at" => sort_key(self.created_at),
+            _ => sort_key(self.last_seen_at),
+        }
+    }
+}
+
+const SESSION_LAST_SEEN_AT: SortField =
+    SortField::new("last_seen_at", "last_seen_at", "timestamptz");
+const SESSION_CREATED_AT: SortField = SortField::new("created_at", "created_at", "timestamptz");
+
+/// `GET /me/sessions`
+///
+/// Lists the caller's active sessions, one per logged-in device, most
+/// recently used first, one [`Page`] at a time. Sort by `last_seen_at` or
+/// `created_at`.
+async fn list_sessions(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    pagination: Pagination,
+) -> Result<Json<Page<SessionInfo>>> {
+    let sort = pagination.sort(
+        &[SESSION_LAST_SEEN_AT, SESSION_CREATED_AT],
+        SESSION_LAST_SEEN_AT.descending(),
+    )?;
+
+    let sessions = ctx
+        .sessions()
+        .list(user.id())
+        .await?
+        .into_iter()
+        .map(|session| SessionInfo {
+            current: user.session_id() == Some(session.id),
+            id: session.id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            device: session.device,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        })
+        .collect();
+
+    Ok(Json(pagination.slice(sessions, sort)?))
+}
+
+/// `DELETE /me/sessions/{id}`
+///
+/// Ends one of the caller's sessions, logging that device out. Responds with
+/// `204 No Content` or `404 Not Found` if no such session belongs to the
+/// caller.
+async fn delete_session(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !ctx.sessions().delete_for_user(user.id(), id).await? {
+        return Err(Error::NotFound("session"));
+    }
+
+    tracing::info!(user_id = %user.id(), session_id = %id, "Session ended by user");
+
+    ctx.publish(Event::SessionsRevoked {
+        user_id: user.id(),
+        session_ids: vec![id],
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
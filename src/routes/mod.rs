@@ -0,0 +1,96 @@
+mod admin;
+mod admin_ui;
+mod api_keys;
+mod auth;
+mod email_change;
+mod email_otp;
+mod email_verification;
+mod jwks;
+mod magic_link;
+mod me;
+mod oauth;
+mod oauth_clients;
+mod oidc;
+mod organizations;
+mod password_reset;
+mod phone;
+mod saml;
+mod sso;
+mod versions;
+mod webauthn;
+mod webhooks;
+mod well_known;
+
+use std::sync::Arc;
+
+use axum::Router;
+
+pub use self::versions::{ApiRouter, ApiVersion};
+use crate::{
+    AppContext,
+    auth::{RequireRole, Role},
+    config::Routes,
+};
+
+/// Builds the router of every API version, limited to the route groups a
+/// listener serving `served` exposes.
+///
+/// Version 1 is served under `/api/v1` and, for clients from before the API
+/// was versioned, without a prefix.
+///
+/// This and the route groups below need the middleware
+/// [`crate::App::with_middleware`] adds before another application can
+/// mount them.
+pub fn router(served: Routes) -> Router<Arc<AppContext>> {
+    ApiRouter::new()
+        .version("v1", v1())
+        .unversioned("v1")
+        .build(served)
+}
+
+/// The route groups of API version 1.
+#[must_use]
+pub fn v1() -> ApiVersion {
+    ApiVersion::new(public_router(), admin_router())
+}
+
+/// Builds the router containing every API v1 route group but the admin API.
+pub fn public_router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .nest(
+            "/auth",
+            auth::router()
+                .merge(api_keys::router())
+                .merge(email_otp::router())
+                .merge(email_verification::router())
+                .merge(magic_link::router())
+                .merge(oauth::router())
+                .merge(organizations::router())
+                .merge(password_reset::router())
+                .merge(phone::router())
+                .merge(saml::router())
+                .merge(sso::router())
+                .merge(webauthn::router()),
+        )
+        .merge(me::router())
+        .merge(email_change::router())
+        .merge(jwks::router())
+        .merge(oauth_clients::router())
+        .merge(oidc::router())
+        .merge(webhooks::router())
+        .merge(well_known::router())
+}
+
+/// Builds the router containing the admin API of v1, open to admins only.
+pub fn admin_router() -> Router<Arc<AppContext>> {
+    admin::router().route_layer(RequireRole(Role::Admin))
+}
+
+/// Builds the router of the admin dashboard under `/admin/ui`, whose pages
+/// are open to admins only. Served by the listeners serving the admin API,
+/// whose v1 routes its actions call, unless `features.admin_ui` is off.
+pub fn admin_ui_router() -> Router<Arc<AppContext>> {
+    admin_ui::router()
+        .route_layer(RequireRole(Role::Admin))
+        .merge(admin_ui::assets())
+}
@@ -0,0 +1,309 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    response::Redirect,
+    routing::get,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    events::{Event, LoginMethod},
+    models::NewUser,
+    oauth::{self, OAUTH_COOKIE, Profile, Provider, ProviderTokens},
+    repositories::PgUserStore,
+    sessions::DeviceInfo,
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/oauth/{provider}", get(authorize))
+        .route("/oauth/{provider}/callback", get(callback))
+}
+
+/// `GET /auth/oauth/{provider}`
+///
+/// Redirects to the provider's consent page. The state and PKCE verifier
+/// for the round trip are kept in a short-lived `HttpOnly` cookie.
+///
+/// Responds with `404 Not Found` if the provider is unknown or has no
+/// configuration, or `features.social_login` is disabled.
+async fn authorize(
+    State(ctx): State<Arc<AppContext>>,
+    Path(provider): Path<String>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect)> {
+    if !ctx.config().features().social_login() {
+        return Err(Error::Disabled("social login"));
+    }
+
+    let provider: Provider = provider.parse()?;
+    let authorization = ctx.oauth().authorize(provider)?;
+    let config = ctx.config();
+    let cookie = oauth::pending_cookie(
+        provider,
+        &authorization,
+        config.cookies(),
+        config.server().is_https(),
+        ctx.oauth().cookie_path(provider)?,
+    );
+
+    Ok((jar.add(cookie), Redirect::to(&authorization.url)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// `GET /auth/oauth/{provider}/callback`
+///
+/// Completes the authorization-code flow and logs the user in exactly like
+/// `POST /auth/login`: a session cookie is set and a token pair returned.
+///
+/// The provider account is resolved to a local user in this order:
+/// 1. a user already linked to the provider account;
+/// 2. a user with the same email, if the provider has verified it;
+/// 3. a newly created user without a password, unless
+///    `features.registration` is disabled.
+///
+/// Responds with `401 Unauthorized` if the state does not match the pending
+/// authorization, `404 Not Found` with `features.social_login` disabled or
+/// no account to log into while `features.registration` is,
+/// `409 Conflict` if an account with the email exists but the provider has
+/// not verified the address, `422 Unprocessable Entity` if the user declined
+/// consent and `502 Bad Gateway` if the provider fails.
+async fn callback(
+    State(ctx): State<Arc<AppContext>>,
+    Path(provider): Path<String>,
+    Query(query): Query<CallbackQuery>,
+    device: DeviceInfo,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    if !ctx.config().features().social_login() {
+        return Err(Error::Disabled("social login"));
+    }
+
+    let provider: Provider = provider.parse()?;
+    let outcome = oauth_login(&ctx, provider, query, &device, jar).await;
+
+    ctx.reports()
+        .record_outcome(&LoginMethod::OAuth(provider), &device.device, &outcome)
+        .await;
+
+    outcome
+}
+
+async fn oauth_login(
+    ctx: &AppContext,
+    provider: Provider,
+    query: CallbackQuery,
+    device: &DeviceInfo,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    if let Some(error) = query.error {
+        return Err(Error::Validation(format!(
+            "authorization was not granted: {error}"
+        )));
+    }
+
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return Err(Error::Validation(String::from(
+            "code and state are required",
+        )));
+    };
+
+    let cookies = ctx.config().cookies().clone();
+    let verifier = oauth::verify_pending(
+        jar.get(&cookies.name(OAUTH_COOKIE))
+            .map(|cookie| cookie.value()),
+        provider,
+        &state,
+    )?;
+    let jar = jar.remove(oauth::pending_removal_cookie(
+        &cookies,
+        ctx.oauth().cookie_path(provider)?,
+    ));
+
+    let tokens = ctx
+        .oauth()
+        .exchange_code(provider, &code, &verifier)
+        .await?;
+    let profile = ctx
+        .oauth()
+        .fetch_profile(provider, &tokens.access_token)
+        .await?;
+
+    let user_id = resolve_user(ctx, provider, &profile, &tokens).await?;
+
+    let verified: bool =
+        sqlx::query_scalar("SELECT verified_at IS NOT NULL FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(ctx.db())
+            .await?;
+
+    if !verified && ctx.config().auth().require_email_verification() {
+        return Err(Error::EmailNotVerified);
+    }
+
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::OAuth(provider))
+        .await?;
+    let pair = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(%user_id, %provider, session_id = %session.id, "User logged in with OAuth");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::OAuth(provider),
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(pair)))
+}
+
+/// Finds or creates the local user for `profile` and records the link with
+/// the latest provider tokens.
+async fn resolve_user(
+    ctx: &AppContext,
+    provider: Provider,
+    profile: &Profile,
+    tokens: &ProviderTokens,
+) -> Result<Uuid> {
+    let now = Utc::now();
+    let expires_at = tokens.expires_in.map(|secs| now + Duration::seconds(secs));
+    let mut created = false;
+    let mut claimed = false;
+    let mut revoked = Vec::new();
+    let mut tx = ctx.db().begin().await?;
+
+    let linked: Option<Uuid> = sqlx::query_scalar(
+        "SELECT user_id FROM oauth_accounts WHERE provider = $1 AND provider_user_id = $2",
+    )
+    .bind(provider.name())
+    .bind(&profile.id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match linked {
+        Some(user_id) => user_id,
+        None => {
+            let existing = sqlx::query_as::<_, (Uuid, bool)>(
+                "SELECT id, verified_at IS NOT NULL FROM users WHERE email_normalized = $1 AND deleted_at IS NULL",
+            )
+            .bind(ctx.normalize_email(&profile.email))
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            match existing {
+                Some(_) if !profile.email_verified => return Err(Error::EmailTaken),
+                Some((user_id, true)) => user_id,
+                Some((user_id, false)) => {
+                    // Nobody proved ownership of the email when this account
+                    // was registered, so whoever set its password may not be
+                    // the person now signing in. Drop the password and any
+                    // sessions it opened before handing the account over.
+                    sqlx::query(
+                        r"
+                        UPDATE users
+                        SET verified_at = now(), password_hash = NULL, updated_at = now()
+                        WHERE id = $1
+                        ",
+                    )
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    revoked =
+                        sqlx::query_scalar("DELETE FROM sessions WHERE user_id = $1 RETURNING id")
+                            .bind(user_id)
+                            .fetch_all(&mut *tx)
+                            .await?;
+                    claimed = true;
+
+                    user_id
+                }
+                None => {
+                    if !ctx.config().features().registration() {
+                        return Err(Error::Disabled("registration"));
+                    }
+
+                    let user_id = PgUserStore::insert(
+                        &mut *tx,
+                        &NewUser {
+                            name: profile.name.as_deref(),
+                            verified_at: profile.email_verified.then_some(now),
+                            ..NewUser::new(
+                                &profile.email,
+                                ctx.config().auth().email_normalization(),
+                            )
+                        },
+                    )
+                    .await?
+                    .ok_or(Error::EmailTaken)?
+                    .id;
+
+                    tracing::info!(%user_id, %provider, "User registered with OAuth");
+                    created = true;
+
+                    user_id
+                }
+            }
+        }
+    };
+
+    sqlx::query(
+        r"
+        INSERT INTO oauth_accounts
+            (user_id, provider, provider_user_id, access_token, refresh_token, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (provider, provider_user_id) DO UPDATE
+        SET access_token = EXCLUDED.access_token,
+            refresh_token = COALESCE(EXCLUDED.refresh_token, oauth_accounts.refresh_token),
+            expires_at = EXCLUDED.expires_at
+        ",
+    )
+    .bind(user_id)
+    .bind(provider.name())
+    .bind(&profile.id)
+    .bind(&tokens.access_token)
+    .bind(tokens.refresh_token.as_deref())
+    .bind(expires_at)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    ctx.sessions().forget(&revoked).await;
+    if claimed {
+        // Sessions kept outside the database are not part of the transaction.
+        revoked.extend(ctx.sessions().delete_all(user_id).await?);
+    }
+
+    if created {
+        ctx.publish(Event::UserCreated {
+            user_id,
+            email: profile.email.clone(),
+        })
+        .await?;
+    }
+    if !revoked.is_empty() {
+        ctx.publish(Event::SessionsRevoked {
+            user_id,
+            session_ids: revoked,
+        })
+        .await?;
+    }
+
+    Ok(user_id)
+}
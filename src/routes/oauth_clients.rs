@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get},
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::AdminUser,
+    oidc::{self, LogoutDelivery, RegisteredClient},
+};
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route(
+            "/admin/oauth-clients",
+            get(list_clients).post(create_client),
+        )
+        .route("/admin/oauth-clients/{id}", delete(delete_client))
+        .route(
+            "/admin/oauth-clients/{id}/logout-deliveries",
+            get(list_logout_deliveries),
+        )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateClientRequest {
+    name: String,
+    #[serde(default)]
+    redirect_uris: Vec<String>,
+    /// Scopes the client may request through the client-credentials grant.
+    #[serde(default)]
+    scopes: Vec<String>,
+    /// Where users may be sent back to after logging out at
+    /// `/oauth/logout`.
+    #[serde(default)]
+    post_logout_redirect_uris: Vec<String>,
+    /// Where logout tokens are posted when a session the client logged a
+    /// user in from ends.
+    backchannel_logout_uri: Option<String>,
+    /// Whether the client can keep a secret. Public clients must use PKCE.
+    #[serde(default = "default_confidential")]
+    confidential: bool,
+}
+
+fn default_confidential() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedClient {
+    #[serde(flatten)]
+    client: RegisteredClient,
+    /// The client secret of confidential clients. It is only ever returned
+    /// here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+}
+
+/// `POST /admin/oauth-clients`
+///
+/// Registers an application that may log users in through the OpenID
+/// Connect provider, or call APIs on its own behalf with the
+/// client-credentials grant. Redirect URIs, and the post-logout redirect
+/// URIs users may be sent back to after logging out, must be absolute and
+/// are later matched exactly; they may be omitted by confidential clients
+/// that only use `scopes`. With a `backchannel_logout_uri`, the client is
+/// sent a logout token whenever a session it logged a user in from ends. Scopes are free-form, space-free names such as
+/// `reports:read`.
+///
+/// Responds with `201 Created` and, for confidential clients, the secret, or
+/// `422 Unprocessable Entity` on invalid input.
+async fn create_client(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    Json(payload): Json<CreateClientRequest>,
+) -> Result<(StatusCode, Json<CreatedClient>)> {
+    let name = payload.name.trim();
+
+    if name.is_empty() || name.len() > 255 {
+        return Err(Error::Validation(String::from(
+            "name must be between 1 and 255 characters",
+        )));
+    }
+
+    if !payload.confidential && !payload.scopes.is_empty() {
+        return Err(Error::Validation(String::from(
+            "only confidential clients may have scopes",
+        )));
+    }
+
+    if payload.redirect_uris.is_empty() && payload.scopes.is_empty() {
+        return Err(Error::Validation(String::from(
+            "at least one redirect URI or scope is required",
+        )));
+    }
+
+    for scope in &payload.scopes {
+        if !oidc::is_valid_scope(scope) {
+            return Err(Error::Validation(format!("scope {scope:?} is invalid")));
+        }
+    }
+
+    for uri in payload
+        .redirect_uris
+        .iter()
+        .chain(&payload.post_logout_redirect_uris)
+        .chain(&payload.backchannel_logout_uri)
+    {
+        match Url::parse(uri) {
+            Ok(url) if url.fragment().is_none() => {}
+            _ => {
+                return Err(Error::Validation(format!(
+                    "URI {uri:?} must be absolute and have no fragment"
+                )));
+            }
+        }
+    }
+
+    let (client, client_secret) = ctx
+        .oauth_clients()
+        .create(
+            name,
+            &payload.redirect_uris,
+            &payload.scopes,
+            &payload.post_logout_redirect_uris,
+            payload.backchannel_logout_uri.as_deref(),
+            payload.confidential,
+        )
+        .await?;
+
+    tracing::info!(admin_id = %admin.id(), client_id = %client.client_id, "OAuth client registered");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedClient {
+            client,
+            client_secret,
+        }),
+    ))
+}
+
+/// `GET /admin/oauth-clients`
+///
+/// Lists registered clients, newest first. Secrets are never included.
+async fn list_clients(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+) -> Result<Json<Vec<RegisteredClient>>> {
+    ctx.oauth_clients().list().await.map(Json)
+}
+
+/// `DELETE /admin/oauth-clients/{id}`
+///
+/// Removes a client. Its outstanding codes and access tokens stop working.
+///
+/// Responds with `204 No Content` or `404 Not Found`.
+async fn delete_client(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !ctx.oauth_clients().delete(id).await? {
+        return Err(Error::NotFound("client"));
+    }
+
+    tracing::info!(admin_id = %admin.id(), %id, "OAuth client deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListLogoutDeliveriesQuery {
+    /// 1-based page number.
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutDeliveryPage {
+    deliveries: Vec<LogoutDelivery>,
+    page: u32,
+    per_page: u32,
+}
+
+/// `GET /admin/oauth-clients/{id}/logout-deliveries`
+///
+/// Lists the back-channel logout tokens sent to a client, with the outcome
+/// of the last attempt, newest first. Page with `page` and `per_page` (at
+/// most 100).
+async fn list_logout_deliveries(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ListLogoutDeliveriesQuery>,
+) -> Result<Json<LogoutDeliveryPage>> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let deliveries = ctx
+        .backchannel_logout()
+        .list(
+            id,
+            i64::from(per_page),
+            i64::from(page - 1) * i64::from(per_page),
+        )
+        .await?;
+
+    Ok(Json(LogoutDeliveryPage {
+        deliveries,
+        page,
+        per_page,
+    }))
+}
@@ -0,0 +1,672 @@
+use std::sync::Arc;
+
+use axum::{
+    Form, Json, Router,
+    extract::{OriginalUri, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use axum_extra::extract::CookieJar;
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use url::{Url, form_urlencoded};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{AuthUser, generate_token, hash_token},
+    events::Event,
+    models::User,
+    oidc::{
+        self, AuthorizationCode, IdTokenGrant, Introspection, OidcProvider, RegisteredClient,
+        TokenError, TokenResponse,
+    },
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/.well-known/openid-configuration", get(discovery))
+        .route("/oauth/jwks", get(jwks))
+        .route("/oauth/authorize", get(authorize))
+        .route("/oauth/logout", get(end_session).post(end_session))
+        .route("/oauth/token", post(token))
+        .route("/oauth/introspect", post(introspect))
+        .route("/oauth/userinfo", get(userinfo).post(userinfo))
+}
+
+/// `GET /.well-known/openid-configuration`
+///
+/// OpenID Connect discovery document. Responds with `404 Not Found` unless
+/// the `oidc` config section is present, as do all other OIDC endpoints.
+async fn discovery(State(ctx): State<Arc<AppContext>>) -> Result<Json<Value>> {
+    Ok(Json(ctx.oidc()?.discovery()))
+}
+
+/// `GET /oauth/jwks`
+///
+/// Public key ID tokens are signed with.
+async fn jwks(State(ctx): State<Arc<AppContext>>) -> Result<Json<JwkSet>> {
+    Ok(Json(ctx.oidc()?.jwks()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeQuery {
+    response_type: Option<String>,
+    client_id: Option<String>,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+}
+
+/// `GET /oauth/authorize`
+///
+/// Authorization endpoint of the code flow. The user must be logged in with
+/// a session; registered clients are trusted, so no consent screen is shown
+/// and a code is sent straight back to the client's `redirect_uri`. Besides
+/// the OIDC scopes, clients may ask for the built-in API scopes the user may
+/// be granted, see [`oidc::grant_scope`], and then call the API with the
+/// access token. Only the
+/// `code` response type and `S256` PKCE are supported, and public clients
+/// must use PKCE.
+///
+/// An unknown client or unregistered `redirect_uri` gets `404 Not Found` or
+/// `422 Unprocessable Entity` here; every later problem is reported to the
+/// client by redirecting with an `error` parameter. Users without a session
+/// are sent to `oidc.login_url` when configured, and get `401 Unauthorized`
+/// otherwise.
+async fn authorize(
+    State(ctx): State<Arc<AppContext>>,
+    OriginalUri(uri): OriginalUri,
+    user: Result<AuthUser>,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Redirect> {
+    let oidc = ctx.oidc()?;
+
+    let client_id = query
+        .client_id
+        .as_deref()
+        .ok_or_else(|| Error::Validation(String::from("client_id is required")))?;
+    let client = ctx
+        .oauth_clients()
+        .find(client_id)
+        .await?
+        .ok_or(Error::NotFound("client"))?;
+    let redirect_uri = query
+        .redirect_uri
+        .as_deref()
+        .filter(|uri| client.allows_redirect(uri))
+        .ok_or_else(|| {
+            Error::Validation(String::from(
+                "redirect_uri is not registered for this client",
+            ))
+        })?;
+
+    // The redirect URI is trusted from here on, so errors go back to the
+    // client instead of being shown to the user.
+    let state = query.state.as_deref();
+    let fail = |error: &str| redirect_to(redirect_uri, &[("error", error)], state);
+
+    if query.response_type.as_deref() != Some("code") {
+        return fail("unsupported_response_type");
+    }
+
+    let requested = query.scope.as_deref().unwrap_or_default();
+    if !requested.split_whitespace().any(|s| s == "openid") {
+        return fail("invalid_scope");
+    }
+
+    let code_challenge = match (
+        query.code_challenge.as_deref(),
+        query.code_challenge_method.as_deref(),
+    ) {
+        (Some(challenge), Some("S256")) => Some(challenge),
+        (None, None) if client.confidential => None,
+        _ => return fail("invalid_request"),
+    };
+
+    let Some((user_id, session_id, role)) = user
+        .ok()
+        .and_then(|user| Some((user.id(), user.session_id()?, user.user().role)))
+    else {
+        return match oidc.config().login_url() {
+            Some(login_url) => {
+                let return_to = format!("{}{uri}", oidc.issuer());
+                redirect_to(login_url, &[("return_to", &return_to)], None)
+            }
+            None => Err(Error::Unauthenticated),
+        };
+    };
+
+    let scope = oidc::grant_scope(requested, role);
+    // When the user last proved who they are, which reauthentication moves
+    // past the start of the session.
+    let auth_time = ctx
+        .sessions()
+        .find(session_id)
+        .await?
+        .and_then(|session| session.authenticated_at)
+        .unwrap_or_else(Utc::now);
+
+    let code = generate_token();
+    let now = Utc::now();
+    let ttl = Duration::seconds(i64::try_from(oidc.config().code_ttl()).unwrap_or(i64::MAX));
+
+    sqlx::query(
+        r"
+        INSERT INTO oauth_authorization_codes
+            (code_hash, client_id, user_id, redirect_uri, scope, nonce, code_challenge,
+             auth_time, session_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ",
+    )
+    .bind(hash_token(&code))
+    .bind(client.id)
+    .bind(user_id)
+    .bind(redirect_uri)
+    .bind(&scope)
+    .bind(query.nonce.as_deref())
+    .bind(code_challenge)
+    .bind(auth_time)
+    .bind(session_id)
+    .bind(now)
+    .bind(now + ttl)
+    .execute(ctx.db())
+    .await?;
+
+    tracing::info!(%user_id, client_id = %client.client_id, "Authorization code issued");
+
+    redirect_to(redirect_uri, &[("code", &code)], state)
+}
+
+/// Redirects to `base` with `params`, and `state` if any, added to its query.
+pub(super) fn redirect_to(
+    base: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<Redirect> {
+    let mut url = Url::parse(base).map_err(|err| Error::Validation(err.to_string()))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+
+    Ok(Redirect::to(url.as_str()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EndSessionRequest {
+    id_token_hint: Option<String>,
+    client_id: Option<String>,
+    post_logout_redirect_uri: Option<String>,
+    state: Option<String>,
+}
+
+/// `GET|POST /oauth/logout`
+///
+/// End-session endpoint of RP-initiated logout. A client sends the user
+/// here with an ID token it was issued as `id_token_hint`; if the user is
+/// still logged in with a session here, as the subject of that token, the
+/// session ends and the session cookie is cleared. Without a hint the
+/// session is left alone, as there is no screen to confirm the logout on
+/// and any site could otherwise log users out.
+///
+/// The user is then sent to `post_logout_redirect_uri`, with `state`, if
+/// that is one of the client's registered post-logout redirect URIs, and
+/// gets `204 No Content` without one. The client is named by `client_id`
+/// or else by the audience of the hint.
+///
+/// Responds with `422 Unprocessable Entity` if the hint was not issued
+/// here, names another client than `client_id`, or the redirect URI is
+/// given without naming the client or is not registered for it, and
+/// `404 Not Found` for an unknown client.
+async fn end_session(
+    State(ctx): State<Arc<AppContext>>,
+    jar: CookieJar,
+    user: Result<AuthUser>,
+    Form(form): Form<EndSessionRequest>,
+) -> Result<(CookieJar, Response)> {
+    let oidc = ctx.oidc()?;
+
+    let hint = form
+        .id_token_hint
+        .as_deref()
+        .map(|token| {
+            oidc.verify_id_token_hint(token)
+                .ok_or_else(|| Error::Validation(String::from("id_token_hint was not issued here")))
+        })
+        .transpose()?;
+
+    let client_id = match (form.client_id.as_deref(), &hint) {
+        (Some(client_id), Some(hint)) if client_id != hint.aud => {
+            return Err(Error::Validation(String::from(
+                "id_token_hint was issued to another client",
+            )));
+        }
+        (Some(client_id), _) => Some(client_id),
+        (None, hint) => hint.as_ref().map(|hint| hint.aud.as_str()),
+    };
+
+    let redirect_uri = match form.post_logout_redirect_uri.as_deref() {
+        Some(uri) => {
+            let client_id = client_id.ok_or_else(|| {
+                Error::Validation(String::from(
+                    "client_id or id_token_hint is required with post_logout_redirect_uri",
+                ))
+            })?;
+            let client = ctx
+                .oauth_clients()
+                .find(client_id)
+                .await?
+                .ok_or(Error::NotFound("client"))?;
+
+            if !client.allows_post_logout_redirect(uri) {
+                return Err(Error::Validation(String::from(
+                    "post_logout_redirect_uri is not registered for this client",
+                )));
+            }
+
+            Some(uri)
+        }
+        None => None,
+    };
+
+    let mut jar = jar;
+    if let (Ok(user), Some(hint)) = (&user, &hint)
+        && hint.sub == user.id()
+        && let Some(session_id) = user.session_id()
+    {
+        ctx.sessions().delete(session_id).await?;
+
+        tracing::info!(user_id = %user.id(), %session_id, client_id, "User logged out through OIDC");
+
+        ctx.publish(Event::SessionsRevoked {
+            user_id: user.id(),
+            session_ids: vec![session_id],
+        })
+        .await?;
+
+        jar = jar.remove(ctx.session_cookies().removal());
+    }
+
+    let response = match redirect_uri {
+        Some(uri) => redirect_to(uri, &[], form.state.as_deref())?.into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    };
+
+    Ok((jar, response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    grant_type: String,
+    code: Option<String>,
+    redirect_uri: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    code_verifier: Option<String>,
+    scope: Option<String>,
+}
+
+/// `POST /oauth/token`
+///
+/// Issues access tokens. Confidential clients authenticate with HTTP Basic
+/// or `client_secret` in the form; public clients send only `client_id`.
+///
+/// With `grant_type=authorization_code` an authorization code is redeemed
+/// for an access token and an ID token; public clients prove the code is
+/// theirs with the PKCE `code_verifier`, and codes are single use. With
+/// `grant_type=client_credentials` a confidential client gets a token for
+/// itself, limited to the requested `scope` or, without one, to every scope
+/// it was registered with.
+///
+/// Errors follow RFC 6749: `400 Bad Request` with `invalid_request`,
+/// `invalid_grant`, `unauthorized_client`, `unsupported_grant_type` or
+/// `invalid_scope`, and `401 Unauthorized` with `invalid_client`.
+async fn token(
+    State(ctx): State<Arc<AppContext>>,
+    headers: HeaderMap,
+    Form(form): Form<TokenRequest>,
+) -> std::result::Result<impl IntoResponse, TokenError> {
+    let oidc = ctx.oidc()?;
+
+    if !matches!(
+        form.grant_type.as_str(),
+        "authorization_code" | "client_credentials"
+    ) {
+        return Err(TokenError::UnsupportedGrantType);
+    }
+
+    let client = authenticate_client(
+        &ctx,
+        &headers,
+        form.client_id.clone(),
+        form.client_secret.clone(),
+    )
+    .await?;
+
+    let response = if form.grant_type == "client_credentials" {
+        client_credentials(&ctx, oidc, &client, form.scope.as_deref()).await?
+    } else {
+        redeem_code(&ctx, oidc, &client, &form).await?
+    };
+
+    Ok((
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::PRAGMA, "no-cache"),
+        ],
+        Json(response),
+    ))
+}
+
+/// The client authenticating with HTTP Basic, or else with the credentials
+/// sent in the form.
+async fn authenticate_client(
+    ctx: &AppContext,
+    headers: &HeaderMap,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> std::result::Result<RegisteredClient, TokenError> {
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            client_id.ok_or(TokenError::InvalidRequest("client_id is required"))?,
+            client_secret,
+        ),
+    };
+
+    ctx.oauth_clients()
+        .authenticate(&client_id, client_secret.as_deref())
+        .await?
+        .ok_or(TokenError::InvalidClient)
+}
+
+/// Access token, stored for introspection, acting for `user_id` or, without
+/// one, for the client itself.
+async fn issue_access_token(
+    ctx: &AppContext,
+    oidc: &OidcProvider,
+    client: &RegisteredClient,
+    user_id: Option<Uuid>,
+    scope: &str,
+) -> Result<String> {
+    let access_token = generate_token();
+    let now = Utc::now();
+    let ttl = oidc.config().token_ttl();
+
+    sqlx::query(
+        r"
+        INSERT INTO oauth_access_tokens (token_hash, client_id, user_id, scope, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ",
+    )
+    .bind(hash_token(&access_token))
+    .bind(client.id)
+    .bind(user_id)
+    .bind(scope)
+    .bind(now)
+    .bind(now + Duration::seconds(i64::try_from(ttl).unwrap_or(i64::MAX)))
+    .execute(ctx.db())
+    .await?;
+
+    Ok(access_token)
+}
+
+async fn redeem_code(
+    ctx: &AppContext,
+    oidc: &OidcProvider,
+    client: &RegisteredClient,
+    form: &TokenRequest,
+) -> std::result::Result<TokenResponse, TokenError> {
+    let code = form
+        .code
+        .as_deref()
+        .ok_or(TokenError::InvalidRequest("code is required"))?;
+
+    let grant = sqlx::query_as::<_, AuthorizationCode>(
+        r"
+        UPDATE oauth_authorization_codes
+        SET used_at = now()
+        WHERE code_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING client_id, user_id, redirect_uri, scope, nonce, code_challenge, auth_time,
+                  session_id
+        ",
+    )
+    .bind(hash_token(code))
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(TokenError::InvalidGrant("code is invalid or expired"))?;
+
+    if grant.client_id != client.id {
+        return Err(TokenError::InvalidGrant(
+            "code was issued to another client",
+        ));
+    }
+
+    if form.redirect_uri.as_deref() != Some(grant.redirect_uri.as_str()) {
+        return Err(TokenError::InvalidGrant("redirect_uri does not match"));
+    }
+
+    if let Some(challenge) = &grant.code_challenge {
+        let verifier = form
+            .code_verifier
+            .as_deref()
+            .ok_or(TokenError::InvalidGrant("code_verifier is required"))?;
+
+        if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != *challenge {
+            return Err(TokenError::InvalidGrant("code_verifier does not match"));
+        }
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        r"
+        SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+               updated_at, deleted_at, user_metadata, app_metadata
+        FROM users
+        WHERE id = $1 AND status = 'active'
+        ",
+    )
+    .bind(grant.user_id)
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(TokenError::InvalidGrant("account is not available"))?;
+
+    let access_token = issue_access_token(ctx, oidc, client, Some(user.id), &grant.scope).await?;
+
+    if let Some(session_id) = grant.session_id {
+        let lifetime = ctx.config().auth().lifetimes().session();
+        ctx.backchannel_logout()
+            .record_session(client.id, session_id, user.id, Utc::now() + lifetime)
+            .await?;
+    }
+
+    let id_token = oidc.id_token(&IdTokenGrant {
+        user: &user,
+        client_id: &client.client_id,
+        scope: &grant.scope,
+        nonce: grant.nonce.as_deref(),
+        auth_time: grant.auth_time.timestamp(),
+        session_id: grant.session_id,
+    })?;
+
+    tracing::info!(user_id = %user.id, client_id = %client.client_id, "OIDC tokens issued");
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: oidc.config().token_ttl(),
+        scope: grant.scope,
+        id_token: Some(id_token),
+    })
+}
+
+async fn client_credentials(
+    ctx: &AppContext,
+    oidc: &OidcProvider,
+    client: &RegisteredClient,
+    scope: Option<&str>,
+) -> std::result::Result<TokenResponse, TokenError> {
+    if !client.confidential || client.scopes.is_empty() {
+        return Err(TokenError::UnauthorizedClient);
+    }
+
+    let scope = client.grant_scope(scope).ok_or(TokenError::InvalidScope)?;
+    let access_token = issue_access_token(ctx, oidc, client, None, &scope).await?;
+
+    tracing::info!(client_id = %client.client_id, %scope, "Client credentials token issued");
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: oidc.config().token_ttl(),
+        scope,
+        id_token: None,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    token: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct IntrospectedToken {
+    scope: String,
+    client_id: String,
+    user_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+/// `POST /oauth/introspect`
+///
+/// Tells resource servers whether an access token issued by the token
+/// endpoint is active, and who and what it was issued for (RFC 7662). The
+/// caller must be a confidential client; any unknown, expired or revoked
+/// token is reported as `{"active": false}`.
+///
+/// Responds with `401 Unauthorized` and `invalid_client` if client
+/// authentication fails.
+async fn introspect(
+    State(ctx): State<Arc<AppContext>>,
+    headers: HeaderMap,
+    Form(form): Form<IntrospectRequest>,
+) -> std::result::Result<impl IntoResponse, TokenError> {
+    ctx.oidc()?;
+
+    let caller = authenticate_client(&ctx, &headers, form.client_id, form.client_secret).await?;
+    if !caller.confidential {
+        return Err(TokenError::InvalidClient);
+    }
+
+    let token = sqlx::query_as::<_, IntrospectedToken>(
+        r"
+        SELECT t.scope, c.client_id, t.user_id, t.created_at, t.expires_at
+        FROM oauth_access_tokens t
+        JOIN oauth_clients c ON c.id = t.client_id
+        LEFT JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = $1
+          AND t.expires_at > now()
+          AND (t.user_id IS NULL
+               OR (u.status = 'active'
+                   AND (u.tokens_revoked_at IS NULL OR t.created_at >= u.tokens_revoked_at)))
+        ",
+    )
+    .bind(hash_token(form.token.trim()))
+    .fetch_optional(ctx.db())
+    .await?;
+
+    let introspection = token.map_or_else(Introspection::default, |token| Introspection {
+        active: true,
+        scope: Some(token.scope),
+        client_id: Some(token.client_id),
+        sub: token.user_id,
+        token_type: Some("Bearer"),
+        iat: Some(token.created_at.timestamp()),
+        exp: Some(token.expires_at.timestamp()),
+    });
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(introspection)))
+}
+
+/// Client credentials from an `Authorization: Basic` header, whose parts
+/// are form-encoded as RFC 6749 requires.
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (id, secret) = decoded.split_once(':')?;
+    let decode = |part: &str| {
+        form_urlencoded::parse(part.as_bytes())
+            .next()
+            .map(|(value, _)| value.into_owned())
+            .unwrap_or_default()
+    };
+
+    Some((decode(id), decode(secret)))
+}
+
+#[derive(sqlx::FromRow)]
+struct UserInfoRow {
+    #[sqlx(flatten)]
+    user: User,
+    scope: String,
+}
+
+/// `GET /oauth/userinfo`
+///
+/// Claims about the user an OIDC access token was issued for, limited to
+/// its scopes. The token is sent as `Authorization: Bearer`. Tokens stop
+/// working when revoked along with the user's other tokens, e.g. by a
+/// password change.
+///
+/// Responds with `401 Unauthorized` for a missing, unknown or expired token.
+async fn userinfo(State(ctx): State<Arc<AppContext>>, headers: HeaderMap) -> Result<Json<Value>> {
+    ctx.oidc()?;
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or(Error::Unauthenticated)?;
+
+    let row = sqlx::query_as::<_, UserInfoRow>(
+        r"
+        SELECT u.id, u.email, u.username, u.name, u.password_hash, u.role, u.phone, u.verified_at, u.status,
+               u.created_at, u.updated_at, u.deleted_at, u.user_metadata, u.app_metadata, t.scope
+        FROM oauth_access_tokens t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = $1
+          AND t.expires_at > now()
+          AND u.status = 'active'
+          AND (u.tokens_revoked_at IS NULL OR t.created_at >= u.tokens_revoked_at)
+        ",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    Ok(Json(oidc::user_claims(&row.user, &row.scope)))
+}
@@ -0,0 +1,452 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{AuthUser, generate_token, hash_token, is_valid_email, normalize_email},
+    i18n::Locale,
+    jobs::Job,
+    mail::EmailTemplate,
+    organizations::{self, AuthPolicy, INVITATION_TTL, OrgRole, Organization},
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route(
+            "/organizations",
+            get(list_organizations).post(create_organization),
+        )
+        .route("/organizations/active", post(switch_organization))
+        .route(
+            "/organizations/{id}/policy",
+            get(get_policy).put(update_policy),
+        )
+        .route("/organizations/{id}/invitations", post(invite_member))
+        .route("/organizations/invitations/accept", post(accept_invitation))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationRequest {
+    name: String,
+    /// URL-friendly identifier; derived from `name` when left out.
+    slug: Option<String>,
+}
+
+/// `POST /auth/organizations`
+///
+/// Creates an organization with the caller as its owner.
+///
+/// Responds with `201 Created`, `409 Conflict` if the slug is taken or
+/// `422 Unprocessable Entity` on invalid input.
+async fn create_organization(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<CreateOrganizationRequest>,
+) -> Result<(StatusCode, Json<Organization>)> {
+    let name = payload.name.trim();
+
+    if name.is_empty() || name.len() > 255 {
+        return Err(Error::Validation(String::from(
+            "name must be between 1 and 255 characters",
+        )));
+    }
+
+    let slug = slugify(payload.slug.as_deref().unwrap_or(name));
+
+    if slug.is_empty() || slug.len() > 64 {
+        return Err(Error::Validation(String::from(
+            "slug must be between 1 and 64 letters, digits or hyphens",
+        )));
+    }
+
+    let now = Utc::now();
+    let user_id = user.id();
+
+    let organization = ctx
+        .transaction(|tx| {
+            Box::pin(async move {
+                let organization = sqlx::query_as::<_, Organization>(
+                    r"
+                    INSERT INTO organizations (name, slug, created_at, updated_at)
+                    VALUES ($1, $2, $3, $3)
+                    ON CONFLICT (slug) DO NOTHING
+                    RETURNING id, name, slug, created_at
+                    ",
+                )
+                .bind(name)
+                .bind(&slug)
+                .bind(now)
+                .fetch_optional(&mut **tx)
+                .await?
+                .ok_or(Error::SlugTaken)?;
+
+                sqlx::query(
+                    r"
+                    INSERT INTO memberships (organization_id, user_id, role, created_at)
+                    VALUES ($1, $2, $3, $4)
+                    ",
+                )
+                .bind(organization.id)
+                .bind(user_id)
+                .bind(OrgRole::Owner.name())
+                .bind(now)
+                .execute(&mut **tx)
+                .await?;
+
+                Ok(organization)
+            })
+        })
+        .await?;
+
+    tracing::info!(%user_id, organization_id = %organization.id, "Organization created");
+
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberOrganization {
+    #[serde(flatten)]
+    organization: Organization,
+    role: OrgRole,
+    /// Whether this is the caller's active organization.
+    active: bool,
+}
+
+/// `GET /auth/organizations`
+///
+/// Lists the organizations the caller belongs to with their role in each,
+/// oldest membership first.
+async fn list_organizations(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+) -> Result<Json<Vec<MemberOrganization>>> {
+    let rows = sqlx::query_as::<_, (Uuid, String, String, DateTime<Utc>, String)>(
+        r"
+        SELECT o.id, o.name, o.slug, o.created_at, m.role
+        FROM memberships m
+        JOIN organizations o ON o.id = m.organization_id
+        WHERE m.user_id = $1
+        ORDER BY m.created_at
+        ",
+    )
+    .bind(user.id())
+    .fetch_all(ctx.db())
+    .await?;
+
+    let organizations = rows
+        .into_iter()
+        .map(|(id, name, slug, created_at, role)| {
+            Ok(MemberOrganization {
+                organization: Organization {
+                    id,
+                    name,
+                    slug,
+                    created_at,
+                },
+                role: role.parse()?,
+                active: user.organization_id() == Some(id),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Json(organizations))
+}
+
+/// `GET /auth/organizations/{id}/policy`
+///
+/// Returns the [`AuthPolicy`] the organization holds its members to. Any
+/// member may read it.
+///
+/// Responds with `200 OK` or `403 Forbidden` if the caller is not a member.
+async fn get_policy(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(organization_id): Path<Uuid>,
+) -> Result<Json<AuthPolicy>> {
+    organizations::require_role(ctx.db(), organization_id, user.id(), OrgRole::Member).await?;
+
+    AuthPolicy::for_organization(ctx.db(), organization_id)
+        .await?
+        .ok_or(Error::NotFound("organization"))
+        .map(Json)
+}
+
+/// `PUT /auth/organizations/{id}/policy`
+///
+/// Replaces the [`AuthPolicy`] the organization holds its members to, from
+/// their next login on. Only owners may change it; an empty object clears
+/// it.
+///
+/// Responds with `200 OK` and the stored policy, `403 Forbidden` if the
+/// caller is not an owner or `422 Unprocessable Entity` on an invalid
+/// policy.
+async fn update_policy(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(organization_id): Path<Uuid>,
+    Json(policy): Json<AuthPolicy>,
+) -> Result<Json<AuthPolicy>> {
+    organizations::require_role(ctx.db(), organization_id, user.id(), OrgRole::Owner).await?;
+
+    policy.validate(ctx.config().password_policy())?;
+
+    if !policy.save(ctx.db(), organization_id).await? {
+        return Err(Error::NotFound("organization"));
+    }
+
+    tracing::info!(user_id = %user.id(), %organization_id, "Organization auth policy updated");
+
+    Ok(Json(policy))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteMemberRequest {
+    email: String,
+    #[serde(default = "default_invite_role")]
+    role: OrgRole,
+}
+
+fn default_invite_role() -> OrgRole {
+    OrgRole::Member
+}
+
+#[derive(Debug, Serialize)]
+pub struct Invitation {
+    id: Uuid,
+    email: String,
+    role: OrgRole,
+    expires_at: DateTime<Utc>,
+}
+
+/// `POST /auth/organizations/{id}/invitations`
+///
+/// Emails an invitation to join the organization. Only owners and admins may
+/// invite, and only owners may invite further owners.
+///
+/// Responds with `201 Created`, `403 Forbidden` if the caller lacks the role
+/// or `422 Unprocessable Entity` on invalid input.
+async fn invite_member(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(organization_id): Path<Uuid>,
+    locale: Locale,
+    Json(payload): Json<InviteMemberRequest>,
+) -> Result<(StatusCode, Json<Invitation>)> {
+    let role =
+        organizations::require_role(ctx.db(), organization_id, user.id(), OrgRole::Admin).await?;
+
+    if payload.role > role {
+        return Err(Error::Forbidden);
+    }
+
+    let email = payload.email.trim();
+
+    if !is_valid_email(email) {
+        return Err(Error::Validation(String::from("email address is invalid")));
+    }
+
+    let name: String = sqlx::query_scalar("SELECT name FROM organizations WHERE id = $1")
+        .bind(organization_id)
+        .fetch_one(ctx.db())
+        .await?;
+
+    let token = generate_token();
+    let now = Utc::now();
+    let expires_at = now + INVITATION_TTL;
+
+    let id: Uuid = sqlx::query_scalar(
+        r"
+        INSERT INTO organization_invitations
+            (organization_id, email, role, token_hash, invited_by, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        ",
+    )
+    .bind(organization_id)
+    .bind(email)
+    .bind(payload.role.name())
+    .bind(hash_token(&token))
+    .bind(user.id())
+    .bind(now)
+    .bind(expires_at)
+    .fetch_one(ctx.db())
+    .await?;
+
+    let message = ctx.mail_templates().render(
+        EmailTemplate::OrganizationInvitation,
+        locale.language(),
+        email,
+        &json!({
+            "organization": name,
+            "role": payload.role.to_string(),
+            "code": token,
+            "ttl_days": INVITATION_TTL.num_days(),
+        }),
+    )?;
+
+    ctx.jobs()
+        .enqueue(Job::SendEmail { email: message })
+        .await?;
+
+    tracing::info!(user_id = %user.id(), %organization_id, invitation_id = %id, "Member invited");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Invitation {
+            id,
+            email: email.to_owned(),
+            role: payload.role,
+            expires_at,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationRequest {
+    token: String,
+}
+
+/// `POST /auth/organizations/invitations/accept`
+///
+/// Redeems an invitation, adding the caller to the organization. The
+/// invitation must have been sent to the caller's email address.
+///
+/// Responds with the organization, `401 Unauthorized` for an unknown,
+/// expired or already accepted invitation, or `403 Forbidden` if it was
+/// addressed to someone else.
+async fn accept_invitation(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<Json<MemberOrganization>> {
+    let user_id = user.id();
+
+    let config = ctx.config();
+    let (organization_id, organization) = ctx
+        .transaction(|tx| {
+            Box::pin(async move {
+                let (organization_id, invited_email, role) =
+                    sqlx::query_as::<_, (Uuid, String, String)>(
+                        r"
+                        UPDATE organization_invitations
+                        SET accepted_at = now()
+                        WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > now()
+                        RETURNING organization_id, email, role
+                        ",
+                    )
+                    .bind(hash_token(&payload.token))
+                    .fetch_optional(&mut **tx)
+                    .await?
+                    .ok_or(Error::InvalidToken)?;
+
+                let email: String =
+                    sqlx::query_scalar("SELECT email_normalized FROM users WHERE id = $1")
+                        .bind(user_id)
+                        .fetch_optional(&mut **tx)
+                        .await?
+                        .ok_or(Error::Unauthenticated)?;
+
+                // Failing rolls the invitation back to unaccepted.
+                if email != normalize_email(&invited_email, config.auth().email_normalization()) {
+                    return Err(Error::Forbidden);
+                }
+
+                // Existing members keep their current role.
+                sqlx::query(
+                    r"
+                    INSERT INTO memberships (organization_id, user_id, role, created_at)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (organization_id, user_id) DO NOTHING
+                    ",
+                )
+                .bind(organization_id)
+                .bind(user_id)
+                .bind(&role)
+                .bind(Utc::now())
+                .execute(&mut **tx)
+                .await?;
+
+                let organization = sqlx::query_as::<_, Organization>(
+                    "SELECT id, name, slug, created_at FROM organizations WHERE id = $1",
+                )
+                .bind(organization_id)
+                .fetch_one(&mut **tx)
+                .await?;
+
+                Ok((organization_id, organization))
+            })
+        })
+        .await?;
+
+    let role = organizations::member_role(ctx.db(), organization_id, user_id)
+        .await?
+        .ok_or(Error::Forbidden)?;
+
+    tracing::info!(%user_id, %organization_id, "Invitation accepted");
+
+    Ok(Json(MemberOrganization {
+        active: user.organization_id() == Some(organization.id),
+        organization,
+        role,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwitchOrganizationRequest {
+    /// The organization to act in, or `null` to leave organization context.
+    organization_id: Option<Uuid>,
+}
+
+/// `POST /auth/organizations/active`
+///
+/// Switches the organization the caller's session acts in. The choice is
+/// stored on the session, so it applies to the session cookie straight away;
+/// a fresh token pair carrying the new organization is returned for API
+/// clients.
+///
+/// Responds with `403 Forbidden` if the caller is not a member of the
+/// organization or authenticated with an API key, or `401 Unauthorized` if
+/// the session has ended.
+async fn switch_organization(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<SwitchOrganizationRequest>,
+) -> Result<Json<TokenPair>> {
+    let session_id = user.session_id().ok_or(Error::Forbidden)?;
+
+    if let Some(organization_id) = payload.organization_id {
+        organizations::require_role(ctx.db(), organization_id, user.id(), OrgRole::Member).await?;
+    }
+
+    let session = ctx
+        .sessions()
+        .set_active_organization(session_id, payload.organization_id)
+        .await?
+        .ok_or(Error::InvalidToken)?;
+
+    tracing::info!(user_id = %user.id(), %session_id, organization_id = ?payload.organization_id, "Active organization switched");
+
+    Ok(Json(ctx.issue_tokens(&session).await?))
+}
+
+/// Lowercases `input` and collapses everything but ASCII letters and digits
+/// into single hyphens.
+fn slugify(input: &str) -> String {
+    input
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::{self, PasswordWarning, generate_token, hash_token},
+    events::Event,
+    i18n::Locale,
+    jobs::Job,
+    mail::EmailTemplate,
+    repositories::PgUserStore,
+    security::Captcha,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
+}
+
+/// Issues a password reset token for `user_id` and emails the link to
+/// `email`, in the language of `locale`.
+///
+/// Any link sent earlier is invalidated. Delivery failures are logged rather
+/// than returned so that they never fail the calling request.
+///
+/// ## Errors
+/// * Database errors while storing the token
+pub(super) async fn send_reset_email(
+    ctx: &AppContext,
+    user_id: Uuid,
+    email: &str,
+    locale: &Locale,
+) -> Result<()> {
+    let config = ctx.config();
+    let config = config.auth();
+    let token = generate_token();
+    let now = Utc::now();
+    let ttl = Duration::from_std(config.lifetimes().password_reset()).unwrap_or(Duration::MAX);
+
+    // A new request supersedes any link sent earlier.
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(ctx.db())
+        .await?;
+
+    sqlx::query(
+        r"
+        INSERT INTO password_reset_tokens (user_id, token_hash, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ",
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(now)
+    .bind(now + ttl)
+    .execute(ctx.db())
+    .await?;
+
+    let base_url = config.password_reset_url().map_or_else(
+        || format!("{}/auth/reset-password", ctx.config().server().url()),
+        ToOwned::to_owned,
+    );
+
+    let email = ctx.mail_templates().render(
+        EmailTemplate::PasswordReset,
+        locale.language(),
+        email,
+        &json!({
+            "url": format!("{base_url}?token={token}"),
+            "ttl_minutes": ttl.num_minutes(),
+        }),
+    )?;
+
+    ctx.jobs().enqueue(Job::SendEmail { email }).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    email: String,
+}
+
+/// `POST /auth/forgot-password`
+///
+/// Emails a single-use password reset link if an account with the given
+/// email exists. Always answers `202 Accepted` so the endpoint cannot be used
+/// to discover registered emails.
+///
+/// When CAPTCHAs are configured, requests without a valid token get
+/// `403 Forbidden`.
+async fn forgot_password(
+    State(ctx): State<Arc<AppContext>>,
+    _: Captcha,
+    locale: Locale,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<StatusCode> {
+    let user = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, email FROM users WHERE email_normalized = $1 AND deleted_at IS NULL",
+    )
+    .bind(ctx.normalize_email(&payload.email))
+    .fetch_optional(ctx.db())
+    .await?;
+
+    if let Some((user_id, email)) = user {
+        send_reset_email(&ctx, user_id, &email, &locale).await?;
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    token: String,
+    password: String,
+}
+
+/// `POST /auth/reset-password`
+///
+/// Sets a new password using a token from a reset email. The token is
+/// consumed, and every existing session of the user is ended so that anyone
+/// holding the old password is logged out.
+///
+/// Responds with `204 No Content`, `401 Unauthorized` for an unknown,
+/// expired or already used token, or `422 Unprocessable Entity` if the new
+/// password is rejected.
+async fn reset_password(
+    State(ctx): State<Arc<AppContext>>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<(PasswordWarning, StatusCode)> {
+    // Looked up ahead of consuming the token, so the new password is held
+    // to the policies of the user's organizations.
+    let token_user: Option<Uuid> = sqlx::query_scalar(
+        r"
+        SELECT user_id FROM password_reset_tokens
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        ",
+    )
+    .bind(hash_token(&payload.token))
+    .fetch_optional(ctx.db())
+    .await?;
+    let passwords = match token_user {
+        Some(user_id) => ctx.password_policy_for(user_id).await?,
+        None => ctx.config().password_policy().clone(),
+    };
+    auth::validate_password(&passwords, &payload.password)?;
+    let warning = ctx
+        .pwned_passwords()
+        .check(&passwords, &payload.password)
+        .await?;
+
+    let password_hash = ctx.hashing().hash(&payload.password).await?;
+    let mut tx = ctx.db().begin().await?;
+
+    let user_id: Uuid = sqlx::query_scalar(
+        r"
+        UPDATE password_reset_tokens
+        SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id
+        ",
+    )
+    .bind(hash_token(&payload.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    PgUserStore::update_password_hash(&mut *tx, user_id, Some(&password_hash)).await?;
+
+    let mut revoked: Vec<Uuid> =
+        sqlx::query_scalar("DELETE FROM sessions WHERE user_id = $1 RETURNING id")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+    tx.commit().await?;
+    ctx.sessions().forget(&revoked).await;
+    // Sessions kept outside the database are not part of the transaction.
+    revoked.extend(ctx.sessions().delete_all(user_id).await?);
+
+    tracing::info!(%user_id, "Password reset");
+
+    ctx.publish(Event::SessionsRevoked {
+        user_id,
+        session_ids: revoked,
+    })
+    .await?;
+
+    Ok((warning, StatusCode::NO_CONTENT))
+}
@@ -0,0 +1,318 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::AuthUser,
+    events::{Event, LoginMethod},
+    security::{Captcha, ClientIp},
+    sessions::DeviceInfo,
+    sms::{OtpPurpose, Sms, normalize_phone},
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/phone", post(add_phone).delete(remove_phone))
+        .route("/phone/verify", post(verify_phone))
+        .route("/phone/otp", post(request_login_code))
+        .route("/phone/login", post(login_with_code))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PhoneRequest {
+    phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PhoneCodeRequest {
+    phone: String,
+    code: String,
+}
+
+fn parse_phone(input: &str) -> Result<String> {
+    normalize_phone(input).ok_or_else(|| {
+        Error::Validation(String::from(
+            "phone must be an international number such as +15551234567",
+        ))
+    })
+}
+
+fn code_message(code: &str, ttl: u64, action: &str) -> String {
+    format!(
+        "{code} is your code to {action}. It expires in {} minutes. Do not share it.",
+        ttl.div_ceil(60)
+    )
+}
+
+/// `POST /auth/phone`
+///
+/// Starts adding a phone number to the caller's account by texting it a
+/// code, to be confirmed at `POST /auth/phone/verify`. The number replaces
+/// any existing one once confirmed. Requires a session; API keys get
+/// `403 Forbidden`.
+///
+/// Responds with `202 Accepted`, `409 Conflict` if another account uses the
+/// number, `422 Unprocessable Entity` for a malformed number, or
+/// `429 Too Many Requests` if the number was sent codes too recently or too
+/// often. All phone endpoints answer `404 Not Found` unless the `sms` config
+/// section is present.
+async fn add_phone(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<PhoneRequest>,
+) -> Result<StatusCode> {
+    let sender = ctx.sms()?;
+
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    let phone = parse_phone(&payload.phone)?;
+
+    let owner: Option<Uuid> = sqlx::query_scalar("SELECT id FROM users WHERE phone = $1")
+        .bind(&phone)
+        .fetch_optional(ctx.db())
+        .await?;
+
+    match owner {
+        Some(owner) if owner == user.id() => {
+            return Err(Error::Validation(String::from(
+                "this phone number is already on your account",
+            )));
+        }
+        Some(_) => return Err(Error::PhoneTaken),
+        None => {}
+    }
+
+    let otp = ctx.phone_otp();
+    let code = otp.issue(user.id(), &phone, OtpPurpose::Verify).await?;
+
+    sender
+        .send(Sms {
+            body: code_message(&code, otp.config().code_ttl(), "confirm your phone number"),
+            to: phone,
+        })
+        .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// `POST /auth/phone/verify`
+///
+/// Confirms the number sent to `POST /auth/phone` with the code texted to
+/// it, and sets it as the caller's phone number.
+///
+/// Responds with `204 No Content`, `401 Unauthorized` for a wrong, expired
+/// or used code, or `409 Conflict` if another account took the number in
+/// the meantime.
+async fn verify_phone(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    user: AuthUser,
+    Json(payload): Json<PhoneCodeRequest>,
+) -> Result<StatusCode> {
+    ctx.sms()?;
+
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    let phone = parse_phone(&payload.phone)?;
+    let redeemed = ctx
+        .phone_otp()
+        .verify(&phone, OtpPurpose::Verify, &payload.code)
+        .await?
+        .filter(|redeemed| redeemed.user_id == user.id())
+        .ok_or(Error::InvalidToken)?;
+
+    sqlx::query("UPDATE users SET phone = $2, updated_at = now() WHERE id = $1")
+        .bind(user.id())
+        .bind(&redeemed.phone)
+        .execute(ctx.db())
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(db) if db.is_unique_violation() => Error::PhoneTaken,
+            other => other.into(),
+        })?;
+
+    ctx.publish(Event::PhoneAdded {
+        user_id: user.id(),
+        phone: redeemed.phone,
+        ip,
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /auth/phone`
+///
+/// Removes the caller's phone number, which stops phone login for the
+/// account. Requires a session.
+///
+/// Responds with `204 No Content`, or `404 Not Found` if the account has no
+/// phone number.
+async fn remove_phone(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    user: AuthUser,
+) -> Result<StatusCode> {
+    ctx.sms()?;
+
+    if user.session_id().is_none() {
+        return Err(Error::Forbidden);
+    }
+
+    let removed: Option<String> = sqlx::query_scalar(
+        r"
+        UPDATE users u
+        SET phone = NULL, updated_at = now()
+        FROM users old
+        WHERE u.id = $1 AND old.id = u.id AND old.phone IS NOT NULL
+        RETURNING old.phone
+        ",
+    )
+    .bind(user.id())
+    .fetch_optional(ctx.db())
+    .await?;
+
+    let phone = removed.ok_or(Error::NotFound("phone number"))?;
+
+    ctx.publish(Event::PhoneRemoved {
+        user_id: user.id(),
+        phone,
+        ip,
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `POST /auth/phone/otp`
+///
+/// Texts a login code to the number if an account has it. Always answers
+/// `202 Accepted` so the endpoint cannot be used to discover registered
+/// numbers; for the same reason, requests over the per-number send limits
+/// are dropped silently.
+///
+/// When CAPTCHAs are configured, requests without a valid token get
+/// `403 Forbidden`.
+async fn request_login_code(
+    State(ctx): State<Arc<AppContext>>,
+    _: Captcha,
+    Json(payload): Json<PhoneRequest>,
+) -> Result<StatusCode> {
+    let sender = ctx.sms()?;
+    let phone = parse_phone(&payload.phone)?;
+
+    let user_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT id FROM users WHERE phone = $1 AND status = 'active'")
+            .bind(&phone)
+            .fetch_optional(ctx.db())
+            .await?;
+
+    let Some(user_id) = user_id else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+
+    let otp = ctx.phone_otp();
+    let code = match otp.issue(user_id, &phone, OtpPurpose::Login).await {
+        Ok(code) => code,
+        Err(Error::RateLimited { .. }) => {
+            tracing::warn!(%user_id, "Login code not sent, number over its send limit");
+            return Ok(StatusCode::ACCEPTED);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let sms = Sms {
+        body: code_message(&code, otp.config().code_ttl(), "sign in"),
+        to: phone,
+    };
+
+    if let Err(err) = sender.send(sms).await {
+        tracing::error!(%user_id, error = %err, "Failed to send login code");
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// `POST /auth/phone/login`
+///
+/// Redeems a code from `POST /auth/phone/otp` and logs the user in exactly
+/// like `POST /auth/login`: a session cookie is set and a token pair
+/// returned. Each code works once and is burned after too many wrong
+/// guesses.
+///
+/// Responds with `401 Unauthorized` for a wrong, expired or used code, and
+/// `403 Forbidden` when `auth.require_email_verification` is enabled and the
+/// email is unverified.
+async fn login_with_code(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Json(payload): Json<PhoneCodeRequest>,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let outcome = phone_login(&ctx, &device, jar, payload).await;
+
+    ctx.reports()
+        .record_outcome(&LoginMethod::Phone, &device.device, &outcome)
+        .await;
+
+    outcome
+}
+
+async fn phone_login(
+    ctx: &AppContext,
+    device: &DeviceInfo,
+    jar: CookieJar,
+    payload: PhoneCodeRequest,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    ctx.sms()?;
+
+    let phone = normalize_phone(&payload.phone).ok_or(Error::InvalidToken)?;
+    let redeemed = ctx
+        .phone_otp()
+        .verify(&phone, OtpPurpose::Login, &payload.code)
+        .await?
+        .ok_or(Error::InvalidToken)?;
+    let user_id = redeemed.user_id;
+
+    // The code proves the number is still this account's only if it was not
+    // removed or moved since the code was sent.
+    let verified = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        "SELECT verified_at FROM users WHERE id = $1 AND phone = $2",
+    )
+    .bind(user_id)
+    .bind(&redeemed.phone)
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    if verified.is_none() && ctx.config().auth().require_email_verification() {
+        return Err(Error::EmailNotVerified);
+    }
+
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::Phone)
+        .await?;
+    let tokens = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(%user_id, session_id = %session.id, "User logged in with phone code");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::Phone,
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(tokens)))
+}
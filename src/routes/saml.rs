@@ -0,0 +1,492 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Form, Json, Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::AuthUser,
+    events::{Event, LoginMethod},
+    models::NewUser,
+    repositories::PgUserStore,
+    saml::{self, Assertion},
+    sessions::DeviceInfo,
+    tokens::TokenPair,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/saml/{tenant}/metadata", get(metadata))
+        .route("/saml/{tenant}/login", get(login))
+        .route("/saml/{tenant}/acs", post(assertion_consumer))
+        .route("/saml/{tenant}/logout", post(logout))
+        .route("/saml/{tenant}/slo", post(single_logout))
+}
+
+/// `GET /auth/saml/{tenant}/metadata`
+///
+/// Service-provider metadata to register with the tenant's identity
+/// provider. Its URL doubles as the SP entity ID.
+///
+/// Responds with `404 Not Found` if the tenant is not configured.
+async fn metadata(
+    State(ctx): State<Arc<AppContext>>,
+    Path(tenant): Path<String>,
+) -> Result<impl IntoResponse> {
+    let metadata = ctx.saml().metadata(&tenant)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/samlmetadata+xml")],
+        metadata,
+    ))
+}
+
+/// `GET /auth/saml/{tenant}/login`
+///
+/// Starts SP-initiated single sign-on by redirecting to the tenant's
+/// identity provider. The request ID is remembered so only a response to it
+/// is accepted, once, within ten minutes.
+///
+/// Responds with `404 Not Found` if the tenant is not configured.
+async fn login(State(ctx): State<Arc<AppContext>>, Path(tenant): Path<String>) -> Result<Redirect> {
+    let request = ctx.saml().authn_request(&tenant)?;
+    let now = Utc::now();
+
+    sqlx::query(
+        r"
+        INSERT INTO saml_requests (id, tenant, purpose, created_at, expires_at)
+        VALUES ($1, $2, 'login', $3, $4)
+        ",
+    )
+    .bind(&request.id)
+    .bind(&tenant)
+    .bind(now)
+    .bind(now + Duration::seconds(saml::REQUEST_TTL))
+    .execute(ctx.db())
+    .await?;
+
+    Ok(Redirect::to(&request.url))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcsForm {
+    #[serde(rename = "SAMLResponse")]
+    saml_response: String,
+}
+
+/// `POST /auth/saml/{tenant}/acs`
+///
+/// Assertion consumer service receiving the identity provider's response
+/// (HTTP-POST binding). Once the response is validated the user is logged in
+/// exactly like `POST /auth/login`: a session cookie is set and a token pair
+/// returned.
+///
+/// The subject is resolved to a local user in this order:
+/// 1. a user already linked to the tenant's NameID;
+/// 2. a user with the asserted email address;
+/// 3. a newly created, verified user without a password, named after the
+///    configured name attribute.
+///
+/// The last two only for addresses within the tenant's `domains`.
+///
+/// Responds with `401 Unauthorized` if the response fails validation, does
+/// not answer a pending request or asserts an address outside the tenant's
+/// domains for an unlinked NameID, and `404 Not Found` if the tenant is not
+/// configured.
+async fn assertion_consumer(
+    State(ctx): State<Arc<AppContext>>,
+    Path(tenant): Path<String>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Form(form): Form<AcsForm>,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let outcome = saml_login(&ctx, &tenant, &device, jar, form).await;
+
+    // Unknown tenants are no login attempt, and would let anyone grow the
+    // set of methods reported on.
+    if ctx.config().saml().tenant(&tenant).is_some() {
+        ctx.reports()
+            .record_outcome(&LoginMethod::Saml(tenant), &device.device, &outcome)
+            .await;
+    }
+
+    outcome
+}
+
+async fn saml_login(
+    ctx: &AppContext,
+    tenant: &str,
+    device: &DeviceInfo,
+    jar: CookieJar,
+    form: AcsForm,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let assertion = ctx.saml().validate_response(tenant, &form.saml_response)?;
+
+    // Consuming the request makes every response single use.
+    let pending: Option<String> = sqlx::query_scalar(
+        r"
+        DELETE FROM saml_requests
+        WHERE id = $1 AND tenant = $2 AND purpose = 'login' AND expires_at > now()
+        RETURNING id
+        ",
+    )
+    .bind(&assertion.request_id)
+    .bind(tenant)
+    .fetch_optional(ctx.db())
+    .await?;
+
+    if pending.is_none() {
+        return Err(Error::InvalidToken);
+    }
+
+    let user_id = resolve_user(ctx, tenant, &assertion).await?;
+
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::Saml(tenant.to_owned()))
+        .await?;
+    let pair = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    // Remembered so a logout at the IdP can end the session here.
+    sqlx::query(
+        r"
+        INSERT INTO saml_sessions (session_id, user_id, tenant, name_id, session_index, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ",
+    )
+    .bind(session.id)
+    .bind(user_id)
+    .bind(tenant)
+    .bind(&assertion.name_id)
+    .bind(&assertion.session_index)
+    .bind(Utc::now())
+    .bind(Utc::now() + ctx.config().auth().lifetimes().session())
+    .execute(ctx.db())
+    .await?;
+
+    tracing::info!(%user_id, %tenant, session_id = %session.id, "User logged in with SAML");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::Saml(tenant.to_owned()),
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(pair)))
+}
+
+/// `POST /auth/saml/{tenant}/logout`
+///
+/// Starts SP-initiated single logout: ends the caller's session and clears
+/// the session cookie like `POST /auth/logout`, then, if the session was
+/// started through the tenant's identity provider and that supports single
+/// logout, redirects there with a `LogoutRequest` so the user is logged out
+/// of it too. The request ID is remembered so only a response to it is
+/// accepted, once, within ten minutes.
+///
+/// Responds with `204 No Content` when there is no one to redirect to,
+/// `401 Unauthorized` without a session and `404 Not Found` if the tenant is
+/// not configured.
+async fn logout(
+    State(ctx): State<Arc<AppContext>>,
+    Path(tenant): Path<String>,
+    jar: CookieJar,
+    user: AuthUser,
+) -> Result<(CookieJar, Response)> {
+    let single_logout = ctx
+        .config()
+        .saml()
+        .tenant(&tenant)
+        .ok_or(Error::NotFound("saml tenant"))?
+        .idp_slo_url()
+        .is_some();
+    let session_id = user.session_id().ok_or(Error::Forbidden)?;
+
+    let subject = sqlx::query_as::<_, (String, Option<String>)>(
+        r"
+        DELETE FROM saml_sessions
+        WHERE session_id = $1 AND tenant = $2
+        RETURNING name_id, session_index
+        ",
+    )
+    .bind(session_id)
+    .bind(&tenant)
+    .fetch_optional(ctx.db())
+    .await?;
+
+    ctx.sessions().delete(session_id).await?;
+
+    tracing::info!(user_id = %user.id(), %session_id, %tenant, "User logged out through SAML");
+
+    ctx.publish(Event::SessionsRevoked {
+        user_id: user.id(),
+        session_ids: vec![session_id],
+    })
+    .await?;
+
+    let jar = jar.remove(ctx.session_cookies().removal());
+
+    let Some((name_id, session_index)) = subject.filter(|_| single_logout) else {
+        return Ok((jar, StatusCode::NO_CONTENT.into_response()));
+    };
+
+    let request = ctx
+        .saml()
+        .logout_request(&tenant, &name_id, session_index.as_deref())?;
+    let now = Utc::now();
+
+    sqlx::query(
+        r"
+        INSERT INTO saml_requests (id, tenant, purpose, created_at, expires_at)
+        VALUES ($1, $2, 'logout', $3, $4)
+        ",
+    )
+    .bind(&request.id)
+    .bind(&tenant)
+    .bind(now)
+    .bind(now + Duration::seconds(saml::REQUEST_TTL))
+    .execute(ctx.db())
+    .await?;
+
+    Ok((jar, Redirect::to(&request.url).into_response()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SloForm {
+    #[serde(rename = "SAMLRequest")]
+    saml_request: Option<String>,
+    #[serde(rename = "SAMLResponse")]
+    saml_response: Option<String>,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+}
+
+/// `POST /auth/saml/{tenant}/slo`
+///
+/// Single logout service receiving the identity provider's logout messages
+/// (HTTP-POST binding).
+///
+/// A `SAMLRequest` means the user logged out at the IdP, or of another
+/// service provider: every session started from the named IdP sessions of
+/// the subject, or from all of them if none are named, ends here. The user
+/// is then redirected back to the IdP with a `LogoutResponse` and the
+/// `RelayState`, or gets `204 No Content` if the IdP has no SLO URL
+/// configured.
+///
+/// A `SAMLResponse` answers a logout started at `POST
+/// /auth/saml/{tenant}/logout`, whose session has already ended; it is
+/// consumed and `204 No Content` returned.
+///
+/// Responds with `401 Unauthorized` if the message fails validation or a
+/// response does not answer a pending request, `422 Unprocessable Entity`
+/// if neither message is given and `404 Not Found` if the tenant is not
+/// configured.
+async fn single_logout(
+    State(ctx): State<Arc<AppContext>>,
+    Path(tenant): Path<String>,
+    Form(form): Form<SloForm>,
+) -> Result<Response> {
+    if let Some(encoded) = form.saml_request {
+        let request = ctx.saml().validate_logout_request(&tenant, &encoded)?;
+
+        let ended = sqlx::query_as::<_, (Uuid, Uuid)>(
+            r"
+            DELETE FROM saml_sessions
+            WHERE tenant = $1 AND name_id = $2
+              AND (cardinality($3::text[]) = 0 OR session_index = ANY($3))
+            RETURNING user_id, session_id
+            ",
+        )
+        .bind(&tenant)
+        .bind(&request.name_id)
+        .bind(&request.session_indexes)
+        .fetch_all(ctx.db())
+        .await?;
+
+        let mut revoked: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (user_id, session_id) in ended {
+            ctx.sessions().delete(session_id).await?;
+            revoked.entry(user_id).or_default().push(session_id);
+        }
+
+        for (user_id, session_ids) in revoked {
+            tracing::info!(%user_id, %tenant, sessions = session_ids.len(), "User logged out by SAML identity provider");
+
+            ctx.publish(Event::SessionsRevoked {
+                user_id,
+                session_ids,
+            })
+            .await?;
+        }
+
+        if ctx
+            .config()
+            .saml()
+            .tenant(&tenant)
+            .is_some_and(|idp| idp.idp_slo_url().is_some())
+        {
+            let url =
+                ctx.saml()
+                    .logout_response(&tenant, &request.id, form.relay_state.as_deref())?;
+
+            return Ok(Redirect::to(&url).into_response());
+        }
+
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let encoded = form.saml_response.ok_or_else(|| {
+        Error::Validation(String::from("SAMLRequest or SAMLResponse is required"))
+    })?;
+    let request_id = ctx.saml().validate_logout_response(&tenant, &encoded)?;
+
+    let pending: Option<String> = sqlx::query_scalar(
+        r"
+        DELETE FROM saml_requests
+        WHERE id = $1 AND tenant = $2 AND purpose = 'logout' AND expires_at > now()
+        RETURNING id
+        ",
+    )
+    .bind(&request_id)
+    .bind(&tenant)
+    .fetch_optional(ctx.db())
+    .await?;
+
+    if pending.is_none() {
+        return Err(Error::InvalidToken);
+    }
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Finds or provisions the local user for `assertion` and links it to the
+/// tenant's NameID.
+async fn resolve_user(ctx: &AppContext, tenant: &str, assertion: &Assertion) -> Result<Uuid> {
+    let now = Utc::now();
+    let mut created = false;
+    let mut claimed = false;
+    let mut revoked = Vec::new();
+    let mut tx = ctx.db().begin().await?;
+
+    let linked: Option<Uuid> =
+        sqlx::query_scalar("SELECT user_id FROM saml_accounts WHERE tenant = $1 AND name_id = $2")
+            .bind(tenant)
+            .bind(&assertion.name_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    if let Some(user_id) = linked {
+        return Ok(user_id);
+    }
+
+    // Any other tenant's IdP could assert the address just as well.
+    if !ctx
+        .config()
+        .saml()
+        .tenant(tenant)
+        .is_some_and(|idp| idp.vouches_for(&assertion.email))
+    {
+        return Err(Error::Saml(String::from(
+            "identity provider does not speak for this email domain",
+        )));
+    }
+
+    let existing = sqlx::query_as::<_, (Uuid, bool)>(
+        "SELECT id, verified_at IS NOT NULL FROM users WHERE email_normalized = $1 AND deleted_at IS NULL",
+    )
+    .bind(ctx.normalize_email(&assertion.email))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match existing {
+        Some((user_id, true)) => user_id,
+        Some((user_id, false)) => {
+            // The IdP vouches for the address, the unverified registration
+            // does not; see the matching case of the OAuth callback.
+            sqlx::query(
+                r"
+                UPDATE users
+                SET verified_at = now(), password_hash = NULL, updated_at = now()
+                WHERE id = $1
+                ",
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+            revoked = sqlx::query_scalar("DELETE FROM sessions WHERE user_id = $1 RETURNING id")
+                .bind(user_id)
+                .fetch_all(&mut *tx)
+                .await?;
+            claimed = true;
+
+            user_id
+        }
+        None => {
+            let user_id = PgUserStore::insert(
+                &mut *tx,
+                &NewUser {
+                    name: assertion.name.as_deref(),
+                    verified_at: Some(now),
+                    ..NewUser::new(&assertion.email, ctx.config().auth().email_normalization())
+                },
+            )
+            .await?
+            .ok_or(Error::EmailTaken)?
+            .id;
+
+            tracing::info!(%user_id, %tenant, "User provisioned from SAML assertion");
+            created = true;
+
+            user_id
+        }
+    };
+
+    sqlx::query(
+        r"
+        INSERT INTO saml_accounts (user_id, tenant, name_id, created_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (tenant, name_id) DO NOTHING
+        ",
+    )
+    .bind(user_id)
+    .bind(tenant)
+    .bind(&assertion.name_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    ctx.sessions().forget(&revoked).await;
+    if claimed {
+        // Sessions kept outside the database are not part of the transaction.
+        revoked.extend(ctx.sessions().delete_all(user_id).await?);
+    }
+
+    if created {
+        ctx.publish(Event::UserCreated {
+            user_id,
+            email: assertion.email.clone(),
+        })
+        .await?;
+    }
+    if !revoked.is_empty() {
+        ctx.publish(Event::SessionsRevoked {
+            user_id,
+            session_ids: revoked,
+        })
+        .await?;
+    }
+
+    Ok(user_id)
+}
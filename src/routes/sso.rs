@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    extract::{OriginalUri, Query, State},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::oidc::redirect_to;
+use crate::{
+    AppContext, Error, Result,
+    auth::{AuthUser, generate_token, hash_token},
+    config::{Config, SsoApp, SsoConfig},
+    sessions::Session,
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/sso/token", post(token))
+        .route("/authorize", get(authorize))
+        .route("/authorize/token", post(redeem))
+}
+
+/// The `sso` config section, or [`Error::Disabled`] without one.
+fn sso(config: &Config) -> Result<&SsoConfig> {
+    config.sso().ok_or(Error::Disabled("single sign-on"))
+}
+
+/// The registered application `id`.
+fn app<'a>(sso: &'a SsoConfig, id: &str) -> Result<&'a SsoApp> {
+    sso.app(id).ok_or(Error::NotFound("application"))
+}
+
+/// The session of the caller, if they were authenticated by the session
+/// cookie rather than a token or API key.
+fn cookie_session(user: AuthUser, session: Option<Extension<Session>>) -> Option<Session> {
+    session
+        .map(|Extension(session)| session)
+        .filter(|session| session.user_id == Some(user.id()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    app: String,
+}
+
+/// `POST /auth/sso/token`
+///
+/// Issues an access token for application `app` to a page of that
+/// application sharing the session cookie, which must come from one of its
+/// `sso.apps.{app}.origins`. The token names `app` in its `aud` claim and
+/// comes without a refresh token: the page asks again once it expires, and
+/// gets one as long as the session lasts. Like every state-changing request
+/// made with the session cookie, it needs the `X-CSRF-Token` header.
+///
+/// Responds with `401 Unauthorized` without a session cookie, `403 Forbidden`
+/// from any other origin, or `404 Not Found` for an unknown application or
+/// when the `sso` config section is absent.
+async fn token(
+    State(ctx): State<Arc<AppContext>>,
+    headers: HeaderMap,
+    user: AuthUser,
+    session: Option<Extension<Session>>,
+    Json(payload): Json<TokenRequest>,
+) -> Result<impl IntoResponse> {
+    let config = ctx.config();
+    let app = app(sso(&config)?, &payload.app)?;
+
+    let session = cookie_session(user, session).ok_or(Error::Unauthenticated)?;
+
+    let origin = headers
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok());
+    if !origin.is_some_and(|origin| app.allows_origin(origin)) {
+        return Err(Error::Forbidden);
+    }
+
+    let token = ctx.issue_access_token(&session, &payload.app).await?;
+
+    tracing::info!(session_id = %session.id, app = %payload.app, "Single sign-on token issued");
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(token)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeQuery {
+    app: Option<String>,
+    redirect_uri: Option<String>,
+    state: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+}
+
+/// `GET /auth/authorize`
+///
+/// Central login for applications on other domains than the session cookie
+/// reaches. A logged-in user is sent straight back to `redirect_uri` with a
+/// one-time `code`, which the application exchanges at
+/// `POST /auth/authorize/token` within `sso.code_ttl`. Applications prove the
+/// code is theirs with PKCE, of which only `S256` is supported.
+///
+/// An unknown application or unregistered `redirect_uri` gets
+/// `404 Not Found` or `422 Unprocessable Entity` here; a missing or
+/// unsupported code challenge is reported to the application by redirecting
+/// with `error=invalid_request`. Users without a session are sent to
+/// `sso.login_url` when configured, and get `401 Unauthorized` otherwise.
+async fn authorize(
+    State(ctx): State<Arc<AppContext>>,
+    OriginalUri(uri): OriginalUri,
+    user: Result<AuthUser>,
+    session: Option<Extension<Session>>,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Redirect> {
+    let config = ctx.config();
+    let sso = sso(&config)?;
+
+    let app_id = query
+        .app
+        .as_deref()
+        .ok_or_else(|| Error::Validation(String::from("app is required")))?;
+    let app = app(sso, app_id)?;
+    let redirect_uri = query
+        .redirect_uri
+        .as_deref()
+        .filter(|uri| app.allows_redirect(uri))
+        .ok_or_else(|| {
+            Error::Validation(String::from(
+                "redirect_uri is not registered for this application",
+            ))
+        })?;
+
+    let state = query.state.as_deref();
+    let Some(code_challenge) = query
+        .code_challenge
+        .as_deref()
+        .filter(|_| query.code_challenge_method.as_deref() == Some("S256"))
+    else {
+        return redirect_to(redirect_uri, &[("error", "invalid_request")], state);
+    };
+
+    let Some(session) = user.ok().and_then(|user| cookie_session(user, session)) else {
+        return match sso.login_url() {
+            Some(login_url) => {
+                let return_to = format!("{}{uri}", config.server().url());
+                redirect_to(login_url, &[("return_to", &return_to)], None)
+            }
+            None => Err(Error::Unauthenticated),
+        };
+    };
+
+    let code = generate_token();
+    let now = Utc::now();
+    let ttl = Duration::seconds(i64::try_from(sso.code_ttl()).unwrap_or(i64::MAX));
+
+    sqlx::query(
+        r"
+        INSERT INTO sso_codes
+            (code_hash, app, session_id, redirect_uri, code_challenge, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ",
+    )
+    .bind(hash_token(&code))
+    .bind(app_id)
+    .bind(session.id)
+    .bind(redirect_uri)
+    .bind(code_challenge)
+    .bind(now)
+    .bind(now + ttl)
+    .execute(ctx.db())
+    .await?;
+
+    tracing::info!(session_id = %session.id, app = %app_id, "Single sign-on code issued");
+
+    redirect_to(redirect_uri, &[("code", &code)], state)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemRequest {
+    app: String,
+    code: String,
+    redirect_uri: String,
+    code_verifier: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct SsoCode {
+    app: String,
+    session_id: Uuid,
+    redirect_uri: String,
+    code_challenge: String,
+}
+
+/// `POST /auth/authorize/token`
+///
+/// Exchanges a code from `GET /auth/authorize` for an access token naming
+/// `app` in its `aud` claim, as `POST /auth/sso/token` issues them. The
+/// `redirect_uri` must be the one the code was sent to and `code_verifier`
+/// must match its code challenge. Codes are single use.
+///
+/// Responds with `401 Unauthorized` for an unknown, expired or used code, a
+/// code issued to another application or redirect URI, a wrong verifier, or
+/// once the session the code was issued for has ended, and `404 Not Found`
+/// when the `sso` config section is absent.
+async fn redeem(
+    State(ctx): State<Arc<AppContext>>,
+    Json(payload): Json<RedeemRequest>,
+) -> Result<impl IntoResponse> {
+    sso(&ctx.config())?;
+
+    let code = sqlx::query_as::<_, SsoCode>(
+        r"
+        UPDATE sso_codes
+        SET used_at = now()
+        WHERE code_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING app, session_id, redirect_uri, code_challenge
+        ",
+    )
+    .bind(hash_token(payload.code.trim()))
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::InvalidToken)?;
+
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(payload.code_verifier.as_bytes()));
+    if code.app != payload.app
+        || code.redirect_uri != payload.redirect_uri
+        || code.code_challenge != challenge
+    {
+        return Err(Error::InvalidToken);
+    }
+
+    let session = ctx
+        .sessions()
+        .find(code.session_id)
+        .await?
+        .filter(|session| session.user_id.is_some())
+        .ok_or(Error::InvalidToken)?;
+
+    let token = ctx.issue_access_token(&session, &code.app).await?;
+
+    tracing::info!(session_id = %session.id, app = %code.app, "Single sign-on code redeemed");
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(token)))
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use axum::Router;
+
+use crate::{AppContext, config::Routes};
+
+/// The route groups of one API version.
+pub struct ApiVersion {
+    public: Router<Arc<AppContext>>,
+    admin: Router<Arc<AppContext>>,
+}
+
+impl ApiVersion {
+    /// A version serving `public` to everyone and `admin` as the admin API.
+    #[must_use]
+    pub fn new(public: Router<Arc<AppContext>>, admin: Router<Arc<AppContext>>) -> Self {
+        Self { public, admin }
+    }
+
+    /// The groups of this version a listener serving `served` exposes.
+    fn routes(&self, served: Routes) -> Router<Arc<AppContext>> {
+        match served {
+            Routes::All => self.public.clone().merge(self.admin.clone()),
+            Routes::Public => self.public.clone(),
+            Routes::Admin => self.admin.clone(),
+        }
+    }
+}
+
+/// Builds the API router out of versions mounted side by side under
+/// `/api/{version}`, so a breaking change can ship as a new version while
+/// clients of the old one keep working.
+///
+/// ```no_run
+/// use betterauth::{
+///     config::Routes,
+///     routes::{self, ApiRouter},
+/// };
+///
+/// let router = ApiRouter::new()
+///     .version("v1", routes::v1())
+///     .unversioned("v1")
+///     .build(Routes::All);
+/// ```
+#[derive(Default)]
+pub struct ApiRouter {
+    versions: Vec<(String, ApiVersion)>,
+    unversioned: Option<String>,
+}
+
+impl ApiRouter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `routes` under `/api/{name}`, e.g. `/api/v1/auth/login`.
+    #[must_use]
+    pub fn version(mut self, name: impl Into<String>, routes: ApiVersion) -> Self {
+        self.versions.push((name.into(), routes));
+        self
+    }
+
+    /// Also serves version `name` without a prefix, e.g. `/auth/login`, for
+    /// clients from before the API was versioned.
+    #[must_use]
+    pub fn unversioned(mut self, name: impl Into<String>) -> Self {
+        self.unversioned = Some(name.into());
+        self
+    }
+
+    /// The router of every version, limited to the groups a listener
+    /// serving `served` exposes.
+    ///
+    /// # Panics
+    /// If [`ApiRouter::unversioned`] names a version that was not added.
+    pub fn build(self, served: Routes) -> Router<Arc<AppContext>> {
+        let mut router = Router::new();
+
+        if let Some(name) = &self.unversioned {
+            let (_, version) = self
+                .versions
+                .iter()
+                .find(|(version, _)| version == name)
+                .unwrap_or_else(|| panic!("API version {name} is not routed"));
+            router = router.merge(version.routes(served));
+        }
+
+        for (name, version) in &self.versions {
+            router = router.nest(&format!("/api/{name}"), version.routes(served));
+        }
+
+        router
+    }
+}
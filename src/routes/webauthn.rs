@@ -0,0 +1,394 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::AuthUser,
+    events::{Event, LoginMethod},
+    sessions::DeviceInfo,
+    tokens::TokenPair,
+    webauthn::{
+        self, AuthenticationCredential, Ceremony, CreationOptions, RegistrationCredential,
+        RequestOptions, WebAuthn,
+    },
+};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/webauthn/register/start", post(register_start))
+        .route("/webauthn/register/finish", post(register_finish))
+        .route("/webauthn/login/start", post(login_start))
+        .route("/webauthn/login/finish", post(login_finish))
+        .route("/webauthn/credentials", get(list_credentials))
+        .route("/webauthn/credentials/{id}", delete(delete_credential))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChallengeResponse<T> {
+    /// Echoed back in the matching `finish` request.
+    challenge_id: Uuid,
+    public_key: T,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Passkey {
+    id: Uuid,
+    name: Option<String>,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+/// `POST /auth/webauthn/register/start`
+///
+/// Issues a challenge for adding a passkey to the caller's account. Passkeys
+/// the user already has are excluded so an authenticator is not registered
+/// twice.
+async fn register_start(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+) -> Result<Json<ChallengeResponse<CreationOptions>>> {
+    let webauthn = ctx.webauthn()?;
+
+    let (email, name) = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT email, name FROM users WHERE id = $1",
+    )
+    .bind(user.id())
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::Unauthenticated)?;
+
+    let existing: Vec<Vec<u8>> =
+        sqlx::query_scalar("SELECT credential_id FROM webauthn_credentials WHERE user_id = $1")
+            .bind(user.id())
+            .fetch_all(ctx.db())
+            .await?;
+
+    let challenge = WebAuthn::new_challenge();
+    let challenge_id = store_challenge(
+        &ctx,
+        webauthn,
+        Ceremony::Registration,
+        Some(user.id()),
+        &challenge,
+    )
+    .await?;
+
+    Ok(Json(ChallengeResponse {
+        challenge_id,
+        public_key: webauthn.creation_options(
+            challenge,
+            user.id(),
+            &email,
+            name.as_deref(),
+            &existing,
+        ),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterFinishRequest {
+    challenge_id: Uuid,
+    credential: RegistrationCredential,
+    /// Label shown when listing passkeys, e.g. "Work laptop".
+    name: Option<String>,
+}
+
+/// `POST /auth/webauthn/register/finish`
+///
+/// Verifies the authenticator's response and stores the new passkey.
+///
+/// Responds with `201 Created` and the passkey, or `401 Unauthorized` if the
+/// challenge is unknown or expired, the response fails verification or the
+/// credential is already registered.
+async fn register_finish(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Json(payload): Json<RegisterFinishRequest>,
+) -> Result<(StatusCode, Json<Passkey>)> {
+    let webauthn = ctx.webauthn()?;
+    let (owner, challenge) =
+        take_challenge(&ctx, payload.challenge_id, Ceremony::Registration).await?;
+
+    if owner != Some(user.id()) {
+        return Err(Error::InvalidToken);
+    }
+
+    let credential = webauthn.verify_registration(&challenge, &payload.credential)?;
+
+    let passkey = sqlx::query_as::<_, Passkey>(
+        r"
+        INSERT INTO webauthn_credentials
+            (user_id, credential_id, public_key, sign_count, name, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (credential_id) DO NOTHING
+        RETURNING id, name, created_at, last_used_at
+        ",
+    )
+    .bind(user.id())
+    .bind(&credential.credential_id)
+    .bind(&credential.public_key)
+    .bind(i64::from(credential.sign_count))
+    .bind(payload.name.as_deref().map(str::trim))
+    .bind(Utc::now())
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or_else(|| Error::WebAuthn(String::from("credential is already registered")))?;
+
+    tracing::info!(user_id = %user.id(), passkey_id = %passkey.id, "Passkey registered");
+
+    Ok((StatusCode::CREATED, Json(passkey)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoginStartRequest {
+    /// Limits the ceremony to this account's passkeys. Leave it out to let
+    /// the browser offer any discoverable passkey.
+    email: Option<String>,
+}
+
+/// `POST /auth/webauthn/login/start`
+///
+/// Issues a login challenge. Unknown emails get an empty credential list,
+/// the same as accounts without passkeys, so the endpoint does not reveal
+/// which emails are registered.
+async fn login_start(
+    State(ctx): State<Arc<AppContext>>,
+    payload: Option<Json<LoginStartRequest>>,
+) -> Result<Json<ChallengeResponse<RequestOptions>>> {
+    let webauthn = ctx.webauthn()?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    let allowed: Vec<Vec<u8>> = match payload.email.as_deref().map(str::trim) {
+        Some(email) => {
+            sqlx::query_scalar(
+                r"
+                SELECT c.credential_id
+                FROM webauthn_credentials c
+                JOIN users u ON u.id = c.user_id
+                WHERE u.email_normalized = $1
+                ",
+            )
+            .bind(ctx.normalize_email(email))
+            .fetch_all(ctx.db())
+            .await?
+        }
+        None => Vec::new(),
+    };
+
+    let challenge = WebAuthn::new_challenge();
+    let challenge_id =
+        store_challenge(&ctx, webauthn, Ceremony::Authentication, None, &challenge).await?;
+
+    Ok(Json(ChallengeResponse {
+        challenge_id,
+        public_key: webauthn.request_options(challenge, &allowed),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginFinishRequest {
+    challenge_id: Uuid,
+    credential: AuthenticationCredential,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredCredential {
+    id: Uuid,
+    user_id: Uuid,
+    public_key: Vec<u8>,
+    sign_count: i64,
+    verified: bool,
+}
+
+/// `POST /auth/webauthn/login/finish`
+///
+/// Verifies a passkey assertion and logs the user in exactly like
+/// `POST /auth/login`: a session cookie is set and a token pair returned.
+///
+/// Responds with `401 Unauthorized` if the challenge is unknown or expired,
+/// the passkey is not registered or the assertion fails verification.
+async fn login_finish(
+    State(ctx): State<Arc<AppContext>>,
+    device: DeviceInfo,
+    jar: CookieJar,
+    Json(payload): Json<LoginFinishRequest>,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let outcome = passkey_login(&ctx, &device, jar, payload).await;
+
+    ctx.reports()
+        .record_outcome(&LoginMethod::Passkey, &device.device, &outcome)
+        .await;
+
+    outcome
+}
+
+async fn passkey_login(
+    ctx: &AppContext,
+    device: &DeviceInfo,
+    jar: CookieJar,
+    payload: LoginFinishRequest,
+) -> Result<(CookieJar, Json<TokenPair>)> {
+    let webauthn = ctx.webauthn()?;
+    let (_, challenge) =
+        take_challenge(ctx, payload.challenge_id, Ceremony::Authentication).await?;
+
+    let credential = sqlx::query_as::<_, StoredCredential>(
+        r"
+        SELECT c.id, c.user_id, c.public_key, c.sign_count, u.verified_at IS NOT NULL AS verified
+        FROM webauthn_credentials c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.credential_id = $1
+        ",
+    )
+    .bind(webauthn::decode(&payload.credential.raw_id)?)
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or_else(|| Error::WebAuthn(String::from("unknown credential")))?;
+
+    let sign_count = webauthn.verify_authentication(
+        &challenge,
+        &payload.credential,
+        &credential.public_key,
+        u32::try_from(credential.sign_count).unwrap_or(u32::MAX),
+    )?;
+
+    if !credential.verified && ctx.config().auth().require_email_verification() {
+        return Err(Error::EmailNotVerified);
+    }
+
+    sqlx::query(
+        "UPDATE webauthn_credentials SET sign_count = $1, last_used_at = now() WHERE id = $2",
+    )
+    .bind(i64::from(sign_count))
+    .bind(credential.id)
+    .execute(ctx.db())
+    .await?;
+
+    let user_id = credential.user_id;
+    let (session, token) = ctx
+        .start_session(user_id, device, &LoginMethod::Passkey)
+        .await?;
+    let pair = ctx.issue_tokens(&session).await?;
+    let cookie = ctx.session_cookies().build(token, &session);
+
+    tracing::info!(%user_id, passkey_id = %credential.id, session_id = %session.id, "User logged in with passkey");
+
+    ctx.publish(Event::UserLoggedIn {
+        user_id,
+        session_id: session.id,
+        method: LoginMethod::Passkey,
+    })
+    .await?;
+
+    Ok((jar.add(cookie), Json(pair)))
+}
+
+/// `GET /auth/webauthn/credentials`
+///
+/// Lists the caller's passkeys, newest first.
+async fn list_credentials(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+) -> Result<Json<Vec<Passkey>>> {
+    let passkeys = sqlx::query_as::<_, Passkey>(
+        r"
+        SELECT id, name, created_at, last_used_at
+        FROM webauthn_credentials
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        ",
+    )
+    .bind(user.id())
+    .fetch_all(ctx.db())
+    .await?;
+
+    Ok(Json(passkeys))
+}
+
+/// `DELETE /auth/webauthn/credentials/{id}`
+///
+/// Removes one of the caller's passkeys. Responds with `204 No Content`, or
+/// `401 Unauthorized` if no such passkey belongs to the caller.
+async fn delete_credential(
+    State(ctx): State<Arc<AppContext>>,
+    user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let deleted = sqlx::query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user.id())
+        .execute(ctx.db())
+        .await?
+        .rows_affected();
+
+    if deleted == 0 {
+        return Err(Error::InvalidToken);
+    }
+
+    tracing::info!(user_id = %user.id(), passkey_id = %id, "Passkey removed");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Persists a challenge so the matching `finish` request can redeem it once.
+async fn store_challenge(
+    ctx: &AppContext,
+    webauthn: &WebAuthn,
+    ceremony: Ceremony,
+    user_id: Option<Uuid>,
+    challenge: &str,
+) -> Result<Uuid> {
+    let now = Utc::now();
+    let ttl = Duration::seconds(i64::try_from(webauthn.challenge_ttl()).unwrap_or(i64::MAX));
+
+    sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at <= now()")
+        .execute(ctx.db())
+        .await?;
+
+    let id = sqlx::query_scalar(
+        r"
+        INSERT INTO webauthn_challenges (user_id, ceremony, challenge, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        ",
+    )
+    .bind(user_id)
+    .bind(ceremony.name())
+    .bind(challenge)
+    .bind(now)
+    .bind(now + ttl)
+    .fetch_one(ctx.db())
+    .await?;
+
+    Ok(id)
+}
+
+/// Consumes an unexpired challenge, returning its user and value.
+async fn take_challenge(
+    ctx: &AppContext,
+    id: Uuid,
+    ceremony: Ceremony,
+) -> Result<(Option<Uuid>, String)> {
+    sqlx::query_as::<_, (Option<Uuid>, String)>(
+        r"
+        DELETE FROM webauthn_challenges
+        WHERE id = $1 AND ceremony = $2 AND expires_at > now()
+        RETURNING user_id, challenge
+        ",
+    )
+    .bind(id)
+    .bind(ceremony.name())
+    .fetch_optional(ctx.db())
+    .await?
+    .ok_or(Error::InvalidToken)
+}
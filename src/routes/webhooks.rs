@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    auth::AdminUser,
+    webhooks::{DeliveryStatus, WebhookDelivery},
+};
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/admin/webhooks/deliveries", get(list_deliveries))
+        .route(
+            "/admin/webhooks/deliveries/{id}/retry",
+            post(retry_delivery),
+        )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListDeliveriesQuery {
+    /// 1-based page number.
+    page: Option<u32>,
+    per_page: Option<u32>,
+    status: Option<DeliveryStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryPage {
+    deliveries: Vec<WebhookDelivery>,
+    page: u32,
+    per_page: u32,
+}
+
+/// `GET /admin/webhooks/deliveries`
+///
+/// Lists webhook deliveries with their payload and the outcome of the last
+/// attempt, newest first. Filter with `status` (`pending`, `delivered` or
+/// `failed`); page with `page` and `per_page` (at most 100).
+async fn list_deliveries(
+    State(ctx): State<Arc<AppContext>>,
+    _: AdminUser,
+    Query(query): Query<ListDeliveriesQuery>,
+) -> Result<Json<DeliveryPage>> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let deliveries = ctx
+        .webhooks()
+        .list(
+            query.status,
+            i64::from(per_page),
+            i64::from(page - 1) * i64::from(per_page),
+        )
+        .await?;
+
+    Ok(Json(DeliveryPage {
+        deliveries,
+        page,
+        per_page,
+    }))
+}
+
+/// `POST /admin/webhooks/deliveries/{id}/retry`
+///
+/// Queues one more attempt at a delivery that failed for good, made right
+/// away by the job worker.
+///
+/// Responds with the delivery as queued, or `404 Not Found` if there is no
+/// such failed delivery.
+async fn retry_delivery(
+    State(ctx): State<Arc<AppContext>>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WebhookDelivery>> {
+    let delivery = ctx
+        .webhooks()
+        .retry(id)
+        .await?
+        .ok_or(Error::NotFound("webhook delivery"))?;
+
+    tracing::info!(admin_id = %admin.id(), delivery_id = %id, "Webhook delivery retried by admin");
+
+    Ok(Json(delivery))
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Redirect},
+    routing::get,
+};
+
+use crate::{AppContext, Error, Result};
+
+pub fn router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .route("/.well-known/change-password", get(change_password))
+        .route("/.well-known/security.txt", get(security_txt))
+}
+
+/// `GET /.well-known/change-password`
+///
+/// Sends password managers to the page where users change their password,
+/// `well_known.change_password_url`. Responds with `404 Not Found` when it
+/// is not set.
+async fn change_password(State(ctx): State<Arc<AppContext>>) -> Result<Redirect> {
+    let config = ctx.config();
+    let url = config
+        .well_known()
+        .change_password_url()
+        .ok_or(Error::Disabled("change password page"))?;
+
+    Ok(Redirect::to(url))
+}
+
+/// `GET /.well-known/security.txt`
+///
+/// How to report vulnerabilities, from `well_known.security_txt` (RFC
+/// 9116). Responds with `404 Not Found` when it is not set.
+async fn security_txt(State(ctx): State<Arc<AppContext>>) -> Result<impl IntoResponse> {
+    let config = ctx.config();
+    let security_txt = config
+        .well_known()
+        .security_txt()
+        .ok_or(Error::Disabled("security.txt"))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        security_txt.render(),
+    ))
+}
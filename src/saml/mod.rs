@@ -0,0 +1,602 @@
+mod xmldsig;
+
+use std::io::Write;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use flate2::{Compression, write::DeflateEncoder};
+use roxmltree::{Document, Node};
+use url::Url;
+
+use self::xmldsig::{DSIG_NS, child, is, strip_whitespace};
+use crate::{
+    Error, Result,
+    auth::generate_token,
+    config::{SamlConfig, SamlProviderConfig},
+};
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const HTTP_POST: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const EMAIL_NAME_ID: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// How long a user has to authenticate at the IdP, in seconds.
+pub const REQUEST_TTL: i64 = 10 * 60;
+
+/// Clock difference tolerated when checking assertion validity windows.
+const CLOCK_SKEW: Duration = Duration::seconds(60);
+
+/// An authentication request started by [`SamlClient::authn_request`].
+#[derive(Debug)]
+pub struct AuthnRequest {
+    /// Request ID the IdP must echo back in `InResponseTo`.
+    pub id: String,
+    /// IdP URL to redirect the user to, carrying the request.
+    pub url: String,
+}
+
+/// What a validated assertion says about the user who signed in.
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    /// The [`AuthnRequest::id`] this assertion answers.
+    pub request_id: String,
+    /// Stable subject identifier at the IdP.
+    pub name_id: String,
+    /// The IdP's session the user signed in with, if it names one.
+    pub session_index: Option<String>,
+    pub email: String,
+    pub name: Option<String>,
+}
+
+/// A logout request started by [`SamlClient::logout_request`].
+#[derive(Debug)]
+pub struct LogoutRequest {
+    /// Request ID the IdP must echo back in `InResponseTo`.
+    pub id: String,
+    /// IdP URL to redirect the user to, carrying the request.
+    pub url: String,
+}
+
+/// What a validated logout request from the IdP asks to end.
+#[derive(Debug, Clone)]
+pub struct IdpLogout {
+    /// Request ID to answer in the `LogoutResponse`.
+    pub id: String,
+    /// Subject whose sessions end.
+    pub name_id: String,
+    /// The IdP sessions that ended; every session of the subject if empty.
+    pub session_indexes: Vec<String>,
+}
+
+/// SAML 2.0 service provider for the configured tenants, using the
+/// HTTP-Redirect binding for messages to the IdP and HTTP-POST for messages
+/// from it.
+#[derive(Clone)]
+pub struct SamlClient {
+    config: SamlConfig,
+    base_url: String,
+}
+
+impl SamlClient {
+    /// `base_url` is the public URL of this server; SP entity IDs and ACS
+    /// URLs are derived from it.
+    #[must_use]
+    pub fn new(config: &SamlConfig, base_url: String) -> Self {
+        Self {
+            config: config.clone(),
+            base_url,
+        }
+    }
+
+    fn tenant(&self, tenant: &str) -> Result<&SamlProviderConfig> {
+        self.config
+            .tenant(tenant)
+            .ok_or(Error::NotFound("saml tenant"))
+    }
+
+    /// Entity ID this server uses as the service provider of `tenant`.
+    #[must_use]
+    pub fn entity_id(&self, tenant: &str) -> String {
+        format!("{}/auth/saml/{tenant}/metadata", self.base_url)
+    }
+
+    /// Assertion consumer service URL of `tenant`.
+    #[must_use]
+    pub fn acs_url(&self, tenant: &str) -> String {
+        format!("{}/auth/saml/{tenant}/acs", self.base_url)
+    }
+
+    /// Single logout service URL of `tenant`.
+    #[must_use]
+    pub fn slo_url(&self, tenant: &str) -> String {
+        format!("{}/auth/saml/{tenant}/slo", self.base_url)
+    }
+
+    /// Service-provider metadata to register with the tenant's IdP.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured
+    pub fn metadata(&self, tenant: &str) -> Result<String> {
+        self.tenant(tenant)?;
+
+        Ok(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{entity_id}">
+  <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{PROTOCOL_NS}">
+    <md:SingleLogoutService Binding="{HTTP_POST}" Location="{slo_url}"/>
+    <md:NameIDFormat>{EMAIL_NAME_ID}</md:NameIDFormat>
+    <md:AssertionConsumerService Binding="{HTTP_POST}" Location="{acs_url}" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+            entity_id = escape(&self.entity_id(tenant)),
+            acs_url = escape(&self.acs_url(tenant)),
+            slo_url = escape(&self.slo_url(tenant)),
+        ))
+    }
+
+    /// Builds an `AuthnRequest` for `tenant` with a fresh ID.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured
+    /// * [`Error::Validation`] if the configured IdP SSO URL is invalid
+    pub fn authn_request(&self, tenant: &str) -> Result<AuthnRequest> {
+        let idp = self.tenant(tenant)?;
+        // IDs must be valid XML names, which cannot start with a digit.
+        let id = format!("_{}", generate_token());
+
+        let request = format!(
+            r#"<samlp:AuthnRequest xmlns:samlp="{PROTOCOL_NS}" xmlns:saml="{ASSERTION_NS}" ID="{id}" Version="2.0" IssueInstant="{now}" Destination="{destination}" AssertionConsumerServiceURL="{acs_url}" ProtocolBinding="{HTTP_POST}"><saml:Issuer>{entity_id}</saml:Issuer><samlp:NameIDPolicy Format="{EMAIL_NAME_ID}" AllowCreate="true"/></samlp:AuthnRequest>"#,
+            now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            destination = escape(idp.idp_sso_url()),
+            acs_url = escape(&self.acs_url(tenant)),
+            entity_id = escape(&self.entity_id(tenant)),
+        );
+
+        let url = redirect(
+            idp.idp_sso_url(),
+            "idp_sso_url",
+            "SAMLRequest",
+            &request,
+            None,
+        )?;
+
+        Ok(AuthnRequest { id, url })
+    }
+
+    /// Builds a `LogoutRequest` for `tenant` with a fresh ID, asking the IdP
+    /// to end session `session_index` of subject `name_id`.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured or its IdP
+    ///   does not support single logout
+    /// * [`Error::Validation`] if the configured IdP SLO URL is invalid
+    pub fn logout_request(
+        &self,
+        tenant: &str,
+        name_id: &str,
+        session_index: Option<&str>,
+    ) -> Result<LogoutRequest> {
+        let slo_url = self.idp_slo_url(tenant)?;
+        let id = format!("_{}", generate_token());
+
+        let session_index = session_index
+            .map(|index| format!("<samlp:SessionIndex>{}</samlp:SessionIndex>", escape(index)))
+            .unwrap_or_default();
+        let request = format!(
+            r#"<samlp:LogoutRequest xmlns:samlp="{PROTOCOL_NS}" xmlns:saml="{ASSERTION_NS}" ID="{id}" Version="2.0" IssueInstant="{now}" Destination="{destination}"><saml:Issuer>{entity_id}</saml:Issuer><saml:NameID>{name_id}</saml:NameID>{session_index}</samlp:LogoutRequest>"#,
+            now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            destination = escape(slo_url),
+            entity_id = escape(&self.entity_id(tenant)),
+            name_id = escape(name_id),
+        );
+
+        let url = redirect(slo_url, "idp_slo_url", "SAMLRequest", &request, None)?;
+
+        Ok(LogoutRequest { id, url })
+    }
+
+    /// URL redirecting the user back to the IdP of `tenant` with a
+    /// successful `LogoutResponse` to request `in_response_to`.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured or its IdP
+    ///   does not support single logout
+    /// * [`Error::Validation`] if the configured IdP SLO URL is invalid
+    pub fn logout_response(
+        &self,
+        tenant: &str,
+        in_response_to: &str,
+        relay_state: Option<&str>,
+    ) -> Result<String> {
+        let slo_url = self.idp_slo_url(tenant)?;
+
+        let response = format!(
+            r#"<samlp:LogoutResponse xmlns:samlp="{PROTOCOL_NS}" xmlns:saml="{ASSERTION_NS}" ID="_{id}" Version="2.0" IssueInstant="{now}" Destination="{destination}" InResponseTo="{in_response_to}"><saml:Issuer>{entity_id}</saml:Issuer><samlp:Status><samlp:StatusCode Value="{STATUS_SUCCESS}"/></samlp:Status></samlp:LogoutResponse>"#,
+            id = generate_token(),
+            now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            destination = escape(slo_url),
+            in_response_to = escape(in_response_to),
+            entity_id = escape(&self.entity_id(tenant)),
+        );
+
+        redirect(
+            slo_url,
+            "idp_slo_url",
+            "SAMLResponse",
+            &response,
+            relay_state,
+        )
+    }
+
+    fn idp_slo_url(&self, tenant: &str) -> Result<&str> {
+        self.tenant(tenant)?
+            .idp_slo_url()
+            .ok_or(Error::NotFound("saml single logout"))
+    }
+
+    /// Validates a base64 `SAMLResponse` posted to the ACS of `tenant`.
+    ///
+    /// Checks the IdP signature over the response or its assertion, the
+    /// issuer, status, destination, audience, validity window and bearer
+    /// subject confirmation. The caller must still check that
+    /// [`Assertion::request_id`] belongs to a pending request.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured
+    /// * [`Error::Saml`] if the response fails any check
+    pub fn validate_response(&self, tenant: &str, encoded: &str) -> Result<Assertion> {
+        let idp = self.tenant(tenant)?;
+        let key = xmldsig::public_key(idp.idp_certificate())?;
+        let acs_url = self.acs_url(tenant);
+        let entity_id = self.entity_id(tenant);
+        let now = Utc::now();
+
+        let xml = decode(encoded)?;
+        let doc = Document::parse(&xml).map_err(|_| rejected("response is not valid XML"))?;
+
+        let response = doc.root_element();
+        if !is(response, PROTOCOL_NS, "Response") {
+            return Err(rejected("not a SAML response"));
+        }
+
+        if response
+            .attribute("Destination")
+            .is_some_and(|destination| destination != acs_url)
+        {
+            return Err(rejected("wrong destination"));
+        }
+
+        let status = child(response, PROTOCOL_NS, "Status")
+            .and_then(|status| child(status, PROTOCOL_NS, "StatusCode"))
+            .and_then(|code| code.attribute("Value"));
+        if status != Some(STATUS_SUCCESS) {
+            return Err(rejected("identity provider reported a failure"));
+        }
+
+        if let Some(issuer) = child(response, ASSERTION_NS, "Issuer")
+            && element_text(issuer)? != idp.idp_entity_id()
+        {
+            return Err(rejected("wrong issuer"));
+        }
+
+        let request_id = response
+            .attribute("InResponseTo")
+            .ok_or_else(|| rejected("unsolicited responses are not accepted"))?;
+
+        if child(response, ASSERTION_NS, "EncryptedAssertion").is_some() {
+            return Err(rejected("encrypted assertions are not supported"));
+        }
+
+        let mut assertions = response
+            .children()
+            .filter(|node| is(*node, ASSERTION_NS, "Assertion"));
+        let assertion = match (assertions.next(), assertions.next()) {
+            (Some(assertion), None) => assertion,
+            _ => return Err(rejected("response must contain exactly one assertion")),
+        };
+
+        if child(response, DSIG_NS, "Signature").is_some() {
+            xmldsig::verify_enveloped(response, &key)?;
+        } else {
+            xmldsig::verify_enveloped(assertion, &key)?;
+        }
+
+        let issuer = child(assertion, ASSERTION_NS, "Issuer")
+            .map(element_text)
+            .transpose()?;
+        if issuer != Some(idp.idp_entity_id()) {
+            return Err(rejected("wrong assertion issuer"));
+        }
+
+        let conditions = child(assertion, ASSERTION_NS, "Conditions")
+            .ok_or_else(|| rejected("assertion has no conditions"))?;
+        check_window(conditions, now)?;
+
+        let restrictions: Vec<_> = conditions
+            .children()
+            .filter(|node| is(*node, ASSERTION_NS, "AudienceRestriction"))
+            .collect();
+        let audience_ok = !restrictions.is_empty()
+            && restrictions.iter().all(|restriction| {
+                restriction
+                    .children()
+                    .filter(|node| is(*node, ASSERTION_NS, "Audience"))
+                    .any(|audience| {
+                        element_text(audience).is_ok_and(|audience| audience == entity_id)
+                    })
+            });
+        if !audience_ok {
+            return Err(rejected("assertion is not meant for this service provider"));
+        }
+
+        let subject = child(assertion, ASSERTION_NS, "Subject")
+            .ok_or_else(|| rejected("assertion has no subject"))?;
+        let name_id = child(subject, ASSERTION_NS, "NameID")
+            .map(element_text)
+            .transpose()?
+            .filter(|name_id| !name_id.is_empty())
+            .ok_or_else(|| rejected("assertion has no NameID"))?;
+
+        let confirmed = subject
+            .children()
+            .filter(|node| is(*node, ASSERTION_NS, "SubjectConfirmation"))
+            .filter(|confirmation| confirmation.attribute("Method") == Some(BEARER))
+            .filter_map(|confirmation| child(confirmation, ASSERTION_NS, "SubjectConfirmationData"))
+            .any(|data| {
+                data.attribute("Recipient") == Some(acs_url.as_str())
+                    && data.attribute("InResponseTo") == Some(request_id)
+                    && check_window(data, now).is_ok()
+                    && data.attribute("NotOnOrAfter").is_some()
+            });
+        if !confirmed {
+            return Err(rejected("subject confirmation failed"));
+        }
+
+        let attribute = |name: &str| {
+            assertion
+                .children()
+                .filter(|node| is(*node, ASSERTION_NS, "AttributeStatement"))
+                .flat_map(|statement| statement.children())
+                .filter(|node| is(*node, ASSERTION_NS, "Attribute"))
+                .find(|node| node.attribute("Name") == Some(name))
+                .and_then(|node| child(node, ASSERTION_NS, "AttributeValue"))
+                .map(element_text)
+                .transpose()
+                .map(|value| value.filter(|value| !value.is_empty()).map(str::to_owned))
+        };
+
+        let email = attribute(idp.email_attribute())?
+            .or_else(|| name_id.contains('@').then(|| name_id.to_owned()))
+            .ok_or_else(|| rejected("assertion has no email address"))?;
+
+        let session_index = child(assertion, ASSERTION_NS, "AuthnStatement")
+            .and_then(|statement| statement.attribute("SessionIndex"))
+            .map(str::to_owned);
+
+        Ok(Assertion {
+            request_id: request_id.to_owned(),
+            name_id: name_id.to_owned(),
+            session_index,
+            email,
+            name: attribute(idp.name_attribute())?,
+        })
+    }
+
+    /// Validates a base64 `SAMLRequest` logout request posted to the SLO
+    /// service of `tenant`.
+    ///
+    /// The request must be signed by the IdP and name its issuer; the
+    /// destination and validity window are checked when present.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured
+    /// * [`Error::Saml`] if the request fails any check
+    pub fn validate_logout_request(&self, tenant: &str, encoded: &str) -> Result<IdpLogout> {
+        let idp = self.tenant(tenant)?;
+        let key = xmldsig::public_key(idp.idp_certificate())?;
+        let xml = decode(encoded)?;
+        let doc = Document::parse(&xml).map_err(|_| rejected("request is not valid XML"))?;
+
+        let request = doc.root_element();
+        if !is(request, PROTOCOL_NS, "LogoutRequest") {
+            return Err(rejected("not a SAML logout request"));
+        }
+
+        xmldsig::verify_enveloped(request, &key)?;
+        self.check_logout_message(tenant, idp, request)?;
+        check_window(request, Utc::now())?;
+
+        let id = request
+            .attribute("ID")
+            .ok_or_else(|| rejected("logout request has no ID"))?;
+
+        if child(request, ASSERTION_NS, "EncryptedID").is_some() {
+            return Err(rejected("encrypted identifiers are not supported"));
+        }
+
+        let name_id = child(request, ASSERTION_NS, "NameID")
+            .map(element_text)
+            .transpose()?
+            .filter(|name_id| !name_id.is_empty())
+            .ok_or_else(|| rejected("logout request has no NameID"))?;
+
+        let session_indexes = request
+            .children()
+            .filter(|node| is(*node, PROTOCOL_NS, "SessionIndex"))
+            .map(|node| element_text(node).map(str::to_owned))
+            .collect::<Result<_>>()?;
+
+        Ok(IdpLogout {
+            id: id.to_owned(),
+            name_id: name_id.to_owned(),
+            session_indexes,
+        })
+    }
+
+    /// Validates a base64 `SAMLResponse` logout response posted to the SLO
+    /// service of `tenant`, returning the ID of the [`LogoutRequest`] it
+    /// answers. The caller must still check that it belongs to a pending
+    /// request.
+    ///
+    /// ## Errors
+    /// * [`Error::NotFound`] if the tenant is not configured
+    /// * [`Error::Saml`] if the response fails any check or the IdP reports
+    ///   a failure
+    pub fn validate_logout_response(&self, tenant: &str, encoded: &str) -> Result<String> {
+        let idp = self.tenant(tenant)?;
+        let key = xmldsig::public_key(idp.idp_certificate())?;
+        let xml = decode(encoded)?;
+        let doc = Document::parse(&xml).map_err(|_| rejected("response is not valid XML"))?;
+
+        let response = doc.root_element();
+        if !is(response, PROTOCOL_NS, "LogoutResponse") {
+            return Err(rejected("not a SAML logout response"));
+        }
+
+        xmldsig::verify_enveloped(response, &key)?;
+        self.check_logout_message(tenant, idp, response)?;
+
+        let status = child(response, PROTOCOL_NS, "Status")
+            .and_then(|status| child(status, PROTOCOL_NS, "StatusCode"))
+            .and_then(|code| code.attribute("Value"));
+        if status != Some(STATUS_SUCCESS) {
+            return Err(rejected("identity provider reported a failure"));
+        }
+
+        response
+            .attribute("InResponseTo")
+            .map(str::to_owned)
+            .ok_or_else(|| rejected("unsolicited logout responses are not accepted"))
+    }
+
+    /// Checks the destination and issuer shared by logout requests and
+    /// responses; unlike on login responses, the issuer is required since
+    /// no assertion names it.
+    fn check_logout_message(
+        &self,
+        tenant: &str,
+        idp: &SamlProviderConfig,
+        message: Node<'_, '_>,
+    ) -> Result<()> {
+        if message
+            .attribute("Destination")
+            .is_some_and(|destination| destination != self.slo_url(tenant))
+        {
+            return Err(rejected("wrong destination"));
+        }
+
+        let issuer = child(message, ASSERTION_NS, "Issuer")
+            .map(element_text)
+            .transpose()?;
+        if issuer != Some(idp.idp_entity_id()) {
+            return Err(rejected("wrong issuer"));
+        }
+
+        Ok(())
+    }
+}
+
+/// `url`, configured as `setting`, with `message` added as parameter `name`
+/// of the HTTP-Redirect binding: deflated and base64-encoded, followed by
+/// `relay_state`.
+fn redirect(
+    url: &str,
+    setting: &str,
+    name: &str,
+    message: &str,
+    relay_state: Option<&str>,
+) -> Result<String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(message.as_bytes())?;
+    let encoded = STANDARD.encode(encoder.finish()?);
+
+    let mut url =
+        Url::parse(url).map_err(|err| Error::Validation(format!("invalid {setting}: {err}")))?;
+    url.query_pairs_mut().append_pair(name, &encoded);
+    if let Some(relay_state) = relay_state {
+        url.query_pairs_mut().append_pair("RelayState", relay_state);
+    }
+
+    Ok(url.into())
+}
+
+/// Decodes a base64 message of the HTTP-POST binding.
+fn decode(encoded: &str) -> Result<String> {
+    let xml = STANDARD
+        .decode(strip_whitespace(encoded))
+        .map_err(|_| rejected("message is not base64"))?;
+
+    String::from_utf8(xml).map_err(|_| rejected("message is not UTF-8"))
+}
+
+fn rejected(reason: &str) -> Error {
+    Error::Saml(reason.to_owned())
+}
+
+/// The text of the element `node`, trimmed, provided it holds nothing but
+/// one run of text.
+///
+/// Comments are left out of the digest of a signed assertion, so a comment
+/// injected into a value keeps the signature valid while a parser reading
+/// only the first text node sees something else: the IdP asserted
+/// `victim@example.com<!---->.evil.com`, not `victim@example.com`. Values
+/// split by comments, processing instructions or child elements are
+/// therefore refused rather than read in part.
+///
+/// ## Examples
+/// ```
+/// # use betterauth::saml::element_text;
+/// let doc = roxmltree::Document::parse("<NameID> alice@example.com </NameID>").unwrap();
+/// assert_eq!(element_text(doc.root_element()).unwrap(), "alice@example.com");
+///
+/// let doc = roxmltree::Document::parse(
+///     "<NameID>victim@example.com<!---->.evil.com</NameID>",
+/// )
+/// .unwrap();
+/// assert!(element_text(doc.root_element()).is_err());
+/// ```
+///
+/// ## Errors
+/// * [`Error::Saml`] if the element holds anything but text
+pub fn element_text<'a>(node: Node<'a, '_>) -> Result<&'a str> {
+    let mut children = node.children();
+
+    match (children.next(), children.next()) {
+        (None, _) => Ok(""),
+        (Some(text), None) if text.is_text() => Ok(text.text().map_or("", str::trim)),
+        _ => Err(rejected("element holds more than text")),
+    }
+}
+
+/// Checks the `NotBefore`/`NotOnOrAfter` attributes of `node`, when present.
+fn check_window(node: Node<'_, '_>, now: DateTime<Utc>) -> Result<()> {
+    let instant = |name: &str| {
+        node.attribute(name)
+            .map(|value| {
+                DateTime::parse_from_rfc3339(value)
+                    .map(|instant| instant.with_timezone(&Utc))
+                    .map_err(|_| rejected("malformed timestamp"))
+            })
+            .transpose()
+    };
+
+    if instant("NotBefore")?.is_some_and(|not_before| now + CLOCK_SKEW < not_before) {
+        return Err(rejected("assertion is not yet valid"));
+    }
+
+    if instant("NotOnOrAfter")?.is_some_and(|not_on_or_after| now - CLOCK_SKEW >= not_on_or_after) {
+        return Err(rejected("assertion has expired"));
+    }
+
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
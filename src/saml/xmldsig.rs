@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use roxmltree::{Node, NodeType};
+use rsa::{
+    RsaPublicKey,
+    pkcs1v15::{Signature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+    signature::Verifier,
+};
+use sha2::{Digest, Sha256};
+use x509_cert::{
+    Certificate,
+    der::{Decode, DecodePem, Encode},
+};
+
+use crate::{Error, Result};
+
+pub(super) const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Parses the IdP signing certificate, given as PEM or bare base64 DER.
+pub(super) fn public_key(certificate: &str) -> Result<RsaPublicKey> {
+    let certificate = certificate.trim();
+    let parsed = if certificate.starts_with("-----BEGIN") {
+        Certificate::from_pem(certificate)
+    } else {
+        let der = STANDARD
+            .decode(strip_whitespace(certificate))
+            .map_err(|_| invalid_certificate())?;
+        Certificate::from_der(&der)
+    }
+    .map_err(|_| invalid_certificate())?;
+
+    let spki = parsed
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|_| invalid_certificate())?;
+
+    RsaPublicKey::from_public_key_der(&spki).map_err(|_| invalid_certificate())
+}
+
+fn invalid_certificate() -> Error {
+    Error::Saml(String::from(
+        "identity provider certificate is not a valid RSA certificate",
+    ))
+}
+
+/// Removes the whitespace IdPs wrap base64 values with.
+pub(super) fn strip_whitespace(value: &str) -> String {
+    value.chars().filter(|c| !c.is_ascii_whitespace()).collect()
+}
+
+/// Checks that `element` carries an enveloped signature by `key` covering
+/// the element itself.
+///
+/// Supports just what IdPs put on SAML responses: exclusive canonicalization
+/// without comments, SHA-256 digests and RSA-SHA256 signatures.
+///
+/// Only the element passed in is vouched for; callers must read the signed
+/// data from this very node rather than searching the document again.
+pub(super) fn verify_enveloped(element: Node<'_, '_>, key: &RsaPublicKey) -> Result<()> {
+    let id = element
+        .attribute("ID")
+        .ok_or_else(|| rejected("signed element has no ID"))?;
+
+    // A second element with the same ID is the classic signature wrapping
+    // setup; refuse it outright.
+    let same_id = element
+        .document()
+        .descendants()
+        .filter(|node| node.attribute("ID") == Some(id))
+        .count();
+    if same_id != 1 {
+        return Err(rejected("duplicate element IDs"));
+    }
+
+    let signature =
+        child(element, DSIG_NS, "Signature").ok_or_else(|| rejected("element is not signed"))?;
+    let signed_info = child(signature, DSIG_NS, "SignedInfo")
+        .ok_or_else(|| rejected("signature has no SignedInfo"))?;
+
+    let c14n_method = child(signed_info, DSIG_NS, "CanonicalizationMethod")
+        .and_then(|node| node.attribute("Algorithm"));
+    if c14n_method != Some(EXC_C14N) {
+        return Err(rejected("unsupported canonicalization method"));
+    }
+
+    let signature_method =
+        child(signed_info, DSIG_NS, "SignatureMethod").and_then(|node| node.attribute("Algorithm"));
+    if signature_method != Some(RSA_SHA256) {
+        return Err(rejected("unsupported signature method"));
+    }
+
+    let mut references = signed_info
+        .children()
+        .filter(|node| is(*node, DSIG_NS, "Reference"));
+    let reference = match (references.next(), references.next()) {
+        (Some(reference), None) => reference,
+        _ => return Err(rejected("signature must have exactly one reference")),
+    };
+
+    if reference.attribute("URI") != Some(&format!("#{id}")) {
+        return Err(rejected("signature does not reference the signed element"));
+    }
+
+    let mut inclusive_prefixes = Vec::new();
+    if let Some(transforms) = child(reference, DSIG_NS, "Transforms") {
+        for transform in transforms
+            .children()
+            .filter(|node| is(*node, DSIG_NS, "Transform"))
+        {
+            match transform.attribute("Algorithm") {
+                Some(ENVELOPED) => {}
+                Some(EXC_C14N) => inclusive_prefixes = prefix_list(transform),
+                _ => return Err(rejected("unsupported transform")),
+            }
+        }
+    }
+
+    let digest_method =
+        child(reference, DSIG_NS, "DigestMethod").and_then(|node| node.attribute("Algorithm"));
+    if digest_method != Some(SHA256) {
+        return Err(rejected("unsupported digest method"));
+    }
+
+    let expected_digest = child(reference, DSIG_NS, "DigestValue")
+        .and_then(|node| node.text())
+        .map(|value| STANDARD.decode(strip_whitespace(value)))
+        .ok_or_else(|| rejected("reference has no digest"))?
+        .map_err(|_| rejected("digest is not base64"))?;
+
+    let canonical = canonicalize(element, Some(signature), &inclusive_prefixes);
+    if Sha256::digest(canonical.as_bytes()).as_slice() != expected_digest.as_slice() {
+        return Err(rejected("digest mismatch"));
+    }
+
+    let signature_value = child(signature, DSIG_NS, "SignatureValue")
+        .and_then(|node| node.text())
+        .map(|value| STANDARD.decode(strip_whitespace(value)))
+        .ok_or_else(|| rejected("signature has no value"))?
+        .map_err(|_| rejected("signature is not base64"))?;
+    let signature_value = Signature::try_from(signature_value.as_slice())
+        .map_err(|_| rejected("malformed signature"))?;
+
+    let signed_info_prefixes = child(signed_info, DSIG_NS, "CanonicalizationMethod")
+        .map(prefix_list)
+        .unwrap_or_default();
+    let canonical = canonicalize(signed_info, None, &signed_info_prefixes);
+
+    VerifyingKey::<Sha256>::new(key.clone())
+        .verify(canonical.as_bytes(), &signature_value)
+        .map_err(|_| rejected("signature mismatch"))
+}
+
+fn rejected(reason: &str) -> Error {
+    Error::Saml(reason.to_owned())
+}
+
+pub(super) fn is(node: Node<'_, '_>, namespace: &str, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(namespace)
+        && node.tag_name().name() == name
+}
+
+pub(super) fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &str,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is(*child, namespace, name))
+}
+
+/// The `PrefixList` of an `InclusiveNamespaces` element under `node`.
+fn prefix_list(node: Node<'_, '_>) -> Vec<String> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == "InclusiveNamespaces")
+        .and_then(|child| child.attribute("PrefixList"))
+        .map(|list| {
+            list.split_whitespace()
+                .map(|prefix| if prefix == "#default" { "" } else { prefix })
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Exclusive XML canonicalization (without comments) of the subtree rooted
+/// at `apex`, leaving out `excluded` and everything below it.
+fn canonicalize(
+    apex: Node<'_, '_>,
+    excluded: Option<Node<'_, '_>>,
+    inclusive: &[String],
+) -> String {
+    let mut out = String::new();
+    write_node(apex, excluded, inclusive, &BTreeMap::new(), &mut out);
+    out
+}
+
+fn write_node(
+    node: Node<'_, '_>,
+    excluded: Option<Node<'_, '_>>,
+    inclusive: &[String],
+    rendered: &BTreeMap<String, String>,
+    out: &mut String,
+) {
+    if excluded == Some(node) {
+        return;
+    }
+
+    match node.node_type() {
+        NodeType::Element => {}
+        NodeType::Text => {
+            escape_text(node.text().unwrap_or_default(), out);
+            return;
+        }
+        NodeType::PI => {
+            if let Some(pi) = node.pi() {
+                out.push_str("<?");
+                out.push_str(pi.target);
+                if let Some(value) = pi.value {
+                    out.push(' ');
+                    out.push_str(value);
+                }
+                out.push_str("?>");
+            }
+            return;
+        }
+        NodeType::Root | NodeType::Comment => return,
+    }
+
+    let source = node.document().input_text();
+    let qname = element_qname(node, source);
+    let element_prefix = prefix_of(qname);
+
+    // Namespaces are rendered where they are visibly used, plus any listed
+    // in the transform's PrefixList, unless an output ancestor already
+    // declared the same binding.
+    let mut used: Vec<&str> = vec![element_prefix];
+    let mut attributes = Vec::new();
+    for attribute in node.attributes() {
+        let attr_qname = &source[attribute.range_qname()];
+        let prefix = prefix_of(attr_qname);
+        if !prefix.is_empty() && prefix != "xml" {
+            used.push(prefix);
+        }
+        attributes.push((
+            attribute.namespace().unwrap_or_default(),
+            attribute.name(),
+            attr_qname,
+            attribute.value(),
+        ));
+    }
+    used.extend(inclusive.iter().map(String::as_str));
+
+    let mut declarations = BTreeMap::new();
+    for prefix in used {
+        let uri = if prefix.is_empty() {
+            node.namespaces()
+                .find(|ns| ns.name().is_none())
+                .map(|ns| ns.uri())
+                .unwrap_or_default()
+        } else {
+            match node.namespaces().find(|ns| ns.name() == Some(prefix)) {
+                Some(ns) if ns.uri() != XML_NS => ns.uri(),
+                _ => continue,
+            }
+        };
+
+        let already = rendered.get(prefix).map_or("", String::as_str);
+        if already != uri {
+            declarations.insert(prefix.to_owned(), uri.to_owned());
+        }
+    }
+
+    attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    out.push('<');
+    out.push_str(qname);
+    for (prefix, uri) in &declarations {
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        escape_attribute(uri, out);
+        out.push('"');
+    }
+    for (_, _, attr_qname, value) in attributes {
+        out.push(' ');
+        out.push_str(attr_qname);
+        out.push_str("=\"");
+        escape_attribute(value, out);
+        out.push('"');
+    }
+    out.push('>');
+
+    let mut in_scope = rendered.clone();
+    in_scope.extend(declarations);
+    for child in node.children() {
+        write_node(child, excluded, inclusive, &in_scope, out);
+    }
+
+    out.push_str("</");
+    out.push_str(qname);
+    out.push('>');
+}
+
+/// The element name exactly as written, prefix included.
+fn element_qname<'input>(node: Node<'_, 'input>, source: &'input str) -> &'input str {
+    let start = node.range().start + 1;
+    let rest = &source[start..];
+    let end = rest
+        .find(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+        .unwrap_or(rest.len());
+    &rest[..end]
+}
+
+fn prefix_of(qname: &str) -> &str {
+    qname.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{Extensions, HeaderMap, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+
+use crate::AppContext;
+
+/// The IP address of the client, when the server was started with
+/// connection info.
+///
+/// That is the peer address of the connection, unless the peer is one of
+/// `server.trusted_proxies`: the client is then the nearest address in the
+/// `Forwarded` header, or `X-Forwarded-For` without one, that is not a
+/// trusted proxy itself. Resolved once per request by [`middleware`].
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl ClientIp {
+    /// The client of a request from `peer` with `headers`, believing the
+    /// forwarding headers only as far as they were added by `trusted`
+    /// proxies. A hop that is not an IP address, such as `unknown`, stops
+    /// the search at the last proxy.
+    ///
+    /// ## Examples
+    /// ```
+    /// # use betterauth::security::ClientIp;
+    /// # use axum::http::HeaderMap;
+    /// let trusted = ["10.0.0.0/8".parse().unwrap()];
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("x-forwarded-for", "198.51.100.1, 203.0.113.9, 10.0.0.2".parse().unwrap());
+    ///
+    /// let proxy = "10.0.0.1".parse().unwrap();
+    /// assert_eq!(ClientIp::resolve(proxy, &headers, &trusted).to_string(), "203.0.113.9");
+    ///
+    /// let stranger = "192.0.2.7".parse().unwrap();
+    /// assert_eq!(ClientIp::resolve(stranger, &headers, &trusted), stranger);
+    ///
+    /// headers.insert("forwarded", r#"for="[2001:db8::17]:4711";proto=https"#.parse().unwrap());
+    /// assert_eq!(ClientIp::resolve(proxy, &headers, &trusted).to_string(), "2001:db8::17");
+    /// ```
+    #[must_use]
+    pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted: &[IpNet]) -> IpAddr {
+        let is_trusted = |ip: IpAddr| trusted.iter().any(|network| network.contains(&ip));
+
+        let mut client = peer.to_canonical();
+        if !is_trusted(client) {
+            return client;
+        }
+
+        for hop in forwarded_for(headers).into_iter().rev() {
+            let Some(hop) = hop else {
+                break;
+            };
+            client = hop.to_canonical();
+            if !is_trusted(client) {
+                break;
+            }
+        }
+
+        client
+    }
+
+    /// The client resolved by [`middleware`], or the peer address on a
+    /// router without it.
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Self {
+        extensions.get::<Self>().copied().unwrap_or_else(|| {
+            Self(
+                extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip()),
+            )
+        })
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_extensions(&parts.extensions))
+    }
+}
+
+/// The addresses the request was forwarded for, the client first and the
+/// nearest proxy last, `None` for those that are not IP addresses. Taken
+/// from `Forwarded` when there is one, from `X-Forwarded-For` otherwise.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+    };
+
+    if headers.contains_key("forwarded") {
+        values("forwarded")
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect()
+    } else {
+        values("x-forwarded-for").map(parse_node).collect()
+    }
+}
+
+/// An address as forwarding headers write it, quoted or not, with or
+/// without a port, IPv6 in brackets when it has one.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+
+    if let Some(rest) = node.strip_prefix('[') {
+        let (ip, _) = rest.split_once(']')?;
+        return ip.parse::<Ipv6Addr>().ok().map(IpAddr::V6);
+    }
+
+    node.parse::<IpAddr>().ok().or_else(|| {
+        let (ip, _) = node.split_once(':')?;
+        ip.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+    })
+}
+
+/// Axum middleware resolving the [`ClientIp`] of the request for the layers
+/// and handlers inside it.
+///
+/// Must run outside the trace layer, so requests are logged with the
+/// client rather than the proxy they came through.
+pub async fn middleware(
+    State(ctx): State<Arc<AppContext>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let ip = peer.map(|peer| {
+        ClientIp::resolve(
+            peer,
+            request.headers(),
+            ctx.config().server().trusted_proxies(),
+        )
+    });
+
+    request.extensions_mut().insert(ClientIp(ip));
+    next.run(request).await
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{AppContext, Error, Result, sessions::Session};
+
+/// Header carrying the CSRF token on state-changing requests.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+fn mac(secret: &str, session_id: Uuid) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"csrf:");
+    mac.update(session_id.as_bytes());
+    mac
+}
+
+/// The CSRF token of session `session_id`.
+///
+/// Tokens are derived from the session with `auth.secret`, so they need no
+/// storage, stay valid for the life of the session and are useless with any
+/// other session.
+#[must_use]
+pub fn csrf_token(secret: &str, session_id: Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(mac(secret, session_id).finalize().into_bytes())
+}
+
+fn is_valid(secret: &str, session_id: Uuid, token: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(token)
+        .is_ok_and(|tag| mac(secret, session_id).verify_slice(&tag).is_ok())
+}
+
+/// Axum middleware requiring a CSRF token on state-changing requests that
+/// carry a session cookie.
+///
+/// Requests other than `GET`, `HEAD`, `OPTIONS` and `TRACE` that were
+/// authenticated by the session cookie must repeat the session's token, from
+/// `GET /auth/csrf`, in the [`CSRF_HEADER`]. Requests without a session cookie,
+/// such as API clients sending bearer tokens or API keys, are not affected
+/// because browsers never attach those on their own. Disabled by
+/// `security.csrf_protection: false`.
+///
+/// Must run inside [`crate::sessions::middleware`].
+///
+/// ## Errors
+/// * [`Error::CsrfFailed`] if the token is missing or wrong
+pub async fn middleware(
+    State(ctx): State<Arc<AppContext>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if !ctx.config().security().csrf_protection() || request.method().is_safe() {
+        return Ok(next.run(request).await);
+    }
+
+    if let Some(session) = request.extensions().get::<Session>() {
+        let valid = request
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|token| {
+                is_valid(ctx.config().auth().secret().expose(), session.id, token)
+            });
+
+        if !valid {
+            return Err(Error::CsrfFailed);
+        }
+    }
+
+    Ok(next.run(request).await)
+}
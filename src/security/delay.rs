@@ -0,0 +1,228 @@
+use std::{net::IpAddr, sync::LazyLock};
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use redis::Script;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{Error, Result, cache::Cache, config::SecurityConfig};
+
+/// Returns the seconds to wait before the next attempt on the failures in
+/// `KEYS[1]`, after dropping those older than `ARGV[1]` seconds. Delays
+/// start after `ARGV[2]` failures at `ARGV[3]` seconds, doubling with each
+/// further failure up to `ARGV[4]`. Uses the Redis clock so instances agree.
+static CHECK: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local window = tonumber(ARGV[1])
+        local after = tonumber(ARGV[2])
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+        redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+        local failures = redis.call('ZCARD', KEYS[1])
+        if failures < after then
+            return 0
+        end
+        local last = tonumber(redis.call('ZRANGE', KEYS[1], -1, -1, 'WITHSCORES')[2])
+        local delay = math.min(tonumber(ARGV[4]), tonumber(ARGV[3]) * 2 ^ (failures - after))
+        return math.max(0, math.ceil(last + delay - now))
+        ",
+    )
+});
+
+/// Adds a failure to `KEYS[1]`, named after the time and `ARGV[2]` so that
+/// simultaneous ones are all kept, and drops those older than `ARGV[1]`
+/// seconds.
+static RECORD: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local window = tonumber(ARGV[1])
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+        redis.call('ZADD', KEYS[1], now, tostring(now) .. ':' .. ARGV[2])
+        redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+        redis.call('EXPIRE', KEYS[1], math.ceil(window))
+        return 0
+        ",
+    )
+});
+
+/// Progressive delays between failed logins for the same identifier from
+/// the same IP, counted over a sliding window.
+///
+/// The pairs are kept as a SHA-256 hash, so neither Redis nor Postgres holds
+/// the identifiers tried. Failures are counted in Redis when a [`Cache`] is
+/// given and in the `failed_login_identifiers` table otherwise, or while
+/// Redis fails.
+#[derive(Clone)]
+pub(super) struct LoginDelays {
+    db: PgPool,
+    config: SecurityConfig,
+    cache: Option<Cache>,
+}
+
+impl LoginDelays {
+    pub(super) fn new(db: PgPool, config: SecurityConfig) -> Self {
+        Self {
+            db,
+            config,
+            cache: None,
+        }
+    }
+
+    pub(super) fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Refuses the attempt until the delay earned by earlier failures of
+    /// `identifier` from `ip` has passed.
+    ///
+    /// ## Errors
+    /// * [`Error::RateLimited`] with the seconds left to wait
+    /// * Database errors
+    pub(super) async fn check(&self, identifier: &str, ip: Option<IpAddr>) -> Result<()> {
+        if self.config.login_delay() == 0 {
+            return Ok(());
+        }
+
+        let key = pair_key(identifier, ip);
+
+        if let Some(cache) = &self.cache {
+            #[allow(clippy::cast_precision_loss)]
+            let args = [
+                self.config.login_delay_window() as f64,
+                f64::from(self.config.login_delay_after()),
+                self.config.login_delay() as f64,
+                self.config.max_login_delay() as f64,
+            ];
+            match cache.eval::<u64>(&CHECK, &redis_key(&key), &args).await {
+                Ok(0) => return Ok(()),
+                Ok(retry_after) => return Err(Error::RateLimited { retry_after }),
+                Err(err) => {
+                    tracing::warn!(error = %err, "Shared login delays failed, using Postgres");
+                }
+            }
+        }
+
+        let (failures, last): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+            r"
+            SELECT COUNT(*), MAX(created_at)
+            FROM failed_login_identifiers
+            WHERE key = $1 AND created_at > $2
+            ",
+        )
+        .bind(&key)
+        .bind(self.window_start())
+        .fetch_one(&self.db)
+        .await?;
+
+        let after = i64::from(self.config.login_delay_after());
+        let Some(last) = last.filter(|_| failures >= after) else {
+            return Ok(());
+        };
+
+        let doublings = u32::try_from(failures - after).unwrap_or(u32::MAX).min(32);
+        let delay = self
+            .config
+            .login_delay()
+            .saturating_mul(1 << doublings)
+            .min(self.config.max_login_delay());
+        let ready_at = last + Duration::seconds(i64::try_from(delay).unwrap_or(i64::MAX));
+        let wait = (ready_at - Utc::now()).num_milliseconds();
+
+        if wait > 0 {
+            return Err(Error::RateLimited {
+                retry_after: u64::try_from(wait).unwrap_or(0).div_ceil(1000),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Counts a failed attempt at `identifier` from `ip`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub(super) async fn record_failure(&self, identifier: &str, ip: Option<IpAddr>) -> Result<()> {
+        if self.config.login_delay() == 0 {
+            return Ok(());
+        }
+
+        let key = pair_key(identifier, ip);
+
+        if let Some(cache) = &self.cache {
+            #[allow(clippy::cast_precision_loss)]
+            let args = [
+                self.config.login_delay_window() as f64,
+                f64::from(rand::rng().random::<u32>()),
+            ];
+            match cache.eval::<i64>(&RECORD, &redis_key(&key), &args).await {
+                Ok(_) => return Ok(()),
+                Err(err) => {
+                    tracing::warn!(error = %err, "Shared login delays failed, using Postgres");
+                }
+            }
+        }
+
+        sqlx::query("DELETE FROM failed_login_identifiers WHERE created_at <= $1")
+            .bind(self.window_start())
+            .execute(&self.db)
+            .await?;
+
+        sqlx::query("INSERT INTO failed_login_identifiers (key, created_at) VALUES ($1, $2)")
+            .bind(&key)
+            .bind(Utc::now())
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Forgets the failures of `identifier` from `ip` after it succeeded.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub(super) async fn clear(&self, identifier: &str, ip: Option<IpAddr>) -> Result<()> {
+        if self.config.login_delay() == 0 {
+            return Ok(());
+        }
+
+        let key = pair_key(identifier, ip);
+
+        if let Some(cache) = &self.cache
+            && let Err(err) = cache.delete(&[redis_key(&key)]).await
+        {
+            tracing::warn!(error = %err, "Failed to clear shared login delays");
+        }
+
+        sqlx::query("DELETE FROM failed_login_identifiers WHERE key = $1")
+            .bind(&key)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    fn window_start(&self) -> DateTime<Utc> {
+        Utc::now()
+            - Duration::seconds(i64::try_from(self.config.login_delay_window()).unwrap_or(i64::MAX))
+    }
+}
+
+/// Hex SHA-256 of `identifier`, case-folded, and `ip`.
+fn pair_key(identifier: &str, ip: Option<IpAddr>) -> String {
+    let ip = ip.map_or_else(|| String::from("-"), |ip| ip.to_string());
+    let digest = Sha256::new()
+        .chain_update(identifier.trim().to_lowercase())
+        .chain_update([0])
+        .chain_update(ip)
+        .finalize();
+
+    format!("{digest:x}")
+}
+
+fn redis_key(key: &str) -> String {
+    format!("login_delay:{key}")
+}
@@ -0,0 +1,189 @@
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use axum::http::{HeaderMap, HeaderValue, header};
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Result,
+    events::{Event, Subscriber},
+    sessions::{Device, Session},
+};
+
+/// What was unusual about a login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginAnomaly {
+    /// The browser and operating system had not been used with the account.
+    NewDevice,
+    /// The client network had not been used with the account.
+    NewLocation,
+}
+
+impl LoginAnomaly {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NewDevice => "new_device",
+            Self::NewLocation => "new_location",
+        }
+    }
+}
+
+/// Flags logins from devices or locations an account has not used before.
+///
+/// Every login is remembered as the [`Device`] its session was created
+/// from, by name, e.g. `Firefox on Linux`, and the network of its IP, the `/24` of an IPv4 address or the
+/// `/48` of an IPv6 one, standing in for its location. A login whose device
+/// or network is new for the account publishes an [`Event::SuspiciousLogin`],
+/// which is audited and, with `security.notify_suspicious_login`, emailed to
+/// the user. The very first login of an account is never flagged.
+#[derive(Clone)]
+pub struct LoginMonitor {
+    db: PgPool,
+}
+
+impl LoginMonitor {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Remembers that `user_id` logged in from `device` and `ip`, and
+    /// returns what was new about it.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn observe(
+        &self,
+        user_id: Uuid,
+        device: &Device,
+        ip: Option<IpAddr>,
+    ) -> Result<Vec<LoginAnomaly>> {
+        let device = device.name();
+        let network = network_of(ip);
+
+        let (known, known_device, known_network): (bool, bool, bool) = sqlx::query_as(
+            r"
+            SELECT COUNT(*) > 0,
+                   COALESCE(bool_or(device = $2), false),
+                   COALESCE(bool_or(network = $3), false)
+            FROM known_logins
+            WHERE user_id = $1
+            ",
+        )
+        .bind(user_id)
+        .bind(&device)
+        .bind(&network)
+        .fetch_one(&self.db)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO known_logins (user_id, device, network, first_seen_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, $4)
+            ON CONFLICT (user_id, device, network) DO UPDATE SET last_seen_at = EXCLUDED.last_seen_at
+            ",
+        )
+        .bind(user_id)
+        .bind(&device)
+        .bind(&network)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+
+        if !known {
+            return Ok(Vec::new());
+        }
+
+        let mut anomalies = Vec::new();
+        if !known_device {
+            anomalies.push(LoginAnomaly::NewDevice);
+        }
+        if !known_network {
+            anomalies.push(LoginAnomaly::NewLocation);
+        }
+
+        Ok(anomalies)
+    }
+}
+
+#[async_trait]
+impl Subscriber for LoginMonitor {
+    async fn handle(&self, ctx: &AppContext, event: &Event) -> Result<()> {
+        let Event::UserLoggedIn {
+            user_id,
+            session_id,
+            ..
+        } = event
+        else {
+            return Ok(());
+        };
+
+        let Some(session) = ctx.sessions().find(*session_id).await? else {
+            return Ok(());
+        };
+
+        let ip = session.ip.as_deref().and_then(|ip| ip.parse().ok());
+        let device = device_of(&session);
+        let anomalies = self.observe(*user_id, &device, ip).await?;
+
+        if anomalies.is_empty() {
+            return Ok(());
+        }
+
+        let Some(email) = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        tracing::warn!(%user_id, %session_id, "Login from a new device or location");
+
+        ctx.publish(Event::SuspiciousLogin {
+            user_id: *user_id,
+            session_id: *session_id,
+            email,
+            device: device.name(),
+            ip,
+            anomalies,
+        })
+        .await
+    }
+}
+
+/// The device of `session`, parsed from its `User-Agent` for sessions
+/// created before devices were recorded.
+fn device_of(session: &Session) -> Device {
+    if let Some(device) = &session.device {
+        return device.clone();
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Some(user_agent) = session
+        .user_agent
+        .as_deref()
+        .and_then(|user_agent| HeaderValue::from_str(user_agent).ok())
+    {
+        headers.insert(header::USER_AGENT, user_agent);
+    }
+    Device::from_headers(&headers)
+}
+
+/// The network `ip` belongs to, its `/24` for IPv4 and `/48` for IPv6.
+pub(crate) fn network_of(ip: Option<IpAddr>) -> String {
+    match ip {
+        Some(IpAddr::V4(ip)) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        Some(IpAddr::V6(ip)) => {
+            let [a, b, c, ..] = ip.segments();
+            format!("{a:x}:{b:x}:{c:x}::/48")
+        }
+        None => String::from("unknown"),
+    }
+}
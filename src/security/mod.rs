@@ -0,0 +1,202 @@
+mod captcha;
+mod client_ip;
+mod csrf;
+mod delay;
+mod login_monitor;
+mod rate_limit;
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use self::delay::LoginDelays;
+pub(crate) use self::login_monitor::network_of;
+pub use self::{
+    captcha::{CAPTCHA_HEADER, Captcha, CaptchaVerifier, SiteVerify},
+    client_ip::{ClientIp, middleware as client_ip},
+    csrf::{CSRF_HEADER, csrf_token, middleware as csrf},
+    login_monitor::{LoginAnomaly, LoginMonitor},
+    rate_limit::{MAX_BUCKETS, RateLimiter, middleware as rate_limit},
+};
+use crate::{Error, Result, cache::Cache, config::SecurityConfig};
+
+/// Tracks failed logins per account, per client IP, and per identifier
+/// tried from an IP.
+///
+/// Accounts are locked after too many consecutive failures; IPs are refused
+/// while their failures within the configured window exceed the limit; and
+/// an email or username tried from the same IP has to wait longer and
+/// longer between attempts, see [`SecurityConfig`].
+#[derive(Clone)]
+pub struct LoginThrottle {
+    db: PgPool,
+    config: SecurityConfig,
+    delays: LoginDelays,
+}
+
+impl LoginThrottle {
+    #[must_use]
+    pub fn new(db: PgPool, config: SecurityConfig) -> Self {
+        Self {
+            delays: LoginDelays::new(db.clone(), config.clone()),
+            db,
+            config,
+        }
+    }
+
+    /// Counts the failures behind login delays in `cache`, shared by every
+    /// instance.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.delays = self.delays.with_cache(cache);
+        self
+    }
+
+    /// Refuses an attempt at `identifier`, the email, username or user id
+    /// being logged in as, if `ip` has failed too often recently or has to
+    /// wait longer after failing for `identifier`.
+    ///
+    /// ## Errors
+    /// * [`Error::TooManyAttempts`] if the IP is over its limit
+    /// * [`Error::RateLimited`] if the attempt comes before its delay passed
+    /// * Database errors
+    pub async fn check(&self, identifier: &str, ip: Option<IpAddr>) -> Result<()> {
+        self.check_ip(ip).await?;
+        self.delays.check(identifier, ip).await
+    }
+
+    /// Refuses the attempt if `ip` has failed too often recently.
+    ///
+    /// ## Errors
+    /// * [`Error::TooManyAttempts`] if the IP is over its limit
+    /// * Database errors
+    pub async fn check_ip(&self, ip: Option<IpAddr>) -> Result<()> {
+        let Some(ip) = ip else {
+            return Ok(());
+        };
+
+        let failures: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM failed_logins WHERE ip = $1 AND created_at > $2",
+        )
+        .bind(ip.to_string())
+        .bind(self.window_start())
+        .fetch_one(&self.db)
+        .await?;
+
+        if failures >= i64::from(self.config.max_failed_logins_per_ip()) {
+            return Err(Error::TooManyAttempts);
+        }
+
+        Ok(())
+    }
+
+    /// Refuses the attempt if `locked_until` is still in the future.
+    ///
+    /// ## Errors
+    /// * [`Error::TooManyAttempts`] if the account is locked
+    pub fn check_account(locked_until: Option<DateTime<Utc>>) -> Result<()> {
+        match locked_until {
+            Some(until) if until > Utc::now() => Err(Error::TooManyAttempts),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a failed login at `identifier` from `ip`, counting it against
+    /// `user_id` when the identifier belonged to an account. Locks the
+    /// account once it reaches the configured number of consecutive
+    /// failures.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn record_failure(
+        &self,
+        identifier: &str,
+        ip: Option<IpAddr>,
+        user_id: Option<Uuid>,
+    ) -> Result<()> {
+        self.delays.record_failure(identifier, ip).await?;
+
+        if let Some(ip) = ip {
+            sqlx::query("DELETE FROM failed_logins WHERE created_at <= $1")
+                .bind(self.window_start())
+                .execute(&self.db)
+                .await?;
+
+            sqlx::query("INSERT INTO failed_logins (ip, created_at) VALUES ($1, $2)")
+                .bind(ip.to_string())
+                .bind(Utc::now())
+                .execute(&self.db)
+                .await?;
+        }
+
+        let Some(user_id) = user_id else {
+            return Ok(());
+        };
+
+        let lockout =
+            Duration::seconds(i64::try_from(self.config.lockout_duration()).unwrap_or(i64::MAX));
+
+        // The counter restarts once an account is locked, so it gets a fresh
+        // set of attempts when the lock expires.
+        let locked_until: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r"
+            UPDATE users
+            SET failed_login_attempts = CASE
+                    WHEN failed_login_attempts + 1 >= $2 THEN 0
+                    ELSE failed_login_attempts + 1
+                END,
+                locked_until = CASE
+                    WHEN failed_login_attempts + 1 >= $2 THEN $3
+                    ELSE locked_until
+                END
+            WHERE id = $1
+            RETURNING locked_until
+            ",
+        )
+        .bind(user_id)
+        .bind(i32::try_from(self.config.max_failed_logins()).unwrap_or(i32::MAX))
+        .bind(Utc::now() + lockout)
+        .fetch_optional(&self.db)
+        .await?
+        .flatten();
+
+        if let Some(until) = locked_until.filter(|until| *until > Utc::now()) {
+            tracing::warn!(%user_id, %until, "Account locked after repeated failed logins");
+        }
+
+        Ok(())
+    }
+
+    /// Clears the failure counts of `user_id`, and of `identifier` from
+    /// `ip`, after a successful login.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn record_success(
+        &self,
+        identifier: &str,
+        ip: Option<IpAddr>,
+        user_id: Uuid,
+    ) -> Result<()> {
+        self.delays.clear(identifier, ip).await?;
+
+        sqlx::query(
+            r"
+            UPDATE users
+            SET failed_login_attempts = 0, locked_until = NULL
+            WHERE id = $1 AND (failed_login_attempts > 0 OR locked_until IS NOT NULL)
+            ",
+        )
+        .bind(user_id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    fn window_start(&self) -> DateTime<Utc> {
+        Utc::now() - Duration::seconds(i64::try_from(self.config.ip_window()).unwrap_or(i64::MAX))
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, LazyLock, Mutex},
+    time::Instant,
+};
+
+use arc_swap::ArcSwap;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use redis::Script;
+use uuid::Uuid;
+
+use super::ClientIp;
+use crate::{
+    AppContext, Error, Result,
+    cache::Cache,
+    config::{RateLimit, RateLimitConfig},
+    sessions::Session,
+    tokens::TokenKind,
+};
+
+/// Most in-memory buckets a [`RateLimiter`] holds. Past it, buckets that
+/// have refilled are dropped, then those used least recently.
+pub const MAX_BUCKETS: usize = 10_000;
+
+/// Takes a token from the bucket in `KEYS[1]`, holding at most `ARGV[1]`
+/// tokens and refilled at `ARGV[2]` per second. Returns `0`, or the seconds
+/// until a token is available. Uses the Redis clock so instances agree.
+static TAKE: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local capacity = tonumber(ARGV[1])
+        local rate = tonumber(ARGV[2])
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+        local tokens = tonumber(bucket[1]) or capacity
+        local updated = tonumber(bucket[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - updated) * rate)
+        local wait = 0
+        if tokens >= 1 then
+            tokens = tokens - 1
+        else
+            wait = math.max(1, math.ceil((1 - tokens) / rate))
+        end
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', tostring(now))
+        redis.call('EXPIRE', KEYS[1], math.ceil(capacity / rate) + 1)
+        return wait
+        ",
+    )
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Subject {
+    Ip(IpAddr),
+    User(Uuid),
+}
+
+impl Subject {
+    fn key(&self, group: &str) -> String {
+        match self {
+            Self::Ip(ip) => format!("rate_limit:{group}:ip:{ip}"),
+            Self::User(id) => format!("rate_limit:{group}:user:{id}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(limit.requests()),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let capacity = f64::from(limit.requests());
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        let rate = capacity / limit.period().as_secs_f64().max(f64::EPSILON);
+
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated = now;
+    }
+
+    /// Takes a token, or returns how many seconds until one is available.
+    fn take(&mut self, limit: &RateLimit) -> Result<(), u64> {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let rate = f64::from(limit.requests()) / limit.period().as_secs_f64().max(f64::EPSILON);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let wait = ((1.0 - self.tokens) / rate).ceil() as u64;
+
+        Err(wait.max(1))
+    }
+}
+
+/// Token buckets enforcing the `rate_limit` config section.
+///
+/// Without a [`Cache`] counts are in memory and per process; behind a load
+/// balancer each instance enforces the limits on its own share of the
+/// traffic. With one the buckets live in Redis and are shared, falling back
+/// to the in-memory ones while Redis fails. The policies can be swapped
+/// while running with [`RateLimiter::reconfigure`].
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<ArcSwap<RateLimitConfig>>,
+    buckets: Arc<Mutex<HashMap<(String, Subject), Bucket>>>,
+    cache: Option<Cache>,
+}
+
+impl RateLimiter {
+    #[must_use]
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: Arc::new(ArcSwap::from_pointee(config)),
+            buckets: Arc::default(),
+            cache: None,
+        }
+    }
+
+    /// Keeps the buckets in `cache`.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Buckets held in memory, at most [`MAX_BUCKETS`] however many clients
+    /// there are.
+    ///
+    /// ```
+    /// use std::net::{IpAddr, Ipv4Addr};
+    ///
+    /// use betterauth::security::{MAX_BUCKETS, RateLimiter};
+    /// use serde_json::json;
+    ///
+    /// let limiter = RateLimiter::new(serde_json::from_value(json!({ "default": "5/minute" })).unwrap());
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// for n in 0..2 * MAX_BUCKETS as u32 {
+    ///     let ip = IpAddr::V4(Ipv4Addr::from(n));
+    ///     limiter.check("/auth/login", Some(ip), None).await.unwrap();
+    /// }
+    /// # });
+    ///
+    /// assert!(limiter.local_buckets() <= MAX_BUCKETS);
+    /// ```
+    #[must_use]
+    pub fn local_buckets(&self) -> usize {
+        self.buckets
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .len()
+    }
+
+    /// Enforces `config` from now on. Buckets are kept, so a tightened limit
+    /// applies to tokens already taken.
+    pub fn reconfigure(&self, config: RateLimitConfig) {
+        self.config.store(Arc::new(config));
+    }
+
+    /// Counts a request to `path` by `ip` and `user_id`, against the limits
+    /// of its route group and of the endpoint itself.
+    ///
+    /// ## Errors
+    /// * [`Error::RateLimited`] if any applicable limit is exhausted
+    pub async fn check(&self, path: &str, ip: Option<IpAddr>, user_id: Option<Uuid>) -> Result<()> {
+        let config = self.config.load_full();
+
+        let limits: Vec<_> = [config.policy(path), config.endpoint_policy(path)]
+            .into_iter()
+            .flatten()
+            .flat_map(|(name, policy)| {
+                [
+                    policy.ip().zip(ip.map(Subject::Ip)),
+                    policy.user().zip(user_id.map(Subject::User)),
+                ]
+                .into_iter()
+                .flatten()
+                .map(move |(limit, subject)| (name, limit, subject))
+            })
+            .collect();
+
+        if limits.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(cache) = &self.cache {
+            match Self::take_shared(cache, &limits).await {
+                Ok(None) => return Ok(()),
+                Ok(Some(retry_after)) => return Err(Error::RateLimited { retry_after }),
+                Err(err) => {
+                    tracing::warn!(error = %err, "Shared rate limit failed, counting locally")
+                }
+            }
+        }
+
+        self.take_local(&config, &limits)
+    }
+
+    /// Takes a token from each shared bucket, returning the seconds to wait
+    /// if one is empty.
+    async fn take_shared(
+        cache: &Cache,
+        limits: &[(&str, &RateLimit, Subject)],
+    ) -> Result<Option<u64>> {
+        for (group, limit, subject) in limits {
+            let capacity = f64::from(limit.requests());
+            let rate = capacity / limit.period().as_secs_f64().max(f64::EPSILON);
+
+            let wait: u64 = cache
+                .eval(&TAKE, &subject.key(group), &[capacity, rate])
+                .await?;
+            if wait > 0 {
+                return Ok(Some(wait));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn take_local(
+        &self,
+        config: &RateLimitConfig,
+        limits: &[(&str, &RateLimit, Subject)],
+    ) -> Result<()> {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        if buckets.len() + limits.len() > MAX_BUCKETS {
+            Self::prune(&mut buckets, config, now);
+        }
+        if buckets.len() + limits.len() > MAX_BUCKETS {
+            // A tenth more room, so the scan is not repeated for every new
+            // client while under attack from many addresses.
+            Self::evict(
+                &mut buckets,
+                (MAX_BUCKETS - MAX_BUCKETS / 10).saturating_sub(limits.len()),
+            );
+        }
+
+        for (group, limit, subject) in limits {
+            let bucket = buckets
+                .entry(((*group).to_owned(), subject.clone()))
+                .or_insert_with(|| Bucket::full(limit, now));
+            bucket.refill(limit, now);
+            bucket
+                .take(limit)
+                .map_err(|retry_after| Error::RateLimited { retry_after })?;
+        }
+
+        Ok(())
+    }
+
+    /// Drops buckets that have refilled completely, which behave exactly
+    /// like missing ones.
+    fn prune(
+        buckets: &mut HashMap<(String, Subject), Bucket>,
+        config: &RateLimitConfig,
+        now: Instant,
+    ) {
+        buckets.retain(|(group, subject), bucket| {
+            let policy = config
+                .endpoint_policy(group)
+                .or_else(|| config.policy(group));
+            let limit = policy.and_then(|(_, policy)| match subject {
+                Subject::Ip(_) => policy.ip(),
+                Subject::User(_) => policy.user(),
+            });
+
+            limit.is_some_and(|limit| {
+                let mut bucket = *bucket;
+                bucket.refill(limit, now);
+                bucket.tokens < f64::from(limit.requests())
+            })
+        });
+    }
+
+    /// Drops the buckets used least recently until at most `keep` are left.
+    fn evict(buckets: &mut HashMap<(String, Subject), Bucket>, keep: usize) {
+        let excess = buckets.len().saturating_sub(keep);
+        if excess == 0 {
+            return;
+        }
+
+        let mut touched: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+        let (_, &mut cutoff, _) = touched.select_nth_unstable(excess - 1);
+        buckets.retain(|_, bucket| bucket.updated > cutoff);
+    }
+}
+
+/// Axum middleware applying the [`RateLimiter`] of the context.
+///
+/// Must run inside [`crate::sessions::middleware`] so cookie sessions are
+/// attributed to their user. Bearer access tokens are attributed by their
+/// subject without hitting the database; API keys and anonymous callers are
+/// only limited per IP.
+///
+/// ## Errors
+/// * [`Error::RateLimited`] with the seconds to wait, sent as `Retry-After`
+pub async fn middleware(
+    State(ctx): State<Arc<AppContext>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let ClientIp(ip) = ClientIp::from_extensions(request.extensions());
+
+    let user_id = request
+        .extensions()
+        .get::<Session>()
+        .and_then(|session| session.user_id)
+        .or_else(|| {
+            request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .and_then(|token| ctx.tokens().verify(token, TokenKind::Access).ok())
+                .map(|claims| claims.sub)
+        });
+
+    ctx.rate_limiter()
+        .check(request.uri().path(), ip, user_id)
+        .await?;
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,208 @@
+use std::path::Path;
+
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    auth::{Passwords, Role, normalize_email},
+    config::{ConfigError, EmailNormalization},
+    organizations::OrgRole,
+};
+
+/// Users and organizations to load into a development or testing database,
+/// read from a YAML, TOML or JSON file by `betterauth db seed`.
+///
+/// ```yaml
+/// users:
+///   - email: admin@example.com
+///     name: Admin
+///     password: correct-horse-battery-staple
+///     role: admin
+///     verified: true
+///   - email: member@example.com
+/// organizations:
+///   - slug: acme
+///     name: Acme
+///     members:
+///       - email: admin@example.com
+///         role: owner
+///       - email: member@example.com
+/// ```
+///
+/// Seeding is idempotent: users are matched by email address and
+/// organizations by slug, and updated in place when they already exist.
+#[derive(Debug, Default, Deserialize)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub users: Vec<UserFixture>,
+    #[serde(default)]
+    pub organizations: Vec<OrganizationFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserFixture {
+    pub email: String,
+    pub name: Option<String>,
+    /// Left unchanged on existing users when omitted, and no password is set
+    /// on new ones.
+    pub password: Option<String>,
+    #[serde(default = "default_role")]
+    pub role: Role,
+    #[serde(default)]
+    pub verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrganizationFixture {
+    pub slug: String,
+    pub name: String,
+    #[serde(default)]
+    pub members: Vec<MemberFixture>,
+}
+
+/// A membership of the user with `email`, who must be among the fixtures or
+/// already registered.
+#[derive(Debug, Deserialize)]
+pub struct MemberFixture {
+    pub email: String,
+    #[serde(default = "default_org_role")]
+    pub role: OrgRole,
+}
+
+/// How many rows [`Fixtures::apply`] inserted or updated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeedReport {
+    pub users: usize,
+    pub organizations: usize,
+    pub memberships: usize,
+}
+
+fn default_role() -> Role {
+    Role::User
+}
+
+fn default_org_role() -> OrgRole {
+    OrgRole::Member
+}
+
+impl Fixtures {
+    /// Reads the fixtures from `path`, in the format its extension names.
+    ///
+    /// ## Errors
+    /// * The file is missing or malformed
+    pub fn load(path: &Path) -> Result<Self> {
+        let fixtures = config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()
+            .and_then(config::Config::try_deserialize)
+            .map_err(ConfigError::Config)?;
+
+        Ok(fixtures)
+    }
+
+    /// Upserts the fixtures into the Postgres database behind `db`, in one
+    /// transaction, hashing passwords with `passwords`. Users are matched by
+    /// their address as `normalization` folds it.
+    ///
+    /// ## Errors
+    /// * A member is neither among the fixtures nor registered
+    /// * Database errors
+    pub async fn apply(
+        &self,
+        db: &PgPool,
+        passwords: &Passwords,
+        normalization: &EmailNormalization,
+    ) -> Result<SeedReport> {
+        let mut report = SeedReport::default();
+        let now = Utc::now();
+        let mut tx = db.begin().await?;
+
+        for user in &self.users {
+            let password_hash = user
+                .password
+                .as_deref()
+                .map(|password| passwords.hash(password))
+                .transpose()?;
+
+            sqlx::query(
+                r"
+                INSERT INTO users
+                    (email, email_normalized, name, password_hash, role, verified_at, created_at,
+                     updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+                ON CONFLICT (email_normalized) DO UPDATE SET
+                    name = EXCLUDED.name,
+                    password_hash = COALESCE(EXCLUDED.password_hash, users.password_hash),
+                    role = EXCLUDED.role,
+                    verified_at = CASE
+                        WHEN EXCLUDED.verified_at IS NULL THEN NULL
+                        ELSE COALESCE(users.verified_at, EXCLUDED.verified_at)
+                    END,
+                    updated_at = EXCLUDED.updated_at
+                ",
+            )
+            .bind(&user.email)
+            .bind(normalize_email(&user.email, normalization))
+            .bind(&user.name)
+            .bind(password_hash)
+            .bind(user.role.name())
+            .bind(user.verified.then_some(now))
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            report.users += 1;
+        }
+
+        for organization in &self.organizations {
+            let id: Uuid = sqlx::query_scalar(
+                r"
+                INSERT INTO organizations (name, slug, created_at, updated_at)
+                VALUES ($1, $2, $3, $3)
+                ON CONFLICT (slug) DO UPDATE SET name = EXCLUDED.name, updated_at = EXCLUDED.updated_at
+                RETURNING id
+                ",
+            )
+            .bind(&organization.name)
+            .bind(&organization.slug)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            report.organizations += 1;
+
+            for member in &organization.members {
+                let inserted = sqlx::query(
+                    r"
+                    INSERT INTO memberships (organization_id, user_id, role, created_at)
+                    SELECT $1, id, $3, $4 FROM users WHERE email_normalized = $2
+                    ON CONFLICT (organization_id, user_id) DO UPDATE SET role = EXCLUDED.role
+                    ",
+                )
+                .bind(id)
+                .bind(normalize_email(&member.email, normalization))
+                .bind(member.role.name())
+                .bind(now)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+                if inserted == 0 {
+                    return Err(Error::Validation(format!(
+                        "member {} of {} is not a registered user",
+                        member.email, organization.slug
+                    )));
+                }
+
+                report.memberships += 1;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,164 @@
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chacha20poly1305::{
+    AeadCore, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, OsRng},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::{SESSION_COOKIE, Session};
+use crate::config::{CookieConfig, SameSitePolicy};
+
+/// Length of an XChaCha20 nonce.
+const NONCE_LEN: usize = 24;
+
+/// Builds and reads session cookies according to the `cookies` config
+/// section, sealing the token when keys are configured.
+#[derive(Clone)]
+pub struct SessionCookies {
+    config: CookieConfig,
+    name: String,
+    secure: bool,
+    /// Longest a session lasts, in seconds.
+    ttl: u64,
+    /// 32-byte keys derived from the configured ones, current first.
+    keys: Vec<[u8; 32]>,
+}
+
+impl SessionCookies {
+    /// `https` is whether the server URL is https, the default for `Secure`.
+    /// Cookies last for the `ttl` seconds sessions live at most, even where
+    /// idle sessions end sooner, as the browser cannot tell when they do.
+    ///
+    /// # Panics
+    /// If `cookies.same_site` is `none` on a cookie that is not `Secure`,
+    /// which browsers would reject.
+    #[must_use]
+    pub fn from_config(config: &CookieConfig, https: bool, ttl: u64) -> Self {
+        let secure = config.is_secure(https);
+
+        assert!(
+            secure || config.same_site() != SameSitePolicy::None,
+            "cookies.same_site: none requires a secure cookie"
+        );
+
+        Self {
+            config: config.clone(),
+            name: config.name(SESSION_COOKIE),
+            secure,
+            ttl,
+            keys: config
+                .keys()
+                .iter()
+                .map(|key| Sha256::digest(key.expose().as_bytes()).into())
+                .collect(),
+        }
+    }
+
+    /// Name of the session cookie, [`SESSION_COOKIE`] after
+    /// `cookies.prefix`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The cookie handing `token` of `session` to the browser.
+    #[must_use]
+    pub fn build(&self, token: String, session: &Session) -> Cookie<'static> {
+        let ends_at = session.created_at
+            + chrono::Duration::seconds(i64::try_from(self.ttl).unwrap_or(i64::MAX));
+        let max_age = (ends_at.max(session.expires_at) - Utc::now())
+            .num_seconds()
+            .max(0);
+        let value = match self.keys.first() {
+            Some(key) => self.seal(key, &token),
+            None => token,
+        };
+
+        let mut cookie = self.base(value);
+        cookie.set_http_only(self.config.http_only());
+        cookie.set_secure(self.secure);
+        cookie.set_same_site(match self.config.same_site() {
+            SameSitePolicy::Strict => SameSite::Strict,
+            SameSitePolicy::Lax => SameSite::Lax,
+            SameSitePolicy::None => SameSite::None,
+        });
+        cookie.set_max_age(time::Duration::seconds(max_age));
+        cookie
+    }
+
+    /// A cookie that, when set, makes the browser drop the session cookie.
+    #[must_use]
+    pub fn removal(&self) -> Cookie<'static> {
+        self.base(String::new())
+    }
+
+    fn base(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), value);
+        cookie.set_path(self.config.path().to_owned());
+        if let Some(domain) = self.config.domain() {
+            cookie.set_domain(domain.to_owned());
+        }
+        cookie
+    }
+
+    /// The session token in cookie `value`, and whether the cookie was
+    /// sealed with a key other than the current one and should be resealed.
+    /// `None` if the value was tampered with or sealed with an unknown key.
+    #[must_use]
+    pub fn open(&self, value: &str) -> Option<(String, bool)> {
+        if self.keys.is_empty() {
+            return Some((value.to_owned(), false));
+        }
+
+        self.keys
+            .iter()
+            .enumerate()
+            .find_map(|(index, key)| self.unseal(key, value).map(|token| (token, index > 0)))
+    }
+
+    fn seal(&self, key: &[u8; 32], token: &str) -> String {
+        if self.config.encrypt() {
+            let cipher = XChaCha20Poly1305::new(key.into());
+            let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(&nonce, token.as_bytes())
+                .expect("encrypting a session token cannot fail");
+
+            URL_SAFE_NO_PAD.encode([nonce.as_slice(), &ciphertext].concat())
+        } else {
+            let tag = mac(key, token).finalize().into_bytes();
+            format!("{token}.{}", URL_SAFE_NO_PAD.encode(tag))
+        }
+    }
+
+    fn unseal(&self, key: &[u8; 32], value: &str) -> Option<String> {
+        if self.config.encrypt() {
+            let sealed = URL_SAFE_NO_PAD.decode(value).ok()?;
+            if sealed.len() < NONCE_LEN {
+                return None;
+            }
+            let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+            let plaintext = XChaCha20Poly1305::new(key.into())
+                .decrypt(XNonce::from_slice(nonce), ciphertext)
+                .ok()?;
+
+            String::from_utf8(plaintext).ok()
+        } else {
+            let (token, tag) = value.rsplit_once('.')?;
+            let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+            mac(key, token).verify_slice(&tag).ok()?;
+
+            Some(token.to_owned())
+        }
+    }
+}
+
+fn mac(key: &[u8; 32], token: &str) -> Hmac<Sha256> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(token.as_bytes());
+    mac
+}
@@ -0,0 +1,201 @@
+use axum::http::{HeaderMap, HeaderName, header};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Header a client may send with an identifier of its own for the device,
+/// e.g. one a mobile app generates on install. It only feeds into
+/// [`Device::fingerprint`] and is not stored as such.
+pub const DEVICE_ID_HEADER: HeaderName = HeaderName::from_static("x-device-id");
+
+/// Browsers by a token of their `User-Agent`, checked in order as most
+/// browsers also claim to be the ones before them, e.g. Chrome to be Safari.
+const BROWSERS: [(&str, &str); 7] = [
+    ("Edg/", "Edge"),
+    ("OPR/", "Opera"),
+    ("Firefox/", "Firefox"),
+    ("FxiOS/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Safari/", "Safari"),
+];
+
+/// Operating systems by a token of the `User-Agent`, checked in order.
+const SYSTEMS: [(&str, &str); 7] = [
+    ("Windows", "Windows"),
+    ("Android", "Android"),
+    ("iPhone", "iOS"),
+    ("iPad", "iPadOS"),
+    ("Mac OS X", "macOS"),
+    ("CrOS", "ChromeOS"),
+    ("Linux", "Linux"),
+];
+
+/// Longest client name, platform or model kept; the rest is cut off.
+const MAX_NAME_LEN: usize = 32;
+
+/// Longest `X-Device-Id` taken into account.
+const MAX_DEVICE_ID_LEN: usize = 128;
+
+/// What kind of device a client runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    Desktop,
+    Mobile,
+    Tablet,
+    Unknown,
+}
+
+impl DeviceKind {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Desktop => "desktop",
+            Self::Mobile => "mobile",
+            Self::Tablet => "tablet",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// The device a session was created from, as far as its request tells.
+///
+/// Parsed from the `User-Agent`, with the `Sec-CH-UA-Platform`,
+/// `Sec-CH-UA-Mobile` and `Sec-CH-UA-Model` client hints taking precedence
+/// where a browser sends them, and [`DEVICE_ID_HEADER`] telling apart
+/// devices that would otherwise look the same.
+///
+/// ```
+/// use axum::http::HeaderMap;
+/// use betterauth::sessions::{Device, DeviceKind};
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(
+///     "user-agent",
+///     "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+///         .parse()
+///         .unwrap(),
+/// );
+///
+/// let device = Device::from_headers(&headers);
+/// assert_eq!(device.name(), "Firefox on Linux");
+/// assert_eq!(device.kind, DeviceKind::Desktop);
+///
+/// headers.insert("x-device-id", "laptop".parse().unwrap());
+/// assert_ne!(Device::from_headers(&headers).fingerprint, device.fingerprint);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Device {
+    /// Browser, or the product that sent the request for other clients,
+    /// e.g. `Firefox` or `curl`.
+    pub client: Option<String>,
+    /// Operating system, e.g. `macOS`.
+    pub platform: Option<String>,
+    pub kind: DeviceKind,
+    /// Device model, only known from the `Sec-CH-UA-Model` hint.
+    pub model: Option<String>,
+    /// Hex SHA-256 of all of the above and the `X-Device-Id` header, equal
+    /// for sessions created from the same device.
+    pub fingerprint: String,
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Self::from_headers(&HeaderMap::new())
+    }
+}
+
+impl Device {
+    /// The device described by the `User-Agent`, client hints and
+    /// [`DEVICE_ID_HEADER`] of a request.
+    #[must_use]
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let hint = |name| {
+            header(name)
+                .map(|value| value.trim_matches('"'))
+                .filter(|value| !value.is_empty())
+                .map(truncate)
+        };
+
+        let user_agent = header(header::USER_AGENT.as_str()).unwrap_or_default();
+        let find = |names: &[(&str, &'static str)]| {
+            names
+                .iter()
+                .find(|(marker, _)| user_agent.contains(marker))
+                .map(|&(_, name)| String::from(name))
+        };
+
+        let client = find(&BROWSERS).or_else(|| {
+            user_agent
+                .split(['/', ' '])
+                .next()
+                .filter(|product| !product.is_empty())
+                .map(truncate)
+        });
+        let platform = hint("sec-ch-ua-platform").or_else(|| find(&SYSTEMS));
+        let model = hint("sec-ch-ua-model");
+
+        let kind = if header("sec-ch-ua-mobile") == Some("?1") {
+            DeviceKind::Mobile
+        } else if user_agent.contains("iPad") || user_agent.contains("Tablet") {
+            DeviceKind::Tablet
+        } else if user_agent.contains("Mobi") || user_agent.contains("iPhone") {
+            DeviceKind::Mobile
+        } else if user_agent.contains("Android") {
+            DeviceKind::Tablet
+        } else if matches!(
+            platform.as_deref(),
+            Some("Windows" | "macOS" | "Linux" | "ChromeOS" | "Chrome OS")
+        ) {
+            DeviceKind::Desktop
+        } else {
+            DeviceKind::Unknown
+        };
+
+        let device_id: String = header(DEVICE_ID_HEADER.as_str())
+            .unwrap_or_default()
+            .chars()
+            .take(MAX_DEVICE_ID_LEN)
+            .collect();
+        let fingerprint = Sha256::new()
+            .chain_update(client.as_deref().unwrap_or_default())
+            .chain_update([0])
+            .chain_update(platform.as_deref().unwrap_or_default())
+            .chain_update([0])
+            .chain_update(serde_json::to_string(&kind).unwrap_or_default())
+            .chain_update([0])
+            .chain_update(model.as_deref().unwrap_or_default())
+            .chain_update([0])
+            .chain_update(device_id)
+            .finalize();
+
+        Self {
+            client,
+            platform,
+            kind,
+            model,
+            fingerprint: format!("{fingerprint:x}"),
+        }
+    }
+
+    /// Name to show the device by, e.g. `Firefox on Linux`.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match (&self.client, &self.platform) {
+            (Some(client), Some(platform)) => format!("{client} on {platform}"),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => String::from("unknown device"),
+        }
+    }
+}
+
+fn truncate(value: &str) -> String {
+    value.chars().take(MAX_NAME_LEN).collect()
+}
@@ -0,0 +1,308 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{DeviceInfo, LAST_SEEN_RESOLUTION, Session, SessionStore, expiry, new_session};
+use crate::{
+    Error, Result,
+    auth::{generate_token, hash_token},
+    clock::{Clock, SystemClock},
+    models::UserStatus,
+    repositories::UserStore,
+};
+
+#[derive(Default)]
+struct Sessions {
+    by_id: HashMap<Uuid, Session>,
+    /// Token hash of each session, for lookups by token.
+    by_token: HashMap<String, Uuid>,
+}
+
+impl Sessions {
+    fn remove(&mut self, ids: &[Uuid]) {
+        for id in ids {
+            self.by_id.remove(id);
+        }
+        self.by_token.retain(|_, id| self.by_id.contains_key(id));
+    }
+}
+
+/// [`SessionStore`] keeping sessions in the memory of the process, selected
+/// with `auth.session_backend: memory`.
+///
+/// Sessions end when the process stops and are not shared with other
+/// instances, so this is for development and tests. Clones share the same
+/// sessions. Expired sessions are dropped whenever one is created.
+#[derive(Clone)]
+pub struct MemorySessionStore {
+    sessions: Arc<RwLock<Sessions>>,
+    users: Arc<dyn UserStore>,
+    ttl: u64,
+    idle: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemorySessionStore {
+    /// Creates a store whose sessions live for `ttl` seconds, checking in
+    /// `users` that accounts may still log in.
+    #[must_use]
+    pub fn new(users: Arc<dyn UserStore>, ttl: u64) -> Self {
+        Self {
+            sessions: Arc::default(),
+            users,
+            ttl,
+            idle: 0,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Ends sessions unused for `idle` seconds, to within
+    /// [`LAST_SEEN_RESOLUTION`]. 0 keeps them for their whole `ttl`.
+    #[must_use]
+    pub fn with_idle_timeout(mut self, idle: u64) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Sessions) -> T) -> T {
+        f(&self.sessions.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn write<T>(&self, f: impl FnOnce(&mut Sessions) -> T) -> T {
+        f(&mut self
+            .sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Applies `change` to the unexpired session `id` if it `applies`,
+    /// returning the result.
+    fn update(
+        &self,
+        id: Uuid,
+        applies: impl FnOnce(&Session) -> bool,
+        change: impl FnOnce(&mut Session),
+    ) -> Option<Session> {
+        let now = self.clock.now();
+
+        self.write(|sessions| {
+            let session = sessions
+                .by_id
+                .get_mut(&id)
+                .filter(|session| session.expires_at > now && applies(session))?;
+            change(session);
+            Some(session.clone())
+        })
+    }
+
+    fn insert(&self, user_id: Option<Uuid>, device: &DeviceInfo) -> (Session, String) {
+        let token = generate_token();
+        let now = self.clock.now();
+        let session = new_session(user_id, device, now, expiry(now, now, self.ttl, self.idle));
+
+        self.write(|sessions| {
+            let expired: Vec<Uuid> = sessions
+                .by_id
+                .values()
+                .filter(|session| session.expires_at <= now)
+                .map(|session| session.id)
+                .collect();
+            sessions.remove(&expired);
+
+            sessions.by_id.insert(session.id, session.clone());
+            sessions.by_token.insert(hash_token(&token), session.id);
+        });
+
+        (session, token)
+    }
+
+    fn remove_where(&self, ends: impl Fn(&Session) -> bool) -> Vec<Uuid> {
+        self.write(|sessions| {
+            let ended: Vec<Uuid> = sessions
+                .by_id
+                .values()
+                .filter(|session| ends(session))
+                .map(|session| session.id)
+                .collect();
+            sessions.remove(&ended);
+            ended
+        })
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn create(&self, user_id: Uuid, device: &DeviceInfo) -> Result<(Session, String)> {
+        let active = self
+            .users
+            .find(user_id)
+            .await?
+            .is_some_and(|user| user.status == UserStatus::Active);
+        if !active {
+            return Err(Error::AccountDisabled);
+        }
+
+        Ok(self.insert(Some(user_id), device))
+    }
+
+    async fn create_anonymous(&self, device: &DeviceInfo) -> Result<(Session, String)> {
+        Ok(self.insert(None, device))
+    }
+
+    async fn upgrade(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>> {
+        let now = self.clock.now();
+
+        Ok(self.update(
+            id,
+            |session| session.user_id.is_none(),
+            |session| {
+                session.user_id = Some(user_id);
+                session.last_seen_at = now;
+                session.authenticated_at = Some(now);
+            },
+        ))
+    }
+
+    async fn find_by_token(&self, token: &str) -> Result<Option<Session>> {
+        let token_hash = hash_token(token);
+        let now = self.clock.now();
+
+        Ok(self.read(|sessions| {
+            sessions
+                .by_token
+                .get(&token_hash)
+                .and_then(|id| sessions.by_id.get(id))
+                .filter(|session| session.expires_at > now)
+                .cloned()
+        }))
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Session>> {
+        let now = self.clock.now();
+
+        Ok(self.read(|sessions| {
+            sessions
+                .by_id
+                .get(&id)
+                .filter(|session| session.expires_at > now)
+                .cloned()
+        }))
+    }
+
+    async fn set_active_organization(
+        &self,
+        id: Uuid,
+        organization_id: Option<Uuid>,
+    ) -> Result<Option<Session>> {
+        Ok(self.update(
+            id,
+            |_| true,
+            |session| session.active_organization_id = organization_id,
+        ))
+    }
+
+    async fn reauthenticate(&self, id: Uuid, device: &DeviceInfo) -> Result<Option<Session>> {
+        let now = self.clock.now();
+
+        Ok(self.update(
+            id,
+            |session| session.user_id.is_some(),
+            |session| {
+                session.authenticated_at = Some(now);
+                session.user_agent.clone_from(&device.user_agent);
+                session.ip = device.ip.map(|ip| ip.to_string());
+                session.device = Some(device.device.clone());
+            },
+        ))
+    }
+
+    async fn limit(&self, id: Uuid, max_expires_at: DateTime<Utc>) -> Result<Option<Session>> {
+        Ok(self.update(
+            id,
+            |_| true,
+            |session| {
+                session.max_expires_at = Some(max_expires_at);
+                session.expires_at = session.expires_at.min(max_expires_at);
+            },
+        ))
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        let now = self.clock.now();
+
+        let mut sessions: Vec<Session> = self.read(|sessions| {
+            sessions
+                .by_id
+                .values()
+                .filter(|session| session.user_id == Some(user_id) && session.expires_at > now)
+                .cloned()
+                .collect()
+        });
+        sessions.sort_by_key(|session| Reverse(session.last_seen_at));
+
+        Ok(sessions)
+    }
+
+    async fn touch(&self, session: &Session) -> Result<()> {
+        let now = self.clock.now();
+        if now - session.last_seen_at < LAST_SEEN_RESOLUTION {
+            return Ok(());
+        }
+
+        let expires_at = (self.idle > 0).then(|| session.ends_at(now, self.ttl, self.idle));
+        self.write(|sessions| {
+            if let Some(stored) = sessions.by_id.get_mut(&session.id) {
+                stored.last_seen_at = now;
+                stored.expires_at = expires_at.unwrap_or(stored.expires_at);
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn delete_for_user(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let ended =
+            self.remove_where(|session| session.id == id && session.user_id == Some(user_id));
+
+        Ok(!ended.is_empty())
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<()> {
+        self.write(|sessions| sessions.remove(&[id]));
+
+        Ok(())
+    }
+
+    async fn delete_all(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        Ok(self.remove_where(|session| session.user_id == Some(user_id)))
+    }
+
+    async fn delete_others(&self, user_id: Uuid, keep: Uuid) -> Result<Vec<Uuid>> {
+        Ok(self.remove_where(|session| session.user_id == Some(user_id) && session.id != keep))
+    }
+
+    async fn count_active(&self) -> Result<Option<u64>> {
+        let now = self.clock.now();
+
+        Ok(Some(self.read(|sessions| {
+            sessions
+                .by_id
+                .values()
+                .filter(|session| session.user_id.is_some() && session.expires_at > now)
+                .count() as u64
+        })))
+    }
+}
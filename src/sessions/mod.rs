@@ -0,0 +1,846 @@
+mod cookie;
+mod device;
+mod memory;
+mod redis;
+
+use std::{convert::Infallible, net::IpAddr, sync::Arc};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+pub use self::{
+    cookie::SessionCookies,
+    device::{DEVICE_ID_HEADER, Device, DeviceKind},
+    memory::MemorySessionStore,
+    redis::RedisSessionStore,
+};
+use crate::{
+    AppContext, Error, Result,
+    auth::{generate_token, hash_token},
+    cache::Cache,
+    clock::{Clock, SystemClock},
+    config::{BindingAction, IpBinding, SessionBinding},
+    events::Event,
+    security::{ClientIp, network_of},
+};
+
+/// Name of the cookie carrying the session token, after `cookies.prefix`.
+pub const SESSION_COOKIE: &str = "session";
+
+/// A server-side login session.
+///
+/// The session token handed to the client is never stored; only its SHA-256
+/// hash is, see [`hash_token`].
+///
+/// Anonymous guest sessions have no `user_id` until registration upgrades
+/// them to the new account.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    /// The organization the user is currently acting in.
+    pub active_organization_id: Option<Uuid>,
+    /// `User-Agent` of the client that logged in, or last reauthenticated.
+    pub user_agent: Option<String>,
+    /// Address the client logged in from, or last reauthenticated from.
+    pub ip: Option<String>,
+    /// The device the client runs on. `None` for sessions created before
+    /// devices were recorded.
+    #[sqlx(json(nullable))]
+    pub device: Option<Device>,
+    pub created_at: DateTime<Utc>,
+    /// Last time the session was used, to within [`LAST_SEEN_RESOLUTION`].
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Latest the session may last, when an organization of the user holds
+    /// it to a shorter lifetime than `auth.lifetimes`, see
+    /// [`SessionStore::limit`].
+    #[serde(default)]
+    pub max_expires_at: Option<DateTime<Utc>>,
+    /// Last time the user proved who they are, by logging in or through
+    /// `POST /auth/reauthenticate`. `None` for anonymous sessions.
+    pub authenticated_at: Option<DateTime<Utc>>,
+}
+
+/// How stale `last_seen_at` may get before a request refreshes it. Keeps
+/// busy sessions from writing on every request.
+pub const LAST_SEEN_RESOLUTION: Duration = Duration::minutes(1);
+
+/// Longest a session stays cached in Redis, in seconds. Sessions ended
+/// without going through the store, e.g. along with their deleted user,
+/// keep working from the cache for at most this long.
+const CACHE_TTL: i64 = 60;
+
+/// When a session created at `created_at` and last used at `now` ends, given
+/// it lasts `ttl` seconds and, unless 0, `idle` seconds unused.
+pub(crate) fn expiry(
+    created_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    ttl: u64,
+    idle: u64,
+) -> DateTime<Utc> {
+    let seconds = |seconds: u64| Duration::seconds(i64::try_from(seconds).unwrap_or(i64::MAX));
+    let end = created_at + seconds(ttl);
+
+    if idle == 0 {
+        end
+    } else {
+        end.min(now + seconds(idle))
+    }
+}
+
+impl Session {
+    /// When the session ends if used at `now`, given it lasts `ttl` seconds
+    /// and, unless 0, `idle` seconds unused, and no later than
+    /// `max_expires_at`.
+    pub(crate) fn ends_at(&self, now: DateTime<Utc>, ttl: u64, idle: u64) -> DateTime<Utc> {
+        let end = expiry(self.created_at, now, ttl, idle);
+        self.max_expires_at.map_or(end, |max| end.min(max))
+    }
+}
+
+/// A session of `user_id`, or an anonymous one, started at `now` for the
+/// client `device`, for stores that do not build it in SQL.
+fn new_session(
+    user_id: Option<Uuid>,
+    device: &DeviceInfo,
+    now: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Session {
+    Session {
+        id: Uuid::new_v4(),
+        user_id,
+        active_organization_id: None,
+        user_agent: device.user_agent.clone(),
+        ip: device.ip.map(|ip| ip.to_string()),
+        device: Some(device.device.clone()),
+        created_at: now,
+        last_seen_at: now,
+        expires_at,
+        max_expires_at: None,
+        authenticated_at: user_id.map(|_| now),
+    }
+}
+
+/// Longest `User-Agent` stored with a session; the rest is cut off.
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Describes the client a session is created for.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub device: Device,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for DeviceInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(ClientIp(ip)) = ClientIp::from_request_parts(parts, state).await;
+
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LEN).collect());
+
+        Ok(Self {
+            user_agent,
+            ip,
+            device: Device::from_headers(&parts.headers),
+        })
+    }
+}
+
+/// Persistence for [`Session`]s.
+///
+/// [`PgSessionStore`] is the default; another backend, e.g. one keeping
+/// sessions in Redis only, is installed with
+/// [`crate::AppContext::with_session_store`].
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Starts a new session for `user_id`.
+    ///
+    /// Returns the session together with the plain token, which must be sent
+    /// to the client now as it cannot be recovered later. Every login method
+    /// goes through here, so disabled accounts are turned away at this point.
+    ///
+    /// ## Errors
+    /// * [`Error::AccountDisabled`] if the user has been disabled
+    /// * Backend errors
+    async fn create(&self, user_id: Uuid, device: &DeviceInfo) -> Result<(Session, String)>;
+
+    /// Starts an anonymous guest session, returned together with its plain
+    /// token.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn create_anonymous(&self, device: &DeviceInfo) -> Result<(Session, String)>;
+
+    /// Attaches the anonymous session `id` to `user_id`, keeping the session
+    /// and its token. Returns `None` if there is no such unexpired anonymous
+    /// session.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn upgrade(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>>;
+
+    /// Looks up the unexpired session identified by a client token.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn find_by_token(&self, token: &str) -> Result<Option<Session>>;
+
+    /// Looks up an unexpired session by id.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn find(&self, id: Uuid) -> Result<Option<Session>>;
+
+    /// Switches the organization `id` acts in, returning the updated session
+    /// or `None` if it has ended. Membership must be checked by the caller.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn set_active_organization(
+        &self,
+        id: Uuid,
+        organization_id: Option<Uuid>,
+    ) -> Result<Option<Session>>;
+
+    /// Records that the user of session `id` just proved who they are
+    /// again from `device`, binding the session to it, and returns the
+    /// updated session or `None` if it has ended.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn reauthenticate(&self, id: Uuid, device: &DeviceInfo) -> Result<Option<Session>>;
+
+    /// Ends session `id` by `max_expires_at` at the latest, however it is used
+    /// until then, returning the updated session or `None` if it has ended.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn limit(&self, id: Uuid, max_expires_at: DateTime<Utc>) -> Result<Option<Session>>;
+
+    /// Lists the unexpired sessions of `user_id`, most recently used first.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>>;
+
+    /// Records that `session` was just used, unless that was already
+    /// recorded within the last [`LAST_SEEN_RESOLUTION`].
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn touch(&self, session: &Session) -> Result<()>;
+
+    /// Ends a session of `user_id`. Returns `false` if the user has no such
+    /// session.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn delete_for_user(&self, user_id: Uuid, id: Uuid) -> Result<bool>;
+
+    /// Ends a session. Deleting a session that does not exist is not an error.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn delete(&self, id: Uuid) -> Result<()>;
+
+    /// Ends every session of `user_id`, returning the ids of those there
+    /// were.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn delete_all(&self, user_id: Uuid) -> Result<Vec<Uuid>>;
+
+    /// Ends every session of `user_id` except `keep`, returning the ids of
+    /// those ended.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn delete_others(&self, user_id: Uuid, keep: Uuid) -> Result<Vec<Uuid>>;
+
+    /// Counts the unexpired sessions of logged-in users, for the
+    /// `active_sessions` metric. Returns `None` for stores that cannot count
+    /// them cheaply.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn count_active(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Drops the cached copies of sessions `ids`. Call it after changing or
+    /// deleting sessions without going through the store. Does nothing for
+    /// stores without a cache.
+    async fn forget(&self, _ids: &[Uuid]) {}
+}
+
+/// Postgres-backed persistence for [`Session`]s, the default
+/// [`SessionStore`].
+///
+/// With a [`Cache`], lookups are served from Redis when possible and every
+/// change made through the store drops the cached copy.
+#[derive(Clone)]
+pub struct PgSessionStore {
+    db: PgPool,
+    ttl: u64,
+    idle: u64,
+    cache: Option<Cache>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PgSessionStore {
+    /// Creates a store whose sessions live for `ttl` seconds.
+    #[must_use]
+    pub fn new(db: PgPool, ttl: u64) -> Self {
+        Self {
+            db,
+            ttl,
+            idle: 0,
+            cache: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Ends sessions unused for `idle` seconds, to within
+    /// [`LAST_SEEN_RESOLUTION`]. 0 keeps them for their whole `ttl`.
+    #[must_use]
+    pub fn with_idle_timeout(mut self, idle: u64) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    /// Caches session lookups in `cache`.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    async fn cached_by_token(&self, cache: &Cache, token_hash: &str) -> Result<Option<Session>> {
+        let Some(id) = cache.get_json::<Uuid>(&token_key(token_hash)).await? else {
+            return Ok(None);
+        };
+
+        Ok(cache
+            .get_json::<Session>(&session_key(id))
+            .await?
+            .filter(|session| session.expires_at > self.clock.now()))
+    }
+
+    /// Caches `session`, and which session `token_hash` belongs to if given.
+    async fn remember(&self, token_hash: Option<&str>, session: &Session) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        let ttl = (session.expires_at - self.clock.now())
+            .num_seconds()
+            .clamp(0, CACHE_TTL)
+            .cast_unsigned();
+        if ttl == 0 {
+            return;
+        }
+
+        let mut outcome = cache.set_json(&session_key(session.id), session, ttl).await;
+        if let Some(token_hash) = token_hash
+            && outcome.is_ok()
+        {
+            outcome = cache
+                .set_json(&token_key(token_hash), &session.id, ttl)
+                .await;
+        }
+
+        if let Err(err) = outcome {
+            tracing::warn!(session_id = %session.id, error = %err, "Failed to cache session");
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for PgSessionStore {
+    async fn create(&self, user_id: Uuid, device: &DeviceInfo) -> Result<(Session, String)> {
+        let token = generate_token();
+        let now = self.clock.now();
+        let expires_at = expiry(now, now, self.ttl, self.idle);
+
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            INSERT INTO sessions
+                (user_id, token_hash, user_agent, ip, device, created_at, last_seen_at, expires_at,
+                 authenticated_at)
+            SELECT id, $2, $3, $4, $7, $5, $5, $6, $5 FROM users WHERE id = $1 AND status = 'active'
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(device.user_agent.as_deref())
+        .bind(device.ip.map(|ip| ip.to_string()))
+        .bind(now)
+        .bind(expires_at)
+        .bind(Json(&device.device))
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(Error::AccountDisabled)?;
+
+        Ok((session, token))
+    }
+
+    async fn create_anonymous(&self, device: &DeviceInfo) -> Result<(Session, String)> {
+        let token = generate_token();
+        let now = self.clock.now();
+        let expires_at = expiry(now, now, self.ttl, self.idle);
+
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            INSERT INTO sessions
+                (token_hash, user_agent, ip, created_at, last_seen_at, expires_at, device)
+            VALUES ($1, $2, $3, $4, $4, $5, $6)
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(hash_token(&token))
+        .bind(device.user_agent.as_deref())
+        .bind(device.ip.map(|ip| ip.to_string()))
+        .bind(now)
+        .bind(expires_at)
+        .bind(Json(&device.device))
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok((session, token))
+    }
+
+    async fn upgrade(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET user_id = $2, last_seen_at = $3, authenticated_at = $3
+            WHERE id = $1 AND user_id IS NULL AND expires_at > $3
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await?;
+
+        self.forget(&[id]).await;
+
+        Ok(session)
+    }
+
+    async fn find_by_token(&self, token: &str) -> Result<Option<Session>> {
+        let token_hash = hash_token(token);
+
+        if let Some(cache) = &self.cache {
+            match self.cached_by_token(cache, &token_hash).await {
+                Ok(Some(session)) => return Ok(Some(session)),
+                Ok(None) => {}
+                Err(err) => tracing::warn!(error = %err, "Session cache lookup failed"),
+            }
+        }
+
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            SELECT id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                   last_seen_at, expires_at, max_expires_at, authenticated_at
+            FROM sessions
+            WHERE token_hash = $1 AND expires_at > $2
+            ",
+        )
+        .bind(&token_hash)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await?;
+
+        if let Some(session) = &session {
+            self.remember(Some(&token_hash), session).await;
+        }
+
+        Ok(session)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Session>> {
+        if let Some(cache) = &self.cache {
+            match cache.get_json::<Session>(&session_key(id)).await {
+                Ok(Some(session)) if session.expires_at > self.clock.now() => {
+                    return Ok(Some(session));
+                }
+                Ok(_) => {}
+                Err(err) => tracing::warn!(error = %err, "Session cache lookup failed"),
+            }
+        }
+
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            SELECT id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                   last_seen_at, expires_at, max_expires_at, authenticated_at
+            FROM sessions
+            WHERE id = $1 AND expires_at > $2
+            ",
+        )
+        .bind(id)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await?;
+
+        if let Some(session) = &session {
+            self.remember(None, session).await;
+        }
+
+        Ok(session)
+    }
+
+    async fn set_active_organization(
+        &self,
+        id: Uuid,
+        organization_id: Option<Uuid>,
+    ) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET active_organization_id = $2
+            WHERE id = $1 AND expires_at > $3
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(organization_id)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await?;
+
+        self.forget(&[id]).await;
+
+        Ok(session)
+    }
+
+    async fn reauthenticate(&self, id: Uuid, device: &DeviceInfo) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET authenticated_at = $2, user_agent = $3, ip = $4, device = $5
+            WHERE id = $1 AND user_id IS NOT NULL AND expires_at > $2
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(self.clock.now())
+        .bind(&device.user_agent)
+        .bind(device.ip.map(|ip| ip.to_string()))
+        .bind(Json(&device.device))
+        .fetch_optional(&self.db)
+        .await?;
+
+        self.forget(&[id]).await;
+
+        Ok(session)
+    }
+
+    async fn limit(&self, id: Uuid, max_expires_at: DateTime<Utc>) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET max_expires_at = $2, expires_at = LEAST(expires_at, $2)
+            WHERE id = $1 AND expires_at > $3
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(max_expires_at)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await?;
+
+        self.forget(&[id]).await;
+
+        Ok(session)
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            SELECT id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                   last_seen_at, expires_at, max_expires_at, authenticated_at
+            FROM sessions
+            WHERE user_id = $1 AND expires_at > $2
+            ORDER BY last_seen_at DESC
+            ",
+        )
+        .bind(user_id)
+        .bind(self.clock.now())
+        .fetch_all(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn touch(&self, session: &Session) -> Result<()> {
+        let now = self.clock.now();
+        if now - session.last_seen_at < LAST_SEEN_RESOLUTION {
+            return Ok(());
+        }
+
+        let expires_at = (self.idle > 0).then(|| session.ends_at(now, self.ttl, self.idle));
+
+        sqlx::query(
+            "UPDATE sessions SET last_seen_at = $2, expires_at = COALESCE($3, expires_at) WHERE id = $1",
+        )
+        .bind(session.id)
+        .bind(now)
+        .bind(expires_at)
+        .execute(&self.db)
+        .await?;
+
+        if self.cache.is_some() {
+            let mut session = session.clone();
+            session.last_seen_at = now;
+            session.expires_at = expires_at.unwrap_or(session.expires_at);
+            self.remember(None, &session).await;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_for_user(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM sessions WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+        self.forget(&[id]).await;
+
+        Ok(deleted > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        self.forget(&[id]).await;
+
+        Ok(())
+    }
+
+    async fn delete_all(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let ended = sqlx::query_scalar("DELETE FROM sessions WHERE user_id = $1 RETURNING id")
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        self.forget(&ended).await;
+
+        Ok(ended)
+    }
+
+    async fn delete_others(&self, user_id: Uuid, keep: Uuid) -> Result<Vec<Uuid>> {
+        let ended =
+            sqlx::query_scalar("DELETE FROM sessions WHERE user_id = $1 AND id <> $2 RETURNING id")
+                .bind(user_id)
+                .bind(keep)
+                .fetch_all(&self.db)
+                .await?;
+
+        self.forget(&ended).await;
+
+        Ok(ended)
+    }
+
+    async fn count_active(&self) -> Result<Option<u64>> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sessions WHERE user_id IS NOT NULL AND expires_at > $1",
+        )
+        .bind(self.clock.now())
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(Some(count.cast_unsigned()))
+    }
+
+    async fn forget(&self, ids: &[Uuid]) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        let keys: Vec<String> = ids.iter().map(|id| session_key(*id)).collect();
+        if let Err(err) = cache.delete(&keys).await {
+            tracing::warn!(error = %err, "Failed to drop cached sessions");
+        }
+    }
+}
+
+fn session_key(id: Uuid) -> String {
+    format!("session:{id}")
+}
+
+fn token_key(token_hash: &str) -> String {
+    format!("session_token:{token_hash}")
+}
+
+/// Marks a request whose session cookie is used by another client than the
+/// one it is bound to, under [`BindingAction::Reauthenticate`]. The session
+/// authenticates nobody until it is reauthenticated.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BindingChanged;
+
+/// Whether `session` may be used by a client at `ip` sending `user_agent`
+/// under `binding`.
+fn is_bound_to(
+    session: &Session,
+    binding: &SessionBinding,
+    ip: Option<IpAddr>,
+    user_agent: Option<&str>,
+) -> bool {
+    let bound_ip = session.ip.as_deref().and_then(|ip| ip.parse().ok());
+    let same_ip = match (binding.ip(), bound_ip, ip) {
+        (IpBinding::Off, ..) | (_, None, _) | (_, _, None) => true,
+        (IpBinding::Exact, bound, ip) => bound == ip,
+        (IpBinding::Network, bound, ip) => network_of(bound) == network_of(ip),
+    };
+
+    let user_agent = user_agent.map(|value| value.chars().take(MAX_USER_AGENT_LEN).collect());
+    let same_user_agent = !binding.user_agent() || session.user_agent == user_agent;
+
+    same_ip && same_user_agent
+}
+
+/// Middleware resolving the session cookie on every request.
+///
+/// When the request carries a session cookie that matches an unexpired
+/// session, the [`Session`] is inserted into the request extensions where
+/// extractors such as [`crate::auth::AuthUser`] pick it up. Unknown or
+/// expired cookies are ignored, leaving the request unauthenticated, as are
+/// sealed cookies that fail to open. Sessions are held to the current
+/// `auth.lifetimes`, both the absolute `session` lifetime counted from
+/// login and the `session_idle` timeout counted from `last_seen_at`, even
+/// when they were issued under longer ones. Cookies sealed with a previous key are
+/// resealed with the current one, unless the handler sets the cookie itself.
+///
+/// A session used by another client than the one it is bound to by
+/// `security.session_binding` is ended, or kept from authenticating anyone
+/// until it is reauthenticated, see [`BindingAction`].
+///
+/// ## Errors
+/// * Database errors while looking up the session
+pub async fn middleware(
+    State(ctx): State<Arc<AppContext>>,
+    ClientIp(ip): ClientIp,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    let mut reseal = None;
+
+    let cookies = ctx.session_cookies();
+    if let Some(cookie) = jar.get(cookies.name())
+        && let Some((token, stale)) = cookies.open(cookie.value())
+        && let Some(session) = ctx.sessions().find_by_token(&token).await?
+    {
+        let config = ctx.config();
+
+        // Sessions keep the expiry of the lifetimes they were issued under,
+        // so shortened ones would only apply to new sessions otherwise.
+        let lifetimes = config.auth().lifetimes();
+        let ends_at = expiry(
+            session.created_at,
+            session.last_seen_at,
+            lifetimes.session().as_secs(),
+            lifetimes.session_idle().as_secs(),
+        );
+        if ends_at <= ctx.clock().now() {
+            tracing::debug!(session_id = %session.id, "Session outlived auth.lifetimes");
+            ctx.sessions().delete(session.id).await?;
+            return Ok(next.run(request).await);
+        }
+
+        let binding = config.security().session_binding();
+        let user_agent = request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok());
+
+        if !is_bound_to(&session, binding, ip, user_agent) {
+            tracing::warn!(
+                session_id = %session.id,
+                bound_ip = session.ip.as_deref().unwrap_or("unknown"),
+                ip = ip.map(|ip| ip.to_string()).unwrap_or_default(),
+                "Session used by another client than it is bound to"
+            );
+
+            match (binding.on_change(), session.user_id) {
+                (BindingAction::Reauthenticate, Some(_)) => {
+                    request.extensions_mut().insert(BindingChanged);
+                    request.extensions_mut().insert(session);
+                }
+                (_, user_id) => {
+                    ctx.sessions().delete(session.id).await?;
+                    if let Some(user_id) = user_id {
+                        ctx.publish(Event::SessionsRevoked {
+                            user_id,
+                            session_ids: vec![session.id],
+                        })
+                        .await?;
+                    }
+                }
+            }
+
+            return Ok(next.run(request).await);
+        }
+
+        ctx.sessions().touch(&session).await?;
+        if stale {
+            reseal = Some(cookies.build(token, &session));
+        }
+        request.extensions_mut().insert(session);
+    }
+
+    let response = next.run(request).await;
+
+    let sets_cookie = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.starts_with(&format!("{}=", cookies.name())));
+
+    match reseal {
+        Some(cookie) if !sets_cookie => {
+            Ok((CookieJar::new().add(cookie), response).into_response())
+        }
+        _ => Ok(response),
+    }
+}
@@ -0,0 +1,481 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
+use ::redis::Script;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::{DeviceInfo, LAST_SEEN_RESOLUTION, Session, SessionStore, expiry, new_session};
+use crate::{
+    Error, Result,
+    auth::{generate_token, hash_token},
+    cache::Cache,
+    clock::{Clock, SystemClock},
+    models::UserStatus,
+    repositories::UserStore,
+};
+
+/// Stores the session fields `ARGV[5..]` in the hash `KEYS[1]` for `ARGV[3]`
+/// seconds, points the token `KEYS[2]` at it and, for sessions with a user,
+/// adds the id `ARGV[1]` to their set `KEYS[3]`. The token and set are kept
+/// for `ARGV[4]` seconds, the longest the session may last.
+static CREATE: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        redis.call('HSET', KEYS[1], unpack(ARGV, 5))
+        redis.call('EXPIRE', KEYS[1], ARGV[3])
+        redis.call('SET', KEYS[2], ARGV[2], 'EX', ARGV[4])
+        if KEYS[3] then
+            redis.call('SADD', KEYS[3], ARGV[1])
+            if redis.call('TTL', KEYS[3]) < tonumber(ARGV[4]) then
+                redis.call('EXPIRE', KEYS[3], ARGV[4])
+            end
+        end
+        return 1
+        ",
+    )
+});
+
+/// Sets the session fields `ARGV[5..]` of the hash `KEYS[1]` if it exists
+/// and, with `ARGV[1]` set to `user` or `guest`, belongs to a user or to
+/// nobody. Keeps the session `ARGV[3]` more seconds unless that is 0. With
+/// `KEYS[2]`, adds the id `ARGV[2]` to that set and keeps it at least
+/// `ARGV[4]` seconds. Returns the updated hash, or nil.
+static UPDATE: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        if redis.call('EXISTS', KEYS[1]) == 0 then
+            return false
+        end
+        if ARGV[1] ~= '' then
+            local guest = redis.call('HGET', KEYS[1], 'user_id') == 'null'
+            if guest ~= (ARGV[1] == 'guest') then
+                return false
+            end
+        end
+        redis.call('HSET', KEYS[1], unpack(ARGV, 5))
+        if tonumber(ARGV[3]) > 0 then
+            redis.call('EXPIRE', KEYS[1], ARGV[3])
+        end
+        if KEYS[2] then
+            redis.call('SADD', KEYS[2], ARGV[2])
+            if redis.call('TTL', KEYS[2]) < tonumber(ARGV[4]) then
+                redis.call('EXPIRE', KEYS[2], ARGV[4])
+            end
+        end
+        return redis.call('HGETALL', KEYS[1])
+        ",
+    )
+});
+
+/// Which sessions [`UPDATE`] applies to.
+#[derive(Clone, Copy)]
+enum Owner {
+    Any,
+    User,
+    Guest,
+}
+
+impl Owner {
+    fn arg(self) -> String {
+        String::from(match self {
+            Self::Any => "",
+            Self::User => "user",
+            Self::Guest => "guest",
+        })
+    }
+}
+
+/// [`SessionStore`] keeping sessions in Redis only, selected with
+/// `auth.session_backend: redis`, so that the reads and writes of every
+/// request stay off the database.
+///
+/// Each session is a hash of its fields, with the session token pointing at
+/// it and a set per user listing their sessions. Redis expires sessions on
+/// its own. They are lost if Redis loses its data, so persist it if users
+/// should stay logged in across Redis restarts.
+#[derive(Clone)]
+pub struct RedisSessionStore {
+    cache: Cache,
+    users: Arc<dyn UserStore>,
+    ttl: u64,
+    idle: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl RedisSessionStore {
+    /// Creates a store whose sessions live for `ttl` seconds, checking in
+    /// `users` that accounts may still log in.
+    #[must_use]
+    pub fn new(cache: Cache, users: Arc<dyn UserStore>, ttl: u64) -> Self {
+        Self {
+            cache,
+            users,
+            ttl,
+            idle: 0,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Ends sessions unused for `idle` seconds, to within
+    /// [`LAST_SEEN_RESOLUTION`]. 0 keeps them for their whole `ttl`.
+    #[must_use]
+    pub fn with_idle_timeout(mut self, idle: u64) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Seconds from now until `at`, at least one.
+    fn seconds_until(&self, at: DateTime<Utc>) -> u64 {
+        (at - self.clock.now()).num_seconds().max(1).cast_unsigned()
+    }
+
+    /// Seconds until a session created at `created_at` reaches its `ttl`.
+    fn lifetime_left(&self, created_at: DateTime<Utc>) -> u64 {
+        self.seconds_until(expiry(created_at, created_at, self.ttl, 0))
+    }
+
+    async fn insert(
+        &self,
+        user_id: Option<Uuid>,
+        device: &DeviceInfo,
+    ) -> Result<(Session, String)> {
+        let token = generate_token();
+        let token_hash = hash_token(&token);
+        let now = self.clock.now();
+        let session = new_session(user_id, device, now, expiry(now, now, self.ttl, self.idle));
+
+        let mut keys = vec![session_key(session.id), token_key(&token_hash)];
+        if let Some(user_id) = user_id {
+            keys.push(user_key(user_id));
+        }
+
+        let mut args = vec![
+            session.id.to_string(),
+            serde_json::to_string(&session.id).unwrap_or_default(),
+            self.seconds_until(session.expires_at).to_string(),
+            self.lifetime_left(now).to_string(),
+        ];
+        args.extend(fields(&session));
+        args.extend([
+            String::from("token_hash"),
+            Value::from(token_hash).to_string(),
+        ]);
+
+        self.cache.run::<i64>(&CREATE, &keys, &args).await?;
+
+        Ok((session, token))
+    }
+
+    /// Sets `changes` on session `id` if it is owned by `owner`, keeping it
+    /// until `expires_at` if given. With `join`, also lists the session,
+    /// created at the time given, among those of the user given.
+    async fn update(
+        &self,
+        id: Uuid,
+        owner: Owner,
+        changes: &[(&str, Value)],
+        expires_at: Option<DateTime<Utc>>,
+        join: Option<(Uuid, DateTime<Utc>)>,
+    ) -> Result<Option<Session>> {
+        let mut keys = vec![session_key(id)];
+        if let Some((user_id, _)) = join {
+            keys.push(user_key(user_id));
+        }
+
+        let mut args = vec![
+            owner.arg(),
+            id.to_string(),
+            expires_at
+                .map_or(0, |at| self.seconds_until(at))
+                .to_string(),
+            join.map_or(0, |(_, created_at)| self.lifetime_left(created_at))
+                .to_string(),
+        ];
+        for (field, value) in changes {
+            args.extend([(*field).to_owned(), value.to_string()]);
+        }
+
+        let updated: Option<HashMap<String, String>> =
+            self.cache.run(&UPDATE, &keys, &args).await?;
+
+        Ok(updated
+            .and_then(|hash| decode(&hash))
+            .map(|(session, _)| session)
+            .filter(|session| session.expires_at > self.clock.now()))
+    }
+
+    /// The unexpired session `id` along with the hash of its token.
+    async fn load(&self, id: Uuid) -> Result<Option<(Session, String)>> {
+        let hash = self.cache.hash(&session_key(id)).await?;
+
+        Ok(decode(&hash).filter(|(session, _)| session.expires_at > self.clock.now()))
+    }
+
+    /// Ends the sessions `ids` of `user_id`.
+    async fn remove(&self, user_id: Uuid, ids: &[Uuid]) -> Result<()> {
+        let mut keys = Vec::with_capacity(ids.len() * 2);
+        for id in ids {
+            if let Some((_, token_hash)) = self.load(*id).await? {
+                keys.push(token_key(&token_hash));
+            }
+            keys.push(session_key(*id));
+        }
+
+        self.cache.delete(&keys).await?;
+        let members: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+        self.cache
+            .remove_members(&user_key(user_id), &members)
+            .await
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn create(&self, user_id: Uuid, device: &DeviceInfo) -> Result<(Session, String)> {
+        let active = self
+            .users
+            .find(user_id)
+            .await?
+            .is_some_and(|user| user.status == UserStatus::Active);
+        if !active {
+            return Err(Error::AccountDisabled);
+        }
+
+        self.insert(Some(user_id), device).await
+    }
+
+    async fn create_anonymous(&self, device: &DeviceInfo) -> Result<(Session, String)> {
+        self.insert(None, device).await
+    }
+
+    async fn upgrade(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>> {
+        let Some((session, _)) = self.load(id).await? else {
+            return Ok(None);
+        };
+
+        let now = json!(self.clock.now());
+        self.update(
+            id,
+            Owner::Guest,
+            &[
+                ("user_id", json!(user_id)),
+                ("last_seen_at", now.clone()),
+                ("authenticated_at", now),
+            ],
+            None,
+            Some((user_id, session.created_at)),
+        )
+        .await
+    }
+
+    async fn find_by_token(&self, token: &str) -> Result<Option<Session>> {
+        let Some(id) = self
+            .cache
+            .get_json::<Uuid>(&token_key(&hash_token(token)))
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        self.find(id).await
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Session>> {
+        Ok(self.load(id).await?.map(|(session, _)| session))
+    }
+
+    async fn set_active_organization(
+        &self,
+        id: Uuid,
+        organization_id: Option<Uuid>,
+    ) -> Result<Option<Session>> {
+        self.update(
+            id,
+            Owner::Any,
+            &[("active_organization_id", json!(organization_id))],
+            None,
+            None,
+        )
+        .await
+    }
+
+    async fn reauthenticate(&self, id: Uuid, device: &DeviceInfo) -> Result<Option<Session>> {
+        self.update(
+            id,
+            Owner::User,
+            &[
+                ("authenticated_at", json!(self.clock.now())),
+                ("user_agent", json!(&device.user_agent)),
+                ("ip", json!(device.ip.map(|ip| ip.to_string()))),
+                ("device", json!(&device.device)),
+            ],
+            None,
+            None,
+        )
+        .await
+    }
+
+    async fn limit(&self, id: Uuid, max_expires_at: DateTime<Utc>) -> Result<Option<Session>> {
+        let Some((session, _)) = self.load(id).await? else {
+            return Ok(None);
+        };
+
+        let expires_at = session.expires_at.min(max_expires_at);
+        self.update(
+            id,
+            Owner::Any,
+            &[
+                ("max_expires_at", json!(max_expires_at)),
+                ("expires_at", json!(expires_at)),
+            ],
+            Some(expires_at),
+            None,
+        )
+        .await
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        let mut sessions = Vec::new();
+        let mut ended = Vec::new();
+
+        for member in self.cache.members(&user_key(user_id)).await? {
+            match member.parse().ok() {
+                Some(id) => match self.find(id).await? {
+                    Some(session) => sessions.push(session),
+                    None => ended.push(member),
+                },
+                None => ended.push(member),
+            }
+        }
+
+        if let Err(err) = self.cache.remove_members(&user_key(user_id), &ended).await {
+            tracing::warn!(error = %err, "Failed to drop ended sessions from their user");
+        }
+
+        sessions.sort_by_key(|session| Reverse(session.last_seen_at));
+
+        Ok(sessions)
+    }
+
+    async fn touch(&self, session: &Session) -> Result<()> {
+        let now = self.clock.now();
+        if now - session.last_seen_at < LAST_SEEN_RESOLUTION {
+            return Ok(());
+        }
+
+        let mut changes = vec![("last_seen_at", json!(now))];
+        let expires_at = (self.idle > 0).then(|| session.ends_at(now, self.ttl, self.idle));
+        if let Some(expires_at) = expires_at {
+            changes.push(("expires_at", json!(expires_at)));
+        }
+
+        self.update(session.id, Owner::Any, &changes, expires_at, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_for_user(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let owned = self
+            .find(id)
+            .await?
+            .is_some_and(|session| session.user_id == Some(user_id));
+        if owned {
+            self.remove(user_id, &[id]).await?;
+        }
+
+        Ok(owned)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<()> {
+        let Some((session, token_hash)) = self.load(id).await? else {
+            return self.cache.delete(&[session_key(id)]).await;
+        };
+
+        match session.user_id {
+            Some(user_id) => self.remove(user_id, &[id]).await,
+            None => {
+                self.cache
+                    .delete(&[session_key(id), token_key(&token_hash)])
+                    .await
+            }
+        }
+    }
+
+    async fn delete_all(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let ended: Vec<Uuid> = self
+            .list(user_id)
+            .await?
+            .into_iter()
+            .map(|session| session.id)
+            .collect();
+        self.remove(user_id, &ended).await?;
+
+        Ok(ended)
+    }
+
+    async fn delete_others(&self, user_id: Uuid, keep: Uuid) -> Result<Vec<Uuid>> {
+        let ended: Vec<Uuid> = self
+            .list(user_id)
+            .await?
+            .into_iter()
+            .map(|session| session.id)
+            .filter(|id| *id != keep)
+            .collect();
+        self.remove(user_id, &ended).await?;
+
+        Ok(ended)
+    }
+}
+
+/// The fields of `session` and their JSON values, alternating.
+fn fields(session: &Session) -> Vec<String> {
+    let Ok(Value::Object(fields)) = serde_json::to_value(session) else {
+        return Vec::new();
+    };
+
+    fields
+        .into_iter()
+        .flat_map(|(field, value)| [field, value.to_string()])
+        .collect()
+}
+
+/// The session stored in `hash`, and the hash of its token. `None` if it is
+/// missing or no longer parses.
+fn decode(hash: &HashMap<String, String>) -> Option<(Session, String)> {
+    let fields: serde_json::Map<String, Value> = hash
+        .iter()
+        .map(|(field, value)| Some((field.clone(), serde_json::from_str(value).ok()?)))
+        .collect::<Option<_>>()?;
+    let token_hash = fields.get("token_hash")?.as_str()?.to_owned();
+
+    Some((
+        serde_json::from_value(Value::Object(fields)).ok()?,
+        token_hash,
+    ))
+}
+
+fn session_key(id: Uuid) -> String {
+    format!("sessions:{id}")
+}
+
+fn token_key(token_hash: &str) -> String {
+    format!("sessions:token:{token_hash}")
+}
+
+fn user_key(user_id: Uuid) -> String {
+    format!("sessions:user:{user_id}")
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Completes on the first SIGINT (Ctrl+C) or, on Unix, SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+/// Spawns the task that cancels `token` on [`signal`].
+pub fn spawn_listener(token: CancellationToken) {
+    tokio::spawn(async move {
+        signal().await;
+        tracing::info!("Shutdown signal received, draining");
+        token.cancel();
+    });
+}
+
+/// Waits up to `timeout` for background `workers` to stop after their
+/// token was cancelled, aborting those still running then.
+pub async fn join(workers: Vec<JoinHandle<()>>, timeout: Duration) {
+    let aborts: Vec<_> = workers.iter().map(JoinHandle::abort_handle).collect();
+
+    let joined = tokio::time::timeout(timeout, async {
+        for worker in workers {
+            if let Err(err) = worker.await {
+                tracing::warn!(error = %err, "Background task failed during shutdown");
+            }
+        }
+    })
+    .await;
+
+    if joined.is_err() {
+        tracing::warn!(
+            timeout = timeout.as_secs(),
+            "Background tasks still running after the shutdown timeout, aborting them"
+        );
+        for abort in aborts {
+            abort.abort();
+        }
+    }
+}
@@ -0,0 +1,122 @@
+mod otp;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+pub use self::otp::{OtpCode, OtpPurpose, PhoneOtp, normalize_phone};
+use crate::{
+    Error, Result,
+    config::{SecretString, SmsConfig},
+};
+
+/// A text message.
+#[derive(Debug, Clone)]
+pub struct Sms {
+    /// Recipient in E.164 format, e.g. `+15551234567`.
+    pub to: String,
+    pub body: String,
+}
+
+/// Delivers the one-time codes of phone verification and login.
+///
+/// [`TwilioSender`] and [`LogSmsSender`] are selected by the `sms` config
+/// section; other gateways can be plugged in by implementing this trait and
+/// installing it with [`crate::AppContext::with_sms_sender`].
+///
+/// ```no_run
+/// use async_trait::async_trait;
+/// use betterauth::{Result, sms::{Sms, SmsSender}};
+///
+/// struct MyGateway;
+///
+/// #[async_trait]
+/// impl SmsSender for MyGateway {
+///     async fn send(&self, sms: Sms) -> Result<()> {
+///         // call the gateway's API here
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    /// Sends `sms`.
+    ///
+    /// ## Errors
+    /// * The message could not be handed over for delivery
+    async fn send(&self, sms: Sms) -> Result<()>;
+}
+
+/// Sender that writes every message to the log instead of delivering it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogSmsSender;
+
+#[async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, sms: Sms) -> Result<()> {
+        tracing::info!(to = %sms.to, body = %sms.body, "Text message not delivered, logged instead");
+
+        Ok(())
+    }
+}
+
+/// Sender using Twilio's Messages API.
+#[derive(Clone)]
+pub struct TwilioSender {
+    http: reqwest::Client,
+    account_sid: String,
+    auth_token: String,
+    from: String,
+}
+
+impl TwilioSender {
+    /// # Panics
+    /// If `account_sid`, `auth_token` or `from` is missing, or the TLS
+    /// backend of the HTTP client cannot be initialised.
+    #[must_use]
+    pub fn from_config(config: &SmsConfig) -> Self {
+        let required = |value: Option<&str>, name: &str| {
+            value
+                .unwrap_or_else(|| panic!("sms.{name} is required by the twilio provider"))
+                .to_owned()
+        };
+
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("betterauth/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("failed to initialise HTTP client");
+
+        Self {
+            http,
+            account_sid: required(config.account_sid(), "account_sid"),
+            auth_token: required(config.auth_token().map(SecretString::expose), "auth_token"),
+            from: required(config.from(), "from"),
+        }
+    }
+}
+
+#[async_trait]
+impl SmsSender for TwilioSender {
+    async fn send(&self, sms: Sms) -> Result<()> {
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+            self.account_sid
+        );
+
+        self.http
+            .post(url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[
+                ("To", sms.to.as_str()),
+                ("From", self.from.as_str()),
+                ("Body", sms.body.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| Error::Sms(err.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    clock::{Clock, SystemClock},
+    config::SmsConfig,
+};
+
+/// Codes sent to a number are kept this long for rate limiting, then
+/// dropped.
+const RETENTION: Duration = Duration::days(1);
+
+/// What a one-time code was sent for, stored in `phone_otp_codes.purpose`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpPurpose {
+    /// Proving ownership of a number being added to an account.
+    Verify,
+    /// Logging in with a number already on an account.
+    Login,
+}
+
+impl OtpPurpose {
+    /// Value stored in `phone_otp_codes.purpose`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Verify => "verify",
+            Self::Login => "login",
+        }
+    }
+}
+
+/// A redeemed one-time code.
+#[derive(Debug, Clone)]
+pub struct OtpCode {
+    pub user_id: Uuid,
+    pub phone: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingCode {
+    id: Uuid,
+    user_id: Uuid,
+    phone: String,
+    code_hash: String,
+}
+
+/// The E.164 form of `input`, e.g. `+15551234567` for `+1 (555) 123-4567`,
+/// or `None` if it is not an international number.
+#[must_use]
+pub fn normalize_phone(input: &str) -> Option<String> {
+    let phone: String = input
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    let digits = phone.strip_prefix('+')?;
+
+    let valid = (7..=15).contains(&digits.len())
+        && digits.bytes().all(|byte| byte.is_ascii_digit())
+        && !digits.starts_with('0');
+
+    valid.then_some(phone)
+}
+
+/// Issues and redeems the six-digit codes sent by text message.
+///
+/// Codes are stored as an HMAC keyed with `auth.secret`, since six digits
+/// could be brute-forced from a plain hash. Only the latest code sent to a
+/// number for a purpose works, and each allows `sms.max_attempts` guesses.
+#[derive(Clone)]
+pub struct PhoneOtp {
+    db: PgPool,
+    secret: String,
+    config: SmsConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl PhoneOtp {
+    #[must_use]
+    pub fn new(db: PgPool, secret: &str, config: SmsConfig) -> Self {
+        Self {
+            db,
+            secret: secret.to_owned(),
+            config,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &SmsConfig {
+        &self.config
+    }
+
+    fn hash(&self, phone: &str, code: &str) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"sms:");
+        mac.update(phone.as_bytes());
+        mac.update(b":");
+        mac.update(code.as_bytes());
+        mac
+    }
+
+    /// Creates a code for `user_id` to redeem with `phone`, replacing any
+    /// earlier one for the same purpose, and returns it for sending.
+    ///
+    /// ## Errors
+    /// * [`Error::RateLimited`] if the number was sent a code too recently
+    ///   or too often in the last hour
+    /// * Database errors
+    pub async fn issue(&self, user_id: Uuid, phone: &str, purpose: OtpPurpose) -> Result<String> {
+        let now = self.clock.now();
+
+        let (sent, first, last) =
+            sqlx::query_as::<_, (i64, Option<DateTime<Utc>>, Option<DateTime<Utc>>)>(
+                r"
+                SELECT count(*), min(created_at), max(created_at)
+                FROM phone_otp_codes
+                WHERE phone = $1 AND created_at > $2
+                ",
+            )
+            .bind(phone)
+            .bind(now - Duration::hours(1))
+            .fetch_one(&self.db)
+            .await?;
+
+        let resend_interval =
+            Duration::seconds(i64::try_from(self.config.resend_interval()).unwrap_or(i64::MAX));
+        let next_send = match (first, last) {
+            (Some(first), _) if sent >= self.config.max_sends_per_hour() => {
+                Some(first + Duration::hours(1))
+            }
+            (_, Some(last)) if last + resend_interval > now => Some(last + resend_interval),
+            _ => None,
+        };
+
+        if let Some(next_send) = next_send {
+            let retry_after = (next_send - now).num_seconds().max(1);
+            return Err(Error::RateLimited {
+                retry_after: u64::try_from(retry_after).unwrap_or(1),
+            });
+        }
+
+        let code = format!("{:06}", rand::rng().random_range(0..1_000_000));
+        let ttl = Duration::seconds(i64::try_from(self.config.code_ttl()).unwrap_or(i64::MAX));
+        let code_hash = URL_SAFE_NO_PAD.encode(self.hash(phone, &code).finalize().into_bytes());
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r"
+            DELETE FROM phone_otp_codes
+            WHERE phone = $1 AND created_at < $2
+            ",
+        )
+        .bind(phone)
+        .bind(now - RETENTION)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r"
+            UPDATE phone_otp_codes
+            SET used_at = $3
+            WHERE phone = $1 AND purpose = $2 AND used_at IS NULL
+            ",
+        )
+        .bind(phone)
+        .bind(purpose.name())
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO phone_otp_codes (user_id, phone, purpose, code_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+        )
+        .bind(user_id)
+        .bind(phone)
+        .bind(purpose.name())
+        .bind(code_hash)
+        .bind(now)
+        .bind(now + ttl)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(code)
+    }
+
+    /// Redeems `code` sent to `phone` for `purpose`. Returns `None` if there
+    /// is no usable code or `code` is wrong, in which case the guess counts
+    /// against the code's attempts.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn verify(
+        &self,
+        phone: &str,
+        purpose: OtpPurpose,
+        code: &str,
+    ) -> Result<Option<OtpCode>> {
+        let now = self.clock.now();
+        let mut tx = self.db.begin().await?;
+
+        let pending = sqlx::query_as::<_, PendingCode>(
+            r"
+            SELECT id, user_id, phone, code_hash
+            FROM phone_otp_codes
+            WHERE phone = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > $3
+            ORDER BY created_at DESC
+            LIMIT 1
+            FOR UPDATE
+            ",
+        )
+        .bind(phone)
+        .bind(purpose.name())
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(pending) = pending else {
+            return Ok(None);
+        };
+
+        let matches = URL_SAFE_NO_PAD
+            .decode(&pending.code_hash)
+            .is_ok_and(|tag| self.hash(phone, code.trim()).verify_slice(&tag).is_ok());
+
+        if matches {
+            sqlx::query("UPDATE phone_otp_codes SET used_at = $2 WHERE id = $1")
+                .bind(pending.id)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            // The last allowed guess burns the code.
+            sqlx::query(
+                r"
+                UPDATE phone_otp_codes
+                SET attempts = attempts + 1,
+                    used_at = CASE WHEN attempts + 1 >= $2 THEN $3 END
+                WHERE id = $1
+                ",
+            )
+            .bind(pending.id)
+            .bind(self.config.max_attempts())
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(matches.then_some(OtpCode {
+            user_id: pending.user_id,
+            phone: pending.phone,
+        }))
+    }
+}
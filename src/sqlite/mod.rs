@@ -0,0 +1,5 @@
+mod sessions;
+mod tokens;
+mod users;
+
+pub use self::{sessions::SqliteSessionStore, tokens::SqliteTokenStore, users::SqliteUserStore};
@@ -0,0 +1,309 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    auth::{generate_token, hash_token},
+    clock::{Clock, SystemClock},
+    sessions::{DeviceInfo, LAST_SEEN_RESOLUTION, Session, SessionStore, expiry},
+};
+
+/// SQLite-backed persistence for [`Session`]s, used with the `sqlite`
+/// database driver. Works like [`crate::sessions::PgSessionStore`] without a
+/// cache.
+#[derive(Clone)]
+pub struct SqliteSessionStore {
+    db: SqlitePool,
+    ttl: u64,
+    idle: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl SqliteSessionStore {
+    /// Creates a store whose sessions live for `ttl` seconds.
+    #[must_use]
+    pub fn new(db: SqlitePool, ttl: u64) -> Self {
+        Self {
+            db,
+            ttl,
+            idle: 0,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Ends sessions unused for `idle` seconds, to within
+    /// [`LAST_SEEN_RESOLUTION`]. 0 keeps them for their whole `ttl`.
+    #[must_use]
+    pub fn with_idle_timeout(mut self, idle: u64) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn expires_at(&self) -> chrono::DateTime<Utc> {
+        let now = self.clock.now();
+        expiry(now, now, self.ttl, self.idle)
+    }
+}
+
+#[async_trait]
+impl SessionStore for SqliteSessionStore {
+    async fn create(&self, user_id: Uuid, device: &DeviceInfo) -> Result<(Session, String)> {
+        let token = generate_token();
+
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            INSERT INTO sessions
+                (id, user_id, token_hash, user_agent, ip, device, created_at, last_seen_at,
+                 expires_at, authenticated_at)
+            SELECT ?1, id, ?3, ?4, ?5, ?8, ?6, ?6, ?7, ?6 FROM users WHERE id = ?2 AND status = 'active'
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(device.user_agent.as_deref())
+        .bind(device.ip.map(|ip| ip.to_string()))
+        .bind(self.clock.now())
+        .bind(self.expires_at())
+        .bind(Json(&device.device))
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(Error::AccountDisabled)?;
+
+        Ok((session, token))
+    }
+
+    async fn create_anonymous(&self, device: &DeviceInfo) -> Result<(Session, String)> {
+        let token = generate_token();
+
+        let session = sqlx::query_as::<_, Session>(
+            r"
+            INSERT INTO sessions
+                (id, token_hash, user_agent, ip, created_at, last_seen_at, expires_at, device)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6, ?7)
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(hash_token(&token))
+        .bind(device.user_agent.as_deref())
+        .bind(device.ip.map(|ip| ip.to_string()))
+        .bind(self.clock.now())
+        .bind(self.expires_at())
+        .bind(Json(&device.device))
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok((session, token))
+    }
+
+    async fn upgrade(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET user_id = ?2, last_seen_at = ?3, authenticated_at = ?3
+            WHERE id = ?1 AND user_id IS NULL AND julianday(expires_at) > julianday(?3)
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn find_by_token(&self, token: &str) -> Result<Option<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            SELECT id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                   last_seen_at, expires_at, max_expires_at, authenticated_at
+            FROM sessions
+            WHERE token_hash = ?1 AND julianday(expires_at) > julianday(?2)
+            ",
+        )
+        .bind(hash_token(token))
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            SELECT id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                   last_seen_at, expires_at, max_expires_at, authenticated_at
+            FROM sessions
+            WHERE id = ?1 AND julianday(expires_at) > julianday(?2)
+            ",
+        )
+        .bind(id)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn set_active_organization(
+        &self,
+        id: Uuid,
+        organization_id: Option<Uuid>,
+    ) -> Result<Option<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET active_organization_id = ?2
+            WHERE id = ?1 AND julianday(expires_at) > julianday(?3)
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(organization_id)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn reauthenticate(&self, id: Uuid, device: &DeviceInfo) -> Result<Option<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET authenticated_at = ?2, user_agent = ?3, ip = ?4, device = ?5
+            WHERE id = ?1 AND user_id IS NOT NULL AND julianday(expires_at) > julianday(?2)
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(self.clock.now())
+        .bind(&device.user_agent)
+        .bind(device.ip.map(|ip| ip.to_string()))
+        .bind(Json(&device.device))
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn limit(&self, id: Uuid, max_expires_at: DateTime<Utc>) -> Result<Option<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            UPDATE sessions
+            SET max_expires_at = ?2,
+                expires_at = CASE WHEN julianday(expires_at) > julianday(?2) THEN ?2 ELSE expires_at END
+            WHERE id = ?1 AND julianday(expires_at) > julianday(?3)
+            RETURNING id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                      last_seen_at, expires_at, max_expires_at, authenticated_at
+            ",
+        )
+        .bind(id)
+        .bind(max_expires_at)
+        .bind(self.clock.now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        sqlx::query_as::<_, Session>(
+            r"
+            SELECT id, user_id, active_organization_id, user_agent, ip, device, created_at,
+                   last_seen_at, expires_at, max_expires_at, authenticated_at
+            FROM sessions
+            WHERE user_id = ?1 AND julianday(expires_at) > julianday(?2)
+            ORDER BY julianday(last_seen_at) DESC
+            ",
+        )
+        .bind(user_id)
+        .bind(self.clock.now())
+        .fetch_all(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn touch(&self, session: &Session) -> Result<()> {
+        let now = self.clock.now();
+        if now - session.last_seen_at < LAST_SEEN_RESOLUTION {
+            return Ok(());
+        }
+
+        let expires_at = (self.idle > 0).then(|| session.ends_at(now, self.ttl, self.idle));
+
+        sqlx::query(
+            "UPDATE sessions SET last_seen_at = ?2, expires_at = COALESCE(?3, expires_at) WHERE id = ?1",
+        )
+        .bind(session.id)
+        .bind(now)
+        .bind(expires_at)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_for_user(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM sessions WHERE id = ?1 AND user_id = ?2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+        Ok(deleted > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = ?1")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_all(&self, user_id: Uuid) -> Result<Vec<Uuid>> {
+        sqlx::query_scalar("DELETE FROM sessions WHERE user_id = ?1 RETURNING id")
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn delete_others(&self, user_id: Uuid, keep: Uuid) -> Result<Vec<Uuid>> {
+        sqlx::query_scalar("DELETE FROM sessions WHERE user_id = ?1 AND id <> ?2 RETURNING id")
+            .bind(user_id)
+            .bind(keep)
+            .fetch_all(&self.db)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn count_active(&self) -> Result<Option<u64>> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sessions WHERE user_id IS NOT NULL AND expires_at > ?1",
+        )
+        .bind(self.clock.now())
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(Some(count.cast_unsigned()))
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    Result,
+    clock::{Clock, SystemClock},
+    tokens::{Claims, TokenStore},
+};
+
+/// SQLite-backed denylist of revoked tokens, used with the `sqlite`
+/// database driver. Works like [`crate::tokens::PgTokenStore`] without a
+/// cache.
+#[derive(Clone)]
+pub struct SqliteTokenStore {
+    db: SqlitePool,
+    clock: Arc<dyn Clock>,
+}
+
+impl SqliteTokenStore {
+    #[must_use]
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+#[async_trait]
+impl TokenStore for SqliteTokenStore {
+    async fn revoke(&self, claims: &Claims) -> Result<()> {
+        let now = self.clock.now();
+
+        sqlx::query("DELETE FROM revoked_tokens WHERE julianday(expires_at) <= julianday(?1)")
+            .bind(now)
+            .execute(&self.db)
+            .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO revoked_tokens (jti, user_id, revoked_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (jti) DO NOTHING
+            ",
+        )
+        .bind(claims.jti)
+        .bind(claims.sub)
+        .bind(now)
+        .bind(DateTime::from_timestamp(claims.exp, 0).unwrap_or(DateTime::<Utc>::MAX_UTC))
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_all(&self, user_id: Uuid) -> Result<()> {
+        // Token `iat`s only have second precision, see `PgTokenStore`.
+        let cutoff = DateTime::from_timestamp(self.clock.now().timestamp(), 0).unwrap_or_default();
+
+        sqlx::query("UPDATE users SET tokens_revoked_at = ?2 WHERE id = ?1")
+            .bind(user_id)
+            .bind(cutoff)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool> {
+        sqlx::query_scalar(
+            r"
+            SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = ?1)
+                OR NOT EXISTS (
+                    SELECT 1 FROM users
+                    WHERE id = ?2
+                      AND status = 'active'
+                      AND (tokens_revoked_at IS NULL
+                           OR unixepoch(tokens_revoked_at) <= ?3)
+                )
+            ",
+        )
+        .bind(claims.jti)
+        .bind(claims.sub)
+        .bind(claims.iat)
+        .fetch_one(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::{SqlitePool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    Error, Result,
+    models::{Metadata, NewUser, User, merge_metadata},
+    repositories::UserStore,
+};
+
+/// SQLite-backed persistence for [`User`]s, used with the `sqlite`
+/// database driver.
+#[derive(Clone)]
+pub struct SqliteUserStore {
+    db: SqlitePool,
+}
+
+impl SqliteUserStore {
+    #[must_use]
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl UserStore for SqliteUserStore {
+    async fn find(&self, id: Uuid) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                   updated_at, deleted_at, user_metadata, app_metadata
+            FROM users
+            WHERE id = ?1 AND deleted_at IS NULL
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                   updated_at, deleted_at, user_metadata, app_metadata
+            FROM users
+            WHERE email_normalized = ?1 AND deleted_at IS NULL
+            ",
+        )
+        .bind(email)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            SELECT id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                   updated_at, deleted_at, user_metadata, app_metadata
+            FROM users
+            WHERE lower(username) = lower(?1) AND deleted_at IS NULL
+            ",
+        )
+        .bind(username)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn create(&self, user: &NewUser<'_>) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            INSERT INTO users
+                (id, email, email_normalized, username, name, password_hash, role, verified_at, created_at,
+                 updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+            ON CONFLICT DO NOTHING
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(user.email)
+        .bind(&user.email_normalized)
+        .bind(user.username)
+        .bind(user.name)
+        .bind(user.password_hash)
+        .bind(user.role.name())
+        .bind(user.verified_at)
+        .bind(Utc::now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn update_name(&self, id: Uuid, name: Option<&str>) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            UPDATE users
+            SET name = ?2, updated_at = ?3
+            WHERE id = ?1
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            ",
+        )
+        .bind(id)
+        .bind(name)
+        .bind(Utc::now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn set_username(&self, id: Uuid, username: Option<&str>) -> Result<Option<User>> {
+        sqlx::query_as::<_, User>(
+            r"
+            UPDATE users
+            SET username = ?2, updated_at = ?3
+            WHERE id = ?1
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            ",
+        )
+        .bind(id)
+        .bind(username)
+        .bind(Utc::now())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(db) if db.is_unique_violation() => Error::UsernameTaken,
+            other => other.into(),
+        })
+    }
+
+    async fn patch_metadata(
+        &self,
+        id: Uuid,
+        metadata: Metadata,
+        patch: &Value,
+        max_size: usize,
+    ) -> Result<Option<User>> {
+        let column = metadata.column();
+        let mut tx = self.db.begin().await?;
+
+        let current: Option<Json<Value>> =
+            sqlx::query_scalar(&format!("SELECT {column} FROM users WHERE id = ?1"))
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let Some(Json(mut value)) = current else {
+            return Ok(None);
+        };
+
+        merge_metadata(&mut value, patch, max_size)?;
+
+        let user = sqlx::query_as::<_, User>(&format!(
+            r"
+            UPDATE users
+            SET {column} = ?2, updated_at = ?3
+            WHERE id = ?1
+            RETURNING id, email, username, name, password_hash, role, phone, verified_at, status, created_at,
+                      updated_at, deleted_at, user_metadata, app_metadata
+            "
+        ))
+        .bind(id)
+        .bind(Json(&value))
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(user))
+    }
+
+    async fn set_password_hash(&self, id: Uuid, password_hash: Option<&str>) -> Result<bool> {
+        let updated =
+            sqlx::query("UPDATE users SET password_hash = ?2, updated_at = ?3 WHERE id = ?1")
+                .bind(id)
+                .bind(password_hash)
+                .bind(Utc::now())
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+
+        Ok(updated > 0)
+    }
+
+    async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query(
+            r"
+            UPDATE users
+            SET status = 'deleted', deleted_at = ?2, updated_at = ?2
+            WHERE id = ?1 AND deleted_at IS NULL
+            ",
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(deleted > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool> {
+        let deleted = sqlx::query("DELETE FROM users WHERE id = ?1")
+            .bind(id)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,232 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    App, AppContext,
+    app::AppBuilder,
+    clock::MockClock,
+    config::{Overrides, Routes},
+    models::User,
+};
+
+/// The server running on an ephemeral port against a database of its own,
+/// for end-to-end tests. Needs the `testing` feature.
+///
+/// Each instance creates a fresh `betterauth_test_*` database next to the
+/// configured one, on the same Postgres server, and migrates it. Call
+/// [`TestApp::cleanup`] at the end of a test to drop it again.
+///
+/// Sessions, tokens and one-time codes expire by [`TestApp::clock`], which
+/// starts at the current time and only moves when advanced.
+///
+/// ```no_run
+/// use betterauth::testing::TestApp;
+///
+/// # async fn example() {
+/// let app = TestApp::spawn().await;
+/// app.register_user("ada@example.com", "correct horse battery staple").await;
+/// let token = app.login("ada@example.com", "correct horse battery staple").await;
+///
+/// let me = app
+///     .client
+///     .get(app.url("/api/v1/me"))
+///     .bearer_auth(token)
+///     .send()
+///     .await
+///     .unwrap();
+/// assert!(me.status().is_success());
+///
+/// app.cleanup().await;
+/// # }
+/// ```
+pub struct TestApp {
+    pub address: SocketAddr,
+    pub client: reqwest::Client,
+    pub clock: MockClock,
+    ctx: Arc<AppContext>,
+    database: String,
+    admin: PgPool,
+    token: CancellationToken,
+}
+
+impl TestApp {
+    /// Spawns the server with the configuration of the current environment,
+    /// see [`TestApp::spawn_with`].
+    ///
+    /// # Panics
+    ///
+    /// See [`TestApp::spawn_with`].
+    pub async fn spawn() -> Self {
+        Self::spawn_with(Overrides::default()).await
+    }
+
+    /// Spawns the server with the configuration loaded with `overrides`,
+    /// serving every route on `127.0.0.1` and a port picked by the OS.
+    ///
+    /// # Panics
+    ///
+    /// If the configuration cannot be loaded, the test database cannot be
+    /// created or migrated, or no port can be bound.
+    pub async fn spawn_with(overrides: Overrides) -> Self {
+        Self::spawn_app(App::builder(), overrides).await
+    }
+
+    /// [`TestApp::spawn_with`] for a server assembled by `app`, with its
+    /// routes, middleware and subscribers. Its background tasks are not
+    /// started.
+    ///
+    /// # Panics
+    ///
+    /// See [`TestApp::spawn_with`].
+    pub async fn spawn_app(mut app: AppBuilder, overrides: Overrides) -> Self {
+        let config = App::config(&overrides)
+            .await
+            .expect("Failed to load the configuration");
+        let admin = config.database().connect_using_options().await;
+
+        let database = format!("betterauth_test_{}", Uuid::new_v4().simple());
+        sqlx::query(&format!(r#"CREATE DATABASE "{database}""#))
+            .execute(&admin)
+            .await
+            .expect("Failed to create the test database");
+
+        let config = App::config(&overrides.with_database_name(&database))
+            .await
+            .expect("Failed to load the configuration");
+        config
+            .database()
+            .run_migrations()
+            .await
+            .expect("Failed to migrate the test database");
+
+        let clock = MockClock::default();
+        let ctx = Arc::new(
+            app.configure(
+                AppContext::from_config_with_clock(&config, Arc::new(clock.clone()))
+                    .await
+                    .expect("Failed to build the context"),
+            ),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind a port");
+        let address = listener.local_addr().expect("Failed to read the address");
+
+        let token = CancellationToken::new();
+        tokio::spawn(crate::app::serve(
+            listener,
+            App::listener_router(&app, &ctx, &config, Routes::All),
+            None,
+            token.clone(),
+        ));
+
+        Self {
+            address,
+            client: reqwest::Client::new(),
+            clock,
+            ctx,
+            database,
+            admin,
+            token,
+        }
+    }
+
+    /// Absolute URL of `path` on the server, e.g. `/api/v1/auth/login`.
+    #[must_use]
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    /// The context the server runs with, e.g. to query its database.
+    #[must_use]
+    pub fn ctx(&self) -> &AppContext {
+        &self.ctx
+    }
+
+    /// Registers a user through `POST /api/v1/auth/register` and marks its
+    /// email address verified, so that it can log in right away.
+    ///
+    /// # Panics
+    ///
+    /// If registration does not succeed.
+    pub async fn register_user(&self, email: &str, password: &str) -> User {
+        let response = self
+            .client
+            .post(self.url("/api/v1/auth/register"))
+            .json(&json!({ "email": email, "password": password }))
+            .send()
+            .await
+            .expect("Failed to send the request");
+        let status = response.status();
+        assert!(
+            status.is_success(),
+            "Registration failed with {status}: {}",
+            response.text().await.unwrap_or_default()
+        );
+
+        let email = self.ctx.normalize_email(email);
+        sqlx::query("UPDATE users SET verified_at = now() WHERE email_normalized = $1")
+            .bind(&email)
+            .execute(self.ctx.db())
+            .await
+            .expect("Failed to verify the user");
+
+        self.ctx
+            .users()
+            .find_by_email(&email)
+            .await
+            .expect("Failed to load the user")
+            .expect("Registered user is missing")
+    }
+
+    /// Logs in through `POST /api/v1/auth/login` and returns the access token.
+    ///
+    /// # Panics
+    ///
+    /// If the login does not succeed.
+    pub async fn login(&self, email: &str, password: &str) -> String {
+        let response = self
+            .client
+            .post(self.url("/api/v1/auth/login"))
+            .json(&json!({ "email": email, "password": password }))
+            .send()
+            .await
+            .expect("Failed to send the request");
+        let status = response.status();
+        assert!(
+            status.is_success(),
+            "Login failed with {status}: {}",
+            response.text().await.unwrap_or_default()
+        );
+
+        let body: Value = response.json().await.expect("Login returned no JSON");
+        body["access_token"]
+            .as_str()
+            .expect("Login returned no access token")
+            .to_owned()
+    }
+
+    /// Stops the server and drops its database.
+    ///
+    /// # Panics
+    ///
+    /// If the database cannot be dropped.
+    pub async fn cleanup(self) {
+        self.token.cancel();
+        self.ctx.db().close().await;
+
+        sqlx::query(&format!(
+            r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#,
+            self.database
+        ))
+        .execute(&self.admin)
+        .await
+        .expect("Failed to drop the test database");
+        self.admin.close().await;
+    }
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use crate::{AppContext, Result, sessions::Session};
+
+/// Claims [`TokenService`](super::TokenService) sets itself, which
+/// enrichers cannot replace.
+pub const RESERVED_CLAIMS: &[&str] = &["sub", "sid", "jti", "iat", "exp", "typ", "org", "aud"];
+
+/// Adds claims of its own to access tokens as they are minted, such as
+/// roles, organization ids or anything else resource servers should read
+/// from the token instead of asking for.
+///
+/// Enrichers are registered with [`AppContext::with_claims_enricher`] and
+/// run in that order for every access token, including those issued on
+/// refresh, so the claims stay current. Refresh tokens carry none of them.
+/// Claims named like one of [`RESERVED_CLAIMS`] are dropped.
+///
+/// ```no_run
+/// use async_trait::async_trait;
+/// use betterauth::{AppContext, Result, sessions::Session, tokens::ClaimsEnricher};
+/// use serde_json::{Map, Value, json};
+///
+/// struct RoleClaim;
+///
+/// #[async_trait]
+/// impl ClaimsEnricher for RoleClaim {
+///     async fn enrich(
+///         &self,
+///         ctx: &AppContext,
+///         session: &Session,
+///         claims: &mut Map<String, Value>,
+///     ) -> Result<()> {
+///         if let Some(user_id) = session.user_id
+///             && let Some(user) = ctx.users().find(user_id).await?
+///         {
+///             claims.insert(String::from("role"), json!(user.role));
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ClaimsEnricher: Send + Sync {
+    /// Adds to `claims` what the access token minted for `session` should
+    /// carry.
+    ///
+    /// ## Errors
+    /// * Whatever failed; no tokens are issued then
+    async fn enrich(
+        &self,
+        ctx: &AppContext,
+        session: &Session,
+        claims: &mut Map<String, Value>,
+    ) -> Result<()>;
+}
@@ -0,0 +1,343 @@
+use std::time::Duration as StdDuration;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chacha20poly1305::{
+    AeadCore, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, OsRng},
+};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, EncodePrivateKey};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey,
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, ThumbprintHash,
+    },
+};
+use rand::Rng;
+use rsa::pkcs8::LineEnding;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::TokenService;
+use crate::{Error, Result};
+
+/// How often [`spawn_key_refresher`] picks up keys rotated by other
+/// instances and rotates keys that are due.
+const REFRESH_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+/// How long a new key is published before it signs, so that every
+/// instance has loaded it by then.
+pub(super) const KEY_ACTIVATION: Duration = Duration::minutes(2);
+
+/// Length of an XChaCha20 nonce.
+const NONCE_LEN: usize = 24;
+
+/// Size of generated RSA keys, in bits.
+const RSA_BITS: usize = 2048;
+
+/// A key tokens are verified with, and signed with while it is the newest.
+pub(super) struct TokenKey {
+    /// `None` for the HS256 secret, which tokens do not name.
+    pub(super) kid: Option<String>,
+    pub(super) encoding: EncodingKey,
+    pub(super) decoding: DecodingKey,
+    /// Public half of asymmetric keys, with its `kid` set.
+    pub(super) jwk: Option<Jwk>,
+}
+
+impl TokenKey {
+    /// The HS256 key of `auth.secret`.
+    pub(super) fn secret(secret: &str) -> Self {
+        Self {
+            kid: None,
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        }
+    }
+
+    /// The PEM-encoded private key `pem` for the asymmetric `algorithm`,
+    /// named after the thumbprint of its public half. `None` if it is not
+    /// such a key.
+    pub(super) fn from_pem(algorithm: Algorithm, pem: &str) -> Option<Self> {
+        let (encoding, mut jwk) = match algorithm {
+            Algorithm::RS256 => {
+                let encoding = EncodingKey::from_rsa_pem(pem.as_bytes()).ok()?;
+                let jwk = Jwk::from_encoding_key(&encoding, algorithm).ok()?;
+                (encoding, jwk)
+            }
+            Algorithm::ES256 => {
+                let encoding = EncodingKey::from_ec_pem(pem.as_bytes()).ok()?;
+                let jwk = Jwk::from_encoding_key(&encoding, algorithm).ok()?;
+                (encoding, jwk)
+            }
+            _ => {
+                let encoding = EncodingKey::from_ed_pem(pem.as_bytes()).ok()?;
+                let public = ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+                    .ok()?
+                    .verifying_key();
+                (encoding, ed25519_jwk(public.as_bytes()))
+            }
+        };
+
+        let kid = jwk.thumbprint(ThumbprintHash::SHA256);
+        jwk.common.key_id = Some(kid.clone());
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+
+        Some(Self {
+            kid: Some(kid),
+            decoding: DecodingKey::from_jwk(&jwk).ok()?,
+            encoding,
+            jwk: Some(jwk),
+        })
+    }
+}
+
+/// A rotated key as loaded from `signing_keys`.
+pub(super) struct StoredKey {
+    pub(super) key: TokenKey,
+    pub(super) created_at: DateTime<Utc>,
+    /// When a newer key replaced it; `None` while it signs.
+    pub(super) retired_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SigningKeyRow {
+    kid: String,
+    private_key: String,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+}
+
+/// Generates and keeps the rotated signing keys in the `signing_keys`
+/// table, their private half sealed with XChaCha20-Poly1305 under a key
+/// derived from `auth.secret`. Keys sealed under another secret cannot be
+/// opened and are skipped.
+#[derive(Clone)]
+pub(super) struct KeyStore {
+    db: PgPool,
+    algorithm: Algorithm,
+    seal: [u8; 32],
+}
+
+impl KeyStore {
+    pub(super) fn new(db: PgPool, algorithm: Algorithm, secret: &str) -> Self {
+        Self {
+            db,
+            algorithm,
+            seal: Sha256::new()
+                .chain_update(b"signing_keys:")
+                .chain_update(secret.as_bytes())
+                .finalize()
+                .into(),
+        }
+    }
+
+    fn name(&self) -> String {
+        format!("{:?}", self.algorithm)
+    }
+
+    /// The keys of the configured algorithm, newest first.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub(super) async fn load(&self) -> Result<Vec<StoredKey>> {
+        let rows = sqlx::query_as::<_, SigningKeyRow>(
+            r"
+            SELECT kid, private_key, created_at, retired_at
+            FROM signing_keys
+            WHERE algorithm = $1
+            ORDER BY created_at DESC
+            ",
+        )
+        .bind(self.name())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let key = self
+                    .open(&row.private_key)
+                    .and_then(|pem| TokenKey::from_pem(self.algorithm, &pem));
+                if key.is_none() {
+                    tracing::warn!(kid = %row.kid, "Skipping signing key that cannot be opened");
+                }
+
+                key.map(|key| StoredKey {
+                    key,
+                    created_at: row.created_at,
+                    retired_at: row.retired_at,
+                })
+            })
+            .collect())
+    }
+
+    /// Generates a new key that signs from now on, retiring the current
+    /// one, and drops keys retired longer than `retention` ago. With
+    /// `max_age`, does nothing unless the newest key is older than that.
+    /// Returns the `kid` of the new key, if one was made.
+    ///
+    /// ## Errors
+    /// * The key cannot be generated
+    /// * Database errors
+    pub(super) async fn rotate(
+        &self,
+        retention: Duration,
+        max_age: Option<Duration>,
+    ) -> Result<Option<String>> {
+        if let Some(max_age) = max_age
+            && !self.due(&self.db, max_age).await?
+        {
+            return Ok(None);
+        }
+
+        let algorithm = self.algorithm;
+        let pem = tokio::task::spawn_blocking(move || generate(algorithm))
+            .await
+            .map_err(|_| Error::Jwt(ErrorKind::InvalidKeyFormat.into()))??;
+        let key = TokenKey::from_pem(self.algorithm, &pem)
+            .ok_or_else(|| Error::Jwt(ErrorKind::InvalidKeyFormat.into()))?;
+        let kid = key.kid.unwrap_or_default();
+
+        let mut tx = self.db.begin().await?;
+
+        // Instances rotating on schedule at once make a single key.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('signing_keys'))")
+            .execute(&mut *tx)
+            .await?;
+
+        if let Some(max_age) = max_age
+            && !self.due(&mut *tx, max_age).await?
+        {
+            return Ok(None);
+        }
+
+        sqlx::query("DELETE FROM signing_keys WHERE algorithm = $1 AND retired_at <= $2")
+            .bind(self.name())
+            .bind(Utc::now() - retention)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "UPDATE signing_keys SET retired_at = now() WHERE algorithm = $1 AND retired_at IS NULL",
+        )
+        .bind(self.name())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO signing_keys (kid, algorithm, private_key) VALUES ($1, $2, $3)")
+            .bind(&kid)
+            .bind(self.name())
+            .bind(self.seal(&pem))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(kid))
+    }
+
+    /// Whether the newest key is older than `max_age`, or there is none.
+    async fn due<'e>(&self, db: impl PgExecutor<'e>, max_age: Duration) -> Result<bool> {
+        let newest: Option<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT max(created_at) FROM signing_keys WHERE algorithm = $1")
+                .bind(self.name())
+                .fetch_one(db)
+                .await?;
+
+        Ok(newest.is_none_or(|newest| newest + max_age <= Utc::now()))
+    }
+
+    fn seal(&self, pem: &str) -> String {
+        let cipher = XChaCha20Poly1305::new(&self.seal.into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, pem.as_bytes())
+            .expect("encrypting a signing key cannot fail");
+
+        URL_SAFE_NO_PAD.encode([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, sealed: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = XChaCha20Poly1305::new(&self.seal.into())
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .ok()?;
+
+        String::from_utf8(plaintext).ok()
+    }
+}
+
+/// A new PEM-encoded PKCS#8 private key for `algorithm`.
+fn generate(algorithm: Algorithm) -> Result<String> {
+    let invalid = |_| Error::Jwt(ErrorKind::InvalidKeyFormat.into());
+
+    let pem = match algorithm {
+        Algorithm::RS256 => {
+            use rsa::pkcs8::EncodePrivateKey;
+
+            rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, RSA_BITS)
+                .map_err(|_| Error::Jwt(ErrorKind::InvalidKeyFormat.into()))?
+                .to_pkcs8_pem(LineEnding::LF)
+                .map_err(invalid)?
+        }
+        Algorithm::ES256 => {
+            use p256::pkcs8::EncodePrivateKey;
+
+            p256::SecretKey::random(&mut rsa::rand_core::OsRng)
+                .to_pkcs8_pem(LineEnding::LF)
+                .map_err(invalid)?
+        }
+        _ => ed25519_dalek::SigningKey::from_bytes(&rand::rng().random())
+            .to_pkcs8_pem(LineEnding::LF)
+            .map_err(invalid)?,
+    };
+
+    Ok(pem.to_string())
+}
+
+/// JWK of an Ed25519 public key, which [`Jwk::from_encoding_key`] cannot
+/// derive.
+fn ed25519_jwk(public: &[u8; 32]) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            key_algorithm: Some(KeyAlgorithm::EdDSA),
+            ..CommonParameters::default()
+        },
+        algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(public),
+        }),
+    }
+}
+
+/// Spawns the background task that reloads the signing keys of `tokens`
+/// every minute, so that keys rotated by other instances are used and
+/// published, and rotates them once `auth.signing_key_rotation` has passed.
+/// Stops when `shutdown` is cancelled.
+pub fn spawn_key_refresher(tokens: TokenService, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.cancelled() => break,
+            }
+
+            if let Err(err) = tokens.refresh_keys().await {
+                tracing::warn!(error = %err, "Failed to refresh signing keys");
+            }
+        }
+    })
+}
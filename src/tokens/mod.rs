@@ -0,0 +1,473 @@
+mod enricher;
+mod keys;
+mod revocation;
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{
+    Algorithm, Header, Validation,
+    errors::ErrorKind,
+    jwk::{Jwk, JwkSet},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use self::keys::{KEY_ACTIVATION, KeyStore, TokenKey};
+pub use self::{
+    enricher::{ClaimsEnricher, RESERVED_CLAIMS},
+    keys::spawn_key_refresher,
+    revocation::{PgTokenStore, TokenStore},
+};
+use crate::{
+    Error, Result,
+    clock::{Clock, SystemClock},
+    config::{AuthConfig, ConfigError, TokenAlgorithm},
+    sessions::Session,
+};
+
+const SIGNING_KEY_MISMATCH: &str =
+    "auth.signing_key is not a PEM-encoded private key for auth.algorithm";
+
+/// Distinguishes the two token types so one can never be used as the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+impl TokenKind {
+    /// The `typ` claim of tokens of this kind.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Access => "access",
+            Self::Refresh => "refresh",
+        }
+    }
+}
+
+/// Claims carried by every token minted by [`TokenService`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    /// The user the token was issued to.
+    pub sub: Uuid,
+    /// The server-side session the token belongs to.
+    pub sid: Uuid,
+    /// Unique token identifier.
+    pub jti: Uuid,
+    /// Issued-at, as a unix timestamp.
+    pub iat: i64,
+    /// Expiry, as a unix timestamp.
+    pub exp: i64,
+    pub typ: TokenKind,
+    /// The organization the session is acting in, if one is active.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<Uuid>,
+    /// The single sign-on application an access token was issued to, see
+    /// [`crate::config::SsoConfig`]. Tokens for this server carry none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// Claims added by [`ClaimsEnricher`]s, on access tokens only.
+    #[serde(flatten)]
+    pub custom: Map<String, Value>,
+}
+
+/// Access/refresh token pair returned by login and refresh.
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    /// Lifetime of the access token, in seconds.
+    pub expires_in: u64,
+}
+
+/// Access token returned alone, to single sign-on applications.
+#[derive(Debug, Serialize)]
+pub struct AccessToken {
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Lifetime of the token, in seconds.
+    pub expires_in: u64,
+}
+
+/// Mints and verifies JWT access and refresh tokens.
+///
+/// Access tokens are short-lived and sent as `Authorization: Bearer` on
+/// every request. Refresh tokens live much longer and may only be exchanged
+/// for a new pair at `POST /auth/refresh`. Both are HS256-signed with the
+/// `auth.secret` key unless `auth.algorithm` selects an asymmetric algorithm,
+/// in which case the public keys are published through [`Self::jwks`]. The
+/// `typ` claim keeps the two kinds from being interchangeable.
+///
+/// Asymmetric keys are named by the `kid` header of the tokens they sign.
+/// Once [`Self::with_key_store`] is given a database, [`Self::rotate_key`]
+/// replaces the signing key while earlier ones keep verifying, see
+/// [`AuthConfig`].
+///
+/// Every pair is bound to a server-side session through the `sid` claim, so
+/// ending the session also stops its refresh token from being exchanged.
+#[derive(Clone)]
+pub struct TokenService {
+    algorithm: Algorithm,
+    /// `auth.secret` for HS256, otherwise `auth.signing_key` if set.
+    configured: Option<Arc<TokenKey>>,
+    /// Keys tokens are verified with, the one signing first.
+    keys: Arc<ArcSwap<Vec<Arc<TokenKey>>>>,
+    store: Option<KeyStore>,
+    rotation: u64,
+    access_ttl: u64,
+    refresh_ttl: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl TokenService {
+    /// ## Errors
+    /// * [`ConfigError::Invalid`] if `auth.signing_key` does not suit an
+    ///   asymmetric `auth.algorithm`
+    pub fn from_config(config: &AuthConfig) -> Result<Self> {
+        let algorithm = algorithm(config.algorithm());
+        let configured = match config.algorithm() {
+            TokenAlgorithm::HS256 => Some(TokenKey::secret(config.secret().expose())),
+            _ => config
+                .signing_key()
+                .map(|pem| {
+                    TokenKey::from_pem(algorithm, pem.expose()).ok_or_else(|| {
+                        ConfigError::Invalid(vec![String::from(SIGNING_KEY_MISMATCH)])
+                    })
+                })
+                .transpose()?,
+        };
+        let configured = configured.map(Arc::new);
+
+        Ok(Self {
+            algorithm,
+            keys: Arc::new(ArcSwap::from_pointee(configured.iter().cloned().collect())),
+            configured,
+            store: None,
+            rotation: config.signing_key_rotation(),
+            access_ttl: config.lifetimes().access_token().as_secs(),
+            refresh_ttl: config.lifetimes().refresh_token().as_secs(),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Tells the time with `clock` instead of the system clock, both when
+    /// minting tokens and checking their expiry.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps rotated asymmetric keys in the `signing_keys` table of `db`,
+    /// sealed with `secret`. Keys are only loaded by
+    /// [`Self::refresh_keys`]. HS256 keys are not rotated.
+    #[must_use]
+    pub fn with_key_store(mut self, db: PgPool, secret: &str) -> Self {
+        if self.algorithm != Algorithm::HS256 {
+            self.store = Some(KeyStore::new(db, self.algorithm, secret));
+        }
+        self
+    }
+
+    /// Public keys tokens can be verified with; empty for HS256, whose key
+    /// must stay secret.
+    #[must_use]
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .keys
+                .load()
+                .iter()
+                .filter_map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+
+    /// Generates a new signing key and returns its public half. The key is
+    /// published right away and signs every token from two minutes on, once
+    /// all instances know it. Tokens signed with earlier keys stay valid;
+    /// those keys are published until `auth.lifetimes.refresh_token` after
+    /// they were replaced.
+    ///
+    /// ## Errors
+    /// * [`Error::Disabled`] for HS256 or without [`Self::with_key_store`]
+    /// * The key cannot be generated
+    /// * Database errors
+    pub async fn rotate_key(&self) -> Result<Jwk> {
+        let store = self
+            .store
+            .as_ref()
+            .ok_or(Error::Disabled("signing key rotation"))?;
+
+        let kid = store.rotate(self.retention(), None).await?;
+        self.refresh_keys().await?;
+
+        self.keys
+            .load()
+            .iter()
+            .find(|key| key.kid == kid)
+            .and_then(|key| key.jwk.clone())
+            .ok_or_else(|| Error::Jwt(ErrorKind::InvalidKeyFormat.into()))
+    }
+
+    /// Loads the rotated keys from the database, rotating first when
+    /// `auth.signing_key_rotation` has passed since the newest one was made
+    /// or there is none yet. Keys replaced longer than
+    /// `auth.lifetimes.refresh_token` ago are dropped, `auth.signing_key` once the
+    /// first rotated key is that old. Does nothing without
+    /// [`Self::with_key_store`].
+    ///
+    /// ## Errors
+    /// * The key cannot be generated
+    /// * Database errors
+    pub async fn refresh_keys(&self) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        if self.rotation > 0 {
+            let max_age = Duration::seconds(i64::try_from(self.rotation).unwrap_or(i64::MAX));
+            if let Some(kid) = store.rotate(self.retention(), Some(max_age)).await? {
+                tracing::info!(%kid, "Rotated token signing key");
+            }
+        }
+
+        let now = self.clock.now();
+        let retention = self.retention();
+        let stored = store.load().await?;
+
+        let configured_retired = stored.last().map(|oldest| oldest.created_at);
+        let mut keys: Vec<(Arc<TokenKey>, Option<DateTime<Utc>>)> = stored
+            .into_iter()
+            .filter(|stored| stored.retired_at.is_none_or(|at| at + retention > now))
+            .map(|stored| (Arc::new(stored.key), Some(stored.created_at)))
+            .collect();
+
+        if let Some(configured) = &self.configured
+            && configured_retired.is_none_or(|at| at + retention > now)
+            && !keys.iter().any(|(key, _)| key.kid == configured.kid)
+        {
+            keys.push((configured.clone(), None));
+        }
+
+        // New keys are published right away but only sign once every
+        // instance has loaded them, so none rejects their tokens.
+        let active = keys
+            .iter()
+            .position(|(_, created_at)| created_at.is_none_or(|at| at + KEY_ACTIVATION <= now))
+            .unwrap_or(keys.len().saturating_sub(1));
+        if !keys.is_empty() {
+            keys[..=active].rotate_right(1);
+        }
+
+        self.keys
+            .store(Arc::new(keys.into_iter().map(|(key, _)| key).collect()));
+
+        Ok(())
+    }
+
+    fn retention(&self) -> Duration {
+        Duration::seconds(i64::try_from(self.refresh_ttl).unwrap_or(i64::MAX))
+    }
+
+    /// Issues a fresh access/refresh pair for the user of `session`, carrying
+    /// its active organization. Handlers go through
+    /// [`crate::AppContext::issue_tokens`], which adds the claims of the
+    /// registered [`ClaimsEnricher`]s.
+    ///
+    /// ## Errors
+    /// * [`Error::Unauthenticated`] if `session` is anonymous
+    /// * The claims cannot be encoded or signed
+    pub fn issue_pair(&self, session: &Session) -> Result<TokenPair> {
+        self.issue_pair_with(session, Map::new())
+    }
+
+    /// [`TokenService::issue_pair`] with `custom` claims added to the access
+    /// token, except any of [`RESERVED_CLAIMS`].
+    ///
+    /// ## Errors
+    /// * [`Error::Unauthenticated`] if `session` is anonymous
+    /// * The claims cannot be encoded or signed
+    pub fn issue_pair_with(
+        &self,
+        session: &Session,
+        mut custom: Map<String, Value>,
+    ) -> Result<TokenPair> {
+        custom.retain(|name, _| !RESERVED_CLAIMS.contains(&name.as_str()));
+
+        Ok(TokenPair {
+            access_token: self.mint(session, TokenKind::Access, self.access_ttl, None, custom)?,
+            refresh_token: self.mint(
+                session,
+                TokenKind::Refresh,
+                self.refresh_ttl,
+                None,
+                Map::new(),
+            )?,
+            token_type: "Bearer",
+            expires_in: self.access_ttl,
+        })
+    }
+
+    /// Issues an access token alone for the user of `session`, naming
+    /// `audience` in its `aud` claim, with `custom` claims added except any
+    /// of [`RESERVED_CLAIMS`]. Handlers go through
+    /// [`crate::AppContext::issue_access_token`].
+    ///
+    /// No refresh token comes with it: the application gets a new access
+    /// token the same way while the session lasts.
+    ///
+    /// ## Errors
+    /// * [`Error::Unauthenticated`] if `session` is anonymous
+    /// * The claims cannot be encoded or signed
+    pub fn issue_access(
+        &self,
+        session: &Session,
+        audience: &str,
+        mut custom: Map<String, Value>,
+    ) -> Result<AccessToken> {
+        custom.retain(|name, _| !RESERVED_CLAIMS.contains(&name.as_str()));
+
+        Ok(AccessToken {
+            access_token: self.mint(
+                session,
+                TokenKind::Access,
+                self.access_ttl,
+                Some(audience),
+                custom,
+            )?,
+            token_type: "Bearer",
+            expires_in: self.access_ttl,
+        })
+    }
+
+    /// Verifies `token`'s signature and expiry and checks it is of `kind`
+    /// and meant for this server, as a credential for its own routes.
+    ///
+    /// Tokens issued to single sign-on applications name them in `aud` and
+    /// are refused: an application holding one must not be able to act as
+    /// the user here. This does not consult the [`TokenStore`].
+    ///
+    /// ## Errors
+    /// * [`Error::InvalidToken`] if the token is malformed, tampered with,
+    ///   expired, of the wrong kind or issued to an application
+    pub fn verify(&self, token: &str, kind: TokenKind) -> Result<Claims> {
+        let claims = self.verify_any_audience(token, kind)?;
+
+        if claims.aud.is_some() {
+            return Err(Error::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+
+    /// Like [`TokenService::verify`], but also accepting the tokens of
+    /// single sign-on applications, for describing tokens to the
+    /// applications rather than authenticating their bearer.
+    ///
+    /// ## Errors
+    /// * [`Error::InvalidToken`] if the token is malformed, tampered with,
+    ///   expired or of the wrong kind
+    pub fn verify_any_audience(&self, token: &str, kind: TokenKind) -> Result<Claims> {
+        let claims = self.decode(token)?;
+
+        if claims.typ != kind {
+            return Err(Error::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+
+    /// Verifies `token`'s signature and expiry, accepting either kind and
+    /// any audience. Not for authenticating the bearer, see
+    /// [`TokenService::verify`].
+    ///
+    /// ## Errors
+    /// * [`Error::InvalidToken`] if the token is malformed, tampered with or
+    ///   expired
+    pub fn decode(&self, token: &str) -> Result<Claims> {
+        let kid = jsonwebtoken::decode_header(token)
+            .map_err(|_| Error::InvalidToken)?
+            .kid;
+        let keys = self.keys.load();
+        let key = keys
+            .iter()
+            .find(|key| key.kid == kid)
+            .ok_or(Error::InvalidToken)?;
+
+        let mut validation = Validation::new(self.algorithm);
+        validation.leeway = 0;
+        // Checked against `self.clock` below instead of the system clock.
+        validation.validate_exp = false;
+        // Tokens of single sign-on applications are decoded here too;
+        // `verify` refuses them as credentials for this server.
+        validation.validate_aud = false;
+
+        let claims = jsonwebtoken::decode::<Claims>(token, &key.decoding, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| Error::InvalidToken)?;
+
+        if claims.exp < self.clock.now().timestamp() {
+            return Err(Error::InvalidToken);
+        }
+
+        Ok(claims)
+    }
+
+    fn mint(
+        &self,
+        session: &Session,
+        kind: TokenKind,
+        ttl: u64,
+        audience: Option<&str>,
+        custom: Map<String, Value>,
+    ) -> Result<String> {
+        let iat = self.clock.now().timestamp();
+        let claims = Claims {
+            sub: session.user_id.ok_or(Error::Unauthenticated)?,
+            sid: session.id,
+            jti: Uuid::new_v4(),
+            iat,
+            exp: iat.saturating_add(i64::try_from(ttl).unwrap_or(i64::MAX)),
+            typ: kind,
+            org: session.active_organization_id,
+            aud: audience.map(str::to_owned),
+            custom,
+        };
+
+        let keys = self.keys.load();
+        let key = keys
+            .first()
+            .ok_or_else(|| Error::Jwt(ErrorKind::InvalidKeyFormat.into()))?;
+
+        let mut header = Header::new(self.algorithm);
+        header.kid.clone_from(&key.kid);
+
+        let token = jsonwebtoken::encode(&header, &claims, &key.encoding).map_err(Error::Jwt)?;
+        metrics::counter!("token_issued_total", "kind" => kind.name()).increment(1);
+
+        Ok(token)
+    }
+}
+
+/// Whether `pem` is a private key tokens can be signed with under the
+/// asymmetric `algorithm`, as `auth.signing_key` must be.
+pub(crate) fn is_signing_key(algorithm: TokenAlgorithm, pem: &str) -> bool {
+    TokenKey::from_pem(self::algorithm(algorithm), pem).is_some()
+}
+
+fn algorithm(algorithm: TokenAlgorithm) -> Algorithm {
+    match algorithm {
+        TokenAlgorithm::HS256 => Algorithm::HS256,
+        TokenAlgorithm::RS256 => Algorithm::RS256,
+        TokenAlgorithm::ES256 => Algorithm::ES256,
+        TokenAlgorithm::EdDSA => Algorithm::EdDSA,
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::Claims;
+use crate::{
+    Result,
+    cache::Cache,
+    clock::{Clock, SystemClock},
+};
+
+/// Denylist for tokens that must stop working before they expire.
+///
+/// [`PgTokenStore`] is the default; another backend is installed with
+/// [`crate::AppContext::with_token_store`].
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Revokes the single token described by `claims`.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn revoke(&self, claims: &Claims) -> Result<()>;
+
+    /// Revokes every access and refresh token issued to `user_id` so far.
+    /// Sessions are left alone; end them through the session store.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn revoke_all(&self, user_id: Uuid) -> Result<()>;
+
+    /// Whether the token described by `claims` has been revoked, on its own
+    /// or along with all tokens of its user. Tokens of deleted or disabled
+    /// users count as revoked.
+    ///
+    /// ## Errors
+    /// * Backend errors
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool>;
+}
+
+/// Postgres-backed denylist for tokens that must stop working before they
+/// expire, the default [`TokenStore`].
+///
+/// Single tokens are revoked by `jti` and kept only until their own expiry.
+/// Revoking every token of a user instead records a cutoff: tokens issued
+/// before it are rejected.
+///
+/// With a [`Cache`], single tokens are listed in Redis instead, falling back
+/// to the `revoked_tokens` table when Redis fails. Both are checked, so
+/// tokens revoked during an outage stay revoked.
+#[derive(Clone)]
+pub struct PgTokenStore {
+    db: PgPool,
+    cache: Option<Cache>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PgTokenStore {
+    #[must_use]
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            db,
+            cache: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Lists revoked tokens in `cache`.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Tells the time with `clock` instead of the system clock.
+    #[must_use]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+#[async_trait]
+impl TokenStore for PgTokenStore {
+    async fn revoke(&self, claims: &Claims) -> Result<()> {
+        let now = self.clock.now();
+
+        if let Some(cache) = &self.cache {
+            let ttl = (claims.exp - now.timestamp()).max(1).cast_unsigned();
+
+            match cache.flag(&revoked_key(claims.jti), ttl).await {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    tracing::warn!(jti = %claims.jti, error = %err, "Failed to revoke token in Redis, using Postgres");
+                }
+            }
+        }
+
+        sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.db)
+            .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO revoked_tokens (jti, user_id, revoked_at, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (jti) DO NOTHING
+            ",
+        )
+        .bind(claims.jti)
+        .bind(claims.sub)
+        .bind(now)
+        .bind(DateTime::from_timestamp(claims.exp, 0).unwrap_or(DateTime::<Utc>::MAX_UTC))
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_all(&self, user_id: Uuid) -> Result<()> {
+        // Token `iat`s only have second precision. Truncating the cutoff keeps
+        // tokens minted right after it, in the same second, valid.
+        let cutoff = DateTime::from_timestamp(self.clock.now().timestamp(), 0).unwrap_or_default();
+
+        sqlx::query("UPDATE users SET tokens_revoked_at = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(cutoff)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool> {
+        if let Some(cache) = &self.cache {
+            match cache.exists(&revoked_key(claims.jti)).await {
+                Ok(true) => return Ok(true),
+                Ok(false) => {}
+                Err(err) => tracing::warn!(error = %err, "Revocation lookup in Redis failed"),
+            }
+        }
+
+        sqlx::query_scalar(
+            r"
+            SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = $1)
+                OR NOT EXISTS (
+                    SELECT 1 FROM users
+                    WHERE id = $2
+                      AND status = 'active'
+                      AND (tokens_revoked_at IS NULL OR tokens_revoked_at <= to_timestamp($3::bigint))
+                )
+            ",
+        )
+        .bind(claims.jti)
+        .bind(claims.sub)
+        .bind(claims.iat)
+        .fetch_one(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+fn revoked_key(jti: Uuid) -> String {
+    format!("revoked_token:{jti}")
+}
@@ -1,37 +1,66 @@
-use std::{net::SocketAddr, time::Duration};
+mod propagation;
+mod query;
+mod report;
+
+use std::time::Duration;
 
 use axum::{
     body::Body,
-    extract::ConnectInfo,
-    http::{Request, Response},
+    http::{HeaderName, Request, Response, StatusCode},
 };
 use tower_http::classify::ServerErrorsFailureClass;
 use tracing::{Span, field};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use self::query::record_query_stats;
+pub use self::{
+    propagation::trace_headers,
+    query::QueryAccounting,
+    report::{record_user, report_scope},
+};
+use crate::security::ClientIp;
+
+/// Header carrying the ID of a request, taken from the client when it sends
+/// one and generated otherwise, then returned on the response.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
 
 pub fn make_span_with(request: &Request<Body>) -> Span {
-    tracing::error_span!(
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("<none>");
+
+    let span = tracing::error_span!(
         "<->",
+        request_id = field::display(request_id),
         version = field::debug(request.version()),
         uri = field::display(request.uri()),
         method = field::display(request.method()),
         source = field::Empty,
+        user_id = field::Empty,
         status = field::Empty,
         latency = field::Empty,
-        error = field::Empty
-    )
+        error = field::Empty,
+        db_queries = field::Empty,
+        db_time = field::Empty
+    );
+
+    // Continues the trace of the caller, if it sent a `traceparent`; does
+    // nothing when spans are not exported.
+    let _ = span.set_parent(propagation::extract(request.headers()));
+
+    span
 }
 
 pub fn on_request(request: &Request<Body>, span: &Span) {
     span.record(
         "source",
-        request
-            .extensions()
-            .get::<ConnectInfo<SocketAddr>>()
+        ClientIp::from_extensions(request.extensions())
+            .0
             .map_or_else(
                 || field::display(String::from("<unkown>")),
-                |connect_info: &ConnectInfo<SocketAddr>| {
-                    field::display(connect_info.ip().to_string())
-                },
+                |ip| field::display(ip.to_string()),
             ),
     );
 
@@ -44,6 +73,7 @@ pub fn on_response(response: &Response<Body>, latency: Duration, span: &Span) {
         "latency",
         field::display(format!("{}µs", latency.as_micros())),
     );
+    record_query_stats(span);
 
     tracing::info!("Response");
 }
@@ -55,6 +85,14 @@ pub fn on_failure(error: ServerErrorsFailureClass, latency: Duration, span: &Spa
         "latency",
         field::display(format!("{}µs", latency.as_millis())),
     );
+    record_query_stats(span);
+
+    // Shed requests and failed readiness checks are expected under load,
+    // where logging each one as an error would flood the logs.
+    if let ServerErrorsFailureClass::StatusCode(StatusCode::SERVICE_UNAVAILABLE) = error {
+        tracing::debug!("Service unavailable");
+        return;
+    }
 
     tracing::error!("Error on request");
 }
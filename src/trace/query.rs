@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use tracing::{
+    Event, Metadata, Span, Subscriber,
+    field::{self, Field, Visit},
+};
+use tracing_subscriber::{Layer, Registry, filter::FilterFn, layer::Context, registry::LookupSpan};
+
+/// Target sqlx uses when it logs an executed statement.
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+/// Span field marking the span that query statistics are accumulated on.
+pub(crate) const QUERIES_FIELD: &str = "db_queries";
+
+/// Database usage accumulated over the lifetime of a single request.
+#[derive(Debug, Default, Clone, Copy)]
+struct QueryStats {
+    count: u64,
+    elapsed: Duration,
+}
+
+/// Tracing layer that counts sqlx queries per request.
+///
+/// sqlx emits one `sqlx::query` event for every statement it executes,
+/// carrying the execution time in `elapsed_secs`. This layer walks up from
+/// the event to the nearest span declaring a [`QUERIES_FIELD`] field (the span
+/// built by [`super::make_span_with`]) and adds the query to the statistics
+/// stored in that span's extensions. [`record_query_stats`] later copies them
+/// onto the span as `db_queries` and `db_time`.
+///
+/// Because every statement is counted regardless of where it is issued,
+/// handlers and repositories need no explicit instrumentation.
+pub struct QueryAccounting;
+
+impl QueryAccounting {
+    /// Per-layer filter admitting only what the layer needs to see: sqlx
+    /// query events and the spans that accumulate them.
+    ///
+    /// Use it with [`Layer::with_filter`] so the layer works independently of
+    /// the log level configured for the output layers.
+    pub fn filter() -> FilterFn<fn(&Metadata<'_>) -> bool> {
+        FilterFn::new(|metadata| {
+            metadata.target() == SQLX_QUERY_TARGET
+                || metadata.fields().field(QUERIES_FIELD).is_some()
+        })
+    }
+}
+
+impl<S> Layer<S> for QueryAccounting
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != SQLX_QUERY_TARGET {
+            return;
+        }
+
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+
+        let Some(span) = scope
+            .into_iter()
+            .find(|span| span.metadata().fields().field(QUERIES_FIELD).is_some())
+        else {
+            return;
+        };
+
+        let mut visitor = ElapsedVisitor::default();
+        event.record(&mut visitor);
+
+        let mut extensions = span.extensions_mut();
+
+        if let Some(stats) = extensions.get_mut::<QueryStats>() {
+            stats.count += 1;
+            stats.elapsed += visitor.elapsed;
+        } else {
+            extensions.insert(QueryStats {
+                count: 1,
+                elapsed: visitor.elapsed,
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct ElapsedVisitor {
+    elapsed: Duration,
+}
+
+impl Visit for ElapsedVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed = Duration::try_from_secs_f64(value).unwrap_or_default();
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// Records the accumulated query count and total database time on `span`.
+///
+/// Does nothing when the request did not touch the database or when the
+/// global subscriber is not backed by a [`Registry`].
+pub(crate) fn record_query_stats(span: &Span) {
+    let stats = span
+        .with_subscriber(|(id, dispatch)| {
+            dispatch
+                .downcast_ref::<Registry>()
+                .and_then(|registry| registry.span(id))
+                .and_then(|span| span.extensions().get::<QueryStats>().copied())
+        })
+        .flatten();
+
+    if let Some(stats) = stats {
+        span.record(QUERIES_FIELD, stats.count);
+        span.record(
+            "db_time",
+            field::display(format!("{}µs", stats.elapsed.as_micros())),
+        );
+    }
+}
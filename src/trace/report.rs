@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use sentry::{Hub, SentryFutureExt, protocol};
+use tracing::{Span, field};
+use uuid::Uuid;
+
+use super::REQUEST_ID_HEADER;
+
+/// Reports errors and panics raised while serving a request with its route
+/// and request ID, by binding a Sentry hub of its own to the request.
+///
+/// Does nothing unless `logger.sentry` is configured.
+pub async fn report_scope(request: Request, next: Next) -> Response {
+    if Hub::current().client().is_none() {
+        return next.run(request).await;
+    }
+
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned());
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .map(ToOwned::to_owned);
+    let details = protocol::Request {
+        url: request.uri().to_string().parse().ok(),
+        method: Some(request.method().to_string()),
+        ..protocol::Request::default()
+    };
+
+    hub.configure_scope(|scope| {
+        if let Some(route) = &route {
+            scope.set_transaction(Some(route));
+            scope.set_tag("route", route);
+        }
+        if let Some(request_id) = &request_id {
+            scope.set_tag("request_id", request_id);
+        }
+        scope.add_event_processor(move |mut event| {
+            event.request.get_or_insert_with(|| details.clone());
+            Some(event)
+        });
+    });
+
+    next.run(request).bind_hub(hub).await
+}
+
+/// Records the authenticated user on the request span and on the Sentry
+/// scope of the request.
+pub fn record_user(id: Uuid) {
+    Span::current().record("user_id", field::display(id));
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            id: Some(id.to_string()),
+            ..sentry::User::default()
+        }));
+    });
+}
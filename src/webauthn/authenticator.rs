@@ -0,0 +1,121 @@
+use p256::{EncodedPoint, FieldBytes};
+use serde_cbor::Value;
+
+use crate::{Error, Result};
+
+/// User present.
+const FLAG_UP: u8 = 0x01;
+/// Attested credential data included.
+const FLAG_AT: u8 = 0x40;
+
+/// COSE key labels and values for an ES256 (ECDSA P-256 / SHA-256) key.
+const COSE_KTY: i128 = 1;
+const COSE_ALG: i128 = 3;
+const COSE_CRV: i128 = -1;
+const COSE_X: i128 = -2;
+const COSE_Y: i128 = -3;
+const COSE_KTY_EC2: i128 = 2;
+const COSE_CRV_P256: i128 = 1;
+pub(super) const COSE_ALG_ES256: i64 = -7;
+
+/// The fixed-layout `authenticatorData` structure, see WebAuthn §6.1.
+pub(super) struct AuthenticatorData {
+    pub rp_id_hash: [u8; 32],
+    pub flags: u8,
+    pub sign_count: u32,
+    pub attested: Option<AttestedCredential>,
+}
+
+/// A newly created credential embedded in the registration response.
+pub(super) struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    /// SEC1 uncompressed P-256 point.
+    pub public_key: Vec<u8>,
+}
+
+impl AuthenticatorData {
+    pub fn user_present(&self) -> bool {
+        self.flags & FLAG_UP != 0
+    }
+
+    /// Parses raw authenticator data.
+    ///
+    /// ## Errors
+    /// * [`Error::WebAuthn`] if the data is truncated or carries an
+    ///   unsupported public key
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let malformed = || Error::WebAuthn(String::from("malformed authenticator data"));
+
+        if bytes.len() < 37 {
+            return Err(malformed());
+        }
+
+        let mut rp_id_hash = [0u8; 32];
+        rp_id_hash.copy_from_slice(&bytes[..32]);
+        let flags = bytes[32];
+        let sign_count = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]);
+
+        let attested = if flags & FLAG_AT == 0 {
+            None
+        } else {
+            // aaguid (16) | credential id length (2) | credential id | COSE key
+            let rest = bytes.get(37 + 16..).ok_or_else(malformed)?;
+            let (len, rest) = rest.split_first_chunk::<2>().ok_or_else(malformed)?;
+            let len = usize::from(u16::from_be_bytes(*len));
+            let credential_id = rest.get(..len).ok_or_else(malformed)?.to_vec();
+
+            // Extensions may follow the key, so decode exactly one value.
+            let key = serde_cbor::Deserializer::from_slice(&rest[len..])
+                .into_iter::<Value>()
+                .next()
+                .ok_or_else(malformed)?
+                .map_err(|_| malformed())?;
+
+            Some(AttestedCredential {
+                credential_id,
+                public_key: es256_public_key(&key)?,
+            })
+        };
+
+        Ok(Self {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested,
+        })
+    }
+}
+
+/// Converts a COSE EC2 key into an uncompressed SEC1 point, accepting only
+/// ES256 keys.
+fn es256_public_key(key: &Value) -> Result<Vec<u8>> {
+    let unsupported = || Error::WebAuthn(String::from("only ES256 passkeys are supported"));
+
+    let Value::Map(map) = key else {
+        return Err(unsupported());
+    };
+
+    let int = |label| match map.get(&Value::Integer(label)) {
+        Some(Value::Integer(value)) => Some(*value),
+        _ => None,
+    };
+    let coordinate = |label| match map.get(&Value::Integer(label)) {
+        Some(Value::Bytes(bytes)) if bytes.len() == 32 => Some(FieldBytes::clone_from_slice(bytes)),
+        _ => None,
+    };
+
+    if int(COSE_KTY) != Some(COSE_KTY_EC2)
+        || int(COSE_ALG) != Some(i128::from(COSE_ALG_ES256))
+        || int(COSE_CRV) != Some(COSE_CRV_P256)
+    {
+        return Err(unsupported());
+    }
+
+    let (Some(x), Some(y)) = (coordinate(COSE_X), coordinate(COSE_Y)) else {
+        return Err(unsupported());
+    };
+
+    Ok(EncodedPoint::from_affine_coordinates(&x, &y, false)
+        .as_bytes()
+        .to_vec())
+}
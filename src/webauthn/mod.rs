@@ -0,0 +1,378 @@
+mod authenticator;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use p256::ecdsa::{Signature, VerifyingKey, signature::Verifier};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use self::authenticator::{AuthenticatorData, COSE_ALG_ES256};
+use crate::{Error, Result, auth::generate_token, config::WebAuthnConfig};
+
+/// Which ceremony a stored challenge was issued for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ceremony {
+    Registration,
+    Authentication,
+}
+
+impl Ceremony {
+    /// Value stored in `webauthn_challenges.ceremony`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Registration => "registration",
+            Self::Authentication => "authentication",
+        }
+    }
+}
+
+/// `PublicKeyCredentialCreationOptions`, ready for
+/// `navigator.credentials.create({ publicKey })` once the base64url fields
+/// are decoded.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameter>,
+    pub timeout: u64,
+    pub attestation: &'static str,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+}
+
+/// `PublicKeyCredentialRequestOptions`, ready for
+/// `navigator.credentials.get({ publicKey })`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u64,
+    pub user_verification: &'static str,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    /// Base64url of the user's UUID bytes; never the email, as authenticators
+    /// may expose it.
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialParameter {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Base64url credential ID.
+    pub id: String,
+}
+
+impl CredentialDescriptor {
+    #[must_use]
+    pub fn new(credential_id: &[u8]) -> Self {
+        Self {
+            kind: "public-key",
+            id: URL_SAFE_NO_PAD.encode(credential_id),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: &'static str,
+    pub user_verification: &'static str,
+}
+
+/// The `PublicKeyCredential` returned by `navigator.credentials.create()`,
+/// in the JSON form produced by `credential.toJSON()`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationCredential {
+    pub raw_id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+/// The `PublicKeyCredential` returned by `navigator.credentials.get()`, in
+/// the JSON form produced by `credential.toJSON()`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationCredential {
+    pub raw_id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+/// A credential that passed registration and may be stored.
+#[derive(Debug)]
+pub struct VerifiedCredential {
+    pub credential_id: Vec<u8>,
+    /// SEC1 uncompressed P-256 public key.
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Relying-party side of the WebAuthn registration and authentication
+/// ceremonies.
+///
+/// Only ES256 credentials and `none` attestation are supported, which covers
+/// platform passkeys and security keys in every current browser. Attestation
+/// statements are not checked, so the authenticator model is not trusted.
+#[derive(Debug, Clone)]
+pub struct WebAuthn {
+    rp_id: String,
+    rp_name: String,
+    origin: String,
+    rp_id_hash: [u8; 32],
+    challenge_ttl: u64,
+}
+
+impl WebAuthn {
+    #[must_use]
+    pub fn from_config(config: &WebAuthnConfig) -> Self {
+        Self {
+            rp_id: config.rp_id().to_owned(),
+            rp_name: config.rp_name().to_owned(),
+            origin: config.origin().to_owned(),
+            rp_id_hash: Sha256::digest(config.rp_id().as_bytes()).into(),
+            challenge_ttl: config.challenge_ttl(),
+        }
+    }
+
+    /// Lifetime of a challenge, in seconds.
+    #[must_use]
+    pub fn challenge_ttl(&self) -> u64 {
+        self.challenge_ttl
+    }
+
+    /// Generates a random base64url challenge.
+    #[must_use]
+    pub fn new_challenge() -> String {
+        generate_token()
+    }
+
+    /// Builds the options for registering a new passkey for a user.
+    #[must_use]
+    pub fn creation_options(
+        &self,
+        challenge: String,
+        user_id: Uuid,
+        email: &str,
+        display_name: Option<&str>,
+        existing: &[Vec<u8>],
+    ) -> CreationOptions {
+        CreationOptions {
+            challenge,
+            rp: RelyingParty {
+                id: self.rp_id.clone(),
+                name: self.rp_name.clone(),
+            },
+            user: UserEntity {
+                id: URL_SAFE_NO_PAD.encode(user_id.as_bytes()),
+                name: email.to_owned(),
+                display_name: display_name.unwrap_or(email).to_owned(),
+            },
+            pub_key_cred_params: vec![CredentialParameter {
+                kind: "public-key",
+                alg: COSE_ALG_ES256,
+            }],
+            timeout: self.challenge_ttl * 1000,
+            attestation: "none",
+            exclude_credentials: existing
+                .iter()
+                .map(|id| CredentialDescriptor::new(id))
+                .collect(),
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred",
+                user_verification: "preferred",
+            },
+        }
+    }
+
+    /// Builds the options for logging in. An empty `allowed` list lets the
+    /// browser offer any discoverable passkey for this relying party.
+    #[must_use]
+    pub fn request_options(&self, challenge: String, allowed: &[Vec<u8>]) -> RequestOptions {
+        RequestOptions {
+            challenge,
+            rp_id: self.rp_id.clone(),
+            timeout: self.challenge_ttl * 1000,
+            user_verification: "preferred",
+            allow_credentials: allowed
+                .iter()
+                .map(|id| CredentialDescriptor::new(id))
+                .collect(),
+        }
+    }
+
+    /// Verifies a registration response against the issued `challenge`.
+    ///
+    /// ## Errors
+    /// * [`Error::WebAuthn`] if the client data, relying party or public key
+    ///   do not check out
+    pub fn verify_registration(
+        &self,
+        challenge: &str,
+        credential: &RegistrationCredential,
+    ) -> Result<VerifiedCredential> {
+        let response = &credential.response;
+        self.verify_client_data(&response.client_data_json, "webauthn.create", challenge)?;
+
+        let attestation: Value = serde_cbor::from_slice(&decode(&response.attestation_object)?)
+            .map_err(|_| rejected("malformed attestation object"))?;
+
+        let auth_data = match &attestation {
+            Value::Map(map) => match map.get(&Value::Text(String::from("authData"))) {
+                Some(Value::Bytes(bytes)) => bytes,
+                _ => return Err(rejected("attestation object has no authenticator data")),
+            },
+            _ => return Err(rejected("malformed attestation object")),
+        };
+
+        let data = self.verify_authenticator_data(auth_data)?;
+        let attested = data
+            .attested
+            .ok_or_else(|| rejected("no credential in registration response"))?;
+
+        if attested.credential_id != decode(&credential.raw_id)? {
+            return Err(rejected("credential id mismatch"));
+        }
+
+        Ok(VerifiedCredential {
+            credential_id: attested.credential_id,
+            public_key: attested.public_key,
+            sign_count: data.sign_count,
+        })
+    }
+
+    /// Verifies a login assertion made with a stored credential and returns
+    /// the authenticator's new signature counter.
+    ///
+    /// ## Errors
+    /// * [`Error::WebAuthn`] if the client data, relying party or signature
+    ///   do not check out, or the counter went backwards (a sign of a cloned
+    ///   authenticator)
+    pub fn verify_authentication(
+        &self,
+        challenge: &str,
+        credential: &AuthenticationCredential,
+        public_key: &[u8],
+        stored_sign_count: u32,
+    ) -> Result<u32> {
+        let response = &credential.response;
+        let client_data =
+            self.verify_client_data(&response.client_data_json, "webauthn.get", challenge)?;
+
+        let auth_data = decode(&response.authenticator_data)?;
+        let data = self.verify_authenticator_data(&auth_data)?;
+
+        let key = VerifyingKey::from_sec1_bytes(public_key)
+            .map_err(|_| rejected("stored public key is invalid"))?;
+        let signature = Signature::from_der(&decode(&response.signature)?)
+            .map_err(|_| rejected("malformed signature"))?;
+
+        let mut signed = auth_data;
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+
+        key.verify(&signed, &signature)
+            .map_err(|_| rejected("signature verification failed"))?;
+
+        // Authenticators that do not implement counters always report 0.
+        if (data.sign_count != 0 || stored_sign_count != 0) && data.sign_count <= stored_sign_count
+        {
+            return Err(rejected("signature counter did not increase"));
+        }
+
+        Ok(data.sign_count)
+    }
+
+    /// Checks the client data and returns its raw bytes for signing.
+    fn verify_client_data(&self, encoded: &str, kind: &str, challenge: &str) -> Result<Vec<u8>> {
+        let raw = decode(encoded)?;
+        let client_data: ClientData =
+            serde_json::from_slice(&raw).map_err(|_| rejected("malformed client data"))?;
+
+        if client_data.kind != kind {
+            return Err(rejected("unexpected ceremony type"));
+        }
+        if client_data.challenge.trim_end_matches('=') != challenge {
+            return Err(rejected("challenge mismatch"));
+        }
+        if client_data.origin != self.origin {
+            return Err(rejected("origin mismatch"));
+        }
+
+        Ok(raw)
+    }
+
+    fn verify_authenticator_data(&self, bytes: &[u8]) -> Result<AuthenticatorData> {
+        let data = AuthenticatorData::parse(bytes)?;
+
+        if data.rp_id_hash != self.rp_id_hash {
+            return Err(rejected("relying party mismatch"));
+        }
+        if !data.user_present() {
+            return Err(rejected("user presence required"));
+        }
+
+        Ok(data)
+    }
+}
+
+/// Decodes a base64url value from the browser, with or without padding.
+///
+/// ## Errors
+/// * [`Error::WebAuthn`] if `value` is not base64url
+pub fn decode(value: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| rejected("invalid base64url encoding"))
+}
+
+fn rejected(reason: &str) -> Error {
+    Error::WebAuthn(reason.to_owned())
+}
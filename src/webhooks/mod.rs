@@ -0,0 +1,382 @@
+use std::time::Duration as StdDuration;
+
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    AppContext, Error, Result,
+    config::WebhooksConfig,
+    events::{Event, Subscriber},
+    jobs::{self, Job, JobQueue},
+    trace,
+};
+
+/// Progress of a [`WebhookDelivery`], stored in `webhook_deliveries.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Not yet accepted by the endpoint; attempted again at
+    /// `next_attempt_at`.
+    Pending,
+    Delivered,
+    /// Gave up after `webhooks.max_attempts`.
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// Value stored in `webhook_deliveries.status`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for DeliveryStatus {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        match s.as_str() {
+            "pending" => Ok(Self::Pending),
+            "delivered" => Ok(Self::Delivered),
+            "failed" => Ok(Self::Failed),
+            other => Err(Error::Validation(format!(
+                "unknown delivery status: {other}"
+            ))),
+        }
+    }
+}
+
+/// One event sent, or to be sent, to one endpoint.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    /// Name of the endpoint in `webhooks.endpoints`.
+    pub endpoint: String,
+    pub event: String,
+    pub payload: Value,
+    #[sqlx(try_from = "String")]
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// HTTP status of the last attempt, if the endpoint answered.
+    pub last_status_code: Option<i32>,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingDelivery {
+    endpoint: String,
+    payload: Value,
+    attempts: i32,
+}
+
+/// Delivers auth events to the endpoints in the `webhooks` config section.
+///
+/// Every published [`Event`] is sent under its [`Event::name`], with
+/// [`Event::data`] as the payload's `data`; `session.revoked` is sent once
+/// per ended session, with `user_id` and `session_id`.
+///
+/// [`Self::dispatch`] records a delivery per subscribed endpoint and queues a
+/// [`Job::DeliverWebhook`] for it, so failed attempts are retried by the job
+/// worker with [`jobs::backoff`].
+#[derive(Clone)]
+pub struct Webhooks {
+    db: PgPool,
+    http: reqwest::Client,
+    config: WebhooksConfig,
+}
+
+impl Webhooks {
+    /// # Panics
+    /// If the TLS backend of the HTTP client cannot be initialised.
+    #[must_use]
+    pub fn new(db: PgPool, config: WebhooksConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("betterauth/", env!("CARGO_PKG_VERSION")))
+            .timeout(StdDuration::from_secs(config.timeout()))
+            .build()
+            .expect("failed to initialise HTTP client");
+
+        Self { db, http, config }
+    }
+
+    /// Records a delivery of `event` with `data` for every endpoint subscribed
+    /// to it and queues the deliveries as jobs. Does nothing when no endpoint
+    /// wants the event.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn dispatch(&self, event: &str, data: Value) -> Result<()> {
+        let mut endpoints: Vec<&str> = self
+            .config
+            .endpoints()
+            .iter()
+            .filter(|(_, endpoint)| endpoint.wants(event))
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        if endpoints.is_empty() {
+            return Ok(());
+        }
+
+        endpoints.sort_unstable();
+
+        let now = Utc::now();
+        let payload = json!({
+            "id": Uuid::new_v4(),
+            "type": event,
+            "created_at": now,
+            "data": data,
+        });
+
+        let mut tx = self.db.begin().await?;
+
+        let ids: Vec<Uuid> = sqlx::query_scalar(
+            r"
+            INSERT INTO webhook_deliveries (endpoint, event, payload, created_at, next_attempt_at)
+            SELECT endpoint, $2, $3, $4, $4 FROM UNNEST($1::VARCHAR[]) AS endpoint
+            RETURNING id
+            ",
+        )
+        .bind(&endpoints)
+        .bind(event)
+        .bind(&payload)
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for delivery_id in ids {
+            JobQueue::push(
+                &mut *tx,
+                &Job::DeliverWebhook { delivery_id },
+                now,
+                self.config.max_attempts(),
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Sends pending delivery `id` once and records the outcome.
+    ///
+    /// ## Errors
+    /// * [`Error::Webhook`] if the endpoint could not be reached or did not
+    ///   answer with a 2xx status, so the job is retried
+    /// * Database errors
+    pub async fn deliver(&self, id: Uuid) -> Result<()> {
+        let pending = sqlx::query_as::<_, PendingDelivery>(
+            "SELECT endpoint, payload, attempts FROM webhook_deliveries WHERE id = $1 AND status = 'pending'",
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let Some(pending) = pending else {
+            return Ok(());
+        };
+
+        let Some(endpoint) = self.config.endpoint(&pending.endpoint) else {
+            return self.abandon(id, "endpoint is no longer configured").await;
+        };
+
+        let body = serde_json::to_string(&pending.payload).unwrap_or_default();
+        let timestamp = Utc::now().timestamp().to_string();
+
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(endpoint.secret().expose().as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("{id}.{timestamp}.{body}").as_bytes());
+        let signature = STANDARD.encode(mac.finalize().into_bytes());
+
+        let outcome = self
+            .http
+            .post(endpoint.url())
+            .headers(trace::trace_headers())
+            .header(CONTENT_TYPE, "application/json")
+            .header("webhook-id", id.to_string())
+            .header("webhook-timestamp", timestamp)
+            .header("webhook-signature", format!("v1,{signature}"))
+            .body(body)
+            .send()
+            .await;
+
+        let (status_code, error) = match outcome {
+            Ok(response) if response.status().is_success() => {
+                sqlx::query(
+                    r"
+                    UPDATE webhook_deliveries
+                    SET status = 'delivered', attempts = attempts + 1,
+                        last_status_code = $2, last_error = NULL, delivered_at = now()
+                    WHERE id = $1
+                    ",
+                )
+                .bind(id)
+                .bind(i32::from(response.status().as_u16()))
+                .execute(&self.db)
+                .await?;
+
+                tracing::info!(delivery_id = %id, endpoint = %pending.endpoint, "Webhook delivered");
+
+                return Ok(());
+            }
+            Ok(response) => (
+                Some(i32::from(response.status().as_u16())),
+                format!("endpoint answered {}", response.status()),
+            ),
+            Err(err) => (None, err.to_string()),
+        };
+
+        let attempts = pending.attempts + 1;
+
+        sqlx::query(
+            r"
+            UPDATE webhook_deliveries
+            SET attempts = $2, last_status_code = $3, last_error = $4, next_attempt_at = now() + $5
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(attempts)
+        .bind(status_code)
+        .bind(&error)
+        .bind(jobs::backoff(attempts))
+        .execute(&self.db)
+        .await?;
+
+        tracing::warn!(
+            delivery_id = %id,
+            endpoint = %pending.endpoint,
+            attempts,
+            error,
+            "Webhook delivery failed"
+        );
+
+        Err(Error::Webhook(error))
+    }
+
+    /// Marks pending delivery `id` failed for good, e.g. once its job ran out
+    /// of attempts.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn abandon(&self, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            r"
+            UPDATE webhook_deliveries
+            SET status = 'failed', last_error = COALESCE(last_error, $2)
+            WHERE id = $1 AND status = 'pending'
+            ",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most recent deliveries first, optionally only those with `status`.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn list(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        sqlx::query_as::<_, WebhookDelivery>(
+            r"
+            SELECT id, endpoint, event, payload, status, attempts, last_status_code,
+                   last_error, created_at, next_attempt_at, delivered_at
+            FROM webhook_deliveries
+            WHERE $1::VARCHAR IS NULL OR status = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            ",
+        )
+        .bind(status.map(DeliveryStatus::name))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.db)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Puts failed delivery `id` back in the queue for one more attempt,
+    /// made right away. Returns `None` if there is no such failed delivery;
+    /// pending ones are already being retried.
+    ///
+    /// ## Errors
+    /// * Database errors
+    pub async fn retry(&self, id: Uuid) -> Result<Option<WebhookDelivery>> {
+        let mut tx = self.db.begin().await?;
+
+        let delivery = sqlx::query_as::<_, WebhookDelivery>(
+            r"
+            UPDATE webhook_deliveries
+            SET status = 'pending', next_attempt_at = now()
+            WHERE id = $1 AND status = 'failed'
+            RETURNING id, endpoint, event, payload, status, attempts, last_status_code,
+                      last_error, created_at, next_attempt_at, delivered_at
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if delivery.is_some() {
+            JobQueue::push(
+                &mut *tx,
+                &Job::DeliverWebhook { delivery_id: id },
+                Utc::now(),
+                1,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(delivery)
+    }
+}
+
+#[async_trait]
+impl Subscriber for Webhooks {
+    async fn handle(&self, _ctx: &AppContext, event: &Event) -> Result<()> {
+        match event {
+            Event::SessionsRevoked {
+                user_id,
+                session_ids,
+            } => {
+                for session_id in session_ids {
+                    self.dispatch(
+                        event.name(),
+                        json!({ "user_id": user_id, "session_id": session_id }),
+                    )
+                    .await?;
+                }
+
+                Ok(())
+            }
+            _ => self.dispatch(event.name(), event.data()).await,
+        }
+    }
+}